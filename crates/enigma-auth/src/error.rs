@@ -18,6 +18,9 @@ pub enum AuthError {
     #[error("invalid input: {0}")]
     InvalidInput(String),
 
+    #[error("weak password: {0}")]
+    WeakPassword(String),
+
     #[error("database error: {0}")]
     Database(String),
 
@@ -33,6 +36,7 @@ impl IntoResponse for AuthError {
             AuthError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg.clone()),
             AuthError::Duplicate(msg) => (StatusCode::CONFLICT, msg.clone()),
             AuthError::InvalidInput(msg) => (StatusCode::BAD_REQUEST, msg.clone()),
+            AuthError::WeakPassword(_) => (StatusCode::BAD_REQUEST, self.to_string()),
             AuthError::Database(msg) => {
                 tracing::error!("Database error: {msg}");
                 (StatusCode::INTERNAL_SERVER_ERROR, "internal error".into())
@@ -13,6 +13,16 @@ pub struct AuthClaims {
     pub iat: usize,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub iss: Option<String>,
+    /// ID of the impersonation record, for impersonation tokens.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+    /// User ID of the admin acting as `sub`, for impersonation tokens.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub impersonated_by: Option<String>,
+    /// Only namespaces whose names start with this are visible to the
+    /// bearer; absent for all of them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace_prefix: Option<String>,
 }
 
 pub fn create_jwt(
@@ -22,11 +32,6 @@ pub fn create_jwt(
     permissions: Vec<String>,
     secret: &str,
 ) -> Result<String, AuthError> {
-    if secret.len() < 32 {
-        return Err(AuthError::InvalidInput(
-            "JWT secret must be at least 32 bytes".to_string(),
-        ));
-    }
     let now = chrono::Utc::now().timestamp() as usize;
     let claims = AuthClaims {
         sub: user_id.to_string(),
@@ -36,10 +41,51 @@ pub fn create_jwt(
         exp: now + 86400,
         iat: now,
         iss: Some("enigma".to_string()),
+        jti: None,
+        impersonated_by: None,
+        namespace_prefix: None,
+    };
+    encode_claims(&claims, secret)
+}
+
+/// A token for `user_id` held by the admin `impersonated_by`, valid for
+/// `duration_seconds`. `impersonation_id` is the store's record of it.
+#[allow(clippy::too_many_arguments)]
+pub fn create_impersonation_jwt(
+    user_id: &str,
+    username: &str,
+    groups: Vec<String>,
+    permissions: Vec<String>,
+    impersonated_by: &str,
+    impersonation_id: &str,
+    duration_seconds: u32,
+    secret: &str,
+) -> Result<String, AuthError> {
+    let now = chrono::Utc::now().timestamp() as usize;
+    let claims = AuthClaims {
+        sub: user_id.to_string(),
+        username: username.to_string(),
+        groups,
+        permissions,
+        exp: now + duration_seconds as usize,
+        iat: now,
+        iss: Some("enigma".to_string()),
+        jti: Some(impersonation_id.to_string()),
+        impersonated_by: Some(impersonated_by.to_string()),
+        namespace_prefix: None,
     };
+    encode_claims(&claims, secret)
+}
+
+fn encode_claims(claims: &AuthClaims, secret: &str) -> Result<String, AuthError> {
+    if secret.len() < 32 {
+        return Err(AuthError::InvalidInput(
+            "JWT secret must be at least 32 bytes".to_string(),
+        ));
+    }
     encode(
         &Header::default(),
-        &claims,
+        claims,
         &EncodingKey::from_secret(secret.as_bytes()),
     )
     .map_err(|e| AuthError::Internal(format!("jwt encode error: {e}")))
@@ -61,3 +107,67 @@ pub fn verify_jwt(token: &str, secret: &str) -> Result<AuthClaims, AuthError> {
     .map_err(|_| AuthError::Unauthorized)?;
     Ok(data.claims)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "test-secret-test-secret-test-secret";
+
+    #[test]
+    fn impersonation_claims_round_trip() {
+        let token = create_impersonation_jwt(
+            "u-bob",
+            "bob",
+            vec!["read".into()],
+            vec!["buckets:read".into()],
+            "u-admin",
+            "imp-1",
+            600,
+            SECRET,
+        )
+        .unwrap();
+        let claims = verify_jwt(&token, SECRET).unwrap();
+        assert_eq!(claims.sub, "u-bob");
+        assert_eq!(claims.impersonated_by.as_deref(), Some("u-admin"));
+        assert_eq!(claims.jti.as_deref(), Some("imp-1"));
+        assert_eq!(claims.exp - claims.iat, 600);
+
+        let regular = create_jwt("u-bob", "bob", vec![], vec![], SECRET).unwrap();
+        assert!(
+            verify_jwt(&regular, SECRET)
+                .unwrap()
+                .impersonated_by
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn namespace_prefix_claim_round_trips() {
+        let now = chrono::Utc::now().timestamp() as usize;
+        let claims = AuthClaims {
+            sub: "u-a".into(),
+            username: "alice".into(),
+            groups: vec![],
+            permissions: vec!["s3:read".into()],
+            exp: now + 60,
+            iat: now,
+            iss: Some("enigma".into()),
+            jti: None,
+            impersonated_by: None,
+            namespace_prefix: Some("team-a/".into()),
+        };
+        let token = encode_claims(&claims, SECRET).unwrap();
+        let decoded = verify_jwt(&token, SECRET).unwrap();
+        assert_eq!(decoded.namespace_prefix.as_deref(), Some("team-a/"));
+
+        // Tokens issued before the claim existed see every namespace
+        let regular = create_jwt("u-a", "alice", vec![], vec![], SECRET).unwrap();
+        assert!(
+            verify_jwt(&regular, SECRET)
+                .unwrap()
+                .namespace_prefix
+                .is_none()
+        );
+    }
+}
@@ -1,6 +1,7 @@
 pub mod error;
 pub mod jwt;
 pub mod middleware;
+pub mod oidc;
 pub mod password;
 pub mod permissions;
 pub mod store;
@@ -8,12 +9,16 @@ pub mod token;
 pub mod types;
 
 pub use error::AuthError;
-pub use jwt::{AuthClaims, create_jwt, verify_jwt};
+pub use jwt::{AuthClaims, create_impersonation_jwt, create_jwt, verify_jwt};
 pub use middleware::AuthUser;
-pub use password::{hash_password, verify_password};
-pub use permissions::{PERMISSIONS, has_permission};
+pub use oidc::{OidcLogin, OidcProvider};
+pub use password::{NewPassword, hash_password, validate_password, verify_password};
+pub use permissions::{
+    PERMISSIONS, has_permission, namespace_visible, parse_allowed_ips, token_allows_ip,
+    token_has_scope,
+};
 pub use store::{AuthStore, SqliteAuthStore};
-pub use token::{generate_api_token, hash_token};
+pub use token::{generate_api_token, generate_reset_token, hash_token};
 pub use types::*;
 
 #[cfg(feature = "postgres")]
@@ -1,13 +1,17 @@
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
 
-use axum::extract::FromRequestParts;
+use axum::extract::{ConnectInfo, FromRequestParts};
 use axum::http::request::Parts;
+use axum::http::{Extensions, HeaderMap};
+use ipnet::IpNet;
 
 use crate::error::AuthError;
 use crate::jwt::{AuthClaims, verify_jwt};
-use crate::permissions::has_permission;
+use crate::permissions::{has_permission, namespace_visible, token_allows_ip, token_has_scope};
 use crate::store::AuthStore;
 use crate::token::hash_token;
+use crate::types::ApiToken;
 
 #[derive(Debug, Clone)]
 pub struct AuthUser {
@@ -15,12 +19,29 @@ pub struct AuthUser {
     pub username: String,
     pub groups: Vec<String>,
     pub permissions: Vec<String>,
+    /// Set when the request was authenticated with an API token; its scopes
+    /// further restrict the user's group permissions.
+    pub api_token: Option<ApiToken>,
+    /// Admin user ID, when the request carries an impersonation token.
+    pub impersonated_by: Option<String>,
+    /// Namespace name prefix the request is restricted to, from the API
+    /// token or the JWT's `namespace_prefix` claim.
+    pub namespace_prefix: Option<String>,
+}
+
+impl AuthUser {
+    /// Whether `namespace` is visible to this user.
+    pub fn sees_namespace(&self, namespace: &str) -> bool {
+        namespace_visible(self.namespace_prefix.as_deref(), namespace)
+    }
 }
 
 #[derive(Clone)]
 pub struct AuthState {
     pub jwt_secret: String,
     pub auth_store: Arc<dyn AuthStore>,
+    /// Reverse proxies whose `X-Forwarded-For` is believed; see [`client_ip`].
+    pub trusted_proxies: Vec<IpNet>,
 }
 
 impl<S> FromRequestParts<S> for AuthUser
@@ -30,6 +51,11 @@ where
     type Rejection = AuthError;
 
     async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        // Already resolved by a middleware in front of the handler
+        if let Some(user) = parts.extensions.get::<AuthUser>() {
+            return Ok(user.clone());
+        }
+
         let auth_state = parts
             .extensions
             .get::<AuthState>()
@@ -51,13 +77,27 @@ where
             let token_hash = hash_token(bearer);
             let store = &auth_state.auth_store;
             let (api_token, user) = store.verify_token(&token_hash).await?;
-            // Touch last_used_at in background
+            if !token_allows_ip(
+                &api_token,
+                client_ip(
+                    &parts.headers,
+                    &parts.extensions,
+                    &auth_state.trusted_proxies,
+                ),
+            ) {
+                return Err(AuthError::Forbidden("ip_not_allowed".into()));
+            }
+            // Touch last_used_at and count the request in background
             let store_clone = auth_state.auth_store.clone();
             let tid = api_token.id.clone();
+            let bytes = request_bytes(parts);
             tokio::spawn(async move {
                 if let Err(e) = store_clone.touch_token(&tid).await {
                     tracing::warn!("Failed to update token last_used_at: {e}");
                 }
+                if let Err(e) = store_clone.record_token_usage(&tid, bytes).await {
+                    tracing::warn!("Failed to record token usage: {e}");
+                }
             });
             let permissions = store.get_user_permissions(&user.id).await?;
             let groups: Vec<String> = store
@@ -71,6 +111,9 @@ where
                 username: user.username,
                 groups,
                 permissions,
+                namespace_prefix: api_token.namespace_prefix.clone(),
+                api_token: Some(api_token),
+                impersonated_by: None,
             });
         }
 
@@ -81,16 +124,217 @@ where
             username: claims.username,
             groups: claims.groups,
             permissions: claims.permissions,
+            api_token: None,
+            impersonated_by: claims.impersonated_by,
+            namespace_prefix: claims.namespace_prefix,
         })
     }
 }
 
+/// Client IP: the socket peer or, when the peer is one of
+/// `trusted_proxies`, the last `X-Forwarded-For` hop not added by a trusted
+/// proxy. Clients can send any `X-Forwarded-For`, so it is ignored from
+/// other peers. Shared by the token IP check and the web rate limiter.
+pub fn client_ip(
+    headers: &HeaderMap,
+    extensions: &Extensions,
+    trusted_proxies: &[IpNet],
+) -> Option<IpAddr> {
+    // IPv4 peers on a dual-stack socket show up as ::ffff:a.b.c.d
+    let peer = extensions
+        .get::<ConnectInfo<SocketAddr>>()?
+        .0
+        .ip()
+        .to_canonical();
+    let trusted = |ip: &IpAddr| trusted_proxies.iter().any(|net| net.contains(ip));
+    if !trusted(&peer) {
+        return Some(peer);
+    }
+    let hops: Vec<IpAddr> = headers
+        .get_all("X-Forwarded-For")
+        .iter()
+        .filter_map(|h| h.to_str().ok())
+        .flat_map(|h| h.split(','))
+        .filter_map(|ip| ip.trim().parse::<IpAddr>().ok())
+        .map(|ip| ip.to_canonical())
+        .collect();
+    // Each proxy appends the address it got the request from
+    let client = hops.iter().rev().find(|ip| !trusted(ip)).or(hops.first());
+    Some(client.copied().unwrap_or(peer))
+}
+
+/// Request body size from `Content-Length`, 0 when absent.
+fn request_bytes(parts: &Parts) -> u64 {
+    parts
+        .headers
+        .get(axum::http::header::CONTENT_LENGTH)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.parse().ok())
+        .unwrap_or(0)
+}
+
 pub fn require_permission(user: &AuthUser, permission: &str) -> Result<(), AuthError> {
-    if has_permission(&user.permissions, permission) {
-        Ok(())
-    } else {
-        Err(AuthError::Forbidden(format!(
+    if !has_permission(&user.permissions, permission) {
+        return Err(AuthError::Forbidden(format!(
             "missing permission: {permission}"
-        )))
+        )));
+    }
+    if let Some(ref token) = user.api_token
+        && !token_has_scope(token, permission)
+    {
+        return Err(AuthError::Forbidden(format!(
+            "token scope does not include: {permission}"
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(permissions: &[&str], scopes: Option<&str>) -> AuthUser {
+        AuthUser {
+            user_id: "u1".into(),
+            username: "alice".into(),
+            groups: vec!["operators".into()],
+            permissions: permissions.iter().map(|p| p.to_string()).collect(),
+            api_token: scopes.map(|scopes| ApiToken {
+                id: "t1".into(),
+                user_id: "u1".into(),
+                name: "ci".into(),
+                token_prefix: "egt_00000000".into(),
+                scopes: scopes.into(),
+                expires_at: None,
+                last_used_at: None,
+                created_at: "2025-01-01 00:00:00".into(),
+                allowed_ips: None,
+                namespace_prefix: None,
+            }),
+            impersonated_by: None,
+            namespace_prefix: None,
+        }
+    }
+
+    #[test]
+    fn namespace_prefix_limits_visible_namespaces() {
+        let mut u = user(&["s3:read"], None);
+        assert!(u.sees_namespace("team-b/logs"));
+
+        u.namespace_prefix = Some("team-a/".into());
+        assert!(u.sees_namespace("team-a/logs"));
+        assert!(!u.sees_namespace("team-b/logs"));
+        assert!(!u.sees_namespace("team-a"));
+    }
+
+    #[test]
+    fn jwt_user_uses_group_permissions_only() {
+        let u = user(&["buckets:read", "buckets:write"], None);
+        assert!(require_permission(&u, "buckets:write").is_ok());
+        assert!(require_permission(&u, "users:read").is_err());
+    }
+
+    #[test]
+    fn token_scope_narrows_group_permissions() {
+        // Granted by the user's groups but missing from the token's scopes
+        let u = user(&["buckets:read", "buckets:write"], Some("buckets:read"));
+        assert!(require_permission(&u, "buckets:read").is_ok());
+        assert!(matches!(
+            require_permission(&u, "buckets:write"),
+            Err(AuthError::Forbidden(_))
+        ));
+    }
+
+    #[test]
+    fn token_scope_cannot_widen_group_permissions() {
+        let u = user(&["buckets:read"], Some("*"));
+        assert!(require_permission(&u, "buckets:write").is_err());
+    }
+
+    /// Request parts carrying `raw_token` from `ip`, with the auth state attached.
+    fn parts(store: Arc<dyn AuthStore>, raw_token: &str, ip: &str) -> Parts {
+        let (mut parts, ()) = axum::http::Request::builder()
+            .header("Authorization", format!("Bearer {raw_token}"))
+            .body(())
+            .unwrap()
+            .into_parts();
+        parts
+            .extensions
+            .insert(ConnectInfo(SocketAddr::new(ip.parse().unwrap(), 40000)));
+        parts.extensions.insert(AuthState {
+            jwt_secret: "secret".into(),
+            auth_store: store,
+            trusted_proxies: Vec::new(),
+        });
+        parts
+    }
+
+    #[tokio::test]
+    async fn token_used_from_disallowed_ip_is_forbidden() {
+        let store = crate::store::SqliteAuthStore::open_in_memory().unwrap();
+        store.migrate().await.unwrap();
+        let user = store
+            .create_user("ci", &crate::password::NewPassword::disabled(), None)
+            .await
+            .unwrap();
+        let raw_token = crate::token::generate_api_token();
+        let token = store
+            .create_token(
+                &user.id,
+                "ci",
+                &hash_token(&raw_token),
+                &raw_token[..12],
+                "*",
+                None,
+            )
+            .await
+            .unwrap();
+        store
+            .update_token_allowed_ips(&token.id, Some("203.0.113.0/24"))
+            .await
+            .unwrap();
+        let store: Arc<dyn AuthStore> = Arc::new(store);
+
+        let mut ok = parts(store.clone(), &raw_token, "203.0.113.9");
+        assert!(AuthUser::from_request_parts(&mut ok, &()).await.is_ok());
+
+        let mut denied = parts(store, &raw_token, "198.51.100.1");
+        let err = AuthUser::from_request_parts(&mut denied, &())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AuthError::Forbidden(ref m) if m == "ip_not_allowed"));
+    }
+
+    /// Headers and extensions of a request from `peer` forwarded for `xff`.
+    fn forwarded(peer: &str, xff: &str) -> (HeaderMap, Extensions) {
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Forwarded-For", xff.parse().unwrap());
+        let mut extensions = Extensions::new();
+        extensions.insert(ConnectInfo(SocketAddr::new(peer.parse().unwrap(), 40000)));
+        (headers, extensions)
+    }
+
+    #[test]
+    fn forwarded_for_counts_only_behind_trusted_proxies() {
+        let trusted: Vec<IpNet> = vec!["10.0.0.0/8".parse().unwrap()];
+        let ip = |s: &str| Some(s.parse::<IpAddr>().unwrap());
+
+        // Spoofed by the client itself
+        let (headers, extensions) = forwarded("198.51.100.7", "203.0.113.9");
+        assert_eq!(
+            client_ip(&headers, &extensions, &trusted),
+            ip("198.51.100.7")
+        );
+
+        // The last hop before the trusted proxies, whatever the client sent
+        let (headers, extensions) = forwarded("10.0.0.2", "192.0.2.1, 203.0.113.9, 10.0.0.1");
+        assert_eq!(
+            client_ip(&headers, &extensions, &trusted),
+            ip("203.0.113.9")
+        );
+        assert_eq!(client_ip(&headers, &extensions, &[]), ip("10.0.0.2"));
+
+        // Not known without the socket peer
+        assert_eq!(client_ip(&headers, &Extensions::new(), &trusted), None);
     }
 }
@@ -0,0 +1,336 @@
+//! OpenID Connect single sign-on (authorization code flow with PKCE).
+//!
+//! [`OidcProvider::authorize`] starts a login and returns the identity
+//! provider URL to send the browser to; [`OidcProvider::complete`] handles
+//! the callback, verifies the ID token and returns the linked user.
+
+use openidconnect::core::{
+    CoreAuthenticationFlow, CoreClient, CoreJwsSigningAlgorithm, CoreProviderMetadata,
+};
+use openidconnect::{
+    AuthorizationCode, ClientId, ClientSecret, CsrfToken, IssuerUrl, Nonce, PkceCodeChallenge,
+    PkceCodeVerifier, RedirectUrl, Scope, TokenResponse, reqwest,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::error::AuthError;
+use crate::store::AuthStore;
+use crate::types::{OidcLoginState, User};
+
+/// How long a started login may take before its callback is refused.
+pub const STATE_TTL_SECONDS: u64 = 600;
+
+const DISCOVERY_SUFFIX: &str = "/.well-known/openid-configuration";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OidcProvider {
+    /// Issuer URL, or the full `/.well-known/openid-configuration` URL.
+    pub discovery_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    /// Callback registered with the provider, e.g.
+    /// `https://enigma.example.com/api/auth/oidc/callback`.
+    pub redirect_uri: String,
+}
+
+/// A completed login.
+#[derive(Debug, Clone)]
+pub struct OidcLogin {
+    pub user: User,
+    /// `name` claim, when the provider sent one.
+    pub name: Option<String>,
+}
+
+impl OidcProvider {
+    /// Start a login: remember its state, nonce and PKCE verifier in
+    /// `store` and return the URL to redirect the browser to.
+    pub async fn authorize(&self, store: &dyn AuthStore) -> Result<String, AuthError> {
+        let http = http_client()?;
+        let metadata = self.discover(&http).await?;
+        let client = CoreClient::from_provider_metadata(
+            metadata,
+            ClientId::new(self.client_id.clone()),
+            Some(ClientSecret::new(self.client_secret.clone())),
+        )
+        .set_redirect_uri(self.redirect_url()?);
+
+        let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+        let (url, state, nonce) = client
+            .authorize_url(
+                CoreAuthenticationFlow::AuthorizationCode,
+                CsrfToken::new_random,
+                Nonce::new_random,
+            )
+            .add_scope(Scope::new("email".to_string()))
+            .add_scope(Scope::new("profile".to_string()))
+            .set_pkce_challenge(pkce_challenge)
+            .url();
+
+        store
+            .save_oidc_state(
+                state.secret(),
+                &OidcLoginState {
+                    nonce: nonce.secret().clone(),
+                    pkce_verifier: pkce_verifier.secret().clone(),
+                },
+            )
+            .await?;
+        Ok(url.to_string())
+    }
+
+    /// Finish the login started with `state`: exchange `code` for tokens,
+    /// verify the ID token and return the user linked to its subject,
+    /// creating it on first login.
+    ///
+    /// The user name is the email address when the provider vouches for
+    /// it, else `preferred_username`, else the subject.
+    pub async fn complete(
+        &self,
+        store: &dyn AuthStore,
+        code: &str,
+        state: &str,
+    ) -> Result<OidcLogin, AuthError> {
+        let login = store
+            .take_oidc_state(state, STATE_TTL_SECONDS)
+            .await?
+            .ok_or(AuthError::Unauthorized)?;
+
+        let http = http_client()?;
+        let metadata = self.discover(&http).await?;
+        // Accept what the provider says it signs with (HS* is keyed with the
+        // client secret), but never unsigned tokens
+        let algs: Vec<CoreJwsSigningAlgorithm> = metadata
+            .id_token_signing_alg_values_supported()
+            .iter()
+            .filter(|alg| **alg != CoreJwsSigningAlgorithm::None)
+            .cloned()
+            .collect();
+        let client = CoreClient::from_provider_metadata(
+            metadata,
+            ClientId::new(self.client_id.clone()),
+            Some(ClientSecret::new(self.client_secret.clone())),
+        )
+        .set_redirect_uri(self.redirect_url()?);
+
+        let response = client
+            .exchange_code(AuthorizationCode::new(code.to_string()))
+            .map_err(|e| AuthError::Internal(format!("OIDC provider has no token endpoint: {e}")))?
+            .set_pkce_verifier(PkceCodeVerifier::new(login.pkce_verifier))
+            .request_async(&http)
+            .await
+            .map_err(|e| {
+                tracing::warn!("OIDC code exchange failed: {e}");
+                AuthError::Unauthorized
+            })?;
+        let id_token = response
+            .id_token()
+            .ok_or_else(|| AuthError::Internal("OIDC token response has no ID token".into()))?;
+        let verifier = client.id_token_verifier().set_allowed_algs(algs);
+        let claims = id_token
+            .claims(&verifier, &Nonce::new(login.nonce))
+            .map_err(|e| {
+                tracing::warn!("OIDC ID token rejected: {e}");
+                AuthError::Unauthorized
+            })?;
+
+        let subject = claims.subject().as_str();
+        let email = claims
+            .email()
+            .filter(|_| claims.email_verified() != Some(false))
+            .map(|email| email.as_str().to_string());
+        let username = email
+            .clone()
+            .or_else(|| claims.preferred_username().map(|u| u.as_str().to_string()))
+            .unwrap_or_else(|| subject.to_string());
+        let name = claims
+            .name()
+            .and_then(|name| name.get(None))
+            .map(|name| name.as_str().to_string());
+
+        let user = store
+            .upsert_oidc_user(
+                claims.issuer().as_str(),
+                subject,
+                &username,
+                email.as_deref(),
+            )
+            .await?;
+        Ok(OidcLogin { user, name })
+    }
+
+    async fn discover(&self, http: &reqwest::Client) -> Result<CoreProviderMetadata, AuthError> {
+        let issuer = self
+            .discovery_url
+            .strip_suffix(DISCOVERY_SUFFIX)
+            .unwrap_or(&self.discovery_url);
+        let issuer = IssuerUrl::new(issuer.to_string())
+            .map_err(|e| AuthError::InvalidInput(format!("invalid OIDC discovery URL: {e}")))?;
+        CoreProviderMetadata::discover_async(issuer, http)
+            .await
+            .map_err(|e| AuthError::Internal(format!("OIDC discovery failed: {e}")))
+    }
+
+    fn redirect_url(&self) -> Result<RedirectUrl, AuthError> {
+        RedirectUrl::new(self.redirect_uri.clone())
+            .map_err(|e| AuthError::InvalidInput(format!("invalid OIDC redirect URI: {e}")))
+    }
+}
+
+fn http_client() -> Result<reqwest::Client, AuthError> {
+    // Following redirects from the provider would allow SSRF
+    reqwest::ClientBuilder::new()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .map_err(|e| AuthError::Internal(format!("OIDC HTTP client: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use jsonwebtoken::{EncodingKey, Header, encode};
+    use serde_json::json;
+    use wiremock::matchers::{body_string_contains, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::store::SqliteAuthStore;
+
+    const CLIENT_SECRET: &str = "client-secret-client-secret-0123";
+
+    async fn store() -> SqliteAuthStore {
+        let store = SqliteAuthStore::open_in_memory().unwrap();
+        store.migrate().await.unwrap();
+        store
+    }
+
+    /// Identity provider serving discovery and an empty JWKS; ID tokens
+    /// are HS256-signed with the client secret.
+    async fn identity_provider() -> (MockServer, OidcProvider) {
+        let server = MockServer::start().await;
+        let base = server.uri();
+        Mock::given(method("GET"))
+            .and(path("/.well-known/openid-configuration"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "issuer": base,
+                "authorization_endpoint": format!("{base}/authorize"),
+                "token_endpoint": format!("{base}/token"),
+                "jwks_uri": format!("{base}/jwks"),
+                "response_types_supported": ["code"],
+                "subject_types_supported": ["public"],
+                "id_token_signing_alg_values_supported": ["HS256"],
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/jwks"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "keys": [] })))
+            .mount(&server)
+            .await;
+
+        let provider = OidcProvider {
+            discovery_url: format!("{base}{DISCOVERY_SUFFIX}"),
+            client_id: "enigma".to_string(),
+            client_secret: CLIENT_SECRET.to_string(),
+            redirect_uri: "http://localhost:8080/api/auth/oidc/callback".to_string(),
+        };
+        (server, provider)
+    }
+
+    /// Answer the next code exchange with an ID token carrying `nonce`.
+    async fn issue_id_token(server: &MockServer, subject: &str, nonce: &str) {
+        let now = chrono::Utc::now().timestamp();
+        let id_token = encode(
+            &Header::default(),
+            &json!({
+                "iss": server.uri(),
+                "aud": "enigma",
+                "sub": subject,
+                "iat": now,
+                "exp": now + 300,
+                "nonce": nonce,
+                "email": "dana@example.com",
+                "email_verified": true,
+                "name": "Dana Scully",
+            }),
+            &EncodingKey::from_secret(CLIENT_SECRET.as_bytes()),
+        )
+        .unwrap();
+        Mock::given(method("POST"))
+            .and(path("/token"))
+            .and(body_string_contains("code_verifier="))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "access_token": "access",
+                "token_type": "Bearer",
+                "expires_in": 300,
+                "id_token": id_token,
+            })))
+            .up_to_n_times(1)
+            .mount(server)
+            .await;
+    }
+
+    fn query_param(url: &str, name: &str) -> String {
+        openidconnect::url::Url::parse(url)
+            .unwrap()
+            .query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn login_creates_then_reuses_user() {
+        let (server, provider) = identity_provider().await;
+        let store = store().await;
+
+        let url = provider.authorize(&store).await.unwrap();
+        assert!(url.starts_with(&format!("{}/authorize?", server.uri())));
+        assert_eq!(query_param(&url, "code_challenge_method"), "S256");
+        assert!(query_param(&url, "scope").contains("openid"));
+        let state = query_param(&url, "state");
+        issue_id_token(&server, "sub-42", &query_param(&url, "nonce")).await;
+
+        let login = provider.complete(&store, "code", &state).await.unwrap();
+        assert_eq!(login.user.username, "dana@example.com");
+        assert_eq!(login.user.email.as_deref(), Some("dana@example.com"));
+        assert_eq!(login.name.as_deref(), Some("Dana Scully"));
+
+        // The state cannot be replayed
+        let err = provider.complete(&store, "code", &state).await.unwrap_err();
+        assert!(matches!(err, AuthError::Unauthorized));
+
+        let url = provider.authorize(&store).await.unwrap();
+        issue_id_token(&server, "sub-42", &query_param(&url, "nonce")).await;
+        let again = provider
+            .complete(&store, "code", &query_param(&url, "state"))
+            .await
+            .unwrap();
+        assert_eq!(again.user.id, login.user.id);
+    }
+
+    #[tokio::test]
+    async fn mismatched_nonce_is_rejected() {
+        let (server, provider) = identity_provider().await;
+        let store = store().await;
+
+        let url = provider.authorize(&store).await.unwrap();
+        issue_id_token(&server, "sub-42", "some-other-nonce").await;
+        let err = provider
+            .complete(&store, "code", &query_param(&url, "state"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AuthError::Unauthorized));
+        assert_eq!(store.user_count().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn unknown_state_is_rejected() {
+        let (_server, provider) = identity_provider().await;
+        let store = store().await;
+
+        let err = provider
+            .complete(&store, "code", "never-issued")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AuthError::Unauthorized));
+    }
+}
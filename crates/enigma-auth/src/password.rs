@@ -4,6 +4,63 @@ use argon2::{
 };
 
 use crate::error::AuthError;
+use crate::types::PasswordPolicy;
+
+/// Check `password` against `policy`. The error names the first rule broken.
+pub fn validate_password(password: &str, policy: &PasswordPolicy) -> Result<(), AuthError> {
+    let weak = |reason: String| Err(AuthError::WeakPassword(reason));
+
+    let len = password.chars().count();
+    if len < policy.min_length {
+        return weak(format!(
+            "must be at least {} characters long",
+            policy.min_length
+        ));
+    }
+    if len > policy.max_length {
+        return weak(format!(
+            "must be at most {} characters long",
+            policy.max_length
+        ));
+    }
+    if policy.require_uppercase && !password.chars().any(char::is_uppercase) {
+        return weak("must contain an uppercase letter".into());
+    }
+    if policy.require_lowercase && !password.chars().any(char::is_lowercase) {
+        return weak("must contain a lowercase letter".into());
+    }
+    if policy.require_digit && !password.chars().any(|c| c.is_ascii_digit()) {
+        return weak("must contain a digit".into());
+    }
+    if policy.require_special && password.chars().all(char::is_alphanumeric) {
+        return weak("must contain a special character".into());
+    }
+    Ok(())
+}
+
+/// A password that passed the password policy, held as its Argon2 hash.
+/// The auth stores only take new passwords in this form, so none can be
+/// set without the policy check.
+#[derive(Clone)]
+pub struct NewPassword(String);
+
+impl NewPassword {
+    /// Check `password` against `policy` and hash it.
+    pub fn new(password: &str, policy: &PasswordPolicy) -> Result<Self, AuthError> {
+        validate_password(password, policy)?;
+        Ok(Self(hash_password(password)?))
+    }
+
+    /// A password nothing matches, for accounts that log in some other way
+    /// (the configured admin). It is not a valid Argon2 hash.
+    pub fn disabled() -> Self {
+        Self("!disabled".into())
+    }
+
+    pub fn hash(&self) -> &str {
+        &self.0
+    }
+}
 
 pub fn hash_password(password: &str) -> Result<String, AuthError> {
     let salt = SaltString::generate(&mut OsRng);
@@ -21,3 +78,96 @@ pub fn verify_password(password: &str, hash: &str) -> Result<bool, AuthError> {
         .verify_password(password.as_bytes(), &parsed)
         .is_ok())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reason(password: &str, policy: &PasswordPolicy) -> String {
+        match validate_password(password, policy) {
+            Err(AuthError::WeakPassword(reason)) => reason,
+            other => panic!("expected WeakPassword, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn default_policy_accepts_strong_password() {
+        let policy = PasswordPolicy::default();
+        assert!(validate_password("Correct-Horse-9", &policy).is_ok());
+    }
+
+    #[test]
+    fn min_length() {
+        let policy = PasswordPolicy::default();
+        assert!(reason("Sh0rt!", &policy).contains("at least 12 characters"));
+        // Counted in characters, not bytes
+        assert!(reason("Ünïcödé-1", &policy).contains("at least 12"));
+    }
+
+    #[test]
+    fn max_length() {
+        let policy = PasswordPolicy {
+            max_length: 16,
+            ..Default::default()
+        };
+        assert!(reason("Way-Too-Long-Password-1", &policy).contains("at most 16 characters"));
+    }
+
+    #[test]
+    fn require_uppercase() {
+        let policy = PasswordPolicy::default();
+        assert!(reason("correct-horse-9", &policy).contains("uppercase"));
+    }
+
+    #[test]
+    fn require_lowercase() {
+        let policy = PasswordPolicy::default();
+        assert!(reason("CORRECT-HORSE-9", &policy).contains("lowercase"));
+    }
+
+    #[test]
+    fn require_digit() {
+        let policy = PasswordPolicy::default();
+        assert!(reason("Correct-Horse-X", &policy).contains("digit"));
+    }
+
+    #[test]
+    fn require_special() {
+        let policy = PasswordPolicy::default();
+        assert!(reason("CorrectHorse99", &policy).contains("special character"));
+    }
+
+    #[test]
+    fn disabled_rules_are_skipped() {
+        let policy = PasswordPolicy {
+            min_length: 4,
+            require_uppercase: false,
+            require_lowercase: false,
+            require_digit: false,
+            require_special: false,
+            ..Default::default()
+        };
+        assert!(validate_password("abcd", &policy).is_ok());
+    }
+
+    #[test]
+    fn error_message_names_the_rule() {
+        let err = validate_password("correct-horse-9", &PasswordPolicy::default()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "weak password: must contain an uppercase letter"
+        );
+    }
+
+    #[test]
+    fn new_password_is_checked_then_hashed() {
+        let policy = PasswordPolicy::default();
+        assert!(matches!(
+            NewPassword::new("correct-horse-9", &policy),
+            Err(AuthError::WeakPassword(_))
+        ));
+        let password = NewPassword::new("Correct-Horse-9", &policy).unwrap();
+        assert!(verify_password("Correct-Horse-9", password.hash()).unwrap());
+        assert!(verify_password("Correct-Horse-9", NewPassword::disabled().hash()).is_err());
+    }
+}
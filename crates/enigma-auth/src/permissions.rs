@@ -1,3 +1,10 @@
+use std::net::IpAddr;
+
+use ipnet::IpNet;
+
+use crate::error::AuthError;
+use crate::types::ApiToken;
+
 pub const PERMISSIONS: &[(&str, &str)] = &[
     ("dashboard:read", "View dashboard and system status"),
     ("storage:read", "View storage providers and chunk stats"),
@@ -20,8 +27,171 @@ pub const PERMISSIONS: &[(&str, &str)] = &[
     ("s3:read", "S3 read operations"),
     ("s3:write", "S3 write operations"),
     ("s3:admin", "S3 administrative operations"),
+    ("objects:lock:bypass", "Override GOVERNANCE object locks"),
+    ("objects:legal-hold:manage", "Place and clear legal holds"),
+    ("admin:impersonate", "Act as another user for debugging"),
 ];
 
 pub fn has_permission(user_permissions: &[String], required: &str) -> bool {
     user_permissions.iter().any(|p| p == "*" || p == required)
 }
+
+/// Whether an API token's comma-separated scope list grants `required_permission`.
+/// `"*"` matches everything and `"buckets:*"` matches any `buckets:` permission.
+pub fn token_has_scope(token: &ApiToken, required_permission: &str) -> bool {
+    token
+        .scopes
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .any(|scope| {
+            if scope == "*" || scope == required_permission {
+                return true;
+            }
+            match scope.strip_suffix('*') {
+                Some(prefix) if prefix.ends_with(':') => required_permission.starts_with(prefix),
+                _ => false,
+            }
+        })
+}
+
+/// Parse a comma-separated list of CIDRs (`10.0.0.0/8`, `2001:db8::/32`) or
+/// bare addresses, which match only themselves.
+pub fn parse_allowed_ips(list: &str) -> Result<Vec<IpNet>, AuthError> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|entry| {
+            entry
+                .parse::<IpNet>()
+                .or_else(|_| entry.parse::<IpAddr>().map(IpNet::from))
+                .map_err(|_| AuthError::InvalidInput(format!("invalid CIDR: {entry}")))
+        })
+        .collect()
+}
+
+/// Whether an API token may be used from `client_ip`. A token without
+/// `allowed_ips` (or with an empty list) works from anywhere; a restricted
+/// token is refused when the client address is unknown.
+pub fn token_allows_ip(token: &ApiToken, client_ip: Option<IpAddr>) -> bool {
+    let nets = match token.allowed_ips.as_deref().map(parse_allowed_ips) {
+        None => return true,
+        Some(Ok(nets)) if nets.is_empty() => return true,
+        Some(Ok(nets)) => nets,
+        // Validated on write; refuse rather than open up on a bad entry
+        Some(Err(_)) => return false,
+    };
+    let Some(ip) = client_ip else { return false };
+    // IPv4 peers on a dual-stack socket show up as ::ffff:a.b.c.d
+    let ip = ip.to_canonical();
+    nets.iter().any(|net| net.contains(&ip))
+}
+
+/// Whether `namespace` is visible under `prefix`. No prefix shows every
+/// namespace.
+pub fn namespace_visible(prefix: Option<&str>, namespace: &str) -> bool {
+    prefix.is_none_or(|prefix| namespace.starts_with(prefix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(scopes: &str) -> ApiToken {
+        ApiToken {
+            id: "t1".into(),
+            user_id: "u1".into(),
+            name: "ci".into(),
+            token_prefix: "egt_00000000".into(),
+            scopes: scopes.into(),
+            expires_at: None,
+            last_used_at: None,
+            created_at: "2025-01-01 00:00:00".into(),
+            allowed_ips: None,
+            namespace_prefix: None,
+        }
+    }
+
+    fn restricted(allowed_ips: &str) -> ApiToken {
+        ApiToken {
+            allowed_ips: Some(allowed_ips.into()),
+            ..token("*")
+        }
+    }
+
+    fn ip(s: &str) -> Option<IpAddr> {
+        Some(s.parse().unwrap())
+    }
+
+    #[test]
+    fn wildcard_scope_matches_everything() {
+        let t = token("*");
+        assert!(token_has_scope(&t, "buckets:read"));
+        assert!(token_has_scope(&t, "users:write"));
+    }
+
+    #[test]
+    fn prefix_wildcard_scope() {
+        let t = token("buckets:*");
+        assert!(token_has_scope(&t, "buckets:read"));
+        assert!(token_has_scope(&t, "buckets:write"));
+        assert!(!token_has_scope(&t, "backups:read"));
+        assert!(!token_has_scope(&t, "bucketsx:read"));
+    }
+
+    #[test]
+    fn exact_scope_match() {
+        let t = token("buckets:read, audit:read");
+        assert!(token_has_scope(&t, "buckets:read"));
+        assert!(token_has_scope(&t, "audit:read"));
+        assert!(!token_has_scope(&t, "buckets:write"));
+        assert!(!token_has_scope(&token(""), "buckets:read"));
+    }
+
+    #[test]
+    fn ipv4_cidr_matching() {
+        let t = restricted("10.0.0.0/8, 192.168.1.7");
+        assert!(token_allows_ip(&t, ip("10.1.2.3")));
+        assert!(token_allows_ip(&t, ip("192.168.1.7")));
+        assert!(!token_allows_ip(&t, ip("192.168.1.8")));
+        assert!(!token_allows_ip(&t, ip("11.0.0.1")));
+        // IPv4-mapped IPv6 peer address
+        assert!(token_allows_ip(&t, ip("::ffff:10.9.9.9")));
+    }
+
+    #[test]
+    fn ipv6_cidr_matching() {
+        let t = restricted("2001:db8::/32");
+        assert!(token_allows_ip(&t, ip("2001:db8:1::42")));
+        assert!(!token_allows_ip(&t, ip("2001:db9::1")));
+        assert!(!token_allows_ip(&t, ip("10.0.0.1")));
+    }
+
+    #[test]
+    fn empty_allowed_ips_is_unrestricted() {
+        assert!(token_allows_ip(&token("*"), ip("203.0.113.5")));
+        assert!(token_allows_ip(&restricted(""), ip("203.0.113.5")));
+        assert!(token_allows_ip(&restricted(" , "), None));
+    }
+
+    #[test]
+    fn mismatch_or_unknown_ip_is_refused() {
+        let t = restricted("198.51.100.0/24");
+        assert!(!token_allows_ip(&t, ip("203.0.113.5")));
+        assert!(!token_allows_ip(&t, None));
+        assert!(!token_allows_ip(
+            &restricted("not-a-cidr"),
+            ip("203.0.113.5")
+        ));
+    }
+
+    #[test]
+    fn parse_rejects_invalid_entries() {
+        assert_eq!(parse_allowed_ips("10.0.0.0/8,::1").unwrap().len(), 2);
+        assert!(matches!(
+            parse_allowed_ips("10.0.0.0/33"),
+            Err(AuthError::InvalidInput(_))
+        ));
+        assert!(parse_allowed_ips("example.com").is_err());
+    }
+}
@@ -10,8 +10,10 @@ pub use sqlite::SqliteAuthStore;
 pub use postgres::PostgresAuthStore;
 
 use async_trait::async_trait;
+use chrono::NaiveDate;
 
 use crate::error::AuthError;
+use crate::password::NewPassword;
 use crate::types::*;
 
 #[async_trait]
@@ -20,18 +22,79 @@ pub trait AuthStore: Send + Sync {
     async fn create_user(
         &self,
         username: &str,
-        password_hash: &str,
+        password: &NewPassword,
         email: Option<&str>,
     ) -> Result<User, AuthError>;
     async fn get_user_by_id(&self, id: &str) -> Result<User, AuthError>;
     async fn get_user_by_username(&self, username: &str) -> Result<User, AuthError>;
+    async fn get_user_by_email(&self, email: &str) -> Result<User, AuthError>;
     async fn list_users(&self) -> Result<Vec<User>, AuthError>;
     async fn update_user(&self, id: &str, req: &UpdateUserRequest) -> Result<User, AuthError>;
-    async fn update_password(&self, id: &str, password_hash: &str) -> Result<(), AuthError>;
+    async fn update_password(&self, id: &str, password: &NewPassword) -> Result<(), AuthError>;
     async fn delete_user(&self, id: &str) -> Result<(), AuthError>;
     async fn get_password_hash(&self, user_id: &str) -> Result<String, AuthError>;
     async fn user_count(&self) -> Result<u64, AuthError>;
 
+    // Login lockout
+    /// The active lock on `user_id`, if any.
+    async fn lockout_status(&self, user_id: &str) -> Result<Option<LockoutInfo>, AuthError>;
+    /// Count a failed login. Returns the lock once the store's lockout
+    /// threshold is reached; the counter then starts over.
+    async fn record_failed_login(&self, user_id: &str) -> Result<Option<LockoutInfo>, AuthError>;
+    /// Reset the failure counter and lift any lock.
+    async fn clear_failed_logins(&self, user_id: &str) -> Result<(), AuthError>;
+
+    // Password reset
+    /// Issue a single-use password reset token for `user_id`, valid for
+    /// `ttl_minutes`. Only its SHA-256 hash is stored; the raw token is
+    /// returned for the reset link.
+    async fn create_reset_token(
+        &self,
+        user_id: &str,
+        ttl_minutes: u32,
+    ) -> Result<String, AuthError>;
+    /// Set the password of the user `raw_token` was issued to and use up
+    /// the token. Fails with `InvalidInput` for an unknown, expired or
+    /// already used token.
+    async fn use_reset_token(
+        &self,
+        raw_token: &str,
+        new_password: &NewPassword,
+    ) -> Result<(), AuthError>;
+
+    // Storage usage and quota
+    /// Add to a user's stored bytes and object count in `namespace`; the
+    /// totals never drop below zero.
+    async fn update_user_usage(
+        &self,
+        user_id: &str,
+        namespace: &str,
+        delta_bytes: i64,
+        delta_objects: i32,
+    ) -> Result<(), AuthError>;
+    /// Usage per namespace, ordered by namespace.
+    async fn get_user_usage(&self, user_id: &str) -> Result<Vec<UserStorageUsage>, AuthError>;
+    /// The user's storage quota in bytes; `None` for unlimited.
+    async fn get_user_quota(&self, user_id: &str) -> Result<Option<u64>, AuthError>;
+    async fn set_user_quota(
+        &self,
+        user_id: &str,
+        quota_bytes: Option<u64>,
+    ) -> Result<(), AuthError>;
+    /// Bytes the user may still store; `None` for unlimited.
+    async fn remaining_user_quota(&self, user_id: &str) -> Result<Option<u64>, AuthError> {
+        let Some(quota) = self.get_user_quota(user_id).await? else {
+            return Ok(None);
+        };
+        let used: u64 = self
+            .get_user_usage(user_id)
+            .await?
+            .iter()
+            .map(|u| u.bytes_stored)
+            .sum();
+        Ok(Some(quota.saturating_sub(used)))
+    }
+
     // Groups
     async fn create_group(
         &self,
@@ -58,6 +121,23 @@ pub trait AuthStore: Send + Sync {
     ) -> Result<(), AuthError>;
     async fn list_group_permissions(&self, group_id: &str) -> Result<Vec<Permission>, AuthError>;
 
+    // Group-Group: a group holds the permissions of every group it
+    // contains, transitively
+    /// Fails with `InvalidInput` when `parent_group_id` is `child_group_id`
+    /// or already contained in it.
+    async fn add_group_member_group(
+        &self,
+        parent_group_id: &str,
+        child_group_id: &str,
+    ) -> Result<(), AuthError>;
+    async fn remove_group_member_group(
+        &self,
+        parent_group_id: &str,
+        child_group_id: &str,
+    ) -> Result<(), AuthError>;
+    /// Groups directly contained in `group_id`.
+    async fn list_group_member_groups(&self, group_id: &str) -> Result<Vec<Group>, AuthError>;
+
     // User-Group
     async fn add_user_group(&self, user_id: &str, group_id: &str) -> Result<(), AuthError>;
     async fn remove_user_group(&self, user_id: &str, group_id: &str) -> Result<(), AuthError>;
@@ -75,9 +155,47 @@ pub trait AuthStore: Send + Sync {
         expires_at: Option<&str>,
     ) -> Result<ApiToken, AuthError>;
     async fn verify_token(&self, token_hash: &str) -> Result<(ApiToken, User), AuthError>;
+    /// SHA-256 hash of the token with ID `id`.
+    async fn get_token_hash(&self, id: &str) -> Result<String, AuthError>;
+    /// Resolve an S3 access key, which is the ID of an API token, to the
+    /// token, its user and the token's hash, which serves as the secret
+    /// key. Fails like [`AuthStore::verify_token`] for an expired token or
+    /// an inactive user.
+    async fn verify_s3_access_key(
+        &self,
+        access_key: &str,
+    ) -> Result<(ApiToken, User, String), AuthError> {
+        let token_hash = self.get_token_hash(access_key).await?;
+        let (token, user) = self.verify_token(&token_hash).await?;
+        Ok((token, user, token_hash))
+    }
     async fn list_tokens(&self, user_id: &str) -> Result<Vec<ApiToken>, AuthError>;
+    async fn update_token_scopes(&self, id: &str, scopes: &str) -> Result<ApiToken, AuthError>;
+    /// Replace a token's comma-separated list of allowed source CIDRs;
+    /// `None` lifts the restriction.
+    async fn update_token_allowed_ips(
+        &self,
+        id: &str,
+        allowed_ips: Option<&str>,
+    ) -> Result<ApiToken, AuthError>;
+    /// Restrict a token to namespaces whose names start with
+    /// `namespace_prefix`; `None` lifts the restriction.
+    async fn update_token_namespace_prefix(
+        &self,
+        id: &str,
+        namespace_prefix: Option<&str>,
+    ) -> Result<ApiToken, AuthError>;
     async fn revoke_token(&self, id: &str) -> Result<(), AuthError>;
     async fn touch_token(&self, id: &str) -> Result<(), AuthError>;
+    /// Count one request of `bytes` against the token for today (UTC).
+    async fn record_token_usage(&self, token_id: &str, bytes: u64) -> Result<(), AuthError>;
+    /// Daily usage over the last `days` days up to today, oldest first, with
+    /// zero entries for days without requests.
+    async fn get_token_usage_history(
+        &self,
+        token_id: &str,
+        days: u32,
+    ) -> Result<Vec<TokenUsageDay>, AuthError>;
 
     // Permissions
     async fn list_permissions(&self) -> Result<Vec<Permission>, AuthError>;
@@ -94,10 +212,96 @@ pub trait AuthStore: Send + Sync {
         action: &str,
         target: Option<&str>,
         ip_addr: Option<&str>,
+        correlation_id: &str,
+    ) -> Result<(), AuthError>;
+    /// Audit a request `user_id` made through an impersonation token
+    /// issued to `impersonated_by`.
+    async fn log_impersonated_audit(
+        &self,
+        user_id: &str,
+        impersonated_by: &str,
+        action: &str,
+        target: Option<&str>,
+        ip_addr: Option<&str>,
+        correlation_id: &str,
     ) -> Result<(), AuthError>;
     async fn list_audit(&self, limit: u32, offset: u32) -> Result<Vec<AuditEntry>, AuthError>;
+    /// Delete audit entries older than `older_than_days` days, in batches of
+    /// [`AUDIT_PURGE_BATCH`] rows so writers are not locked out meanwhile.
+    /// Returns the number of entries deleted.
+    async fn purge_old_audit_entries(&self, older_than_days: u32) -> Result<u64, AuthError>;
+    async fn audit_stats(&self) -> Result<AuditStats, AuthError>;
+
+    // Impersonation
+    /// Record an impersonation token for `target_user_id`, valid for
+    /// `duration_seconds` from now.
+    async fn create_impersonation(
+        &self,
+        admin_user_id: &str,
+        target_user_id: &str,
+        duration_seconds: u32,
+    ) -> Result<Impersonation, AuthError>;
+    /// Impersonation tokens that have not expired yet, newest first.
+    async fn list_active_impersonations(&self) -> Result<Vec<Impersonation>, AuthError>;
+
+    // OpenID Connect
+    async fn save_oidc_state(&self, state: &str, login: &OidcLoginState) -> Result<(), AuthError>;
+    /// Remove and return the login started with `state`, unless it is
+    /// older than `max_age_seconds`. Expired logins are purged as well.
+    async fn take_oidc_state(
+        &self,
+        state: &str,
+        max_age_seconds: u64,
+    ) -> Result<Option<OidcLoginState>, AuthError>;
+    /// Return the user linked to the identity provider's `subject`,
+    /// creating it (without a usable password) on first login.
+    async fn upsert_oidc_user(
+        &self,
+        issuer: &str,
+        subject: &str,
+        username: &str,
+        email: Option<&str>,
+    ) -> Result<User, AuthError>;
 
     // Lifecycle
     async fn migrate(&self) -> Result<(), AuthError>;
     async fn seed_defaults(&self) -> Result<(), AuthError>;
 }
+
+/// Audit entries deleted per statement by
+/// [`AuthStore::purge_old_audit_entries`].
+pub const AUDIT_PURGE_BATCH: u32 = 1000;
+
+/// Password hash stored for users created by OpenID Connect login. It is
+/// not a valid Argon2 hash, so password login always fails for them.
+pub(crate) const OIDC_PASSWORD_HASH: &str = "!oidc";
+
+/// First day of a `days`-long usage window ending on `today`.
+pub(crate) fn usage_window_start(today: NaiveDate, days: u32) -> NaiveDate {
+    today - chrono::Days::new(u64::from(days.max(1) - 1))
+}
+
+/// One entry per day from `start` through `today`, taking recorded days
+/// from `recorded` and zero for the rest.
+pub(crate) fn fill_usage_days(
+    start: NaiveDate,
+    today: NaiveDate,
+    recorded: Vec<TokenUsageDay>,
+) -> Vec<TokenUsageDay> {
+    let mut recorded: std::collections::HashMap<String, TokenUsageDay> = recorded
+        .into_iter()
+        .map(|day| (day.date.clone(), day))
+        .collect();
+    start
+        .iter_days()
+        .take_while(|day| *day <= today)
+        .map(|day| {
+            let date = day.format("%Y-%m-%d").to_string();
+            recorded.remove(&date).unwrap_or(TokenUsageDay {
+                date,
+                request_count: 0,
+                bytes_transferred: 0,
+            })
+        })
+        .collect()
+}
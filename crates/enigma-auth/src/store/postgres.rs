@@ -3,12 +3,15 @@
 use async_trait::async_trait;
 use sqlx::PgPool;
 
-use super::AuthStore;
+use super::{AUDIT_PURGE_BATCH, AuthStore, OIDC_PASSWORD_HASH};
 use crate::error::AuthError;
+use crate::password::NewPassword;
+use crate::token::{generate_reset_token, hash_token};
 use crate::types::*;
 
 pub struct PostgresAuthStore {
     pool: PgPool,
+    lockout: LockoutPolicy,
 }
 
 impl PostgresAuthStore {
@@ -16,7 +19,15 @@ impl PostgresAuthStore {
         let pool = PgPool::connect(database_url)
             .await
             .map_err(|e| AuthError::Database(e.to_string()))?;
-        Ok(Self { pool })
+        Ok(Self {
+            pool,
+            lockout: LockoutPolicy::default(),
+        })
+    }
+
+    pub fn with_lockout(mut self, lockout: LockoutPolicy) -> Self {
+        self.lockout = lockout;
+        self
     }
 }
 
@@ -31,6 +42,11 @@ CREATE TABLE IF NOT EXISTS auth_users (
     updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
 );
 
+ALTER TABLE auth_users ADD COLUMN IF NOT EXISTS failed_login_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE auth_users ADD COLUMN IF NOT EXISTS locked_until TIMESTAMPTZ;
+ALTER TABLE auth_users ADD COLUMN IF NOT EXISTS last_failed_at TIMESTAMPTZ;
+ALTER TABLE auth_users ADD COLUMN IF NOT EXISTS user_storage_quota_bytes BIGINT;
+
 CREATE TABLE IF NOT EXISTS auth_groups (
     id TEXT PRIMARY KEY,
     name TEXT UNIQUE NOT NULL,
@@ -58,6 +74,12 @@ CREATE TABLE IF NOT EXISTS auth_user_groups (
     PRIMARY KEY (user_id, group_id)
 );
 
+CREATE TABLE IF NOT EXISTS auth_group_members (
+    parent_group_id TEXT NOT NULL REFERENCES auth_groups(id) ON DELETE CASCADE,
+    child_group_id TEXT NOT NULL REFERENCES auth_groups(id) ON DELETE CASCADE,
+    PRIMARY KEY (parent_group_id, child_group_id)
+);
+
 CREATE TABLE IF NOT EXISTS auth_api_tokens (
     id TEXT PRIMARY KEY,
     user_id TEXT NOT NULL REFERENCES auth_users(id) ON DELETE CASCADE,
@@ -70,14 +92,69 @@ CREATE TABLE IF NOT EXISTS auth_api_tokens (
     created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
 );
 
+ALTER TABLE auth_api_tokens ADD COLUMN IF NOT EXISTS allowed_ips TEXT;
+ALTER TABLE auth_api_tokens ADD COLUMN IF NOT EXISTS namespace_prefix TEXT;
+
+CREATE TABLE IF NOT EXISTS user_storage_usage (
+    user_id TEXT NOT NULL REFERENCES auth_users(id) ON DELETE CASCADE,
+    namespace TEXT NOT NULL,
+    bytes_stored BIGINT NOT NULL DEFAULT 0,
+    object_count BIGINT NOT NULL DEFAULT 0,
+    last_updated TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, namespace)
+);
+
+CREATE TABLE IF NOT EXISTS auth_token_usage (
+    token_id TEXT NOT NULL REFERENCES auth_api_tokens(id) ON DELETE CASCADE,
+    date TEXT NOT NULL,
+    request_count BIGINT NOT NULL DEFAULT 0,
+    bytes_transferred BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (token_id, date)
+);
+
 CREATE TABLE IF NOT EXISTS auth_audit_log (
     id BIGSERIAL PRIMARY KEY,
     user_id TEXT,
     action TEXT NOT NULL,
     target TEXT,
     ip_addr TEXT,
+    correlation_id TEXT NOT NULL DEFAULT '',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+ALTER TABLE auth_audit_log ADD COLUMN IF NOT EXISTS correlation_id TEXT NOT NULL DEFAULT '';
+ALTER TABLE auth_audit_log ADD COLUMN IF NOT EXISTS impersonated_by TEXT;
+CREATE INDEX IF NOT EXISTS idx_auth_audit_log_created_at ON auth_audit_log (created_at);
+
+CREATE TABLE IF NOT EXISTS auth_impersonations (
+    id TEXT PRIMARY KEY,
+    admin_user_id TEXT NOT NULL REFERENCES auth_users(id) ON DELETE CASCADE,
+    target_user_id TEXT NOT NULL REFERENCES auth_users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS auth_password_reset_tokens (
+    token_hash TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL REFERENCES auth_users(id) ON DELETE CASCADE,
+    expires_at TIMESTAMPTZ NOT NULL,
+    used BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE TABLE IF NOT EXISTS auth_oidc_state (
+    state TEXT PRIMARY KEY,
+    nonce TEXT NOT NULL,
+    pkce_verifier TEXT NOT NULL,
     created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
 );
+
+CREATE TABLE IF NOT EXISTS auth_oidc_identities (
+    issuer TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    user_id TEXT NOT NULL REFERENCES auth_users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (issuer, subject)
+);
 "#;
 
 #[async_trait]
@@ -99,7 +176,7 @@ impl AuthStore for PostgresAuthStore {
     async fn create_user(
         &self,
         username: &str,
-        password_hash: &str,
+        password: &NewPassword,
         email: Option<&str>,
     ) -> Result<User, AuthError> {
         let id = uuid::Uuid::now_v7().to_string();
@@ -111,7 +188,7 @@ impl AuthStore for PostgresAuthStore {
         .bind(&id)
         .bind(username)
         .bind(email)
-        .bind(password_hash)
+        .bind(password.hash())
         .fetch_one(&self.pool)
         .await
         .map_err(|e| {
@@ -172,6 +249,26 @@ impl AuthStore for PostgresAuthStore {
         })
     }
 
+    async fn get_user_by_email(&self, email: &str) -> Result<User, AuthError> {
+        let row = sqlx::query_as::<_, (String, String, Option<String>, bool, String, String)>(
+            "SELECT id, username, email, is_active, created_at::text, updated_at::text
+             FROM auth_users WHERE email = $1",
+        )
+        .bind(email)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AuthError::Database(e.to_string()))?
+        .ok_or_else(|| AuthError::NotFound("user not found".into()))?;
+        Ok(User {
+            id: row.0,
+            username: row.1,
+            email: row.2,
+            is_active: row.3,
+            created_at: row.4,
+            updated_at: row.5,
+        })
+    }
+
     async fn list_users(&self) -> Result<Vec<User>, AuthError> {
         let rows = sqlx::query_as::<_, (String, String, Option<String>, bool, String, String)>(
             "SELECT id, username, email, is_active, created_at::text, updated_at::text
@@ -213,11 +310,11 @@ impl AuthStore for PostgresAuthStore {
         self.get_user_by_id(id).await
     }
 
-    async fn update_password(&self, id: &str, password_hash: &str) -> Result<(), AuthError> {
+    async fn update_password(&self, id: &str, password: &NewPassword) -> Result<(), AuthError> {
         let result = sqlx::query(
             "UPDATE auth_users SET password_hash = $1, updated_at = NOW() WHERE id = $2",
         )
-        .bind(password_hash)
+        .bind(password.hash())
         .bind(id)
         .execute(&self.pool)
         .await
@@ -259,6 +356,195 @@ impl AuthStore for PostgresAuthStore {
         Ok(row.0 as u64)
     }
 
+    // --- Login lockout ---
+
+    async fn lockout_status(&self, user_id: &str) -> Result<Option<LockoutInfo>, AuthError> {
+        let row = sqlx::query_as::<_, (String, i64)>(
+            "SELECT locked_until::text, CEIL(EXTRACT(EPOCH FROM locked_until - NOW()))::BIGINT
+             FROM auth_users WHERE id = $1 AND locked_until > NOW()",
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AuthError::Database(e.to_string()))?;
+        Ok(row.map(|(locked_until, secs)| LockoutInfo {
+            locked_until,
+            retry_after_seconds: secs.max(1) as u64,
+        }))
+    }
+
+    async fn record_failed_login(&self, user_id: &str) -> Result<Option<LockoutInfo>, AuthError> {
+        let result = sqlx::query(
+            "UPDATE auth_users SET failed_login_count = failed_login_count + 1,
+                last_failed_at = NOW() WHERE id = $1",
+        )
+        .bind(user_id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AuthError::Database(e.to_string()))?;
+        if result.rows_affected() == 0 {
+            return Err(AuthError::NotFound("user not found".into()));
+        }
+        if self.lockout.threshold == 0 {
+            return Ok(None);
+        }
+        sqlx::query(
+            "UPDATE auth_users SET failed_login_count = 0,
+                locked_until = NOW() + make_interval(secs => $1)
+             WHERE id = $2 AND failed_login_count >= $3",
+        )
+        .bind(self.lockout.duration_seconds as f64)
+        .bind(user_id)
+        .bind(self.lockout.threshold as i32)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AuthError::Database(e.to_string()))?;
+        self.lockout_status(user_id).await
+    }
+
+    async fn clear_failed_logins(&self, user_id: &str) -> Result<(), AuthError> {
+        let result = sqlx::query(
+            "UPDATE auth_users SET failed_login_count = 0, locked_until = NULL WHERE id = $1",
+        )
+        .bind(user_id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AuthError::Database(e.to_string()))?;
+        if result.rows_affected() == 0 {
+            return Err(AuthError::NotFound("user not found".into()));
+        }
+        Ok(())
+    }
+
+    // --- Password reset ---
+
+    async fn create_reset_token(
+        &self,
+        user_id: &str,
+        ttl_minutes: u32,
+    ) -> Result<String, AuthError> {
+        let raw_token = generate_reset_token();
+        sqlx::query(
+            "INSERT INTO auth_password_reset_tokens (token_hash, user_id, expires_at)
+             VALUES ($1, $2, NOW() + make_interval(mins => $3))",
+        )
+        .bind(hash_token(&raw_token))
+        .bind(user_id)
+        .bind(ttl_minutes as i32)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AuthError::Database(e.to_string()))?;
+        Ok(raw_token)
+    }
+
+    async fn use_reset_token(
+        &self,
+        raw_token: &str,
+        new_password: &NewPassword,
+    ) -> Result<(), AuthError> {
+        // Claiming the token in one statement keeps two concurrent resets
+        // from both using it
+        let (user_id,) = sqlx::query_as::<_, (String,)>(
+            "UPDATE auth_password_reset_tokens SET used = TRUE
+             WHERE token_hash = $1 AND NOT used AND expires_at > NOW()
+             RETURNING user_id",
+        )
+        .bind(hash_token(raw_token))
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AuthError::Database(e.to_string()))?
+        .ok_or_else(|| AuthError::InvalidInput("invalid or expired reset token".into()))?;
+        // Other links mailed to the user die with this one
+        sqlx::query("UPDATE auth_password_reset_tokens SET used = TRUE WHERE user_id = $1")
+            .bind(&user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| AuthError::Database(e.to_string()))?;
+        self.update_password(&user_id, new_password).await
+    }
+
+    // --- Storage usage ---
+
+    async fn update_user_usage(
+        &self,
+        user_id: &str,
+        namespace: &str,
+        delta_bytes: i64,
+        delta_objects: i32,
+    ) -> Result<(), AuthError> {
+        sqlx::query(
+            "INSERT INTO user_storage_usage (user_id, namespace, bytes_stored, object_count)
+             VALUES ($1, $2, GREATEST(0, $3), GREATEST(0, $4))
+             ON CONFLICT (user_id, namespace) DO UPDATE SET
+                 bytes_stored = GREATEST(0, user_storage_usage.bytes_stored + $3),
+                 object_count = GREATEST(0, user_storage_usage.object_count + $4),
+                 last_updated = NOW()",
+        )
+        .bind(user_id)
+        .bind(namespace)
+        .bind(delta_bytes)
+        .bind(i64::from(delta_objects))
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AuthError::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn get_user_usage(&self, user_id: &str) -> Result<Vec<UserStorageUsage>, AuthError> {
+        let rows = sqlx::query_as::<_, (String, i64, i64, String)>(
+            "SELECT namespace, bytes_stored, object_count, last_updated::text
+             FROM user_storage_usage WHERE user_id = $1 ORDER BY namespace",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AuthError::Database(e.to_string()))?;
+        Ok(rows
+            .into_iter()
+            .map(
+                |(namespace, bytes, objects, last_updated)| UserStorageUsage {
+                    namespace,
+                    bytes_stored: bytes as u64,
+                    object_count: objects as u64,
+                    last_updated,
+                },
+            )
+            .collect())
+    }
+
+    async fn get_user_quota(&self, user_id: &str) -> Result<Option<u64>, AuthError> {
+        let row = sqlx::query_as::<_, (Option<i64>,)>(
+            "SELECT user_storage_quota_bytes FROM auth_users WHERE id = $1",
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AuthError::Database(e.to_string()))?;
+        match row {
+            Some((quota,)) => Ok(quota.map(|q| q as u64)),
+            None => Err(AuthError::NotFound("user not found".into())),
+        }
+    }
+
+    async fn set_user_quota(
+        &self,
+        user_id: &str,
+        quota_bytes: Option<u64>,
+    ) -> Result<(), AuthError> {
+        let result = sqlx::query(
+            "UPDATE auth_users SET user_storage_quota_bytes = $1, updated_at = NOW() WHERE id = $2",
+        )
+        .bind(quota_bytes.map(|q| q as i64))
+        .bind(user_id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AuthError::Database(e.to_string()))?;
+        if result.rows_affected() == 0 {
+            return Err(AuthError::NotFound("user not found".into()));
+        }
+        Ok(())
+    }
+
     // --- Groups ---
 
     async fn create_group(
@@ -446,6 +732,97 @@ impl AuthStore for PostgresAuthStore {
             .collect())
     }
 
+    // --- Group-Group ---
+
+    async fn add_group_member_group(
+        &self,
+        parent_group_id: &str,
+        child_group_id: &str,
+    ) -> Result<(), AuthError> {
+        let found =
+            sqlx::query_as::<_, (i64,)>("SELECT COUNT(*) FROM auth_groups WHERE id IN ($1, $2)")
+                .bind(parent_group_id)
+                .bind(child_group_id)
+                .fetch_one(&self.pool)
+                .await
+                .map_err(|e| AuthError::Database(e.to_string()))?;
+        if found.0 < 2 && parent_group_id != child_group_id {
+            return Err(AuthError::NotFound("group not found".into()));
+        }
+        // The parent must not already be the child or one of its members
+        let cycle = sqlx::query_as::<_, (bool,)>(
+            "WITH RECURSIVE descendants(id) AS (
+                 SELECT $1::text
+                 UNION
+                 SELECT gm.child_group_id
+                 FROM auth_group_members gm
+                 JOIN descendants d ON gm.parent_group_id = d.id
+             )
+             SELECT EXISTS (SELECT 1 FROM descendants WHERE id = $2)",
+        )
+        .bind(child_group_id)
+        .bind(parent_group_id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| AuthError::Database(e.to_string()))?;
+        if cycle.0 {
+            return Err(AuthError::InvalidInput(
+                "a group cannot be added as its own ancestor".into(),
+            ));
+        }
+        sqlx::query(
+            "INSERT INTO auth_group_members (parent_group_id, child_group_id)
+             VALUES ($1, $2)
+             ON CONFLICT DO NOTHING",
+        )
+        .bind(parent_group_id)
+        .bind(child_group_id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AuthError::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn remove_group_member_group(
+        &self,
+        parent_group_id: &str,
+        child_group_id: &str,
+    ) -> Result<(), AuthError> {
+        sqlx::query(
+            "DELETE FROM auth_group_members WHERE parent_group_id = $1 AND child_group_id = $2",
+        )
+        .bind(parent_group_id)
+        .bind(child_group_id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AuthError::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn list_group_member_groups(&self, group_id: &str) -> Result<Vec<Group>, AuthError> {
+        let rows = sqlx::query_as::<_, (String, String, String, bool, String)>(
+            "SELECT g.id, g.name, g.description, g.is_system, g.created_at::text
+             FROM auth_groups g
+             JOIN auth_group_members gm ON gm.child_group_id = g.id
+             WHERE gm.parent_group_id = $1
+             ORDER BY g.name",
+        )
+        .bind(group_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AuthError::Database(e.to_string()))?;
+        Ok(rows
+            .into_iter()
+            .map(|r| Group {
+                id: r.0,
+                name: r.1,
+                description: r.2,
+                is_system: r.3,
+                created_at: r.4,
+            })
+            .collect())
+    }
+
     // --- User-Group ---
 
     async fn add_user_group(&self, user_id: &str, group_id: &str) -> Result<(), AuthError> {
@@ -495,12 +872,19 @@ impl AuthStore for PostgresAuthStore {
     }
 
     async fn get_user_permissions(&self, user_id: &str) -> Result<Vec<String>, AuthError> {
+        // Expand the user's groups through nested groups before collecting
         let rows = sqlx::query_as::<_, (String,)>(
-            "SELECT DISTINCT p.action
+            "WITH RECURSIVE member_groups(group_id) AS (
+                 SELECT group_id FROM auth_user_groups WHERE user_id = $1
+                 UNION
+                 SELECT gm.child_group_id
+                 FROM auth_group_members gm
+                 JOIN member_groups mg ON gm.parent_group_id = mg.group_id
+             )
+             SELECT DISTINCT p.action
              FROM auth_permissions p
              JOIN auth_group_permissions gp ON gp.permission_id = p.id
-             JOIN auth_user_groups ug ON ug.group_id = gp.group_id
-             WHERE ug.user_id = $1
+             JOIN member_groups mg ON mg.group_id = gp.group_id
              ORDER BY p.action",
         )
         .bind(user_id)
@@ -522,10 +906,10 @@ impl AuthStore for PostgresAuthStore {
         expires_at: Option<&str>,
     ) -> Result<ApiToken, AuthError> {
         let id = uuid::Uuid::now_v7().to_string();
-        let row = sqlx::query_as::<_, (String, String, String, String, String, Option<String>, Option<String>, String)>(
+        let row = sqlx::query_as::<_, (String, String, String, String, String, Option<String>, Option<String>, String, Option<String>, Option<String>)>(
             "INSERT INTO auth_api_tokens (id, user_id, name, token_hash, token_prefix, scopes, expires_at)
              VALUES ($1, $2, $3, $4, $5, $6, $7::timestamptz)
-             RETURNING id, user_id, name, token_prefix, scopes, expires_at::text, last_used_at::text, created_at::text",
+             RETURNING id, user_id, name, token_prefix, scopes, expires_at::text, last_used_at::text, created_at::text, allowed_ips, namespace_prefix",
         )
         .bind(&id)
         .bind(user_id)
@@ -546,16 +930,18 @@ impl AuthStore for PostgresAuthStore {
             expires_at: row.5,
             last_used_at: row.6,
             created_at: row.7,
+            allowed_ips: row.8,
+            namespace_prefix: row.9,
         })
     }
 
     async fn verify_token(&self, token_hash: &str) -> Result<(ApiToken, User), AuthError> {
         let row = sqlx::query_as::<_, (
             String, String, String, String, String, Option<String>, Option<String>, String,
-            String, String, Option<String>, bool, String, String,
+            Option<String>, Option<String>, String, String, Option<String>, bool, String, String,
         )>(
             "SELECT t.id, t.user_id, t.name, t.token_prefix, t.scopes, t.expires_at::text, t.last_used_at::text, t.created_at::text,
-                    u.id, u.username, u.email, u.is_active, u.created_at::text, u.updated_at::text
+                    t.allowed_ips, t.namespace_prefix, u.id, u.username, u.email, u.is_active, u.created_at::text, u.updated_at::text
              FROM auth_api_tokens t
              JOIN auth_users u ON u.id = t.user_id
              WHERE t.token_hash = $1",
@@ -575,14 +961,16 @@ impl AuthStore for PostgresAuthStore {
             expires_at: row.5,
             last_used_at: row.6,
             created_at: row.7,
+            allowed_ips: row.8,
+            namespace_prefix: row.9,
         };
         let user = User {
-            id: row.8,
-            username: row.9,
-            email: row.10,
-            is_active: row.11,
-            created_at: row.12,
-            updated_at: row.13,
+            id: row.10,
+            username: row.11,
+            email: row.12,
+            is_active: row.13,
+            created_at: row.14,
+            updated_at: row.15,
         };
 
         if !user.is_active {
@@ -599,8 +987,8 @@ impl AuthStore for PostgresAuthStore {
     }
 
     async fn list_tokens(&self, user_id: &str) -> Result<Vec<ApiToken>, AuthError> {
-        let rows = sqlx::query_as::<_, (String, String, String, String, String, Option<String>, Option<String>, String)>(
-            "SELECT id, user_id, name, token_prefix, scopes, expires_at::text, last_used_at::text, created_at::text
+        let rows = sqlx::query_as::<_, (String, String, String, String, String, Option<String>, Option<String>, String, Option<String>, Option<String>)>(
+            "SELECT id, user_id, name, token_prefix, scopes, expires_at::text, last_used_at::text, created_at::text, allowed_ips, namespace_prefix
              FROM auth_api_tokens WHERE user_id = $1 ORDER BY created_at DESC",
         )
         .bind(user_id)
@@ -618,10 +1006,106 @@ impl AuthStore for PostgresAuthStore {
                 expires_at: r.5,
                 last_used_at: r.6,
                 created_at: r.7,
+                allowed_ips: r.8,
+                namespace_prefix: r.9,
             })
             .collect())
     }
 
+    async fn update_token_scopes(&self, id: &str, scopes: &str) -> Result<ApiToken, AuthError> {
+        let r = sqlx::query_as::<_, (String, String, String, String, String, Option<String>, Option<String>, String, Option<String>, Option<String>)>(
+            "UPDATE auth_api_tokens SET scopes = $2 WHERE id = $1
+             RETURNING id, user_id, name, token_prefix, scopes, expires_at::text, last_used_at::text, created_at::text, allowed_ips, namespace_prefix",
+        )
+        .bind(id)
+        .bind(scopes)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AuthError::Database(e.to_string()))?
+        .ok_or(AuthError::NotFound("token not found".into()))?;
+        Ok(ApiToken {
+            id: r.0,
+            user_id: r.1,
+            name: r.2,
+            token_prefix: r.3,
+            scopes: r.4,
+            expires_at: r.5,
+            last_used_at: r.6,
+            created_at: r.7,
+            allowed_ips: r.8,
+            namespace_prefix: r.9,
+        })
+    }
+
+    async fn update_token_allowed_ips(
+        &self,
+        id: &str,
+        allowed_ips: Option<&str>,
+    ) -> Result<ApiToken, AuthError> {
+        let r = sqlx::query_as::<_, (String, String, String, String, String, Option<String>, Option<String>, String, Option<String>, Option<String>)>(
+            "UPDATE auth_api_tokens SET allowed_ips = $2 WHERE id = $1
+             RETURNING id, user_id, name, token_prefix, scopes, expires_at::text, last_used_at::text, created_at::text, allowed_ips, namespace_prefix",
+        )
+        .bind(id)
+        .bind(allowed_ips)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AuthError::Database(e.to_string()))?
+        .ok_or(AuthError::NotFound("token not found".into()))?;
+        Ok(ApiToken {
+            id: r.0,
+            user_id: r.1,
+            name: r.2,
+            token_prefix: r.3,
+            scopes: r.4,
+            expires_at: r.5,
+            last_used_at: r.6,
+            created_at: r.7,
+            allowed_ips: r.8,
+            namespace_prefix: r.9,
+        })
+    }
+
+    async fn update_token_namespace_prefix(
+        &self,
+        id: &str,
+        namespace_prefix: Option<&str>,
+    ) -> Result<ApiToken, AuthError> {
+        let r = sqlx::query_as::<_, (String, String, String, String, String, Option<String>, Option<String>, String, Option<String>, Option<String>)>(
+            "UPDATE auth_api_tokens SET namespace_prefix = $2 WHERE id = $1
+             RETURNING id, user_id, name, token_prefix, scopes, expires_at::text, last_used_at::text, created_at::text, allowed_ips, namespace_prefix",
+        )
+        .bind(id)
+        .bind(namespace_prefix)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AuthError::Database(e.to_string()))?
+        .ok_or(AuthError::NotFound("token not found".into()))?;
+        Ok(ApiToken {
+            id: r.0,
+            user_id: r.1,
+            name: r.2,
+            token_prefix: r.3,
+            scopes: r.4,
+            expires_at: r.5,
+            last_used_at: r.6,
+            created_at: r.7,
+            allowed_ips: r.8,
+            namespace_prefix: r.9,
+        })
+    }
+
+    async fn get_token_hash(&self, id: &str) -> Result<String, AuthError> {
+        let row =
+            sqlx::query_as::<_, (String,)>("SELECT token_hash FROM auth_api_tokens WHERE id = $1")
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(|e| AuthError::Database(e.to_string()))?
+                .ok_or_else(|| AuthError::NotFound("token not found".into()))?;
+        Ok(row.0)
+    }
+
     async fn revoke_token(&self, id: &str) -> Result<(), AuthError> {
         let result = sqlx::query("DELETE FROM auth_api_tokens WHERE id = $1")
             .bind(id)
@@ -643,6 +1127,52 @@ impl AuthStore for PostgresAuthStore {
         Ok(())
     }
 
+    async fn record_token_usage(&self, token_id: &str, bytes: u64) -> Result<(), AuthError> {
+        let today = chrono::Utc::now().format("%Y-%m-%d").to_string();
+        sqlx::query(
+            "INSERT INTO auth_token_usage (token_id, date, request_count, bytes_transferred)
+             VALUES ($1, $2, 1, $3)
+             ON CONFLICT (token_id, date) DO UPDATE SET
+                 request_count = auth_token_usage.request_count + 1,
+                 bytes_transferred = auth_token_usage.bytes_transferred + EXCLUDED.bytes_transferred",
+        )
+        .bind(token_id)
+        .bind(today)
+        .bind(bytes as i64)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AuthError::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn get_token_usage_history(
+        &self,
+        token_id: &str,
+        days: u32,
+    ) -> Result<Vec<TokenUsageDay>, AuthError> {
+        let today = chrono::Utc::now().date_naive();
+        let start = super::usage_window_start(today, days);
+        let rows = sqlx::query_as::<_, (String, i64, i64)>(
+            "SELECT date, request_count, bytes_transferred FROM auth_token_usage
+             WHERE token_id = $1 AND date >= $2 AND date <= $3 ORDER BY date",
+        )
+        .bind(token_id)
+        .bind(start.format("%Y-%m-%d").to_string())
+        .bind(today.format("%Y-%m-%d").to_string())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AuthError::Database(e.to_string()))?;
+        let recorded = rows
+            .into_iter()
+            .map(|(date, requests, bytes)| TokenUsageDay {
+                date,
+                request_count: requests as u64,
+                bytes_transferred: bytes as u64,
+            })
+            .collect();
+        Ok(super::fill_usage_days(start, today, recorded))
+    }
+
     // --- Permissions ---
 
     async fn list_permissions(&self) -> Result<Vec<crate::types::Permission>, AuthError> {
@@ -693,6 +1223,108 @@ impl AuthStore for PostgresAuthStore {
         })
     }
 
+    // --- OpenID Connect ---
+
+    async fn save_oidc_state(&self, state: &str, login: &OidcLoginState) -> Result<(), AuthError> {
+        sqlx::query(
+            "INSERT INTO auth_oidc_state (state, nonce, pkce_verifier) VALUES ($1, $2, $3)",
+        )
+        .bind(state)
+        .bind(&login.nonce)
+        .bind(&login.pkce_verifier)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AuthError::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn take_oidc_state(
+        &self,
+        state: &str,
+        max_age_seconds: u64,
+    ) -> Result<Option<OidcLoginState>, AuthError> {
+        sqlx::query(
+            "DELETE FROM auth_oidc_state WHERE created_at < NOW() - make_interval(secs => $1)",
+        )
+        .bind(max_age_seconds as f64)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AuthError::Database(e.to_string()))?;
+        let row = sqlx::query_as::<_, (String, String)>(
+            "DELETE FROM auth_oidc_state WHERE state = $1 RETURNING nonce, pkce_verifier",
+        )
+        .bind(state)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AuthError::Database(e.to_string()))?;
+        Ok(row.map(|(nonce, pkce_verifier)| OidcLoginState {
+            nonce,
+            pkce_verifier,
+        }))
+    }
+
+    async fn upsert_oidc_user(
+        &self,
+        issuer: &str,
+        subject: &str,
+        username: &str,
+        email: Option<&str>,
+    ) -> Result<User, AuthError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| AuthError::Database(e.to_string()))?;
+        let linked = sqlx::query_as::<_, (String,)>(
+            "SELECT user_id FROM auth_oidc_identities WHERE issuer = $1 AND subject = $2",
+        )
+        .bind(issuer)
+        .bind(subject)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| AuthError::Database(e.to_string()))?;
+        let id = match linked {
+            Some((id,)) => id,
+            None => {
+                // Never link to an existing local account by name: that
+                // would let whoever controls the IdP claim take it over
+                let id = uuid::Uuid::now_v7().to_string();
+                sqlx::query(
+                    "INSERT INTO auth_users (id, username, email, password_hash)
+                     VALUES ($1, $2, $3, $4)",
+                )
+                .bind(&id)
+                .bind(username)
+                .bind(email)
+                .bind(OIDC_PASSWORD_HASH)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| {
+                    if let sqlx::Error::Database(ref db_err) = e
+                        && db_err.code().is_some_and(|c| c == "23505")
+                    {
+                        return AuthError::Duplicate(format!("user '{username}' already exists"));
+                    }
+                    AuthError::Database(e.to_string())
+                })?;
+                sqlx::query(
+                    "INSERT INTO auth_oidc_identities (issuer, subject, user_id) VALUES ($1, $2, $3)",
+                )
+                .bind(issuer)
+                .bind(subject)
+                .bind(&id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| AuthError::Database(e.to_string()))?;
+                id
+            }
+        };
+        tx.commit()
+            .await
+            .map_err(|e| AuthError::Database(e.to_string()))?;
+        self.get_user_by_id(&id).await
+    }
+
     // --- Audit ---
 
     async fn log_audit(
@@ -701,14 +1333,43 @@ impl AuthStore for PostgresAuthStore {
         action: &str,
         target: Option<&str>,
         ip_addr: Option<&str>,
+        correlation_id: &str,
+    ) -> Result<(), AuthError> {
+        sqlx::query(
+            "INSERT INTO auth_audit_log (user_id, action, target, ip_addr, correlation_id)
+             VALUES ($1, $2, $3, $4, $5)",
+        )
+        .bind(user_id)
+        .bind(action)
+        .bind(target)
+        .bind(ip_addr)
+        .bind(correlation_id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AuthError::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn log_impersonated_audit(
+        &self,
+        user_id: &str,
+        impersonated_by: &str,
+        action: &str,
+        target: Option<&str>,
+        ip_addr: Option<&str>,
+        correlation_id: &str,
     ) -> Result<(), AuthError> {
         sqlx::query(
-            "INSERT INTO auth_audit_log (user_id, action, target, ip_addr) VALUES ($1, $2, $3, $4)",
+            "INSERT INTO auth_audit_log
+                 (user_id, action, target, ip_addr, correlation_id, impersonated_by)
+             VALUES ($1, $2, $3, $4, $5, $6)",
         )
         .bind(user_id)
         .bind(action)
         .bind(target)
         .bind(ip_addr)
+        .bind(correlation_id)
+        .bind(impersonated_by)
         .execute(&self.pool)
         .await
         .map_err(|e| AuthError::Database(e.to_string()))?;
@@ -725,10 +1386,13 @@ impl AuthStore for PostgresAuthStore {
                 Option<String>,
                 Option<String>,
                 String,
+                Option<String>,
+                String,
             ),
         >(
-            "SELECT id, user_id, action, target, ip_addr, created_at::text
-             FROM auth_audit_log ORDER BY created_at DESC LIMIT $1 OFFSET $2",
+            "SELECT id, user_id, action, target, ip_addr, correlation_id, impersonated_by,
+                    created_at::text
+             FROM auth_audit_log ORDER BY created_at DESC, id DESC LIMIT $1 OFFSET $2",
         )
         .bind(limit as i64)
         .bind(offset as i64)
@@ -743,8 +1407,101 @@ impl AuthStore for PostgresAuthStore {
                 action: r.2,
                 target: r.3,
                 ip_addr: r.4,
-                created_at: r.5,
+                correlation_id: r.5,
+                impersonated_by: r.6,
+                created_at: r.7,
             })
             .collect())
     }
+
+    async fn purge_old_audit_entries(&self, older_than_days: u32) -> Result<u64, AuthError> {
+        let mut deleted = 0;
+        loop {
+            let n = sqlx::query(
+                "DELETE FROM auth_audit_log WHERE id IN (
+                     SELECT id FROM auth_audit_log
+                     WHERE created_at < NOW() - make_interval(days => $1) LIMIT $2
+                 )",
+            )
+            .bind(older_than_days as i32)
+            .bind(AUDIT_PURGE_BATCH as i64)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| AuthError::Database(e.to_string()))?
+            .rows_affected();
+            deleted += n;
+            if n < u64::from(AUDIT_PURGE_BATCH) {
+                return Ok(deleted);
+            }
+        }
+    }
+
+    async fn audit_stats(&self) -> Result<AuditStats, AuthError> {
+        let (total, oldest, size) = sqlx::query_as::<_, (i64, Option<String>, i64)>(
+            "SELECT COUNT(*), MIN(created_at)::text, pg_total_relation_size('auth_audit_log')
+             FROM auth_audit_log",
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| AuthError::Database(e.to_string()))?;
+        Ok(AuditStats {
+            total_entries: total as u64,
+            oldest_entry_date: oldest,
+            estimated_size_bytes: size as u64,
+        })
+    }
+
+    // --- Impersonation ---
+
+    async fn create_impersonation(
+        &self,
+        admin_user_id: &str,
+        target_user_id: &str,
+        duration_seconds: u32,
+    ) -> Result<Impersonation, AuthError> {
+        let id = uuid::Uuid::now_v7().to_string();
+        let row = sqlx::query_as::<_, (String, String, String, String, String)>(
+            "INSERT INTO auth_impersonations (id, admin_user_id, target_user_id, expires_at)
+             VALUES ($1, $2, $3, NOW() + make_interval(secs => $4))
+             RETURNING id, admin_user_id, target_user_id, created_at::text, expires_at::text",
+        )
+        .bind(&id)
+        .bind(admin_user_id)
+        .bind(target_user_id)
+        .bind(duration_seconds as f64)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| AuthError::Database(e.to_string()))?;
+        Ok(impersonation_from_row(row))
+    }
+
+    async fn list_active_impersonations(&self) -> Result<Vec<Impersonation>, AuthError> {
+        let rows = sqlx::query_as::<_, (String, String, String, String, String)>(
+            "SELECT id, admin_user_id, target_user_id, created_at::text, expires_at::text
+             FROM auth_impersonations WHERE expires_at > NOW()
+             ORDER BY created_at DESC, id DESC",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AuthError::Database(e.to_string()))?;
+        Ok(rows.into_iter().map(impersonation_from_row).collect())
+    }
+}
+
+fn impersonation_from_row(
+    (id, admin_user_id, target_user_id, created_at, expires_at): (
+        String,
+        String,
+        String,
+        String,
+        String,
+    ),
+) -> Impersonation {
+    Impersonation {
+        id,
+        admin_user_id,
+        target_user_id,
+        created_at,
+        expires_at,
+    }
 }
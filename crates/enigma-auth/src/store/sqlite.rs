@@ -1,23 +1,35 @@
 use std::sync::Mutex;
 
 use async_trait::async_trait;
-use rusqlite::Connection;
+use chrono::{DateTime, NaiveDate, Utc};
+use rusqlite::{Connection, OptionalExtension};
 
-use super::AuthStore;
+use super::{
+    AUDIT_PURGE_BATCH, AuthStore, OIDC_PASSWORD_HASH, fill_usage_days, usage_window_start,
+};
 use crate::error::AuthError;
+use crate::password::NewPassword;
+use crate::token::{generate_reset_token, hash_token};
 use crate::types::*;
 
 pub struct SqliteAuthStore {
     conn: Mutex<Connection>,
+    lockout: LockoutPolicy,
 }
 
 impl SqliteAuthStore {
     pub fn new(conn: Connection) -> Self {
         Self {
             conn: Mutex::new(conn),
+            lockout: LockoutPolicy::default(),
         }
     }
 
+    pub fn with_lockout(mut self, lockout: LockoutPolicy) -> Self {
+        self.lockout = lockout;
+        self
+    }
+
     pub fn open(path: &str) -> Result<Self, AuthError> {
         let conn = Connection::open(path)?;
         conn.execute_batch("PRAGMA journal_mode=WAL; PRAGMA foreign_keys=ON;")?;
@@ -29,6 +41,63 @@ impl SqliteAuthStore {
         conn.execute_batch("PRAGMA foreign_keys=ON;")?;
         Ok(Self::new(conn))
     }
+
+    /// Count one request of `bytes` against the token on the UTC day of `at`.
+    pub fn record_token_usage_at(
+        &self,
+        token_id: &str,
+        at: DateTime<Utc>,
+        bytes: u64,
+    ) -> Result<(), AuthError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| AuthError::Database(format!("Lock poisoned: {e}")))?;
+        conn.execute(
+            "INSERT INTO auth_token_usage (token_id, date, request_count, bytes_transferred)
+             VALUES (?1, ?2, 1, ?3)
+             ON CONFLICT(token_id, date) DO UPDATE SET
+                 request_count = request_count + 1,
+                 bytes_transferred = bytes_transferred + excluded.bytes_transferred",
+            rusqlite::params![token_id, at.format("%Y-%m-%d").to_string(), bytes as i64],
+        )?;
+        Ok(())
+    }
+
+    /// Daily usage over the `days` days ending on `today`.
+    pub fn token_usage_history_until(
+        &self,
+        token_id: &str,
+        today: NaiveDate,
+        days: u32,
+    ) -> Result<Vec<TokenUsageDay>, AuthError> {
+        let start = usage_window_start(today, days);
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| AuthError::Database(format!("Lock poisoned: {e}")))?;
+        let mut stmt = conn.prepare(
+            "SELECT date, request_count, bytes_transferred FROM auth_token_usage
+             WHERE token_id = ?1 AND date >= ?2 AND date <= ?3 ORDER BY date",
+        )?;
+        let recorded = stmt
+            .query_map(
+                rusqlite::params![
+                    token_id,
+                    start.format("%Y-%m-%d").to_string(),
+                    today.format("%Y-%m-%d").to_string()
+                ],
+                |row| {
+                    Ok(TokenUsageDay {
+                        date: row.get(0)?,
+                        request_count: row.get::<_, i64>(1)? as u64,
+                        bytes_transferred: row.get::<_, i64>(2)? as u64,
+                    })
+                },
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(fill_usage_days(start, today, recorded))
+    }
 }
 
 const MIGRATE_SQL: &str = r#"
@@ -39,7 +108,11 @@ CREATE TABLE IF NOT EXISTS auth_users (
     password_hash TEXT NOT NULL,
     is_active INTEGER NOT NULL DEFAULT 1,
     created_at TEXT NOT NULL DEFAULT (datetime('now')),
-    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+    failed_login_count INTEGER NOT NULL DEFAULT 0,
+    locked_until TEXT,
+    last_failed_at TEXT,
+    user_storage_quota_bytes INTEGER
 );
 
 CREATE TABLE IF NOT EXISTS auth_groups (
@@ -69,6 +142,12 @@ CREATE TABLE IF NOT EXISTS auth_user_groups (
     PRIMARY KEY (user_id, group_id)
 );
 
+CREATE TABLE IF NOT EXISTS auth_group_members (
+    parent_group_id TEXT NOT NULL REFERENCES auth_groups(id) ON DELETE CASCADE,
+    child_group_id TEXT NOT NULL REFERENCES auth_groups(id) ON DELETE CASCADE,
+    PRIMARY KEY (parent_group_id, child_group_id)
+);
+
 CREATE TABLE IF NOT EXISTS auth_api_tokens (
     id TEXT PRIMARY KEY,
     user_id TEXT NOT NULL REFERENCES auth_users(id) ON DELETE CASCADE,
@@ -78,7 +157,26 @@ CREATE TABLE IF NOT EXISTS auth_api_tokens (
     scopes TEXT NOT NULL DEFAULT '*',
     expires_at TEXT,
     last_used_at TEXT,
-    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    allowed_ips TEXT,
+    namespace_prefix TEXT
+);
+
+CREATE TABLE IF NOT EXISTS user_storage_usage (
+    user_id TEXT NOT NULL REFERENCES auth_users(id) ON DELETE CASCADE,
+    namespace TEXT NOT NULL,
+    bytes_stored INTEGER NOT NULL DEFAULT 0,
+    object_count INTEGER NOT NULL DEFAULT 0,
+    last_updated TEXT NOT NULL DEFAULT (datetime('now')),
+    PRIMARY KEY (user_id, namespace)
+);
+
+CREATE TABLE IF NOT EXISTS auth_token_usage (
+    token_id TEXT NOT NULL REFERENCES auth_api_tokens(id) ON DELETE CASCADE,
+    date TEXT NOT NULL,
+    request_count INTEGER NOT NULL DEFAULT 0,
+    bytes_transferred INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (token_id, date)
 );
 
 CREATE TABLE IF NOT EXISTS auth_audit_log (
@@ -87,10 +185,90 @@ CREATE TABLE IF NOT EXISTS auth_audit_log (
     action TEXT NOT NULL,
     target TEXT,
     ip_addr TEXT,
+    correlation_id TEXT NOT NULL DEFAULT '',
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    impersonated_by TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_auth_audit_log_created_at ON auth_audit_log (created_at);
+
+CREATE TABLE IF NOT EXISTS auth_impersonations (
+    id TEXT PRIMARY KEY,
+    admin_user_id TEXT NOT NULL REFERENCES auth_users(id) ON DELETE CASCADE,
+    target_user_id TEXT NOT NULL REFERENCES auth_users(id) ON DELETE CASCADE,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    expires_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS auth_password_reset_tokens (
+    token_hash TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL REFERENCES auth_users(id) ON DELETE CASCADE,
+    expires_at TEXT NOT NULL,
+    used INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS auth_oidc_state (
+    state TEXT PRIMARY KEY,
+    nonce TEXT NOT NULL,
+    pkce_verifier TEXT NOT NULL,
     created_at TEXT NOT NULL DEFAULT (datetime('now'))
 );
+
+CREATE TABLE IF NOT EXISTS auth_oidc_identities (
+    issuer TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    user_id TEXT NOT NULL REFERENCES auth_users(id) ON DELETE CASCADE,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    PRIMARY KEY (issuer, subject)
+);
 "#;
 
+/// Columns added to `auth_users` after the first release, for databases
+/// created before them.
+const USER_COLUMNS: &[(&str, &str)] = &[
+    ("failed_login_count", "INTEGER NOT NULL DEFAULT 0"),
+    ("locked_until", "TEXT"),
+    ("last_failed_at", "TEXT"),
+    ("user_storage_quota_bytes", "INTEGER"),
+];
+
+/// Columns added to `auth_api_tokens` after the first release.
+const TOKEN_COLUMNS: &[(&str, &str)] = &[("allowed_ips", "TEXT"), ("namespace_prefix", "TEXT")];
+
+/// Columns added to `auth_audit_log` after the first release.
+const AUDIT_COLUMNS: &[(&str, &str)] = &[
+    ("correlation_id", "TEXT NOT NULL DEFAULT ''"),
+    ("impersonated_by", "TEXT"),
+];
+
+/// Lock still in force for `user_id`, with the seconds left.
+fn active_lock(conn: &Connection, user_id: &str) -> Result<Option<LockoutInfo>, AuthError> {
+    let row = conn.query_row(
+        "SELECT locked_until, CAST(strftime('%s', locked_until) - strftime('%s', 'now') AS INTEGER)
+         FROM auth_users WHERE id = ?1 AND locked_until > datetime('now')",
+        [user_id],
+        |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)),
+    );
+    match row {
+        Ok((locked_until, secs)) => Ok(Some(LockoutInfo {
+            locked_until,
+            retry_after_seconds: secs.max(1) as u64,
+        })),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn impersonation_from_row(row: &rusqlite::Row) -> rusqlite::Result<Impersonation> {
+    Ok(Impersonation {
+        id: row.get(0)?,
+        admin_user_id: row.get(1)?,
+        target_user_id: row.get(2)?,
+        created_at: row.get(3)?,
+        expires_at: row.get(4)?,
+    })
+}
+
 #[async_trait]
 impl AuthStore for SqliteAuthStore {
     async fn migrate(&self) -> Result<(), AuthError> {
@@ -99,6 +277,22 @@ impl AuthStore for SqliteAuthStore {
             .lock()
             .map_err(|e| AuthError::Database(format!("Lock poisoned: {e}")))?;
         conn.execute_batch(MIGRATE_SQL)?;
+
+        for (table, columns) in [
+            ("auth_users", USER_COLUMNS),
+            ("auth_api_tokens", TOKEN_COLUMNS),
+            ("auth_audit_log", AUDIT_COLUMNS),
+        ] {
+            let existing: Vec<String> = conn
+                .prepare(&format!("SELECT name FROM pragma_table_info('{table}')"))?
+                .query_map([], |row| row.get(0))?
+                .collect::<Result<_, _>>()?;
+            for (column, ddl) in columns {
+                if !existing.iter().any(|c| c == column) {
+                    conn.execute_batch(&format!("ALTER TABLE {table} ADD COLUMN {column} {ddl}"))?;
+                }
+            }
+        }
         Ok(())
     }
 
@@ -111,7 +305,7 @@ impl AuthStore for SqliteAuthStore {
     async fn create_user(
         &self,
         username: &str,
-        password_hash: &str,
+        password: &NewPassword,
         email: Option<&str>,
     ) -> Result<User, AuthError> {
         let id = {
@@ -122,7 +316,7 @@ impl AuthStore for SqliteAuthStore {
             let id = uuid::Uuid::now_v7().to_string();
             conn.execute(
                 "INSERT INTO auth_users (id, username, email, password_hash) VALUES (?1, ?2, ?3, ?4)",
-                rusqlite::params![id, username, email, password_hash],
+                rusqlite::params![id, username, email, password.hash()],
             )
             .map_err(|e| {
                 if let rusqlite::Error::SqliteFailure(ref err, _) = e
@@ -187,6 +381,31 @@ impl AuthStore for SqliteAuthStore {
         })
     }
 
+    async fn get_user_by_email(&self, email: &str) -> Result<User, AuthError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| AuthError::Database(format!("Lock poisoned: {e}")))?;
+        conn.query_row(
+            "SELECT id, username, email, is_active, created_at, updated_at FROM auth_users WHERE email = ?1",
+            [email],
+            |row| {
+                Ok(User {
+                    id: row.get(0)?,
+                    username: row.get(1)?,
+                    email: row.get(2)?,
+                    is_active: row.get::<_, i32>(3)? != 0,
+                    created_at: row.get(4)?,
+                    updated_at: row.get(5)?,
+                })
+            },
+        )
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => AuthError::NotFound("user not found".into()),
+            _ => AuthError::Database(e.to_string()),
+        })
+    }
+
     async fn list_users(&self) -> Result<Vec<User>, AuthError> {
         let conn = self
             .conn
@@ -232,14 +451,14 @@ impl AuthStore for SqliteAuthStore {
         self.get_user_by_id(id).await
     }
 
-    async fn update_password(&self, id: &str, password_hash: &str) -> Result<(), AuthError> {
+    async fn update_password(&self, id: &str, password: &NewPassword) -> Result<(), AuthError> {
         let conn = self
             .conn
             .lock()
             .map_err(|e| AuthError::Database(format!("Lock poisoned: {e}")))?;
         let changed = conn.execute(
             "UPDATE auth_users SET password_hash = ?1, updated_at = datetime('now') WHERE id = ?2",
-            rusqlite::params![password_hash, id],
+            rusqlite::params![password.hash(), id],
         )?;
         if changed == 0 {
             return Err(AuthError::NotFound("user not found".into()));
@@ -284,6 +503,200 @@ impl AuthStore for SqliteAuthStore {
         Ok(count)
     }
 
+    // --- Login lockout ---
+
+    async fn lockout_status(&self, user_id: &str) -> Result<Option<LockoutInfo>, AuthError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| AuthError::Database(format!("Lock poisoned: {e}")))?;
+        active_lock(&conn, user_id)
+    }
+
+    async fn record_failed_login(&self, user_id: &str) -> Result<Option<LockoutInfo>, AuthError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| AuthError::Database(format!("Lock poisoned: {e}")))?;
+        let changed = conn.execute(
+            "UPDATE auth_users SET failed_login_count = failed_login_count + 1,
+                last_failed_at = datetime('now') WHERE id = ?1",
+            [user_id],
+        )?;
+        if changed == 0 {
+            return Err(AuthError::NotFound("user not found".into()));
+        }
+        if self.lockout.threshold == 0 {
+            return Ok(None);
+        }
+        conn.execute(
+            "UPDATE auth_users SET failed_login_count = 0,
+                locked_until = datetime('now', '+' || ?1 || ' seconds')
+             WHERE id = ?2 AND failed_login_count >= ?3",
+            rusqlite::params![
+                self.lockout.duration_seconds as i64,
+                user_id,
+                self.lockout.threshold
+            ],
+        )?;
+        active_lock(&conn, user_id)
+    }
+
+    async fn clear_failed_logins(&self, user_id: &str) -> Result<(), AuthError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| AuthError::Database(format!("Lock poisoned: {e}")))?;
+        let changed = conn.execute(
+            "UPDATE auth_users SET failed_login_count = 0, locked_until = NULL WHERE id = ?1",
+            [user_id],
+        )?;
+        if changed == 0 {
+            return Err(AuthError::NotFound("user not found".into()));
+        }
+        Ok(())
+    }
+
+    // --- Password reset ---
+
+    async fn create_reset_token(
+        &self,
+        user_id: &str,
+        ttl_minutes: u32,
+    ) -> Result<String, AuthError> {
+        let raw_token = generate_reset_token();
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| AuthError::Database(format!("Lock poisoned: {e}")))?;
+        conn.execute(
+            "INSERT INTO auth_password_reset_tokens (token_hash, user_id, expires_at)
+             VALUES (?1, ?2, datetime('now', '+' || ?3 || ' minutes'))",
+            rusqlite::params![hash_token(&raw_token), user_id, ttl_minutes],
+        )?;
+        Ok(raw_token)
+    }
+
+    async fn use_reset_token(
+        &self,
+        raw_token: &str,
+        new_password: &NewPassword,
+    ) -> Result<(), AuthError> {
+        let user_id = {
+            let conn = self
+                .conn
+                .lock()
+                .map_err(|e| AuthError::Database(format!("Lock poisoned: {e}")))?;
+            // Claiming the token in one statement keeps two concurrent
+            // resets from both using it
+            let user_id: String = conn
+                .query_row(
+                    "UPDATE auth_password_reset_tokens SET used = 1
+                     WHERE token_hash = ?1 AND used = 0 AND expires_at > datetime('now')
+                     RETURNING user_id",
+                    [hash_token(raw_token)],
+                    |row| row.get(0),
+                )
+                .map_err(|e| match e {
+                    rusqlite::Error::QueryReturnedNoRows => {
+                        AuthError::InvalidInput("invalid or expired reset token".into())
+                    }
+                    _ => AuthError::Database(e.to_string()),
+                })?;
+            // Other links mailed to the user die with this one
+            conn.execute(
+                "UPDATE auth_password_reset_tokens SET used = 1 WHERE user_id = ?1",
+                [&user_id],
+            )?;
+            user_id
+        };
+        self.update_password(&user_id, new_password).await
+    }
+
+    // --- Storage usage ---
+
+    async fn update_user_usage(
+        &self,
+        user_id: &str,
+        namespace: &str,
+        delta_bytes: i64,
+        delta_objects: i32,
+    ) -> Result<(), AuthError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| AuthError::Database(format!("Lock poisoned: {e}")))?;
+        conn.execute(
+            "INSERT INTO user_storage_usage (user_id, namespace, bytes_stored, object_count)
+             VALUES (?1, ?2, MAX(0, ?3), MAX(0, ?4))
+             ON CONFLICT(user_id, namespace) DO UPDATE SET
+                 bytes_stored = MAX(0, bytes_stored + ?3),
+                 object_count = MAX(0, object_count + ?4),
+                 last_updated = datetime('now')",
+            rusqlite::params![user_id, namespace, delta_bytes, delta_objects],
+        )?;
+        Ok(())
+    }
+
+    async fn get_user_usage(&self, user_id: &str) -> Result<Vec<UserStorageUsage>, AuthError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| AuthError::Database(format!("Lock poisoned: {e}")))?;
+        let mut stmt = conn.prepare(
+            "SELECT namespace, bytes_stored, object_count, last_updated
+             FROM user_storage_usage WHERE user_id = ?1 ORDER BY namespace",
+        )?;
+        let usage = stmt
+            .query_map([user_id], |row| {
+                Ok(UserStorageUsage {
+                    namespace: row.get(0)?,
+                    bytes_stored: row.get::<_, i64>(1)? as u64,
+                    object_count: row.get::<_, i64>(2)? as u64,
+                    last_updated: row.get(3)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(usage)
+    }
+
+    async fn get_user_quota(&self, user_id: &str) -> Result<Option<u64>, AuthError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| AuthError::Database(format!("Lock poisoned: {e}")))?;
+        conn.query_row(
+            "SELECT user_storage_quota_bytes FROM auth_users WHERE id = ?1",
+            [user_id],
+            |row| row.get::<_, Option<i64>>(0),
+        )
+        .map(|quota| quota.map(|q| q as u64))
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => AuthError::NotFound("user not found".into()),
+            other => AuthError::Database(other.to_string()),
+        })
+    }
+
+    async fn set_user_quota(
+        &self,
+        user_id: &str,
+        quota_bytes: Option<u64>,
+    ) -> Result<(), AuthError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| AuthError::Database(format!("Lock poisoned: {e}")))?;
+        let changed = conn.execute(
+            "UPDATE auth_users SET user_storage_quota_bytes = ?1, updated_at = datetime('now')
+             WHERE id = ?2",
+            rusqlite::params![quota_bytes.map(|q| q as i64), user_id],
+        )?;
+        if changed == 0 {
+            return Err(AuthError::NotFound("user not found".into()));
+        }
+        Ok(())
+    }
+
     // --- Groups ---
 
     async fn create_group(
@@ -489,6 +902,93 @@ impl AuthStore for SqliteAuthStore {
         Ok(perms)
     }
 
+    // --- Group-Group ---
+
+    async fn add_group_member_group(
+        &self,
+        parent_group_id: &str,
+        child_group_id: &str,
+    ) -> Result<(), AuthError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| AuthError::Database(format!("Lock poisoned: {e}")))?;
+        let found: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM auth_groups WHERE id IN (?1, ?2)",
+            rusqlite::params![parent_group_id, child_group_id],
+            |row| row.get(0),
+        )?;
+        if found < 2 && parent_group_id != child_group_id {
+            return Err(AuthError::NotFound("group not found".into()));
+        }
+        // The parent must not already be the child or one of its members
+        let cycle: bool = conn.query_row(
+            "WITH RECURSIVE descendants(id) AS (
+                 SELECT ?1
+                 UNION
+                 SELECT gm.child_group_id
+                 FROM auth_group_members gm
+                 JOIN descendants d ON gm.parent_group_id = d.id
+             )
+             SELECT EXISTS (SELECT 1 FROM descendants WHERE id = ?2)",
+            rusqlite::params![child_group_id, parent_group_id],
+            |row| row.get(0),
+        )?;
+        if cycle {
+            return Err(AuthError::InvalidInput(
+                "a group cannot be added as its own ancestor".into(),
+            ));
+        }
+        conn.execute(
+            "INSERT OR IGNORE INTO auth_group_members (parent_group_id, child_group_id)
+             VALUES (?1, ?2)",
+            rusqlite::params![parent_group_id, child_group_id],
+        )?;
+        Ok(())
+    }
+
+    async fn remove_group_member_group(
+        &self,
+        parent_group_id: &str,
+        child_group_id: &str,
+    ) -> Result<(), AuthError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| AuthError::Database(format!("Lock poisoned: {e}")))?;
+        conn.execute(
+            "DELETE FROM auth_group_members WHERE parent_group_id = ?1 AND child_group_id = ?2",
+            rusqlite::params![parent_group_id, child_group_id],
+        )?;
+        Ok(())
+    }
+
+    async fn list_group_member_groups(&self, group_id: &str) -> Result<Vec<Group>, AuthError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| AuthError::Database(format!("Lock poisoned: {e}")))?;
+        let mut stmt = conn.prepare(
+            "SELECT g.id, g.name, g.description, g.is_system, g.created_at
+             FROM auth_groups g
+             JOIN auth_group_members gm ON gm.child_group_id = g.id
+             WHERE gm.parent_group_id = ?1
+             ORDER BY g.name",
+        )?;
+        let groups = stmt
+            .query_map([group_id], |row| {
+                Ok(Group {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    description: row.get(2)?,
+                    is_system: row.get::<_, i32>(3)? != 0,
+                    created_at: row.get(4)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(groups)
+    }
+
     // --- User-Group ---
 
     async fn add_user_group(&self, user_id: &str, group_id: &str) -> Result<(), AuthError> {
@@ -546,12 +1046,19 @@ impl AuthStore for SqliteAuthStore {
             .conn
             .lock()
             .map_err(|e| AuthError::Database(format!("Lock poisoned: {e}")))?;
+        // Expand the user's groups through nested groups before collecting
         let mut stmt = conn.prepare(
-            "SELECT DISTINCT p.action
+            "WITH RECURSIVE member_groups(group_id) AS (
+                 SELECT group_id FROM auth_user_groups WHERE user_id = ?1
+                 UNION
+                 SELECT gm.child_group_id
+                 FROM auth_group_members gm
+                 JOIN member_groups mg ON gm.parent_group_id = mg.group_id
+             )
+             SELECT DISTINCT p.action
              FROM auth_permissions p
              JOIN auth_group_permissions gp ON gp.permission_id = p.id
-             JOIN auth_user_groups ug ON ug.group_id = gp.group_id
-             WHERE ug.user_id = ?1
+             JOIN member_groups mg ON mg.group_id = gp.group_id
              ORDER BY p.action",
         )?;
         let perms = stmt
@@ -582,7 +1089,7 @@ impl AuthStore for SqliteAuthStore {
             rusqlite::params![id, user_id, name, token_hash, token_prefix, scopes, expires_at],
         )?;
         conn.query_row(
-            "SELECT id, user_id, name, token_prefix, scopes, expires_at, last_used_at, created_at
+            "SELECT id, user_id, name, token_prefix, scopes, expires_at, last_used_at, created_at, allowed_ips, namespace_prefix
              FROM auth_api_tokens WHERE id = ?1",
             [&id],
             |row| {
@@ -595,6 +1102,8 @@ impl AuthStore for SqliteAuthStore {
                     expires_at: row.get(5)?,
                     last_used_at: row.get(6)?,
                     created_at: row.get(7)?,
+                    allowed_ips: row.get(8)?,
+                    namespace_prefix: row.get(9)?,
                 })
             },
         )
@@ -608,7 +1117,8 @@ impl AuthStore for SqliteAuthStore {
             .map_err(|e| AuthError::Database(format!("Lock poisoned: {e}")))?;
         let result = conn.query_row(
             "SELECT t.id, t.user_id, t.name, t.token_prefix, t.scopes, t.expires_at, t.last_used_at, t.created_at,
-                    u.id, u.username, u.email, u.is_active, u.created_at, u.updated_at
+                    t.allowed_ips, t.namespace_prefix, u.id, u.username, u.email, u.is_active, u.created_at,
+                    u.updated_at
              FROM auth_api_tokens t
              JOIN auth_users u ON u.id = t.user_id
              WHERE t.token_hash = ?1",
@@ -624,14 +1134,16 @@ impl AuthStore for SqliteAuthStore {
                         expires_at: row.get(5)?,
                         last_used_at: row.get(6)?,
                         created_at: row.get(7)?,
+                        allowed_ips: row.get(8)?,
+                        namespace_prefix: row.get(9)?,
                     },
                     User {
-                        id: row.get(8)?,
-                        username: row.get(9)?,
-                        email: row.get(10)?,
-                        is_active: row.get::<_, i32>(11)? != 0,
-                        created_at: row.get(12)?,
-                        updated_at: row.get(13)?,
+                        id: row.get(10)?,
+                        username: row.get(11)?,
+                        email: row.get(12)?,
+                        is_active: row.get::<_, i32>(13)? != 0,
+                        created_at: row.get(14)?,
+                        updated_at: row.get(15)?,
                     },
                 ))
             },
@@ -663,7 +1175,7 @@ impl AuthStore for SqliteAuthStore {
             .lock()
             .map_err(|e| AuthError::Database(format!("Lock poisoned: {e}")))?;
         let mut stmt = conn.prepare(
-            "SELECT id, user_id, name, token_prefix, scopes, expires_at, last_used_at, created_at
+            "SELECT id, user_id, name, token_prefix, scopes, expires_at, last_used_at, created_at, allowed_ips, namespace_prefix
              FROM auth_api_tokens WHERE user_id = ?1 ORDER BY created_at DESC",
         )?;
         let tokens = stmt
@@ -677,36 +1189,176 @@ impl AuthStore for SqliteAuthStore {
                     expires_at: row.get(5)?,
                     last_used_at: row.get(6)?,
                     created_at: row.get(7)?,
+                    allowed_ips: row.get(8)?,
+                    namespace_prefix: row.get(9)?,
                 })
             })?
             .collect::<Result<Vec<_>, _>>()?;
         Ok(tokens)
     }
 
-    async fn revoke_token(&self, id: &str) -> Result<(), AuthError> {
+    async fn update_token_scopes(&self, id: &str, scopes: &str) -> Result<ApiToken, AuthError> {
         let conn = self
             .conn
             .lock()
             .map_err(|e| AuthError::Database(format!("Lock poisoned: {e}")))?;
-        let changed = conn.execute("DELETE FROM auth_api_tokens WHERE id = ?1", [id])?;
+        let changed = conn.execute(
+            "UPDATE auth_api_tokens SET scopes = ?2 WHERE id = ?1",
+            rusqlite::params![id, scopes],
+        )?;
         if changed == 0 {
             return Err(AuthError::NotFound("token not found".into()));
         }
-        Ok(())
-    }
-
-    async fn touch_token(&self, id: &str) -> Result<(), AuthError> {
-        let conn = self
-            .conn
-            .lock()
-            .map_err(|e| AuthError::Database(format!("Lock poisoned: {e}")))?;
-        conn.execute(
-            "UPDATE auth_api_tokens SET last_used_at = datetime('now') WHERE id = ?1",
+        conn.query_row(
+            "SELECT id, user_id, name, token_prefix, scopes, expires_at, last_used_at, created_at, allowed_ips, namespace_prefix
+             FROM auth_api_tokens WHERE id = ?1",
+            [id],
+            |row| {
+                Ok(ApiToken {
+                    id: row.get(0)?,
+                    user_id: row.get(1)?,
+                    name: row.get(2)?,
+                    token_prefix: row.get(3)?,
+                    scopes: row.get(4)?,
+                    expires_at: row.get(5)?,
+                    last_used_at: row.get(6)?,
+                    created_at: row.get(7)?,
+                    allowed_ips: row.get(8)?,
+                    namespace_prefix: row.get(9)?,
+                })
+            },
+        )
+        .map_err(|e| AuthError::Database(e.to_string()))
+    }
+
+    async fn update_token_allowed_ips(
+        &self,
+        id: &str,
+        allowed_ips: Option<&str>,
+    ) -> Result<ApiToken, AuthError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| AuthError::Database(format!("Lock poisoned: {e}")))?;
+        let changed = conn.execute(
+            "UPDATE auth_api_tokens SET allowed_ips = ?2 WHERE id = ?1",
+            rusqlite::params![id, allowed_ips],
+        )?;
+        if changed == 0 {
+            return Err(AuthError::NotFound("token not found".into()));
+        }
+        conn.query_row(
+            "SELECT id, user_id, name, token_prefix, scopes, expires_at, last_used_at, created_at, allowed_ips, namespace_prefix
+             FROM auth_api_tokens WHERE id = ?1",
+            [id],
+            |row| {
+                Ok(ApiToken {
+                    id: row.get(0)?,
+                    user_id: row.get(1)?,
+                    name: row.get(2)?,
+                    token_prefix: row.get(3)?,
+                    scopes: row.get(4)?,
+                    expires_at: row.get(5)?,
+                    last_used_at: row.get(6)?,
+                    created_at: row.get(7)?,
+                    allowed_ips: row.get(8)?,
+                    namespace_prefix: row.get(9)?,
+                })
+            },
+        )
+        .map_err(|e| AuthError::Database(e.to_string()))
+    }
+
+    async fn update_token_namespace_prefix(
+        &self,
+        id: &str,
+        namespace_prefix: Option<&str>,
+    ) -> Result<ApiToken, AuthError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| AuthError::Database(format!("Lock poisoned: {e}")))?;
+        let changed = conn.execute(
+            "UPDATE auth_api_tokens SET namespace_prefix = ?2 WHERE id = ?1",
+            rusqlite::params![id, namespace_prefix],
+        )?;
+        if changed == 0 {
+            return Err(AuthError::NotFound("token not found".into()));
+        }
+        conn.query_row(
+            "SELECT id, user_id, name, token_prefix, scopes, expires_at, last_used_at, created_at, allowed_ips, namespace_prefix
+             FROM auth_api_tokens WHERE id = ?1",
+            [id],
+            |row| {
+                Ok(ApiToken {
+                    id: row.get(0)?,
+                    user_id: row.get(1)?,
+                    name: row.get(2)?,
+                    token_prefix: row.get(3)?,
+                    scopes: row.get(4)?,
+                    expires_at: row.get(5)?,
+                    last_used_at: row.get(6)?,
+                    created_at: row.get(7)?,
+                    allowed_ips: row.get(8)?,
+                    namespace_prefix: row.get(9)?,
+                })
+            },
+        )
+        .map_err(|e| AuthError::Database(e.to_string()))
+    }
+
+    async fn get_token_hash(&self, id: &str) -> Result<String, AuthError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| AuthError::Database(format!("Lock poisoned: {e}")))?;
+        conn.query_row(
+            "SELECT token_hash FROM auth_api_tokens WHERE id = ?1",
+            [id],
+            |row| row.get(0),
+        )
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => AuthError::NotFound("token not found".into()),
+            _ => AuthError::Database(e.to_string()),
+        })
+    }
+
+    async fn revoke_token(&self, id: &str) -> Result<(), AuthError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| AuthError::Database(format!("Lock poisoned: {e}")))?;
+        let changed = conn.execute("DELETE FROM auth_api_tokens WHERE id = ?1", [id])?;
+        if changed == 0 {
+            return Err(AuthError::NotFound("token not found".into()));
+        }
+        Ok(())
+    }
+
+    async fn touch_token(&self, id: &str) -> Result<(), AuthError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| AuthError::Database(format!("Lock poisoned: {e}")))?;
+        conn.execute(
+            "UPDATE auth_api_tokens SET last_used_at = datetime('now') WHERE id = ?1",
             [id],
         )?;
         Ok(())
     }
 
+    async fn record_token_usage(&self, token_id: &str, bytes: u64) -> Result<(), AuthError> {
+        self.record_token_usage_at(token_id, Utc::now(), bytes)
+    }
+
+    async fn get_token_usage_history(
+        &self,
+        token_id: &str,
+        days: u32,
+    ) -> Result<Vec<TokenUsageDay>, AuthError> {
+        self.token_usage_history_until(token_id, Utc::now().date_naive(), days)
+    }
+
     // --- Permissions ---
 
     async fn list_permissions(&self) -> Result<Vec<Permission>, AuthError> {
@@ -760,6 +1412,101 @@ impl AuthStore for SqliteAuthStore {
         .map_err(|e| AuthError::Database(e.to_string()))
     }
 
+    // --- OpenID Connect ---
+
+    async fn save_oidc_state(&self, state: &str, login: &OidcLoginState) -> Result<(), AuthError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| AuthError::Database(format!("Lock poisoned: {e}")))?;
+        conn.execute(
+            "INSERT INTO auth_oidc_state (state, nonce, pkce_verifier) VALUES (?1, ?2, ?3)",
+            rusqlite::params![state, login.nonce, login.pkce_verifier],
+        )?;
+        Ok(())
+    }
+
+    async fn take_oidc_state(
+        &self,
+        state: &str,
+        max_age_seconds: u64,
+    ) -> Result<Option<OidcLoginState>, AuthError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| AuthError::Database(format!("Lock poisoned: {e}")))?;
+        let cutoff = format!("-{max_age_seconds} seconds");
+        conn.execute(
+            "DELETE FROM auth_oidc_state WHERE created_at < datetime('now', ?1)",
+            [&cutoff],
+        )?;
+        conn.query_row(
+            "DELETE FROM auth_oidc_state WHERE state = ?1 RETURNING nonce, pkce_verifier",
+            [state],
+            |row| {
+                Ok(OidcLoginState {
+                    nonce: row.get(0)?,
+                    pkce_verifier: row.get(1)?,
+                })
+            },
+        )
+        .optional()
+        .map_err(Into::into)
+    }
+
+    async fn upsert_oidc_user(
+        &self,
+        issuer: &str,
+        subject: &str,
+        username: &str,
+        email: Option<&str>,
+    ) -> Result<User, AuthError> {
+        let id = {
+            let mut conn = self
+                .conn
+                .lock()
+                .map_err(|e| AuthError::Database(format!("Lock poisoned: {e}")))?;
+            let tx = conn.transaction()?;
+            let linked: Option<String> = tx
+                .query_row(
+                    "SELECT user_id FROM auth_oidc_identities WHERE issuer = ?1 AND subject = ?2",
+                    [issuer, subject],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            let id = match linked {
+                Some(id) => id,
+                None => {
+                    // Never link to an existing local account by name: that
+                    // would let whoever controls the IdP claim take it over
+                    let id = uuid::Uuid::now_v7().to_string();
+                    tx.execute(
+                        "INSERT INTO auth_users (id, username, email, password_hash) VALUES (?1, ?2, ?3, ?4)",
+                        rusqlite::params![id, username, email, OIDC_PASSWORD_HASH],
+                    )
+                    .map_err(|e| {
+                        if let rusqlite::Error::SqliteFailure(ref err, _) = e
+                            && err.extended_code == 2067
+                        {
+                            return AuthError::Duplicate(format!(
+                                "user '{username}' already exists"
+                            ));
+                        }
+                        AuthError::Database(e.to_string())
+                    })?;
+                    tx.execute(
+                        "INSERT INTO auth_oidc_identities (issuer, subject, user_id) VALUES (?1, ?2, ?3)",
+                        rusqlite::params![issuer, subject, id],
+                    )?;
+                    id
+                }
+            };
+            tx.commit()?;
+            id
+        };
+        self.get_user_by_id(&id).await
+    }
+
     // --- Audit ---
 
     async fn log_audit(
@@ -768,14 +1515,45 @@ impl AuthStore for SqliteAuthStore {
         action: &str,
         target: Option<&str>,
         ip_addr: Option<&str>,
+        correlation_id: &str,
+    ) -> Result<(), AuthError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| AuthError::Database(format!("Lock poisoned: {e}")))?;
+        conn.execute(
+            "INSERT INTO auth_audit_log (user_id, action, target, ip_addr, correlation_id)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![user_id, action, target, ip_addr, correlation_id],
+        )?;
+        Ok(())
+    }
+
+    async fn log_impersonated_audit(
+        &self,
+        user_id: &str,
+        impersonated_by: &str,
+        action: &str,
+        target: Option<&str>,
+        ip_addr: Option<&str>,
+        correlation_id: &str,
     ) -> Result<(), AuthError> {
         let conn = self
             .conn
             .lock()
             .map_err(|e| AuthError::Database(format!("Lock poisoned: {e}")))?;
         conn.execute(
-            "INSERT INTO auth_audit_log (user_id, action, target, ip_addr) VALUES (?1, ?2, ?3, ?4)",
-            rusqlite::params![user_id, action, target, ip_addr],
+            "INSERT INTO auth_audit_log
+                 (user_id, action, target, ip_addr, correlation_id, impersonated_by)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![
+                user_id,
+                action,
+                target,
+                ip_addr,
+                correlation_id,
+                impersonated_by
+            ],
         )?;
         Ok(())
     }
@@ -786,8 +1564,8 @@ impl AuthStore for SqliteAuthStore {
             .lock()
             .map_err(|e| AuthError::Database(format!("Lock poisoned: {e}")))?;
         let mut stmt = conn.prepare(
-            "SELECT id, user_id, action, target, ip_addr, created_at
-             FROM auth_audit_log ORDER BY created_at DESC LIMIT ?1 OFFSET ?2",
+            "SELECT id, user_id, action, target, ip_addr, correlation_id, impersonated_by, created_at
+             FROM auth_audit_log ORDER BY created_at DESC, id DESC LIMIT ?1 OFFSET ?2",
         )?;
         let entries = stmt
             .query_map(rusqlite::params![limit, offset], |row| {
@@ -797,10 +1575,677 @@ impl AuthStore for SqliteAuthStore {
                     action: row.get(2)?,
                     target: row.get(3)?,
                     ip_addr: row.get(4)?,
-                    created_at: row.get(5)?,
+                    correlation_id: row.get(5)?,
+                    impersonated_by: row.get(6)?,
+                    created_at: row.get(7)?,
                 })
             })?
             .collect::<Result<Vec<_>, _>>()?;
         Ok(entries)
     }
+
+    async fn purge_old_audit_entries(&self, older_than_days: u32) -> Result<u64, AuthError> {
+        let cutoff = format!("-{older_than_days} days");
+        let mut deleted = 0;
+        loop {
+            // The connection is released between batches
+            let conn = self
+                .conn
+                .lock()
+                .map_err(|e| AuthError::Database(format!("Lock poisoned: {e}")))?;
+            let n = conn.execute(
+                "DELETE FROM auth_audit_log WHERE id IN (
+                     SELECT id FROM auth_audit_log
+                     WHERE created_at < datetime('now', ?1) LIMIT ?2
+                 )",
+                rusqlite::params![cutoff, AUDIT_PURGE_BATCH],
+            )?;
+            deleted += n as u64;
+            if n < AUDIT_PURGE_BATCH as usize {
+                return Ok(deleted);
+            }
+        }
+    }
+
+    async fn audit_stats(&self) -> Result<AuditStats, AuthError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| AuthError::Database(format!("Lock poisoned: {e}")))?;
+        // Stored bytes of each row: the integer id and the text columns
+        let stats = conn.query_row(
+            "SELECT COUNT(*), MIN(created_at),
+                    COALESCE(SUM(8 + LENGTH(action) + LENGTH(correlation_id)
+                        + LENGTH(created_at) + IFNULL(LENGTH(user_id), 0)
+                        + IFNULL(LENGTH(target), 0) + IFNULL(LENGTH(ip_addr), 0)
+                        + IFNULL(LENGTH(impersonated_by), 0)), 0)
+             FROM auth_audit_log",
+            [],
+            |row| {
+                Ok(AuditStats {
+                    total_entries: row.get::<_, i64>(0)? as u64,
+                    oldest_entry_date: row.get(1)?,
+                    estimated_size_bytes: row.get::<_, i64>(2)? as u64,
+                })
+            },
+        )?;
+        Ok(stats)
+    }
+
+    // --- Impersonation ---
+
+    async fn create_impersonation(
+        &self,
+        admin_user_id: &str,
+        target_user_id: &str,
+        duration_seconds: u32,
+    ) -> Result<Impersonation, AuthError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| AuthError::Database(format!("Lock poisoned: {e}")))?;
+        let id = uuid::Uuid::now_v7().to_string();
+        conn.execute(
+            "INSERT INTO auth_impersonations (id, admin_user_id, target_user_id, expires_at)
+             VALUES (?1, ?2, ?3, datetime('now', '+' || ?4 || ' seconds'))",
+            rusqlite::params![id, admin_user_id, target_user_id, duration_seconds],
+        )?;
+        conn.query_row(
+            "SELECT id, admin_user_id, target_user_id, created_at, expires_at
+             FROM auth_impersonations WHERE id = ?1",
+            [&id],
+            impersonation_from_row,
+        )
+        .map_err(Into::into)
+    }
+
+    async fn list_active_impersonations(&self) -> Result<Vec<Impersonation>, AuthError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| AuthError::Database(format!("Lock poisoned: {e}")))?;
+        let mut stmt = conn.prepare(
+            "SELECT id, admin_user_id, target_user_id, created_at, expires_at
+             FROM auth_impersonations WHERE expires_at > datetime('now')
+             ORDER BY created_at DESC, id DESC",
+        )?;
+        let impersonations = stmt
+            .query_map([], impersonation_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(impersonations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn password(raw: &str) -> NewPassword {
+        NewPassword::new(raw, &PasswordPolicy::default()).unwrap()
+    }
+
+    async fn store_with_user(threshold: u32) -> (SqliteAuthStore, String) {
+        let store = SqliteAuthStore::open_in_memory()
+            .unwrap()
+            .with_lockout(LockoutPolicy {
+                threshold,
+                duration_seconds: 900,
+            });
+        store.migrate().await.unwrap();
+        let user = store
+            .create_user("alice", &NewPassword::disabled(), None)
+            .await
+            .unwrap();
+        (store, user.id)
+    }
+
+    #[tokio::test]
+    async fn locks_after_threshold() {
+        let (store, id) = store_with_user(3).await;
+
+        assert!(store.record_failed_login(&id).await.unwrap().is_none());
+        assert!(store.record_failed_login(&id).await.unwrap().is_none());
+        assert!(store.lockout_status(&id).await.unwrap().is_none());
+
+        let lock = store.record_failed_login(&id).await.unwrap().unwrap();
+        assert!(lock.retry_after_seconds > 890 && lock.retry_after_seconds <= 900);
+        assert!(store.lockout_status(&id).await.unwrap().is_some());
+
+        store.clear_failed_logins(&id).await.unwrap();
+        assert!(store.lockout_status(&id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn reset_token_sets_password_once() {
+        let (store, id) = store_with_user(0).await;
+        let token = store.create_reset_token(&id, 30).await.unwrap();
+        assert_ne!(token, hash_token(&token));
+
+        let new_password = password("Correct-Horse-9");
+        store.use_reset_token(&token, &new_password).await.unwrap();
+        assert_eq!(
+            store.get_password_hash(&id).await.unwrap(),
+            new_password.hash()
+        );
+
+        let reused = store
+            .use_reset_token(&token, &password("Other-Horse-9"))
+            .await;
+        assert!(matches!(reused, Err(AuthError::InvalidInput(_))));
+        assert_eq!(
+            store.get_password_hash(&id).await.unwrap(),
+            new_password.hash()
+        );
+    }
+
+    #[tokio::test]
+    async fn expired_or_unknown_reset_token_is_refused() {
+        let (store, id) = store_with_user(0).await;
+        // Expires the moment it is issued
+        let expired = store.create_reset_token(&id, 0).await.unwrap();
+        let new_password = password("Correct-Horse-9");
+        for token in [expired.as_str(), "not-a-token"] {
+            let result = store.use_reset_token(token, &new_password).await;
+            assert!(matches!(result, Err(AuthError::InvalidInput(_))));
+        }
+        assert_eq!(
+            store.get_password_hash(&id).await.unwrap(),
+            NewPassword::disabled().hash()
+        );
+    }
+
+    #[tokio::test]
+    async fn using_a_reset_token_voids_the_others() {
+        let (store, id) = store_with_user(0).await;
+        let first = store.create_reset_token(&id, 30).await.unwrap();
+        let second = store.create_reset_token(&id, 30).await.unwrap();
+        let new_password = password("Correct-Horse-9");
+        store.use_reset_token(&second, &new_password).await.unwrap();
+        assert!(store.use_reset_token(&first, &new_password).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn successful_login_resets_counter() {
+        let (store, id) = store_with_user(2).await;
+
+        store.record_failed_login(&id).await.unwrap();
+        store.clear_failed_logins(&id).await.unwrap();
+        assert!(store.record_failed_login(&id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn zero_threshold_never_locks() {
+        let (store, id) = store_with_user(0).await;
+        for _ in 0..10 {
+            assert!(store.record_failed_login(&id).await.unwrap().is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn migrate_adds_lockout_columns_to_old_schema() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE auth_users (
+                id TEXT PRIMARY KEY,
+                username TEXT UNIQUE NOT NULL,
+                email TEXT UNIQUE,
+                password_hash TEXT NOT NULL,
+                is_active INTEGER NOT NULL DEFAULT 1,
+                created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+            );",
+        )
+        .unwrap();
+        let store = SqliteAuthStore::new(conn);
+        store.migrate().await.unwrap();
+        // Idempotent
+        store.migrate().await.unwrap();
+
+        let user = store
+            .create_user("bob", &NewPassword::disabled(), None)
+            .await
+            .unwrap();
+        assert!(store.record_failed_login(&user.id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn migrate_adds_correlation_id_to_old_audit_log() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE auth_audit_log (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                user_id TEXT,
+                action TEXT NOT NULL,
+                target TEXT,
+                ip_addr TEXT,
+                created_at TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+            INSERT INTO auth_audit_log (action, created_at)
+                VALUES ('user.create', '2020-01-01 00:00:00');",
+        )
+        .unwrap();
+        let store = SqliteAuthStore::new(conn);
+        store.migrate().await.unwrap();
+
+        store
+            .log_audit(None, "user.login", Some("bob"), None, "req-1")
+            .await
+            .unwrap();
+        let entries = store.list_audit(10, 0).await.unwrap();
+        let ids: Vec<(&str, &str)> = entries
+            .iter()
+            .map(|e| (e.action.as_str(), e.correlation_id.as_str()))
+            .collect();
+        assert_eq!(ids, vec![("user.login", "req-1"), ("user.create", "")]);
+    }
+
+    #[tokio::test]
+    async fn impersonations_are_tracked_until_they_expire() {
+        let (store, admin) = store_with_user(0).await;
+        let bob = store
+            .create_user("bob", &NewPassword::disabled(), None)
+            .await
+            .unwrap();
+
+        let imp = store
+            .create_impersonation(&admin, &bob.id, 600)
+            .await
+            .unwrap();
+        assert_eq!(imp.admin_user_id, admin);
+        assert_eq!(imp.target_user_id, bob.id);
+        assert!(imp.expires_at > imp.created_at);
+        store
+            .conn
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT INTO auth_impersonations (id, admin_user_id, target_user_id, expires_at)
+                 VALUES ('old', ?1, ?2, '2020-01-01 00:00:00')",
+                rusqlite::params![admin, bob.id],
+            )
+            .unwrap();
+
+        let active = store.list_active_impersonations().await.unwrap();
+        let ids: Vec<&str> = active.iter().map(|i| i.id.as_str()).collect();
+        assert_eq!(ids, vec![imp.id.as_str()]);
+    }
+
+    #[tokio::test]
+    async fn impersonated_requests_record_the_admin() {
+        let (store, admin) = store_with_user(0).await;
+        store
+            .log_audit(
+                Some(&admin),
+                "user.impersonate",
+                Some("u-bob"),
+                None,
+                "req-1",
+            )
+            .await
+            .unwrap();
+        store
+            .log_impersonated_audit(
+                "u-bob",
+                &admin,
+                "api.request",
+                Some("GET /x"),
+                None,
+                "req-2",
+            )
+            .await
+            .unwrap();
+
+        let entries = store.list_audit(10, 0).await.unwrap();
+        let summary: Vec<(&str, Option<&str>, Option<&str>)> = entries
+            .iter()
+            .map(|e| {
+                (
+                    e.action.as_str(),
+                    e.user_id.as_deref(),
+                    e.impersonated_by.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("api.request", Some("u-bob"), Some(admin.as_str())),
+                ("user.impersonate", Some(admin.as_str()), None),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn purge_removes_only_old_audit_entries() {
+        let store = SqliteAuthStore::open_in_memory().unwrap();
+        store.migrate().await.unwrap();
+        // More old entries than one purge batch
+        store
+            .conn
+            .lock()
+            .unwrap()
+            .execute_batch(
+                "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 2500)
+                 INSERT INTO auth_audit_log (action, created_at)
+                     SELECT 'user.login', datetime('now', '-100 days') FROM n;
+                 INSERT INTO auth_audit_log (action, created_at)
+                     VALUES ('user.create', datetime('now', '-89 days'));",
+            )
+            .unwrap();
+        store
+            .log_audit(None, "user.delete", None, None, "req-1")
+            .await
+            .unwrap();
+
+        let stats = store.audit_stats().await.unwrap();
+        assert_eq!(stats.total_entries, 2502);
+        assert!(stats.estimated_size_bytes > 0);
+
+        assert_eq!(store.purge_old_audit_entries(90).await.unwrap(), 2500);
+        let actions: Vec<String> = store
+            .list_audit(10, 0)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|e| e.action)
+            .collect();
+        assert_eq!(actions, vec!["user.delete", "user.create"]);
+        assert_eq!(store.purge_old_audit_entries(90).await.unwrap(), 0);
+
+        let stats = store.audit_stats().await.unwrap();
+        assert_eq!(stats.total_entries, 2);
+        assert!(stats.oldest_entry_date.is_some());
+    }
+
+    #[tokio::test]
+    async fn audit_stats_of_empty_log() {
+        let store = SqliteAuthStore::open_in_memory().unwrap();
+        store.migrate().await.unwrap();
+        let stats = store.audit_stats().await.unwrap();
+        assert_eq!(stats.total_entries, 0);
+        assert_eq!(stats.oldest_entry_date, None);
+        assert_eq!(stats.estimated_size_bytes, 0);
+    }
+
+    #[tokio::test]
+    async fn usage_deltas_accumulate_per_namespace() {
+        let (store, uid) = store_with_user(0).await;
+        store
+            .update_user_usage(&uid, "photos", 100, 1)
+            .await
+            .unwrap();
+        store
+            .update_user_usage(&uid, "photos", 50, 1)
+            .await
+            .unwrap();
+        store.update_user_usage(&uid, "docs", 10, 1).await.unwrap();
+        // Overwrite with a smaller object, then delete one photo
+        store
+            .update_user_usage(&uid, "photos", -20, 0)
+            .await
+            .unwrap();
+        store
+            .update_user_usage(&uid, "photos", -30, -1)
+            .await
+            .unwrap();
+
+        let usage = store.get_user_usage(&uid).await.unwrap();
+        let summary: Vec<(&str, u64, u64)> = usage
+            .iter()
+            .map(|u| (u.namespace.as_str(), u.bytes_stored, u.object_count))
+            .collect();
+        assert_eq!(summary, vec![("docs", 10, 1), ("photos", 100, 1)]);
+
+        // Totals never go negative
+        store
+            .update_user_usage(&uid, "docs", -500, -5)
+            .await
+            .unwrap();
+        let docs = &store.get_user_usage(&uid).await.unwrap()[0];
+        assert_eq!((docs.bytes_stored, docs.object_count), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn quota_limits_remaining_bytes() {
+        let (store, uid) = store_with_user(0).await;
+        assert_eq!(store.get_user_quota(&uid).await.unwrap(), None);
+        assert_eq!(store.remaining_user_quota(&uid).await.unwrap(), None);
+
+        store.set_user_quota(&uid, Some(1000)).await.unwrap();
+        store.update_user_usage(&uid, "a", 300, 1).await.unwrap();
+        store.update_user_usage(&uid, "b", 200, 1).await.unwrap();
+        assert_eq!(store.remaining_user_quota(&uid).await.unwrap(), Some(500));
+
+        store.update_user_usage(&uid, "a", 900, 1).await.unwrap();
+        assert_eq!(store.remaining_user_quota(&uid).await.unwrap(), Some(0));
+
+        store.set_user_quota(&uid, None).await.unwrap();
+        assert_eq!(store.remaining_user_quota(&uid).await.unwrap(), None);
+        assert!(matches!(
+            store.set_user_quota("missing", Some(1)).await,
+            Err(AuthError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn nested_groups_grant_transitive_permissions() {
+        let (store, uid) = store_with_user(0).await;
+        let a = store.create_group("a", "", false).await.unwrap();
+        let b = store.create_group("b", "", false).await.unwrap();
+        let c = store.create_group("c", "", false).await.unwrap();
+        let read = store.create_permission("c:read", "").await.unwrap();
+        let write = store.create_permission("b:write", "").await.unwrap();
+        store.add_group_permission(&c.id, &read.id).await.unwrap();
+        store.add_group_permission(&b.id, &write.id).await.unwrap();
+
+        store.add_group_member_group(&a.id, &b.id).await.unwrap();
+        store.add_group_member_group(&b.id, &c.id).await.unwrap();
+        store.add_user_group(&uid, &a.id).await.unwrap();
+        assert_eq!(
+            store.get_user_permissions(&uid).await.unwrap(),
+            ["b:write", "c:read"]
+        );
+
+        let members = store.list_group_member_groups(&a.id).await.unwrap();
+        assert_eq!(members.len(), 1);
+        assert_eq!(members[0].name, "b");
+
+        // C cannot contain its ancestor A, nor can a group contain itself
+        assert!(matches!(
+            store.add_group_member_group(&c.id, &a.id).await,
+            Err(AuthError::InvalidInput(_))
+        ));
+        assert!(matches!(
+            store.add_group_member_group(&a.id, &a.id).await,
+            Err(AuthError::InvalidInput(_))
+        ));
+        assert!(matches!(
+            store.add_group_member_group(&a.id, "missing").await,
+            Err(AuthError::NotFound(_))
+        ));
+
+        store.remove_group_member_group(&b.id, &c.id).await.unwrap();
+        assert_eq!(store.get_user_permissions(&uid).await.unwrap(), ["b:write"]);
+        store.delete_group(&b.id).await.unwrap();
+        assert!(store.get_user_permissions(&uid).await.unwrap().is_empty());
+    }
+
+    async fn store_with_token() -> (SqliteAuthStore, String) {
+        let store = SqliteAuthStore::open_in_memory().unwrap();
+        store.migrate().await.unwrap();
+        let user = store
+            .create_user("ci", &NewPassword::disabled(), None)
+            .await
+            .unwrap();
+        let token = store
+            .create_token(&user.id, "ci", "hash", "egt_00000000", "*", None)
+            .await
+            .unwrap();
+        (store, token.id)
+    }
+
+    #[tokio::test]
+    async fn s3_access_key_is_the_token_id() {
+        let (store, tid) = store_with_token().await;
+        let (token, user, secret) = store.verify_s3_access_key(&tid).await.unwrap();
+        assert_eq!(token.id, tid);
+        assert_eq!(user.username, "ci");
+        assert_eq!(secret, "hash");
+
+        store.revoke_token(&tid).await.unwrap();
+        assert!(matches!(
+            store.verify_s3_access_key(&tid).await,
+            Err(AuthError::NotFound(_))
+        ));
+    }
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[tokio::test]
+    async fn token_usage_splits_at_midnight_utc() {
+        let (store, tid) = store_with_token().await;
+        store
+            .record_token_usage_at(&tid, utc("2025-03-01T23:59:59Z"), 100)
+            .unwrap();
+        store
+            .record_token_usage_at(&tid, utc("2025-03-01T12:00:00Z"), 50)
+            .unwrap();
+        store
+            .record_token_usage_at(&tid, utc("2025-03-02T00:00:00Z"), 7)
+            .unwrap();
+        // Local midnight elsewhere is still March 2nd in UTC
+        store
+            .record_token_usage_at(&tid, utc("2025-03-03T00:30:00+02:00"), 3)
+            .unwrap();
+
+        let today = NaiveDate::from_ymd_opt(2025, 3, 3).unwrap();
+        let history = store.token_usage_history_until(&tid, today, 4).unwrap();
+        let summary: Vec<(&str, u64, u64)> = history
+            .iter()
+            .map(|d| (d.date.as_str(), d.request_count, d.bytes_transferred))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("2025-02-28", 0, 0),
+                ("2025-03-01", 2, 150),
+                ("2025-03-02", 2, 10),
+                ("2025-03-03", 0, 0),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn token_usage_window_and_revocation() {
+        let (store, tid) = store_with_token().await;
+        store
+            .record_token_usage_at(&tid, utc("2025-01-01T10:00:00Z"), 1)
+            .unwrap();
+        store
+            .record_token_usage_at(&tid, utc("2025-01-10T10:00:00Z"), 1)
+            .unwrap();
+        let today = NaiveDate::from_ymd_opt(2025, 1, 10).unwrap();
+        let history = store.token_usage_history_until(&tid, today, 1).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].request_count, 1);
+        assert_eq!(
+            store
+                .token_usage_history_until(&tid, today, 10)
+                .unwrap()
+                .iter()
+                .map(|d| d.request_count)
+                .sum::<u64>(),
+            2
+        );
+
+        store.record_token_usage(&tid, 5).await.unwrap();
+        let recent = store.get_token_usage_history(&tid, 1).await.unwrap();
+        assert_eq!(recent[0].request_count, 1);
+        assert_eq!(recent[0].bytes_transferred, 5);
+
+        store.revoke_token(&tid).await.unwrap();
+        assert!(
+            store
+                .token_usage_history_until(&tid, today, 10)
+                .unwrap()
+                .iter()
+                .all(|d| d.request_count == 0)
+        );
+    }
+
+    #[tokio::test]
+    async fn oidc_state_is_single_use() {
+        let (store, _) = store_with_user(0).await;
+        let login = OidcLoginState {
+            nonce: "n".into(),
+            pkce_verifier: "v".into(),
+        };
+        store.save_oidc_state("s1", &login).await.unwrap();
+
+        let taken = store.take_oidc_state("s1", 600).await.unwrap().unwrap();
+        assert_eq!(taken.nonce, "n");
+        assert_eq!(taken.pkce_verifier, "v");
+        assert!(store.take_oidc_state("s1", 600).await.unwrap().is_none());
+
+        // Expired logins are refused
+        store.save_oidc_state("s2", &login).await.unwrap();
+        {
+            let conn = store.conn.lock().unwrap();
+            conn.execute(
+                "UPDATE auth_oidc_state SET created_at = datetime('now', '-11 minutes')",
+                [],
+            )
+            .unwrap();
+        }
+        assert!(store.take_oidc_state("s2", 600).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn oidc_users_are_linked_by_subject() {
+        let (store, _) = store_with_user(0).await;
+
+        let user = store
+            .upsert_oidc_user(
+                "https://idp",
+                "sub-1",
+                "carol@example.com",
+                Some("carol@example.com"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(user.username, "carol@example.com");
+        assert_eq!(
+            store.get_password_hash(&user.id).await.unwrap(),
+            OIDC_PASSWORD_HASH
+        );
+
+        // Same subject again: same user, even if the claims changed
+        let again = store
+            .upsert_oidc_user("https://idp", "sub-1", "renamed", None)
+            .await
+            .unwrap();
+        assert_eq!(again.id, user.id);
+
+        // A new subject never takes over a local account with that name
+        let err = store
+            .upsert_oidc_user("https://idp", "sub-2", "alice", None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AuthError::Duplicate(_)));
+        let err = store
+            .upsert_oidc_user("https://other", "sub-1", "carol@example.com", None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AuthError::Duplicate(_)));
+
+        // Deleting the user removes the link
+        store.delete_user(&user.id).await.unwrap();
+        let recreated = store
+            .upsert_oidc_user("https://idp", "sub-1", "carol@example.com", None)
+            .await
+            .unwrap();
+        assert_ne!(recreated.id, user.id);
+    }
 }
@@ -8,6 +8,13 @@ pub fn generate_api_token() -> String {
     format!("egt_{}", hex::encode(bytes))
 }
 
+/// Raw token for a password reset link: 32 random bytes, hex-encoded.
+pub fn generate_reset_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill(&mut bytes);
+    hex::encode(bytes)
+}
+
 pub fn hash_token(raw: &str) -> String {
     let digest = Sha256::digest(raw.as_bytes());
     hex::encode(digest)
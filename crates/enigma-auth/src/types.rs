@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct User {
@@ -37,6 +38,29 @@ pub struct ApiToken {
     pub expires_at: Option<String>,
     pub last_used_at: Option<String>,
     pub created_at: String,
+    /// Comma-separated CIDRs the token may be used from; `None` for anywhere.
+    pub allowed_ips: Option<String>,
+    /// Only namespaces whose names start with this are visible to the token;
+    /// `None` for all of them.
+    pub namespace_prefix: Option<String>,
+}
+
+/// Data a user has stored in one namespace.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserStorageUsage {
+    pub namespace: String,
+    pub bytes_stored: u64,
+    pub object_count: u64,
+    pub last_updated: String,
+}
+
+/// Requests made with an API token on one UTC day.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsageDay {
+    /// `YYYY-MM-DD`, UTC.
+    pub date: String,
+    pub request_count: u64,
+    pub bytes_transferred: u64,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -52,51 +76,160 @@ pub struct AuditEntry {
     pub action: String,
     pub target: Option<String>,
     pub ip_addr: Option<String>,
+    /// `X-Request-ID` of the HTTP request that made the change; empty for
+    /// entries written before it was recorded.
+    pub correlation_id: String,
+    /// Admin who made the request with an impersonation token for `user_id`.
+    pub impersonated_by: Option<String>,
+    pub created_at: String,
+}
+
+/// Size of the audit log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditStats {
+    pub total_entries: u64,
+    /// `created_at` of the oldest entry; `None` when the log is empty.
+    pub oldest_entry_date: Option<String>,
+    /// Space taken by the entries, as estimated by the database.
+    pub estimated_size_bytes: u64,
+}
+
+/// A short-lived token an admin was issued to act as another user.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Impersonation {
+    /// Also the `jti` claim of the issued token.
+    pub id: String,
+    pub admin_user_id: String,
+    pub target_user_id: String,
     pub created_at: String,
+    pub expires_at: String,
+}
+
+/// Lock an account for `duration_seconds` after `threshold` consecutive
+/// failed logins. A threshold of 0 disables lockout.
+#[derive(Debug, Clone, Copy)]
+pub struct LockoutPolicy {
+    pub threshold: u32,
+    pub duration_seconds: u64,
+}
+
+impl Default for LockoutPolicy {
+    fn default() -> Self {
+        Self {
+            threshold: 5,
+            duration_seconds: 900,
+        }
+    }
+}
+
+/// An account that is currently locked out.
+#[derive(Debug, Clone, Serialize)]
+pub struct LockoutInfo {
+    pub locked_until: String,
+    /// Seconds until the lock expires (at least 1).
+    pub retry_after_seconds: u64,
+}
+
+/// Values saved when an OpenID Connect login starts, looked up again by
+/// the `state` parameter when the identity provider redirects back.
+#[derive(Debug, Clone)]
+pub struct OidcLoginState {
+    pub nonce: String,
+    pub pkce_verifier: String,
 }
 
-#[derive(Debug, Deserialize)]
+/// Rules a new password must satisfy. Lengths count characters, not bytes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub max_length: usize,
+    pub require_uppercase: bool,
+    pub require_lowercase: bool,
+    pub require_digit: bool,
+    /// Any character that is not a letter or digit.
+    pub require_special: bool,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: 12,
+            max_length: 128,
+            require_uppercase: true,
+            require_lowercase: true,
+            require_digit: true,
+            require_special: true,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct CreateUserRequest {
     pub username: String,
     pub password: String,
     pub email: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct UpdateUserRequest {
     pub email: Option<String>,
     pub is_active: Option<bool>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct UpdatePasswordRequest {
     pub password: String,
 }
 
-#[derive(Debug, Deserialize)]
+/// Body of `PUT /api/admin/users/{id}/quota`; `None` removes the quota.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateUserQuotaRequest {
+    pub quota_bytes: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct CreateGroupRequest {
     pub name: String,
     pub description: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct UpdateGroupRequest {
     pub description: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct CreateTokenRequest {
     pub name: String,
     pub scopes: Option<String>,
     pub expires_in_days: Option<u32>,
+    pub allowed_ips: Option<String>,
+    pub namespace_prefix: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateTokenScopesRequest {
+    pub scopes: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateTokenAllowedIpsRequest {
+    pub allowed_ips: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct GroupPermissionRequest {
     pub permission_id: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct UserGroupRequest {
     pub group_id: String,
 }
+
+/// Group to nest inside another group.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GroupMemberRequest {
+    pub group_id: String,
+}
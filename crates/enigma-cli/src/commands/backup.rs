@@ -1,42 +1,91 @@
 use anyhow::Result;
 use indicatif::{ProgressBar, ProgressStyle};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
+use std::time::Instant;
 
 use enigma_core::chunk::{CdcChunkEngine, ChunkEngine, FixedSizeChunkEngine};
 use enigma_core::config::EnigmaConfig;
-use enigma_core::crypto::encrypt_chunk;
+use enigma_core::crypto::{chunk_hmac_tag, encrypt_chunk, signing};
+use enigma_core::dedup::DedupIndex;
 use enigma_core::distributor::Distributor;
-use enigma_core::manifest::ManifestDb;
-use enigma_core::types::{ChunkStrategy, DistributionStrategy, KeyMaterial, ProviderType};
+use enigma_core::events::{BackupEvents, BackupPhase, BackupProgress};
+use enigma_core::limits::worker_semaphore;
+use enigma_core::manifest::{BulkInsertHandle, ManifestDb};
+use enigma_core::notify::{BackupNotificationEvent, spawn_backup_notification};
+use enigma_core::types::{
+    BackupStatus, ChunkStrategy, DistributionStrategy, KeyMaterial, ProviderType,
+};
+use enigma_core::webhook::{self, WebhookPayload};
 use enigma_storage::local::LocalStorageProvider;
 
+use super::exclude::ExcludeFilter;
 use super::providers::init_providers;
+use crate::output::{JsonPrinter, RunReport};
 
-pub async fn run(source: &Path, base_dir: &Path, cli_passphrase: &Option<String>) -> Result<()> {
+/// Back up `source`, skipping files matched by `exclude` or by the
+/// configured `exclude_patterns`.
+pub async fn run(
+    source: &Path,
+    base_dir: &Path,
+    cli_passphrase: &Option<String>,
+    tags: &[(String, String)],
+    exclude: &[String],
+    json: bool,
+) -> Result<()> {
+    run_with_events(
+        source,
+        base_dir,
+        cli_passphrase,
+        tags,
+        exclude,
+        json,
+        &BackupEvents::default(),
+    )
+    .await
+}
+
+/// Like [`run`], publishing progress on `events`.
+pub async fn run_with_events(
+    source: &Path,
+    base_dir: &Path,
+    cli_passphrase: &Option<String>,
+    tags: &[(String, String)],
+    exclude: &[String],
+    json: bool,
+    events: &BackupEvents,
+) -> Result<()> {
     let source = source.canonicalize()?;
-    println!("Backing up: {}", source.display());
+    if !json {
+        println!("Backing up: {}", source.display());
+    }
 
     // Load config
     let config_path = EnigmaConfig::default_path(base_dir);
     let config = EnigmaConfig::load(&config_path)?;
 
+    let mut patterns = config.enigma.exclude_patterns.clone();
+    patterns.extend_from_slice(exclude);
+    let filter = ExcludeFilter::new(&patterns)?;
+
+    if let Some(priority) = &config.enigma.io_priority {
+        enigma_core::limits::set_io_priority(priority)?;
+    }
+
     // Open database
-    let db = ManifestDb::open(Path::new(&config.enigma.db_path))?;
+    let mut db = ManifestDb::open(Path::new(&config.enigma.db_path))?;
 
     // Get encryption key via factory
-    let passphrase = if config.enigma.key_provider == "local" {
+    let passphrase = if config.enigma.needs_passphrase() {
         Some(crate::get_passphrase(cli_passphrase)?)
     } else {
         None
     };
     let key_provider = enigma_keys::factory::create_key_provider(
         &config.enigma.key_provider,
-        passphrase.as_deref().map(|s| s.as_bytes()),
-        &config.enigma.keyfile_path,
-        config.enigma.vault_url.as_deref(),
-        config.enigma.gcp_project_id.as_deref(),
-        config.enigma.aws_region.as_deref(),
-        config.enigma.secret_prefix.as_deref(),
+        &crate::key_provider_options(&config.enigma, passphrase.as_deref()),
     )
     .await?;
     let managed_key = key_provider.get_current_key().await?;
@@ -87,14 +136,34 @@ pub async fn run(source: &Path, base_dir: &Path, cli_passphrase: &Option<String>
     // Create backup record
     let backup_id = uuid::Uuid::now_v7().to_string();
     db.create_backup(&backup_id, source.to_str().unwrap_or(""))?;
+    for (key, value) in tags {
+        db.set_backup_tag(&backup_id, key, value)?;
+    }
     db.log(Some(&backup_id), "INFO", "Backup started")?;
+    webhook::enqueue(
+        &db,
+        &config.webhooks,
+        &WebhookPayload::new(
+            "backup.started",
+            json!({ "backup_id": backup_id, "source": source }),
+        ),
+    )?;
 
     // Walk source directory
-    let files = walk_files(&source)?;
-    println!("Found {} files", files.len());
-
-    match run_backup_inner(
-        &db,
+    let files = walk_files(&source, &filter)?;
+    if !json {
+        println!("Found {} files", files.len());
+    }
+    let bytes_total = files
+        .iter()
+        .filter_map(|f| std::fs::metadata(f).ok())
+        .map(|m| m.len())
+        .sum();
+    let mut progress = BackupProgress::new(&backup_id, files.len() as u64, bytes_total);
+
+    let started = Instant::now();
+    let outcome = run_backup_inner(
+        &mut db,
         &backup_id,
         &source,
         &files,
@@ -103,42 +172,223 @@ pub async fn run(source: &Path, base_dir: &Path, cli_passphrase: &Option<String>
         &key_material,
         &storage_providers,
         &distributor,
+        json,
+        &mut progress,
+        events,
     )
-    .await
-    {
-        Ok((total_bytes, total_chunks, dedup_chunks)) => {
-            db.complete_backup(
-                &backup_id,
-                files.len() as u64,
-                total_bytes,
-                total_chunks,
-                dedup_chunks,
-            )?;
-            db.log(Some(&backup_id), "INFO", "Backup completed")?;
+    .await;
+
+    // Mailed in the background; awaited below so the process does not exit first
+    let notification = spawn_backup_notification(
+        config.notifications.as_ref(),
+        BackupNotificationEvent {
+            backup_id: backup_id.clone(),
+            status: if outcome.is_ok() {
+                BackupStatus::Completed
+            } else {
+                BackupStatus::Failed
+            },
+            duration: started.elapsed(),
+            total_bytes: progress.bytes_done,
+            dedup_ratio: progress.event(BackupPhase::Done).dedup_ratio,
+            error: outcome.as_ref().err().map(|e| e.to_string()),
+        },
+    );
 
-            println!("\nBackup completed:");
-            println!("  ID:             {backup_id}");
-            println!("  Files:          {}", files.len());
-            println!("  Total size:     {} bytes", total_bytes);
-            println!("  Total chunks:   {total_chunks}");
-            println!("  Dedup'd chunks: {dedup_chunks}");
+    let result: Result<()> = async {
+        match outcome {
+            Ok((total_bytes, total_chunks, dedup_chunks)) => {
+                events.publish(progress.event(BackupPhase::Done));
+                db.complete_backup(
+                    &backup_id,
+                    files.len() as u64,
+                    total_bytes,
+                    total_chunks,
+                    dedup_chunks,
+                )?;
+                db.log(Some(&backup_id), "INFO", "Backup completed")?;
+                webhook::enqueue(
+                    &db,
+                    &config.webhooks,
+                    &WebhookPayload::new(
+                        "backup.completed",
+                        json!({
+                            "backup_id": backup_id,
+                            "files": files.len(),
+                            "total_bytes": total_bytes,
+                            "total_chunks": total_chunks,
+                            "dedup_chunks": dedup_chunks,
+                            "duration_secs": started.elapsed().as_secs_f64(),
+                        }),
+                    ),
+                )?;
+                let signed = match &config.enigma.backup_signing_key {
+                    Some(key) => {
+                        db.sign_backup(&backup_id, &signing::decode_signing_key(key)?)?;
+                        true
+                    }
+                    None => false,
+                };
+
+                if json {
+                    let report = RunReport {
+                        files_processed: files.len() as u64,
+                        bytes_processed: total_bytes,
+                        chunks_new: total_chunks - dedup_chunks,
+                        chunks_deduped: dedup_chunks,
+                        ..Default::default()
+                    }
+                    .completed();
+                    let tags: serde_json::Map<String, serde_json::Value> =
+                        tags.iter().map(|(k, v)| (k.clone(), json!(v))).collect();
+                    return JsonPrinter::stdout().print(
+                        "backup",
+                        report.to_json(
+                            json!({ "backup_id": backup_id, "tags": tags, "signed": signed }),
+                        )?,
+                    );
+                }
+
+                println!("\nBackup completed:");
+                println!("  ID:             {backup_id}");
+                println!("  Files:          {}", files.len());
+                println!("  Total size:     {} bytes", total_bytes);
+                println!("  Total chunks:   {total_chunks}");
+                println!("  Dedup'd chunks: {dedup_chunks}");
+                if !tags.is_empty() {
+                    let tags: Vec<String> = tags.iter().map(|(k, v)| format!("{k}={v}")).collect();
+                    println!("  Tags:           {}", tags.join(","));
+                }
+                if signed {
+                    println!("  Signed:         yes");
+                }
 
-            Ok(())
+                Ok(())
+            }
+            Err(e) => {
+                events.publish(progress.event(BackupPhase::Error));
+                tracing::error!("Backup {backup_id} failed: {e}");
+                if let Err(fail_err) = db.fail_backup(&backup_id) {
+                    tracing::error!("Failed to mark backup as failed: {fail_err}");
+                }
+                let _ = db.log(Some(&backup_id), "ERROR", &format!("Backup failed: {e}"));
+                let _ = webhook::enqueue(
+                    &db,
+                    &config.webhooks,
+                    &WebhookPayload::new(
+                        "backup.failed",
+                        json!({ "backup_id": backup_id, "error": e.to_string() }),
+                    ),
+                );
+                if json {
+                    let report = RunReport::default().failed(&e);
+                    JsonPrinter::stdout()
+                        .print("backup", report.to_json(json!({ "backup_id": backup_id }))?)?;
+                }
+                Err(e)
+            }
         }
-        Err(e) => {
-            tracing::error!("Backup {backup_id} failed: {e}");
-            if let Err(fail_err) = db.fail_backup(&backup_id) {
-                tracing::error!("Failed to mark backup as failed: {fail_err}");
+    }
+    .await;
+
+    webhook::flush_queue(db, &config.webhooks).await;
+    if let Some(notification) = notification {
+        let _ = notification.await;
+    }
+    result
+}
+
+/// AWS S3 Standard storage price, USD per GiB-month, for the dry-run cost hint.
+const S3_STANDARD_USD_PER_GIB: f64 = 0.023;
+
+/// What a backup would do, from `enigma backup --dry-run`.
+#[derive(Debug, Default, Serialize)]
+pub struct DryRunReport {
+    pub files_total: u64,
+    /// Files whose chunks are all stored already.
+    pub files_unchanged: u64,
+    pub chunks_total: u64,
+    pub chunks_new: u64,
+    pub chunks_deduped: u64,
+    /// Plaintext bytes of the new chunks, before compression and encryption.
+    pub bytes_to_upload: u64,
+    pub bytes_already_stored: u64,
+    /// Rough monthly cost of the new bytes on AWS S3 Standard.
+    pub estimated_cost_hint: String,
+}
+
+/// Preview a backup of `source`: chunk and hash every file and check the
+/// chunks against the manifest, which is opened read-only. Nothing is
+/// uploaded or recorded.
+pub fn dry_run(source: &Path, base_dir: &Path, exclude: &[String], json: bool) -> Result<()> {
+    let report = dry_run_report(source, base_dir, exclude)?;
+    if json {
+        return JsonPrinter::stdout().print("backup", serde_json::to_value(&report)?);
+    }
+
+    println!("Dry run of backup of {}:", source.display());
+    println!(
+        "  Files:          {} ({} unchanged)",
+        report.files_total, report.files_unchanged
+    );
+    println!(
+        "  Chunks:         {} ({} new, {} already stored)",
+        report.chunks_total, report.chunks_new, report.chunks_deduped
+    );
+    println!("  To upload:      {} bytes", report.bytes_to_upload);
+    println!("  Already stored: {} bytes", report.bytes_already_stored);
+    println!("  Estimated cost: {}", report.estimated_cost_hint);
+    Ok(())
+}
+
+fn dry_run_report(source: &Path, base_dir: &Path, exclude: &[String]) -> Result<DryRunReport> {
+    let source = source.canonicalize()?;
+    let config = EnigmaConfig::load(&EnigmaConfig::default_path(base_dir))?;
+    let mut patterns = config.enigma.exclude_patterns.clone();
+    patterns.extend_from_slice(exclude);
+    let filter = ExcludeFilter::new(&patterns)?;
+
+    let db = ManifestDb::open_readonly(Path::new(&config.enigma.db_path))?;
+    let chunk_engine: Box<dyn ChunkEngine> = match config.enigma.chunk_strategy {
+        ChunkStrategy::Cdc { target_size } => Box::new(CdcChunkEngine::new(target_size)?),
+        ChunkStrategy::Fixed { size } => Box::new(FixedSizeChunkEngine::new(size)?),
+    };
+
+    let files = walk_files(&source, &filter)?;
+    let mut report = DryRunReport {
+        files_total: files.len() as u64,
+        ..Default::default()
+    };
+    // Chunks this backup would have uploaded by the time it reaches a file
+    let mut uploaded = HashSet::new();
+    for file_path in &files {
+        let mut unchanged = true;
+        for chunk in chunk_engine.chunk_file(file_path)? {
+            let hash_hex = chunk.hash.to_hex();
+            report.chunks_total += 1;
+            if uploaded.contains(&hash_hex) || db.get_chunk_info(&hash_hex)?.is_some() {
+                report.chunks_deduped += 1;
+                report.bytes_already_stored += chunk.length as u64;
+            } else {
+                unchanged = false;
+                report.chunks_new += 1;
+                report.bytes_to_upload += chunk.length as u64;
+                uploaded.insert(hash_hex);
             }
-            let _ = db.log(Some(&backup_id), "ERROR", &format!("Backup failed: {e}"));
-            Err(e)
+        }
+        if unchanged {
+            report.files_unchanged += 1;
         }
     }
+
+    let cost = report.bytes_to_upload as f64 * S3_STANDARD_USD_PER_GIB / 1_073_741_824.0;
+    report.estimated_cost_hint = format!("~${cost:.2}/month on AWS S3 Standard");
+    Ok(report)
 }
 
 #[allow(clippy::too_many_arguments)]
 async fn run_backup_inner(
-    db: &ManifestDb,
+    db: &mut ManifestDb,
     backup_id: &str,
     source: &Path,
     files: &[PathBuf],
@@ -150,8 +400,15 @@ async fn run_backup_inner(
         Box<dyn enigma_storage::provider::StorageProvider>,
     >,
     distributor: &Distributor,
+    json: bool,
+    progress: &mut BackupProgress,
+    events: &BackupEvents,
 ) -> Result<(u64, u64, u64)> {
-    let pb = ProgressBar::new(files.len() as u64);
+    let pb = if json {
+        ProgressBar::hidden()
+    } else {
+        ProgressBar::new(files.len() as u64)
+    };
     pb.set_style(
         ProgressStyle::default_bar()
             .template("{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} {msg}")
@@ -162,6 +419,9 @@ async fn run_backup_inner(
     let mut total_bytes = 0u64;
     let mut total_chunks = 0u64;
     let mut dedup_chunks = 0u64;
+    let upload_permits = worker_semaphore(config.enigma.backup_workers);
+    // Chunks already in the manifest; one missing here is new for sure
+    let mut dedup_index = DedupIndex::load_from_db(db, &config.enigma)?;
 
     for file_path in files {
         let relative = file_path.strip_prefix(source).unwrap_or(file_path);
@@ -172,6 +432,7 @@ async fn run_backup_inner(
         total_bytes += file_size;
 
         // Chunk the file
+        events.publish(progress.event(BackupPhase::Chunking));
         let chunks = chunk_engine.chunk_file(file_path)?;
         let chunk_count = chunks.len() as u32;
 
@@ -191,7 +452,10 @@ async fn run_backup_inner(
                 .ok()
                 .map(|d| d.as_secs().to_string())
         });
-        let file_id = db.insert_backup_file(
+        // The file's records are written in one transaction: the file row,
+        // its chunks, and the file-chunk mappings in a single batch
+        let mut bulk = db.begin_bulk_insert()?;
+        let file_id = bulk.insert_backup_file(
             backup_id,
             relative.to_str().unwrap_or(""),
             file_size,
@@ -200,9 +464,8 @@ async fn run_backup_inner(
             chunk_count,
         )?;
 
-        // Process each chunk (batched in a transaction for performance)
         let compression = &config.enigma.compression;
-        db.begin_transaction()?;
+        let mut file_chunks = Vec::with_capacity(chunks.len());
         for (idx, chunk) in chunks.iter().enumerate() {
             let hash_hex = chunk.hash.to_hex();
             total_chunks += 1;
@@ -215,9 +478,13 @@ async fn run_backup_inner(
             let primary = targets[0];
 
             // Compress (optional, before encryption)
+            events.publish(progress.event(BackupPhase::Encrypting));
             let (data_to_encrypt, size_compressed) = if compression.enabled {
-                let compressed =
-                    enigma_core::compression::compress_chunk(&chunk.data, compression.level)?;
+                let compressed = enigma_core::compression::compress(
+                    &chunk.data,
+                    compression.algorithm,
+                    compression.level,
+                )?;
                 let sz = compressed.len() as u64;
                 (compressed, Some(sz))
             } else {
@@ -227,8 +494,15 @@ async fn run_backup_inner(
             // Encrypt
             let encrypted = encrypt_chunk(&data_to_encrypt, &chunk.hash, key_material)?;
 
-            // Dedup + upload
-            let is_new = db.insert_or_dedup_chunk(
+            // Dedup + upload. Only chunks the index may know need the
+            // manifest's ref_count lookup
+            let insert = if dedup_index.contains(&chunk.hash) {
+                BulkInsertHandle::insert_or_dedup_chunk
+            } else {
+                BulkInsertHandle::insert_new_chunk
+            };
+            let is_new = insert(
+                &mut bulk,
                 &hash_hex,
                 &encrypted.nonce,
                 &key_material.id,
@@ -238,17 +512,24 @@ async fn run_backup_inner(
                 encrypted.ciphertext.len() as u64,
                 size_compressed,
             )?;
+            dedup_index.insert(&chunk.hash);
 
             if is_new {
+                let tag = chunk_hmac_tag(key_material, &storage_key, &encrypted.ciphertext);
+                bulk.set_chunk_hmac_tag(&hash_hex, &tag)?;
+
                 // Upload to all target providers concurrently
+                events.publish(progress.event(BackupPhase::Uploading));
                 let upload_futures: Vec<(i64, _)> = targets
                     .iter()
                     .filter_map(|target| {
                         storage_providers.get(&target.id).map(|provider| {
-                            (
-                                target.id,
-                                provider.upload_chunk(&storage_key, &encrypted.ciphertext),
-                            )
+                            let upload_permits = &upload_permits;
+                            let (storage_key, ciphertext) = (&storage_key, &encrypted.ciphertext);
+                            (target.id, async move {
+                                let _permit = upload_permits.acquire().await?;
+                                provider.upload_chunk(storage_key, ciphertext).await
+                            })
                         })
                     })
                     .collect();
@@ -275,17 +556,23 @@ async fn run_backup_inner(
                         .iter()
                         .map(|t| (t.id, storage_key.as_str()))
                         .collect();
-                    db.insert_chunk_replicas(&hash_hex, &replicas)?;
+                    bulk.insert_chunk_replicas(&hash_hex, &replicas)?;
                 }
             } else {
                 dedup_chunks += 1;
             }
+            progress.chunk_done(chunk.length as u64, !is_new);
 
-            // Record file-chunk mapping
-            db.insert_file_chunk(file_id, &hash_hex, idx as u32, chunk.offset)?;
+            file_chunks.push((hash_hex, idx as u32, chunk.offset));
         }
-        db.commit_transaction()?;
-
+        let records: Vec<(i64, &str, u32, u64)> = file_chunks
+            .iter()
+            .map(|(hash, idx, offset)| (file_id, hash.as_str(), *idx, *offset))
+            .collect();
+        bulk.batch_insert_file_chunks(&records)?;
+        bulk.commit()?;
+
+        progress.files_done += 1;
         pb.inc(1);
     }
 
@@ -294,28 +581,119 @@ async fn run_backup_inner(
     Ok((total_bytes, total_chunks, dedup_chunks))
 }
 
-fn walk_files(dir: &Path) -> Result<Vec<PathBuf>> {
+fn walk_files(dir: &Path, filter: &ExcludeFilter) -> Result<Vec<PathBuf>> {
     let mut files = Vec::new();
-    walk_recursive(dir, &mut files)?;
-    files.sort();
-    Ok(files)
-}
-
-fn walk_recursive(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
-    if !dir.is_dir() {
+    if dir.is_dir() {
+        walk_recursive(dir, dir, filter, &mut files)?;
+    } else {
         // Single file backup
         files.push(dir.to_path_buf());
-        return Ok(());
     }
+    files.sort();
+    Ok(files)
+}
 
+fn walk_recursive(
+    root: &Path,
+    dir: &Path,
+    filter: &ExcludeFilter,
+    files: &mut Vec<PathBuf>,
+) -> Result<()> {
     for entry in std::fs::read_dir(dir)? {
         let entry = entry?;
         let path = entry.path();
+        let relative = path.strip_prefix(root).unwrap_or(&path);
         if path.is_dir() {
-            walk_recursive(&path, files)?;
-        } else if path.is_file() {
+            if !filter.excludes_dir(relative) {
+                walk_recursive(root, &path, filter, files)?;
+            }
+        } else if path.is_file() && !filter.excludes_file(relative) {
             files.push(path);
         }
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn excluded_directory_is_not_backed_up() {
+        let tmp = tempfile::tempdir().unwrap();
+        let base = tmp.path().join("enigma");
+        let source = tmp.path().join("project");
+        std::fs::create_dir_all(source.join("src")).unwrap();
+        std::fs::create_dir_all(source.join("target/debug")).unwrap();
+        std::fs::write(source.join("Cargo.toml"), b"[package]").unwrap();
+        std::fs::write(source.join("src/main.rs"), b"fn main() {}").unwrap();
+        std::fs::write(source.join("src/cache.pyc"), b"bytecode").unwrap();
+        std::fs::write(source.join("target/debug/app"), b"binary").unwrap();
+
+        let passphrase = Some("test-passphrase".to_string());
+        super::super::init::run(&base, &passphrase, Default::default())
+            .await
+            .unwrap();
+        let exclude = vec!["target/".to_string(), "*.pyc".to_string()];
+        run(&source, &base, &passphrase, &[], &exclude, true)
+            .await
+            .unwrap();
+
+        let config = EnigmaConfig::load(&EnigmaConfig::default_path(&base)).unwrap();
+        let db = ManifestDb::open(Path::new(&config.enigma.db_path)).unwrap();
+        let backup_id = db.list_backups().unwrap()[0].id.clone();
+        let mut paths: Vec<String> = db
+            .list_backup_files(&backup_id)
+            .unwrap()
+            .into_iter()
+            .map(|(_, path, ..)| path)
+            .collect();
+        paths.sort();
+        assert_eq!(paths, vec!["Cargo.toml", "src/main.rs"]);
+    }
+
+    #[tokio::test]
+    async fn dry_run_counts_new_chunks_without_writing() {
+        let tmp = tempfile::tempdir().unwrap();
+        let base = tmp.path().join("enigma");
+        let source = tmp.path().join("data");
+        std::fs::create_dir_all(&source).unwrap();
+        std::fs::write(source.join("old.txt"), b"backed up already").unwrap();
+
+        let passphrase = Some("test-passphrase".to_string());
+        super::super::init::run(&base, &passphrase, Default::default())
+            .await
+            .unwrap();
+        run(&source, &base, &passphrase, &[], &[], true)
+            .await
+            .unwrap();
+        std::fs::write(source.join("new.txt"), b"not backed up yet").unwrap();
+        std::fs::write(source.join("copy.txt"), b"not backed up yet").unwrap();
+
+        let config = EnigmaConfig::load(&EnigmaConfig::default_path(&base)).unwrap();
+        let db_path = PathBuf::from(&config.enigma.db_path);
+        let wal_path = db_path.with_extension("db-wal");
+        let snapshot = || {
+            (
+                std::fs::read(&db_path).unwrap(),
+                std::fs::read(&wal_path).unwrap_or_default(),
+            )
+        };
+        let before = snapshot();
+
+        let report = dry_run_report(&source, &base, &[]).unwrap();
+        assert_eq!(report.files_total, 3);
+        assert_eq!(report.files_unchanged, 2);
+        assert_eq!(report.chunks_total, 3);
+        assert_eq!(report.chunks_new, 1);
+        assert_eq!(report.chunks_deduped, 2);
+        assert_eq!(report.bytes_to_upload, 17);
+        assert_eq!(report.bytes_already_stored, 34);
+        assert!(report.estimated_cost_hint.starts_with("~$0.00"));
+
+        // Neither the database nor its write-ahead log changed
+        assert!(snapshot() == before);
+        let db = ManifestDb::open(&db_path).unwrap();
+        assert_eq!(db.list_backups().unwrap().len(), 1);
+    }
+}
@@ -1,12 +1,19 @@
 use anyhow::Result;
+use serde_json::{Value, json};
 use std::path::Path;
 
-use enigma_core::config::EnigmaConfig;
+use enigma_core::config::{ConfigError, EnigmaConfig};
 
-pub fn run(base_dir: &Path) -> Result<()> {
+use crate::output::JsonPrinter;
+
+pub fn run(base_dir: &Path, json: bool) -> Result<()> {
     let config_path = EnigmaConfig::default_path(base_dir);
     let config = EnigmaConfig::load(&config_path)?;
 
+    if json {
+        return JsonPrinter::stdout().print("config", config_json(&config_path, &config));
+    }
+
     println!("Config: {}", config_path.display());
     println!();
     println!("  DB path:        {}", config.enigma.db_path);
@@ -37,3 +44,111 @@ pub fn run(base_dir: &Path) -> Result<()> {
 
     Ok(())
 }
+
+/// `enigma config validate`: report every problem with the config, each
+/// under the TOML key it concerns. Fails when there is any.
+pub fn validate(base_dir: &Path, json: bool) -> Result<()> {
+    let config_path = EnigmaConfig::default_path(base_dir);
+    let config = EnigmaConfig::load_unchecked(&config_path)?;
+    let errors = config.validate().err().unwrap_or_default();
+
+    if json {
+        JsonPrinter::stdout().print("config-validate", errors_json(&errors))?;
+    } else if errors.is_empty() {
+        println!("{}: OK", config_path.display());
+    } else {
+        println!("{}:", config_path.display());
+        for error in &errors {
+            println!("  {error}");
+        }
+    }
+
+    if !errors.is_empty() {
+        anyhow::bail!("{} problem(s) in {}", errors.len(), config_path.display());
+    }
+    Ok(())
+}
+
+fn errors_json(errors: &[ConfigError]) -> Value {
+    Value::Array(
+        errors
+            .iter()
+            .map(|e| json!({ "field": e.field, "error": e.message }))
+            .collect(),
+    )
+}
+
+/// Same fields as the text output; credentials are never included.
+fn config_json(config_path: &Path, config: &EnigmaConfig) -> Value {
+    let providers: Vec<Value> = config
+        .providers
+        .iter()
+        .map(|p| {
+            json!({
+                "name": p.name,
+                "type": p.provider_type.to_string(),
+                "bucket": p.bucket,
+                "region": p.region,
+                "weight": p.weight,
+            })
+        })
+        .collect();
+    json!({
+        "config_path": config_path.display().to_string(),
+        "db_path": config.enigma.db_path,
+        "key_provider": config.enigma.key_provider,
+        "keyfile_path": config.enigma.keyfile_path,
+        "chunk_strategy": config.enigma.chunk_strategy,
+        "distribution": config.enigma.distribution,
+        "providers": providers,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::render;
+
+    #[test]
+    fn config_json_fields() {
+        let tmp = std::env::temp_dir();
+        let config = EnigmaConfig::default_config(&tmp);
+        let path = EnigmaConfig::default_path(&tmp);
+
+        let doc = render("config", config_json(&path, &config));
+        assert_eq!(doc["version"], 1);
+        assert_eq!(doc["command"], "config");
+        assert_eq!(doc["key_provider"], config.enigma.key_provider);
+        assert_eq!(doc["db_path"], config.enigma.db_path);
+        assert!(doc["chunk_strategy"].is_object());
+        assert!(doc["providers"].is_array());
+    }
+
+    #[test]
+    fn validate_json_lists_errors() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut config = EnigmaConfig::default_config(tmp.path());
+        config.enigma.key_provider = "azure-keyvault".to_string();
+        config.enigma.replication_factor = 2;
+        let errors = config.validate().unwrap_err();
+
+        let doc = render("config-validate", errors_json(&errors));
+        assert_eq!(doc["command"], "config-validate");
+        assert_eq!(
+            doc["data"],
+            json!([
+                {
+                    "field": "enigma.vault_url",
+                    "error": "is required for key provider \"azure-keyvault\"",
+                },
+                {
+                    "field": "enigma.replication_factor",
+                    "error": "is 2 but only 1 provider(s) are configured",
+                },
+            ])
+        );
+
+        let doc = render("config-validate", errors_json(&[]));
+        assert_eq!(doc["data"], json!([]));
+    }
+}
@@ -8,19 +8,14 @@ pub async fn run(value: &str, base_dir: &Path, cli_passphrase: &Option<String>)
     let config_path = EnigmaConfig::default_path(base_dir);
     let config = EnigmaConfig::load(&config_path)?;
 
-    let passphrase = if config.enigma.key_provider == "local" {
+    let passphrase = if config.enigma.needs_passphrase() {
         Some(crate::get_passphrase(cli_passphrase)?)
     } else {
         None
     };
     let key_provider = enigma_keys::factory::create_key_provider(
         &config.enigma.key_provider,
-        passphrase.as_deref().map(|s| s.as_bytes()),
-        &config.enigma.keyfile_path,
-        config.enigma.vault_url.as_deref(),
-        config.enigma.gcp_project_id.as_deref(),
-        config.enigma.aws_region.as_deref(),
-        config.enigma.secret_prefix.as_deref(),
+        &crate::key_provider_options(&config.enigma, passphrase.as_deref()),
     )
     .await?;
 
@@ -0,0 +1,162 @@
+//! Exclude rules for `enigma backup`.
+//!
+//! Patterns are globs matched against paths relative to the backup root,
+//! with a few gitignore-style conveniences: a pattern without a `/`
+//! (`*.pyc`) matches at any depth, a trailing `/` (`target/`) matches a
+//! directory and everything below it, and a leading `/` anchors the
+//! pattern to the root.
+
+use anyhow::{Context, Result};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use std::path::Path;
+
+/// Patterns added by `--exclude-caches`.
+pub const CACHE_PATTERNS: &[&str] = &[
+    ".cache/",
+    "target/",
+    "node_modules/",
+    "__pycache__/",
+    ".gradle/",
+    ".ivy2/",
+];
+
+/// Compiled exclude patterns.
+pub struct ExcludeFilter {
+    /// Matches excluded files, and anything below an excluded directory.
+    entries: GlobSet,
+    /// Matches directories excluded by a trailing-`/` pattern.
+    dirs: GlobSet,
+}
+
+impl ExcludeFilter {
+    pub fn new(patterns: &[String]) -> Result<Self> {
+        let mut entries = GlobSetBuilder::new();
+        let mut dirs = GlobSetBuilder::new();
+        for pattern in patterns {
+            let dir_only = pattern.ends_with('/');
+            let trimmed = pattern.trim_end_matches('/');
+            let base = match trimmed.strip_prefix('/') {
+                Some(anchored) => anchored.to_string(),
+                None if trimmed.contains('/') => trimmed.to_string(),
+                None => format!("**/{trimmed}"),
+            };
+            if dir_only {
+                dirs.add(glob(&base, pattern)?);
+            } else {
+                entries.add(glob(&base, pattern)?);
+            }
+            if !base.ends_with("/**") {
+                entries.add(glob(&format!("{base}/**"), pattern)?);
+            }
+        }
+        Ok(Self {
+            entries: entries.build()?,
+            dirs: dirs.build()?,
+        })
+    }
+
+    /// Whether the file at `relative` (to the backup root) is excluded.
+    pub fn excludes_file(&self, relative: &Path) -> bool {
+        self.entries.is_match(relative)
+    }
+
+    /// Whether the directory at `relative` is excluded, so the walk can
+    /// skip it entirely.
+    pub fn excludes_dir(&self, relative: &Path) -> bool {
+        self.dirs.is_match(relative) || self.entries.is_match(relative)
+    }
+}
+
+fn glob(glob: &str, pattern: &str) -> Result<globset::Glob> {
+    GlobBuilder::new(glob)
+        .literal_separator(true)
+        .build()
+        .with_context(|| format!("invalid exclude pattern '{pattern}'"))
+}
+
+/// Whether the file at `path`, relative to the backup root, matches any of
+/// `patterns`. Invalid patterns match nothing. This compiles the patterns
+/// on every call; the backup walk builds one [`ExcludeFilter`] instead.
+#[cfg(test)]
+pub fn matches_exclude(path: &Path, patterns: &[String]) -> bool {
+    ExcludeFilter::new(patterns).is_ok_and(|filter| filter.excludes_file(path))
+}
+
+/// Gather the patterns given on the command line: `--exclude` values, the
+/// lines of `--exclude-from` (blank lines and `#` comments skipped) and
+/// the cache directories with `--exclude-caches`.
+pub fn collect_patterns(
+    exclude: &[String],
+    exclude_from: Option<&Path>,
+    exclude_caches: bool,
+) -> Result<Vec<String>> {
+    let mut patterns = exclude.to_vec();
+    if let Some(file) = exclude_from {
+        let contents = std::fs::read_to_string(file)
+            .with_context(|| format!("read exclude file {}", file.display()))?;
+        patterns.extend(
+            contents
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(String::from),
+        );
+    }
+    if exclude_caches {
+        patterns.extend(CACHE_PATTERNS.iter().map(|p| p.to_string()));
+    }
+    Ok(patterns)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(list: &[&str]) -> Vec<String> {
+        list.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn extension_matches_at_any_depth() {
+        let p = patterns(&["*.pyc"]);
+        assert!(matches_exclude(Path::new("a.pyc"), &p));
+        assert!(matches_exclude(Path::new("src/pkg/a.pyc"), &p));
+        assert!(!matches_exclude(Path::new("src/a.py"), &p));
+    }
+
+    #[test]
+    fn trailing_slash_excludes_directory_contents() {
+        let p = patterns(&["target/", ".git/"]);
+        assert!(matches_exclude(Path::new("target/debug/app"), &p));
+        assert!(matches_exclude(Path::new("crates/x/target/out.o"), &p));
+        assert!(matches_exclude(Path::new(".git/HEAD"), &p));
+        // A file called `target` is not a directory
+        assert!(!matches_exclude(Path::new("docs/target"), &p));
+
+        let filter = ExcludeFilter::new(&p).unwrap();
+        assert!(filter.excludes_dir(Path::new("crates/x/target")));
+        assert!(!filter.excludes_dir(Path::new("crates/x/src")));
+    }
+
+    #[test]
+    fn double_star_patterns_and_anchoring() {
+        let p = patterns(&["**/__pycache__/**", "/build", "docs/*.tmp"]);
+        assert!(matches_exclude(Path::new("a/__pycache__/m.pyc"), &p));
+        assert!(matches_exclude(Path::new("build/out.bin"), &p));
+        assert!(!matches_exclude(Path::new("src/build/out.bin"), &p));
+        assert!(matches_exclude(Path::new("docs/x.tmp"), &p));
+        assert!(!matches_exclude(Path::new("docs/sub/x.tmp"), &p));
+    }
+
+    #[test]
+    fn collect_patterns_from_file_and_caches() {
+        let tmp = tempfile::tempdir().unwrap();
+        let file = tmp.path().join("excludes");
+        std::fs::write(&file, "# build output\n*.o\n\n  dist/  \n").unwrap();
+
+        let collected = collect_patterns(&patterns(&["*.log"]), Some(&file), true).unwrap();
+        assert_eq!(&collected[..3], &patterns(&["*.log", "*.o", "dist/"])[..]);
+        assert!(collected.contains(&"node_modules/".to_string()));
+        assert_eq!(collected.len(), 3 + CACHE_PATTERNS.len());
+    }
+}
@@ -0,0 +1,169 @@
+use anyhow::{Context, Result};
+use serde_json::{Value, json};
+use std::path::Path;
+
+use enigma_core::config::EnigmaConfig;
+use enigma_core::crypto::{decrypt_data, encrypt_data};
+use enigma_core::manifest::ManifestDb;
+use enigma_keys::provider::KeyProvider;
+
+use crate::output::JsonPrinter;
+
+/// Leading bytes of an encrypted manifest export.
+const MAGIC: &[u8; 8] = b"ENGMEXP1";
+
+/// Encrypted export file: `MAGIC | key id length (u16 BE) | key id | nonce | ciphertext`.
+/// The magic and key id are bound to the ciphertext as AAD.
+pub(crate) fn seal(plaintext: &[u8], key_id: &str, key: &[u8; 32]) -> Result<Vec<u8>> {
+    let header = header(key_id)?;
+    let (ciphertext, nonce) = encrypt_data(plaintext, key, &header)?;
+    let mut out = header;
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(&ciphertext);
+    Ok(out)
+}
+
+/// Key id an export file was encrypted with.
+pub(crate) fn sealed_key_id(data: &[u8]) -> Result<&str> {
+    if data.len() < MAGIC.len() + 2 || &data[..MAGIC.len()] != MAGIC {
+        anyhow::bail!("Not an Enigma manifest export");
+    }
+    let len = u16::from_be_bytes([data[MAGIC.len()], data[MAGIC.len() + 1]]) as usize;
+    let start = MAGIC.len() + 2;
+    let id = data
+        .get(start..start + len)
+        .context("Truncated manifest export")?;
+    Ok(std::str::from_utf8(id)?)
+}
+
+pub(crate) fn open(data: &[u8], key: &[u8; 32]) -> Result<Vec<u8>> {
+    let header_len = MAGIC.len() + 2 + sealed_key_id(data)?.len();
+    let nonce: [u8; 12] = data
+        .get(header_len..header_len + 12)
+        .context("Truncated manifest export")?
+        .try_into()?;
+    let plaintext = decrypt_data(&data[header_len + 12..], key, &nonce, &data[..header_len])
+        .context("Failed to decrypt manifest export (wrong key or corrupted file)")?;
+    Ok(plaintext)
+}
+
+fn header(key_id: &str) -> Result<Vec<u8>> {
+    let len = u16::try_from(key_id.len()).context("Key id too long")?;
+    let mut header = MAGIC.to_vec();
+    header.extend_from_slice(&len.to_be_bytes());
+    header.extend_from_slice(key_id.as_bytes());
+    Ok(header)
+}
+
+pub(crate) async fn key_provider(
+    config: &EnigmaConfig,
+    cli_passphrase: &Option<String>,
+) -> Result<Box<dyn KeyProvider>> {
+    let passphrase = if config.enigma.needs_passphrase() {
+        Some(crate::get_passphrase(cli_passphrase)?)
+    } else {
+        None
+    };
+    enigma_keys::factory::create_key_provider(
+        &config.enigma.key_provider,
+        &crate::key_provider_options(&config.enigma, passphrase.as_deref()),
+    )
+    .await
+}
+
+pub async fn run(
+    base_dir: &Path,
+    cli_passphrase: &Option<String>,
+    output: &Path,
+    json: bool,
+) -> Result<()> {
+    let config_path = EnigmaConfig::default_path(base_dir);
+    let config = EnigmaConfig::load(&config_path)?;
+    let db = ManifestDb::open(Path::new(&config.enigma.db_path))?;
+
+    let key = key_provider(&config, cli_passphrase)
+        .await?
+        .get_current_key()
+        .await?;
+
+    let mut plaintext = Vec::new();
+    db.export_to_json(&mut plaintext)?;
+    let sealed = seal(&plaintext, &key.id, &key.key)?;
+    std::fs::write(output, &sealed)
+        .with_context(|| format!("Failed to write {}", output.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(output, std::fs::Permissions::from_mode(0o600))?;
+    }
+
+    let report = ExportReport {
+        output,
+        key_id: &key.id,
+        bytes: sealed.len() as u64,
+    };
+    if json {
+        return JsonPrinter::stdout().print("export", report.to_json());
+    }
+    println!(
+        "Exported manifest to {} ({} bytes, key {})",
+        output.display(),
+        report.bytes,
+        key.id
+    );
+    Ok(())
+}
+
+struct ExportReport<'a> {
+    output: &'a Path,
+    key_id: &'a str,
+    bytes: u64,
+}
+
+impl ExportReport<'_> {
+    fn to_json(&self) -> Value {
+        json!({
+            "output": self.output.display().to_string(),
+            "key_id": self.key_id,
+            "bytes": self.bytes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::render;
+
+    #[test]
+    fn sealed_export_roundtrip() {
+        let key = [0x42; 32];
+        let sealed = seal(b"[]", "key-1", &key).unwrap();
+        assert_eq!(sealed_key_id(&sealed).unwrap(), "key-1");
+        assert_eq!(open(&sealed, &key).unwrap(), b"[]");
+        assert!(open(&sealed, &[0x43; 32]).is_err());
+    }
+
+    #[test]
+    fn tampered_header_is_rejected() {
+        let key = [0x42; 32];
+        let mut sealed = seal(b"[]", "key-1", &key).unwrap();
+        sealed[MAGIC.len() + 2] = b'K';
+        assert!(open(&sealed, &key).is_err());
+        assert!(sealed_key_id(b"not an export").is_err());
+    }
+
+    #[test]
+    fn export_json() {
+        let report = ExportReport {
+            output: Path::new("/tmp/manifest.enc"),
+            key_id: "key-1",
+            bytes: 128,
+        };
+        let doc = render("export", report.to_json());
+        assert_eq!(doc["command"], "export");
+        assert_eq!(doc["output"], "/tmp/manifest.enc");
+        assert_eq!(doc["key_id"], "key-1");
+        assert_eq!(doc["bytes"], 128);
+    }
+}
@@ -1,34 +1,91 @@
 use anyhow::Result;
-use std::collections::HashSet;
+use serde_json::{Value, json};
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
+use std::time::Duration;
+use tokio::time::{Interval, MissedTickBehavior};
 
 use enigma_core::config::EnigmaConfig;
-use enigma_core::manifest::ManifestDb;
+use enigma_core::crypto::decrypt_chunk;
+use enigma_core::dedup::compute_hash;
+use enigma_core::manifest::{CheckpointMode, ManifestDb};
+use enigma_core::types::{ChunkHash, EncryptedChunk, KeyMaterial};
+use enigma_core::webhook::{self, WebhookPayload};
+use enigma_keys::provider::KeyProvider;
+use enigma_storage::provider::{StorageProvider, is_archive_class};
 
 use super::providers::init_providers;
+use crate::output::JsonPrinter;
 
-pub async fn run(base_dir: &Path, dry_run: bool) -> Result<()> {
+/// Chunk hashes verified per manifest query.
+const PAGE_SIZE: u32 = 1000;
+
+/// What `enigma gc` does besides deleting orphans.
+pub struct GcOptions {
+    pub dry_run: bool,
+    /// Download and re-hash every referenced chunk.
+    pub verify: bool,
+    /// Queue chunks that fail verification for re-upload (not on a dry run).
+    pub fix: bool,
+    /// Maximum provider downloads per second while verifying.
+    pub verify_rate: u32,
+}
+
+pub async fn run(
+    base_dir: &Path,
+    cli_passphrase: &Option<String>,
+    options: &GcOptions,
+    json: bool,
+) -> Result<()> {
     let config_path = EnigmaConfig::default_path(base_dir);
     let config = EnigmaConfig::load(&config_path)?;
     let db = ManifestDb::open(Path::new(&config.enigma.db_path))?;
+    let dry_run = options.dry_run;
 
-    let (total, orphan_count) = db.chunk_stats()?;
-    println!("Chunk stats: {total} total, {orphan_count} orphans");
+    let mut storage_providers = None;
+    let verify = if options.verify {
+        let providers = init_providers(&config.providers, &db).await?;
+        let key_provider = super::export::key_provider(&config, cli_passphrase).await?;
+        if !json {
+            println!("Verifying chunk storage...");
+        }
+        let report = verify_storage(
+            &db,
+            &providers,
+            key_provider.as_ref(),
+            options.fix && !dry_run,
+            options.verify_rate,
+        )
+        .await?;
+        if !json {
+            report.print_text();
+        }
+        storage_providers = Some(providers);
+        Some(report)
+    } else {
+        None
+    };
 
+    let (total, orphan_count) = db.chunk_stats()?;
     let orphans = db.find_orphan_chunks()?;
     let orphan_replicas = db.find_orphan_chunk_replicas()?;
 
-    if orphans.is_empty() && orphan_replicas.is_empty() {
-        println!("No orphaned chunks found.");
-        return Ok(());
+    if !json {
+        println!("Chunk stats: {total} total, {orphan_count} orphans");
+        if orphans.is_empty() && orphan_replicas.is_empty() {
+            println!("No orphaned chunks found.");
+            if !dry_run {
+                gc_completed(db, &config, 0, 0).await?;
+            }
+            return Ok(());
+        }
+        println!(
+            "Found {} orphaned chunks, {} orphan replicas",
+            orphans.len(),
+            orphan_replicas.len()
+        );
     }
 
-    println!(
-        "Found {} orphaned chunks, {} orphan replicas",
-        orphans.len(),
-        orphan_replicas.len()
-    );
-
     // Collect all storage locations to delete (primary + replicas), deduped via HashSet
     let mut seen: HashSet<(i64, String)> = HashSet::new();
     let mut all_deletions: Vec<(String, i64, String)> = Vec::new();
@@ -52,6 +109,24 @@ pub async fn run(base_dir: &Path, dry_run: bool) -> Result<()> {
         }
     }
 
+    let mut report = GcReport {
+        total_chunks: total,
+        orphan_chunks: orphans.len(),
+        orphan_replicas: orphan_replicas.len(),
+        dry_run,
+        deletions: &all_deletions,
+        deleted: 0,
+        errors: 0,
+        verify,
+    };
+
+    if json && (dry_run || all_deletions.is_empty()) {
+        if !dry_run {
+            gc_completed(db, &config, 0, 0).await?;
+        }
+        return JsonPrinter::stdout().print("gc", report.to_json());
+    }
+
     if dry_run {
         println!(
             "\nDry run — would delete {} storage entries:",
@@ -63,24 +138,24 @@ pub async fn run(base_dir: &Path, dry_run: bool) -> Result<()> {
         return Ok(());
     }
 
-    // Initialize storage providers for deletion
-    let storage_providers = init_providers(&config.providers, &db).await?;
-
-    let mut deleted = 0u64;
-    let mut errors = 0u64;
+    // Initialize storage providers for deletion, unless verify already did
+    let storage_providers = match storage_providers {
+        Some(providers) => providers,
+        None => init_providers(&config.providers, &db).await?,
+    };
 
     // Delete storage objects
     for (_hash, provider_id, storage_key) in &all_deletions {
         if let Some(provider) = storage_providers.get(provider_id) {
             match provider.delete_chunk(storage_key).await {
                 Ok(_) => {
-                    deleted += 1;
+                    report.deleted += 1;
                 }
                 Err(e) => {
                     eprintln!(
                         "WARN: Failed to delete {storage_key} from provider {provider_id}: {e}"
                     );
-                    errors += 1;
+                    report.errors += 1;
                 }
             }
         }
@@ -91,6 +166,339 @@ pub async fn run(base_dir: &Path, dry_run: bool) -> Result<()> {
         db.delete_chunk_record(hash)?;
     }
 
-    println!("\nGC completed: {deleted} storage entries deleted, {errors} errors");
+    // Move the deletions out of the WAL now rather than leave a large log
+    // for whoever checkpoints next
+    db.checkpoint(CheckpointMode::Full)?;
+    gc_completed(db, &config, report.deleted, report.errors).await?;
+
+    if json {
+        return JsonPrinter::stdout().print("gc", report.to_json());
+    }
+
+    println!(
+        "\nGC completed: {} storage entries deleted, {} errors",
+        report.deleted, report.errors
+    );
     Ok(())
 }
+
+/// Queue and send the `gc.completed` webhook.
+async fn gc_completed(
+    db: ManifestDb,
+    config: &EnigmaConfig,
+    deleted: u64,
+    errors: u64,
+) -> Result<()> {
+    webhook::enqueue(
+        &db,
+        &config.webhooks,
+        &WebhookPayload::new(
+            "gc.completed",
+            json!({ "deleted": deleted, "errors": errors }),
+        ),
+    )?;
+    webhook::flush_queue(db, &config.webhooks).await;
+    Ok(())
+}
+
+struct GcReport<'a> {
+    total_chunks: u64,
+    orphan_chunks: usize,
+    orphan_replicas: usize,
+    dry_run: bool,
+    /// (chunk hash, provider id, storage key) — deleted, or to delete on a dry run.
+    deletions: &'a [(String, i64, String)],
+    deleted: u64,
+    errors: u64,
+    verify: Option<VerifyReport>,
+}
+
+impl GcReport<'_> {
+    fn to_json(&self) -> Value {
+        let deletions: Vec<Value> = self
+            .deletions
+            .iter()
+            .map(|(hash, provider_id, storage_key)| {
+                json!({
+                    "hash": hash,
+                    "provider_id": provider_id,
+                    "storage_key": storage_key,
+                })
+            })
+            .collect();
+        let mut doc = json!({
+            "total_chunks": self.total_chunks,
+            "orphan_chunks": self.orphan_chunks,
+            "orphan_replicas": self.orphan_replicas,
+            "dry_run": self.dry_run,
+            "deletions": deletions,
+            "deleted": self.deleted,
+            "errors": self.errors,
+        });
+        if let Some(verify) = &self.verify {
+            doc["verify"] = verify.to_json();
+        }
+        doc
+    }
+}
+
+// ── Verify ─────────────────────────────────────────────────
+
+/// Outcome of verifying one chunk against storage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChunkStatus {
+    Ok,
+    /// No location could be downloaded.
+    Missing,
+    /// Downloaded, but no copy decrypted to the recorded hash.
+    Mismatch,
+    /// Only in an archive tier, which is being restored; verify again once
+    /// the restore completes.
+    Archived,
+}
+
+impl ChunkStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            ChunkStatus::Ok => "ok",
+            ChunkStatus::Missing => "missing",
+            ChunkStatus::Mismatch => "mismatch",
+            ChunkStatus::Archived => "archived",
+        }
+    }
+}
+
+/// Totals of a finished verify pass, including chunks checked by earlier,
+/// interrupted runs.
+#[derive(Debug, Default)]
+struct VerifyReport {
+    total_chunks: u64,
+    verified_ok: u64,
+    missing_from_storage: u64,
+    hash_mismatch: u64,
+    archived: u64,
+    repair_queued: u64,
+}
+
+impl VerifyReport {
+    fn print_text(&self) {
+        println!(
+            "Verified {} chunks: {} ok, {} missing from storage, {} hash mismatches",
+            self.total_chunks, self.verified_ok, self.missing_from_storage, self.hash_mismatch
+        );
+        if self.archived > 0 {
+            println!(
+                "Requested restores of {} archived chunks; run `enigma gc --verify` again once they complete",
+                self.archived
+            );
+        }
+        if self.repair_queued > 0 {
+            println!(
+                "Queued {} chunks for re-upload by the next backup of their data",
+                self.repair_queued
+            );
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "total_chunks": self.total_chunks,
+            "verified_ok": self.verified_ok,
+            "missing_from_storage": self.missing_from_storage,
+            "hash_mismatch": self.hash_mismatch,
+            "archived": self.archived,
+            "repair_queued": self.repair_queued,
+        })
+    }
+}
+
+/// Download and re-hash every referenced chunk. Each outcome is recorded in
+/// the manifest as it happens, so an interrupted pass resumes where it
+/// stopped; the progress is cleared once the pass completes.
+async fn verify_storage(
+    db: &ManifestDb,
+    storage_providers: &HashMap<i64, Box<dyn StorageProvider>>,
+    key_provider: &dyn KeyProvider,
+    fix: bool,
+    rate: u32,
+) -> Result<VerifyReport> {
+    let mut limiter = tokio::time::interval(Duration::from_secs(1) / rate.max(1));
+    limiter.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut keys: HashMap<String, KeyMaterial> = HashMap::new();
+
+    let mut after = String::new();
+    loop {
+        let page = db.unverified_chunk_hashes(&after, PAGE_SIZE)?;
+        let Some(last) = page.last() else { break };
+        after = last.clone();
+
+        for hash in page {
+            let status = verify_chunk(
+                db,
+                storage_providers,
+                key_provider,
+                &mut keys,
+                &mut limiter,
+                &hash,
+            )
+            .await?;
+            let corrupt = matches!(status, ChunkStatus::Missing | ChunkStatus::Mismatch);
+            if corrupt {
+                eprintln!("ERROR: chunk {hash} is corrupt ({})", status.as_str());
+            }
+            db.record_gc_verify(&hash, status.as_str(), fix && corrupt)?;
+        }
+    }
+
+    let (total, by_status, repair_queued) = db.gc_verify_counts()?;
+    let count = |status: ChunkStatus| by_status.get(status.as_str()).copied().unwrap_or(0);
+    let report = VerifyReport {
+        total_chunks: total,
+        verified_ok: count(ChunkStatus::Ok),
+        missing_from_storage: count(ChunkStatus::Missing),
+        hash_mismatch: count(ChunkStatus::Mismatch),
+        archived: count(ChunkStatus::Archived),
+        repair_queued,
+    };
+    db.clear_gc_verify_progress()?;
+    Ok(report)
+}
+
+/// Try each location of a chunk until one decrypts to the recorded hash.
+/// Every download waits for the rate limiter. Locations on an archive-tier
+/// provider get a restore request first; one that cannot be downloaded yet
+/// makes the chunk [`ChunkStatus::Archived`] rather than missing.
+async fn verify_chunk(
+    db: &ManifestDb,
+    storage_providers: &HashMap<i64, Box<dyn StorageProvider>>,
+    key_provider: &dyn KeyProvider,
+    keys: &mut HashMap<String, KeyMaterial>,
+    limiter: &mut Interval,
+    hash: &str,
+) -> Result<ChunkStatus> {
+    let Some((nonce, key_id, locations, _size_enc, size_compressed)) =
+        db.get_chunk_locations(hash)?
+    else {
+        return Ok(ChunkStatus::Missing);
+    };
+    if !keys.contains_key(&key_id) {
+        let managed = key_provider.get_key_by_id(&key_id).await?;
+        keys.insert(
+            key_id.clone(),
+            KeyMaterial {
+                id: managed.id.clone(),
+                key: managed.key,
+            },
+        );
+    }
+    let key_material = &keys[&key_id];
+    let nonce: [u8; 12] = nonce
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("Invalid nonce for chunk {hash}"))?;
+    let hash_arr: [u8; 32] = hex::decode(hash)
+        .map_err(|e| anyhow::anyhow!("hex decode error: {e}"))?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("Invalid hash length"))?;
+
+    let mut status = ChunkStatus::Missing;
+    for (pid, skey) in &locations {
+        let Some(provider) = storage_providers.get(pid) else {
+            continue;
+        };
+        let archived = provider.storage_class().is_some_and(is_archive_class);
+        if archived && let Err(e) = provider.restore_from_archive(skey).await {
+            eprintln!("WARN: provider {pid} could not restore archived chunk {hash}: {e}");
+            continue;
+        }
+        limiter.tick().await;
+        let ciphertext = match provider.download_chunk(skey).await {
+            Ok(data) => data,
+            Err(_) if archived => {
+                if status == ChunkStatus::Missing {
+                    status = ChunkStatus::Archived;
+                }
+                continue;
+            }
+            Err(e) => {
+                eprintln!("WARN: provider {pid} failed for chunk {hash}: {e}");
+                continue;
+            }
+        };
+        let encrypted = EncryptedChunk {
+            hash: ChunkHash(hash_arr),
+            nonce,
+            ciphertext,
+            key_id: key_material.id.clone(),
+        };
+        let plaintext = decrypt_chunk(&encrypted, key_material).and_then(|decrypted| {
+            if size_compressed.is_some() {
+                enigma_core::compression::decompress_chunk(&decrypted)
+            } else {
+                Ok(decrypted)
+            }
+        });
+        match plaintext {
+            Ok(data) if compute_hash(&data).to_hex() == hash => return Ok(ChunkStatus::Ok),
+            _ => status = ChunkStatus::Mismatch,
+        }
+    }
+    Ok(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::render;
+
+    #[test]
+    fn gc_json_dry_run() {
+        let deletions = vec![("ab".repeat(32), 1, "chunks/abab".to_string())];
+        let report = GcReport {
+            total_chunks: 5,
+            orphan_chunks: 1,
+            orphan_replicas: 0,
+            dry_run: true,
+            deletions: &deletions,
+            deleted: 0,
+            errors: 0,
+            verify: None,
+        };
+        let doc = render("gc", report.to_json());
+        assert_eq!(doc["version"], 1);
+        assert_eq!(doc["command"], "gc");
+        assert_eq!(doc["dry_run"], true);
+        assert_eq!(doc["total_chunks"], 5);
+        assert_eq!(doc["deletions"][0]["provider_id"], 1);
+        assert_eq!(doc["deletions"][0]["storage_key"], "chunks/abab");
+        assert_eq!(doc["deleted"], 0);
+        assert!(doc.get("verify").is_none());
+    }
+
+    #[test]
+    fn gc_json_verify() {
+        let report = GcReport {
+            total_chunks: 4,
+            orphan_chunks: 0,
+            orphan_replicas: 0,
+            dry_run: false,
+            deletions: &[],
+            deleted: 0,
+            errors: 0,
+            verify: Some(VerifyReport {
+                total_chunks: 4,
+                verified_ok: 2,
+                missing_from_storage: 1,
+                hash_mismatch: 1,
+                archived: 0,
+                repair_queued: 2,
+            }),
+        };
+        let doc = render("gc", report.to_json());
+        assert_eq!(doc["verify"]["total_chunks"], 4);
+        assert_eq!(doc["verify"]["verified_ok"], 2);
+        assert_eq!(doc["verify"]["missing_from_storage"], 1);
+        assert_eq!(doc["verify"]["hash_mismatch"], 1);
+        assert_eq!(doc["verify"]["archived"], 0);
+        assert_eq!(doc["verify"]["repair_queued"], 2);
+    }
+}
@@ -0,0 +1,93 @@
+use anyhow::{Context, Result};
+use serde_json::{Value, json};
+use std::path::Path;
+
+use enigma_core::config::EnigmaConfig;
+use enigma_core::manifest::{ImportStats, ManifestDb};
+
+use super::export::{key_provider, open, sealed_key_id};
+use crate::output::JsonPrinter;
+
+pub async fn run(
+    base_dir: &Path,
+    cli_passphrase: &Option<String>,
+    input: &Path,
+    merge: bool,
+    json: bool,
+) -> Result<()> {
+    let config_path = EnigmaConfig::default_path(base_dir);
+    let config = EnigmaConfig::load(&config_path)?;
+    let db = ManifestDb::open(Path::new(&config.enigma.db_path))?;
+
+    let sealed =
+        std::fs::read(input).with_context(|| format!("Failed to read {}", input.display()))?;
+    let key_id = sealed_key_id(&sealed)?;
+    let key = key_provider(&config, cli_passphrase)
+        .await?
+        .get_key_by_id(key_id)
+        .await?;
+    let plaintext = open(&sealed, &key.key)?;
+
+    let stats = if merge {
+        db.import_from_json(&mut plaintext.as_slice())?
+    } else {
+        db.replace_from_json(&mut plaintext.as_slice())?
+    };
+
+    let report = ImportReport { merge, stats };
+    if json {
+        return JsonPrinter::stdout().print("import", report.to_json());
+    }
+    if merge {
+        println!(
+            "Merged {}: {} records imported, {} already present",
+            input.display(),
+            stats.inserted,
+            stats.skipped
+        );
+    } else {
+        println!(
+            "Replaced manifest with {}: {} records imported",
+            input.display(),
+            stats.inserted
+        );
+    }
+    Ok(())
+}
+
+struct ImportReport {
+    merge: bool,
+    stats: ImportStats,
+}
+
+impl ImportReport {
+    fn to_json(&self) -> Value {
+        json!({
+            "merge": self.merge,
+            "inserted": self.stats.inserted,
+            "skipped": self.stats.skipped,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::render;
+
+    #[test]
+    fn import_json() {
+        let report = ImportReport {
+            merge: true,
+            stats: ImportStats {
+                inserted: 7,
+                skipped: 3,
+            },
+        };
+        let doc = render("import", report.to_json());
+        assert_eq!(doc["command"], "import");
+        assert_eq!(doc["merge"], true);
+        assert_eq!(doc["inserted"], 7);
+        assert_eq!(doc["skipped"], 3);
+    }
+}
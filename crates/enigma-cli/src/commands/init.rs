@@ -1,10 +1,35 @@
 use anyhow::Result;
 use std::path::Path;
 
-use enigma_core::config::EnigmaConfig;
+use enigma_core::config::{Argon2Config, EnigmaConfig};
 use enigma_core::manifest::ManifestDb;
 
-pub async fn run(base_dir: &Path, cli_passphrase: &Option<String>) -> Result<()> {
+/// Argon2id overrides given on the command line.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Argon2Flags {
+    pub memory_kib: Option<u32>,
+    pub iterations: Option<u32>,
+    pub parallelism: Option<u32>,
+}
+
+impl Argon2Flags {
+    fn is_set(&self) -> bool {
+        self.memory_kib.is_some() || self.iterations.is_some() || self.parallelism.is_some()
+    }
+
+    /// Override the fields of `config` given on the command line.
+    fn apply(&self, config: &mut Argon2Config) {
+        config.memory_kib = self.memory_kib.unwrap_or(config.memory_kib);
+        config.iterations = self.iterations.unwrap_or(config.iterations);
+        config.parallelism = self.parallelism.unwrap_or(config.parallelism);
+    }
+}
+
+pub async fn run(
+    base_dir: &Path,
+    cli_passphrase: &Option<String>,
+    argon2_flags: Argon2Flags,
+) -> Result<()> {
     println!("Initializing Enigma in {}", base_dir.display());
 
     // Create base directory
@@ -21,7 +46,15 @@ pub async fn run(base_dir: &Path, cli_passphrase: &Option<String>) -> Result<()>
     }
 
     // Load config to get DB path
-    let config = EnigmaConfig::load(&config_path)?;
+    let mut config = EnigmaConfig::load(&config_path)?;
+
+    // Argon2id flags are saved so the config documents how the keyfile was made
+    if argon2_flags.is_set() {
+        argon2_flags.apply(&mut config.enigma.argon2);
+        config.check()?;
+        config.save(&config_path)?;
+        println!("Saved Argon2id parameters to {}", config_path.display());
+    }
 
     // Initialize SQLite database
     let db_path = Path::new(&config.enigma.db_path);
@@ -33,16 +66,28 @@ pub async fn run(base_dir: &Path, cli_passphrase: &Option<String>) -> Result<()>
         let keyfile_path = Path::new(&config.enigma.keyfile_path);
         if keyfile_path.exists() {
             println!("Keyfile already exists: {}", keyfile_path.display());
+            if argon2_flags.is_set() {
+                println!("  It keeps the Argon2id parameters it was created with.");
+            }
         } else {
+            let argon2 = config.enigma.argon2;
+            println!(
+                "Argon2id: {} KiB memory, {} passes, {} lanes (minimum: {} KiB, {} passes)",
+                argon2.memory_kib,
+                argon2.iterations,
+                argon2.parallelism,
+                Argon2Config::MIN_MEMORY_KIB,
+                Argon2Config::MIN_ITERATIONS
+            );
             let passphrase = crate::get_passphrase(cli_passphrase)?;
             enigma_keys::factory::create_key_provider(
                 "local",
-                Some(passphrase.as_bytes()),
-                &config.enigma.keyfile_path,
-                None,
-                None,
-                None,
-                None,
+                &enigma_keys::factory::KeyProviderOptions {
+                    passphrase: Some(passphrase.as_bytes()),
+                    keyfile_path: &config.enigma.keyfile_path,
+                    argon2: crate::argon2_params(&argon2),
+                    ..Default::default()
+                },
             )
             .await?;
             println!("Created keyfile: {}", keyfile_path.display());
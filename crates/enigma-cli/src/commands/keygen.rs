@@ -0,0 +1,88 @@
+use anyhow::Result;
+use serde_json::json;
+use std::path::{Path, PathBuf};
+
+use enigma_core::crypto::signing;
+
+use crate::output::JsonPrinter;
+
+/// Generate an Ed25519 keypair for signing backup records. The base64
+/// keypair goes to `output` (mode 0600 on Unix), the public key to
+/// `output.pub`. Existing files are never overwritten.
+pub fn run(output: &Path, json: bool) -> Result<()> {
+    let public_path = public_key_path(output);
+    for path in [output, public_path.as_path()] {
+        if path.exists() {
+            anyhow::bail!("{} already exists", path.display());
+        }
+    }
+
+    let keypair = signing::generate_keypair();
+    let signing_key = signing::encode_key(&keypair);
+    let verify_key = signing::encode_key(&signing::public_key(&keypair)?);
+
+    std::fs::write(output, format!("{signing_key}\n"))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(output, std::fs::Permissions::from_mode(0o600))?;
+    }
+    std::fs::write(&public_path, format!("{verify_key}\n"))?;
+
+    if json {
+        return JsonPrinter::stdout().print(
+            "keygen",
+            json!({
+                "signing_key_path": output.display().to_string(),
+                "verify_key_path": public_path.display().to_string(),
+                "verify_key": verify_key,
+            }),
+        );
+    }
+
+    println!("Signing key: {}", output.display());
+    println!("Verify key:  {}", public_path.display());
+    println!("\nTo sign new backups and check signatures, add to [enigma] in enigma.toml:");
+    println!(
+        "  backup_signing_key = \"<contents of {}>\"",
+        output.display()
+    );
+    println!("  backup_verify_key = \"{verify_key}\"");
+    Ok(())
+}
+
+/// `output` with `.pub` appended to its file name.
+fn public_key_path(output: &Path) -> PathBuf {
+    let mut name = output.as_os_str().to_owned();
+    name.push(".pub");
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_matching_keypair_and_refuses_overwrite() {
+        let tmp = tempfile::tempdir().unwrap();
+        let output = tmp.path().join("backup-signing");
+        run(&output, true).unwrap();
+
+        let keypair =
+            signing::decode_signing_key(&std::fs::read_to_string(&output).unwrap()).unwrap();
+        let public = signing::decode_verify_key(
+            &std::fs::read_to_string(tmp.path().join("backup-signing.pub")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(signing::public_key(&keypair).unwrap(), public);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&output).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        assert!(run(&output, true).is_err());
+    }
+}
@@ -1,15 +1,34 @@
 use anyhow::Result;
+use serde_json::{Value, json};
 use std::path::Path;
 
 use enigma_core::config::EnigmaConfig;
 use enigma_core::manifest::ManifestDb;
+use enigma_core::types::BackupRecord;
 
-pub fn run(base_dir: &Path) -> Result<()> {
+use crate::output::JsonPrinter;
+
+pub fn run(base_dir: &Path, filter_tags: &[(String, String)], json: bool) -> Result<()> {
     let config_path = EnigmaConfig::default_path(base_dir);
     let config = EnigmaConfig::load(&config_path)?;
     let db = ManifestDb::open(Path::new(&config.enigma.db_path))?;
 
-    let backups = db.list_backups()?;
+    let backups = match filter_tags.split_first() {
+        None => db.list_backups()?,
+        Some(((key, value), rest)) => {
+            // Backups must carry every requested tag.
+            let mut backups = db.list_backups_with_tag(key, value)?;
+            for (key, value) in rest {
+                let matching = db.list_backups_with_tag(key, value)?;
+                backups.retain(|b| matching.iter().any(|m| m.id == b.id));
+            }
+            backups
+        }
+    };
+
+    if json {
+        return JsonPrinter::stdout().print("list", backups_json(&db, &backups)?);
+    }
 
     if backups.is_empty() {
         println!("No backups found.");
@@ -17,26 +36,57 @@ pub fn run(base_dir: &Path) -> Result<()> {
     }
 
     println!(
-        "{:<38} {:<12} {:<8} {:>12} {:>8} CREATED",
-        "ID", "STATUS", "FILES", "SIZE", "CHUNKS"
+        "{:<38} {:<12} {:<8} {:>12} {:>8} {:<20} TAGS",
+        "ID", "STATUS", "FILES", "SIZE", "CHUNKS", "CREATED"
     );
-    println!("{}", "-".repeat(100));
+    println!("{}", "-".repeat(120));
 
     for b in &backups {
+        let mut tags: Vec<String> = db
+            .get_backup_tags(&b.id)?
+            .into_iter()
+            .map(|(k, v)| format!("{k}={v}"))
+            .collect();
+        tags.sort();
         println!(
-            "{:<38} {:<12} {:<8} {:>12} {:>8} {}",
+            "{:<38} {:<12} {:<8} {:>12} {:>8} {:<20} {}",
             b.id,
             b.status,
             b.total_files,
             format_bytes(b.total_bytes),
             b.total_chunks,
             b.created_at,
+            tags.join(","),
         );
     }
 
     Ok(())
 }
 
+fn backups_json(db: &ManifestDb, backups: &[BackupRecord]) -> Result<Value> {
+    let mut entries = Vec::with_capacity(backups.len());
+    for b in backups {
+        let tags: serde_json::Map<String, Value> = db
+            .get_backup_tags(&b.id)?
+            .into_iter()
+            .map(|(k, v)| (k, Value::String(v)))
+            .collect();
+        entries.push(json!({
+            "id": b.id,
+            "source_path": b.source_path,
+            "status": b.status.to_string(),
+            "total_files": b.total_files,
+            "total_bytes": b.total_bytes,
+            "total_chunks": b.total_chunks,
+            "dedup_chunks": b.dedup_chunks,
+            "created_at": b.created_at,
+            "completed_at": b.completed_at,
+            "tags": tags,
+        }));
+    }
+    Ok(json!({ "backups": entries }))
+}
+
 fn format_bytes(bytes: u64) -> String {
     const KB: u64 = 1024;
     const MB: u64 = KB * 1024;
@@ -52,3 +102,36 @@ fn format_bytes(bytes: u64) -> String {
         format!("{bytes} B")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::render;
+
+    #[test]
+    fn list_json_includes_backups_and_tags() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        db.create_backup("b1", "/data").unwrap();
+        db.set_backup_tag("b1", "env", "prod").unwrap();
+        db.complete_backup("b1", 2, 100, 3, 1).unwrap();
+
+        let backups = db.list_backups().unwrap();
+        let doc = render("list", backups_json(&db, &backups).unwrap());
+
+        assert_eq!(doc["version"], 1);
+        assert_eq!(doc["command"], "list");
+        let entry = &doc["backups"][0];
+        assert_eq!(entry["id"], "b1");
+        assert_eq!(entry["status"], "completed");
+        assert_eq!(entry["total_files"], 2);
+        assert_eq!(entry["total_bytes"], 100);
+        assert_eq!(entry["tags"]["env"], "prod");
+    }
+
+    #[test]
+    fn list_json_empty() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let doc = render("list", backups_json(&db, &[]).unwrap());
+        assert_eq!(doc["backups"], json!([]));
+    }
+}
@@ -0,0 +1,99 @@
+use anyhow::{Result, anyhow};
+use serde_json::{Value, json};
+use std::path::Path;
+
+use enigma_core::config::EnigmaConfig;
+use enigma_core::manifest::ManifestDb;
+use enigma_storage::migrate::{LAYOUTS, MigrationStats, layout, migrate_chunk_layout};
+
+use super::providers::init_providers;
+use crate::output::JsonPrinter;
+
+pub async fn run(base_dir: &Path, scheme: &str, dry_run: bool, json: bool) -> Result<()> {
+    let new_key_fn = layout(scheme).ok_or_else(|| anyhow!("unknown layout '{scheme}'"))?;
+
+    let config_path = EnigmaConfig::default_path(base_dir);
+    let config = EnigmaConfig::load(&config_path)?;
+    let db = ManifestDb::open(Path::new(&config.enigma.db_path))?;
+    let storage_providers = init_providers(&config.providers, &db).await?;
+
+    // Chunks are moved from the other known layout
+    let (old_scheme, old_key_fn) = LAYOUTS
+        .iter()
+        .find(|(name, _)| *name != scheme)
+        .ok_or_else(|| anyhow!("no layout to migrate from"))?;
+    if !json {
+        if dry_run {
+            println!("Dry run — counting chunks to move from {old_scheme} to {scheme}...");
+        } else {
+            println!("Moving chunks from {old_scheme} to {scheme}...");
+        }
+    }
+    let stats =
+        migrate_chunk_layout(&db, &storage_providers, *old_key_fn, new_key_fn, dry_run).await?;
+
+    let report = MigrateLayoutReport {
+        scheme,
+        dry_run,
+        stats,
+    };
+    if json {
+        return JsonPrinter::stdout().print("migrate-layout", report.to_json());
+    }
+
+    let verb = if dry_run { "would move" } else { "moved" };
+    println!(
+        "\nLayout migration: {} chunks ({} copies) {verb}, {} already on {scheme}, {} failed",
+        report.stats.migrated, report.stats.copies_moved, report.stats.skipped, report.stats.failed
+    );
+    if report.stats.failed > 0 && !dry_run {
+        println!("Run `enigma migrate-layout {scheme}` again to retry the failed chunks.");
+    }
+    Ok(())
+}
+
+struct MigrateLayoutReport<'a> {
+    scheme: &'a str,
+    dry_run: bool,
+    stats: MigrationStats,
+}
+
+impl MigrateLayoutReport<'_> {
+    fn to_json(&self) -> Value {
+        json!({
+            "scheme": self.scheme,
+            "dry_run": self.dry_run,
+            "migrated": self.stats.migrated,
+            "copies_moved": self.stats.copies_moved,
+            "skipped": self.stats.skipped,
+            "failed": self.stats.failed,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::render;
+
+    #[test]
+    fn migrate_layout_json() {
+        let report = MigrateLayoutReport {
+            scheme: "three-level",
+            dry_run: true,
+            stats: MigrationStats {
+                migrated: 5,
+                copies_moved: 10,
+                skipped: 2,
+                failed: 0,
+            },
+        };
+        let doc = render("migrate-layout", report.to_json());
+        assert_eq!(doc["command"], "migrate-layout");
+        assert_eq!(doc["scheme"], "three-level");
+        assert_eq!(doc["dry_run"], true);
+        assert_eq!(doc["migrated"], 5);
+        assert_eq!(doc["copies_moved"], 10);
+        assert_eq!(doc["skipped"], 2);
+    }
+}
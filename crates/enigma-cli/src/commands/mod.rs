@@ -1,10 +1,20 @@
 pub mod backup;
 pub mod config;
 pub mod encrypt_cred;
+pub mod exclude;
+pub mod export;
 pub mod gc;
+pub mod import;
 pub mod init;
+pub mod keygen;
 pub mod list;
+pub mod migrate_layout;
+#[cfg(feature = "fuse")]
+pub mod mount;
 pub mod providers;
+pub mod reencrypt;
+pub mod repair;
 pub mod restore;
 pub mod status;
 pub mod verify;
+pub mod watch;
@@ -0,0 +1,639 @@
+//! Read-only FUSE view of a backup (`enigma mount`, feature `fuse`).
+//!
+//! The directory tree is built from the manifest at mount time, so
+//! `lookup`/`readdir`/`getattr` never touch a provider. File contents are
+//! downloaded and decrypted chunk by chunk as they are read.
+
+use anyhow::Result;
+use fuser::{
+    FileAttr, FileType, Filesystem, MountOption, ReplyAttr, ReplyData, ReplyDirectory, ReplyEntry,
+    ReplyOpen, Request,
+};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::ffi::OsStr;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::runtime::Handle;
+
+use enigma_core::config::EnigmaConfig;
+use enigma_core::manifest::ManifestDb;
+use enigma_keys::provider::KeyProvider;
+use enigma_storage::provider::StorageProvider;
+
+use super::providers::init_providers;
+use super::restore::fetch_chunk;
+
+/// The backup never changes while mounted, so the kernel may cache freely.
+const TTL: Duration = Duration::from_secs(3600);
+const ROOT_INO: u64 = fuser::FUSE_ROOT_ID;
+/// Decrypted chunks kept in memory (up to 64 MB with the default 4 MB chunks).
+const CHUNK_CACHE_SIZE: usize = 16;
+const BLOCK_SIZE: u32 = 4096;
+
+pub async fn run(
+    backup_id: &str,
+    mountpoint: &Path,
+    base_dir: &Path,
+    cli_passphrase: &Option<String>,
+) -> Result<()> {
+    let config_path = EnigmaConfig::default_path(base_dir);
+    let config = EnigmaConfig::load(&config_path)?;
+    let db = ManifestDb::open(Path::new(&config.enigma.db_path))?;
+    let backup = db.get_backup(backup_id)?;
+
+    let passphrase = if config.enigma.needs_passphrase() {
+        Some(crate::get_passphrase(cli_passphrase)?)
+    } else {
+        None
+    };
+    let key_provider = enigma_keys::factory::create_key_provider(
+        &config.enigma.key_provider,
+        &crate::key_provider_options(&config.enigma, passphrase.as_deref()),
+    )
+    .await?;
+    let storage_providers = init_providers(&config.providers, &db).await?;
+
+    let fs = BackupFs::new(
+        db,
+        storage_providers,
+        key_provider,
+        backup_id,
+        config.enigma.verify_on_read,
+        config.enigma.verify_hmac,
+        mountpoint,
+    )?;
+    let session = fuser::spawn_mount2(fs, mountpoint, &mount_options())?;
+    println!(
+        "Mounted backup {backup_id} ({} files) at {}",
+        backup.total_files,
+        mountpoint.display()
+    );
+    println!(
+        "Press Ctrl-C or run `umount {}` to unmount.",
+        mountpoint.display()
+    );
+
+    tokio::select! {
+        _ = super::watch::shutdown_signal() => {}
+        _ = wait_for_unmount(&session) => {}
+    }
+    // Dropping the session unmounts if it is still mounted
+    drop(session);
+    println!("Unmounted {}", mountpoint.display());
+    Ok(())
+}
+
+fn mount_options() -> Vec<MountOption> {
+    vec![
+        MountOption::RO,
+        MountOption::FSName("enigma".to_string()),
+        MountOption::Subtype("enigma".to_string()),
+        MountOption::DefaultPermissions,
+    ]
+}
+
+/// Resolve once the session thread exits, i.e. after an external `umount`.
+async fn wait_for_unmount(session: &fuser::BackgroundSession) {
+    while !session.guard.is_finished() {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+}
+
+// ── Directory tree ─────────────────────────────────────────
+
+enum NodeKind {
+    /// Children by name.
+    Dir(BTreeMap<String, u64>),
+    File {
+        file_id: i64,
+        size: u64,
+    },
+}
+
+struct Node {
+    parent: u64,
+    mtime: SystemTime,
+    kind: NodeKind,
+}
+
+/// Inode number of `path` (relative to the backup root): the first 8 bytes
+/// of its SHA-256, kept clear of the reserved 0 and root inodes.
+fn inode_for(path: &str) -> u64 {
+    let digest = Sha256::digest(path.as_bytes());
+    let ino = u64::from_le_bytes(digest[..8].try_into().expect("8 bytes"));
+    ino.max(ROOT_INO + 1)
+}
+
+/// Inode table of a backup, built from `list_backup_files`.
+struct Tree {
+    nodes: HashMap<u64, Node>,
+}
+
+impl Tree {
+    fn build(files: &[(i64, String, u64, String)], mtimes: &HashMap<i64, String>) -> Result<Self> {
+        let mut nodes = HashMap::new();
+        nodes.insert(
+            ROOT_INO,
+            Node {
+                parent: ROOT_INO,
+                mtime: UNIX_EPOCH,
+                kind: NodeKind::Dir(BTreeMap::new()),
+            },
+        );
+        let mut tree = Self { nodes };
+        // Detects the (astronomically unlikely) inode hash collision
+        let mut paths: HashMap<u64, String> = HashMap::new();
+
+        for (file_id, path, size, _hash) in files {
+            let mtime = mtimes
+                .get(file_id)
+                .and_then(|s| s.parse::<u64>().ok())
+                .map_or(UNIX_EPOCH, |secs| UNIX_EPOCH + Duration::from_secs(secs));
+            let components: Vec<&str> = path.split('/').filter(|c| !c.is_empty()).collect();
+            let Some((file_name, dirs)) = components.split_last() else {
+                continue;
+            };
+
+            let mut parent = ROOT_INO;
+            let mut ancestors = vec![ROOT_INO];
+            for (depth, name) in dirs.iter().enumerate() {
+                let dir_path = components[..=depth].join("/");
+                parent = tree.insert(&mut paths, parent, name, &dir_path, || Node {
+                    parent,
+                    mtime: UNIX_EPOCH,
+                    kind: NodeKind::Dir(BTreeMap::new()),
+                })?;
+                ancestors.push(parent);
+            }
+            tree.insert(&mut paths, parent, file_name, &components.join("/"), || {
+                Node {
+                    parent,
+                    mtime,
+                    kind: NodeKind::File {
+                        file_id: *file_id,
+                        size: *size,
+                    },
+                }
+            })?;
+
+            // A directory shows the newest modification time below it
+            for ino in ancestors {
+                let dir = tree.nodes.get_mut(&ino).expect("ancestor exists");
+                dir.mtime = dir.mtime.max(mtime);
+            }
+        }
+
+        Ok(tree)
+    }
+
+    /// Add `name` under `parent` unless present, returning its inode.
+    fn insert(
+        &mut self,
+        paths: &mut HashMap<u64, String>,
+        parent: u64,
+        name: &str,
+        path: &str,
+        node: impl FnOnce() -> Node,
+    ) -> Result<u64> {
+        let ino = inode_for(path);
+        match paths.get(&ino) {
+            Some(existing) if existing == path => return Ok(ino),
+            Some(existing) => {
+                anyhow::bail!("inode collision between {existing} and {path}")
+            }
+            None => {}
+        }
+        let Some(NodeKind::Dir(children)) = self.nodes.get_mut(&parent).map(|n| &mut n.kind) else {
+            anyhow::bail!("{path} is both a file and a directory in this backup");
+        };
+        children.insert(name.to_string(), ino);
+        paths.insert(ino, path.to_string());
+        self.nodes.insert(ino, node());
+        Ok(ino)
+    }
+
+    fn lookup(&self, parent: u64, name: &str) -> Option<u64> {
+        match &self.nodes.get(&parent)?.kind {
+            NodeKind::Dir(children) => children.get(name).copied(),
+            NodeKind::File { .. } => None,
+        }
+    }
+}
+
+// ── Chunk cache ────────────────────────────────────────────
+
+/// Decrypted chunks by hash, least recently used evicted first.
+struct ChunkCache {
+    capacity: usize,
+    entries: VecDeque<(String, Arc<Vec<u8>>)>,
+}
+
+impl ChunkCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    fn get(&mut self, hash: &str) -> Option<Arc<Vec<u8>>> {
+        let pos = self.entries.iter().position(|(h, _)| h == hash)?;
+        let entry = self.entries.remove(pos)?;
+        let data = entry.1.clone();
+        self.entries.push_back(entry);
+        Some(data)
+    }
+
+    fn insert(&mut self, hash: String, data: Arc<Vec<u8>>) {
+        if self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((hash, data));
+    }
+}
+
+// ── Filesystem ─────────────────────────────────────────────
+
+/// A chunk of a file and the byte range it covers.
+struct ChunkSpan {
+    hash: String,
+    start: u64,
+    end: u64,
+}
+
+/// Read-only [`Filesystem`] serving one backup.
+pub struct BackupFs {
+    tree: Tree,
+    db: ManifestDb,
+    providers: HashMap<i64, Box<dyn StorageProvider>>,
+    key_provider: Box<dyn KeyProvider>,
+    verify_on_read: bool,
+    verify_hmac: bool,
+    /// FUSE callbacks are synchronous; downloads run on this runtime.
+    runtime: Handle,
+    cache: ChunkCache,
+    spans: HashMap<u64, Arc<[ChunkSpan]>>,
+    uid: u32,
+    gid: u32,
+}
+
+impl BackupFs {
+    /// Build the view of `backup_id`. Files are owned by the owner of
+    /// `mountpoint`. Must be called from within a Tokio runtime.
+    pub fn new(
+        db: ManifestDb,
+        providers: HashMap<i64, Box<dyn StorageProvider>>,
+        key_provider: Box<dyn KeyProvider>,
+        backup_id: &str,
+        verify_on_read: bool,
+        verify_hmac: bool,
+        mountpoint: &Path,
+    ) -> Result<Self> {
+        let files = db.list_backup_files(backup_id)?;
+        let mtimes = db.list_backup_file_mtimes(backup_id)?;
+        let tree = Tree::build(&files, &mtimes)?;
+        let owner = std::fs::metadata(mountpoint)?;
+
+        Ok(Self {
+            tree,
+            db,
+            providers,
+            key_provider,
+            verify_on_read,
+            verify_hmac,
+            runtime: Handle::current(),
+            cache: ChunkCache::new(CHUNK_CACHE_SIZE),
+            spans: HashMap::new(),
+            uid: owner.uid(),
+            gid: owner.gid(),
+        })
+    }
+
+    fn attr(&self, ino: u64) -> Option<FileAttr> {
+        let node = self.tree.nodes.get(&ino)?;
+        let (kind, size, perm, nlink) = match &node.kind {
+            NodeKind::Dir(_) => (FileType::Directory, 0, 0o555, 2),
+            NodeKind::File { size, .. } => (FileType::RegularFile, *size, 0o444, 1),
+        };
+        Some(FileAttr {
+            ino,
+            size,
+            blocks: size.div_ceil(512),
+            atime: node.mtime,
+            mtime: node.mtime,
+            ctime: node.mtime,
+            crtime: node.mtime,
+            kind,
+            perm,
+            nlink,
+            uid: self.uid,
+            gid: self.gid,
+            rdev: 0,
+            blksize: BLOCK_SIZE,
+            flags: 0,
+        })
+    }
+
+    /// Chunk layout of a file, loaded from the manifest on first read.
+    fn chunk_spans(&mut self, ino: u64, file_id: i64, size: u64) -> Result<Arc<[ChunkSpan]>> {
+        if let Some(spans) = self.spans.get(&ino) {
+            return Ok(spans.clone());
+        }
+        let chunks = self.db.get_file_chunks(file_id)?;
+        let spans: Arc<[ChunkSpan]> = chunks
+            .iter()
+            .enumerate()
+            .map(|(i, (hash, _index, offset))| ChunkSpan {
+                hash: hash.clone(),
+                start: *offset,
+                end: chunks.get(i + 1).map_or(size, |next| next.2),
+            })
+            .collect();
+        self.spans.insert(ino, spans.clone());
+        Ok(spans)
+    }
+
+    fn chunk(&mut self, hash: &str) -> Result<Arc<Vec<u8>>> {
+        if let Some(data) = self.cache.get(hash) {
+            return Ok(data);
+        }
+        let data = Arc::new(self.runtime.block_on(fetch_chunk(
+            &self.db,
+            &self.providers,
+            self.key_provider.as_ref(),
+            hash,
+            self.verify_on_read,
+            self.verify_hmac,
+        ))?);
+        self.cache.insert(hash.to_string(), data.clone());
+        Ok(data)
+    }
+
+    /// Read up to `size` bytes of file `ino` starting at `offset`.
+    fn read_range(&mut self, ino: u64, offset: u64, size: u32) -> Result<Vec<u8>> {
+        let Some(NodeKind::File {
+            file_id,
+            size: file_size,
+        }) = self.tree.nodes.get(&ino).map(|n| &n.kind)
+        else {
+            anyhow::bail!("inode {ino} is not a file");
+        };
+        let (file_id, file_size) = (*file_id, *file_size);
+        let end = offset.saturating_add(size as u64).min(file_size);
+        if offset >= end {
+            return Ok(Vec::new());
+        }
+
+        let spans = self.chunk_spans(ino, file_id, file_size)?;
+        let mut out = Vec::with_capacity((end - offset) as usize);
+        for span in spans.iter().filter(|s| s.end > offset && s.start < end) {
+            let data = self.chunk(&span.hash)?;
+            let from = (offset.max(span.start) - span.start) as usize;
+            let to = (end.min(span.end) - span.start) as usize;
+            let slice = data.get(from..to).ok_or_else(|| {
+                anyhow::anyhow!("chunk {} is shorter than the manifest says", span.hash)
+            })?;
+            out.extend_from_slice(slice);
+        }
+        Ok(out)
+    }
+}
+
+impl Filesystem for BackupFs {
+    fn lookup(&mut self, _req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEntry) {
+        let attr = name
+            .to_str()
+            .and_then(|name| self.tree.lookup(parent, name))
+            .and_then(|ino| self.attr(ino));
+        match attr {
+            Some(attr) => reply.entry(&TTL, &attr, 0),
+            None => reply.error(libc::ENOENT),
+        }
+    }
+
+    fn getattr(&mut self, _req: &Request<'_>, ino: u64, _fh: Option<u64>, reply: ReplyAttr) {
+        match self.attr(ino) {
+            Some(attr) => reply.attr(&TTL, &attr),
+            None => reply.error(libc::ENOENT),
+        }
+    }
+
+    fn open(&mut self, _req: &Request<'_>, ino: u64, flags: i32, reply: ReplyOpen) {
+        if !self.tree.nodes.contains_key(&ino) {
+            reply.error(libc::ENOENT);
+        } else if flags & libc::O_ACCMODE != libc::O_RDONLY {
+            reply.error(libc::EROFS);
+        } else {
+            reply.opened(0, 0);
+        }
+    }
+
+    fn read(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        _fh: u64,
+        offset: i64,
+        size: u32,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        reply: ReplyData,
+    ) {
+        let Ok(offset) = u64::try_from(offset) else {
+            reply.error(libc::EINVAL);
+            return;
+        };
+        match self.read_range(ino, offset, size) {
+            Ok(data) => reply.data(&data),
+            Err(e) => {
+                tracing::error!("FUSE read of inode {ino} failed: {e:#}");
+                reply.error(libc::EIO);
+            }
+        }
+    }
+
+    fn readdir(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        _fh: u64,
+        offset: i64,
+        mut reply: ReplyDirectory,
+    ) {
+        let Some(node) = self.tree.nodes.get(&ino) else {
+            reply.error(libc::ENOENT);
+            return;
+        };
+        let NodeKind::Dir(children) = &node.kind else {
+            reply.error(libc::ENOTDIR);
+            return;
+        };
+
+        let entries = [
+            (ino, FileType::Directory, "."),
+            (node.parent, FileType::Directory, ".."),
+        ]
+        .into_iter()
+        .chain(children.iter().map(|(name, &child)| {
+            let kind = match self.tree.nodes[&child].kind {
+                NodeKind::Dir(_) => FileType::Directory,
+                NodeKind::File { .. } => FileType::RegularFile,
+            };
+            (child, kind, name.as_str())
+        }));
+        // `offset` is the position after the last entry the kernel received
+        for (i, (child, kind, name)) in entries.enumerate().skip(offset.max(0) as usize) {
+            if reply.add(child, (i + 1) as i64, kind, name) {
+                break;
+            }
+        }
+        reply.ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use enigma_core::config::ProviderConfig;
+    use enigma_core::types::ProviderType;
+
+    fn files(paths: &[(&str, u64)]) -> Vec<(i64, String, u64, String)> {
+        paths
+            .iter()
+            .enumerate()
+            .map(|(i, (path, size))| (i as i64 + 1, path.to_string(), *size, String::new()))
+            .collect()
+    }
+
+    #[test]
+    fn tree_nests_directories_and_propagates_mtime() {
+        let files = files(&[("a.txt", 3), ("docs/b.md", 5), ("docs/sub/c.bin", 7)]);
+        let mtimes = HashMap::from([(2, "100".to_string()), (3, "200".to_string())]);
+        let tree = Tree::build(&files, &mtimes).unwrap();
+
+        let docs = tree.lookup(ROOT_INO, "docs").unwrap();
+        let sub = tree.lookup(docs, "sub").unwrap();
+        let c = tree.lookup(sub, "c.bin").unwrap();
+        assert_eq!(c, inode_for("docs/sub/c.bin"));
+        assert!(matches!(
+            tree.nodes[&c].kind,
+            NodeKind::File { size: 7, .. }
+        ));
+        assert_eq!(tree.nodes[&c].parent, sub);
+        assert!(tree.lookup(c, "x").is_none());
+
+        let secs = |s| UNIX_EPOCH + Duration::from_secs(s);
+        assert_eq!(tree.nodes[&docs].mtime, secs(200));
+        assert_eq!(tree.nodes[&ROOT_INO].mtime, secs(200));
+        assert_eq!(
+            tree.nodes[&tree.lookup(ROOT_INO, "a.txt").unwrap()].mtime,
+            UNIX_EPOCH
+        );
+    }
+
+    #[test]
+    fn chunk_cache_evicts_least_recently_used() {
+        let mut cache = ChunkCache::new(2);
+        cache.insert("a".into(), Arc::new(vec![1]));
+        cache.insert("b".into(), Arc::new(vec![2]));
+        assert!(cache.get("a").is_some());
+        cache.insert("c".into(), Arc::new(vec![3]));
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("a").is_some());
+        assert!(cache.get("c").is_some());
+    }
+
+    /// Back up a directory through the real CLI path, mount it and read it
+    /// back. Skipped where FUSE is unavailable (e.g. most CI containers).
+    #[tokio::test(flavor = "multi_thread")]
+    async fn mount_lists_and_reads_backup() {
+        if !Path::new("/dev/fuse").exists() {
+            eprintln!("skipping: /dev/fuse not available");
+            return;
+        }
+        let tmp = tempfile::tempdir().unwrap();
+        let base = tmp.path().join("enigma");
+        let source = tmp.path().join("source");
+        let mountpoint = tmp.path().join("mnt");
+        std::fs::create_dir_all(source.join("docs")).unwrap();
+        std::fs::create_dir_all(&mountpoint).unwrap();
+        let big: Vec<u8> = (0..10_000_000u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(source.join("hello.txt"), b"hello fuse").unwrap();
+        std::fs::write(source.join("docs/big.bin"), &big).unwrap();
+
+        let passphrase = Some("test-passphrase".to_string());
+        super::super::init::run(&base, &passphrase, Default::default())
+            .await
+            .unwrap();
+        let config_path = EnigmaConfig::default_path(&base);
+        let mut config = EnigmaConfig::load(&config_path).unwrap();
+        config.providers.push(ProviderConfig {
+            name: "local".to_string(),
+            provider_type: ProviderType::Local,
+            bucket: tmp.path().join("store").display().to_string(),
+            region: None,
+            weight: 1,
+            endpoint_url: None,
+            path_style: None,
+            access_key: None,
+            secret_key: None,
+            credential_ref: None,
+            connect_timeout_ms: None,
+            request_timeout_ms: None,
+            idle_timeout_ms: None,
+            storage_class: None,
+            upload_bandwidth_bps: None,
+            download_bandwidth_bps: None,
+            gcs_signing_key_path: None,
+            credentials_file: None,
+            credential_refresh_interval_seconds: None,
+        });
+        config.save(&config_path).unwrap();
+        super::super::backup::run(&source, &base, &passphrase, &[], &[], true)
+            .await
+            .unwrap();
+
+        let db = ManifestDb::open(Path::new(&config.enigma.db_path)).unwrap();
+        let backup_id = db.list_backups().unwrap()[0].id.clone();
+        let key_provider = enigma_keys::factory::create_key_provider(
+            "local",
+            &crate::key_provider_options(&config.enigma, passphrase.as_deref()),
+        )
+        .await
+        .unwrap();
+        let providers = init_providers(&config.providers, &db).await.unwrap();
+        let fs = BackupFs::new(
+            db,
+            providers,
+            key_provider,
+            &backup_id,
+            true,
+            true,
+            &mountpoint,
+        )
+        .unwrap();
+        let session = fuser::spawn_mount2(fs, &mountpoint, &mount_options()).unwrap();
+
+        let mnt = mountpoint.clone();
+        let (names, hello, read_big) = tokio::task::spawn_blocking(move || {
+            let mut names: Vec<String> = std::fs::read_dir(&mnt)
+                .unwrap()
+                .map(|e| e.unwrap().file_name().into_string().unwrap())
+                .collect();
+            names.sort();
+            let hello = std::fs::read(mnt.join("hello.txt")).unwrap();
+            let big = std::fs::read(mnt.join("docs/big.bin")).unwrap();
+            (names, hello, big)
+        })
+        .await
+        .unwrap();
+        drop(session);
+
+        assert_eq!(names, vec!["docs", "hello.txt"]);
+        assert_eq!(hello, b"hello fuse");
+        assert_eq!(read_big, big);
+    }
+}
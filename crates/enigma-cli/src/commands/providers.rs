@@ -5,7 +5,7 @@ use enigma_core::config::ProviderConfig;
 use enigma_core::manifest::ManifestDb;
 use enigma_core::types::ProviderType;
 use enigma_storage::local::LocalStorageProvider;
-use enigma_storage::provider::StorageProvider;
+use enigma_storage::provider::{StorageProvider, TimeoutConfig};
 use enigma_storage::s3::S3StorageProvider;
 
 /// Initialize storage providers from config, register them in the DB, and test connections.
@@ -28,13 +28,22 @@ pub async fn init_providers(
             )?,
         };
 
+        let timeouts = TimeoutConfig::from(pc);
         let provider: Box<dyn StorageProvider> = match pc.provider_type {
             ProviderType::Local => {
+                if timeouts.is_set() {
+                    tracing::warn!(provider = %pc.name, "Local provider ignores timeouts");
+                }
+                if pc.storage_class.is_some() {
+                    tracing::warn!(provider = %pc.name, "Local provider ignores storage_class");
+                }
                 Box::new(LocalStorageProvider::new(Path::new(&pc.bucket), &pc.name)?)
             }
-            ProviderType::S3 => {
-                Box::new(S3StorageProvider::new(&pc.bucket, pc.region.as_deref(), &pc.name).await?)
-            }
+            ProviderType::S3 => Box::new(
+                S3StorageProvider::new(&pc.bucket, pc.region.as_deref(), &pc.name, timeouts)
+                    .await?
+                    .with_storage_class(pc.storage_class.as_deref()),
+            ),
             ProviderType::S3Compatible => {
                 let endpoint = pc.endpoint_url.as_deref().ok_or_else(|| {
                     anyhow::anyhow!(
@@ -50,8 +59,10 @@ pub async fn init_providers(
                         &pc.name,
                         pc.access_key.as_deref(),
                         pc.secret_key.as_deref(),
+                        timeouts,
                     )
-                    .await?,
+                    .await?
+                    .with_storage_class(pc.storage_class.as_deref()),
                 )
             }
             ProviderType::Azure => {
@@ -0,0 +1,160 @@
+use anyhow::Result;
+use serde_json::{Value, json};
+use std::path::Path;
+
+use enigma_core::config::EnigmaConfig;
+use enigma_core::manifest::ManifestDb;
+use enigma_core::types::KeyMaterial;
+use enigma_core::webhook::{self, WebhookPayload};
+use enigma_storage::reencrypt::{ReencryptStats, reencrypt_all_chunks};
+
+use super::providers::init_providers;
+use crate::output::JsonPrinter;
+
+pub async fn run(
+    base_dir: &Path,
+    cli_passphrase: &Option<String>,
+    dry_run: bool,
+    json: bool,
+) -> Result<()> {
+    let config_path = EnigmaConfig::default_path(base_dir);
+    let config = EnigmaConfig::load(&config_path)?;
+    let db = ManifestDb::open(Path::new(&config.enigma.db_path))?;
+
+    let passphrase = if config.enigma.needs_passphrase() {
+        Some(crate::get_passphrase(cli_passphrase)?)
+    } else {
+        None
+    };
+    let key_provider = enigma_keys::factory::create_key_provider(
+        &config.enigma.key_provider,
+        &crate::key_provider_options(&config.enigma, passphrase.as_deref()),
+    )
+    .await?;
+    let current = key_provider.get_current_key().await?;
+    let new_key = KeyMaterial {
+        id: current.id.clone(),
+        key: current.key,
+    };
+
+    // Every key other than the current one is an old key to migrate away from
+    let old_keys: Vec<(String, u64)> = db
+        .chunk_key_ids()?
+        .into_iter()
+        .filter(|(key_id, _)| *key_id != new_key.id)
+        .collect();
+
+    let mut report = ReencryptReport {
+        current_key_id: &new_key.id,
+        old_keys: &old_keys,
+        dry_run,
+        stats: ReencryptStats::default(),
+    };
+
+    if old_keys.is_empty() || dry_run {
+        if json {
+            return JsonPrinter::stdout().print("reencrypt", report.to_json());
+        }
+        if old_keys.is_empty() {
+            println!("All chunks already use the current key ({}).", new_key.id);
+            return Ok(());
+        }
+        println!("Dry run — would re-encrypt to key {}:", new_key.id);
+        for (key_id, count) in &old_keys {
+            println!("  {count} chunks with key {key_id}");
+        }
+        return Ok(());
+    }
+
+    let storage_providers = init_providers(&config.providers, &db).await?;
+
+    for (key_id, count) in &old_keys {
+        if !json {
+            println!("Re-encrypting {count} chunks from key {key_id}...");
+        }
+        let managed = key_provider.get_key_by_id(key_id).await?;
+        let old_key = KeyMaterial {
+            id: managed.id.clone(),
+            key: managed.key,
+        };
+        report.stats += reencrypt_all_chunks(&db, &storage_providers, &old_key, &new_key).await?;
+    }
+    let old_key_ids: Vec<&str> = old_keys.iter().map(|(id, _)| id.as_str()).collect();
+    webhook::enqueue(
+        &db,
+        &config.webhooks,
+        &WebhookPayload::new(
+            "key.rotated",
+            json!({
+                "key_id": new_key.id,
+                "old_key_ids": old_key_ids,
+                "reencrypted": report.stats.reencrypted,
+                "failed": report.stats.failed,
+            }),
+        ),
+    )?;
+    webhook::flush_queue(db, &config.webhooks).await;
+
+    if json {
+        return JsonPrinter::stdout().print("reencrypt", report.to_json());
+    }
+
+    println!(
+        "\nRe-encryption completed: {} chunks ({} copies) re-encrypted, {} failed",
+        report.stats.reencrypted, report.stats.copies_rewritten, report.stats.failed
+    );
+    if report.stats.failed > 0 {
+        println!("Run `enigma reencrypt` again to retry the failed chunks.");
+    }
+    Ok(())
+}
+
+struct ReencryptReport<'a> {
+    current_key_id: &'a str,
+    /// (key id, chunk count) of every key still in use besides the current one.
+    old_keys: &'a [(String, u64)],
+    dry_run: bool,
+    stats: ReencryptStats,
+}
+
+impl ReencryptReport<'_> {
+    fn to_json(&self) -> Value {
+        let old_keys: Vec<Value> = self
+            .old_keys
+            .iter()
+            .map(|(key_id, chunks)| json!({ "key_id": key_id, "chunks": chunks }))
+            .collect();
+        json!({
+            "current_key_id": self.current_key_id,
+            "old_keys": old_keys,
+            "dry_run": self.dry_run,
+            "reencrypted": self.stats.reencrypted,
+            "copies_rewritten": self.stats.copies_rewritten,
+            "failed": self.stats.failed,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::render;
+
+    #[test]
+    fn reencrypt_json_dry_run() {
+        let old_keys = vec![("key-1".to_string(), 42)];
+        let report = ReencryptReport {
+            current_key_id: "key-2",
+            old_keys: &old_keys,
+            dry_run: true,
+            stats: ReencryptStats::default(),
+        };
+        let doc = render("reencrypt", report.to_json());
+        assert_eq!(doc["command"], "reencrypt");
+        assert_eq!(doc["current_key_id"], "key-2");
+        assert_eq!(doc["dry_run"], true);
+        assert_eq!(doc["old_keys"][0]["key_id"], "key-1");
+        assert_eq!(doc["old_keys"][0]["chunks"], 42);
+        assert_eq!(doc["reencrypted"], 0);
+    }
+}
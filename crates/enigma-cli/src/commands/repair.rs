@@ -0,0 +1,361 @@
+use anyhow::Result;
+use serde_json::{Value, json};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use enigma_core::config::EnigmaConfig;
+use enigma_core::manifest::ManifestDb;
+use enigma_storage::provider::StorageProvider;
+
+use super::providers::init_providers;
+use crate::output::JsonPrinter;
+
+/// Chunk hashes checked against storage per manifest query.
+const PAGE_SIZE: u32 = 1000;
+
+/// What `enigma repair` may change. Nothing is modified on a dry run.
+pub struct RepairOptions {
+    pub dry_run: bool,
+    pub fix_missing: bool,
+    pub fix_orphans: bool,
+}
+
+pub async fn run(base_dir: &Path, options: &RepairOptions, json: bool) -> Result<()> {
+    let config_path = EnigmaConfig::default_path(base_dir);
+    let config = EnigmaConfig::load(&config_path)?;
+    let db = ManifestDb::open(Path::new(&config.enigma.db_path))?;
+    let storage_providers = init_providers(&config.providers, &db).await?;
+
+    if !json {
+        println!("Checking chunk storage...");
+    }
+    let report = repair(&db, &storage_providers, options).await?;
+
+    if json {
+        JsonPrinter::stdout().print("repair", report.to_json())
+    } else {
+        report.print_text(options);
+        Ok(())
+    }
+}
+
+/// Check every chunk record against storage, collect orphaned records, and
+/// apply the fixes enabled in `options`.
+async fn repair(
+    db: &ManifestDb,
+    storage_providers: &HashMap<i64, Box<dyn StorageProvider>>,
+    options: &RepairOptions,
+) -> Result<RepairReport> {
+    let mut report = RepairReport {
+        dry_run: options.dry_run,
+        orphan_chunks: db.find_orphan_chunks()?,
+        orphan_replicas: db.find_orphan_chunk_replicas()?,
+        ..Default::default()
+    };
+    // Orphans are GC's business, whether or not their data still exists
+    let orphan_hashes: HashSet<&str> = report
+        .orphan_chunks
+        .iter()
+        .map(|(hash, _, _)| hash.as_str())
+        .collect();
+
+    let mut after = String::new();
+    loop {
+        let page = db.chunk_hashes(&after, PAGE_SIZE)?;
+        let Some(last) = page.last() else { break };
+        after = last.clone();
+
+        for hash in page {
+            if orphan_hashes.contains(hash.as_str()) {
+                continue;
+            }
+            report.chunks_checked += 1;
+            match check_stored(db, storage_providers, &hash).await? {
+                Some(true) => {}
+                Some(false) => report.missing.push(hash),
+                None => report.unreachable += 1,
+            }
+        }
+    }
+
+    if options.dry_run {
+        return Ok(report);
+    }
+
+    if options.fix_missing {
+        for hash in &report.missing {
+            let (file_refs, object_refs) = db.remove_missing_chunk(hash)?;
+            report.file_refs_removed += file_refs;
+            report.object_refs_removed += object_refs;
+        }
+    }
+
+    if options.fix_orphans {
+        for (hash, provider_id, storage_key) in report
+            .orphan_chunks
+            .iter()
+            .chain(report.orphan_replicas.iter())
+        {
+            if let Some(provider) = storage_providers.get(provider_id)
+                && let Err(e) = provider.delete_chunk(storage_key).await
+            {
+                eprintln!("WARN: Failed to delete {storage_key} from provider {provider_id}: {e}");
+                report.errors += 1;
+            }
+            db.delete_chunk_replica(hash, *provider_id)?;
+        }
+        // Cascades to any replica records left
+        for (hash, _, _) in &report.orphan_chunks {
+            db.delete_chunk_record(hash)?;
+        }
+        report.orphans_deleted = (report.orphan_chunks.len() + report.orphan_replicas.len()) as u64;
+    }
+
+    Ok(report)
+}
+
+/// Whether any stored copy of a chunk exists: `Some(false)` when every
+/// location was checked and none was found, `None` when some location could
+/// not be checked (provider not configured or unreachable) and none was found.
+async fn check_stored(
+    db: &ManifestDb,
+    storage_providers: &HashMap<i64, Box<dyn StorageProvider>>,
+    hash: &str,
+) -> Result<Option<bool>> {
+    let Some((_nonce, _key_id, locations, _size_enc, _size_compressed)) =
+        db.get_chunk_locations(hash)?
+    else {
+        return Ok(Some(false));
+    };
+
+    let mut unchecked = false;
+    for (pid, skey) in &locations {
+        let Some(provider) = storage_providers.get(pid) else {
+            unchecked = true;
+            continue;
+        };
+        match provider.chunk_exists(skey).await {
+            Ok(true) => return Ok(Some(true)),
+            Ok(false) => {}
+            Err(e) => {
+                eprintln!("WARN: provider {pid} failed to check chunk {hash}: {e}");
+                unchecked = true;
+            }
+        }
+    }
+    Ok(if unchecked { None } else { Some(false) })
+}
+
+#[derive(Default)]
+struct RepairReport {
+    dry_run: bool,
+    /// Referenced chunks looked up in storage.
+    chunks_checked: u64,
+    /// Chunks with no stored copy on any provider.
+    missing: Vec<String>,
+    /// Chunks that could not be checked on every provider holding them.
+    unreachable: u64,
+    /// (chunk hash, provider id, storage key) of unreferenced chunks and replicas.
+    orphan_chunks: Vec<(String, i64, String)>,
+    orphan_replicas: Vec<(String, i64, String)>,
+    file_refs_removed: u64,
+    object_refs_removed: u64,
+    orphans_deleted: u64,
+    errors: u64,
+}
+
+impl RepairReport {
+    fn print_text(&self, options: &RepairOptions) {
+        println!(
+            "Checked {} chunks: {} missing from storage, {} could not be checked",
+            self.chunks_checked,
+            self.missing.len(),
+            self.unreachable
+        );
+        for hash in &self.missing {
+            println!("  missing  {hash}");
+        }
+        println!(
+            "Found {} orphaned chunks, {} orphan replicas",
+            self.orphan_chunks.len(),
+            self.orphan_replicas.len()
+        );
+        for (hash, provider_id, storage_key) in
+            self.orphan_chunks.iter().chain(&self.orphan_replicas)
+        {
+            println!("  orphan   {hash}  provider={provider_id}  key={storage_key}");
+        }
+
+        if self.dry_run {
+            println!("\nDry run — nothing was changed.");
+            return;
+        }
+        if options.fix_missing {
+            println!(
+                "\nRemoved {} missing chunks: {} file references, {} object references",
+                self.missing.len(),
+                self.file_refs_removed,
+                self.object_refs_removed
+            );
+        } else if !self.missing.is_empty() {
+            println!("\nRe-run with --fix-missing to drop references to missing chunks.");
+        }
+        if options.fix_orphans {
+            println!(
+                "Deleted {} orphaned records, {} errors",
+                self.orphans_deleted, self.errors
+            );
+        } else if !self.orphan_chunks.is_empty() || !self.orphan_replicas.is_empty() {
+            println!("Re-run with --fix-orphans to delete orphaned chunks.");
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        let orphans = |entries: &[(String, i64, String)]| -> Vec<Value> {
+            entries
+                .iter()
+                .map(|(hash, provider_id, storage_key)| {
+                    json!({
+                        "hash": hash,
+                        "provider_id": provider_id,
+                        "storage_key": storage_key,
+                    })
+                })
+                .collect()
+        };
+        json!({
+            "dry_run": self.dry_run,
+            "chunks_checked": self.chunks_checked,
+            "missing": self.missing,
+            "unreachable": self.unreachable,
+            "orphan_chunks": orphans(&self.orphan_chunks),
+            "orphan_replicas": orphans(&self.orphan_replicas),
+            "file_refs_removed": self.file_refs_removed,
+            "object_refs_removed": self.object_refs_removed,
+            "orphans_deleted": self.orphans_deleted,
+            "errors": self.errors,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::render;
+    use enigma_core::types::ProviderType;
+    use enigma_storage::mock::{MockMethod, MockStorageProvider};
+
+    /// A completed backup of one file over chunks "aa" (present in storage)
+    /// and "bb" (missing), plus an orphaned chunk "cc".
+    fn setup() -> (
+        ManifestDb,
+        HashMap<i64, Box<dyn StorageProvider>>,
+        MockStorageProvider,
+        i64,
+    ) {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let pid = db
+            .insert_provider("mock", ProviderType::Local, "/tmp/enigma", None, 1)
+            .unwrap();
+        db.create_backup("b1", "/src").unwrap();
+        let file_id = db
+            .insert_backup_file("b1", "a.txt", 2, None, "h", 2)
+            .unwrap();
+        for (idx, hash) in ["aa", "bb"].iter().enumerate() {
+            db.insert_or_dedup_chunk(hash, &[0u8; 12], "k", pid, hash, 1, 1, None)
+                .unwrap();
+            db.insert_file_chunk(file_id, hash, idx as u32, idx as u64)
+                .unwrap();
+        }
+        db.complete_backup("b1", 1, 2, 2, 0).unwrap();
+        db.insert_or_dedup_chunk("cc", &[0u8; 12], "k", pid, "cc", 1, 1, None)
+            .unwrap();
+        db.conn()
+            .execute("UPDATE chunks SET ref_count = 0 WHERE hash = 'cc'", [])
+            .unwrap();
+
+        let mock = MockStorageProvider::default();
+        mock.insert("aa", &[0]);
+        mock.insert("cc", &[0]);
+        let mut providers: HashMap<i64, Box<dyn StorageProvider>> = HashMap::new();
+        providers.insert(pid, Box::new(mock.clone()));
+        (db, providers, mock, file_id)
+    }
+
+    #[tokio::test]
+    async fn dry_run_reports_without_changes() {
+        let (db, providers, mock, file_id) = setup();
+        let options = RepairOptions {
+            dry_run: true,
+            fix_missing: true,
+            fix_orphans: true,
+        };
+
+        let report = repair(&db, &providers, &options).await.unwrap();
+        assert_eq!(report.chunks_checked, 2);
+        assert_eq!(report.missing, vec!["bb"]);
+        assert_eq!(report.orphan_chunks.len(), 1);
+        assert_eq!(report.file_refs_removed, 0);
+
+        assert_eq!(db.get_file_chunks(file_id).unwrap().len(), 2);
+        assert_eq!(db.chunk_hashes("", 10).unwrap(), vec!["aa", "bb", "cc"]);
+        assert!(mock.contains("cc"));
+        assert_eq!(mock.call_count(MockMethod::ChunkExists), 2);
+        assert_eq!(mock.call_count(MockMethod::DeleteChunk), 0);
+    }
+
+    #[tokio::test]
+    async fn fix_missing_drops_references() {
+        let (db, providers, mock, file_id) = setup();
+        let options = RepairOptions {
+            dry_run: false,
+            fix_missing: true,
+            fix_orphans: false,
+        };
+
+        let report = repair(&db, &providers, &options).await.unwrap();
+        assert_eq!(report.missing, vec!["bb"]);
+        assert_eq!(report.file_refs_removed, 1);
+
+        let chunks = db.get_file_chunks(file_id).unwrap();
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].0, "aa");
+        assert_eq!(db.get_backup("b1").unwrap().total_chunks, 1);
+        // Orphan left alone
+        assert_eq!(db.chunk_hashes("", 10).unwrap(), vec!["aa", "cc"]);
+        assert_eq!(mock.call_count(MockMethod::DeleteChunk), 0);
+    }
+
+    #[tokio::test]
+    async fn fix_orphans_deletes_orphaned_chunks() {
+        let (db, providers, mock, _) = setup();
+        let options = RepairOptions {
+            dry_run: false,
+            fix_missing: false,
+            fix_orphans: true,
+        };
+
+        let report = repair(&db, &providers, &options).await.unwrap();
+        assert_eq!(report.orphans_deleted, 1);
+        assert_eq!(db.chunk_hashes("", 10).unwrap(), vec!["aa", "bb"]);
+        assert!(!mock.contains("cc"));
+        assert_eq!(mock.call_count(MockMethod::DeleteChunk), 1);
+    }
+
+    #[test]
+    fn repair_json() {
+        let report = RepairReport {
+            chunks_checked: 2,
+            missing: vec!["bb".to_string()],
+            orphan_chunks: vec![("cc".to_string(), 1, "cc".to_string())],
+            ..Default::default()
+        };
+        let doc = render("repair", report.to_json());
+        assert_eq!(doc["version"], 1);
+        assert_eq!(doc["command"], "repair");
+        assert_eq!(doc["chunks_checked"], 2);
+        assert_eq!(doc["missing"][0], "bb");
+        assert_eq!(doc["orphan_chunks"][0]["provider_id"], 1);
+        assert_eq!(doc["orphans_deleted"], 0);
+    }
+}
@@ -1,15 +1,26 @@
 use anyhow::Result;
+use futures::{StreamExt, TryStreamExt};
 use indicatif::{ProgressBar, ProgressStyle};
-use std::path::Path;
+use serde::Serialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
 
 use enigma_core::config::EnigmaConfig;
-use enigma_core::crypto::decrypt_chunk;
+use enigma_core::crypto::{decrypt_chunk, verify_chunk_hmac};
 use enigma_core::dedup::compute_hash;
 use enigma_core::manifest::ManifestDb;
+use enigma_core::merkle;
 use enigma_core::types::{ChunkHash, EncryptedChunk, KeyMaterial};
+use enigma_keys::provider::KeyProvider;
+use enigma_storage::provider::StorageProvider;
 
 use super::providers::init_providers;
+use crate::output::{JsonPrinter, RunReport};
 
+#[allow(clippy::too_many_arguments)]
 pub async fn run(
     backup_id: &str,
     dest: &Path,
@@ -18,37 +29,44 @@ pub async fn run(
     path_filter: Option<&str>,
     glob_filter: Option<&str>,
     list_only: bool,
+    verify: Option<bool>,
+    json: bool,
 ) -> Result<()> {
-    println!("Restoring backup {backup_id} to {}", dest.display());
+    if !json {
+        println!("Restoring backup {backup_id} to {}", dest.display());
+    }
 
     // Load config
     let config_path = EnigmaConfig::default_path(base_dir);
     let config = EnigmaConfig::load(&config_path)?;
 
+    let verify = verify.unwrap_or(config.enigma.restore_verify);
+
+    if let Some(priority) = &config.enigma.io_priority {
+        enigma_core::limits::set_io_priority(priority)?;
+    }
+
     // Open database
     let db = ManifestDb::open(Path::new(&config.enigma.db_path))?;
 
     // Verify backup exists
     let backup = db.get_backup(backup_id)?;
-    println!(
-        "Backup: {} files, {} bytes, created {}",
-        backup.total_files, backup.total_bytes, backup.created_at
-    );
+    if !json {
+        println!(
+            "Backup: {} files, {} bytes, created {}",
+            backup.total_files, backup.total_bytes, backup.created_at
+        );
+    }
 
     // Get key provider via factory
-    let passphrase = if config.enigma.key_provider == "local" {
+    let passphrase = if config.enigma.needs_passphrase() {
         Some(crate::get_passphrase(cli_passphrase)?)
     } else {
         None
     };
     let key_provider = enigma_keys::factory::create_key_provider(
         &config.enigma.key_provider,
-        passphrase.as_deref().map(|s| s.as_bytes()),
-        &config.enigma.keyfile_path,
-        config.enigma.vault_url.as_deref(),
-        config.enigma.gcp_project_id.as_deref(),
-        config.enigma.aws_region.as_deref(),
-        config.enigma.secret_prefix.as_deref(),
+        &crate::key_provider_options(&config.enigma, passphrase.as_deref()),
     )
     .await?;
 
@@ -60,6 +78,7 @@ pub async fn run(
 
     // Get files in this backup
     let all_files = db.list_backup_files(backup_id)?;
+    let total_files = all_files.len();
 
     // Apply filters
     let glob_pattern = glob_filter.map(glob::Pattern::new).transpose()?;
@@ -80,6 +99,17 @@ pub async fn run(
         })
         .collect();
 
+    if list_only && json {
+        let files: Vec<_> = files
+            .iter()
+            .map(|(_id, path, size, hash)| json!({ "path": path, "size": size, "hash": hash }))
+            .collect();
+        return JsonPrinter::stdout().print(
+            "restore",
+            json!({ "backup_id": backup_id, "list": true, "files": files }),
+        );
+    }
+
     if list_only {
         println!("\nFiles in backup ({} matching):", files.len());
         for (_id, path, size, hash) in &files {
@@ -88,7 +118,11 @@ pub async fn run(
         return Ok(());
     }
 
-    let pb = ProgressBar::new(files.len() as u64);
+    let pb = if json {
+        ProgressBar::hidden()
+    } else {
+        ProgressBar::new(files.len() as u64)
+    };
     pb.set_style(
         ProgressStyle::default_bar()
             .template("{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} {msg}")
@@ -96,7 +130,168 @@ pub async fn run(
             .progress_chars("=>-"),
     );
 
-    for (file_id, file_path, _file_size, file_hash) in &files {
+    let mut report = RunReport::default();
+    let mut verify_report = verify.then(RestoreVerifyReport::default);
+    let mut result = restore_files(
+        &db,
+        &storage_providers,
+        key_provider.as_ref(),
+        &config,
+        &files,
+        dest,
+        &pb,
+        &mut report,
+        verify_report.as_mut(),
+    )
+    .await;
+
+    // The Merkle root covers the whole backup, so only a full restore
+    // can be checked against it
+    if let Some(verify_report) = verify_report.as_mut()
+        && result.is_ok()
+        && files.len() == total_files
+    {
+        match merkle_match(&db, backup_id, &verify_report.leaves) {
+            Ok(matched) => verify_report.merkle_match = matched,
+            Err(e) => result = Err(e),
+        }
+    }
+    if let Some(verify_report) = &verify_report
+        && result.is_ok()
+        && !verify_report.passed()
+    {
+        result = Err(anyhow::anyhow!(
+            "Restore verification failed: {} corrupted, {} missing{}",
+            verify_report.files_corrupted.len(),
+            verify_report.files_missing.len(),
+            if verify_report.merkle_match == Some(false) {
+                ", Merkle root mismatch"
+            } else {
+                ""
+            }
+        ));
+    }
+
+    let extra = json!({
+        "backup_id": backup_id,
+        "dest": dest.display().to_string(),
+        "verify": verify_report,
+    });
+    match result {
+        Ok(()) => {
+            pb.finish_with_message("done");
+            if json {
+                return JsonPrinter::stdout().print("restore", report.completed().to_json(extra)?);
+            }
+            println!("\nRestore completed: {} files", files.len());
+            if let Some(verify_report) = &verify_report {
+                verify_report.print_text();
+            }
+            Ok(())
+        }
+        Err(e) => {
+            pb.abandon();
+            if json {
+                JsonPrinter::stdout().print("restore", report.failed(&e).to_json(extra)?)?;
+            } else if let Some(verify_report) = &verify_report {
+                verify_report.print_text();
+            }
+            Err(e)
+        }
+    }
+}
+
+/// Outcome of `--verify`: every restored file re-read from disk and
+/// compared with the hash recorded at backup time.
+#[derive(Debug, Default, Serialize)]
+struct RestoreVerifyReport {
+    files_ok: u64,
+    /// Files whose content did not verify, kept as `<path>.corrupted`.
+    files_corrupted: Vec<String>,
+    /// Files with chunks that no provider could supply; not written.
+    files_missing: Vec<String>,
+    /// Whether the Merkle root over the restored chunks matches the stored
+    /// root; None for partial restores and backups without a root.
+    merkle_match: Option<bool>,
+    /// (path, hash of the restored chunk) for each chunk, sorted like
+    /// [`ManifestDb::backup_merkle_leaves`].
+    #[serde(skip)]
+    leaves: Vec<(String, String)>,
+}
+
+impl RestoreVerifyReport {
+    fn passed(&self) -> bool {
+        self.files_corrupted.is_empty()
+            && self.files_missing.is_empty()
+            && self.merkle_match != Some(false)
+    }
+
+    fn print_text(&self) {
+        let merkle = match self.merkle_match {
+            Some(true) => "Merkle root OK",
+            Some(false) => "Merkle root MISMATCH",
+            None => "Merkle root not checked",
+        };
+        println!(
+            "Verification {}: {} files OK, {} corrupted, {} missing, {merkle}",
+            if self.passed() { "PASSED" } else { "FAILED" },
+            self.files_ok,
+            self.files_corrupted.len(),
+            self.files_missing.len()
+        );
+        for path in &self.files_corrupted {
+            println!("  corrupted: {path} (kept as {path}.corrupted)");
+        }
+        for path in &self.files_missing {
+            println!("  missing:   {path}");
+        }
+    }
+}
+
+/// Whether the Merkle root over `leaves` equals the backup's stored root;
+/// None when it has none.
+fn merkle_match(
+    db: &ManifestDb,
+    backup_id: &str,
+    leaves: &[(String, String)],
+) -> Result<Option<bool>> {
+    let Some(stored) = db.get_backup_merkle_root(backup_id)? else {
+        return Ok(None);
+    };
+    let leaves: Vec<[u8; 32]> = leaves
+        .iter()
+        .map(|(path, chunk_hash)| merkle::leaf_hash(path, chunk_hash))
+        .collect();
+    Ok(Some(merkle::root(&leaves) == stored))
+}
+
+/// No copy of a chunk could be downloaded: it is not in the manifest, or
+/// every provider failed without returning any data.
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub(crate) struct ChunkMissing(String);
+
+/// Restore `files` under `dest`, tallying progress into `report`. A chunk
+/// referenced more than once in the restore counts as deduplicated.
+///
+/// Without `verify`, the first file that cannot be restored intact fails
+/// the restore. With it, such files are recorded in the report instead, and
+/// every written file is re-read and checked.
+#[allow(clippy::too_many_arguments)]
+async fn restore_files(
+    db: &ManifestDb,
+    storage_providers: &HashMap<i64, Box<dyn StorageProvider>>,
+    key_provider: &dyn KeyProvider,
+    config: &EnigmaConfig,
+    files: &[(i64, String, u64, String)],
+    dest: &Path,
+    pb: &ProgressBar,
+    report: &mut RunReport,
+    mut verify: Option<&mut RestoreVerifyReport>,
+) -> Result<()> {
+    let mut seen_chunks: HashSet<String> = HashSet::new();
+
+    for (file_id, file_path, _file_size, file_hash) in files {
         pb.set_message(file_path.clone());
 
         let dest_file = dest.join(file_path);
@@ -106,99 +301,409 @@ pub async fn run(
 
         // Get ordered chunks for this file
         let file_chunks = db.get_file_chunks(*file_id)?;
-
-        let mut file_data = Vec::new();
         for (chunk_hash, _chunk_index, _offset) in &file_chunks {
-            // Get chunk locations (with replica fallback)
-            let (nonce, key_id, locations, _size_enc, size_compressed) = db
-                .get_chunk_locations(chunk_hash)?
-                .ok_or_else(|| anyhow::anyhow!("Chunk {chunk_hash} not found in database"))?;
-
-            // Download with fallback across replicas
-            let mut ciphertext = None;
-            for (pid, skey) in &locations {
-                if let Some(provider) = storage_providers.get(pid) {
-                    match provider.download_chunk(skey).await {
-                        Ok(data) => {
-                            ciphertext = Some(data);
-                            break;
-                        }
-                        Err(e) => {
-                            eprintln!(
-                                "WARN: Provider {pid} failed for chunk {chunk_hash}: {e}, trying next"
-                            );
-                        }
-                    }
-                }
-            }
-            let ciphertext = ciphertext
-                .ok_or_else(|| anyhow::anyhow!("All providers failed for chunk {chunk_hash}"))?;
-
-            // Get the key
-            let managed_key = key_provider.get_key_by_id(&key_id).await?;
-            let key_material = KeyMaterial {
-                id: managed_key.id.clone(),
-                key: managed_key.key,
-            };
-
-            // Decrypt
-            let nonce_arr: [u8; 12] = nonce
-                .try_into()
-                .map_err(|_| anyhow::anyhow!("Invalid nonce length"))?;
-            let hash_bytes: [u8; 32] = hex::decode(chunk_hash)
-                .map_err(|e| anyhow::anyhow!("hex decode error: {e}"))?
-                .try_into()
-                .map_err(|_| anyhow::anyhow!("Invalid hash length"))?;
-            let encrypted = EncryptedChunk {
-                hash: ChunkHash(hash_bytes),
-                nonce: nonce_arr,
-                ciphertext,
-                key_id: key_material.id.clone(),
-            };
-
-            let decrypted = decrypt_chunk(&encrypted, &key_material)?;
-
-            // Decompress if this chunk was compressed
-            let plaintext = if size_compressed.is_some() {
-                enigma_core::compression::decompress_chunk(&decrypted)?
+            if seen_chunks.insert(chunk_hash.clone()) {
+                report.chunks_new += 1;
             } else {
-                decrypted
-            };
+                report.chunks_deduped += 1;
+            }
+        }
 
-            // Verify chunk hash
-            let computed = compute_hash(&plaintext);
-            if computed.to_hex() != *chunk_hash {
+        let mut chunk_hashes = Vec::new();
+        let written = write_file(
+            db,
+            storage_providers,
+            key_provider,
+            config,
+            file_chunks,
+            &dest_file,
+            report,
+            verify.is_some().then_some(&mut chunk_hashes),
+        )
+        .await?;
+
+        let Some(verify) = verify.as_deref_mut() else {
+            // Verify file hash
+            let restored_hash = written?;
+            if restored_hash != *file_hash {
                 anyhow::bail!(
-                    "Hash mismatch for chunk {chunk_hash}: got {}",
-                    computed.to_hex()
+                    "File hash mismatch for {file_path}: expected {file_hash}, got {restored_hash}"
                 );
             }
+            report.files_processed += 1;
+            pb.inc(1);
+            continue;
+        };
 
-            file_data.extend_from_slice(&plaintext);
+        verify.leaves.extend(
+            chunk_hashes
+                .into_iter()
+                .map(|hash| (file_path.clone(), hash)),
+        );
+        match written {
+            Err(e) if e.downcast_ref::<ChunkMissing>().is_some() => {
+                eprintln!("ERROR: {file_path} not restored: {e:#}");
+                std::fs::remove_file(&dest_file)?;
+                verify.files_missing.push(file_path.clone());
+            }
+            Err(e) => {
+                eprintln!("ERROR: {file_path} is corrupted: {e:#}");
+                quarantine(&dest_file)?;
+                verify.files_corrupted.push(file_path.clone());
+            }
+            Ok(_) => {
+                let on_disk = hash_file(&dest_file)?;
+                if on_disk == *file_hash {
+                    verify.files_ok += 1;
+                } else {
+                    eprintln!(
+                        "ERROR: {file_path} is corrupted: expected hash {file_hash}, read back {on_disk}"
+                    );
+                    quarantine(&dest_file)?;
+                    verify.files_corrupted.push(file_path.clone());
+                }
+            }
         }
+        report.files_processed += 1;
+        pb.inc(1);
+    }
+
+    if let Some(verify) = verify {
+        verify.leaves.sort();
+    }
+    Ok(())
+}
 
-        // Write file
-        std::fs::write(&dest_file, &file_data)?;
+/// Download `file_chunks` and write them to `dest_file`. The outer error
+/// is a local I/O failure; the inner one a chunk that could not be
+/// restored, after which the file is left incomplete. Returns the SHA-256
+/// of the data as it was written, and with `chunk_hashes`, records the
+/// hash of each restored chunk.
+#[allow(clippy::too_many_arguments)]
+async fn write_file(
+    db: &ManifestDb,
+    storage_providers: &HashMap<i64, Box<dyn StorageProvider>>,
+    key_provider: &dyn KeyProvider,
+    config: &EnigmaConfig,
+    file_chunks: Vec<(String, u32, u64)>,
+    dest_file: &Path,
+    report: &mut RunReport,
+    mut chunk_hashes: Option<&mut Vec<String>>,
+) -> Result<Result<String>> {
+    // Download up to `restore_concurrency` chunks at once; `buffered`
+    // yields them in chunk order so they can be written as they arrive.
+    let mut chunks = futures::stream::iter(file_chunks)
+        .map(|(chunk_hash, _chunk_index, _offset)| {
+            let verify_on_read = config.enigma.verify_on_read;
+            let verify_hmac = config.enigma.verify_hmac;
+            async move {
+                fetch_chunk(
+                    db,
+                    storage_providers,
+                    key_provider,
+                    &chunk_hash,
+                    verify_on_read,
+                    verify_hmac,
+                )
+                .await
+            }
+        })
+        .buffered(config.enigma.restore_concurrency());
 
-        // Verify file hash
-        let restored_hash = {
-            use sha2::{Digest, Sha256};
-            let mut hasher = Sha256::new();
-            hasher.update(&file_data);
-            format!("{:x}", hasher.finalize())
+    let mut out = std::io::BufWriter::new(std::fs::File::create(dest_file)?);
+    let mut hasher = Sha256::new();
+    loop {
+        let plaintext = match chunks.try_next().await {
+            Ok(Some(plaintext)) => plaintext,
+            Ok(None) => break,
+            Err(e) => {
+                out.flush()?;
+                return Ok(Err(e));
+            }
         };
+        if let Some(chunk_hashes) = chunk_hashes.as_deref_mut() {
+            chunk_hashes.push(compute_hash(&plaintext).to_hex());
+        }
+        hasher.update(&plaintext);
+        out.write_all(&plaintext)?;
+        report.bytes_processed += plaintext.len() as u64;
+    }
+    out.flush()?;
+    Ok(Ok(format!("{:x}", hasher.finalize())))
+}
+
+/// SHA-256 of the file at `path`, read back from disk.
+fn hash_file(path: &Path) -> Result<String> {
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut std::fs::File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Move a file that failed verification to `<path>.corrupted`.
+fn quarantine(path: &Path) -> Result<()> {
+    let mut corrupted = path.as_os_str().to_owned();
+    corrupted.push(".corrupted");
+    std::fs::rename(path, PathBuf::from(corrupted))?;
+    Ok(())
+}
+
+/// Download one chunk (with replica fallback), decrypt, decompress and,
+/// when `verify_on_read` is set, verify its hash. With `verify_hmac` a
+/// copy that fails its HMAC tag is skipped for the next replica.
+pub(crate) async fn fetch_chunk(
+    db: &ManifestDb,
+    storage_providers: &HashMap<i64, Box<dyn StorageProvider>>,
+    key_provider: &dyn KeyProvider,
+    chunk_hash: &str,
+    verify_on_read: bool,
+    verify_hmac: bool,
+) -> Result<Vec<u8>> {
+    // Get chunk locations (with replica fallback)
+    let (nonce, key_id, locations, _size_enc, size_compressed) = db
+        .get_chunk_locations(chunk_hash)?
+        .ok_or_else(|| ChunkMissing(format!("Chunk {chunk_hash} not found in database")))?;
+    let hmac_tag = if verify_hmac {
+        db.get_chunk_hmac_tag(chunk_hash)?
+    } else {
+        None
+    };
+
+    // Get the key
+    let managed_key = key_provider.get_key_by_id(&key_id).await?;
+    let key_material = KeyMaterial {
+        id: managed_key.id.clone(),
+        key: managed_key.key,
+    };
+
+    // Download with fallback across replicas
+    let mut ciphertext = None;
+    let mut tampered = false;
+    for (pid, skey) in &locations {
+        if let Some(provider) = storage_providers.get(pid) {
+            match provider.download_chunk(skey).await {
+                Ok(data) => {
+                    if let Some(tag) = &hmac_tag
+                        && let Err(e) = verify_chunk_hmac(&key_material, skey, &data, tag)
+                    {
+                        eprintln!("WARN: Provider {pid}: {e}, trying next");
+                        tampered = true;
+                        continue;
+                    }
+                    ciphertext = Some((data, *pid, skey.as_str()));
+                    break;
+                }
+                Err(e) => {
+                    eprintln!(
+                        "WARN: Provider {pid} failed for chunk {chunk_hash}: {e}, trying next"
+                    );
+                }
+            }
+        }
+    }
+    let Some((ciphertext, provider_id, storage_key)) = ciphertext else {
+        if tampered {
+            anyhow::bail!("No copy of chunk {chunk_hash} passed its HMAC check");
+        }
+        return Err(ChunkMissing(format!("All providers failed for chunk {chunk_hash}")).into());
+    };
+    db.record_chunk_access(chunk_hash)?;
+
+    // Decrypt
+    let nonce_arr: [u8; 12] = nonce
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("Invalid nonce length"))?;
+    let hash_bytes: [u8; 32] = hex::decode(chunk_hash)
+        .map_err(|e| anyhow::anyhow!("hex decode error: {e}"))?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("Invalid hash length"))?;
+    let encrypted = EncryptedChunk {
+        hash: ChunkHash(hash_bytes),
+        nonce: nonce_arr,
+        ciphertext,
+        key_id: key_material.id.clone(),
+    };
+
+    let decrypted = decrypt_chunk(&encrypted, &key_material)?;
+
+    // Decompress if this chunk was compressed
+    let plaintext = if size_compressed.is_some() {
+        enigma_core::compression::decompress_chunk(&decrypted)?
+    } else {
+        decrypted
+    };
 
-        if restored_hash != *file_hash {
+    // Verify chunk hash (paranoid mode)
+    if verify_on_read {
+        let computed = compute_hash(&plaintext).to_hex();
+        if computed != chunk_hash {
             anyhow::bail!(
-                "File hash mismatch for {file_path}: expected {file_hash}, got {restored_hash}"
+                "Hash mismatch for chunk {chunk_hash} (provider {provider_id}, key {storage_key}): got {computed}"
             );
         }
+    }
 
-        pb.inc(1);
+    Ok(plaintext)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use enigma_core::crypto::encrypt_chunk;
+    use enigma_core::types::ProviderType;
+    use enigma_keys::local::{Argon2Params, LocalKeyProvider};
+    use enigma_storage::mock::MockStorageProvider;
+
+    /// A completed backup "b1" of `files`, one chunk per file, stored in
+    /// a mock provider.
+    struct Fixture {
+        _tmp: tempfile::TempDir,
+        db: ManifestDb,
+        mock: MockStorageProvider,
+        providers: HashMap<i64, Box<dyn StorageProvider>>,
+        key_provider: LocalKeyProvider,
+        key_id: String,
+        config: EnigmaConfig,
+        dest: PathBuf,
     }
 
-    pb.finish_with_message("done");
-    println!("\nRestore completed: {} files", files.len());
+    async fn fixture(files: &[(&str, &[u8])]) -> Fixture {
+        let tmp = tempfile::tempdir().unwrap();
+        let argon2 = Argon2Params {
+            memory_kib: 8,
+            iterations: 1,
+            parallelism: 1,
+        };
+        let key_provider =
+            LocalKeyProvider::create_with_params(&tmp.path().join("keys.enc"), b"test", argon2)
+                .unwrap();
+        let managed_key = key_provider.get_current_key().await.unwrap();
+        let key = KeyMaterial {
+            id: managed_key.id.clone(),
+            key: managed_key.key,
+        };
 
-    Ok(())
+        let db = ManifestDb::open_in_memory().unwrap();
+        let pid = db
+            .insert_provider("mock", ProviderType::Local, "/tmp/enigma", None, 1)
+            .unwrap();
+        let mock = MockStorageProvider::default();
+        db.create_backup("b1", "/src").unwrap();
+        for (path, data) in files {
+            let hash = compute_hash(data);
+            let hex = hash.to_hex();
+            let encrypted = encrypt_chunk(data, &hash, &key).unwrap();
+            mock.insert(&hex, &encrypted.ciphertext);
+            db.insert_or_dedup_chunk(
+                &hex,
+                &encrypted.nonce,
+                &key.id,
+                pid,
+                &hex,
+                data.len() as u64,
+                encrypted.ciphertext.len() as u64,
+                None,
+            )
+            .unwrap();
+            let file_hash = format!("{:x}", Sha256::digest(data));
+            let file_id = db
+                .insert_backup_file("b1", path, data.len() as u64, None, &file_hash, 1)
+                .unwrap();
+            db.insert_file_chunk(file_id, &hex, 0, 0).unwrap();
+        }
+        db.complete_backup("b1", files.len() as u64, 0, files.len() as u64, 0)
+            .unwrap();
+
+        let mut providers: HashMap<i64, Box<dyn StorageProvider>> = HashMap::new();
+        providers.insert(pid, Box::new(mock.clone()));
+        let config = EnigmaConfig::default_config(tmp.path());
+        let dest = tmp.path().join("restore");
+        Fixture {
+            _tmp: tmp,
+            db,
+            mock,
+            providers,
+            key_provider,
+            key_id: key.id.clone(),
+            config,
+            dest,
+        }
+    }
+
+    async fn restore(f: &Fixture, verify: Option<&mut RestoreVerifyReport>) -> Result<()> {
+        let files = f.db.list_backup_files("b1").unwrap();
+        restore_files(
+            &f.db,
+            &f.providers,
+            &f.key_provider,
+            &f.config,
+            &files,
+            &f.dest,
+            &ProgressBar::hidden(),
+            &mut RunReport::default(),
+            verify,
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn verified_restore_matches_merkle_root() {
+        let f = fixture(&[("a.txt", b"alpha"), ("dir/b.txt", b"bravo")]).await;
+        let mut report = RestoreVerifyReport::default();
+        restore(&f, Some(&mut report)).await.unwrap();
+
+        assert_eq!(report.files_ok, 2);
+        assert!(report.passed());
+        assert_eq!(std::fs::read(f.dest.join("dir/b.txt")).unwrap(), b"bravo");
+        assert_eq!(
+            merkle_match(&f.db, "b1", &report.leaves).unwrap(),
+            Some(true)
+        );
+    }
+
+    #[tokio::test]
+    async fn corrupted_chunk_is_reported_and_quarantined() {
+        let f = fixture(&[("a.txt", b"alpha"), ("b.txt", b"bravo")]).await;
+        let hex = compute_hash(b"bravo").to_hex();
+        let mut ciphertext = f.mock.download_chunk(&hex).await.unwrap();
+        ciphertext[0] ^= 0xff;
+        f.mock.insert(&hex, &ciphertext);
+
+        let mut report = RestoreVerifyReport::default();
+        restore(&f, Some(&mut report)).await.unwrap();
+        assert_eq!(report.files_ok, 1);
+        assert_eq!(report.files_corrupted, vec!["b.txt"]);
+        assert!(report.files_missing.is_empty());
+        assert!(!report.passed());
+        assert!(f.dest.join("b.txt.corrupted").exists());
+        assert!(!f.dest.join("b.txt").exists());
+        assert_eq!(
+            merkle_match(&f.db, "b1", &report.leaves).unwrap(),
+            Some(false)
+        );
+
+        let doc = crate::output::render("restore", json!({ "verify": report }));
+        assert_eq!(doc["verify"]["files_corrupted"][0], "b.txt");
+        assert!(doc["verify"].get("leaves").is_none());
+
+        // Without --verify the restore stops at the bad file
+        assert!(restore(&f, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn unavailable_chunk_is_reported_missing() {
+        let f = fixture(&[("a.txt", b"alpha")]).await;
+        // Recorded, but never uploaded
+        f.db.insert_or_dedup_chunk("ff", &[0; 12], &f.key_id, 1, "ff", 1, 17, None)
+            .unwrap();
+        let file_id =
+            f.db.insert_backup_file("b1", "gone.txt", 1, None, "h", 1)
+                .unwrap();
+        f.db.insert_file_chunk(file_id, "ff", 0, 0).unwrap();
+
+        let mut report = RestoreVerifyReport::default();
+        restore(&f, Some(&mut report)).await.unwrap();
+        assert_eq!(report.files_ok, 1);
+        assert_eq!(report.files_missing, vec!["gone.txt"]);
+        assert!(!f.dest.join("gone.txt").exists());
+    }
 }
@@ -1,14 +1,21 @@
 use anyhow::Result;
+use serde_json::{Value, json};
 use std::path::Path;
 
 use enigma_core::config::EnigmaConfig;
-use enigma_core::manifest::ManifestDb;
+use enigma_core::manifest::{HISTOGRAM_BUCKET_KB, ManifestDb};
 
-pub fn run(base_dir: &Path) -> Result<()> {
+use crate::output::JsonPrinter;
+
+pub fn run(base_dir: &Path, json: bool) -> Result<()> {
     let config_path = EnigmaConfig::default_path(base_dir);
     let config = EnigmaConfig::load(&config_path)?;
     let db = ManifestDb::open(Path::new(&config.enigma.db_path))?;
 
+    if json {
+        return JsonPrinter::stdout().print("status", status_json(&db)?);
+    }
+
     match db.latest_backup()? {
         Some(backup) => {
             println!("Latest backup:");
@@ -19,19 +26,38 @@ pub fn run(base_dir: &Path) -> Result<()> {
             println!("  Total size:     {} bytes", backup.total_bytes);
             println!("  Total chunks:   {}", backup.total_chunks);
             println!("  Dedup'd chunks: {}", backup.dedup_chunks);
+            let dedup = db.backup_dedup_stats(&backup.id)?;
+            println!("  Unique chunks:  {}", dedup.unique_chunks);
+            println!("  Stored size:    {} bytes", dedup.total_bytes_stored);
+            println!("  Dedup ratio:    {:.1}%", dedup.dedup_ratio_percent);
+            let compression = db.compression_ratio(Some(&backup.id))?;
+            println!("  Compression:    {:.1}% saved", compression * 100.0);
             println!("  Created:        {}", backup.created_at);
             if let Some(ref completed) = backup.completed_at {
                 println!("  Completed:      {completed}");
             }
 
+            let histogram = db.chunk_size_histogram(Some(&backup.id))?;
+            if !histogram.is_empty() {
+                println!("\n  Chunk sizes:");
+                for line in histogram_bars(&histogram, 40) {
+                    println!("    {line}");
+                }
+            }
+
             // Show providers
             let providers = db.list_providers()?;
+            let chunk_sizes = db.provider_chunk_sizes(None)?;
             if !providers.is_empty() {
                 println!("\n  Providers:");
                 for p in &providers {
+                    let (chunks, avg) = chunk_sizes
+                        .iter()
+                        .find(|(id, ..)| *id == p.id)
+                        .map_or((0, 0), |(_, _, chunks, avg)| (*chunks, *avg));
                     println!(
-                        "    - {} ({}) bucket={} weight={}",
-                        p.name, p.provider_type, p.bucket, p.weight
+                        "    - {} ({}) bucket={} weight={} chunks={} avg_chunk={} bytes",
+                        p.name, p.provider_type, p.bucket, p.weight, chunks, avg
                     );
                 }
             }
@@ -43,3 +69,151 @@ pub fn run(base_dir: &Path) -> Result<()> {
 
     Ok(())
 }
+
+/// One line per histogram bucket: its size range, a bar scaled so the
+/// largest bucket is `width` characters, and the chunk count.
+fn histogram_bars(buckets: &[(u64, u64)], width: usize) -> Vec<String> {
+    let max = buckets.iter().map(|(_, count)| *count).max().unwrap_or(0);
+    buckets
+        .iter()
+        .map(|(start_kb, count)| {
+            let len = if max == 0 {
+                0
+            } else {
+                // At least one mark for a non-empty bucket
+                ((*count as usize * width).div_ceil(max as usize)).max(1)
+            };
+            format!(
+                "{:>6}-{:<6} KB |{:<width$}| {count}",
+                start_kb,
+                start_kb + HISTOGRAM_BUCKET_KB,
+                "#".repeat(len)
+            )
+        })
+        .collect()
+}
+
+/// `latest_backup` is null when there are no backups yet.
+fn status_json(db: &ManifestDb) -> Result<Value> {
+    let latest = match db.latest_backup()? {
+        Some(b) => {
+            let dedup = db.backup_dedup_stats(&b.id)?;
+            let compression_ratio = db.compression_ratio(Some(&b.id))?;
+            let histogram: Vec<Value> = db
+                .chunk_size_histogram(Some(&b.id))?
+                .into_iter()
+                .map(|(start_kb, count)| json!({ "bucket_start_kb": start_kb, "count": count }))
+                .collect();
+            Some(json!({
+                "id": b.id,
+                "source_path": b.source_path,
+                "status": b.status.to_string(),
+                "total_files": b.total_files,
+                "total_bytes": b.total_bytes,
+                "total_chunks": b.total_chunks,
+                "dedup_chunks": b.dedup_chunks,
+                "created_at": b.created_at,
+                "completed_at": b.completed_at,
+                "dedup": dedup,
+                "compression_ratio": compression_ratio,
+                "chunk_size_histogram": histogram,
+            }))
+        }
+        None => None,
+    };
+    let chunk_sizes = db.provider_chunk_sizes(None)?;
+    let providers: Vec<Value> = db
+        .list_providers()?
+        .into_iter()
+        .map(|p| {
+            let (chunks, avg) = chunk_sizes
+                .iter()
+                .find(|(id, ..)| *id == p.id)
+                .map_or((0, 0), |(_, _, chunks, avg)| (*chunks, *avg));
+            json!({
+                "name": p.name,
+                "type": p.provider_type.to_string(),
+                "bucket": p.bucket,
+                "weight": p.weight,
+                "chunks": chunks,
+                "avg_chunk_bytes": avg,
+            })
+        })
+        .collect();
+    Ok(json!({ "latest_backup": latest, "providers": providers }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::render;
+    use enigma_core::types::ProviderType;
+
+    #[test]
+    fn status_json_reports_latest_backup() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        db.insert_provider("local", ProviderType::Local, "/tmp/x", None, 2)
+            .unwrap();
+        db.create_backup("b1", "/data").unwrap();
+        db.complete_backup("b1", 1, 10, 4, 1).unwrap();
+
+        let doc = render("status", status_json(&db).unwrap());
+        assert_eq!(doc["version"], 1);
+        assert_eq!(doc["latest_backup"]["id"], "b1");
+        assert_eq!(doc["latest_backup"]["status"], "completed");
+        assert_eq!(doc["latest_backup"]["dedup"]["dedup_chunks"], 1);
+        assert_eq!(doc["latest_backup"]["dedup"]["dedup_ratio_percent"], 25.0);
+        assert_eq!(doc["providers"][0]["name"], "local");
+        assert_eq!(doc["providers"][0]["weight"], 2);
+    }
+
+    #[test]
+    fn status_json_reports_chunk_sizes() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let pid = db
+            .insert_provider("local", ProviderType::Local, "/tmp/x", None, 1)
+            .unwrap();
+        db.create_backup("b1", "/data").unwrap();
+        let file_id = db
+            .insert_backup_file("b1", "f.bin", 0, None, "h", 3)
+            .unwrap();
+        for (idx, (hash, size)) in [("a", 1000), ("b", 2000), ("c", 70_000)]
+            .into_iter()
+            .enumerate()
+        {
+            db.insert_or_dedup_chunk(hash, &[0u8; 12], "k", pid, hash, size, size, Some(size / 4))
+                .unwrap();
+            db.insert_file_chunk(file_id, hash, idx as u32, 0).unwrap();
+        }
+        db.complete_backup("b1", 1, 73_000, 3, 0).unwrap();
+
+        let doc = render("status", status_json(&db).unwrap());
+        let latest = &doc["latest_backup"];
+        assert_eq!(latest["compression_ratio"], 0.75);
+        assert_eq!(
+            latest["chunk_size_histogram"],
+            json!([
+                { "bucket_start_kb": 0, "count": 2 },
+                { "bucket_start_kb": 64, "count": 1 },
+            ])
+        );
+        assert_eq!(doc["providers"][0]["chunks"], 3);
+        assert_eq!(doc["providers"][0]["avg_chunk_bytes"], 73_000 / 3);
+    }
+
+    #[test]
+    fn histogram_bars_scale_to_largest_bucket() {
+        let lines = histogram_bars(&[(0, 10), (64, 1), (128, 5)], 10);
+        assert_eq!(lines[0], "     0-64     KB |##########| 10");
+        assert_eq!(lines[1], "    64-128    KB |#         | 1");
+        assert_eq!(lines[2], "   128-192    KB |#####     | 5");
+    }
+
+    #[test]
+    fn status_json_without_backups() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let doc = render("status", status_json(&db).unwrap());
+        assert!(doc["latest_backup"].is_null());
+        assert_eq!(doc["providers"], json!([]));
+    }
+}
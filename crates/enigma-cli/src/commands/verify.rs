@@ -1,15 +1,31 @@
 use anyhow::Result;
+use rand::Rng;
+use serde_json::{Value, json};
+use std::collections::HashMap;
 use std::path::Path;
 
 use super::providers::init_providers;
 use enigma_core::config::EnigmaConfig;
-use enigma_core::crypto::decrypt_chunk;
+use enigma_core::crypto::{decrypt_chunk, signing, verify_chunk_hmac};
 use enigma_core::dedup::compute_hash;
 use enigma_core::manifest::ManifestDb;
+use enigma_core::merkle;
 use enigma_core::types::{ChunkHash, EncryptedChunk, KeyMaterial};
+use enigma_keys::provider::KeyProvider;
+use enigma_storage::provider::StorageProvider;
 
-pub async fn run(backup_id: &str, base_dir: &Path, cli_passphrase: &Option<String>) -> Result<()> {
-    println!("Verifying backup {backup_id}...");
+use crate::output::JsonPrinter;
+
+pub async fn run(
+    backup_id: &str,
+    base_dir: &Path,
+    cli_passphrase: &Option<String>,
+    fast: bool,
+    json: bool,
+) -> Result<()> {
+    if !json {
+        println!("Verifying backup {backup_id}...");
+    }
 
     let config_path = EnigmaConfig::default_path(base_dir);
     let config = EnigmaConfig::load(&config_path)?;
@@ -17,132 +33,351 @@ pub async fn run(backup_id: &str, base_dir: &Path, cli_passphrase: &Option<Strin
 
     let _backup = db.get_backup(backup_id)?;
 
-    let passphrase = if config.enigma.key_provider == "local" {
+    let passphrase = if config.enigma.needs_passphrase() {
         Some(crate::get_passphrase(cli_passphrase)?)
     } else {
         None
     };
     let key_provider = enigma_keys::factory::create_key_provider(
         &config.enigma.key_provider,
-        passphrase.as_deref().map(|s| s.as_bytes()),
-        &config.enigma.keyfile_path,
-        config.enigma.vault_url.as_deref(),
-        config.enigma.gcp_project_id.as_deref(),
-        config.enigma.aws_region.as_deref(),
-        config.enigma.secret_prefix.as_deref(),
+        &crate::key_provider_options(&config.enigma, passphrase.as_deref()),
     )
     .await?;
 
     // Storage providers
     let storage_providers = init_providers(&config.providers, &db).await?;
 
+    let mut report = if fast {
+        verify_fast(backup_id, &db, &storage_providers, key_provider.as_ref()).await?
+    } else {
+        verify_full(backup_id, &db, &storage_providers, key_provider.as_ref()).await?
+    };
+    if let Some(key) = &config.enigma.backup_verify_key {
+        let verify_key = signing::decode_verify_key(key)?;
+        report.signature_ok = Some(db.verify_backup_signature(backup_id, &verify_key)?);
+    }
+
+    if json {
+        JsonPrinter::stdout().print("verify", report.to_json())
+    } else {
+        report.print_text();
+        Ok(())
+    }
+}
+
+/// Re-download and check every chunk of the backup.
+async fn verify_full(
+    backup_id: &str,
+    db: &ManifestDb,
+    storage_providers: &HashMap<i64, Box<dyn StorageProvider>>,
+    key_provider: &dyn KeyProvider,
+) -> Result<VerifyReport> {
     let files = db.list_backup_files(backup_id)?;
-    let mut errors = 0u32;
-    let mut verified = 0u32;
+    let mut report = VerifyReport::new(backup_id, false);
 
     for (file_id, file_path, _size, _file_hash) in &files {
         let chunks = db.get_file_chunks(*file_id)?;
 
         for (chunk_hash, _idx, _offset) in &chunks {
-            let chunk_locations = match db.get_chunk_locations(chunk_hash)? {
-                Some(info) => info,
-                None => {
-                    eprintln!("ERROR: chunk {chunk_hash} not found in DB");
-                    errors += 1;
-                    continue;
+            report.chunks_total += 1;
+            if check_chunk(db, storage_providers, key_provider, file_path, chunk_hash).await? {
+                report.chunks_ok += 1;
+            } else {
+                report.chunk_errors += 1;
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// Probabilistic check: pick a random Merkle leaf, verify its audit path
+/// against the stored root, and re-download only the O(log n) chunks
+/// sampled along that path.
+async fn verify_fast(
+    backup_id: &str,
+    db: &ManifestDb,
+    storage_providers: &HashMap<i64, Box<dyn StorageProvider>>,
+    key_provider: &dyn KeyProvider,
+) -> Result<VerifyReport> {
+    if db.get_backup_merkle_root(backup_id)?.is_none() {
+        anyhow::bail!(
+            "Backup {backup_id} has no Merkle root (completed before it was introduced) — run a full verify"
+        );
+    }
+
+    let leaves = db.backup_merkle_leaves(backup_id)?;
+    let mut report = VerifyReport::new(backup_id, true);
+    report.chunks_total = leaves.len() as u64;
+    if leaves.is_empty() {
+        return Ok(report);
+    }
+
+    let leaf_index = rand::thread_rng().gen_range(0..leaves.len());
+    report.leaf_index = Some(leaf_index);
+    let path_ok = db.verify_merkle_path(backup_id, leaf_index)?;
+    report.merkle_path_ok = Some(path_ok);
+    if !path_ok {
+        return Ok(report);
+    }
+
+    for i in merkle::audit_sample(leaves.len(), leaf_index) {
+        let (file_path, chunk_hash) = &leaves[i];
+        if check_chunk(db, storage_providers, key_provider, file_path, chunk_hash).await? {
+            report.chunks_ok += 1;
+        } else {
+            report.chunk_errors += 1;
+        }
+    }
+
+    Ok(report)
+}
+
+/// Outcome of a full or fast verification.
+struct VerifyReport {
+    backup_id: String,
+    fast: bool,
+    /// Chunks referenced by the backup.
+    chunks_total: u64,
+    chunks_ok: u64,
+    chunk_errors: u64,
+    /// Fast mode only: the sampled leaf and whether its audit path matched.
+    leaf_index: Option<usize>,
+    merkle_path_ok: Option<bool>,
+    /// Whether the record's signature matched `backup_verify_key`; None
+    /// when no verify key is configured.
+    signature_ok: Option<bool>,
+}
+
+impl VerifyReport {
+    fn new(backup_id: &str, fast: bool) -> Self {
+        Self {
+            backup_id: backup_id.to_string(),
+            fast,
+            chunks_total: 0,
+            chunks_ok: 0,
+            chunk_errors: 0,
+            leaf_index: None,
+            merkle_path_ok: None,
+            signature_ok: None,
+        }
+    }
+
+    fn passed(&self) -> bool {
+        self.chunk_errors == 0
+            && self.merkle_path_ok != Some(false)
+            && self.signature_ok != Some(false)
+    }
+
+    fn print_text(&self) {
+        match self.signature_ok {
+            Some(true) => println!("Signature OK"),
+            Some(false) => {
+                println!("Signature INVALID: backup is unsigned or its record was modified")
+            }
+            None => {}
+        }
+        let checked = self.chunks_ok + self.chunk_errors;
+        if !self.fast {
+            if self.passed() {
+                println!(
+                    "Verification PASSED: {} chunks verified, 0 errors",
+                    self.chunks_ok
+                );
+            } else {
+                println!(
+                    "Verification FAILED: {} chunks OK, {} errors",
+                    self.chunks_ok, self.chunk_errors
+                );
+            }
+        } else if self.chunks_total == 0 {
+            println!("Verification PASSED: backup has no chunks");
+        } else if self.merkle_path_ok == Some(false) {
+            println!(
+                "Verification FAILED: manifest does not match the stored Merkle root (leaf {})",
+                self.leaf_index.unwrap_or_default()
+            );
+        } else if self.passed() {
+            println!(
+                "Verification PASSED (fast): Merkle path OK, {checked} of {} chunks sampled, 0 errors",
+                self.chunks_total
+            );
+        } else {
+            println!(
+                "Verification FAILED (fast): {} of {checked} sampled chunks failed",
+                self.chunk_errors
+            );
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "backup_id": self.backup_id,
+            "mode": if self.fast { "fast" } else { "full" },
+            "passed": self.passed(),
+            "chunks_total": self.chunks_total,
+            "chunks_checked": self.chunks_ok + self.chunk_errors,
+            "chunks_ok": self.chunks_ok,
+            "chunk_errors": self.chunk_errors,
+            "leaf_index": self.leaf_index,
+            "merkle_path_ok": self.merkle_path_ok,
+            "signature_ok": self.signature_ok,
+        })
+    }
+}
+
+/// Download one chunk, check its HMAC tag, decrypt and re-hash it. Problems
+/// are reported on stderr; returns whether the chunk verified.
+async fn check_chunk(
+    db: &ManifestDb,
+    storage_providers: &HashMap<i64, Box<dyn StorageProvider>>,
+    key_provider: &dyn KeyProvider,
+    file_path: &str,
+    chunk_hash: &str,
+) -> Result<bool> {
+    let chunk_locations = match db.get_chunk_locations(chunk_hash)? {
+        Some(info) => info,
+        None => {
+            eprintln!("ERROR: chunk {chunk_hash} not found in DB");
+            return Ok(false);
+        }
+    };
+    let (nonce, key_id, locations, _size_enc, size_compressed) = chunk_locations;
+
+    // Download with fallback across replicas
+    let mut ciphertext = None;
+    for (pid, skey) in &locations {
+        if let Some(provider) = storage_providers.get(pid) {
+            match provider.download_chunk(skey).await {
+                Ok(data) => {
+                    ciphertext = Some((data, *pid, skey.as_str()));
+                    break;
                 }
-            };
-            let (nonce, key_id, locations, _size_enc, size_compressed) = chunk_locations;
-
-            // Download with fallback across replicas
-            let mut ciphertext = None;
-            for (pid, skey) in &locations {
-                if let Some(provider) = storage_providers.get(pid) {
-                    match provider.download_chunk(skey).await {
-                        Ok(data) => {
-                            ciphertext = Some(data);
-                            break;
-                        }
-                        Err(e) => {
-                            eprintln!(
-                                "WARN: provider {pid} failed for chunk {chunk_hash}: {e}, trying next"
-                            );
-                        }
-                    }
+                Err(e) => {
+                    eprintln!(
+                        "WARN: provider {pid} failed for chunk {chunk_hash}: {e}, trying next"
+                    );
                 }
             }
-            let ciphertext = match ciphertext {
-                Some(data) => data,
-                None => {
-                    eprintln!("ERROR: all providers failed for chunk {chunk_hash}");
-                    errors += 1;
-                    continue;
-                }
-            };
+        }
+    }
+    let (ciphertext, provider_id, storage_key) = match ciphertext {
+        Some(found) => found,
+        None => {
+            eprintln!("ERROR: all providers failed for chunk {chunk_hash}");
+            return Ok(false);
+        }
+    };
 
-            // Decrypt + verify
-            let managed_key = key_provider.get_key_by_id(&key_id).await?;
-            let key_material = KeyMaterial {
-                id: managed_key.id.clone(),
-                key: managed_key.key,
-            };
+    // HMAC tag, then decrypt + verify
+    let managed_key = key_provider.get_key_by_id(&key_id).await?;
+    let key_material = KeyMaterial {
+        id: managed_key.id.clone(),
+        key: managed_key.key,
+    };
 
-            let nonce_arr: [u8; 12] = nonce
-                .try_into()
-                .map_err(|_| anyhow::anyhow!("Invalid nonce"))?;
+    if let Some(tag) = db.get_chunk_hmac_tag(chunk_hash)?
+        && let Err(e) = verify_chunk_hmac(&key_material, storage_key, &ciphertext, &tag)
+    {
+        eprintln!("ERROR: chunk {chunk_hash} in {file_path} (provider {provider_id}): {e}");
+        return Ok(false);
+    }
 
-            let hash_arr: [u8; 32] = hex::decode(chunk_hash)
-                .map_err(|e| anyhow::anyhow!("hex decode error: {e}"))?
-                .try_into()
-                .map_err(|_| anyhow::anyhow!("Invalid hash length"))?;
+    let nonce_arr: [u8; 12] = nonce
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("Invalid nonce"))?;
 
-            let encrypted = EncryptedChunk {
-                hash: ChunkHash(hash_arr),
-                nonce: nonce_arr,
-                ciphertext,
-                key_id: key_material.id.clone(),
-            };
+    let hash_arr: [u8; 32] = hex::decode(chunk_hash)
+        .map_err(|e| anyhow::anyhow!("hex decode error: {e}"))?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("Invalid hash length"))?;
+
+    let encrypted = EncryptedChunk {
+        hash: ChunkHash(hash_arr),
+        nonce: nonce_arr,
+        ciphertext,
+        key_id: key_material.id.clone(),
+    };
 
-            match decrypt_chunk(&encrypted, &key_material) {
-                Ok(decrypted) => {
-                    let plaintext = if size_compressed.is_some() {
-                        match enigma_core::compression::decompress_chunk(&decrypted) {
-                            Ok(d) => d,
-                            Err(e) => {
-                                eprintln!(
-                                    "ERROR: decompression failed for chunk {chunk_hash}: {e}"
-                                );
-                                errors += 1;
-                                continue;
-                            }
-                        }
-                    } else {
-                        decrypted
-                    };
-                    let computed = compute_hash(&plaintext);
-                    if computed.to_hex() != *chunk_hash {
-                        eprintln!(
-                            "ERROR: hash mismatch for chunk in {file_path}: expected {chunk_hash}, got {}",
-                            computed.to_hex()
-                        );
-                        errors += 1;
-                    } else {
-                        verified += 1;
+    match decrypt_chunk(&encrypted, &key_material) {
+        Ok(decrypted) => {
+            let plaintext = if size_compressed.is_some() {
+                match enigma_core::compression::decompress_chunk(&decrypted) {
+                    Ok(d) => d,
+                    Err(e) => {
+                        eprintln!("ERROR: decompression failed for chunk {chunk_hash}: {e}");
+                        return Ok(false);
                     }
                 }
-                Err(e) => {
-                    eprintln!("ERROR: decryption failed for chunk {chunk_hash}: {e}");
-                    errors += 1;
-                }
+            } else {
+                decrypted
+            };
+            let computed = compute_hash(&plaintext);
+            if computed.to_hex() != chunk_hash {
+                eprintln!(
+                    "ERROR: hash mismatch for chunk in {file_path}: expected {chunk_hash}, got {} (provider {provider_id}, key {storage_key})",
+                    computed.to_hex()
+                );
+                Ok(false)
+            } else {
+                Ok(true)
             }
         }
+        Err(e) => {
+            eprintln!("ERROR: decryption failed for chunk {chunk_hash}: {e}");
+            Ok(false)
+        }
     }
+}
 
-    if errors == 0 {
-        println!("Verification PASSED: {verified} chunks verified, 0 errors");
-    } else {
-        println!("Verification FAILED: {verified} chunks OK, {errors} errors");
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::render;
+
+    #[test]
+    fn verify_json_full() {
+        let mut report = VerifyReport::new("b1", false);
+        report.chunks_total = 4;
+        report.chunks_ok = 3;
+        report.chunk_errors = 1;
+
+        let doc = render("verify", report.to_json());
+        assert_eq!(doc["version"], 1);
+        assert_eq!(doc["command"], "verify");
+        assert_eq!(doc["backup_id"], "b1");
+        assert_eq!(doc["mode"], "full");
+        assert_eq!(doc["passed"], false);
+        assert_eq!(doc["chunks_checked"], 4);
+        assert_eq!(doc["chunk_errors"], 1);
+        assert!(doc["merkle_path_ok"].is_null());
+        assert!(doc["signature_ok"].is_null());
     }
 
-    Ok(())
+    #[test]
+    fn verify_json_bad_signature_fails() {
+        let mut report = VerifyReport::new("b1", false);
+        report.chunks_total = 2;
+        report.chunks_ok = 2;
+        report.signature_ok = Some(false);
+
+        let doc = render("verify", report.to_json());
+        assert_eq!(doc["passed"], false);
+        assert_eq!(doc["chunk_errors"], 0);
+        assert_eq!(doc["signature_ok"], false);
+    }
+
+    #[test]
+    fn verify_json_fast_merkle_mismatch_fails() {
+        let mut report = VerifyReport::new("b1", true);
+        report.chunks_total = 8;
+        report.leaf_index = Some(5);
+        report.merkle_path_ok = Some(false);
+
+        let doc = render("verify", report.to_json());
+        assert_eq!(doc["mode"], "fast");
+        assert_eq!(doc["passed"], false);
+        assert_eq!(doc["leaf_index"], 5);
+        assert_eq!(doc["merkle_path_ok"], false);
+    }
 }
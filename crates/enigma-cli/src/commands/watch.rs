@@ -0,0 +1,323 @@
+use anyhow::Result;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+use tokio::time::{Instant, timeout};
+
+use enigma_core::config::EnigmaConfig;
+
+/// Why a watch-mode backup was started.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    /// Filesystem changes settled for the debounce window.
+    Changes,
+    /// `max_interval` elapsed without any changes.
+    Interval,
+}
+
+pub async fn run(
+    source: &Path,
+    base_dir: &Path,
+    cli_passphrase: &Option<String>,
+    debounce_ms: u64,
+    max_interval_s: u64,
+) -> Result<()> {
+    let source = source.canonicalize()?;
+    let config = EnigmaConfig::load(&EnigmaConfig::default_path(base_dir))?;
+
+    // Ask once rather than before every backup
+    let passphrase = if config.enigma.needs_passphrase() {
+        Some(crate::get_passphrase(cli_passphrase)?)
+    } else {
+        cli_passphrase.clone()
+    };
+
+    let filter = EventFilter {
+        ignored_dir: base_dir
+            .canonicalize()
+            .unwrap_or_else(|_| base_dir.to_path_buf()),
+        db_path: config.enigma.db_path.clone(),
+    };
+    let (tx, events) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| match res {
+        Ok(event) if filter.is_relevant(&event) => {
+            let _ = tx.send(event);
+        }
+        Ok(_) => {}
+        Err(e) => tracing::warn!("Watch error: {e}"),
+    })?;
+    watcher.watch(&source, RecursiveMode::Recursive)?;
+
+    let (shutdown_tx, shutdown) = watch::channel(false);
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        eprintln!("Shutdown requested, finishing current backup...");
+        let _ = shutdown_tx.send(true);
+    });
+
+    println!(
+        "Watching {} (debounce {debounce_ms} ms, max interval {max_interval_s} s)",
+        source.display()
+    );
+    super::backup::run(&source, base_dir, &passphrase, &[], &[], false).await?;
+
+    watch_loop(
+        events,
+        Duration::from_millis(debounce_ms),
+        Duration::from_secs(max_interval_s),
+        shutdown,
+        |trigger| {
+            let source = &source;
+            let passphrase = &passphrase;
+            async move {
+                let reason = match trigger {
+                    Trigger::Changes => "changes detected",
+                    Trigger::Interval => "max interval reached",
+                };
+                println!("\n{reason}, starting backup");
+                super::backup::run(source, base_dir, passphrase, &[], &[], false).await
+            }
+        },
+    )
+    .await?;
+
+    println!("Watch stopped.");
+    Ok(())
+}
+
+/// Drive `backup` from filesystem events until `shutdown` flips to true.
+/// A shutdown requested during a backup takes effect once it completes.
+/// Failed backups are logged and watching continues.
+pub(crate) async fn watch_loop<F, Fut>(
+    mut events: mpsc::UnboundedReceiver<Event>,
+    debounce: Duration,
+    max_interval: Duration,
+    mut shutdown: watch::Receiver<bool>,
+    mut backup: F,
+) -> Result<()>
+where
+    F: FnMut(Trigger) -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    while !*shutdown.borrow() {
+        let trigger = tokio::select! {
+            _ = shutdown.changed() => return Ok(()),
+            trigger = next_trigger(&mut events, debounce, max_interval) => trigger?,
+        };
+        if let Err(e) = backup(trigger).await {
+            tracing::error!("Watch backup failed: {e:#}");
+        }
+    }
+    Ok(())
+}
+
+/// Wait for the next backup trigger: either `max_interval` with no events,
+/// or a burst of events followed by `debounce` of quiet. A tree that never
+/// goes quiet is still backed up `max_interval` after its first event.
+async fn next_trigger(
+    events: &mut mpsc::UnboundedReceiver<Event>,
+    debounce: Duration,
+    max_interval: Duration,
+) -> Result<Trigger> {
+    match timeout(max_interval, events.recv()).await {
+        Err(_) => return Ok(Trigger::Interval),
+        Ok(None) => anyhow::bail!("filesystem watcher stopped"),
+        Ok(Some(_)) => {}
+    }
+
+    let deadline = Instant::now() + max_interval;
+    loop {
+        let wait = debounce.min(deadline.saturating_duration_since(Instant::now()));
+        match timeout(wait, events.recv()).await {
+            Err(_) => return Ok(Trigger::Changes),
+            Ok(None) => anyhow::bail!("filesystem watcher stopped"),
+            Ok(Some(_)) => {}
+        }
+    }
+}
+
+/// Drops event kinds we don't back up for, and changes to Enigma's own
+/// files — otherwise watching a tree that contains the config directory or
+/// manifest DB would retrigger after every backup.
+struct EventFilter {
+    ignored_dir: PathBuf,
+    db_path: String,
+}
+
+impl EventFilter {
+    fn is_relevant(&self, event: &Event) -> bool {
+        matches!(
+            event.kind,
+            EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+        ) && event.paths.iter().any(|p| {
+            // String prefix so the DB's -wal / -journal files match too
+            !p.starts_with(&self.ignored_dir) && !p.to_string_lossy().starts_with(&self.db_path)
+        })
+    }
+}
+
+pub(crate) async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+        match signal(SignalKind::terminate()) {
+            Ok(mut term) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = term.recv() => {}
+                }
+            }
+            Err(e) => {
+                tracing::warn!("Cannot listen for SIGTERM: {e}");
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{AccessKind, CreateKind, ModifyKind};
+    use std::sync::{Arc, Mutex};
+
+    fn modified(path: &str) -> Event {
+        Event::new(EventKind::Modify(ModifyKind::Any)).add_path(PathBuf::from(path))
+    }
+
+    struct Harness {
+        events: mpsc::UnboundedSender<Event>,
+        shutdown: watch::Sender<bool>,
+        triggers: Arc<Mutex<Vec<Trigger>>>,
+        handle: tokio::task::JoinHandle<Result<()>>,
+    }
+
+    /// Run `watch_loop` on its own task with a recording backup closure.
+    fn spawn_loop(debounce: Duration, max_interval: Duration, backup_time: Duration) -> Harness {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let triggers = Arc::new(Mutex::new(Vec::new()));
+        let recorded = triggers.clone();
+        let handle = tokio::spawn(watch_loop(
+            rx,
+            debounce,
+            max_interval,
+            shutdown_rx,
+            move |trigger| {
+                let recorded = recorded.clone();
+                async move {
+                    tokio::time::sleep(backup_time).await;
+                    recorded.lock().unwrap().push(trigger);
+                    Ok(())
+                }
+            },
+        ));
+        Harness {
+            events: tx,
+            shutdown: shutdown_tx,
+            triggers,
+            handle,
+        }
+    }
+
+    #[tokio::test]
+    async fn burst_of_events_triggers_one_backup_per_debounce_window() {
+        let Harness {
+            events: tx,
+            shutdown,
+            triggers,
+            handle,
+        } = spawn_loop(
+            Duration::from_millis(100),
+            Duration::from_secs(60),
+            Duration::ZERO,
+        );
+
+        for _ in 0..5 {
+            tx.send(modified("/src/a.txt")).unwrap();
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(*triggers.lock().unwrap(), vec![Trigger::Changes]);
+
+        // A second, separate burst gets its own backup
+        for _ in 0..3 {
+            tx.send(modified("/src/b.txt")).unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(
+            *triggers.lock().unwrap(),
+            vec![Trigger::Changes, Trigger::Changes]
+        );
+
+        shutdown.send(true).unwrap();
+        handle.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn max_interval_triggers_without_events() {
+        let Harness {
+            events: _tx,
+            shutdown,
+            triggers,
+            handle,
+        } = spawn_loop(
+            Duration::from_millis(50),
+            Duration::from_millis(100),
+            Duration::ZERO,
+        );
+
+        tokio::time::sleep(Duration::from_millis(350)).await;
+        shutdown.send(true).unwrap();
+        handle.await.unwrap().unwrap();
+
+        let triggers = triggers.lock().unwrap();
+        assert!(triggers.len() >= 2, "got {triggers:?}");
+        assert!(triggers.iter().all(|t| *t == Trigger::Interval));
+    }
+
+    #[tokio::test]
+    async fn shutdown_waits_for_in_progress_backup() {
+        let Harness {
+            events: tx,
+            shutdown,
+            triggers,
+            handle,
+        } = spawn_loop(
+            Duration::from_millis(20),
+            Duration::from_secs(60),
+            Duration::from_millis(200),
+        );
+
+        tx.send(modified("/src/a.txt")).unwrap();
+        // Debounce has elapsed and the backup is running
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        shutdown.send(true).unwrap();
+        handle.await.unwrap().unwrap();
+
+        assert_eq!(*triggers.lock().unwrap(), vec![Trigger::Changes]);
+    }
+
+    #[test]
+    fn filter_ignores_access_events_and_enigma_files() {
+        let filter = EventFilter {
+            ignored_dir: PathBuf::from("/home/u/.enigma"),
+            db_path: "/home/u/data/enigma.db".to_string(),
+        };
+        assert!(filter.is_relevant(&modified("/home/u/data/a.txt")));
+        assert!(filter.is_relevant(
+            &Event::new(EventKind::Create(CreateKind::File)).add_path("/home/u/data/b".into())
+        ));
+        assert!(!filter.is_relevant(
+            &Event::new(EventKind::Access(AccessKind::Any)).add_path("/home/u/data/a.txt".into())
+        ));
+        assert!(!filter.is_relevant(&modified("/home/u/.enigma/keys.enc")));
+        assert!(!filter.is_relevant(&modified("/home/u/data/enigma.db-wal")));
+    }
+}
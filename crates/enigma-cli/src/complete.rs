@@ -0,0 +1,165 @@
+//! Shell completion scripts.
+//!
+//! The scripts come from `clap_complete`, plus a small shell-specific hook
+//! that completes the backup ID of `restore` and `verify` by calling the
+//! hidden `enigma __backup-ids` subcommand, which prints one ID per line.
+
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::Result;
+use clap::Command;
+use clap_complete::Shell;
+
+use enigma_core::config::EnigmaConfig;
+use enigma_core::manifest::ReadonlyManifestDb;
+
+/// Subcommands whose first positional argument is a backup ID.
+const BACKUP_ID_COMMANDS: &[&str] = &["restore", "verify"];
+
+/// All backup IDs in the manifest under `base_dir`, newest first. Completion
+/// must never print errors into the user's prompt, so a missing config or
+/// database yields an empty list.
+pub fn list_backup_ids(base_dir: &Path) -> Vec<String> {
+    backup_ids(base_dir).unwrap_or_default()
+}
+
+fn backup_ids(base_dir: &Path) -> Result<Vec<String>> {
+    let config = EnigmaConfig::load(&EnigmaConfig::default_path(base_dir))?;
+    let db = ReadonlyManifestDb::open(Path::new(&config.enigma.db_path))?;
+    Ok(db.list_backups()?.into_iter().map(|b| b.id).collect())
+}
+
+/// Write the completion script for `shell` to `out`.
+pub fn generate(shell: Shell, cmd: &mut Command, out: &mut impl Write) -> Result<()> {
+    let name = cmd.get_name().to_string();
+    let mut script = Vec::new();
+    clap_complete::generate(shell, cmd, &name, &mut script);
+    let script = String::from_utf8(script)?;
+
+    let script = match shell {
+        Shell::Bash => format!("{script}\n{}", bash_hook(&name)),
+        Shell::Fish => format!("{script}\n{}", fish_hook(&name)),
+        Shell::Zsh => zsh_with_hook(&script, &name),
+        // Other shells get clap's static completions only
+        _ => script,
+    };
+    out.write_all(script.as_bytes())?;
+    Ok(())
+}
+
+/// Wraps the generated `_<name>` function, answering backup IDs itself.
+fn bash_hook(name: &str) -> String {
+    let commands = BACKUP_ID_COMMANDS.join("|");
+    format!(
+        r#"_{name}_backup_ids() {{
+    local cur="${{COMP_WORDS[COMP_CWORD]}}"
+    if [[ ${{COMP_CWORD}} -eq 2 && "$cur" != -* ]]; then
+        case "${{COMP_WORDS[1]}}" in
+            {commands})
+                COMPREPLY=( $(compgen -W "$({name} __backup-ids 2>/dev/null)" -- "$cur") )
+                return 0
+                ;;
+        esac
+    fi
+    _{name} "$@"
+}}
+complete -F _{name}_backup_ids -o nosort -o bashdefault -o default {name}
+"#
+    )
+}
+
+fn fish_hook(name: &str) -> String {
+    let commands = BACKUP_ID_COMMANDS.join(" ");
+    format!(
+        "complete -c {name} -n \"__fish_seen_subcommand_from {commands}\" -f -a \"({name} __backup-ids 2>/dev/null)\"\n"
+    )
+}
+
+/// zsh completes positionals through the action after the last `:` of their
+/// spec, so the `backup_id` specs are pointed at a helper defined up front.
+fn zsh_with_hook(script: &str, name: &str) -> String {
+    let helper = format!(
+        r#"_{name}_backup_ids() {{
+    local -a ids
+    ids=(${{(f)"$({name} __backup-ids 2>/dev/null)"}})
+    _describe 'backup ID' ids
+}}
+"#
+    );
+    let mut out = String::with_capacity(script.len() + helper.len());
+    for (i, line) in script.lines().enumerate() {
+        if line.trim_start().starts_with("':backup_id") {
+            out.push_str(&line.replacen(":_default'", &format!(":_{name}_backup_ids'"), 1));
+        } else {
+            out.push_str(line);
+        }
+        out.push('\n');
+        // After the `#compdef` line, before the script calls `_<name>`
+        if i == 0 {
+            out.push_str(&helper);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+
+    use super::*;
+    use crate::Cli;
+
+    fn script(shell: Shell) -> String {
+        let mut out = Vec::new();
+        generate(shell, &mut Cli::command(), &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn bash_script_covers_subcommands() {
+        let script = script(Shell::Bash);
+        assert!(!script.is_empty());
+        for sub in [
+            "init",
+            "backup",
+            "restore",
+            "list",
+            "verify",
+            "gc",
+            "completions",
+        ] {
+            assert!(script.contains(sub), "missing subcommand {sub}");
+        }
+        assert!(script.contains("enigma __backup-ids"));
+    }
+
+    #[test]
+    fn zsh_and_fish_complete_backup_ids() {
+        let zsh = script(Shell::Zsh);
+        assert!(zsh.starts_with("#compdef enigma\n_enigma_backup_ids()"));
+        assert_eq!(zsh.matches(":_enigma_backup_ids'").count(), 2);
+        assert!(script(Shell::Fish).contains("__fish_seen_subcommand_from restore verify"));
+    }
+
+    #[tokio::test]
+    async fn backup_ids_come_from_the_manifest() {
+        let tmp = tempfile::tempdir().unwrap();
+        let base = tmp.path().join("enigma");
+        assert!(list_backup_ids(&base).is_empty());
+
+        let passphrase = Some("test-passphrase".to_string());
+        crate::commands::init::run(&base, &passphrase, Default::default())
+            .await
+            .unwrap();
+        let config = EnigmaConfig::load(&EnigmaConfig::default_path(&base)).unwrap();
+        let db =
+            enigma_core::manifest::ManifestDb::open(Path::new(&config.enigma.db_path)).unwrap();
+        db.create_backup("b1", "/data").unwrap();
+        db.create_backup("b2", "/data").unwrap();
+
+        let mut ids = list_backup_ids(&base);
+        ids.sort();
+        assert_eq!(ids, vec!["b1", "b2"]);
+    }
+}
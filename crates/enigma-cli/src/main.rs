@@ -1,6 +1,8 @@
 mod commands;
+mod complete;
+mod output;
 
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand};
 use std::path::PathBuf;
 
 #[derive(Parser)]
@@ -17,6 +19,10 @@ struct Cli {
     #[arg(long, global = true, env = "ENIGMA_PASSPHRASE")]
     passphrase: Option<String>,
 
+    /// Print machine-readable JSON instead of human-readable text
+    #[arg(long, global = true)]
+    json: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -24,12 +30,37 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// Initialize Enigma configuration and keyfile
-    Init,
+    Init {
+        /// Argon2id memory cost in KiB for a new keyfile (default: 65536, minimum: 19456)
+        #[arg(long)]
+        argon2_memory_kib: Option<u32>,
+        /// Argon2id passes for a new keyfile (default: 3, minimum: 2)
+        #[arg(long)]
+        argon2_iterations: Option<u32>,
+        /// Argon2id lanes for a new keyfile (default: 4, range: 1-64)
+        #[arg(long)]
+        argon2_parallelism: Option<u32>,
+    },
 
     /// Backup a directory
     Backup {
         /// Path to the directory or file to backup
         path: PathBuf,
+        /// Attach a tag to the backup (repeatable, format: key=value)
+        #[arg(long = "tag", value_parser = parse_tag)]
+        tags: Vec<(String, String)>,
+        /// Skip files matching this glob, relative to the backup root (repeatable)
+        #[arg(long = "exclude")]
+        exclude: Vec<String>,
+        /// Read exclude patterns from a file, one per line
+        #[arg(long)]
+        exclude_from: Option<PathBuf>,
+        /// Skip common cache and build directories (.cache/, target/, node_modules/, ...)
+        #[arg(long)]
+        exclude_caches: bool,
+        /// Report what would be uploaded without uploading or recording anything
+        #[arg(long, conflicts_with = "tags")]
+        dry_run: bool,
     },
 
     /// Restore a backup
@@ -47,10 +78,18 @@ enum Commands {
         /// List matching files without restoring
         #[arg(long)]
         list: bool,
+        /// Re-read each restored file and check it against its backup hash
+        /// (default: `restore_verify` in the config, itself on by default)
+        #[arg(long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+        verify: Option<bool>,
     },
 
     /// List all backups
-    List,
+    List {
+        /// Only list backups carrying this tag (repeatable, format: key=value)
+        #[arg(long = "filter-tag", value_parser = parse_tag)]
+        filter_tags: Vec<(String, String)>,
+    },
 
     /// Show status of the latest backup
     Status,
@@ -59,16 +98,98 @@ enum Commands {
     Verify {
         /// Backup ID to verify
         backup_id: String,
+        /// Only re-download the chunks on the Merkle audit path of a random leaf
+        #[arg(long)]
+        fast: bool,
+    },
+
+    /// Watch a directory and back it up whenever it changes
+    Watch {
+        /// Path to the directory to watch
+        path: PathBuf,
+        /// Wait for this many milliseconds without changes before backing up
+        #[arg(long, default_value_t = 2000)]
+        debounce_ms: u64,
+        /// Back up at least this often (seconds), even without changes
+        #[arg(long, default_value_t = 3600)]
+        max_interval_s: u64,
+    },
+
+    /// Mount a backup as a read-only filesystem until Ctrl-C or `umount`
+    #[cfg(feature = "fuse")]
+    Mount {
+        /// Backup ID to mount
+        backup_id: String,
+        /// Empty directory to mount it on
+        mountpoint: PathBuf,
     },
 
     /// Show current configuration
-    Config,
+    Config {
+        #[command(subcommand)]
+        action: Option<ConfigCommands>,
+    },
 
     /// Garbage collect orphaned chunks
     Gc {
         /// List orphans without deleting
         #[arg(long)]
         dry_run: bool,
+        /// Also download and re-hash every referenced chunk (resumable)
+        #[arg(long)]
+        verify: bool,
+        /// Queue chunks that fail verification for re-upload
+        #[arg(long, requires = "verify")]
+        fix: bool,
+        /// Maximum provider downloads per second while verifying
+        #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..))]
+        verify_rate: u32,
+    },
+
+    /// Check chunk records against storage and heal manifest inconsistencies
+    Repair {
+        /// Report problems without changing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Drop file and object references to chunks missing from storage
+        #[arg(long)]
+        fix_missing: bool,
+        /// Delete orphaned chunks, from storage and the manifest
+        #[arg(long)]
+        fix_orphans: bool,
+    },
+
+    /// Write the whole manifest to an encrypted file for disaster recovery
+    Export {
+        /// Path of the encrypted export file
+        output: PathBuf,
+    },
+
+    /// Restore the manifest from an encrypted export file
+    Import {
+        /// Path of the encrypted export file
+        input: PathBuf,
+        /// Keep existing records instead of wiping the manifest first
+        #[arg(long)]
+        merge: bool,
+    },
+
+    /// Re-encrypt chunks stored under old keys with the current key
+    Reencrypt {
+        /// Count the chunks to re-encrypt without touching them
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Move stored chunks to another storage key layout
+    MigrateLayout {
+        /// Target layout: two-level (enigma/chunks/ab/cd/…) or three-level
+        /// (enigma/chunks/ab/cd/ef/…)
+        #[arg(value_parser = ["two-level", "three-level"])]
+        scheme: String,
+        /// Count the chunks to move without touching them
+        #[arg(long)]
+        dry_run: bool,
     },
 
     /// Encrypt a credential value for use in TOML config
@@ -76,6 +197,70 @@ enum Commands {
         /// The plaintext value to encrypt
         value: String,
     },
+
+    /// Generate an Ed25519 keypair for signing backup records
+    KeyGen {
+        /// Path of the private key file; the public key is written to `<output>.pub`
+        output: PathBuf,
+    },
+
+    /// Print a shell completion script, e.g.
+    /// `enigma completions bash > ~/.local/share/bash-completion/completions/enigma`
+    Completions {
+        /// Shell to generate the script for
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
+
+    /// Print every backup ID, one per line (used by the completion scripts)
+    #[command(name = "__backup-ids", hide = true)]
+    BackupIds,
+}
+
+#[derive(Subcommand)]
+enum ConfigCommands {
+    /// Check the configuration and list every problem found
+    Validate,
+}
+
+/// Parse a `key=value` tag argument.
+fn parse_tag(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((k, v)) if !k.is_empty() => Ok((k.to_string(), v.to_string())),
+        _ => Err(format!("invalid tag '{s}', expected key=value")),
+    }
+}
+
+/// Argon2id parameters for the local key provider, from the config.
+pub fn argon2_params(
+    config: &enigma_core::config::Argon2Config,
+) -> enigma_keys::local::Argon2Params {
+    enigma_keys::local::Argon2Params {
+        memory_kib: config.memory_kib,
+        iterations: config.iterations,
+        parallelism: config.parallelism,
+    }
+}
+
+/// Key provider settings from `config`, with the passphrase if one was read.
+pub fn key_provider_options<'a>(
+    config: &'a enigma_core::config::EnigmaSettings,
+    passphrase: Option<&'a str>,
+) -> enigma_keys::factory::KeyProviderOptions<'a> {
+    enigma_keys::factory::KeyProviderOptions {
+        key_providers: &config.key_providers,
+        passphrase: passphrase.map(str::as_bytes),
+        keyfile_path: &config.keyfile_path,
+        vault_url: config.vault_url.as_deref(),
+        gcp_project_id: config.gcp_project_id.as_deref(),
+        aws_region: config.aws_region.as_deref(),
+        secret_prefix: config.secret_prefix.as_deref(),
+        pkcs11_library: config.pkcs11_library.as_deref(),
+        pkcs11_slot: config.pkcs11_slot,
+        aws_kms_key_id: config.aws_kms_key_id.as_deref(),
+        aws_kms_key_store: config.aws_kms_key_store.as_deref(),
+        argon2: argon2_params(&config.argon2),
+    }
 }
 
 /// Get passphrase from CLI arg, env var, or interactive prompt.
@@ -112,16 +297,47 @@ fn main() -> anyhow::Result<()> {
     let rt = tokio::runtime::Runtime::new()?;
 
     match cli.command {
-        Commands::Init => rt.block_on(commands::init::run(&base_dir, &cli.passphrase)),
-        Commands::Backup { ref path } => {
-            rt.block_on(commands::backup::run(path, &base_dir, &cli.passphrase))
-        }
+        Commands::Init {
+            argon2_memory_kib,
+            argon2_iterations,
+            argon2_parallelism,
+        } => rt.block_on(commands::init::run(
+            &base_dir,
+            &cli.passphrase,
+            commands::init::Argon2Flags {
+                memory_kib: argon2_memory_kib,
+                iterations: argon2_iterations,
+                parallelism: argon2_parallelism,
+            },
+        )),
+        Commands::Backup {
+            ref path,
+            ref tags,
+            ref exclude,
+            ref exclude_from,
+            exclude_caches,
+            dry_run,
+        } => commands::exclude::collect_patterns(exclude, exclude_from.as_deref(), exclude_caches)
+            .and_then(|exclude| {
+                if dry_run {
+                    return commands::backup::dry_run(path, &base_dir, &exclude, cli.json);
+                }
+                rt.block_on(commands::backup::run(
+                    path,
+                    &base_dir,
+                    &cli.passphrase,
+                    tags,
+                    &exclude,
+                    cli.json,
+                ))
+            }),
         Commands::Restore {
             ref backup_id,
             ref dest,
             ref path,
             ref glob,
             list,
+            verify,
         } => rt.block_on(commands::restore::run(
             backup_id,
             dest,
@@ -130,18 +346,114 @@ fn main() -> anyhow::Result<()> {
             path.as_deref(),
             glob.as_deref(),
             list,
+            verify,
+            cli.json,
+        )),
+        Commands::List { ref filter_tags } => commands::list::run(&base_dir, filter_tags, cli.json),
+        Commands::Status => commands::status::run(&base_dir, cli.json),
+        Commands::Verify {
+            ref backup_id,
+            fast,
+        } => rt.block_on(commands::verify::run(
+            backup_id,
+            &base_dir,
+            &cli.passphrase,
+            fast,
+            cli.json,
+        )),
+        Commands::Watch {
+            ref path,
+            debounce_ms,
+            max_interval_s,
+        } => rt.block_on(commands::watch::run(
+            path,
+            &base_dir,
+            &cli.passphrase,
+            debounce_ms,
+            max_interval_s,
+        )),
+        #[cfg(feature = "fuse")]
+        Commands::Mount {
+            ref backup_id,
+            ref mountpoint,
+        } => rt.block_on(commands::mount::run(
+            backup_id,
+            mountpoint,
+            &base_dir,
+            &cli.passphrase,
+        )),
+        Commands::Config { action: None } => commands::config::run(&base_dir, cli.json),
+        Commands::Config {
+            action: Some(ConfigCommands::Validate),
+        } => commands::config::validate(&base_dir, cli.json),
+        Commands::Gc {
+            dry_run,
+            verify,
+            fix,
+            verify_rate,
+        } => rt.block_on(commands::gc::run(
+            &base_dir,
+            &cli.passphrase,
+            &commands::gc::GcOptions {
+                dry_run,
+                verify,
+                fix,
+                verify_rate,
+            },
+            cli.json,
+        )),
+        Commands::Repair {
+            dry_run,
+            fix_missing,
+            fix_orphans,
+        } => rt.block_on(commands::repair::run(
+            &base_dir,
+            &commands::repair::RepairOptions {
+                dry_run,
+                fix_missing,
+                fix_orphans,
+            },
+            cli.json,
+        )),
+        Commands::Export { ref output } => rt.block_on(commands::export::run(
+            &base_dir,
+            &cli.passphrase,
+            output,
+            cli.json,
+        )),
+        Commands::Import { ref input, merge } => rt.block_on(commands::import::run(
+            &base_dir,
+            &cli.passphrase,
+            input,
+            merge,
+            cli.json,
+        )),
+        Commands::Reencrypt { dry_run } => rt.block_on(commands::reencrypt::run(
+            &base_dir,
+            &cli.passphrase,
+            dry_run,
+            cli.json,
+        )),
+        Commands::MigrateLayout {
+            ref scheme,
+            dry_run,
+        } => rt.block_on(commands::migrate_layout::run(
+            &base_dir, scheme, dry_run, cli.json,
         )),
-        Commands::List => commands::list::run(&base_dir),
-        Commands::Status => commands::status::run(&base_dir),
-        Commands::Verify { ref backup_id } => {
-            rt.block_on(commands::verify::run(backup_id, &base_dir, &cli.passphrase))
-        }
-        Commands::Config => commands::config::run(&base_dir),
-        Commands::Gc { dry_run } => rt.block_on(commands::gc::run(&base_dir, dry_run)),
         Commands::EncryptCred { ref value } => rt.block_on(commands::encrypt_cred::run(
             value,
             &base_dir,
             &cli.passphrase,
         )),
+        Commands::KeyGen { ref output } => commands::keygen::run(output, cli.json),
+        Commands::Completions { shell } => {
+            complete::generate(shell, &mut Cli::command(), &mut std::io::stdout())
+        }
+        Commands::BackupIds => {
+            for id in complete::list_backup_ids(&base_dir) {
+                println!("{id}");
+            }
+            Ok(())
+        }
     }
 }
@@ -0,0 +1,143 @@
+//! Machine-readable output for `--json`.
+//!
+//! Every command prints a single JSON object on one line of stdout. The
+//! object always carries `version` (the schema version below) and `command`;
+//! the remaining fields are command specific. Human-oriented messages and
+//! progress bars are suppressed or go to stderr in JSON mode.
+
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::{Map, Value, json};
+use std::io::Write;
+
+/// Version of the JSON output schema. Bump on incompatible changes.
+pub const SCHEMA_VERSION: u64 = 1;
+
+/// Writes versioned JSON documents, one per line.
+pub struct JsonPrinter<W: Write> {
+    out: W,
+}
+
+impl JsonPrinter<std::io::Stdout> {
+    pub fn stdout() -> Self {
+        Self::new(std::io::stdout())
+    }
+}
+
+impl<W: Write> JsonPrinter<W> {
+    pub fn new(out: W) -> Self {
+        Self { out }
+    }
+
+    /// Print `body` for `command`. Object bodies are flattened into the
+    /// envelope; anything else is placed under `data`.
+    pub fn print(&mut self, command: &str, body: Value) -> Result<()> {
+        let mut doc = Map::new();
+        doc.insert("version".to_string(), json!(SCHEMA_VERSION));
+        doc.insert("command".to_string(), json!(command));
+        match body {
+            Value::Object(fields) => doc.extend(fields),
+            other => {
+                doc.insert("data".to_string(), other);
+            }
+        }
+        serde_json::to_writer(&mut self.out, &Value::Object(doc))?;
+        writeln!(self.out)?;
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// Outcome of a `backup` or `restore` run.
+#[derive(Debug, Default, Serialize)]
+pub struct RunReport {
+    pub status: String,
+    pub files_processed: u64,
+    pub bytes_processed: u64,
+    pub chunks_new: u64,
+    pub chunks_deduped: u64,
+    pub errors: Vec<String>,
+}
+
+impl RunReport {
+    pub fn completed(mut self) -> Self {
+        self.status = "completed".to_string();
+        self
+    }
+
+    pub fn failed(mut self, error: &anyhow::Error) -> Self {
+        self.status = "failed".to_string();
+        self.errors.push(format!("{error:#}"));
+        self
+    }
+
+    /// The report as a JSON body, with `extra` fields merged in.
+    pub fn to_json(&self, extra: Value) -> Result<Value> {
+        let mut body = serde_json::to_value(self)?;
+        if let (Value::Object(fields), Value::Object(extra)) = (&mut body, extra) {
+            fields.extend(extra);
+        }
+        Ok(body)
+    }
+}
+
+/// Render `body` through a [`JsonPrinter`] and parse it back.
+#[cfg(test)]
+pub fn render(command: &str, body: Value) -> Value {
+    let mut buf = Vec::new();
+    JsonPrinter::new(&mut buf).print(command, body).unwrap();
+    let text = String::from_utf8(buf).unwrap();
+    assert_eq!(text.lines().count(), 1);
+    serde_json::from_str(&text).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn envelope_is_versioned() {
+        let doc = render("list", json!({ "backups": [] }));
+        assert_eq!(doc["version"], 1);
+        assert_eq!(doc["command"], "list");
+        assert!(doc["backups"].is_array());
+    }
+
+    #[test]
+    fn non_object_body_goes_under_data() {
+        let doc = render("status", Value::Null);
+        assert_eq!(doc["version"], 1);
+        assert!(doc["data"].is_null());
+    }
+
+    #[test]
+    fn run_report_fields() {
+        let report = RunReport {
+            files_processed: 3,
+            bytes_processed: 42,
+            chunks_new: 2,
+            chunks_deduped: 1,
+            ..Default::default()
+        }
+        .completed();
+        let doc = render(
+            "backup",
+            report.to_json(json!({ "backup_id": "b1" })).unwrap(),
+        );
+        assert_eq!(doc["status"], "completed");
+        assert_eq!(doc["files_processed"], 3);
+        assert_eq!(doc["bytes_processed"], 42);
+        assert_eq!(doc["chunks_new"], 2);
+        assert_eq!(doc["chunks_deduped"], 1);
+        assert_eq!(doc["errors"], json!([]));
+        assert_eq!(doc["backup_id"], "b1");
+    }
+
+    #[test]
+    fn failed_run_report_lists_error() {
+        let report = RunReport::default().failed(&anyhow::anyhow!("disk full"));
+        let doc = render("restore", report.to_json(json!({})).unwrap());
+        assert_eq!(doc["status"], "failed");
+        assert_eq!(doc["errors"], json!(["disk full"]));
+    }
+}
@@ -0,0 +1,171 @@
+//! Compression codec benchmark: LZ4 vs zstd level 1 vs zstd level 3.
+//!
+//! Three 4 MB workloads:
+//! - source code: this crate's own `.rs` files (high compressibility)
+//! - images: pseudo-random bytes standing in for JPEG data, whose
+//!   entropy-coded payload is already near-incompressible (low)
+//! - web logs: synthetic combined-log-format access lines (medium)
+//!
+//! Throughput comes from criterion; the compression ratio of each codec is
+//! printed once per workload before it is measured.
+//!
+//! Run:
+//!   cargo bench -p enigma-core --bench compression
+use std::path::Path;
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+
+use enigma_core::compression::{CompressionAlgorithm, compress, decompress_chunk};
+
+const WORKLOAD_SIZE: usize = 4 * 1024 * 1024;
+
+const CODECS: &[(&str, CompressionAlgorithm, i32)] = &[
+    ("lz4", CompressionAlgorithm::Lz4, 0),
+    ("zstd-1", CompressionAlgorithm::Zstd, 1),
+    ("zstd-3", CompressionAlgorithm::Zstd, 3),
+];
+
+/// Deterministic xorshift64 so every run measures the same bytes.
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[(self.next() % items.len() as u64) as usize]
+    }
+}
+
+fn collect_sources(dir: &Path, out: &mut Vec<u8>) {
+    let mut entries: Vec<_> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .collect();
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            collect_sources(&path, out);
+        } else if path.extension().is_some_and(|e| e == "rs") {
+            out.extend_from_slice(&std::fs::read(&path).unwrap());
+        }
+    }
+}
+
+fn source_code() -> Vec<u8> {
+    let mut sources = Vec::new();
+    collect_sources(
+        &Path::new(env!("CARGO_MANIFEST_DIR")).join("src"),
+        &mut sources,
+    );
+    sources
+        .iter()
+        .copied()
+        .cycle()
+        .take(WORKLOAD_SIZE)
+        .collect()
+}
+
+fn images() -> Vec<u8> {
+    let mut rng = XorShift(0x9E37_79B9_7F4A_7C15);
+    let mut data = Vec::with_capacity(WORKLOAD_SIZE);
+    while data.len() < WORKLOAD_SIZE {
+        data.extend_from_slice(&rng.next().to_le_bytes());
+    }
+    data.truncate(WORKLOAD_SIZE);
+    data
+}
+
+fn web_logs() -> Vec<u8> {
+    const METHODS: &[&str] = &["GET", "GET", "GET", "POST", "PUT", "DELETE"];
+    const PATHS: &[&str] = &[
+        "/",
+        "/index.html",
+        "/api/status",
+        "/api/storage/backups",
+        "/static/app.js",
+        "/static/style.css",
+        "/bucket/photos/2024/img_0001.jpg",
+        "/bucket/reports/q3.pdf",
+    ];
+    const STATUSES: &[&str] = &["200", "200", "200", "204", "304", "404", "500"];
+    const AGENTS: &[&str] = &[
+        "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0",
+        "Mozilla/5.0 (Macintosh; Intel Mac OS X 14_2) AppleWebKit/605.1.15 Safari/605.1.15",
+        "aws-sdk-rust/1.1.0 os/linux lang/rust",
+        "curl/8.5.0",
+    ];
+
+    let mut rng = XorShift(0xD1B5_4A32_D192_ED03);
+    let mut data = Vec::with_capacity(WORKLOAD_SIZE + 512);
+    let mut ts = 1_700_000_000u64;
+    while data.len() < WORKLOAD_SIZE {
+        ts += rng.next() % 3;
+        let ip = rng.next();
+        let line = format!(
+            "10.{}.{}.{} - - [{}] \"{} {} HTTP/1.1\" {} {} \"-\" \"{}\"\n",
+            ip & 0xFF,
+            (ip >> 8) & 0xFF,
+            (ip >> 16) & 0xFF,
+            ts,
+            rng.pick(METHODS),
+            rng.pick(PATHS),
+            rng.pick(STATUSES),
+            rng.next() % 100_000,
+            rng.pick(AGENTS),
+        );
+        data.extend_from_slice(line.as_bytes());
+    }
+    data.truncate(WORKLOAD_SIZE);
+    data
+}
+
+fn bench_workload(c: &mut Criterion, name: &str, data: &[u8]) {
+    for (codec, algorithm, level) in CODECS {
+        let compressed = compress(data, *algorithm, *level).unwrap();
+        println!(
+            "{name}/{codec}: {} -> {} bytes, ratio {:.3}",
+            data.len(),
+            compressed.len(),
+            data.len() as f64 / compressed.len() as f64
+        );
+    }
+
+    let mut group = c.benchmark_group(format!("compress/{name}"));
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    for (codec, algorithm, level) in CODECS {
+        group.bench_with_input(BenchmarkId::from_parameter(codec), data, |b, data| {
+            b.iter(|| compress(data, *algorithm, *level).unwrap())
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group(format!("decompress/{name}"));
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    for (codec, algorithm, level) in CODECS {
+        let compressed = compress(data, *algorithm, *level).unwrap();
+        group.bench_with_input(
+            BenchmarkId::from_parameter(codec),
+            &compressed,
+            |b, compressed| b.iter(|| decompress_chunk(compressed).unwrap()),
+        );
+    }
+    group.finish();
+}
+
+fn compression(c: &mut Criterion) {
+    bench_workload(c, "source-code", &source_code());
+    bench_workload(c, "images", &images());
+    bench_workload(c, "web-logs", &web_logs());
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(20);
+    targets = compression
+}
+criterion_main!(benches);
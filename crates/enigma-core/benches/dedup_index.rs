@@ -0,0 +1,73 @@
+//! Dedup index lookups against a manifest of 1,000,000 chunks:
+//! - index-hit / index-miss: `DedupIndex::contains` for a stored chunk and
+//!   for a new one
+//! - db-miss: the `chunks` lookup a backup did for every new chunk before
+//!   the index existed
+//!
+//! The manifest is built once, on disk in WAL mode; the time to load the
+//! index from it is printed before the lookups are measured.
+//!
+//! Run:
+//!   cargo bench -p enigma-core --bench dedup_index
+use std::hint::black_box;
+use std::time::Instant;
+
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+
+use enigma_core::config::EnigmaConfig;
+use enigma_core::dedup::{DedupIndex, compute_hash};
+use enigma_core::manifest::ManifestDb;
+use enigma_core::types::{ChunkHash, ProviderType};
+
+const CHUNKS: u32 = 1_000_000;
+const LOOKUPS: u32 = 1_000;
+
+/// Hash of stored chunk `i`; new chunks use `CHUNKS + i`.
+fn chunk_hash(i: u32) -> ChunkHash {
+    compute_hash(&i.to_le_bytes())
+}
+
+fn dedup_index(c: &mut Criterion) {
+    let dir = tempfile::tempdir().unwrap();
+    let mut db = ManifestDb::open(&dir.path().join("enigma.db")).unwrap();
+    let provider_id = db
+        .insert_provider("local", ProviderType::Local, "/tmp/enigma", None, 1)
+        .unwrap();
+    let mut bulk = db.begin_bulk_insert().unwrap();
+    for i in 0..CHUNKS {
+        let hash = chunk_hash(i).to_hex();
+        bulk.insert_new_chunk(&hash, &[0; 12], "k", provider_id, &hash, 4096, 4112, None)
+            .unwrap();
+    }
+    bulk.commit().unwrap();
+
+    let settings = EnigmaConfig::default_config(dir.path()).enigma;
+    let started = Instant::now();
+    let index = DedupIndex::load_from_db(&db, &settings).unwrap();
+    println!("loaded {CHUNKS} chunks in {:?}", started.elapsed());
+
+    let stored: Vec<ChunkHash> = (0..LOOKUPS).map(chunk_hash).collect();
+    let new: Vec<ChunkHash> = (CHUNKS..CHUNKS + LOOKUPS).map(chunk_hash).collect();
+    let new_hex: Vec<String> = new.iter().map(ChunkHash::to_hex).collect();
+
+    let mut group = c.benchmark_group("dedup-index-1m-chunks");
+    group.throughput(Throughput::Elements(LOOKUPS as u64));
+    group.bench_function("index-hit", |b| {
+        b.iter(|| stored.iter().filter(|h| index.contains(h)).count())
+    });
+    group.bench_function("index-miss", |b| {
+        b.iter(|| new.iter().filter(|h| index.contains(h)).count())
+    });
+    group.bench_function("db-miss", |b| {
+        b.iter(|| {
+            new_hex
+                .iter()
+                .filter(|h| db.get_chunk_info(black_box(h)).unwrap().is_some())
+                .count()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, dedup_index);
+criterion_main!(benches);
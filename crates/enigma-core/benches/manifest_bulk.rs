@@ -0,0 +1,140 @@
+//! Manifest writes for a backup of 10,000 files with 3 chunks each, one of
+//! them shared by every file:
+//! - per-file: `ManifestDb` methods in one transaction per file, as the
+//!   backup command used to write them
+//! - bulk-per-file: one `BulkInsertHandle` per file, file chunks mapped in
+//!   a single multi-row insert (what `enigma backup` does now)
+//! - bulk: one `BulkInsertHandle` for the whole backup
+//!
+//! Each iteration writes into a fresh on-disk database in WAL mode, so
+//! commit costs are included.
+//!
+//! Run:
+//!   cargo bench -p enigma-core --bench manifest_bulk
+use std::time::Duration;
+
+use criterion::{BatchSize, Criterion, Throughput, criterion_group, criterion_main};
+use tempfile::TempDir;
+
+use enigma_core::manifest::{BulkInsertHandle, ManifestDb};
+use enigma_core::types::ProviderType;
+
+const FILES: usize = 10_000;
+const CHUNKS_PER_FILE: usize = 3;
+const BACKUP_ID: &str = "01234567-abcd-7000-0000-000000000001";
+const SHARED_CHUNK: &str = "5ea7ed0000000000000000000000000000000000000000000000000000000000";
+
+struct Fixture {
+    _dir: TempDir,
+    db: ManifestDb,
+    provider_id: i64,
+}
+
+fn fixture() -> Fixture {
+    let dir = tempfile::tempdir().unwrap();
+    let db = ManifestDb::open(&dir.path().join("enigma.db")).unwrap();
+    let provider_id = db
+        .insert_provider("local", ProviderType::Local, "/tmp/enigma", None, 1)
+        .unwrap();
+    db.create_backup(BACKUP_ID, "/data").unwrap();
+    Fixture {
+        _dir: dir,
+        db,
+        provider_id,
+    }
+}
+
+/// Chunk hashes of file `file`; the last one is shared by all files.
+fn chunk_hashes(file: usize) -> [String; CHUNKS_PER_FILE] {
+    [
+        format!("{:064x}", file * 2),
+        format!("{:064x}", file * 2 + 1),
+        SHARED_CHUNK.to_string(),
+    ]
+}
+
+fn per_file(f: &Fixture) {
+    for file in 0..FILES {
+        let db = &f.db;
+        let file_id = db
+            .insert_backup_file(BACKUP_ID, &format!("f{file}"), 12_288, None, "h", 3)
+            .unwrap();
+        db.begin_transaction().unwrap();
+        for (idx, hash) in chunk_hashes(file).iter().enumerate() {
+            if db
+                .insert_or_dedup_chunk(hash, &[0; 12], "k", f.provider_id, hash, 4096, 4112, None)
+                .unwrap()
+            {
+                db.set_chunk_hmac_tag(hash, &[0; 32]).unwrap();
+            }
+            db.insert_file_chunk(file_id, hash, idx as u32, idx as u64 * 4096)
+                .unwrap();
+        }
+        db.commit_transaction().unwrap();
+    }
+}
+
+fn write_file(bulk: &mut BulkInsertHandle<'_>, provider_id: i64, file: usize) {
+    let file_id = bulk
+        .insert_backup_file(BACKUP_ID, &format!("f{file}"), 12_288, None, "h", 3)
+        .unwrap();
+    let hashes = chunk_hashes(file);
+    for hash in &hashes {
+        if bulk
+            .insert_or_dedup_chunk(hash, &[0; 12], "k", provider_id, hash, 4096, 4112, None)
+            .unwrap()
+        {
+            bulk.set_chunk_hmac_tag(hash, &[0; 32]).unwrap();
+        }
+    }
+    let records: Vec<(i64, &str, u32, u64)> = hashes
+        .iter()
+        .enumerate()
+        .map(|(idx, hash)| (file_id, hash.as_str(), idx as u32, idx as u64 * 4096))
+        .collect();
+    bulk.batch_insert_file_chunks(&records).unwrap();
+}
+
+fn bulk_per_file(f: &mut Fixture) {
+    for file in 0..FILES {
+        let mut bulk = f.db.begin_bulk_insert().unwrap();
+        write_file(&mut bulk, f.provider_id, file);
+        bulk.commit().unwrap();
+    }
+}
+
+fn bulk(f: &mut Fixture) {
+    let mut bulk = f.db.begin_bulk_insert().unwrap();
+    for file in 0..FILES {
+        write_file(&mut bulk, f.provider_id, file);
+    }
+    bulk.commit().unwrap();
+}
+
+fn manifest_bulk(c: &mut Criterion) {
+    let mut group = c.benchmark_group("backup-10k-files");
+    group.throughput(Throughput::Elements(FILES as u64));
+    group.bench_function("per-file", |b| {
+        b.iter_batched(fixture, |f| per_file(&f), BatchSize::PerIteration)
+    });
+    group.bench_function("bulk-per-file", |b| {
+        b.iter_batched(
+            fixture,
+            |mut f| bulk_per_file(&mut f),
+            BatchSize::PerIteration,
+        )
+    });
+    group.bench_function("bulk", |b| {
+        b.iter_batched(fixture, |mut f| bulk(&mut f), BatchSize::PerIteration)
+    });
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default()
+        .sample_size(10)
+        .measurement_time(Duration::from_secs(30));
+    targets = manifest_bulk
+}
+criterion_main!(benches);
@@ -1,32 +1,86 @@
-use std::io::Read;
+use std::io::{Read, Write};
 
-use crate::error::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{EnigmaError, Result};
 
 /// Maximum decompressed output size (64 MB) to prevent decompression bombs.
 pub const MAX_DECOMPRESS_SIZE: usize = 64 * 1024 * 1024;
 
+/// Frame magic numbers, as they appear at the start of the data.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+const LZ4_MAGIC: [u8; 4] = [0x04, 0x22, 0x4D, 0x18];
+
+/// Codec used for new chunks. Both write self-describing frames, so the
+/// read path detects the codec and existing chunks stay readable whatever
+/// this is set to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompressionAlgorithm {
+    /// Better ratio; `level` 1-22 trades speed for size.
+    #[default]
+    Zstd,
+    /// Faster, lower ratio; ignores `level`.
+    Lz4,
+}
+
+/// Compress a chunk with `algorithm`. `level` only applies to zstd.
+pub fn compress(data: &[u8], algorithm: CompressionAlgorithm, level: i32) -> Result<Vec<u8>> {
+    match algorithm {
+        CompressionAlgorithm::Zstd => compress_chunk(data, level),
+        CompressionAlgorithm::Lz4 => compress_lz4(data),
+    }
+}
+
 /// Compress a chunk using zstd at the given level (1-22, default 3).
 pub fn compress_chunk(data: &[u8], level: i32) -> Result<Vec<u8>> {
-    zstd::encode_all(data, level).map_err(|e| crate::error::EnigmaError::Compression(e.to_string()))
+    zstd::encode_all(data, level).map_err(|e| EnigmaError::Compression(e.to_string()))
 }
 
-/// Decompress a zstd-compressed chunk.
+/// Compress a chunk as an LZ4 frame.
+pub fn compress_lz4(data: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::new());
+    encoder
+        .write_all(data)
+        .map_err(|e| EnigmaError::Compression(e.to_string()))?;
+    encoder
+        .finish()
+        .map_err(|e| EnigmaError::Compression(e.to_string()))
+}
+
+/// Decompress a compressed chunk, detecting zstd or LZ4 from its frame
+/// header.
 ///
 /// Enforces a maximum output size of [`MAX_DECOMPRESS_SIZE`] to prevent
 /// decompression bombs.
 pub fn decompress_chunk(data: &[u8]) -> Result<Vec<u8>> {
-    let decoder = zstd::Decoder::new(data)
-        .map_err(|e| crate::error::EnigmaError::Compression(e.to_string()))?;
+    if data.starts_with(&LZ4_MAGIC) {
+        return decompress_lz4(data);
+    }
+    if !data.starts_with(&ZSTD_MAGIC) {
+        return Err(EnigmaError::Compression(
+            "unknown compression frame header".to_string(),
+        ));
+    }
+    let decoder = zstd::Decoder::new(data).map_err(|e| EnigmaError::Compression(e.to_string()))?;
+    read_limited(decoder)
+}
 
+/// Decompress an LZ4 frame, with the same output limit as [`decompress_chunk`].
+pub fn decompress_lz4(data: &[u8]) -> Result<Vec<u8>> {
+    read_limited(lz4_flex::frame::FrameDecoder::new(data))
+}
+
+fn read_limited(decoder: impl Read) -> Result<Vec<u8>> {
     // Read up to MAX_DECOMPRESS_SIZE + 1 to detect overflow
     let mut limited = decoder.take(MAX_DECOMPRESS_SIZE as u64 + 1);
     let mut output = Vec::new();
     limited
         .read_to_end(&mut output)
-        .map_err(|e| crate::error::EnigmaError::Compression(e.to_string()))?;
+        .map_err(|e| EnigmaError::Compression(e.to_string()))?;
 
     if output.len() > MAX_DECOMPRESS_SIZE {
-        return Err(crate::error::EnigmaError::Compression(format!(
+        return Err(EnigmaError::Compression(format!(
             "Decompressed data exceeds maximum size of {} bytes",
             MAX_DECOMPRESS_SIZE
         )));
@@ -53,4 +107,39 @@ mod tests {
         let decompressed = decompress_chunk(&compressed).unwrap();
         assert!(decompressed.is_empty());
     }
+
+    #[test]
+    fn lz4_roundtrip() {
+        let original = b"hello world, this is a test of lz4 compression in enigma".repeat(100);
+        let compressed = compress_lz4(&original).unwrap();
+        assert!(compressed.len() < original.len());
+        assert!(compressed.starts_with(&LZ4_MAGIC));
+        assert_eq!(decompress_lz4(&compressed).unwrap(), original);
+    }
+
+    #[test]
+    fn lz4_empty_data() {
+        let compressed = compress_lz4(b"").unwrap();
+        assert!(decompress_lz4(&compressed).unwrap().is_empty());
+    }
+
+    #[test]
+    fn decompress_detects_codec() {
+        let original = b"same data, either codec".repeat(50);
+        for algorithm in [CompressionAlgorithm::Zstd, CompressionAlgorithm::Lz4] {
+            let compressed = compress(&original, algorithm, 3).unwrap();
+            assert_eq!(decompress_chunk(&compressed).unwrap(), original);
+        }
+    }
+
+    #[test]
+    fn unknown_frame_rejected() {
+        assert!(decompress_chunk(b"not a compressed frame").is_err());
+    }
+
+    #[test]
+    fn lz4_bomb_rejected() {
+        let compressed = compress_lz4(&vec![0u8; MAX_DECOMPRESS_SIZE + 1]).unwrap();
+        assert!(decompress_chunk(&compressed).is_err());
+    }
 }
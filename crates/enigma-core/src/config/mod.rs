@@ -1,8 +1,15 @@
 pub mod credentials;
 
+use crate::compression::CompressionAlgorithm;
+use crate::crypto::signing;
 use crate::error::{EnigmaError, Result};
+use crate::limits::IO_PRIORITIES;
+use crate::manifest::CACHED_QUERIES;
+use crate::notify::NotificationConfig;
 use crate::types::{ChunkStrategy, DistributionStrategy, ProviderType};
+use crate::webhook::{EVENT_TYPES, WebhookConfig};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
 /// Top-level Enigma configuration stored as TOML.
@@ -11,6 +18,12 @@ pub struct EnigmaConfig {
     pub enigma: EnigmaSettings,
     #[serde(default)]
     pub providers: Vec<ProviderConfig>,
+    /// Email notifications about finished backups; unset sends none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notifications: Option<NotificationConfig>,
+    /// HTTP endpoints POSTed backup and storage events.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub webhooks: Vec<WebhookConfig>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,12 +36,19 @@ pub struct EnigmaSettings {
     /// Distribution strategy.
     #[serde(default)]
     pub distribution: DistributionStrategy,
-    /// Key provider type ("local" or "vault").
+    /// Key provider type ("local", "azure-keyvault", "gcp-secretmanager", "aws-secretsmanager",
+    /// "aws-kms", "pkcs11" or "aggregate").
     #[serde(default = "default_key_provider")]
     pub key_provider: String,
+    /// Provider types chained by key_provider = "aggregate", primary first.
+    #[serde(default)]
+    pub key_providers: Vec<String>,
     /// Path to the encrypted keyfile (for local key provider).
     #[serde(default = "default_keyfile_path")]
     pub keyfile_path: String,
+    /// Argon2id parameters for new local keyfiles.
+    #[serde(default)]
+    pub argon2: Argon2Config,
     /// Compression settings.
     #[serde(default)]
     pub compression: CompressionConfig,
@@ -41,18 +61,129 @@ pub struct EnigmaSettings {
     /// GCP project ID (for key_provider = "gcp-secretmanager").
     #[serde(default)]
     pub gcp_project_id: Option<String>,
-    /// AWS region (for key_provider = "aws-secretsmanager").
+    /// AWS region (for key_provider = "aws-secretsmanager" or "aws-kms").
     #[serde(default)]
     pub aws_region: Option<String>,
     /// Secret name prefix used in vault backends (default: "enigma-key").
     #[serde(default)]
     pub secret_prefix: Option<String>,
+    /// Path to the PKCS#11 module (for key_provider = "pkcs11").
+    #[serde(default)]
+    pub pkcs11_library: Option<String>,
+    /// PKCS#11 slot ID holding the token (for key_provider = "pkcs11").
+    #[serde(default)]
+    pub pkcs11_slot: Option<u64>,
+    /// KMS key ID, ARN or alias wrapping the data keys (for key_provider = "aws-kms").
+    #[serde(default)]
+    pub aws_kms_key_id: Option<String>,
+    /// Where "aws-kms" keeps the wrapped data keys: "secretsmanager" (default)
+    /// or "file" (next to keyfile_path).
+    #[serde(default)]
+    pub aws_kms_key_store: Option<String>,
+    /// Maximum number of chunk downloads in flight during restore/GET (default: 8).
+    #[serde(default = "default_download_concurrency")]
+    pub download_concurrency: usize,
+    /// Re-hash every decrypted chunk on read and compare it with the manifest
+    /// (default: true). Catches a corrupted `chunks.hash` that AES-GCM alone cannot.
+    #[serde(default = "default_verify_on_read")]
+    pub verify_on_read: bool,
+    /// Check each downloaded chunk against its HMAC tag before decrypting
+    /// (default: true). Chunks stored before tags existed are not checked.
+    #[serde(default = "default_verify_hmac")]
+    pub verify_hmac: bool,
+    /// Re-read every file `enigma restore` writes and check it against the
+    /// hash recorded at backup time; overridden by `--verify` (default: true).
+    #[serde(default = "default_restore_verify")]
+    pub restore_verify: bool,
+    /// Base64 Ed25519 keypair from `enigma keygen`; when set, completed
+    /// backups are signed with it. Unrelated to the encryption keys.
+    #[serde(default)]
+    pub backup_signing_key: Option<String>,
+    /// Base64 Ed25519 public key; when set, `enigma verify` checks the
+    /// backup's signature against it.
+    #[serde(default)]
+    pub backup_verify_key: Option<String>,
+    /// Days a deleted namespace can still be restored before it is purged (default: 7).
+    #[serde(default = "default_namespace_recovery_days")]
+    pub namespace_recovery_days: u32,
+    /// Days S3 server access log entries are kept before the proxy's
+    /// cleanup task deletes them (default: 30).
+    #[serde(default = "default_access_log_retention_days")]
+    pub access_log_retention_days: u32,
+    /// Glob patterns, relative to the backup root, of files `enigma backup` skips.
+    #[serde(default)]
+    pub exclude_patterns: Vec<String>,
+    /// Directory for S3 multipart part data until the upload completes
+    /// (default: the system temp directory).
+    #[serde(default)]
+    pub multipart_part_spill_dir: Option<String>,
+    /// Keep recently downloaded chunks in memory and pre-fetch the next
+    /// chunk of an object while the current one is sent (default: false).
+    #[serde(default)]
+    pub chunk_cache_enabled: bool,
+    /// Chunks held by the cache when enabled (default: 64).
+    #[serde(default = "default_chunk_cache_max_entries")]
+    pub chunk_cache_max_entries: usize,
+    /// Most chunk uploads in flight at once during a backup, and across
+    /// all S3 PUTs in the proxy (default: unlimited).
+    #[serde(default)]
+    pub backup_workers: Option<usize>,
+    /// Most chunk downloads in flight at once during a restore; lowers
+    /// download_concurrency when smaller (default: unset).
+    #[serde(default)]
+    pub restore_workers: Option<usize>,
+    /// I/O scheduling priority of backups, restores and the proxy: "low",
+    /// "normal" or "high" (Linux only; default: left to the OS).
+    #[serde(default)]
+    pub io_priority: Option<String>,
+    /// Chunks the backup dedup index's Bloom filter is sized for; it grows
+    /// to the manifest's chunk count when that is larger (default: 1,000,000).
+    #[serde(default = "default_dedup_bloom_size")]
+    pub dedup_bloom_size: usize,
+    /// False-positive rate of the dedup index at that size (default: 0.01).
+    /// A false positive costs one extra manifest lookup.
+    #[serde(default = "default_dedup_bloom_fp_rate")]
+    pub dedup_bloom_fp_rate: f64,
+    /// GetObject bodies of at least this many bytes are streamed chunk by
+    /// chunk as the client reads them; smaller ones are read in full before
+    /// the response is sent. 0 streams everything (default: 1 MiB).
+    #[serde(default = "default_stream_response_threshold_bytes")]
+    pub stream_response_threshold_bytes: u64,
+    /// Seconds the web UI reuses the result of an expensive manifest query,
+    /// by method name (see [`CACHED_QUERIES`]). Unlisted methods and 0 are
+    /// not cached (default: 10 for each).
+    #[serde(default = "default_cache_ttl_seconds")]
+    pub cache_ttl_seconds: HashMap<String, u64>,
+}
+
+impl EnigmaSettings {
+    /// Chunks a restore downloads at once: download_concurrency, capped by
+    /// restore_workers.
+    pub fn restore_concurrency(&self) -> usize {
+        let concurrency = self.download_concurrency.max(1);
+        self.restore_workers
+            .map_or(concurrency, |workers| concurrency.min(workers.max(1)))
+    }
+
+    /// Whether the configured key provider (or any member of an aggregate)
+    /// needs the passphrase: the local keyfile, or the PKCS#11 user PIN.
+    pub fn needs_passphrase(&self) -> bool {
+        let needs = |t: &str| matches!(t, "local" | "pkcs11");
+        if self.key_provider == "aggregate" {
+            self.key_providers.iter().any(|t| needs(t))
+        } else {
+            needs(&self.key_provider)
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompressionConfig {
     pub enabled: bool,
     pub level: i32,
+    /// Codec for new chunks (default: zstd).
+    #[serde(default)]
+    pub algorithm: CompressionAlgorithm,
 }
 
 impl Default for CompressionConfig {
@@ -60,6 +191,87 @@ impl Default for CompressionConfig {
         Self {
             enabled: false,
             level: 3,
+            algorithm: CompressionAlgorithm::Zstd,
+        }
+    }
+}
+
+/// Argon2id cost parameters used when a local keyfile is created.
+///
+/// An existing keyfile records the parameters it was created with and is
+/// always opened with those, so changing this only affects new keyfiles.
+/// The defaults (64 MiB, 3 passes, 4 lanes) are what every keyfile used
+/// before the parameters became configurable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Argon2Config {
+    /// Memory cost in KiB.
+    pub memory_kib: u32,
+    /// Number of passes over the memory.
+    pub iterations: u32,
+    /// Number of lanes.
+    pub parallelism: u32,
+}
+
+impl Argon2Config {
+    /// OWASP minimum for Argon2id: 19 MiB, 2 passes, 1 lane. Lower values
+    /// are rejected even on constrained hardware.
+    pub const MIN_MEMORY_KIB: u32 = 19 * 1024;
+    pub const MIN_ITERATIONS: u32 = 2;
+    pub const MIN_PARALLELISM: u32 = 1;
+    /// Upper bound on lanes; more buys nothing on real machines.
+    pub const MAX_PARALLELISM: u32 = 64;
+
+    pub fn validate(&self) -> Result<()> {
+        match self.errors().into_iter().next() {
+            Some(error) => Err(EnigmaError::Config(error.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    fn errors(&self) -> Vec<ConfigError> {
+        let mut errors = Vec::new();
+        if self.memory_kib < Self::MIN_MEMORY_KIB {
+            errors.push(ConfigError::new(
+                "enigma.argon2.memory_kib",
+                format!(
+                    "must be >= {}, got {}",
+                    Self::MIN_MEMORY_KIB,
+                    self.memory_kib
+                ),
+            ));
+        }
+        if self.iterations < Self::MIN_ITERATIONS {
+            errors.push(ConfigError::new(
+                "enigma.argon2.iterations",
+                format!(
+                    "must be >= {}, got {}",
+                    Self::MIN_ITERATIONS,
+                    self.iterations
+                ),
+            ));
+        }
+        if !(Self::MIN_PARALLELISM..=Self::MAX_PARALLELISM).contains(&self.parallelism) {
+            errors.push(ConfigError::new(
+                "enigma.argon2.parallelism",
+                format!(
+                    "must be between {} and {}, got {}",
+                    Self::MIN_PARALLELISM,
+                    Self::MAX_PARALLELISM,
+                    self.parallelism
+                ),
+            ));
+        }
+        errors
+    }
+}
+
+impl Default for Argon2Config {
+    fn default() -> Self {
+        Self {
+            memory_kib: 64 * 1024,
+            iterations: 3,
+            parallelism: 4,
         }
     }
 }
@@ -68,6 +280,53 @@ fn default_replication_factor() -> u32 {
     1
 }
 
+fn default_download_concurrency() -> usize {
+    8
+}
+
+fn default_verify_on_read() -> bool {
+    true
+}
+
+fn default_verify_hmac() -> bool {
+    true
+}
+
+fn default_restore_verify() -> bool {
+    true
+}
+
+fn default_chunk_cache_max_entries() -> usize {
+    64
+}
+
+fn default_dedup_bloom_size() -> usize {
+    1_000_000
+}
+
+fn default_dedup_bloom_fp_rate() -> f64 {
+    0.01
+}
+
+fn default_stream_response_threshold_bytes() -> u64 {
+    1024 * 1024
+}
+
+fn default_cache_ttl_seconds() -> HashMap<String, u64> {
+    CACHED_QUERIES
+        .iter()
+        .map(|method| (method.to_string(), 10))
+        .collect()
+}
+
+fn default_namespace_recovery_days() -> u32 {
+    7
+}
+
+fn default_access_log_retention_days() -> u32 {
+    30
+}
+
 fn default_key_provider() -> String {
     "local".to_string()
 }
@@ -101,40 +360,372 @@ pub struct ProviderConfig {
     /// Credential reference — either inline encrypted or a vault path.
     #[serde(default)]
     pub credential_ref: Option<String>,
+    /// TCP connect timeout in milliseconds. Default: SDK default.
+    #[serde(default)]
+    pub connect_timeout_ms: Option<u64>,
+    /// Overall request timeout in milliseconds. Default: SDK default.
+    #[serde(default)]
+    pub request_timeout_ms: Option<u64>,
+    /// How long an idle pooled connection is kept, in milliseconds.
+    /// Default: SDK default.
+    #[serde(default)]
+    pub idle_timeout_ms: Option<u64>,
+    /// Storage class new chunks are written with: e.g. "INTELLIGENT_TIERING"
+    /// or "GLACIER" (S3), "COOL" or "ARCHIVE" (Azure), "NEARLINE" or
+    /// "ARCHIVE" (GCS). Default: the bucket's own default.
+    #[serde(default)]
+    pub storage_class: Option<String>,
+    /// Chunk upload rate to this provider in bytes per second, shared by
+    /// all concurrent uploads. Default: unlimited.
+    #[serde(default)]
+    pub upload_bandwidth_bps: Option<u64>,
+    /// Chunk download rate from this provider in bytes per second.
+    /// Default: unlimited.
+    #[serde(default)]
+    pub download_bandwidth_bps: Option<u64>,
+    /// Service account JSON key used to sign direct download URLs (Gcs
+    /// providers only). Default: URL signing is unavailable.
+    #[serde(default)]
+    pub gcs_signing_key_path: Option<String>,
+    /// File the provider's credentials are read from, in the AWS shared
+    /// credentials format, so they can be rotated without a restart (S3,
+    /// S3Compatible and Azure providers). Default: `access_key`/`secret_key`.
+    #[serde(default)]
+    pub credentials_file: Option<String>,
+    /// How often rotated credentials are re-read, from `credentials_file`
+    /// or else from the environment (`AWS_ACCESS_KEY_ID`,
+    /// `AWS_SECRET_ACCESS_KEY` and `AWS_SESSION_TOKEN`, or
+    /// `AZURE_STORAGE_ACCOUNT`, `AZURE_STORAGE_KEY` and
+    /// `AZURE_STORAGE_SAS_TOKEN`). S3 providers also re-read them when a
+    /// request is rejected with 401/403. Default: only on rejection.
+    #[serde(default)]
+    pub credential_refresh_interval_seconds: Option<u64>,
 }
 
 fn default_weight() -> u32 {
     1
 }
 
+/// A problem found in a config: the TOML key at fault and what is wrong
+/// with it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    pub field: String,
+    pub message: String,
+}
+
+impl ConfigError {
+    fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+/// Why files cannot be created in `dir`, if they cannot. A missing
+/// directory is created on first use, so its nearest existing ancestor is
+/// what has to be writable.
+fn unwritable_dir_reason(dir: &Path) -> Option<String> {
+    let existing = dir
+        .ancestors()
+        .find(|d| d.as_os_str().is_empty() || d.exists())
+        .filter(|d| !d.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    if !existing.is_dir() {
+        return Some(format!("{} is not a directory", existing.display()));
+    }
+    let probe = existing.join(format!(".enigma-write-test-{}", std::process::id()));
+    match std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe)
+    {
+        Ok(_) => {
+            let _ = std::fs::remove_file(&probe);
+            None
+        }
+        Err(e) => Some(format!(
+            "cannot create files in {}: {e}",
+            existing.display()
+        )),
+    }
+}
+
 impl EnigmaConfig {
-    /// Validate configuration values.
-    pub fn validate(&self) -> Result<()> {
-        if self.enigma.compression.level < 1 || self.enigma.compression.level > 22 {
-            return Err(EnigmaError::Config(format!(
-                "compression.level must be between 1 and 22 (zstd range), got {}",
-                self.enigma.compression.level
-            )));
-        }
-        if self.enigma.replication_factor < 1 {
-            return Err(EnigmaError::Config(format!(
-                "replication_factor must be >= 1, got {}",
-                self.enigma.replication_factor
-            )));
+    /// Reject out-of-range values. This is what [`EnigmaConfig::load`]
+    /// enforces; [`EnigmaConfig::validate`] checks more.
+    pub fn check(&self) -> Result<()> {
+        match self.value_errors().into_iter().next() {
+            Some(error) => Err(EnigmaError::Config(error.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    /// Check the whole configuration and report every problem: the value
+    /// ranges [`EnigmaConfig::check`] covers, plus settings that only make
+    /// sense together and directories that must be writable.
+    pub fn validate(&self) -> std::result::Result<(), Vec<ConfigError>> {
+        let mut errors = self.value_errors();
+        let settings = &self.enigma;
+
+        let key_providers: Vec<&str> = if settings.key_provider == "aggregate" {
+            settings.key_providers.iter().map(String::as_str).collect()
+        } else {
+            vec![settings.key_provider.as_str()]
+        };
+        for kind in &key_providers {
+            let (field, present) = match *kind {
+                "azure-keyvault" => ("enigma.vault_url", settings.vault_url.is_some()),
+                "gcp-secretmanager" => ("enigma.gcp_project_id", settings.gcp_project_id.is_some()),
+                "aws-kms" => ("enigma.aws_kms_key_id", settings.aws_kms_key_id.is_some()),
+                "pkcs11" => ("enigma.pkcs11_library", settings.pkcs11_library.is_some()),
+                _ => continue,
+            };
+            if !present {
+                errors.push(ConfigError::new(
+                    field,
+                    format!("is required for key provider \"{kind}\""),
+                ));
+            }
+        }
+
+        // Without providers, backups go to a single local fallback
+        let provider_count = self.providers.len().max(1);
+        if settings.replication_factor as usize > provider_count {
+            errors.push(ConfigError::new(
+                "enigma.replication_factor",
+                format!(
+                    "is {} but only {provider_count} provider(s) are configured",
+                    settings.replication_factor
+                ),
+            ));
+        }
+        if settings.distribution == DistributionStrategy::Weighted
+            && !self.providers.is_empty()
+            && self.providers.iter().all(|p| p.weight == 0)
+        {
+            errors.push(ConfigError::new(
+                "providers",
+                "every provider has weight = 0, so distribution = \"weighted\" cannot place any chunk",
+            ));
+        }
+
+        let db_dir = Path::new(&settings.db_path)
+            .parent()
+            .unwrap_or(Path::new("."));
+        if let Some(reason) = unwritable_dir_reason(db_dir) {
+            errors.push(ConfigError::new("enigma.db_path", reason));
+        }
+        let uses_keyfile = key_providers.iter().any(|kind| {
+            *kind == "local"
+                || (*kind == "aws-kms" && settings.aws_kms_key_store.as_deref() == Some("file"))
+        });
+        if uses_keyfile {
+            let keyfile_dir = Path::new(&settings.keyfile_path)
+                .parent()
+                .unwrap_or(Path::new("."));
+            if let Some(reason) = unwritable_dir_reason(keyfile_dir) {
+                errors.push(ConfigError::new("enigma.keyfile_path", reason));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
         }
-        Ok(())
+    }
+
+    fn value_errors(&self) -> Vec<ConfigError> {
+        let settings = &self.enigma;
+        let mut errors = Vec::new();
+        if settings.compression.algorithm == CompressionAlgorithm::Zstd
+            && (settings.compression.level < 1 || settings.compression.level > 22)
+        {
+            errors.push(ConfigError::new(
+                "enigma.compression.level",
+                format!(
+                    "must be between 1 and 22 (zstd range), got {}",
+                    settings.compression.level
+                ),
+            ));
+        }
+        if settings.replication_factor < 1 {
+            errors.push(ConfigError::new(
+                "enigma.replication_factor",
+                format!("must be >= 1, got {}", settings.replication_factor),
+            ));
+        }
+        if settings.download_concurrency < 1 {
+            errors.push(ConfigError::new(
+                "enigma.download_concurrency",
+                format!("must be >= 1, got {}", settings.download_concurrency),
+            ));
+        }
+        if settings.chunk_cache_enabled && settings.chunk_cache_max_entries < 1 {
+            errors.push(ConfigError::new(
+                "enigma.chunk_cache_max_entries",
+                "must be >= 1 when chunk_cache_enabled is set",
+            ));
+        }
+        for (field, workers) in [
+            ("enigma.backup_workers", settings.backup_workers),
+            ("enigma.restore_workers", settings.restore_workers),
+        ] {
+            if workers == Some(0) {
+                errors.push(ConfigError::new(field, "must be >= 1 when set, got 0"));
+            }
+        }
+        if settings.dedup_bloom_size < 1 {
+            errors.push(ConfigError::new(
+                "enigma.dedup_bloom_size",
+                "must be >= 1, got 0",
+            ));
+        }
+        if !(settings.dedup_bloom_fp_rate > 0.0 && settings.dedup_bloom_fp_rate < 1.0) {
+            errors.push(ConfigError::new(
+                "enigma.dedup_bloom_fp_rate",
+                format!(
+                    "must be between 0 and 1 (exclusive), got {}",
+                    settings.dedup_bloom_fp_rate
+                ),
+            ));
+        }
+        if let Some(priority) = &settings.io_priority
+            && !IO_PRIORITIES.contains(&priority.as_str())
+        {
+            errors.push(ConfigError::new(
+                "enigma.io_priority",
+                format!("must be \"low\", \"normal\" or \"high\", got \"{priority}\""),
+            ));
+        }
+        for method in settings.cache_ttl_seconds.keys() {
+            if !CACHED_QUERIES.contains(&method.as_str()) {
+                errors.push(ConfigError::new(
+                    "enigma.cache_ttl_seconds",
+                    format!("unknown query \"{method}\""),
+                ));
+            }
+        }
+        if settings.key_provider == "aggregate" && settings.key_providers.is_empty() {
+            errors.push(ConfigError::new(
+                "enigma.key_providers",
+                "must not be empty when key_provider = \"aggregate\"",
+            ));
+        }
+        if let Some(key) = &settings.backup_signing_key
+            && signing::decode_signing_key(key)
+                .and_then(|keypair| signing::public_key(&keypair))
+                .is_err()
+        {
+            errors.push(ConfigError::new(
+                "enigma.backup_signing_key",
+                "must be a base64 Ed25519 keypair from `enigma keygen`",
+            ));
+        }
+        if let Some(key) = &settings.backup_verify_key
+            && signing::decode_verify_key(key).is_err()
+        {
+            errors.push(ConfigError::new(
+                "enigma.backup_verify_key",
+                "must be a base64 Ed25519 public key from `enigma keygen`",
+            ));
+        }
+        errors.extend(settings.argon2.errors());
+        if let Some(notifications) = &self.notifications
+            && notifications.to.is_empty()
+        {
+            errors.push(ConfigError::new(
+                "notifications.to",
+                "must list at least one recipient",
+            ));
+        }
+        for (i, provider) in self.providers.iter().enumerate() {
+            for (field, bps) in [
+                ("upload_bandwidth_bps", provider.upload_bandwidth_bps),
+                ("download_bandwidth_bps", provider.download_bandwidth_bps),
+            ] {
+                if bps == Some(0) {
+                    errors.push(ConfigError::new(
+                        format!("providers[{i}].{field}"),
+                        "must be >= 1 when set, got 0",
+                    ));
+                }
+            }
+            if provider.gcs_signing_key_path.is_some()
+                && provider.provider_type != ProviderType::Gcs
+            {
+                errors.push(ConfigError::new(
+                    format!("providers[{i}].gcs_signing_key_path"),
+                    "is only supported by Gcs providers",
+                ));
+            }
+            if provider.credential_refresh_interval_seconds == Some(0) {
+                errors.push(ConfigError::new(
+                    format!("providers[{i}].credential_refresh_interval_seconds"),
+                    "must be >= 1 when set, got 0",
+                ));
+            }
+            let rotates = matches!(
+                provider.provider_type,
+                ProviderType::S3 | ProviderType::S3Compatible | ProviderType::Azure
+            );
+            for (field, set) in [
+                ("credentials_file", provider.credentials_file.is_some()),
+                (
+                    "credential_refresh_interval_seconds",
+                    provider.credential_refresh_interval_seconds.is_some(),
+                ),
+            ] {
+                if set && !rotates {
+                    errors.push(ConfigError::new(
+                        format!("providers[{i}].{field}"),
+                        "is only supported by S3, S3Compatible and Azure providers",
+                    ));
+                }
+            }
+        }
+        for (i, webhook) in self.webhooks.iter().enumerate() {
+            if webhook.events.is_empty() {
+                errors.push(ConfigError::new(
+                    format!("webhooks[{i}].events"),
+                    "must list at least one event",
+                ));
+            }
+            for event in &webhook.events {
+                if !EVENT_TYPES.contains(&event.as_str()) {
+                    errors.push(ConfigError::new(
+                        format!("webhooks[{i}].events"),
+                        format!("unknown event \"{event}\""),
+                    ));
+                }
+            }
+        }
+        errors
     }
 
     /// Load config from a TOML file.
     pub fn load(path: &Path) -> Result<Self> {
+        let config = Self::load_unchecked(path)?;
+        config.check()?;
+        Ok(config)
+    }
+
+    /// Parse a TOML config file without checking its values, for
+    /// reporting every problem with [`EnigmaConfig::validate`].
+    pub fn load_unchecked(path: &Path) -> Result<Self> {
         if !path.exists() {
             return Err(EnigmaError::ConfigNotFound(path.display().to_string()));
         }
         let content = std::fs::read_to_string(path)?;
-        let config: Self =
-            toml::from_str(&content).map_err(|e| EnigmaError::TomlDe(e.to_string()))?;
-        config.validate()?;
-        Ok(config)
+        toml::from_str(&content).map_err(|e| EnigmaError::TomlDe(e.to_string()))
     }
 
     /// Save config to a TOML file. On Unix, sets permissions to 0o600 (owner-only).
@@ -164,15 +755,42 @@ impl EnigmaConfig {
                 chunk_strategy: ChunkStrategy::default(),
                 distribution: DistributionStrategy::default(),
                 key_provider: "local".to_string(),
+                key_providers: vec![],
                 keyfile_path: base_dir.join("keys.enc").display().to_string(),
+                argon2: Argon2Config::default(),
                 compression: CompressionConfig::default(),
                 replication_factor: 1,
                 vault_url: None,
                 gcp_project_id: None,
                 aws_region: None,
                 secret_prefix: None,
+                pkcs11_library: None,
+                pkcs11_slot: None,
+                aws_kms_key_id: None,
+                aws_kms_key_store: None,
+                download_concurrency: default_download_concurrency(),
+                verify_on_read: default_verify_on_read(),
+                verify_hmac: default_verify_hmac(),
+                restore_verify: default_restore_verify(),
+                backup_signing_key: None,
+                backup_verify_key: None,
+                namespace_recovery_days: default_namespace_recovery_days(),
+                access_log_retention_days: default_access_log_retention_days(),
+                exclude_patterns: vec![],
+                multipart_part_spill_dir: None,
+                chunk_cache_enabled: false,
+                chunk_cache_max_entries: default_chunk_cache_max_entries(),
+                backup_workers: None,
+                restore_workers: None,
+                io_priority: None,
+                dedup_bloom_size: default_dedup_bloom_size(),
+                dedup_bloom_fp_rate: default_dedup_bloom_fp_rate(),
+                stream_response_threshold_bytes: default_stream_response_threshold_bytes(),
+                cache_ttl_seconds: default_cache_ttl_seconds(),
             },
             providers: vec![],
+            notifications: None,
+            webhooks: vec![],
         }
     }
 
@@ -228,4 +846,459 @@ mod tests {
         let config = EnigmaConfig::default_config(tmp.path());
         assert_eq!(config.enigma.replication_factor, 1);
     }
+
+    #[test]
+    fn download_concurrency_defaults_when_missing() {
+        let toml = r#"
+            [enigma]
+            db_path = "/tmp/enigma.db"
+        "#;
+        let config: EnigmaConfig = toml::from_str(toml).unwrap();
+        assert_eq!(config.enigma.download_concurrency, 8);
+    }
+
+    #[test]
+    fn verify_on_read_defaults_to_enabled() {
+        let toml = r#"
+            [enigma]
+            db_path = "/tmp/enigma.db"
+        "#;
+        let config: EnigmaConfig = toml::from_str(toml).unwrap();
+        assert!(config.enigma.verify_on_read);
+        assert!(config.enigma.verify_hmac);
+        assert!(config.enigma.restore_verify);
+    }
+
+    #[test]
+    fn namespace_recovery_days_default() {
+        let toml = r#"
+            [enigma]
+            db_path = "/tmp/enigma.db"
+        "#;
+        let config: EnigmaConfig = toml::from_str(toml).unwrap();
+        assert_eq!(config.enigma.namespace_recovery_days, 7);
+        assert_eq!(config.enigma.access_log_retention_days, 30);
+    }
+
+    #[test]
+    fn zero_download_concurrency_rejected() {
+        let tmp = TempDir::new().unwrap();
+        let mut config = EnigmaConfig::default_config(tmp.path());
+        config.enigma.download_concurrency = 0;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn chunk_cache_defaults_and_validation() {
+        let toml = r#"
+            [enigma]
+            db_path = "/tmp/enigma.db"
+        "#;
+        let mut config: EnigmaConfig = toml::from_str(toml).unwrap();
+        assert!(!config.enigma.chunk_cache_enabled);
+        assert_eq!(config.enigma.chunk_cache_max_entries, 64);
+
+        config.enigma.chunk_cache_max_entries = 0;
+        assert!(config.validate().is_ok());
+        config.enigma.chunk_cache_enabled = true;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn worker_limits_and_io_priority() {
+        let toml = r#"
+            [enigma]
+            db_path = "/tmp/enigma.db"
+            download_concurrency = 8
+            restore_workers = 2
+            io_priority = "low"
+        "#;
+        let mut config: EnigmaConfig = toml::from_str(toml).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.enigma.backup_workers, None);
+        assert_eq!(config.enigma.restore_concurrency(), 2);
+
+        config.enigma.restore_workers = None;
+        assert_eq!(config.enigma.restore_concurrency(), 8);
+
+        config.enigma.backup_workers = Some(0);
+        config.enigma.io_priority = Some("urgent".to_string());
+        let errors = config.validate().unwrap_err();
+        let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, ["enigma.backup_workers", "enigma.io_priority"]);
+    }
+
+    #[test]
+    fn aggregate_key_providers() {
+        let toml = r#"
+            [enigma]
+            db_path = "/tmp/enigma.db"
+            key_provider = "aggregate"
+            key_providers = ["aws-secretsmanager", "local"]
+        "#;
+        let mut config: EnigmaConfig = toml::from_str(toml).unwrap();
+        assert!(config.validate().is_ok());
+        assert!(config.enigma.needs_passphrase());
+
+        config.enigma.key_providers = vec!["aws-secretsmanager".to_string()];
+        assert!(!config.enigma.needs_passphrase());
+
+        config.enigma.key_providers.clear();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn argon2_partial_table_keeps_defaults() {
+        let toml = r#"
+            [enigma]
+            db_path = "/tmp/enigma.db"
+
+            [enigma.argon2]
+            memory_kib = 262144
+        "#;
+        let config: EnigmaConfig = toml::from_str(toml).unwrap();
+        assert_eq!(config.enigma.argon2.memory_kib, 262144);
+        assert_eq!(config.enigma.argon2.iterations, 3);
+        assert_eq!(config.enigma.argon2.parallelism, 4);
+    }
+
+    #[test]
+    fn compression_algorithm_defaults_to_zstd() {
+        let toml = r#"
+            [enigma]
+            db_path = "/tmp/enigma.db"
+
+            [enigma.compression]
+            enabled = true
+            level = 3
+        "#;
+        let config: EnigmaConfig = toml::from_str(toml).unwrap();
+        assert_eq!(
+            config.enigma.compression.algorithm,
+            CompressionAlgorithm::Zstd
+        );
+
+        let config: EnigmaConfig =
+            toml::from_str(&toml.replace("level = 3", "level = 0\nalgorithm = \"lz4\"")).unwrap();
+        assert_eq!(
+            config.enigma.compression.algorithm,
+            CompressionAlgorithm::Lz4
+        );
+        // The zstd level range does not apply to lz4
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn provider_timeouts_are_optional() {
+        let toml = r#"
+            [enigma]
+            db_path = "/tmp/enigma.db"
+
+            [[providers]]
+            name = "minio"
+            type = "S3Compatible"
+            bucket = "chunks"
+            connect_timeout_ms = 2000
+
+            [[providers]]
+            name = "s3"
+            type = "S3"
+            bucket = "chunks"
+        "#;
+        let config: EnigmaConfig = toml::from_str(toml).unwrap();
+        assert_eq!(config.providers[0].connect_timeout_ms, Some(2000));
+        assert_eq!(config.providers[0].request_timeout_ms, None);
+        assert_eq!(config.providers[1].connect_timeout_ms, None);
+        assert_eq!(config.providers[1].idle_timeout_ms, None);
+    }
+
+    #[test]
+    fn weak_argon2_params_rejected() {
+        let tmp = TempDir::new().unwrap();
+        let mut config = EnigmaConfig::default_config(tmp.path());
+        config.enigma.argon2.memory_kib = 8 * 1024;
+        assert!(config.validate().is_err());
+
+        let mut config = EnigmaConfig::default_config(tmp.path());
+        config.enigma.argon2.iterations = 1;
+        assert!(config.validate().is_err());
+
+        let mut config = EnigmaConfig::default_config(tmp.path());
+        config.enigma.argon2.parallelism = 0;
+        assert!(config.validate().is_err());
+    }
+
+    fn local_provider(dir: &Path, name: &str, weight: u32) -> ProviderConfig {
+        ProviderConfig {
+            name: name.to_string(),
+            provider_type: ProviderType::Local,
+            bucket: dir.join(name).display().to_string(),
+            region: None,
+            weight,
+            endpoint_url: None,
+            path_style: None,
+            access_key: None,
+            secret_key: None,
+            credential_ref: None,
+            connect_timeout_ms: None,
+            request_timeout_ms: None,
+            idle_timeout_ms: None,
+            storage_class: None,
+            upload_bandwidth_bps: None,
+            download_bandwidth_bps: None,
+            gcs_signing_key_path: None,
+            credentials_file: None,
+            credential_refresh_interval_seconds: None,
+        }
+    }
+
+    fn error_fields(config: &EnigmaConfig) -> Vec<String> {
+        match config.validate() {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors.into_iter().map(|e| e.field).collect(),
+        }
+    }
+
+    #[test]
+    fn valid_config_passes_validation() {
+        let tmp = TempDir::new().unwrap();
+        let mut config = EnigmaConfig::default_config(tmp.path());
+        config.enigma.replication_factor = 2;
+        config.enigma.distribution = DistributionStrategy::Weighted;
+        config.providers = vec![
+            local_provider(tmp.path(), "a", 1),
+            local_provider(tmp.path(), "b", 0),
+        ];
+        assert_eq!(config.validate(), Ok(()));
+        // The write probes leave nothing behind
+        assert_eq!(std::fs::read_dir(tmp.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn weighted_distribution_needs_a_positive_weight() {
+        let tmp = TempDir::new().unwrap();
+        let mut config = EnigmaConfig::default_config(tmp.path());
+        config.providers = vec![
+            local_provider(tmp.path(), "a", 0),
+            local_provider(tmp.path(), "b", 0),
+        ];
+        assert!(config.validate().is_ok());
+        config.enigma.distribution = DistributionStrategy::Weighted;
+        assert_eq!(error_fields(&config), vec!["providers"]);
+    }
+
+    #[test]
+    fn gcs_signing_key_is_for_gcs_providers() {
+        let tmp = TempDir::new().unwrap();
+        let mut config = EnigmaConfig::default_config(tmp.path());
+        let mut provider = local_provider(tmp.path(), "a", 1);
+        provider.gcs_signing_key_path = Some("/etc/enigma/gcs-sa.json".to_string());
+        config.providers = vec![provider];
+        assert_eq!(
+            error_fields(&config),
+            vec!["providers[0].gcs_signing_key_path"]
+        );
+
+        config.providers[0].provider_type = ProviderType::Gcs;
+        config.providers[0].bucket = "enigma-chunks".to_string();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn credential_rotation_is_for_cloud_providers() {
+        let tmp = TempDir::new().unwrap();
+        let mut config = EnigmaConfig::default_config(tmp.path());
+        let mut provider = local_provider(tmp.path(), "a", 1);
+        provider.credentials_file = Some("/etc/enigma/s3-credentials".to_string());
+        provider.credential_refresh_interval_seconds = Some(0);
+        config.providers = vec![provider];
+        assert_eq!(
+            error_fields(&config),
+            vec![
+                "providers[0].credential_refresh_interval_seconds",
+                "providers[0].credentials_file",
+                "providers[0].credential_refresh_interval_seconds",
+            ]
+        );
+
+        config.providers[0].provider_type = ProviderType::S3;
+        config.providers[0].bucket = "enigma-chunks".to_string();
+        config.providers[0].credential_refresh_interval_seconds = Some(300);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn vault_key_providers_need_their_settings() {
+        let tmp = TempDir::new().unwrap();
+        let mut config = EnigmaConfig::default_config(tmp.path());
+        config.enigma.key_provider = "azure-keyvault".to_string();
+        assert_eq!(error_fields(&config), vec!["enigma.vault_url"]);
+        config.enigma.vault_url = Some("https://vault.example.net".to_string());
+        assert!(config.validate().is_ok());
+
+        // Members of an aggregate are checked too
+        config.enigma.key_provider = "aggregate".to_string();
+        config.enigma.key_providers = vec!["gcp-secretmanager".to_string(), "local".to_string()];
+        assert_eq!(error_fields(&config), vec!["enigma.gcp_project_id"]);
+    }
+
+    #[test]
+    fn db_path_directory_must_be_writable() {
+        let tmp = TempDir::new().unwrap();
+        let blocker = tmp.path().join("blocker");
+        std::fs::write(&blocker, b"").unwrap();
+        let mut config = EnigmaConfig::default_config(tmp.path());
+        // A missing directory is fine: it is created on first use
+        config.enigma.db_path = tmp.path().join("new/dir/enigma.db").display().to_string();
+        assert!(config.validate().is_ok());
+
+        config.enigma.db_path = blocker.join("enigma.db").display().to_string();
+        let errors = config.validate().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "enigma.db_path");
+        assert!(errors[0].message.contains("not a directory"));
+    }
+
+    #[test]
+    fn keyfile_path_directory_must_be_writable() {
+        let tmp = TempDir::new().unwrap();
+        let blocker = tmp.path().join("blocker");
+        std::fs::write(&blocker, b"").unwrap();
+        let mut config = EnigmaConfig::default_config(tmp.path());
+        config.enigma.keyfile_path = blocker.join("keys.enc").display().to_string();
+        assert_eq!(error_fields(&config), vec!["enigma.keyfile_path"]);
+
+        // The keyfile is not used by a vault provider
+        config.enigma.key_provider = "aws-secretsmanager".to_string();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn backup_signing_keys_must_decode() {
+        let tmp = TempDir::new().unwrap();
+        let mut config = EnigmaConfig::default_config(tmp.path());
+        let keypair = signing::generate_keypair();
+        config.enigma.backup_signing_key = Some(signing::encode_key(&keypair));
+        config.enigma.backup_verify_key = Some(signing::encode_key(&keypair[32..]));
+        assert!(config.validate().is_ok());
+
+        // Swapped: the keypair is too long for a public key and vice versa
+        config.enigma.backup_signing_key = Some(signing::encode_key(&keypair[32..]));
+        config.enigma.backup_verify_key = Some(signing::encode_key(&keypair));
+        assert_eq!(
+            error_fields(&config),
+            vec!["enigma.backup_signing_key", "enigma.backup_verify_key"]
+        );
+    }
+
+    #[test]
+    fn replication_factor_cannot_exceed_provider_count() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("enigma.toml");
+        let mut config = EnigmaConfig::default_config(tmp.path());
+        config.enigma.replication_factor = 2;
+        // No providers means the single local fallback
+        assert_eq!(error_fields(&config), vec!["enigma.replication_factor"]);
+        config.providers = vec![local_provider(tmp.path(), "a", 1)];
+        assert_eq!(error_fields(&config), vec!["enigma.replication_factor"]);
+
+        // `load` only rejects out-of-range values
+        config.save(&path).unwrap();
+        assert!(EnigmaConfig::load(&path).is_ok());
+
+        config.providers.push(local_provider(tmp.path(), "b", 1));
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn validate_reports_every_error() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("enigma.toml");
+        let mut config = EnigmaConfig::default_config(tmp.path());
+        config.enigma.download_concurrency = 0;
+        config.enigma.argon2.iterations = 1;
+        config.enigma.replication_factor = 3;
+        assert_eq!(
+            error_fields(&config),
+            vec![
+                "enigma.download_concurrency",
+                "enigma.argon2.iterations",
+                "enigma.replication_factor",
+            ]
+        );
+
+        config.save(&path).unwrap();
+        let err = EnigmaConfig::load(&path).unwrap_err();
+        assert!(err.to_string().contains("enigma.download_concurrency"));
+        assert_eq!(
+            EnigmaConfig::load_unchecked(&path)
+                .unwrap()
+                .validate()
+                .unwrap_err()
+                .len(),
+            3
+        );
+    }
+
+    #[test]
+    fn dedup_bloom_fp_rate_must_be_a_probability() {
+        let tmp = TempDir::new().unwrap();
+        let mut config = EnigmaConfig::default_config(tmp.path());
+        for rate in [0.0, 1.0, f64::NAN] {
+            config.enigma.dedup_bloom_fp_rate = rate;
+            assert_eq!(error_fields(&config), vec!["enigma.dedup_bloom_fp_rate"]);
+        }
+        config.enigma.dedup_bloom_fp_rate = 0.001;
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn cache_ttl_seconds_names_known_queries() {
+        let toml = r#"
+            [enigma]
+            db_path = "/tmp/enigma.db"
+
+            [enigma.cache_ttl_seconds]
+            chunk_stats = 30
+            chunk_sizes = 5
+        "#;
+        let config: EnigmaConfig = toml::from_str(toml).unwrap();
+        assert_eq!(config.enigma.cache_ttl_seconds["chunk_stats"], 30);
+        assert_eq!(error_fields(&config), vec!["enigma.cache_ttl_seconds"]);
+
+        let tmp = TempDir::new().unwrap();
+        let config = EnigmaConfig::default_config(tmp.path());
+        assert_eq!(config.enigma.cache_ttl_seconds.len(), CACHED_QUERIES.len());
+    }
+
+    #[test]
+    fn webhook_events_must_be_known() {
+        let tmp = TempDir::new().unwrap();
+        let mut config = EnigmaConfig::default_config(tmp.path());
+        config.webhooks = toml::from_str::<EnigmaConfig>(
+            r#"
+            [enigma]
+            db_path = "enigma.db"
+            keyfile_path = "keys.enc"
+
+            [[webhooks]]
+            url = "https://hooks.example.com/enigma"
+            secret = "s3cret"
+            events = ["backup.completed", "gc.completed"]
+            "#,
+        )
+        .unwrap()
+        .webhooks;
+        assert_eq!(config.webhooks[0].max_retries, 3);
+        assert_eq!(config.webhooks[0].timeout_ms, 5000);
+        assert!(config.validate().is_ok());
+
+        config.webhooks[0]
+            .events
+            .push("backup.exploded".to_string());
+        let errors = config.validate().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "webhooks[0].events");
+        assert!(errors[0].message.contains("backup.exploded"));
+    }
 }
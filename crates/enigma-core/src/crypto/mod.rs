@@ -1,7 +1,12 @@
+pub mod signing;
+
 use aes_gcm::aead::{Aead, KeyInit};
 use aes_gcm::{Aes256Gcm, Nonce};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
 use rand::RngCore;
 use rand::rngs::OsRng;
+use sha2::Sha256;
 
 use crate::error::{EnigmaError, Result};
 use crate::types::{ChunkHash, EncryptedChunk, KeyMaterial};
@@ -58,6 +63,60 @@ pub fn decrypt_chunk(encrypted: &EncryptedChunk, key: &KeyMaterial) -> Result<Ve
         .map_err(|e| EnigmaError::Decryption(format!("Decryption failed: {e}")))
 }
 
+/// HKDF info string of the chunk HMAC key.
+const HMAC_KEY_INFO: &[u8] = b"enigma-hmac-v1";
+
+/// Ciphertext bytes covered by a chunk's HMAC tag.
+const HMAC_PREFIX_LEN: usize = 32;
+
+/// Key for chunk HMAC tags: HKDF-SHA256 of the master key with info
+/// `enigma-hmac-v1`, so the tags never reuse the encryption key itself.
+pub fn derive_hmac_key(master_key: &[u8; 32]) -> [u8; 32] {
+    let mut hmac_key = [0u8; 32];
+    Hkdf::<Sha256>::new(None, master_key)
+        .expand(HMAC_KEY_INFO, &mut hmac_key)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    hmac_key
+}
+
+fn chunk_mac(key: &KeyMaterial, storage_key: &str, ciphertext: &[u8]) -> Hmac<Sha256> {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&derive_hmac_key(&key.key))
+        .expect("HMAC accepts keys of any length");
+    mac.update(storage_key.as_bytes());
+    mac.update(&(ciphertext.len() as u64).to_le_bytes());
+    mac.update(&ciphertext[..ciphertext.len().min(HMAC_PREFIX_LEN)]);
+    mac
+}
+
+/// Tamper-detection tag of a stored chunk copy:
+/// `HMAC-SHA256(hmac_key, storage_key || len(ciphertext) as u64 LE || first 32 bytes)`.
+///
+/// Checking it is cheap next to decryption and tells a truncated,
+/// overwritten or misplaced copy apart from a wrong key.
+pub fn chunk_hmac_tag(key: &KeyMaterial, storage_key: &str, ciphertext: &[u8]) -> [u8; 32] {
+    chunk_mac(key, storage_key, ciphertext)
+        .finalize()
+        .into_bytes()
+        .into()
+}
+
+/// Check a chunk copy against its HMAC tag, in constant time.
+pub fn verify_chunk_hmac(
+    key: &KeyMaterial,
+    storage_key: &str,
+    ciphertext: &[u8],
+    tag: &[u8; 32],
+) -> Result<()> {
+    chunk_mac(key, storage_key, ciphertext)
+        .verify_slice(tag)
+        .map_err(|_| {
+            EnigmaError::Integrity(format!(
+                "HMAC mismatch for chunk stored at {storage_key} ({} bytes)",
+                ciphertext.len()
+            ))
+        })
+}
+
 /// Encrypt arbitrary data (for manifest, keyfiles, etc.) with a given key and AAD.
 pub fn encrypt_data(data: &[u8], key: &[u8; 32], aad: &[u8]) -> Result<(Vec<u8>, [u8; 12])> {
     let cipher = Aes256Gcm::new_from_slice(key)
@@ -164,6 +223,38 @@ mod tests {
         assert_ne!(e1.ciphertext, e2.ciphertext);
     }
 
+    #[test]
+    fn hmac_tag_detects_tampering() {
+        let key = test_key();
+        let hash = ChunkHash([0x42; 32]);
+        let encrypted = encrypt_chunk(&[7u8; 4096], &hash, &key).unwrap();
+        let storage_key = hash.storage_key();
+        let tag = chunk_hmac_tag(&key, &storage_key, &encrypted.ciphertext);
+
+        verify_chunk_hmac(&key, &storage_key, &encrypted.ciphertext, &tag).unwrap();
+
+        let mut flipped = encrypted.ciphertext.clone();
+        flipped[0] ^= 1;
+        assert!(verify_chunk_hmac(&key, &storage_key, &flipped, &tag).is_err());
+
+        let truncated = &encrypted.ciphertext[..encrypted.ciphertext.len() - 1];
+        assert!(verify_chunk_hmac(&key, &storage_key, truncated, &tag).is_err());
+
+        // The same bytes under another storage key are a misplaced copy
+        assert!(verify_chunk_hmac(&key, "other/key", &encrypted.ciphertext, &tag).is_err());
+
+        let other_key = test_key();
+        assert!(verify_chunk_hmac(&other_key, &storage_key, &encrypted.ciphertext, &tag).is_err());
+    }
+
+    #[test]
+    fn hmac_key_differs_from_master_key() {
+        let master = [0x11; 32];
+        let hmac_key = derive_hmac_key(&master);
+        assert_ne!(hmac_key, master);
+        assert_eq!(hmac_key, derive_hmac_key(&master));
+    }
+
     #[test]
     fn encrypt_decrypt_data_roundtrip() {
         let mut key = [0u8; 32];
@@ -0,0 +1,99 @@
+//! Ed25519 keys for signing backup records.
+//!
+//! A signing key is the 64-byte keypair (secret || public) as produced by
+//! `enigma keygen`, stored base64-encoded in the config; the verify key is
+//! the 32-byte public half. These keys only sign manifest records and are
+//! unrelated to the chunk encryption key material.
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use rand::RngCore;
+use rand::rngs::OsRng;
+
+use crate::error::{EnigmaError, Result};
+
+/// Generate a fresh keypair: 32 secret bytes followed by the public key.
+pub fn generate_keypair() -> [u8; 64] {
+    let mut secret = [0u8; 32];
+    OsRng.fill_bytes(&mut secret);
+    SigningKey::from_bytes(&secret).to_keypair_bytes()
+}
+
+/// Public half of a keypair.
+pub fn public_key(keypair: &[u8; 64]) -> Result<[u8; 32]> {
+    Ok(signing_key(keypair)?.verifying_key().to_bytes())
+}
+
+/// Base64 form of a keypair or public key, as written to the config.
+pub fn encode_key(key: &[u8]) -> String {
+    BASE64.encode(key)
+}
+
+/// Decode a base64 keypair, e.g. `backup_signing_key` from the config.
+pub fn decode_signing_key(encoded: &str) -> Result<[u8; 64]> {
+    decode_key(encoded, "signing key")
+}
+
+/// Decode a base64 public key, e.g. `backup_verify_key` from the config.
+pub fn decode_verify_key(encoded: &str) -> Result<[u8; 32]> {
+    decode_key(encoded, "verify key")
+}
+
+/// Sign `message` with a keypair.
+pub fn sign(keypair: &[u8; 64], message: &[u8]) -> Result<[u8; 64]> {
+    Ok(signing_key(keypair)?.sign(message).to_bytes())
+}
+
+/// Check an Ed25519 signature. Malformed keys or signatures do not verify.
+pub fn verify(public_key: &[u8; 32], message: &[u8], signature: &[u8]) -> bool {
+    let Ok(key) = VerifyingKey::from_bytes(public_key) else {
+        return false;
+    };
+    let Ok(signature) = Signature::from_slice(signature) else {
+        return false;
+    };
+    key.verify(message, &signature).is_ok()
+}
+
+fn signing_key(keypair: &[u8; 64]) -> Result<SigningKey> {
+    SigningKey::from_keypair_bytes(keypair)
+        .map_err(|_| EnigmaError::Config("invalid Ed25519 signing keypair".to_string()))
+}
+
+fn decode_key<const N: usize>(encoded: &str, what: &str) -> Result<[u8; N]> {
+    BASE64
+        .decode(encoded.trim())
+        .ok()
+        .and_then(|bytes| <[u8; N]>::try_from(bytes).ok())
+        .ok_or_else(|| EnigmaError::Config(format!("{what} must be {N} base64-encoded bytes")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keypair_signs_and_verifies() {
+        let keypair = generate_keypair();
+        let public = public_key(&keypair).unwrap();
+        assert_eq!(public, keypair[32..]);
+
+        let signature = sign(&keypair, b"backup").unwrap();
+        assert!(verify(&public, b"backup", &signature));
+        assert!(!verify(&public, b"backup!", &signature));
+        assert!(!verify(&public, b"backup", &signature[..63]));
+    }
+
+    #[test]
+    fn keys_roundtrip_through_base64() {
+        let keypair = generate_keypair();
+        assert_eq!(decode_signing_key(&encode_key(&keypair)).unwrap(), keypair);
+        assert_eq!(
+            decode_verify_key(&encode_key(&keypair[32..])).unwrap(),
+            keypair[32..]
+        );
+        assert!(decode_verify_key(&encode_key(&keypair)).is_err());
+        assert!(decode_signing_key("not base64").is_err());
+    }
+}
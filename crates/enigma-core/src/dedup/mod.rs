@@ -1,6 +1,9 @@
 use sha2::{Digest, Sha256};
 use subtle::ConstantTimeEq;
 
+use crate::config::EnigmaSettings;
+use crate::error::Result;
+use crate::manifest::ManifestDb;
 use crate::types::ChunkHash;
 
 /// Compute the SHA-256 hash of data.
@@ -21,6 +24,66 @@ pub fn find_duplicate(hash: &ChunkHash, existing: &[ChunkHash]) -> Option<usize>
     existing.iter().position(|h| hashes_equal(h, hash))
 }
 
+/// Chunk hashes known to the manifest, held in a Bloom filter so a backup
+/// can tell a new chunk from a possible duplicate without asking the
+/// database. A negative answer is definite; a positive one may be wrong
+/// at the configured rate, which only costs the usual manifest lookup.
+pub struct DedupIndex {
+    bits: Vec<u64>,
+    num_bits: u64,
+    num_hashes: u32,
+}
+
+impl DedupIndex {
+    /// An empty index sized for `capacity` hashes at a false-positive
+    /// rate of `fp_rate`.
+    pub fn with_capacity(capacity: usize, fp_rate: f64) -> Self {
+        let n = capacity.max(1) as f64;
+        let ln2 = std::f64::consts::LN_2;
+        let num_bits = (-n * fp_rate.ln() / (ln2 * ln2)).ceil().max(64.0) as u64;
+        let num_hashes = ((num_bits as f64 / n) * ln2).round().clamp(1.0, 32.0) as u32;
+        Self {
+            bits: vec![0; num_bits.div_ceil(64) as usize],
+            num_bits,
+            num_hashes,
+        }
+    }
+
+    /// Index every chunk in the manifest, sized by `dedup_bloom_size` and
+    /// `dedup_bloom_fp_rate`, or by the chunk count when that is larger.
+    pub fn load_from_db(db: &ManifestDb, settings: &EnigmaSettings) -> Result<Self> {
+        let chunks = db.chunk_count()? as usize;
+        let mut index = Self::with_capacity(
+            settings.dedup_bloom_size.max(chunks),
+            settings.dedup_bloom_fp_rate,
+        );
+        db.for_each_chunk_hash(|hash| index.insert(&hash))?;
+        Ok(index)
+    }
+
+    /// Add a hash, e.g. a chunk first stored during this backup.
+    pub fn insert(&mut self, hash: &ChunkHash) {
+        for bit in self.bit_positions(hash) {
+            self.bits[(bit / 64) as usize] |= 1 << (bit % 64);
+        }
+    }
+
+    /// False if `hash` is definitely not in the manifest.
+    pub fn contains(&self, hash: &ChunkHash) -> bool {
+        self.bit_positions(hash)
+            .all(|bit| self.bits[(bit / 64) as usize] & (1 << (bit % 64)) != 0)
+    }
+
+    /// Double hashing over two words of the hash: SHA-256 output is
+    /// already uniform, so it needs no further mixing.
+    fn bit_positions(&self, hash: &ChunkHash) -> impl Iterator<Item = u64> + use<> {
+        let word = |i: usize| u64::from_le_bytes(hash.0[i..i + 8].try_into().unwrap());
+        let (h1, h2) = (word(0), word(8) | 1);
+        let num_bits = self.num_bits;
+        (0..u64::from(self.num_hashes)).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % num_bits)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -53,4 +116,43 @@ mod tests {
         let target = compute_hash(&[99]);
         assert_eq!(find_duplicate(&target, &hashes), None);
     }
+
+    #[test]
+    fn index_has_no_false_negatives() {
+        let mut index = DedupIndex::with_capacity(10_000, 0.01);
+        let hashes: Vec<ChunkHash> = (0..10_000u32)
+            .map(|i| compute_hash(&i.to_le_bytes()))
+            .collect();
+        for hash in &hashes {
+            index.insert(hash);
+        }
+        assert!(hashes.iter().all(|h| index.contains(h)));
+
+        // Roughly the configured rate for hashes never inserted
+        let false_positives = (10_000..110_000u32)
+            .filter(|i| index.contains(&compute_hash(&i.to_le_bytes())))
+            .count();
+        assert!(false_positives < 2_000, "{false_positives} false positives");
+    }
+
+    #[test]
+    fn index_loads_every_manifest_chunk() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let pid = db
+            .insert_provider("local", crate::types::ProviderType::Local, "/tmp", None, 1)
+            .unwrap();
+        let stored = compute_hash(b"stored");
+        let hex = stored.to_hex();
+        db.insert_or_dedup_chunk(&hex, &[0; 12], "k", pid, &hex, 6, 22, None)
+            .unwrap();
+
+        let settings =
+            crate::config::EnigmaConfig::default_config(std::path::Path::new("/tmp")).enigma;
+        let mut index = DedupIndex::load_from_db(&db, &settings).unwrap();
+        assert!(index.contains(&stored));
+        let fresh = compute_hash(b"fresh");
+        assert!(!index.contains(&fresh));
+        index.insert(&fresh);
+        assert!(index.contains(&fresh));
+    }
 }
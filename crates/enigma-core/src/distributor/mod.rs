@@ -1,12 +1,19 @@
 use crate::error::{EnigmaError, Result};
 use crate::types::ProviderInfo;
+use std::collections::HashMap;
+use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
+/// Reports whether a provider currently accepts requests (e.g. its circuit
+/// breaker is not open).
+pub type HealthCheck = Arc<dyn Fn() -> bool + Send + Sync>;
+
 /// Distributes chunks across storage providers.
 pub struct Distributor {
     providers: Vec<ProviderInfo>,
     strategy: Strategy,
     rr_counter: AtomicUsize,
+    health: HashMap<i64, HealthCheck>,
 }
 
 enum Strategy {
@@ -26,6 +33,7 @@ impl Distributor {
             providers,
             strategy: Strategy::RoundRobin,
             rr_counter: AtomicUsize::new(0),
+            health: HashMap::new(),
         })
     }
 
@@ -48,11 +56,38 @@ impl Distributor {
                 cumulative_weights: cumulative,
             },
             rr_counter: AtomicUsize::new(0),
+            health: HashMap::new(),
         })
     }
 
-    /// Select the next provider for a chunk.
+    /// Attach a health check to a provider. Unhealthy providers are skipped
+    /// by `next_provider` as long as at least one healthy provider remains.
+    pub fn set_health_check(&mut self, provider_id: i64, check: HealthCheck) {
+        self.health.insert(provider_id, check);
+    }
+
+    /// Whether a provider is healthy. Providers without a health check always are.
+    pub fn is_healthy(&self, provider_id: i64) -> bool {
+        self.health.get(&provider_id).is_none_or(|check| check())
+    }
+
+    /// Select the next provider for a chunk, skipping unhealthy providers.
+    /// If every provider is unhealthy, falls back to the strategy's pick.
     pub fn next_provider(&self) -> &ProviderInfo {
+        let first = self.pick();
+        if self.health.is_empty() || self.is_healthy(first.id) {
+            return first;
+        }
+        for _ in 1..self.providers.len() {
+            let p = self.pick();
+            if self.is_healthy(p.id) {
+                return p;
+            }
+        }
+        first
+    }
+
+    fn pick(&self) -> &ProviderInfo {
         match &self.strategy {
             Strategy::RoundRobin => {
                 let idx = self.rr_counter.fetch_add(1, Ordering::Relaxed) % self.providers.len();
@@ -203,6 +238,29 @@ mod tests {
         assert_eq!(ids.len(), unique.len());
     }
 
+    #[test]
+    fn unhealthy_provider_is_skipped() {
+        let providers = make_providers(3);
+        let mut dist = Distributor::round_robin(providers).unwrap();
+        dist.set_health_check(1, Arc::new(|| false));
+
+        let ids: Vec<i64> = (0..6).map(|_| dist.next_provider().id).collect();
+        assert!(!ids.contains(&1), "unhealthy provider selected: {ids:?}");
+        assert!(ids.contains(&0) && ids.contains(&2));
+    }
+
+    #[test]
+    fn all_unhealthy_falls_back() {
+        let providers = make_providers(2);
+        let mut dist = Distributor::round_robin(providers).unwrap();
+        dist.set_health_check(0, Arc::new(|| false));
+        dist.set_health_check(1, Arc::new(|| false));
+
+        assert!(!dist.is_healthy(0));
+        // Still returns a provider so the caller gets a fast error, not a panic
+        let _ = dist.next_provider();
+    }
+
     #[test]
     fn next_providers_clamped() {
         let providers = make_providers(2);
@@ -56,6 +56,9 @@ pub enum EnigmaError {
     #[error("Hash mismatch for chunk {0}: expected {1}, got {2}")]
     HashMismatch(String, String, String),
 
+    #[error("Integrity error: {0}")]
+    Integrity(String),
+
     // Serialization
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
@@ -0,0 +1,147 @@
+//! Backup progress events.
+//!
+//! The backup pipelines publish [`BackupEvent`]s on a [`BackupEvents`]
+//! broadcast channel; the web UI forwards them to WebSocket clients.
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+/// Stage a backup has reached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupPhase {
+    Chunking,
+    Encrypting,
+    Uploading,
+    Done,
+    Error,
+}
+
+/// Progress snapshot of one backup.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupEvent {
+    pub backup_id: String,
+    pub phase: BackupPhase,
+    pub files_done: u64,
+    pub files_total: u64,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    /// Fraction of chunks so far that were already stored (0.0 to 1.0).
+    pub dedup_ratio: f64,
+}
+
+/// Running counters for one backup, turned into events by [`Self::event`].
+#[derive(Debug, Clone)]
+pub struct BackupProgress {
+    pub backup_id: String,
+    pub files_done: u64,
+    pub files_total: u64,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    pub chunks: u64,
+    pub deduped_chunks: u64,
+}
+
+impl BackupProgress {
+    pub fn new(backup_id: &str, files_total: u64, bytes_total: u64) -> Self {
+        Self {
+            backup_id: backup_id.to_string(),
+            files_done: 0,
+            files_total,
+            bytes_done: 0,
+            bytes_total,
+            chunks: 0,
+            deduped_chunks: 0,
+        }
+    }
+
+    /// Record a processed chunk of `bytes` plaintext bytes.
+    pub fn chunk_done(&mut self, bytes: u64, deduped: bool) {
+        self.chunks += 1;
+        self.bytes_done += bytes;
+        if deduped {
+            self.deduped_chunks += 1;
+        }
+    }
+
+    pub fn event(&self, phase: BackupPhase) -> BackupEvent {
+        BackupEvent {
+            backup_id: self.backup_id.clone(),
+            phase,
+            files_done: self.files_done,
+            files_total: self.files_total,
+            bytes_done: self.bytes_done,
+            bytes_total: self.bytes_total,
+            dedup_ratio: if self.chunks == 0 {
+                0.0
+            } else {
+                self.deduped_chunks as f64 / self.chunks as f64
+            },
+        }
+    }
+}
+
+/// Broadcast channel for [`BackupEvent`]s. Clones share the channel.
+///
+/// Publishing never blocks: with no subscribers events are dropped, and a
+/// subscriber that falls more than [`Self::CAPACITY`] events behind skips
+/// the oldest ones.
+#[derive(Debug, Clone)]
+pub struct BackupEvents {
+    tx: broadcast::Sender<BackupEvent>,
+}
+
+impl BackupEvents {
+    pub const CAPACITY: usize = 256;
+
+    pub fn publish(&self, event: BackupEvent) {
+        // Err only means nobody is listening
+        let _ = self.tx.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<BackupEvent> {
+        self.tx.subscribe()
+    }
+}
+
+impl Default for BackupEvents {
+    fn default() -> Self {
+        let (tx, _rx) = broadcast::channel(Self::CAPACITY);
+        Self { tx }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn event_serializes_phase_lowercase() {
+        let mut progress = BackupProgress::new("b1", 2, 300);
+        progress.files_done = 1;
+        progress.chunk_done(100, false);
+        progress.chunk_done(100, true);
+
+        let json = serde_json::to_value(progress.event(BackupPhase::Uploading)).unwrap();
+        assert_eq!(json["backup_id"], "b1");
+        assert_eq!(json["phase"], "uploading");
+        assert_eq!(json["files_done"], 1);
+        assert_eq!(json["bytes_done"], 200);
+        assert_eq!(json["dedup_ratio"], 0.5);
+    }
+
+    #[tokio::test]
+    async fn subscribers_receive_published_events() {
+        let events = BackupEvents::default();
+        // Publishing with no subscribers is a no-op
+        events.publish(BackupProgress::new("b0", 0, 0).event(BackupPhase::Done));
+
+        let mut rx = events.subscribe();
+        let progress = BackupProgress::new("b1", 1, 10);
+        events.publish(progress.event(BackupPhase::Chunking));
+        events.publish(progress.event(BackupPhase::Done));
+
+        assert_eq!(rx.recv().await.unwrap().phase, BackupPhase::Chunking);
+        assert_eq!(rx.recv().await.unwrap().phase, BackupPhase::Done);
+    }
+}
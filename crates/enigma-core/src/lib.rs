@@ -5,5 +5,11 @@ pub mod crypto;
 pub mod dedup;
 pub mod distributor;
 pub mod error;
+pub mod events;
+pub mod limits;
 pub mod manifest;
+pub mod merkle;
+pub mod notify;
+pub mod tagging;
 pub mod types;
+pub mod webhook;
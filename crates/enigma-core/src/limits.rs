@@ -0,0 +1,99 @@
+//! Resource limits for the backup and restore pipelines: how many chunk
+//! transfers run at once, and the I/O scheduling priority of the process.
+
+use std::sync::Arc;
+
+use tokio::sync::Semaphore;
+
+/// Values accepted by `io_priority`.
+pub const IO_PRIORITIES: &[&str] = &["low", "normal", "high"];
+
+/// A semaphore with `workers` permits; `None` means no limit.
+pub fn worker_semaphore(workers: Option<usize>) -> Arc<Semaphore> {
+    let permits = workers.map_or(Semaphore::MAX_PERMITS, |w| {
+        w.clamp(1, Semaphore::MAX_PERMITS)
+    });
+    Arc::new(Semaphore::new(permits))
+}
+
+/// Put the whole process in the best-effort I/O scheduling class at the
+/// level matching `priority` ("low", "normal" or "high"). Only Linux has
+/// per-process I/O priorities; elsewhere this does nothing.
+pub fn set_io_priority(priority: &str) -> std::io::Result<()> {
+    // Best-effort levels run from 0 (highest) to 7 (lowest)
+    let level = match priority {
+        "low" => 7,
+        "normal" => 4,
+        "high" => 0,
+        other => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("unknown io_priority \"{other}\""),
+            ));
+        }
+    };
+    set_best_effort_level(level)
+}
+
+#[cfg(target_os = "linux")]
+fn set_best_effort_level(level: i32) -> std::io::Result<()> {
+    const IOPRIO_CLASS_BE: i32 = 2;
+    const IOPRIO_CLASS_SHIFT: i32 = 13;
+    const IOPRIO_WHO_PROCESS: i32 = 1;
+
+    let ioprio = (IOPRIO_CLASS_BE << IOPRIO_CLASS_SHIFT) | level;
+    // The priority is per thread: set it on every running thread (such as
+    // the async runtime's workers); threads started later inherit it
+    for entry in std::fs::read_dir("/proc/self/task")? {
+        let Some(tid) = entry?
+            .file_name()
+            .to_str()
+            .and_then(|s| s.parse::<i32>().ok())
+        else {
+            continue;
+        };
+        // SAFETY: ioprio_set only takes integers
+        let rc = unsafe { libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, tid, ioprio) };
+        if rc == -1 {
+            let err = std::io::Error::last_os_error();
+            // The thread exited since the directory was read
+            if err.raw_os_error() != Some(libc::ESRCH) {
+                return Err(err);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_best_effort_level(_level: i32) -> std::io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn worker_semaphore_permits() {
+        assert_eq!(worker_semaphore(Some(3)).available_permits(), 3);
+        assert_eq!(worker_semaphore(Some(0)).available_permits(), 1);
+        assert_eq!(
+            worker_semaphore(None).available_permits(),
+            Semaphore::MAX_PERMITS
+        );
+    }
+
+    #[test]
+    fn unknown_io_priority_rejected() {
+        let err = set_io_priority("urgent").unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn normal_io_priority_applies() {
+        // Lowering our own priority within the best-effort class needs no privileges
+        set_io_priority("normal").unwrap();
+        set_io_priority("low").unwrap();
+    }
+}
@@ -0,0 +1,322 @@
+//! Bulk inserts for large backups.
+//!
+//! A backup writes a file row, a chunk upsert and a file-chunk mapping for
+//! every chunk it stores. [`BulkInsertHandle`] runs those writes in one
+//! `BEGIN IMMEDIATE` transaction with statements prepared once up front,
+//! and maps file chunks with multi-row `INSERT`s.
+
+use rusqlite::types::ToSql;
+use rusqlite::{Connection, Statement, params, params_from_iter};
+
+use super::ManifestDb;
+use super::queries::{
+    CHUNK_REF_COUNT, INSERT_BACKUP_FILE, INSERT_CHUNK_REPLICA, INSERT_FILE_CHUNK, INSERT_NEW_CHUNK,
+    SET_CHUNK_HMAC_TAG, UPSERT_CHUNK,
+};
+use crate::error::{EnigmaError, Result};
+
+/// Rows per multi-row `INSERT` in [`BulkInsertHandle::batch_insert_file_chunks`].
+const FILE_CHUNK_BATCH_ROWS: usize = 500;
+
+impl ManifestDb {
+    /// Start a bulk insert. The write lock is taken immediately, so the
+    /// inserts cannot fail halfway with `SQLITE_BUSY`. Nothing is written
+    /// unless [`BulkInsertHandle::commit`] is called; dropping the handle
+    /// rolls the transaction back.
+    pub fn begin_bulk_insert(&mut self) -> Result<BulkInsertHandle<'_>> {
+        let conn = self.conn();
+        let insert_backup_file = conn.prepare(INSERT_BACKUP_FILE)?;
+        let upsert_chunk = conn.prepare(UPSERT_CHUNK)?;
+        let insert_new_chunk = conn.prepare(INSERT_NEW_CHUNK)?;
+        let chunk_ref_count = conn.prepare(CHUNK_REF_COUNT)?;
+        let set_chunk_hmac_tag = conn.prepare(SET_CHUNK_HMAC_TAG)?;
+        let insert_chunk_replica = conn.prepare(INSERT_CHUNK_REPLICA)?;
+        let insert_file_chunk = conn.prepare(INSERT_FILE_CHUNK)?;
+        conn.execute_batch("BEGIN IMMEDIATE")?;
+        Ok(BulkInsertHandle {
+            conn,
+            insert_backup_file,
+            upsert_chunk,
+            insert_new_chunk,
+            chunk_ref_count,
+            set_chunk_hmac_tag,
+            insert_chunk_replica,
+            insert_file_chunk,
+            committed: false,
+        })
+    }
+}
+
+/// An open bulk-insert transaction. Its methods behave like the
+/// [`ManifestDb`] methods of the same name.
+pub struct BulkInsertHandle<'a> {
+    conn: &'a Connection,
+    insert_backup_file: Statement<'a>,
+    upsert_chunk: Statement<'a>,
+    insert_new_chunk: Statement<'a>,
+    chunk_ref_count: Statement<'a>,
+    set_chunk_hmac_tag: Statement<'a>,
+    insert_chunk_replica: Statement<'a>,
+    insert_file_chunk: Statement<'a>,
+    committed: bool,
+}
+
+impl BulkInsertHandle<'_> {
+    pub fn insert_backup_file(
+        &mut self,
+        backup_id: &str,
+        path: &str,
+        size: u64,
+        mtime: Option<&str>,
+        hash: &str,
+        chunk_count: u32,
+    ) -> Result<i64> {
+        self.insert_backup_file.execute(params![
+            backup_id,
+            path,
+            size,
+            mtime,
+            hash,
+            chunk_count
+        ])?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Returns true if the chunk is new, false if it was deduplicated.
+    #[allow(clippy::too_many_arguments)]
+    pub fn insert_or_dedup_chunk(
+        &mut self,
+        hash: &str,
+        nonce: &[u8],
+        key_id: &str,
+        provider_id: i64,
+        storage_key: &str,
+        size_plain: u64,
+        size_encrypted: u64,
+        size_compressed: Option<u64>,
+    ) -> Result<bool> {
+        self.upsert_chunk.execute(params![
+            hash,
+            nonce,
+            key_id,
+            provider_id,
+            storage_key,
+            size_plain,
+            size_encrypted,
+            size_compressed
+        ])?;
+        let ref_count: u64 = self
+            .chunk_ref_count
+            .query_row(params![hash], |row| row.get(0))?;
+        Ok(ref_count == 1)
+    }
+
+    /// [`Self::insert_or_dedup_chunk`] for a chunk the caller expects to be
+    /// new, e.g. one missing from its [`crate::dedup::DedupIndex`]: a plain
+    /// insert without the ref_count lookup. Falls back to the upsert when
+    /// the chunk turns out to be recorded already.
+    #[allow(clippy::too_many_arguments)]
+    pub fn insert_new_chunk(
+        &mut self,
+        hash: &str,
+        nonce: &[u8],
+        key_id: &str,
+        provider_id: i64,
+        storage_key: &str,
+        size_plain: u64,
+        size_encrypted: u64,
+        size_compressed: Option<u64>,
+    ) -> Result<bool> {
+        let inserted = self.insert_new_chunk.execute(params![
+            hash,
+            nonce,
+            key_id,
+            provider_id,
+            storage_key,
+            size_plain,
+            size_encrypted,
+            size_compressed
+        ])?;
+        if inserted == 1 {
+            return Ok(true);
+        }
+        self.insert_or_dedup_chunk(
+            hash,
+            nonce,
+            key_id,
+            provider_id,
+            storage_key,
+            size_plain,
+            size_encrypted,
+            size_compressed,
+        )
+    }
+
+    pub fn set_chunk_hmac_tag(&mut self, hash: &str, tag: &[u8; 32]) -> Result<()> {
+        self.set_chunk_hmac_tag.execute(params![hash, &tag[..]])?;
+        Ok(())
+    }
+
+    pub fn insert_chunk_replicas(
+        &mut self,
+        chunk_hash: &str,
+        replicas: &[(i64, &str)],
+    ) -> Result<()> {
+        for (provider_id, storage_key) in replicas {
+            self.insert_chunk_replica
+                .execute(params![chunk_hash, provider_id, storage_key])?;
+        }
+        Ok(())
+    }
+
+    pub fn insert_file_chunk(
+        &mut self,
+        file_id: i64,
+        chunk_hash: &str,
+        chunk_index: u32,
+        offset: u64,
+    ) -> Result<()> {
+        self.insert_file_chunk
+            .execute(params![file_id, chunk_hash, chunk_index, offset])?;
+        Ok(())
+    }
+
+    /// Map `(file_id, chunk_hash, chunk_index, offset)` records with
+    /// multi-row `INSERT`s of up to 500 rows each.
+    pub fn batch_insert_file_chunks(&mut self, records: &[(i64, &str, u32, u64)]) -> Result<()> {
+        for batch in records.chunks(FILE_CHUNK_BATCH_ROWS) {
+            let mut stmt = self
+                .conn
+                .prepare_cached(&multi_row_file_chunk_sql(batch.len()))?;
+            stmt.execute(params_from_iter(batch.iter().flat_map(
+                |(file_id, chunk_hash, chunk_index, offset)| {
+                    [file_id as &dyn ToSql, chunk_hash, chunk_index, offset]
+                },
+            )))?;
+        }
+        Ok(())
+    }
+
+    /// Commit everything inserted through this handle. If the commit
+    /// fails the transaction is rolled back.
+    pub fn commit(mut self) -> Result<()> {
+        self.conn
+            .execute_batch("COMMIT")
+            .map_err(EnigmaError::Database)?;
+        self.committed = true;
+        Ok(())
+    }
+}
+
+impl Drop for BulkInsertHandle<'_> {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = self.conn.execute_batch("ROLLBACK");
+        }
+    }
+}
+
+fn multi_row_file_chunk_sql(rows: usize) -> String {
+    let mut sql =
+        String::from("INSERT INTO file_chunks (file_id, chunk_hash, chunk_index, offset) VALUES ");
+    for row in 0..rows {
+        if row > 0 {
+            sql.push_str(", ");
+        }
+        sql.push_str("(?, ?, ?, ?)");
+    }
+    sql
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ProviderType;
+
+    const BACKUP_ID: &str = "01234567-abcd-7000-0000-000000000001";
+
+    fn db_with_backup() -> (ManifestDb, i64) {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let pid = db
+            .insert_provider("local", ProviderType::Local, "/tmp/enigma", None, 1)
+            .unwrap();
+        db.create_backup(BACKUP_ID, "/data").unwrap();
+        (db, pid)
+    }
+
+    #[test]
+    fn batch_of_5000_file_chunks_is_complete() {
+        let (mut db, pid) = db_with_backup();
+        let hashes: Vec<String> = (0..5_000).map(|i| format!("{i:064x}")).collect();
+
+        let mut bulk = db.begin_bulk_insert().unwrap();
+        let mut records = Vec::with_capacity(hashes.len());
+        for (i, hash) in hashes.iter().enumerate() {
+            let file_id = bulk
+                .insert_backup_file(BACKUP_ID, &format!("f{i}"), 4096, None, hash, 1)
+                .unwrap();
+            assert!(
+                bulk.insert_or_dedup_chunk(hash, &[0; 12], "k", pid, hash, 4096, 4112, None)
+                    .unwrap()
+            );
+            records.push((file_id, hash.as_str(), 0, 0));
+        }
+        bulk.batch_insert_file_chunks(&records).unwrap();
+        bulk.commit().unwrap();
+
+        let files = db.list_backup_files(BACKUP_ID).unwrap();
+        assert_eq!(files.len(), 5_000);
+        for (file_id, _, _, hash) in files {
+            assert_eq!(db.get_file_chunks(file_id).unwrap(), vec![(hash, 0, 0)]);
+        }
+    }
+
+    #[test]
+    fn dropped_handle_rolls_back() {
+        let (mut db, pid) = db_with_backup();
+        {
+            let mut bulk = db.begin_bulk_insert().unwrap();
+            let file_id = bulk
+                .insert_backup_file(BACKUP_ID, "a", 1, None, "h", 1)
+                .unwrap();
+            bulk.insert_or_dedup_chunk("h", &[0; 12], "k", pid, "h", 1, 17, None)
+                .unwrap();
+            bulk.insert_file_chunk(file_id, "h", 0, 0).unwrap();
+        }
+        assert!(db.list_backup_files(BACKUP_ID).unwrap().is_empty());
+
+        // The connection is usable again, and dedup is reported as usual
+        let mut bulk = db.begin_bulk_insert().unwrap();
+        assert!(
+            bulk.insert_or_dedup_chunk("h", &[0; 12], "k", pid, "h", 1, 17, None)
+                .unwrap()
+        );
+        assert!(
+            !bulk
+                .insert_or_dedup_chunk("h", &[0; 12], "k", pid, "h", 1, 17, None)
+                .unwrap()
+        );
+        bulk.commit().unwrap();
+    }
+
+    #[test]
+    fn insert_new_chunk_falls_back_to_dedup() {
+        let (mut db, pid) = db_with_backup();
+        let mut bulk = db.begin_bulk_insert().unwrap();
+        assert!(
+            bulk.insert_new_chunk("h", &[1; 12], "k", pid, "h", 1, 17, None)
+                .unwrap()
+        );
+        // Expected new but already recorded: deduplicated, first nonce kept
+        assert!(
+            !bulk
+                .insert_new_chunk("h", &[2; 12], "k", pid, "h", 1, 17, None)
+                .unwrap()
+        );
+        bulk.commit().unwrap();
+
+        let (nonce, ..) = db.get_chunk_info("h").unwrap().unwrap();
+        assert_eq!(nonce, vec![1; 12]);
+        assert_eq!(db.chunk_count().unwrap(), 1);
+    }
+}
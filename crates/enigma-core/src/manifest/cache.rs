@@ -0,0 +1,388 @@
+//! Short-lived caching of expensive manifest reads.
+//!
+//! Dashboards poll chunk statistics every few seconds, and each poll runs
+//! aggregates over the whole `chunks` table. A [`QueryCache`] shared by
+//! several connections keeps each result for the TTL configured in
+//! `enigma.cache_ttl_seconds`; [`CachedManifestDb`] reads through it and
+//! evicts the results its writes change. Writes made through other
+//! connections or processes show up once the TTL runs out.
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use dashmap::mapref::entry::Entry;
+
+use super::ManifestDb;
+use crate::error::Result;
+use crate::types::ProviderType;
+
+/// Methods whose results can be cached: the keys of `cache_ttl_seconds`.
+pub const CACHED_QUERIES: &[&str] = &[
+    "chunk_stats",
+    "chunk_storage_details",
+    "chunks_per_provider",
+    "recent_chunks",
+];
+
+/// Every cached method.
+const ALL: &[&str] = CACHED_QUERIES;
+/// Methods counting or summing chunks.
+const CHUNK_TOTALS: &[&str] = &[
+    "chunk_stats",
+    "chunk_storage_details",
+    "chunks_per_provider",
+];
+/// Methods reporting provider names.
+const PROVIDER_NAMES: &[&str] = &["chunks_per_provider", "recent_chunks"];
+/// Methods reporting chunk replicas.
+const REPLICAS: &[&str] = &["chunks_per_provider"];
+/// Methods counting orphans, which depend on chunk references.
+const ORPHANS: &[&str] = &["chunk_stats"];
+
+/// A cached method result; the concrete type depends on the method.
+type CachedResult = Arc<dyn Any + Send + Sync>;
+
+/// Cached query results, shared by the connections of one manifest.
+/// The default cache keeps nothing.
+#[derive(Default)]
+pub struct QueryCache {
+    entries: DashMap<String, (Instant, CachedResult)>,
+    ttls: HashMap<String, Duration>,
+    misses: AtomicU64,
+}
+
+impl QueryCache {
+    /// A cache keeping each method's results for `ttl_seconds[method]`;
+    /// methods that are not listed, or have a TTL of 0, are not cached.
+    pub fn new(ttl_seconds: &HashMap<String, u64>) -> Self {
+        Self {
+            entries: DashMap::new(),
+            ttls: ttl_seconds
+                .iter()
+                .filter(|(_, secs)| **secs > 0)
+                .map(|(method, secs)| (method.clone(), Duration::from_secs(*secs)))
+                .collect(),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Queries that went to the database.
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// Drop every cached result.
+    pub fn clear(&self) {
+        self.entries.clear();
+    }
+
+    /// Drop the cached results of `methods`, whatever their arguments.
+    fn invalidate(&self, methods: &[&str]) {
+        self.entries.retain(|key, _| {
+            let method = key.split_once(':').map_or(key.as_str(), |(m, _)| m);
+            !methods.contains(&method)
+        });
+    }
+
+    /// The result of `method` for `args`, from the cache if it is fresh.
+    /// Otherwise `load` runs while the entry is locked, so concurrent
+    /// misses wait for one query instead of each running it. Errors are
+    /// not cached.
+    fn get_or_load<T>(
+        &self,
+        method: &str,
+        args: &str,
+        load: impl FnOnce() -> Result<T>,
+    ) -> Result<T>
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        let Some(ttl) = self.ttls.get(method) else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return load();
+        };
+        let key = if args.is_empty() {
+            method.to_string()
+        } else {
+            format!("{method}:{args}")
+        };
+        let entry = self.entries.entry(key);
+        if let Entry::Occupied(cached) = &entry {
+            let (at, result) = cached.get();
+            if at.elapsed() < *ttl
+                && let Some(value) = result.downcast_ref::<T>()
+            {
+                return Ok(value.clone());
+            }
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let value = load()?;
+        entry.insert((Instant::now(), Arc::new(value.clone())));
+        Ok(value)
+    }
+}
+
+/// Generates [`CachedManifestDb`] methods forwarding a write to the
+/// [`ManifestDb`] method of the same name, then evicting the cached
+/// results it may have changed.
+macro_rules! forward_writes {
+    ($($(#[$attr:meta])* fn $name:ident(&self $(, $arg:ident: $ty:ty)*) -> $ret:ty => $evicts:expr;)*) => {
+        $(
+            $(#[$attr])*
+            #[allow(clippy::too_many_arguments)]
+            pub fn $name(&self $(, $arg: $ty)*) -> $ret {
+                let result = self.db.$name($($arg),*);
+                self.cache.invalidate($evicts);
+                result
+            }
+        )*
+    };
+}
+
+/// A manifest connection caching expensive reads in a shared [`QueryCache`].
+pub struct CachedManifestDb {
+    db: ManifestDb,
+    cache: Arc<QueryCache>,
+}
+
+impl CachedManifestDb {
+    pub fn new(db: ManifestDb, cache: Arc<QueryCache>) -> Self {
+        Self { db, cache }
+    }
+
+    /// Open the manifest at `path`; see [`ManifestDb::open`].
+    pub fn open(path: &Path, cache: Arc<QueryCache>) -> Result<Self> {
+        Ok(Self::new(ManifestDb::open(path)?, cache))
+    }
+
+    /// The underlying connection, for queries that are not cached. Writes
+    /// made through it do not evict anything.
+    pub fn db(&self) -> &ManifestDb {
+        &self.db
+    }
+
+    pub fn cache(&self) -> &Arc<QueryCache> {
+        &self.cache
+    }
+
+    /// See [`ManifestDb::chunk_stats`].
+    pub fn chunk_stats(&self) -> Result<(u64, u64)> {
+        self.cache
+            .get_or_load("chunk_stats", "", || self.db.chunk_stats())
+    }
+
+    /// See [`ManifestDb::chunk_storage_details`].
+    pub fn chunk_storage_details(&self) -> Result<(u64, u64, u64, Option<u64>, u64)> {
+        self.cache.get_or_load("chunk_storage_details", "", || {
+            self.db.chunk_storage_details()
+        })
+    }
+
+    /// See [`ManifestDb::chunks_per_provider`].
+    pub fn chunks_per_provider(&self) -> Result<Vec<(i64, String, u64, u64)>> {
+        self.cache
+            .get_or_load("chunks_per_provider", "", || self.db.chunks_per_provider())
+    }
+
+    /// See [`ManifestDb::recent_chunks`].
+    #[allow(clippy::type_complexity)]
+    pub fn recent_chunks(
+        &self,
+        limit: u32,
+    ) -> Result<Vec<(String, String, u64, u64, u64, String)>> {
+        self.cache
+            .get_or_load("recent_chunks", &limit.to_string(), || {
+                self.db.recent_chunks(limit)
+            })
+    }
+
+    forward_writes! {
+        /// See [`ManifestDb::insert_provider`].
+        fn insert_provider(
+            &self,
+            name: &str,
+            provider_type: ProviderType,
+            bucket: &str,
+            region: Option<&str>,
+            weight: u32
+        ) -> Result<i64> => PROVIDER_NAMES;
+        /// See [`ManifestDb::insert_or_dedup_chunk`].
+        fn insert_or_dedup_chunk(
+            &self,
+            hash: &str,
+            nonce: &[u8],
+            key_id: &str,
+            provider_id: i64,
+            storage_key: &str,
+            size_plain: u64,
+            size_encrypted: u64,
+            size_compressed: Option<u64>
+        ) -> Result<bool> => ALL;
+        /// See [`ManifestDb::decrement_chunk_ref`].
+        fn decrement_chunk_ref(&self, hash: &str) -> Result<Vec<(i64, String)>> => CHUNK_TOTALS;
+        /// See [`ManifestDb::delete_chunk_record`].
+        fn delete_chunk_record(&self, hash: &str) -> Result<()> => ALL;
+        /// See [`ManifestDb::insert_chunk_replicas`].
+        fn insert_chunk_replicas(
+            &self,
+            chunk_hash: &str,
+            replicas: &[(i64, &str)]
+        ) -> Result<()> => REPLICAS;
+        /// See [`ManifestDb::delete_chunk_replica`].
+        fn delete_chunk_replica(&self, hash: &str, provider_id: i64) -> Result<()> => REPLICAS;
+        /// See [`ManifestDb::insert_file_chunk`].
+        fn insert_file_chunk(
+            &self,
+            file_id: i64,
+            chunk_hash: &str,
+            chunk_index: u32,
+            offset: u64
+        ) -> Result<()> => ORPHANS;
+        /// See [`ManifestDb::insert_object_chunk`].
+        fn insert_object_chunk(
+            &self,
+            object_id: i64,
+            chunk_hash: &str,
+            chunk_index: u32,
+            offset: u64
+        ) -> Result<()> => ORPHANS;
+        /// See [`ManifestDb::delete_object_by_ns_key`].
+        fn delete_object_by_ns_key(
+            &self,
+            namespace_id: i64,
+            key: &str
+        ) -> Result<Vec<(i64, String)>> => CHUNK_TOTALS;
+        /// See [`ManifestDb::delete_object_version`].
+        fn delete_object_version(
+            &self,
+            namespace_id: i64,
+            key: &str,
+            version_id: &str
+        ) -> Result<Option<Vec<(i64, String)>>> => CHUNK_TOTALS;
+        /// See [`ManifestDb::delete_namespace_permanently`].
+        fn delete_namespace_permanently(
+            &self,
+            name: &str
+        ) -> Result<Option<Vec<(i64, String)>>> => CHUNK_TOTALS;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Barrier;
+
+    use super::*;
+
+    fn ttls(secs: u64) -> HashMap<String, u64> {
+        CACHED_QUERIES
+            .iter()
+            .map(|method| (method.to_string(), secs))
+            .collect()
+    }
+
+    fn insert_chunk(db: &CachedManifestDb, hash: &str, provider_id: i64) {
+        db.insert_or_dedup_chunk(hash, &[0; 12], "k", provider_id, hash, 100, 116, None)
+            .unwrap();
+    }
+
+    #[test]
+    fn parallel_reads_share_one_query() {
+        const READERS: usize = 100;
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("manifest.db");
+        let cache = Arc::new(QueryCache::new(&ttls(60)));
+        let db = CachedManifestDb::open(&path, cache.clone()).unwrap();
+        let pid = db
+            .insert_provider("local", ProviderType::Local, "/tmp/enigma", None, 1)
+            .unwrap();
+        insert_chunk(&db, "h1", pid);
+        let misses = cache.misses();
+
+        let barrier = Arc::new(Barrier::new(READERS));
+        let readers: Vec<_> = (0..READERS)
+            .map(|_| {
+                let db =
+                    CachedManifestDb::new(ManifestDb::open_readonly(&path).unwrap(), cache.clone());
+                let barrier = barrier.clone();
+                std::thread::spawn(move || {
+                    barrier.wait();
+                    db.chunk_stats().unwrap()
+                })
+            })
+            .collect();
+        for reader in readers {
+            assert_eq!(reader.join().unwrap(), (1, 0));
+        }
+        assert_eq!(cache.misses() - misses, 1);
+    }
+
+    #[test]
+    fn writes_evict_what_they_change() {
+        let cache = Arc::new(QueryCache::new(&ttls(60)));
+        let db = CachedManifestDb::new(ManifestDb::open_in_memory().unwrap(), cache.clone());
+        let pid = db
+            .insert_provider("local", ProviderType::Local, "/tmp/enigma", None, 1)
+            .unwrap();
+        assert_eq!(db.chunk_stats().unwrap(), (0, 0));
+        assert_eq!(db.recent_chunks(10).unwrap().len(), 0);
+
+        insert_chunk(&db, "h1", pid);
+        assert_eq!(db.chunk_stats().unwrap(), (1, 0));
+        assert_eq!(db.recent_chunks(10).unwrap().len(), 1);
+        assert_eq!(db.chunks_per_provider().unwrap()[0].2, 1);
+
+        // Replicas only change the per-provider counts
+        let misses = cache.misses();
+        db.insert_chunk_replicas("h1", &[(pid, "h1-copy")]).unwrap();
+        assert_eq!(db.chunk_stats().unwrap(), (1, 0));
+        assert_eq!(db.chunks_per_provider().unwrap()[0].2, 2);
+        assert_eq!(cache.misses() - misses, 1);
+
+        db.delete_chunk_record("h1").unwrap();
+        assert_eq!(db.chunk_stats().unwrap(), (0, 0));
+        assert!(db.recent_chunks(10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn writes_elsewhere_are_not_seen_until_evicted() {
+        let cache = Arc::new(QueryCache::new(&ttls(60)));
+        let db = CachedManifestDb::new(ManifestDb::open_in_memory().unwrap(), cache.clone());
+        let pid = db
+            .insert_provider("local", ProviderType::Local, "/tmp/enigma", None, 1)
+            .unwrap();
+        assert_eq!(db.chunk_stats().unwrap(), (0, 0));
+
+        // Bypasses the cache, as another process would
+        db.db()
+            .insert_or_dedup_chunk("h1", &[0; 12], "k", pid, "h1", 100, 116, None)
+            .unwrap();
+        assert_eq!(db.chunk_stats().unwrap(), (0, 0));
+        cache.clear();
+        assert_eq!(db.chunk_stats().unwrap(), (1, 0));
+    }
+
+    #[test]
+    fn uncached_methods_always_query() {
+        let mut ttl_seconds = ttls(60);
+        ttl_seconds.insert("chunk_stats".to_string(), 0);
+        let cache = Arc::new(QueryCache::new(&ttl_seconds));
+        let db = CachedManifestDb::new(ManifestDb::open_in_memory().unwrap(), cache.clone());
+
+        for _ in 0..3 {
+            db.chunk_stats().unwrap();
+            db.chunk_storage_details().unwrap();
+        }
+        assert_eq!(cache.misses(), 4);
+
+        // Another limit is another query
+        db.recent_chunks(1).unwrap();
+        db.recent_chunks(2).unwrap();
+        db.recent_chunks(2).unwrap();
+        assert_eq!(cache.misses(), 6);
+    }
+}
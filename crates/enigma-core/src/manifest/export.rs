@@ -0,0 +1,334 @@
+//! Manifest export and import for disaster recovery.
+//!
+//! The export is a JSON array of `{"table": ..., "row": {...}}` records,
+//! parents before children so an import never trips a foreign key. Every
+//! cell is tagged with its SQLite type; blobs are base64.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{BufReader, Read, Write};
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use rusqlite::Connection;
+use rusqlite::types::Value;
+use serde::de::{self, Deserializer as _, SeqAccess, Visitor};
+use serde::{Deserialize, Serialize};
+
+use super::ManifestDb;
+use crate::error::{EnigmaError, Result};
+
+/// Exported tables, in insertion order.
+const EXPORT_TABLES: &[&str] = &[
+    "providers",
+    "namespaces",
+    "namespace_tags",
+    "namespace_inventory_config",
+    "backups",
+    "backup_tags",
+    "backup_files",
+    "chunks",
+    "chunk_replicas",
+    "chunk_rekeys",
+    "file_chunks",
+    "objects",
+    "object_tags",
+    "object_metadata",
+    "object_chunks",
+];
+
+/// Tables that are not exported but reference exported ones; a full
+/// import empties them too.
+const TRANSIENT_TABLES: &[&str] = &[
+    "multipart_part_chunks",
+    "multipart_parts",
+    "multipart_uploads",
+    "pending_object_chunks",
+    "pending_objects",
+    "backup_logs",
+    "gc_verify_progress",
+];
+
+/// Outcome of [`ManifestDb::import_from_json`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ImportStats {
+    /// Records written to the manifest.
+    pub inserted: u64,
+    /// Records whose primary or unique key already existed.
+    pub skipped: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ExportRecord {
+    table: String,
+    row: BTreeMap<String, Cell>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Cell {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    Blob(String),
+}
+
+impl From<Value> for Cell {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Null => Cell::Null,
+            Value::Integer(i) => Cell::Integer(i),
+            Value::Real(f) => Cell::Real(f),
+            Value::Text(s) => Cell::Text(s),
+            Value::Blob(b) => Cell::Blob(BASE64.encode(b)),
+        }
+    }
+}
+
+impl Cell {
+    fn into_value(self) -> Result<Value> {
+        Ok(match self {
+            Cell::Null => Value::Null,
+            Cell::Integer(i) => Value::Integer(i),
+            Cell::Real(f) => Value::Real(f),
+            Cell::Text(s) => Value::Text(s),
+            Cell::Blob(b) => Value::Blob(
+                BASE64
+                    .decode(b)
+                    .map_err(|e| EnigmaError::Integrity(format!("invalid blob in export: {e}")))?,
+            ),
+        })
+    }
+}
+
+impl ManifestDb {
+    /// Write every manifest table to `writer` as a JSON array of records,
+    /// one row at a time. Multipart uploads, backup logs and
+    /// `gc --verify` progress are left out.
+    pub fn export_to_json(&self, writer: &mut dyn Write) -> Result<()> {
+        // A read transaction keeps the tables consistent with each other
+        let tx = self.conn().unchecked_transaction()?;
+        writer.write_all(b"[")?;
+        let mut first = true;
+        for table in EXPORT_TABLES {
+            let mut stmt = tx.prepare(&format!("SELECT * FROM {table} ORDER BY rowid"))?;
+            let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+            let mut rows = stmt.query([])?;
+            while let Some(row) = rows.next()? {
+                let mut cells = BTreeMap::new();
+                for (i, column) in columns.iter().enumerate() {
+                    cells.insert(column.clone(), Cell::from(row.get::<_, Value>(i)?));
+                }
+                let record = ExportRecord {
+                    table: table.to_string(),
+                    row: cells,
+                };
+                writer.write_all(if first { b"\n" } else { b",\n" })?;
+                serde_json::to_writer(&mut *writer, &record)?;
+                first = false;
+            }
+        }
+        writer.write_all(b"\n]\n")?;
+        Ok(())
+    }
+
+    /// Merge an export into this manifest. Records whose key already exists
+    /// are kept as they are (`INSERT OR IGNORE`).
+    pub fn import_from_json(&self, reader: &mut dyn Read) -> Result<ImportStats> {
+        self.import(reader, false)
+    }
+
+    /// Replace the whole manifest with an export. The wipe and the import
+    /// run in one transaction, so a bad file leaves the manifest untouched.
+    pub fn replace_from_json(&self, reader: &mut dyn Read) -> Result<ImportStats> {
+        self.import(reader, true)
+    }
+
+    fn import(&self, reader: &mut dyn Read, wipe: bool) -> Result<ImportStats> {
+        let tx = self.conn().unchecked_transaction()?;
+        if wipe {
+            for table in TRANSIENT_TABLES.iter().chain(EXPORT_TABLES.iter().rev()) {
+                tx.execute(&format!("DELETE FROM {table}"), [])?;
+            }
+        }
+
+        let mut importer = Importer {
+            conn: &tx,
+            columns: HashMap::new(),
+            stats: ImportStats::default(),
+        };
+        let mut de = serde_json::Deserializer::from_reader(BufReader::new(reader));
+        de.deserialize_seq(&mut importer)?;
+        de.end()?;
+
+        let stats = importer.stats;
+        tx.commit()?;
+        Ok(stats)
+    }
+}
+
+/// Inserts records as the JSON array is parsed, so the export is never
+/// held in memory as a whole.
+struct Importer<'a> {
+    conn: &'a Connection,
+    /// Known columns of each table seen so far.
+    columns: HashMap<String, HashSet<String>>,
+    stats: ImportStats,
+}
+
+impl Importer<'_> {
+    fn insert(&mut self, record: ExportRecord) -> Result<()> {
+        let table = EXPORT_TABLES
+            .iter()
+            .find(|t| **t == record.table)
+            .ok_or_else(|| {
+                EnigmaError::Integrity(format!("unknown table in export: {}", record.table))
+            })?;
+        if !self.columns.contains_key(*table) {
+            let mut stmt = self
+                .conn
+                .prepare(&format!("SELECT name FROM pragma_table_info('{table}')"))?;
+            let names = stmt
+                .query_map([], |row| row.get(0))?
+                .collect::<std::result::Result<HashSet<String>, _>>()?;
+            self.columns.insert(table.to_string(), names);
+        }
+        let known = &self.columns[*table];
+
+        let mut columns = Vec::with_capacity(record.row.len());
+        let mut values = Vec::with_capacity(record.row.len());
+        for (column, cell) in record.row {
+            if !known.contains(&column) {
+                return Err(EnigmaError::Integrity(format!(
+                    "unknown column in export: {table}.{column}"
+                )));
+            }
+            columns.push(format!("\"{column}\""));
+            values.push(cell.into_value()?);
+        }
+        let placeholders: Vec<String> = (1..=values.len()).map(|i| format!("?{i}")).collect();
+        let sql = format!(
+            "INSERT OR IGNORE INTO {table} ({}) VALUES ({})",
+            columns.join(", "),
+            placeholders.join(", ")
+        );
+        let changed = self
+            .conn
+            .execute(&sql, rusqlite::params_from_iter(values))?;
+        if changed > 0 {
+            self.stats.inserted += 1;
+        } else {
+            self.stats.skipped += 1;
+        }
+        Ok(())
+    }
+}
+
+impl<'de> Visitor<'de> for &mut Importer<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("an array of manifest records")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<(), A::Error> {
+        while let Some(record) = seq.next_element::<ExportRecord>()? {
+            self.insert(record).map_err(de::Error::custom)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ProviderType;
+
+    fn sample_db() -> ManifestDb {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let pid = db
+            .insert_provider("local", ProviderType::Local, "/tmp/enigma", None, 1)
+            .unwrap();
+        db.create_backup("b1", "/src").unwrap();
+        db.set_backup_tag("b1", "env", "prod").unwrap();
+        let file_id = db
+            .insert_backup_file("b1", "a.txt", 2, None, "h", 1)
+            .unwrap();
+        db.insert_or_dedup_chunk("aa", &[7u8; 12], "k", pid, "aa", 2, 2, None)
+            .unwrap();
+        db.insert_chunk_replicas("aa", &[(pid, "aa")]).unwrap();
+        db.insert_file_chunk(file_id, "aa", 0, 0).unwrap();
+        db.complete_backup("b1", 1, 2, 1, 0).unwrap();
+        let ns = db.create_namespace("bucket").unwrap();
+        let object_id = db.insert_object(ns, "obj", 2, "e", None, 1, "k").unwrap();
+        db.insert_object_chunk(object_id, "aa", 0, 0).unwrap();
+        db
+    }
+
+    fn export(db: &ManifestDb) -> Vec<u8> {
+        let mut out = Vec::new();
+        db.export_to_json(&mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn export_import_roundtrip() {
+        let source = sample_db();
+        let data = export(&source);
+
+        let target = ManifestDb::open_in_memory().unwrap();
+        let stats = target.import_from_json(&mut data.as_slice()).unwrap();
+        assert_eq!(stats.skipped, 0);
+        assert_eq!(stats.inserted, 10);
+
+        let backup = target.get_backup("b1").unwrap();
+        assert_eq!(backup.total_chunks, 1);
+        assert_eq!(target.get_backup_tags("b1").unwrap()["env"], "prod");
+        let (nonce, key_id, ..) = target.get_chunk_info("aa").unwrap().unwrap();
+        assert_eq!(nonce, vec![7u8; 12]);
+        assert_eq!(key_id, "k");
+        let ns = target.get_namespace_id("bucket").unwrap().unwrap();
+        let (object_id, ..) = target.get_object(ns, "obj").unwrap().unwrap();
+        assert_eq!(target.get_object_chunks(object_id).unwrap().len(), 1);
+        assert!(target.verify_merkle_path("b1", 0).unwrap());
+
+        // The re-export is identical
+        assert_eq!(export(&target), data);
+    }
+
+    #[test]
+    fn merge_keeps_existing_records() {
+        let db = sample_db();
+        let data = export(&db);
+        let stats = db.import_from_json(&mut data.as_slice()).unwrap();
+        assert_eq!(stats.inserted, 0);
+        assert_eq!(stats.skipped, 10);
+    }
+
+    #[test]
+    fn replace_wipes_first() {
+        let source = ManifestDb::open_in_memory().unwrap();
+        source.create_namespace("only").unwrap();
+        let data = export(&source);
+
+        let db = sample_db();
+        db.replace_from_json(&mut data.as_slice()).unwrap();
+        assert!(db.list_backups().unwrap().is_empty());
+        assert_eq!(db.list_namespaces().unwrap().len(), 1);
+        assert!(db.get_namespace_id("only").unwrap().is_some());
+    }
+
+    #[test]
+    fn bad_import_changes_nothing() {
+        let db = sample_db();
+        let data = br#"[{"table":"namespaces","row":{"name":{"text":"x"}}},
+            {"table":"sqlite_master","row":{}}]"#;
+        assert!(db.replace_from_json(&mut data.as_slice()).is_err());
+        assert_eq!(db.list_backups().unwrap().len(), 1);
+        assert!(db.get_namespace_id("x").unwrap().is_none());
+
+        let data = br#"[{"table":"namespaces","row":{"name); DROP TABLE chunks; --":"null"}}]"#;
+        assert!(db.import_from_json(&mut data.as_slice()).is_err());
+        assert!(db.get_chunk_info("aa").unwrap().is_some());
+    }
+}
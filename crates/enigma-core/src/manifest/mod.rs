@@ -1,5 +1,16 @@
+mod bulk;
+mod cache;
+mod export;
 mod queries;
+mod readonly;
 mod schema;
 
-pub use queries::ManifestDb;
+pub use bulk::BulkInsertHandle;
+pub use cache::{CACHED_QUERIES, CachedManifestDb, QueryCache};
+pub use export::ImportStats;
+pub use queries::{
+    CheckpointMode, HISTOGRAM_BUCKET_KB, HISTOGRAM_MAX_KB, ManifestDb, MultipartPart,
+    MultipartPartsCursor, ObjectVersionRecord, QueuedWebhook,
+};
+pub use readonly::ReadonlyManifestDb;
 pub use schema::migrate;
@@ -1,10 +1,30 @@
-use rusqlite::{Connection, params};
-use std::collections::HashSet;
+use rusqlite::{Connection, OptionalExtension, params};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 use std::time::Duration;
 
+use crate::crypto::signing;
 use crate::error::{EnigmaError, Result};
-use crate::types::{BackupRecord, BackupStatus, ProviderInfo, ProviderType};
+use crate::merkle;
+use crate::types::{
+    BackupRecord, BackupStatus, ChunkHash, CrossNamespaceDedupEntry, DedupStats, GlobalDedupStats,
+    InventoryConfig, InventoryReport, KeyRotationRecord, ProviderInfo, ProviderType,
+    S3AccessLogEntry, VersioningStatus, dedup_ratio_percent,
+};
+
+fn inventory_config_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<InventoryConfig> {
+    Ok(InventoryConfig {
+        id: row.get(0)?,
+        enabled: row.get(1)?,
+        destination_bucket: row.get(2)?,
+        destination_prefix: row.get(3)?,
+        format: row.get(4)?,
+        frequency: row.get(5)?,
+        included_object_versions: row.get(6)?,
+        filter_prefix: row.get(7)?,
+    })
+}
 
 /// Escape special characters in a string used as a LIKE pattern argument.
 fn escape_like(s: &str) -> String {
@@ -13,6 +33,75 @@ fn escape_like(s: &str) -> String {
         .replace('_', "\\_")
 }
 
+/// Width of a [`ManifestDb::chunk_size_histogram`] bucket.
+pub const HISTOGRAM_BUCKET_KB: u64 = 64;
+/// Chunks of this size or larger are counted in the last histogram bucket.
+pub const HISTOGRAM_MAX_KB: u64 = 32 * 1024;
+
+/// Keeps the rows of `chunks c` that backup `?1` references, or all of them
+/// when `?1` is NULL.
+const CHUNK_SCOPE: &str = "(?1 IS NULL OR c.hash IN (
+    SELECT fc.chunk_hash FROM file_chunks fc
+    JOIN backup_files bf ON bf.id = fc.file_id
+    WHERE bf.backup_id = ?1
+))";
+
+/// Chunks referenced from more than one namespace, as `(hash, names,
+/// namespace_count)` with `names` joined by U+001F.
+const CROSS_NAMESPACE_CHUNKS: &str = "SELECT hash, group_concat(name, char(31)) AS names,
+        COUNT(*) AS namespace_count
+    FROM (
+        SELECT DISTINCT oc.chunk_hash AS hash, n.name AS name
+        FROM object_chunks oc
+        JOIN objects o ON o.id = oc.object_id
+        JOIN namespaces n ON n.id = o.namespace_id
+    )
+    GROUP BY hash
+    HAVING COUNT(*) > 1";
+
+/// Shared with [`super::BulkInsertHandle`], which prepares the same
+/// statements once per transaction.
+pub(super) const INSERT_BACKUP_FILE: &str = "INSERT INTO backup_files (backup_id, path, size, mtime, hash, chunk_count) VALUES (?1, ?2, ?3, ?4, ?5, ?6)";
+pub(super) const UPSERT_CHUNK: &str = "INSERT INTO chunks (hash, nonce, key_id, provider_id, storage_key, size_plain, size_encrypted, size_compressed, ref_count)
+     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, 1)
+     ON CONFLICT(hash) DO UPDATE SET
+         nonce = CASE WHEN ref_count = 0 THEN excluded.nonce ELSE nonce END,
+         key_id = CASE WHEN ref_count = 0 THEN excluded.key_id ELSE key_id END,
+         provider_id = CASE WHEN ref_count = 0 THEN excluded.provider_id ELSE provider_id END,
+         storage_key = CASE WHEN ref_count = 0 THEN excluded.storage_key ELSE storage_key END,
+         size_encrypted = CASE WHEN ref_count = 0 THEN excluded.size_encrypted ELSE size_encrypted END,
+         size_compressed = CASE WHEN ref_count = 0 THEN excluded.size_compressed ELSE size_compressed END,
+         ref_count = ref_count + 1";
+pub(super) const INSERT_NEW_CHUNK: &str = "INSERT OR IGNORE INTO chunks (hash, nonce, key_id, provider_id, storage_key, size_plain, size_encrypted, size_compressed, ref_count)
+     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, 1)";
+pub(super) const CHUNK_REF_COUNT: &str = "SELECT ref_count FROM chunks WHERE hash = ?1";
+pub(super) const SET_CHUNK_HMAC_TAG: &str = "UPDATE chunks SET hmac_tag = ?2 WHERE hash = ?1";
+pub(super) const INSERT_CHUNK_REPLICA: &str = "INSERT OR IGNORE INTO chunk_replicas (chunk_hash, provider_id, storage_key) VALUES (?1, ?2, ?3)";
+pub(super) const INSERT_FILE_CHUNK: &str =
+    "INSERT INTO file_chunks (file_id, chunk_hash, chunk_index, offset) VALUES (?1, ?2, ?3, ?4)";
+
+/// How hard [`ManifestDb::checkpoint`] tries to move the WAL into the
+/// database file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckpointMode {
+    /// Copy what it can without waiting for readers or writers.
+    Passive,
+    /// Wait for writers, then copy every frame.
+    Full,
+    /// Like `Full`, then truncate the WAL file to zero bytes.
+    Truncate,
+}
+
+impl CheckpointMode {
+    fn as_sql(self) -> &'static str {
+        match self {
+            Self::Passive => "PASSIVE",
+            Self::Full => "FULL",
+            Self::Truncate => "TRUNCATE",
+        }
+    }
+}
+
 /// High-level interface for manifest database operations.
 pub struct ManifestDb {
     conn: Connection,
@@ -36,10 +125,30 @@ impl ManifestDb {
         Ok(Self { conn })
     }
 
+    /// Wrap an already-open connection, e.g. one handed out by a pool.
+    /// The schema is not migrated: open the database once with
+    /// [`ManifestDb::open`] before wrapping further connections to it.
+    pub fn from_connection(conn: Connection) -> Self {
+        Self { conn }
+    }
+
     pub fn conn(&self) -> &Connection {
         &self.conn
     }
 
+    /// Checkpoint the write-ahead log. Returns `(wal_frames,
+    /// checkpointed_frames)`: the frames in the WAL and how many of them
+    /// are now in the database file. Both are 0 for a database that is not
+    /// in WAL mode, e.g. an in-memory one.
+    pub fn checkpoint(&self, mode: CheckpointMode) -> Result<(u32, u32)> {
+        let (log, checkpointed): (i64, i64) = self.conn.query_row(
+            &format!("PRAGMA wal_checkpoint({})", mode.as_sql()),
+            [],
+            |row| Ok((row.get(1)?, row.get(2)?)),
+        )?;
+        Ok((log.max(0) as u32, checkpointed.max(0) as u32))
+    }
+
     /// Begin an explicit SQLite transaction for batched writes.
     pub fn begin_transaction(&self) -> Result<()> {
         self.conn
@@ -54,6 +163,13 @@ impl ManifestDb {
             .map_err(EnigmaError::Database)
     }
 
+    /// Roll back the current SQLite transaction.
+    pub fn rollback_transaction(&self) -> Result<()> {
+        self.conn
+            .execute_batch("ROLLBACK")
+            .map_err(EnigmaError::Database)
+    }
+
     // ── Providers ──────────────────────────────────────────────
 
     pub fn insert_provider(
@@ -71,6 +187,15 @@ impl ManifestDb {
         Ok(self.conn.last_insert_rowid())
     }
 
+    /// Change a provider's distribution weight.
+    pub fn set_provider_weight(&self, id: i64, weight: u32) -> Result<()> {
+        self.conn.execute(
+            "UPDATE providers SET weight=?2 WHERE id=?1",
+            params![id, weight],
+        )?;
+        Ok(())
+    }
+
     pub fn list_providers(&self) -> Result<Vec<ProviderInfo>> {
         let mut stmt = self
             .conn
@@ -125,6 +250,7 @@ impl ManifestDb {
             "UPDATE backups SET status='completed', total_files=?2, total_bytes=?3, total_chunks=?4, dedup_chunks=?5, completed_at=datetime('now') WHERE id=?1",
             params![id, total_files, total_bytes, total_chunks, dedup_chunks],
         )?;
+        self.build_backup_merkle_root(id)?;
         Ok(())
     }
 
@@ -280,6 +406,309 @@ impl ManifestDb {
         }
     }
 
+    /// Deduplication stats of one backup, from the counters recorded by
+    /// `complete_backup` and the chunks its files reference.
+    pub fn backup_dedup_stats(&self, backup_id: &str) -> Result<DedupStats> {
+        let backup = self.get_backup(backup_id)?;
+        let (unique_chunks, total_bytes_stored) = self.conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(c.size_encrypted), 0) FROM chunks c
+             WHERE c.hash IN (
+                 SELECT fc.chunk_hash FROM file_chunks fc
+                 JOIN backup_files bf ON bf.id = fc.file_id
+                 WHERE bf.backup_id = ?1
+             )",
+            params![backup_id],
+            |row| Ok((row.get::<_, u64>(0)?, row.get::<_, u64>(1)?)),
+        )?;
+        Ok(DedupStats {
+            total_chunks: backup.total_chunks,
+            unique_chunks,
+            dedup_chunks: backup.dedup_chunks,
+            total_bytes_logical: backup.total_bytes,
+            total_bytes_stored,
+            dedup_ratio_percent: dedup_ratio_percent(backup.dedup_chunks, backup.total_chunks),
+        })
+    }
+
+    /// Deduplication stats summed over all completed backups. Chunk totals
+    /// come from the chunk table's ref counts rather than a walk of every
+    /// file's chunk list.
+    pub fn global_dedup_stats(&self) -> Result<GlobalDedupStats> {
+        let (backups, total_chunks, dedup_chunks, total_bytes_logical) = self.conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(total_chunks), 0), COALESCE(SUM(dedup_chunks), 0),
+                    COALESCE(SUM(total_bytes), 0)
+             FROM backups WHERE status = 'completed'",
+            [],
+            |row| {
+                Ok((
+                    row.get::<_, u64>(0)?,
+                    row.get::<_, u64>(1)?,
+                    row.get::<_, u64>(2)?,
+                    row.get::<_, u64>(3)?,
+                ))
+            },
+        )?;
+        let (shared_chunks, total_bytes_stored) = self.conn.query_row(
+            "SELECT COALESCE(SUM(ref_count > 1), 0), COALESCE(SUM(size_encrypted), 0) FROM chunks",
+            [],
+            |row| Ok((row.get::<_, u64>(0)?, row.get::<_, u64>(1)?)),
+        )?;
+        Ok(GlobalDedupStats {
+            backups,
+            total_chunks,
+            shared_chunks,
+            dedup_chunks,
+            total_bytes_logical,
+            total_bytes_stored,
+            dedup_ratio_percent: dedup_ratio_percent(dedup_chunks, total_chunks),
+        })
+    }
+
+    /// Every chunk shared between namespaces, largest first.
+    pub fn cross_namespace_dedup_report(&self) -> Result<Vec<CrossNamespaceDedupEntry>> {
+        self.cross_namespace_dedup_query(-1, 0)
+    }
+
+    /// One page of [`Self::cross_namespace_dedup_report`].
+    pub fn cross_namespace_dedup_page(
+        &self,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<CrossNamespaceDedupEntry>> {
+        self.cross_namespace_dedup_query(limit.into(), offset.into())
+    }
+
+    fn cross_namespace_dedup_query(
+        &self,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<CrossNamespaceDedupEntry>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT s.hash, s.names, c.ref_count, c.size_encrypted
+             FROM ({CROSS_NAMESPACE_CHUNKS}) s
+             JOIN chunks c ON c.hash = s.hash
+             ORDER BY c.size_encrypted DESC, s.hash
+             LIMIT ?1 OFFSET ?2"
+        ))?;
+        let rows = stmt.query_map(params![limit, offset], |row| {
+            let names: String = row.get(1)?;
+            let mut namespaces: Vec<String> = names.split('\u{1f}').map(String::from).collect();
+            namespaces.sort();
+            Ok(CrossNamespaceDedupEntry {
+                chunk_hash: row.get(0)?,
+                namespaces,
+                ref_count: row.get(2)?,
+                size_bytes: row.get(3)?,
+            })
+        })?;
+        Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+    }
+
+    /// Bytes saved by sharing chunks between namespaces: each shared chunk
+    /// would otherwise be stored once per namespace.
+    pub fn cross_namespace_savings_bytes(&self) -> Result<u64> {
+        Ok(self.conn.query_row(
+            &format!(
+                "SELECT COALESCE(SUM((s.namespace_count - 1) * c.size_encrypted), 0)
+                 FROM ({CROSS_NAMESPACE_CHUNKS}) s
+                 JOIN chunks c ON c.hash = s.hash"
+            ),
+            [],
+            |row| row.get(0),
+        )?)
+    }
+
+    // ── Backup tags ────────────────────────────────────────────
+
+    /// Set a tag on a backup, overwriting any existing value for `key`.
+    pub fn set_backup_tag(&self, backup_id: &str, key: &str, value: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO backup_tags (backup_id, key, value) VALUES (?1, ?2, ?3)
+             ON CONFLICT(backup_id, key) DO UPDATE SET value = excluded.value",
+            params![backup_id, key, value],
+        )?;
+        Ok(())
+    }
+
+    /// Remove a tag from a backup. Returns true if the tag existed.
+    pub fn remove_backup_tag(&self, backup_id: &str, key: &str) -> Result<bool> {
+        let deleted = self.conn.execute(
+            "DELETE FROM backup_tags WHERE backup_id=?1 AND key=?2",
+            params![backup_id, key],
+        )?;
+        Ok(deleted > 0)
+    }
+
+    pub fn get_backup_tags(&self, backup_id: &str) -> Result<HashMap<String, String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT key, value FROM backup_tags WHERE backup_id=?1")?;
+        let rows = stmt.query_map(params![backup_id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        Ok(rows.collect::<std::result::Result<HashMap<_, _>, _>>()?)
+    }
+
+    /// List backups carrying the tag `key=value`, newest first.
+    pub fn list_backups_with_tag(&self, key: &str, value: &str) -> Result<Vec<BackupRecord>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT b.id FROM backups b
+             JOIN backup_tags t ON t.backup_id = b.id
+             WHERE t.key=?1 AND t.value=?2
+             ORDER BY b.created_at DESC",
+        )?;
+        let ids = stmt
+            .query_map(params![key, value], |row| row.get::<_, String>(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        ids.iter().map(|id| self.get_backup(id)).collect()
+    }
+
+    // ── Backup Merkle tree ─────────────────────────────────────
+
+    /// Merkle leaves of a backup: (file_path, chunk_hash) pairs, sorted.
+    pub fn backup_merkle_leaves(&self, backup_id: &str) -> Result<Vec<(String, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT bf.path, fc.chunk_hash FROM backup_files bf
+             JOIN file_chunks fc ON fc.file_id = bf.id
+             WHERE bf.backup_id=?1
+             ORDER BY bf.path, fc.chunk_hash",
+        )?;
+        let rows = stmt.query_map(params![backup_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+    }
+
+    /// Build the Merkle tree over the backup's leaves and store its root.
+    pub fn build_backup_merkle_root(&self, backup_id: &str) -> Result<[u8; 32]> {
+        let root = merkle::root(&self.backup_merkle_leaf_hashes(backup_id)?);
+        self.conn.execute(
+            "UPDATE backups SET merkle_root=?2 WHERE id=?1",
+            params![backup_id, hex::encode(root)],
+        )?;
+        Ok(root)
+    }
+
+    /// Stored Merkle root, or None for backups completed before v4.
+    pub fn get_backup_merkle_root(&self, backup_id: &str) -> Result<Option<[u8; 32]>> {
+        let root: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT merkle_root FROM backups WHERE id=?1",
+                params![backup_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => {
+                    EnigmaError::BackupNotFound(backup_id.to_string())
+                }
+                other => EnigmaError::Database(other),
+            })?;
+        root.map(|hex_root| {
+            hex::decode(&hex_root)
+                .ok()
+                .and_then(|b| <[u8; 32]>::try_from(b).ok())
+                .ok_or_else(|| {
+                    EnigmaError::Integrity(format!("invalid merkle_root for {backup_id}"))
+                })
+        })
+        .transpose()
+    }
+
+    /// Check that leaf `leaf_index`, combined with its audit path, hashes up
+    /// to the stored root. Returns false if there is no stored root.
+    pub fn verify_merkle_path(&self, backup_id: &str, leaf_index: usize) -> Result<bool> {
+        let Some(root) = self.get_backup_merkle_root(backup_id)? else {
+            return Ok(false);
+        };
+        let leaves = self.backup_merkle_leaf_hashes(backup_id)?;
+        let Some(leaf) = leaves.get(leaf_index) else {
+            return Err(EnigmaError::Integrity(format!(
+                "leaf index {leaf_index} out of range ({} leaves)",
+                leaves.len()
+            )));
+        };
+        let path = merkle::audit_path(&leaves, leaf_index);
+        Ok(merkle::verify_path(*leaf, &path, &root))
+    }
+
+    fn backup_merkle_leaf_hashes(&self, backup_id: &str) -> Result<Vec<[u8; 32]>> {
+        Ok(self
+            .backup_merkle_leaves(backup_id)?
+            .iter()
+            .map(|(path, chunk_hash)| merkle::leaf_hash(path, chunk_hash))
+            .collect())
+    }
+
+    // ── Backup signatures ──────────────────────────────────────
+
+    /// Sign the backup record with an Ed25519 keypair (secret || public)
+    /// and store the hex signature. The backup must have a Merkle root.
+    pub fn sign_backup(&self, backup_id: &str, signing_key: &[u8; 64]) -> Result<String> {
+        let digest = self.backup_signing_digest(backup_id)?;
+        let signature = hex::encode(signing::sign(signing_key, &digest)?);
+        self.conn.execute(
+            "UPDATE backups SET signature=?2 WHERE id=?1",
+            params![backup_id, signature],
+        )?;
+        Ok(signature)
+    }
+
+    /// Check the stored signature against the record as it is now. False
+    /// if the backup is unsigned, the signature is malformed, or any signed
+    /// field changed since signing.
+    pub fn verify_backup_signature(&self, backup_id: &str, verify_key: &[u8; 32]) -> Result<bool> {
+        let signature: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT signature FROM backups WHERE id=?1",
+                params![backup_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => {
+                    EnigmaError::BackupNotFound(backup_id.to_string())
+                }
+                other => EnigmaError::Database(other),
+            })?;
+        let Some(signature) = signature.and_then(|s| hex::decode(s).ok()) else {
+            return Ok(false);
+        };
+        let digest = match self.backup_signing_digest(backup_id) {
+            Ok(digest) => digest,
+            Err(EnigmaError::Integrity(_)) => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        Ok(signing::verify(verify_key, &digest, &signature))
+    }
+
+    /// SHA-256(backup_id || source_path || total_bytes (u64 LE) || merkle_root).
+    fn backup_signing_digest(&self, backup_id: &str) -> Result<[u8; 32]> {
+        let (source_path, total_bytes): (String, u64) = self
+            .conn
+            .query_row(
+                "SELECT source_path, total_bytes FROM backups WHERE id=?1",
+                params![backup_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => {
+                    EnigmaError::BackupNotFound(backup_id.to_string())
+                }
+                other => EnigmaError::Database(other),
+            })?;
+        let Some(root) = self.get_backup_merkle_root(backup_id)? else {
+            return Err(EnigmaError::Integrity(format!(
+                "backup {backup_id} has no Merkle root to sign"
+            )));
+        };
+
+        let mut hasher = Sha256::new();
+        hasher.update(backup_id.as_bytes());
+        hasher.update(source_path.as_bytes());
+        hasher.update(total_bytes.to_le_bytes());
+        hasher.update(root);
+        Ok(hasher.finalize().into())
+    }
+
     // ── Backup files ───────────────────────────────────────────
 
     pub fn insert_backup_file(
@@ -292,7 +721,7 @@ impl ManifestDb {
         chunk_count: u32,
     ) -> Result<i64> {
         self.conn.execute(
-            "INSERT INTO backup_files (backup_id, path, size, mtime, hash, chunk_count) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            INSERT_BACKUP_FILE,
             params![backup_id, path, size, mtime, hash, chunk_count],
         )?;
         Ok(self.conn.last_insert_rowid())
@@ -308,6 +737,16 @@ impl ManifestDb {
         Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
     }
 
+    /// Recorded modification times (Unix seconds) of a backup's files, by
+    /// file id. Files backed up without one are left out.
+    pub fn list_backup_file_mtimes(&self, backup_id: &str) -> Result<HashMap<i64, String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, mtime FROM backup_files WHERE backup_id=?1 AND mtime IS NOT NULL",
+        )?;
+        let rows = stmt.query_map(params![backup_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(rows.collect::<std::result::Result<HashMap<_, _>, _>>()?)
+    }
+
     // ── Chunks ─────────────────────────────────────────────────
 
     /// Insert a new chunk or increment its ref_count if it already exists.
@@ -326,18 +765,25 @@ impl ManifestDb {
         size_compressed: Option<u64>,
     ) -> Result<bool> {
         self.conn.execute(
-            "INSERT INTO chunks (hash, nonce, key_id, provider_id, storage_key, size_plain, size_encrypted, size_compressed, ref_count)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, 1)
-             ON CONFLICT(hash) DO UPDATE SET ref_count = ref_count + 1",
-            params![hash, nonce, key_id, provider_id, storage_key, size_plain, size_encrypted, size_compressed],
+            UPSERT_CHUNK,
+            params![
+                hash,
+                nonce,
+                key_id,
+                provider_id,
+                storage_key,
+                size_plain,
+                size_encrypted,
+                size_compressed
+            ],
         )?;
 
-        // ref_count == 1 means we just inserted; > 1 means it already existed
-        let ref_count: u64 = self.conn.query_row(
-            "SELECT ref_count FROM chunks WHERE hash = ?1",
-            params![hash],
-            |row| row.get(0),
-        )?;
+        // ref_count == 1 means we just inserted; > 1 means it already existed.
+        // A chunk revived from ref_count 0 counts as new: the caller uploads
+        // it again, so its record takes the new encryption and location.
+        let ref_count: u64 = self
+            .conn
+            .query_row(CHUNK_REF_COUNT, params![hash], |row| row.get(0))?;
 
         Ok(ref_count == 1)
     }
@@ -367,6 +813,64 @@ impl ManifestDb {
         }
     }
 
+    /// Record the HMAC tag of a chunk's stored ciphertext.
+    pub fn set_chunk_hmac_tag(&self, hash: &str, tag: &[u8; 32]) -> Result<()> {
+        self.conn
+            .execute(SET_CHUNK_HMAC_TAG, params![hash, &tag[..]])?;
+        Ok(())
+    }
+
+    /// HMAC tag of a chunk, or None if it was stored without one.
+    pub fn get_chunk_hmac_tag(&self, hash: &str) -> Result<Option<[u8; 32]>> {
+        let tag: Option<Option<Vec<u8>>> = self
+            .conn
+            .query_row(
+                "SELECT hmac_tag FROM chunks WHERE hash = ?1",
+                params![hash],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(tag.flatten().and_then(|t| t.try_into().ok()))
+    }
+
+    /// Record that a chunk was just downloaded.
+    pub fn record_chunk_access(&self, hash: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE chunks SET last_accessed_at = datetime('now') WHERE hash = ?1",
+            params![hash],
+        )?;
+        Ok(())
+    }
+
+    /// When a chunk was last downloaded, or None if it never was.
+    pub fn get_chunk_last_accessed(&self, hash: &str) -> Result<Option<String>> {
+        let accessed: Option<Option<String>> = self
+            .conn
+            .query_row(
+                "SELECT last_accessed_at FROM chunks WHERE hash = ?1",
+                params![hash],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(accessed.flatten())
+    }
+
+    /// Increment ref_count for one more reference to a live chunk, as when
+    /// an object is copied without re-uploading its data. Fails if the chunk
+    /// is gone or awaiting re-upload (ref_count 0).
+    pub fn add_chunk_ref(&self, hash: &str) -> Result<()> {
+        let updated = self.conn.execute(
+            "UPDATE chunks SET ref_count = ref_count + 1 WHERE hash = ?1 AND ref_count > 0",
+            params![hash],
+        )?;
+        if updated == 0 {
+            return Err(EnigmaError::Integrity(format!(
+                "chunk {hash} is not stored"
+            )));
+        }
+        Ok(())
+    }
+
     /// Decrement ref_count. If it reaches 0, return ALL storage locations for deletion
     /// (primary + replicas). The ON DELETE CASCADE cleans up chunk_replicas automatically.
     pub fn decrement_chunk_ref(&self, hash: &str) -> Result<Vec<(i64, String)>> {
@@ -403,13 +907,74 @@ impl ManifestDb {
         }
     }
 
+    // ── Chunk statistics ───────────────────────────────────────
+
+    /// Chunk counts by plaintext size, as `(bucket_start_kb, count)` for the
+    /// non-empty buckets in ascending order. Buckets are
+    /// [`HISTOGRAM_BUCKET_KB`] wide; chunks of [`HISTOGRAM_MAX_KB`] or more
+    /// are counted in the last bucket. With `backup_id`, only the chunks
+    /// that backup references are counted.
+    pub fn chunk_size_histogram(&self, backup_id: Option<&str>) -> Result<Vec<(u64, u64)>> {
+        let last_bucket = HISTOGRAM_MAX_KB / HISTOGRAM_BUCKET_KB - 1;
+        let sql = format!(
+            "SELECT MIN(c.size_plain / ?2, ?3) AS bucket, COUNT(*) FROM chunks c
+             WHERE {CHUNK_SCOPE} GROUP BY bucket ORDER BY bucket"
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(
+            params![backup_id, HISTOGRAM_BUCKET_KB * 1024, last_bucket],
+            |row| {
+                Ok((
+                    row.get::<_, u64>(0)? * HISTOGRAM_BUCKET_KB,
+                    row.get::<_, u64>(1)?,
+                ))
+            },
+        )?;
+        Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+    }
+
+    /// Space saved by compression, `1 - compressed / plain`, over all chunks
+    /// or those of `backup_id`. Chunks stored uncompressed count at their
+    /// plaintext size; no chunks gives 0.
+    pub fn compression_ratio(&self, backup_id: Option<&str>) -> Result<f64> {
+        let sql = format!(
+            "SELECT COALESCE(SUM(COALESCE(c.size_compressed, c.size_plain)), 0),
+                    COALESCE(SUM(c.size_plain), 0)
+             FROM chunks c WHERE {CHUNK_SCOPE}"
+        );
+        let (compressed, plain) = self.conn.query_row(&sql, params![backup_id], |row| {
+            Ok((row.get::<_, u64>(0)?, row.get::<_, u64>(1)?))
+        })?;
+        if plain == 0 {
+            return Ok(0.0);
+        }
+        Ok(1.0 - compressed as f64 / plain as f64)
+    }
+
+    /// Per provider `(provider_id, name, chunk_count, average_chunk_bytes)`,
+    /// counting the chunks whose primary copy it holds. Providers without
+    /// chunks are listed with zeros, so a skewed distribution shows up.
+    pub fn provider_chunk_sizes(
+        &self,
+        backup_id: Option<&str>,
+    ) -> Result<Vec<(i64, String, u64, u64)>> {
+        let sql = format!(
+            "SELECT p.id, p.name, COUNT(c.hash), CAST(COALESCE(AVG(c.size_plain), 0) AS INTEGER)
+             FROM providers p LEFT JOIN chunks c ON c.provider_id = p.id AND {CHUNK_SCOPE}
+             GROUP BY p.id ORDER BY p.id"
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(params![backup_id], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+        })?;
+        Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+    }
+
     // ── Chunk Replicas ──────────────────────────────────────────
 
     /// Insert replica records for a chunk (called when replication_factor > 1).
     pub fn insert_chunk_replicas(&self, chunk_hash: &str, replicas: &[(i64, &str)]) -> Result<()> {
-        let mut stmt = self.conn.prepare(
-            "INSERT OR IGNORE INTO chunk_replicas (chunk_hash, provider_id, storage_key) VALUES (?1, ?2, ?3)",
-        )?;
+        let mut stmt = self.conn.prepare(INSERT_CHUNK_REPLICA)?;
         for (provider_id, storage_key) in replicas {
             stmt.execute(params![chunk_hash, provider_id, storage_key])?;
         }
@@ -494,7 +1059,7 @@ impl ManifestDb {
         offset: u64,
     ) -> Result<()> {
         self.conn.execute(
-            "INSERT INTO file_chunks (file_id, chunk_hash, chunk_index, offset) VALUES (?1, ?2, ?3, ?4)",
+            INSERT_FILE_CHUNK,
             params![file_id, chunk_hash, chunk_index, offset],
         )?;
         Ok(())
@@ -530,6 +1095,100 @@ impl ManifestDb {
         Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
     }
 
+    // ── S3 access logs ─────────────────────────────────────────
+
+    pub fn insert_s3_log(&self, entry: &S3AccessLogEntry) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO s3_access_log
+                (bucket, key, operation, requester_ip, timestamp, status_code, bytes_transferred)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                entry.bucket,
+                entry.key,
+                entry.operation,
+                entry.requester_ip,
+                entry.timestamp,
+                entry.status_code,
+                entry.bytes_transferred as i64,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Access log entries with `from <= timestamp <= to` (RFC 3339, UTC),
+    /// oldest first, optionally of one bucket only.
+    pub fn list_s3_logs(
+        &self,
+        bucket: Option<&str>,
+        from: &str,
+        to: &str,
+        limit: u32,
+    ) -> Result<Vec<S3AccessLogEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT bucket, key, operation, requester_ip, timestamp, status_code, bytes_transferred
+             FROM s3_access_log
+             WHERE (?1 IS NULL OR bucket = ?1) AND timestamp >= ?2 AND timestamp <= ?3
+             ORDER BY timestamp, id
+             LIMIT ?4",
+        )?;
+        let rows = stmt.query_map(params![bucket, from, to, limit], |row| {
+            Ok(S3AccessLogEntry {
+                bucket: row.get(0)?,
+                key: row.get(1)?,
+                operation: row.get(2)?,
+                requester_ip: row.get(3)?,
+                timestamp: row.get(4)?,
+                status_code: row.get(5)?,
+                bytes_transferred: row.get::<_, i64>(6)? as u64,
+            })
+        })?;
+        Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+    }
+
+    /// Delete access log entries older than `retention_days`. Returns the
+    /// number of entries deleted.
+    pub fn purge_s3_logs(&self, retention_days: u32) -> Result<usize> {
+        Ok(self.conn.execute(
+            "DELETE FROM s3_access_log
+             WHERE timestamp < strftime('%Y-%m-%dT%H:%M:%SZ', 'now', ?1)",
+            params![format!("-{retention_days} days")],
+        )?)
+    }
+
+    // ── Webhook queue ──────────────────────────────────────────
+
+    /// Queue a webhook delivery of `body` to `url`. Returns its queue ID.
+    pub fn enqueue_webhook(&self, url: &str, event: &str, body: &str) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO webhook_queue (url, event, body) VALUES (?1, ?2, ?3)",
+            params![url, event, body],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Up to `limit` queued webhook deliveries, oldest first.
+    pub fn pending_webhooks(&self, limit: u32) -> Result<Vec<QueuedWebhook>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, url, event, body, created_at FROM webhook_queue ORDER BY id LIMIT ?1",
+        )?;
+        let rows = stmt.query_map(params![limit], |row| {
+            Ok(QueuedWebhook {
+                id: row.get(0)?,
+                url: row.get(1)?,
+                event: row.get(2)?,
+                body: row.get(3)?,
+                created_at: row.get(4)?,
+            })
+        })?;
+        Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+    }
+
+    pub fn delete_webhook(&self, id: i64) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM webhook_queue WHERE id=?1", params![id])?;
+        Ok(())
+    }
+
     // ── GC (Garbage Collection) ──────────────────────────────
 
     /// Find orphaned chunks: chunks with ref_count <= 0 that are not referenced
@@ -643,31 +1302,337 @@ impl ManifestDb {
         Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
     }
 
-    // ── Snapshots ──────────────────────────────────────────────
+    // ── GC verify ──────────────────────────────────────────────
 
-    /// Serialize the entire DB to bytes via the SQLite backup API.
-    pub fn snapshot_to_bytes(&self) -> Result<Vec<u8>> {
-        let tmp = tempfile::NamedTempFile::new()?;
-        let mut dest = Connection::open(tmp.path())?;
-        let backup = rusqlite::backup::Backup::new(&self.conn, &mut dest)?;
-        backup.run_to_completion(100, Duration::ZERO, None)?;
-        drop(backup);
-        drop(dest);
-        Ok(std::fs::read(tmp.path())?)
+    /// Next page of referenced chunks not yet checked by the current
+    /// `gc --verify` pass, in hash order, starting after `after`.
+    pub fn unverified_chunk_hashes(&self, after: &str, limit: u32) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT hash FROM chunks c WHERE ref_count > 0 AND hash > ?1
+             AND NOT EXISTS (SELECT 1 FROM gc_verify_progress p WHERE p.chunk_hash = c.hash)
+             ORDER BY hash LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![after, limit], |row| row.get::<_, String>(0))?;
+        Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
     }
 
-    /// Restore DB from raw bytes, writing to the given path.
-    pub fn restore_from_bytes(data: &[u8], path: &Path) -> Result<Self> {
-        let tmp_path = path.with_extension("snap.tmp");
-        std::fs::write(&tmp_path, data)?;
-        // Verify it opens correctly
-        let db = Self::open(&tmp_path)?;
-        drop(db);
-        std::fs::rename(&tmp_path, path)?;
-        Self::open(path)
+    /// Record the outcome of verifying one chunk. With `queue_reupload`, the
+    /// chunk's ref_count drops to 0 and its replica records go, so the next
+    /// backup or PUT of the same data uploads it again.
+    pub fn record_gc_verify(&self, hash: &str, status: &str, queue_reupload: bool) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        if queue_reupload {
+            tx.execute(
+                "UPDATE chunks SET ref_count = 0 WHERE hash = ?1",
+                params![hash],
+            )?;
+            tx.execute(
+                "DELETE FROM chunk_replicas WHERE chunk_hash = ?1",
+                params![hash],
+            )?;
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO gc_verify_progress (chunk_hash, status, verified_at)
+             VALUES (?1, ?2, datetime('now'))",
+            params![hash, status],
+        )?;
+        tx.commit()?;
+        Ok(())
     }
 
-    // ── S3 Gateway: Namespaces ───────────────────────────────
+    /// Chunks checked so far by the current `gc --verify` pass:
+    /// (total, per-status counts, chunks queued for re-upload).
+    pub fn gc_verify_counts(&self) -> Result<(u64, HashMap<String, u64>, u64)> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT status, COUNT(*) FROM gc_verify_progress GROUP BY status")?;
+        let by_status = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, u64>(1)?))
+            })?
+            .collect::<std::result::Result<HashMap<_, _>, _>>()?;
+        let queued: u64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM gc_verify_progress p JOIN chunks c ON c.hash = p.chunk_hash
+             WHERE p.status != 'ok' AND c.ref_count = 0",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok((by_status.values().sum(), by_status, queued))
+    }
+
+    /// Forget the progress of a finished `gc --verify` pass.
+    pub fn clear_gc_verify_progress(&self) -> Result<()> {
+        self.conn.execute("DELETE FROM gc_verify_progress", [])?;
+        Ok(())
+    }
+
+    // ── Dedup index ────────────────────────────────────────────
+
+    /// Number of chunk records, whatever their ref_count.
+    pub fn chunk_count(&self) -> Result<u64> {
+        Ok(self
+            .conn
+            .query_row("SELECT COUNT(*) FROM chunks", [], |row| row.get(0))?)
+    }
+
+    /// Call `f` with the hash of every chunk record, in no particular
+    /// order. Rows whose hash is not 64 hex digits are skipped.
+    pub fn for_each_chunk_hash(&self, mut f: impl FnMut(ChunkHash)) -> Result<()> {
+        let mut stmt = self.conn.prepare("SELECT hash FROM chunks")?;
+        let mut rows = stmt.query([])?;
+        let mut bytes = [0u8; 32];
+        while let Some(row) = rows.next()? {
+            let hash: String = row.get(0)?;
+            if hex::decode_to_slice(&hash, &mut bytes).is_ok() {
+                f(ChunkHash(bytes));
+            }
+        }
+        Ok(())
+    }
+
+    // ── Repair ─────────────────────────────────────────────────
+
+    /// Next page of all chunk hashes, in hash order, starting after `after`
+    /// ("" for the first page).
+    pub fn chunk_hashes(&self, after: &str, limit: u32) -> Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT hash FROM chunks WHERE hash > ?1 ORDER BY hash LIMIT ?2")?;
+        let rows = stmt.query_map(params![after, limit], |row| row.get::<_, String>(0))?;
+        Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+    }
+
+    /// Forget a chunk whose data is gone from every provider, atomically:
+    /// drop its file and object references, decrement `total_chunks` and
+    /// rebuild the Merkle root of the backups that used it, and delete the
+    /// chunk record (cascading to its replicas).
+    /// Returns (file references, object references) removed.
+    pub fn remove_missing_chunk(&self, hash: &str) -> Result<(u64, u64)> {
+        let tx = self.conn.unchecked_transaction()?;
+        let backup_ids: Vec<String> = {
+            let mut stmt = tx.prepare(
+                "SELECT DISTINCT bf.backup_id FROM file_chunks fc
+                 JOIN backup_files bf ON fc.file_id = bf.id
+                 WHERE fc.chunk_hash = ?1",
+            )?;
+            let rows = stmt.query_map(params![hash], |row| row.get(0))?;
+            rows.collect::<std::result::Result<Vec<_>, _>>()?
+        };
+        tx.execute(
+            "UPDATE backups SET total_chunks = MAX(0, total_chunks - (
+                 SELECT COUNT(*) FROM file_chunks fc JOIN backup_files bf ON fc.file_id = bf.id
+                 WHERE bf.backup_id = backups.id AND fc.chunk_hash = ?1))
+             WHERE id IN (
+                 SELECT bf.backup_id FROM file_chunks fc JOIN backup_files bf ON fc.file_id = bf.id
+                 WHERE fc.chunk_hash = ?1)",
+            params![hash],
+        )?;
+        let file_refs = tx.execute(
+            "DELETE FROM file_chunks WHERE chunk_hash = ?1",
+            params![hash],
+        )?;
+        let object_refs = tx.execute(
+            "DELETE FROM object_chunks WHERE chunk_hash = ?1",
+            params![hash],
+        )?;
+        tx.execute("DELETE FROM chunks WHERE hash = ?1", params![hash])?;
+        for backup_id in &backup_ids {
+            if self.get_backup_merkle_root(backup_id)?.is_some() {
+                self.build_backup_merkle_root(backup_id)?;
+            }
+        }
+        tx.commit()?;
+        Ok((file_refs as u64, object_refs as u64))
+    }
+
+    /// Delete one replica record of a chunk.
+    pub fn delete_chunk_replica(&self, hash: &str, provider_id: i64) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM chunk_replicas WHERE chunk_hash = ?1 AND provider_id = ?2",
+            params![hash, provider_id],
+        )?;
+        Ok(())
+    }
+
+    // ── Chunk layout ───────────────────────────────────────────
+
+    /// Point every location of a chunk stored under `old_key` (the primary
+    /// record and its replicas) at `new_key`, atomically. The chunk's HMAC
+    /// tag covers its storage key, so it is dropped.
+    pub fn rename_chunk_storage_key(&self, hash: &str, old_key: &str, new_key: &str) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "UPDATE chunks SET storage_key = ?3, hmac_tag = NULL WHERE hash = ?1 AND storage_key = ?2",
+            params![hash, old_key, new_key],
+        )?;
+        tx.execute(
+            "UPDATE chunk_replicas SET storage_key = ?3 WHERE chunk_hash = ?1 AND storage_key = ?2",
+            params![hash, old_key, new_key],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    // ── Key rotation ───────────────────────────────────────────
+
+    /// Distinct chunk key IDs with their chunk counts.
+    pub fn chunk_key_ids(&self) -> Result<Vec<(String, u64)>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT key_id, COUNT(*) FROM chunks GROUP BY key_id ORDER BY key_id")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, u64>(1)?))
+        })?;
+        Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+    }
+
+    /// Next page of chunk hashes encrypted with `key_id`, in hash order,
+    /// starting after `after` ("" for the first page).
+    pub fn chunk_hashes_with_key(
+        &self,
+        key_id: &str,
+        after: &str,
+        limit: u32,
+    ) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT DISTINCT hash FROM chunks WHERE key_id = ?1 AND hash > ?2 ORDER BY hash LIMIT ?3",
+        )?;
+        let rows = stmt.query_map(params![key_id, after, limit], |row| row.get::<_, String>(0))?;
+        Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+    }
+
+    /// Record the nonce/key a chunk is being re-encrypted with, before any
+    /// of its copies are overwritten.
+    pub fn begin_chunk_rekey(&self, hash: &str, nonce: &[u8], key_id: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO chunk_rekeys (chunk_hash, nonce, key_id) VALUES (?1, ?2, ?3)",
+            params![hash, nonce, key_id],
+        )?;
+        Ok(())
+    }
+
+    /// Pending re-encryption of a chunk: (nonce, key_id).
+    pub fn get_chunk_rekey(&self, hash: &str) -> Result<Option<(Vec<u8>, String)>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT nonce, key_id FROM chunk_rekeys WHERE chunk_hash = ?1",
+                params![hash],
+                |row| Ok((row.get::<_, Vec<u8>>(0)?, row.get::<_, String>(1)?)),
+            )
+            .optional()?)
+    }
+
+    /// Point a chunk at its new nonce/key and HMAC tag and clear its pending
+    /// re-encryption, atomically.
+    pub fn finish_chunk_rekey(
+        &self,
+        hash: &str,
+        nonce: &[u8],
+        key_id: &str,
+        hmac_tag: Option<&[u8; 32]>,
+    ) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "UPDATE chunks SET nonce = ?2, key_id = ?3, hmac_tag = ?4 WHERE hash = ?1",
+            params![hash, nonce, key_id, hmac_tag.map(|t| &t[..])],
+        )?;
+        tx.execute(
+            "DELETE FROM chunk_rekeys WHERE chunk_hash = ?1",
+            params![hash],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Record a rotation from `old_key_id` to `key_id`. With
+    /// `reencrypt`, the rotation stays pending until
+    /// [`ManifestDb::finish_key_reencryption`].
+    pub fn record_key_rotation(
+        &self,
+        key_id: &str,
+        old_key_id: &str,
+        triggered_by: &str,
+        reencrypt: bool,
+    ) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO key_rotation_history (key_id, old_key_id, triggered_by, reencrypt_pending)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![key_id, old_key_id, triggered_by, reencrypt],
+        )?;
+        Ok(())
+    }
+
+    /// Key rotations, newest first.
+    pub fn list_key_rotations(&self) -> Result<Vec<KeyRotationRecord>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT key_id, old_key_id, rotated_at, triggered_by, reencrypt_pending
+             FROM key_rotation_history ORDER BY id DESC",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(KeyRotationRecord {
+                key_id: row.get(0)?,
+                old_key_id: row.get(1)?,
+                rotated_at: row.get(2)?,
+                triggered_by: row.get(3)?,
+                reencrypt_pending: row.get(4)?,
+            })
+        })?;
+        Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+    }
+
+    /// Whether a rotation to `key_id` still waits for its chunks to be
+    /// re-encrypted.
+    pub fn key_reencryption_pending(&self, key_id: &str) -> Result<bool> {
+        let pending: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM key_rotation_history WHERE key_id = ?1 AND reencrypt_pending = 1",
+            params![key_id],
+            |row| row.get(0),
+        )?;
+        Ok(pending > 0)
+    }
+
+    /// Clear the pending re-encryption of every rotation to `key_id`.
+    pub fn finish_key_reencryption(&self, key_id: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE key_rotation_history SET reencrypt_pending = 0 WHERE key_id = ?1",
+            params![key_id],
+        )?;
+        Ok(())
+    }
+
+    // ── Snapshots ──────────────────────────────────────────────
+
+    /// Serialize the entire DB to bytes via the SQLite backup API.
+    pub fn snapshot_to_bytes(&self) -> Result<Vec<u8>> {
+        let tmp = tempfile::NamedTempFile::new()?;
+        let mut dest = Connection::open(tmp.path())?;
+        let backup = rusqlite::backup::Backup::new(&self.conn, &mut dest)?;
+        backup.run_to_completion(100, Duration::ZERO, None)?;
+        drop(backup);
+        drop(dest);
+        Ok(std::fs::read(tmp.path())?)
+    }
+
+    /// Restore DB from raw bytes into the database at the given path.
+    /// Copies with the backup API rather than replacing the file, so the
+    /// WAL of connections still open on the old database is not replayed
+    /// over the restored one.
+    pub fn restore_from_bytes(data: &[u8], path: &Path) -> Result<Self> {
+        let tmp_path = path.with_extension("snap.tmp");
+        std::fs::write(&tmp_path, data)?;
+        // Verify it opens correctly
+        let restored = Self::open(&tmp_path)?;
+        let mut db = Self::open(path)?;
+        let backup = rusqlite::backup::Backup::new(&restored.conn, &mut db.conn)?;
+        backup.run_to_completion(100, Duration::ZERO, None)?;
+        drop(backup);
+        drop(restored);
+        std::fs::remove_file(&tmp_path)?;
+        Ok(db)
+    }
+
+    // ── S3 Gateway: Namespaces ───────────────────────────────
 
     pub fn create_namespace(&self, name: &str) -> Result<i64> {
         self.conn
@@ -678,7 +1643,7 @@ impl ManifestDb {
     pub fn get_namespace_id(&self, name: &str) -> Result<Option<i64>> {
         let mut stmt = self
             .conn
-            .prepare("SELECT id FROM namespaces WHERE name=?1")?;
+            .prepare("SELECT id FROM namespaces WHERE name=?1 AND deleted_at IS NULL")?;
         let mut rows = stmt.query_map(params![name], |row| row.get::<_, i64>(0))?;
         match rows.next() {
             Some(Ok(v)) => Ok(Some(v)),
@@ -691,23 +1656,246 @@ impl ManifestDb {
         Ok(self.get_namespace_id(name)?.is_some())
     }
 
-    pub fn delete_namespace(&self, name: &str) -> Result<bool> {
-        let deleted = self
+    /// `created_at` of a live namespace (SQLite `datetime('now')`, UTC).
+    pub fn namespace_created_at(&self, name: &str) -> Result<Option<String>> {
+        Ok(self
             .conn
-            .execute("DELETE FROM namespaces WHERE name=?1", params![name])?;
+            .query_row(
+                "SELECT created_at FROM namespaces WHERE name=?1 AND deleted_at IS NULL",
+                params![name],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    /// Soft-delete a namespace: it disappears from lookups and listings
+    /// but keeps its objects until [`Self::delete_namespace_permanently`].
+    pub fn delete_namespace(&self, name: &str) -> Result<bool> {
+        let deleted = self.conn.execute(
+            "UPDATE namespaces SET deleted_at=datetime('now') WHERE name=?1 AND deleted_at IS NULL",
+            params![name],
+        )?;
         Ok(deleted > 0)
     }
 
+    /// Undo a soft delete. Returns false if `name` is not soft-deleted.
+    pub fn restore_namespace(&self, name: &str) -> Result<bool> {
+        let restored = self.conn.execute(
+            "UPDATE namespaces SET deleted_at=NULL WHERE name=?1 AND deleted_at IS NOT NULL",
+            params![name],
+        )?;
+        Ok(restored > 0)
+    }
+
+    /// Remove a namespace (deleted or not) with its objects, pending
+    /// objects and pending multipart uploads. Returns the chunk locations
+    /// that need physical deletion, or `None` if the namespace does not exist.
+    pub fn delete_namespace_permanently(&self, name: &str) -> Result<Option<Vec<(i64, String)>>> {
+        let tx = self.conn.unchecked_transaction()?;
+        let ns_id: Option<i64> = tx
+            .query_row(
+                "SELECT id FROM namespaces WHERE name=?1",
+                params![name],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let Some(ns_id) = ns_id else {
+            return Ok(None);
+        };
+
+        let object_ids: Vec<i64> = tx
+            .prepare("SELECT id FROM objects WHERE namespace_id=?1")?
+            .query_map(params![ns_id], |row| row.get(0))?
+            .collect::<std::result::Result<_, _>>()?;
+        let mut to_delete = Vec::new();
+        for object_id in object_ids {
+            to_delete.extend(self.delete_object_row(object_id)?);
+        }
+        let pending_ids: Vec<i64> = tx
+            .prepare("SELECT id FROM pending_objects WHERE namespace_id=?1")?
+            .query_map(params![ns_id], |row| row.get(0))?
+            .collect::<std::result::Result<_, _>>()?;
+        for pending_id in pending_ids {
+            for (_, chunk_hash, _) in self.pending_object_chunks(pending_id)? {
+                to_delete.extend(self.decrement_chunk_ref(&chunk_hash)?);
+            }
+            self.delete_pending_object_rows(pending_id)?;
+        }
+        let upload_ids: Vec<String> = tx
+            .prepare("SELECT id FROM multipart_uploads WHERE namespace_id=?1")?
+            .query_map(params![ns_id], |row| row.get(0))?
+            .collect::<std::result::Result<_, _>>()?;
+        for upload_id in upload_ids {
+            to_delete.extend(self.release_part_chunk_refs(&upload_id)?);
+        }
+        let spilled = self.query_spill_paths(
+            "SELECT spill_path FROM multipart_parts WHERE spill_path IS NOT NULL AND upload_id IN (
+                SELECT id FROM multipart_uploads WHERE namespace_id=?1
+            )",
+            params![ns_id],
+        )?;
+        tx.execute(
+            "DELETE FROM multipart_parts WHERE upload_id IN (
+                SELECT id FROM multipart_uploads WHERE namespace_id=?1
+            )",
+            params![ns_id],
+        )?;
+        tx.execute(
+            "DELETE FROM multipart_uploads WHERE namespace_id=?1",
+            params![ns_id],
+        )?;
+        tx.execute("DELETE FROM namespaces WHERE id=?1", params![ns_id])?;
+        tx.commit()?;
+        spilled.iter().for_each(|path| remove_spill_file(path));
+        Ok(Some(to_delete))
+    }
+
+    /// Names of namespaces soft-deleted at least `recovery_days` ago.
+    pub fn list_expired_namespaces(&self, recovery_days: u32) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT name FROM namespaces WHERE deleted_at <= datetime('now', ?1) ORDER BY deleted_at",
+        )?;
+        let rows = stmt.query_map(params![format!("-{recovery_days} days")], |row| row.get(0))?;
+        Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+    }
+
+    /// Versioning state of a namespace: (enabled, suspended). Both false
+    /// means versioning was never turned on.
+    pub fn get_namespace_versioning(&self, namespace_id: i64) -> Result<(bool, bool)> {
+        Ok(self.conn.query_row(
+            "SELECT versioning_enabled, versioning_suspended FROM namespaces WHERE id=?1",
+            params![namespace_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?)
+    }
+
+    pub fn set_namespace_versioning(
+        &self,
+        namespace_id: i64,
+        enabled: bool,
+        suspended: bool,
+    ) -> Result<()> {
+        self.conn.execute(
+            "UPDATE namespaces SET versioning_enabled=?2, versioning_suspended=?3 WHERE id=?1",
+            params![namespace_id, enabled, suspended],
+        )?;
+        Ok(())
+    }
+
+    /// Turn versioning on for bucket `name`, or back on after a suspend.
+    /// Returns false if there is no such bucket.
+    pub fn enable_bucket_versioning(&self, name: &str) -> Result<bool> {
+        let updated = self.conn.execute(
+            "UPDATE namespaces SET versioning_enabled=1, versioning_suspended=0
+             WHERE name=?1 AND deleted_at IS NULL",
+            params![name],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// Stop creating new versions in bucket `name`; existing versions are
+    /// kept. Returns false if there is no such bucket.
+    pub fn suspend_bucket_versioning(&self, name: &str) -> Result<bool> {
+        let updated = self.conn.execute(
+            "UPDATE namespaces SET versioning_enabled=0, versioning_suspended=1
+             WHERE name=?1 AND deleted_at IS NULL",
+            params![name],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// Versioning state of bucket `name`, or `None` if there is no such bucket.
+    pub fn get_bucket_versioning_status(&self, name: &str) -> Result<Option<VersioningStatus>> {
+        let state: Option<(bool, bool)> = self
+            .conn
+            .query_row(
+                "SELECT versioning_enabled, versioning_suspended FROM namespaces
+                 WHERE name=?1 AND deleted_at IS NULL",
+                params![name],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        Ok(state.map(|state| match state {
+            (true, _) => VersioningStatus::Enabled,
+            (false, true) => VersioningStatus::Suspended,
+            (false, false) => VersioningStatus::Unversioned,
+        }))
+    }
+
+    pub fn get_namespace_object_lock(&self, namespace_id: i64) -> Result<bool> {
+        Ok(self.conn.query_row(
+            "SELECT object_lock_enabled FROM namespaces WHERE id=?1",
+            params![namespace_id],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// Allow retention settings on the namespace's objects. Like S3, object
+    /// lock cannot be turned off again.
+    pub fn enable_namespace_object_lock(&self, namespace_id: i64) -> Result<()> {
+        self.conn.execute(
+            "UPDATE namespaces SET object_lock_enabled=1 WHERE id=?1",
+            params![namespace_id],
+        )?;
+        Ok(())
+    }
+
+    /// Server access logging target of a namespace: (target bucket, key
+    /// prefix), or `None` when logging is off.
+    pub fn get_namespace_logging(&self, namespace_id: i64) -> Result<Option<(String, String)>> {
+        let (bucket, prefix): (Option<String>, Option<String>) = self.conn.query_row(
+            "SELECT logging_target_bucket, logging_target_prefix FROM namespaces WHERE id=?1",
+            params![namespace_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        Ok(bucket.map(|b| (b, prefix.unwrap_or_default())))
+    }
+
+    /// Turn server access logging on with a (target bucket, key prefix), or
+    /// off with `None`.
+    pub fn set_namespace_logging(
+        &self,
+        namespace_id: i64,
+        target: Option<(&str, &str)>,
+    ) -> Result<()> {
+        let (bucket, prefix) = target.unzip();
+        self.conn.execute(
+            "UPDATE namespaces SET logging_target_bucket=?2, logging_target_prefix=?3 WHERE id=?1",
+            params![namespace_id, bucket, prefix],
+        )?;
+        Ok(())
+    }
+
     pub fn list_namespaces(&self) -> Result<Vec<(i64, String, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, created_at FROM namespaces WHERE deleted_at IS NULL ORDER BY name",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+    }
+
+    /// All namespaces as (id, name, created_at, deleted_at), soft-deleted
+    /// ones included.
+    #[allow(clippy::type_complexity)]
+    pub fn list_namespaces_including_deleted(
+        &self,
+    ) -> Result<Vec<(i64, String, String, Option<String>)>> {
         let mut stmt = self
             .conn
-            .prepare("SELECT id, name, created_at FROM namespaces ORDER BY name")?;
-        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+            .prepare("SELECT id, name, created_at, deleted_at FROM namespaces ORDER BY name")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+        })?;
         Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
     }
 
     // ── S3 Gateway: Objects ──────────────────────────────────
 
+    /// Record a new object version and make it the latest one.
+    ///
+    /// With versioning enabled the previous versions are kept and the new
+    /// one gets a fresh version ID. Otherwise the "null" version of the key
+    /// is replaced (with versioning suspended, older versions are kept).
     #[allow(clippy::too_many_arguments)]
     pub fn insert_object(
         &self,
@@ -719,25 +1907,69 @@ impl ManifestDb {
         chunk_count: u32,
         key_id: &str,
     ) -> Result<i64> {
-        // Upsert: delete old object if exists, then insert new one
-        self.delete_object_by_ns_key(namespace_id, key)?;
+        let version_id = self.next_version_id(namespace_id, key)?;
         self.conn.execute(
-            "INSERT INTO objects (namespace_id, key, size, etag, content_type, chunk_count, key_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-            params![namespace_id, key, size, etag, content_type, chunk_count, key_id],
+            "INSERT INTO objects (namespace_id, key, version_id, size, etag, content_type, chunk_count, key_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![namespace_id, key, version_id, size, etag, content_type, chunk_count, key_id],
         )?;
         Ok(self.conn.last_insert_rowid())
     }
 
+    /// Make room for a new latest version of `key` and return its version
+    /// ID (`None` for the "null" version).
+    fn next_version_id(&self, namespace_id: i64, key: &str) -> Result<Option<String>> {
+        let (enabled, suspended) = self.get_namespace_versioning(namespace_id)?;
+        if !enabled && !suspended {
+            // Upsert: delete old object if exists, then insert new one
+            self.delete_object_by_ns_key(namespace_id, key)?;
+            return Ok(None);
+        }
+        if suspended && let Some(object_id) = self.null_version(namespace_id, key)? {
+            self.delete_object_row(object_id)?;
+        }
+        self.conn.execute(
+            "UPDATE objects SET is_latest=0 WHERE namespace_id=?1 AND key=?2 AND is_latest=1",
+            params![namespace_id, key],
+        )?;
+        Ok(enabled.then(|| uuid::Uuid::now_v7().to_string()))
+    }
+
+    /// Latest version of an object, unless it is a delete marker.
     #[allow(clippy::type_complexity)]
     pub fn get_object(
         &self,
         namespace_id: i64,
         key: &str,
     ) -> Result<Option<(i64, u64, String, Option<String>, u32, String, String)>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, size, etag, content_type, chunk_count, key_id, created_at FROM objects WHERE namespace_id=?1 AND key=?2",
-        )?;
-        let mut rows = stmt.query_map(params![namespace_id, key], |row| {
+        self.query_object(
+            "SELECT id, size, etag, content_type, chunk_count, key_id, created_at FROM objects WHERE namespace_id=?1 AND key=?2 AND is_latest=1 AND is_delete_marker=0",
+            params![namespace_id, key],
+        )
+    }
+
+    /// A specific version of an object ("null" for the unversioned one).
+    /// Delete markers are not returned.
+    #[allow(clippy::type_complexity)]
+    pub fn get_object_version(
+        &self,
+        namespace_id: i64,
+        key: &str,
+        version_id: &str,
+    ) -> Result<Option<(i64, u64, String, Option<String>, u32, String, String)>> {
+        self.query_object(
+            "SELECT id, size, etag, content_type, chunk_count, key_id, created_at FROM objects WHERE namespace_id=?1 AND key=?2 AND IFNULL(version_id, 'null')=?3 AND is_delete_marker=0",
+            params![namespace_id, key, version_id],
+        )
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn query_object(
+        &self,
+        sql: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Option<(i64, u64, String, Option<String>, u32, String, String)>> {
+        let mut stmt = self.conn.prepare(sql)?;
+        let mut rows = stmt.query_map(params, |row| {
             Ok((
                 row.get::<_, i64>(0)?,
                 row.get::<_, u64>(1)?,
@@ -755,6 +1987,26 @@ impl ManifestDb {
         }
     }
 
+    /// Bytes and number of versions stored under a key, delete markers
+    /// excluded.
+    pub fn object_key_usage(&self, namespace_id: i64, key: &str) -> Result<(u64, u64)> {
+        Ok(self.conn.query_row(
+            "SELECT COALESCE(SUM(size), 0), COUNT(*) FROM objects
+             WHERE namespace_id=?1 AND key=?2 AND is_delete_marker=0",
+            params![namespace_id, key],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?)
+    }
+
+    /// Version ID of an object row, "null" for the unversioned one.
+    pub fn get_object_version_id(&self, object_id: i64) -> Result<String> {
+        Ok(self.conn.query_row(
+            "SELECT IFNULL(version_id, 'null') FROM objects WHERE id=?1",
+            params![object_id],
+            |row| row.get(0),
+        )?)
+    }
+
     /// Delete an object by namespace_id + key. Also cleans up object_chunks
     /// and decrements chunk ref_counts.
     /// Returns the list of (provider_id, storage_key) for chunks that need physical deletion.
@@ -767,37 +2019,120 @@ impl ManifestDb {
         let Some((object_id, ..)) = obj else {
             return Ok(vec![]);
         };
-
-        // Get all chunk hashes for this object
-        let chunk_hashes = self.get_object_chunks(object_id)?;
-        let mut to_delete = Vec::new();
-
-        for (chunk_hash, _, _) in &chunk_hashes {
-            let locations = self.decrement_chunk_ref(chunk_hash)?;
-            to_delete.extend(locations);
-        }
-
-        // Delete object_chunks and object
-        self.conn.execute(
-            "DELETE FROM object_chunks WHERE object_id=?1",
-            params![object_id],
-        )?;
-        self.conn
-            .execute("DELETE FROM objects WHERE id=?1", params![object_id])?;
-
+        let to_delete = self.delete_object_row(object_id)?;
+        self.promote_latest_version(namespace_id, key)?;
         Ok(to_delete)
     }
 
-    pub fn list_objects(
+    /// Permanently delete one version of an object (or delete marker).
+    /// Returns the chunk locations that need physical deletion, or `None`
+    /// if the version does not exist.
+    pub fn delete_object_version(
         &self,
         namespace_id: i64,
-        prefix: &str,
-        max_keys: u32,
-        start_after: &str,
+        key: &str,
+        version_id: &str,
+    ) -> Result<Option<Vec<(i64, String)>>> {
+        let object_id: Option<i64> = self
+            .conn
+            .query_row(
+                "SELECT id FROM objects WHERE namespace_id=?1 AND key=?2 AND IFNULL(version_id, 'null')=?3",
+                params![namespace_id, key, version_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let Some(object_id) = object_id else {
+            return Ok(None);
+        };
+        let to_delete = self.delete_object_row(object_id)?;
+        self.promote_latest_version(namespace_id, key)?;
+        Ok(Some(to_delete))
+    }
+
+    /// Hide `key` behind a new delete marker, keeping its versions.
+    /// Returns the marker's version ID ("null" with versioning suspended,
+    /// in which case the previous null version is removed and its chunk
+    /// locations returned).
+    pub fn insert_delete_marker(
+        &self,
+        namespace_id: i64,
+        key: &str,
+    ) -> Result<(String, Vec<(i64, String)>)> {
+        let (enabled, _suspended) = self.get_namespace_versioning(namespace_id)?;
+        let mut to_delete = Vec::new();
+        if !enabled && let Some(object_id) = self.null_version(namespace_id, key)? {
+            to_delete = self.delete_object_row(object_id)?;
+        }
+        self.conn.execute(
+            "UPDATE objects SET is_latest=0 WHERE namespace_id=?1 AND key=?2 AND is_latest=1",
+            params![namespace_id, key],
+        )?;
+        let version_id = enabled.then(|| uuid::Uuid::now_v7().to_string());
+        self.conn.execute(
+            "INSERT INTO objects (namespace_id, key, version_id, is_delete_marker, size, etag, chunk_count, key_id) VALUES (?1, ?2, ?3, 1, 0, '', 0, '')",
+            params![namespace_id, key, version_id],
+        )?;
+        Ok((version_id.unwrap_or_else(|| "null".to_string()), to_delete))
+    }
+
+    fn null_version(&self, namespace_id: i64, key: &str) -> Result<Option<i64>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT id FROM objects WHERE namespace_id=?1 AND key=?2 AND version_id IS NULL",
+                params![namespace_id, key],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    /// Remove one object row with its chunk mappings and decrement the
+    /// chunks' ref_counts.
+    fn delete_object_row(&self, object_id: i64) -> Result<Vec<(i64, String)>> {
+        // Get all chunk hashes for this object
+        let chunk_hashes = self.get_object_chunks(object_id)?;
+        let mut to_delete = Vec::new();
+
+        for (chunk_hash, _, _) in &chunk_hashes {
+            let locations = self.decrement_chunk_ref(chunk_hash)?;
+            to_delete.extend(locations);
+        }
+
+        // Delete object_chunks and object
+        self.conn.execute(
+            "DELETE FROM object_chunks WHERE object_id=?1",
+            params![object_id],
+        )?;
+        self.conn
+            .execute("DELETE FROM objects WHERE id=?1", params![object_id])?;
+
+        Ok(to_delete)
+    }
+
+    /// After the latest version of `key` was removed, the newest remaining
+    /// version (if any) becomes the latest.
+    fn promote_latest_version(&self, namespace_id: i64, key: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE objects SET is_latest=1 WHERE id=(
+                SELECT id FROM objects WHERE namespace_id=?1 AND key=?2 ORDER BY id DESC LIMIT 1
+             ) AND NOT EXISTS (
+                SELECT 1 FROM objects WHERE namespace_id=?1 AND key=?2 AND is_latest=1
+             )",
+            params![namespace_id, key],
+        )?;
+        Ok(())
+    }
+
+    pub fn list_objects(
+        &self,
+        namespace_id: i64,
+        prefix: &str,
+        max_keys: u32,
+        start_after: &str,
     ) -> Result<Vec<(String, u64, String, String)>> {
         let prefix_pattern = format!("{}%", escape_like(prefix));
         let mut stmt = self.conn.prepare(
-            "SELECT key, size, etag, created_at FROM objects WHERE namespace_id=?1 AND key LIKE ?2 ESCAPE '\\' AND key > ?3 ORDER BY key LIMIT ?4",
+            "SELECT key, size, etag, created_at FROM objects WHERE namespace_id=?1 AND is_latest=1 AND is_delete_marker=0 AND key LIKE ?2 ESCAPE '\\' AND key > ?3 ORDER BY key LIMIT ?4",
         )?;
         let rows = stmt.query_map(
             params![namespace_id, prefix_pattern, start_after, max_keys],
@@ -813,16 +2148,307 @@ impl ManifestDb {
         Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
     }
 
+    /// At most `max_keys` versions and delete markers under `prefix`,
+    /// ordered by key and then newest first. Resumes after (`key_marker`,
+    /// `version_id_marker`); an empty version marker skips every version of
+    /// `key_marker`.
+    pub fn list_object_versions(
+        &self,
+        namespace_id: i64,
+        prefix: &str,
+        max_keys: u32,
+        key_marker: &str,
+        version_id_marker: &str,
+    ) -> Result<Vec<ObjectVersionRecord>> {
+        let prefix_pattern = format!("{}%", escape_like(prefix));
+        let marker_id: i64 = if version_id_marker.is_empty() {
+            0
+        } else {
+            self.conn
+                .query_row(
+                    "SELECT id FROM objects WHERE namespace_id=?1 AND key=?2 AND IFNULL(version_id, 'null')=?3",
+                    params![namespace_id, key_marker, version_id_marker],
+                    |row| row.get(0),
+                )
+                .optional()?
+                .unwrap_or(0)
+        };
+        let mut stmt = self.conn.prepare(
+            "SELECT key, IFNULL(version_id, 'null'), size, etag, is_latest, created_at, is_delete_marker FROM objects
+             WHERE namespace_id=?1 AND key LIKE ?2 ESCAPE '\\' AND (key > ?3 OR (key = ?3 AND id < ?4))
+             ORDER BY key, id DESC LIMIT ?5",
+        )?;
+        let rows = stmt.query_map(
+            params![
+                namespace_id,
+                prefix_pattern,
+                key_marker,
+                marker_id,
+                max_keys
+            ],
+            |row| {
+                Ok(ObjectVersionRecord {
+                    key: row.get(0)?,
+                    version_id: row.get(1)?,
+                    size: row.get(2)?,
+                    etag: row.get(3)?,
+                    is_latest: row.get(4)?,
+                    created_at: row.get(5)?,
+                    is_delete_marker: row.get(6)?,
+                })
+            },
+        )?;
+        Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+    }
+
     pub fn count_objects_with_prefix(&self, namespace_id: i64, prefix: &str) -> Result<u64> {
         let prefix_pattern = format!("{}%", escape_like(prefix));
         let count: u64 = self.conn.query_row(
-            "SELECT COUNT(*) FROM objects WHERE namespace_id=?1 AND key LIKE ?2 ESCAPE '\\'",
+            "SELECT COUNT(*) FROM objects WHERE namespace_id=?1 AND is_latest=1 AND is_delete_marker=0 AND key LIKE ?2 ESCAPE '\\'",
             params![namespace_id, prefix_pattern],
             |row| row.get(0),
         )?;
         Ok(count)
     }
 
+    /// Number of object versions and delete markers in a namespace. A
+    /// bucket can only be deleted once this is zero.
+    pub fn count_object_versions(&self, namespace_id: i64) -> Result<u64> {
+        let count: u64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM objects WHERE namespace_id=?1",
+            params![namespace_id],
+            |row| row.get(0),
+        )?;
+        Ok(count)
+    }
+
+    // ── S3 Gateway: Object tags ──────────────────────────────
+
+    /// Replace the full tag set of an object. An empty slice clears all tags.
+    pub fn set_object_tags(&self, object_id: i64, tags: &[(String, String)]) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM object_tags WHERE object_id=?1",
+            params![object_id],
+        )?;
+        let mut stmt = self
+            .conn
+            .prepare("INSERT INTO object_tags (object_id, key, value) VALUES (?1, ?2, ?3)")?;
+        for (key, value) in tags {
+            stmt.execute(params![object_id, key, value])?;
+        }
+        Ok(())
+    }
+
+    pub fn get_object_tags(&self, object_id: i64) -> Result<Vec<(String, String)>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT key, value FROM object_tags WHERE object_id=?1 ORDER BY key")?;
+        let rows = stmt.query_map(params![object_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+    }
+
+    // ── S3 Gateway: Bucket tags ──────────────────────────────
+
+    /// Replace the full tag set of a namespace. An empty slice clears all tags.
+    pub fn set_namespace_tags(&self, namespace_id: i64, tags: &[(String, String)]) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM namespace_tags WHERE namespace_id=?1",
+            params![namespace_id],
+        )?;
+        let mut stmt = self
+            .conn
+            .prepare("INSERT INTO namespace_tags (namespace_id, key, value) VALUES (?1, ?2, ?3)")?;
+        for (key, value) in tags {
+            stmt.execute(params![namespace_id, key, value])?;
+        }
+        Ok(())
+    }
+
+    pub fn get_namespace_tags(&self, namespace_id: i64) -> Result<Vec<(String, String)>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT key, value FROM namespace_tags WHERE namespace_id=?1 ORDER BY key")?;
+        let rows = stmt.query_map(params![namespace_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+    }
+
+    // ── S3 Gateway: Bucket inventory ─────────────────────────
+
+    /// Create or replace the inventory configuration `config.id` of a
+    /// namespace.
+    pub fn put_namespace_inventory_config(
+        &self,
+        namespace_id: i64,
+        config: &InventoryConfig,
+    ) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO namespace_inventory_config
+                (namespace_id, id, enabled, destination_bucket, destination_prefix, format,
+                 frequency, included_object_versions, filter_prefix)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                namespace_id,
+                config.id,
+                config.enabled,
+                config.destination_bucket,
+                config.destination_prefix,
+                config.format,
+                config.frequency,
+                config.included_object_versions,
+                config.filter_prefix,
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_namespace_inventory_config(
+        &self,
+        namespace_id: i64,
+        id: &str,
+    ) -> Result<Option<InventoryConfig>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT id, enabled, destination_bucket, destination_prefix, format, frequency,
+                        included_object_versions, filter_prefix
+                 FROM namespace_inventory_config WHERE namespace_id=?1 AND id=?2",
+                params![namespace_id, id],
+                inventory_config_from_row,
+            )
+            .optional()?)
+    }
+
+    /// Enabled inventory configurations of live namespaces, as (namespace
+    /// ID, namespace name, configuration), ordered by namespace name.
+    pub fn list_enabled_inventory_configs(&self) -> Result<Vec<(i64, String, InventoryConfig)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT c.id, c.enabled, c.destination_bucket, c.destination_prefix, c.format,
+                    c.frequency, c.included_object_versions, c.filter_prefix, n.id, n.name
+             FROM namespace_inventory_config c JOIN namespaces n ON n.id = c.namespace_id
+             WHERE c.enabled=1 AND n.deleted_at IS NULL
+             ORDER BY n.name, c.id",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get(8)?, row.get(9)?, inventory_config_from_row(row)?))
+        })?;
+        Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+    }
+
+    pub fn insert_inventory_report(&self, report: &InventoryReport) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO inventory_reports (key, size, row_count, generated_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                report.key,
+                report.size as i64,
+                report.rows as i64,
+                report.generated_at,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// The most recently generated inventory report, if any.
+    pub fn latest_inventory_report(&self) -> Result<Option<InventoryReport>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT key, size, row_count, generated_at FROM inventory_reports
+                 ORDER BY id DESC LIMIT 1",
+                [],
+                |row| {
+                    Ok(InventoryReport {
+                        key: row.get(0)?,
+                        size: row.get::<_, i64>(1)? as u64,
+                        rows: row.get::<_, i64>(2)? as u64,
+                        generated_at: row.get(3)?,
+                    })
+                },
+            )
+            .optional()?)
+    }
+
+    // ── S3 Gateway: Object metadata ──────────────────────────
+
+    /// Replace the user-defined metadata (`x-amz-meta-*`) of an object.
+    pub fn set_object_metadata(&self, object_id: i64, meta: &[(String, String)]) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM object_metadata WHERE object_id=?1",
+            params![object_id],
+        )?;
+        let mut stmt = self
+            .conn
+            .prepare("INSERT INTO object_metadata (object_id, key, value) VALUES (?1, ?2, ?3)")?;
+        for (key, value) in meta {
+            stmt.execute(params![object_id, key, value])?;
+        }
+        Ok(())
+    }
+
+    pub fn get_object_metadata(&self, object_id: i64) -> Result<Vec<(String, String)>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT key, value FROM object_metadata WHERE object_id=?1 ORDER BY key")?;
+        let rows = stmt.query_map(params![object_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+    }
+
+    // ── S3 Gateway: Object lock ──────────────────────────────
+
+    /// Set (or with `None`, remove) the retention of an object version as
+    /// `(mode, retain_until)`.
+    pub fn set_object_retention(
+        &self,
+        object_id: i64,
+        retention: Option<(&str, &str)>,
+    ) -> Result<()> {
+        let (mode, retain_until) = retention.unzip();
+        self.conn.execute(
+            "UPDATE objects SET object_lock_enabled=?2, object_lock_mode=?3,
+                    object_lock_retain_until=?4
+             WHERE id=?1",
+            params![object_id, retention.is_some(), mode, retain_until],
+        )?;
+        Ok(())
+    }
+
+    /// Retention of an object version as `(mode, retain_until)`, expired or
+    /// not; `None` when it was never locked.
+    pub fn get_object_retention(&self, object_id: i64) -> Result<Option<(String, String)>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT object_lock_mode, object_lock_retain_until FROM objects
+                 WHERE id=?1 AND object_lock_enabled=1",
+                params![object_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?)
+    }
+
+    /// Place (`true`) or clear the legal hold on an object version.
+    pub fn set_object_legal_hold(&self, object_id: i64, hold: bool) -> Result<()> {
+        self.conn.execute(
+            "UPDATE objects SET legal_hold=?2 WHERE id=?1",
+            params![object_id, hold],
+        )?;
+        Ok(())
+    }
+
+    /// Whether an object version is under legal hold. Unknown versions are
+    /// not.
+    pub fn get_object_legal_hold(&self, object_id: i64) -> Result<bool> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT legal_hold FROM objects WHERE id=?1",
+                params![object_id],
+                |row| row.get(0),
+            )
+            .optional()?
+            .unwrap_or(false))
+    }
+
     // ── S3 Gateway: Object Chunks ────────────────────────────
 
     pub fn insert_object_chunk(
@@ -849,6 +2475,104 @@ impl ManifestDb {
         Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
     }
 
+    // ── S3 Gateway: Pending objects ──────────────────────────
+
+    /// Start an object whose body is still streaming in. Chunks are added
+    /// with [`Self::add_pending_object_chunk`] as they are stored.
+    pub fn insert_object_pending(&self, namespace_id: i64, key: &str) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO pending_objects (namespace_id, key) VALUES (?1, ?2)",
+            params![namespace_id, key],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Record the next stored chunk of a pending object.
+    pub fn add_pending_object_chunk(
+        &self,
+        pending_id: i64,
+        chunk_index: u32,
+        chunk_hash: &str,
+        size: u64,
+    ) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO pending_object_chunks (pending_id, chunk_index, chunk_hash, size) VALUES (?1, ?2, ?3, ?4)",
+            params![pending_id, chunk_index, chunk_hash, size],
+        )?;
+        Ok(())
+    }
+
+    /// Turn a pending object into an object with its recorded chunks, and
+    /// return the object's ID. Call within a transaction so the object
+    /// appears complete or not at all.
+    pub fn commit_pending_object(
+        &self,
+        pending_id: i64,
+        size: u64,
+        etag: &str,
+        content_type: Option<&str>,
+        key_id: &str,
+    ) -> Result<i64> {
+        let (namespace_id, key): (i64, String) = self.conn.query_row(
+            "SELECT namespace_id, key FROM pending_objects WHERE id=?1",
+            params![pending_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        let chunks = self.pending_object_chunks(pending_id)?;
+        let object_id = self.insert_object(
+            namespace_id,
+            &key,
+            size,
+            etag,
+            content_type,
+            chunks.len() as u32,
+            key_id,
+        )?;
+        let mut offset = 0u64;
+        for (chunk_index, chunk_hash, chunk_size) in &chunks {
+            self.insert_object_chunk(object_id, chunk_hash, *chunk_index, offset)?;
+            offset += chunk_size;
+        }
+        self.delete_pending_object_rows(pending_id)?;
+        Ok(object_id)
+    }
+
+    /// Drop a pending object and the references its chunks hold. Returns
+    /// the chunk locations that need physical deletion.
+    pub fn rollback_pending_object(&self, pending_id: i64) -> Result<Vec<(i64, String)>> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut to_delete = Vec::new();
+        for (_, chunk_hash, _) in self.pending_object_chunks(pending_id)? {
+            to_delete.extend(self.decrement_chunk_ref(&chunk_hash)?);
+        }
+        self.delete_pending_object_rows(pending_id)?;
+        tx.commit()?;
+        Ok(to_delete)
+    }
+
+    /// Chunks of a pending object as (chunk_index, chunk_hash, size).
+    fn pending_object_chunks(&self, pending_id: i64) -> Result<Vec<(u32, String, u64)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT chunk_index, chunk_hash, size FROM pending_object_chunks WHERE pending_id=?1 ORDER BY chunk_index",
+        )?;
+        let rows = stmt.query_map(params![pending_id], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })?;
+        Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+    }
+
+    fn delete_pending_object_rows(&self, pending_id: i64) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM pending_object_chunks WHERE pending_id=?1",
+            params![pending_id],
+        )?;
+        self.conn.execute(
+            "DELETE FROM pending_objects WHERE id=?1",
+            params![pending_id],
+        )?;
+        Ok(())
+    }
+
     // ── S3 Gateway: Multipart uploads ────────────────────────
 
     pub fn create_multipart_upload(
@@ -893,34 +2617,168 @@ impl ManifestDb {
         Ok(())
     }
 
-    #[allow(clippy::type_complexity)]
-    pub fn get_multipart_parts(&self, upload_id: &str) -> Result<Vec<(i32, Vec<u8>, u64, String)>> {
+    /// Record a part whose data was written to `spill_path` instead of the
+    /// database. The spill file of a part it replaces is removed.
+    pub fn insert_multipart_part_file(
+        &self,
+        upload_id: &str,
+        part_number: i32,
+        spill_path: &str,
+        size: u64,
+        etag: &str,
+    ) -> Result<()> {
+        let replaced: Option<Option<String>> = self
+            .conn
+            .query_row(
+                "SELECT spill_path FROM multipart_parts WHERE upload_id=?1 AND part_number=?2",
+                params![upload_id, part_number],
+                |row| row.get(0),
+            )
+            .optional()?;
+        self.conn.execute(
+            "INSERT OR REPLACE INTO multipart_parts (upload_id, part_number, data, size, etag, spill_path) VALUES (?1, ?2, x'', ?3, ?4, ?5)",
+            params![upload_id, part_number, size, etag, spill_path],
+        )?;
+        if let Some(Some(old)) = replaced
+            && old != spill_path
+        {
+            remove_spill_file(&old);
+        }
+        Ok(())
+    }
+
+    /// Parts of an upload in part number order, read one at a time with
+    /// [`MultipartPartsCursor::next_part`].
+    pub fn get_multipart_parts_cursor(&self, upload_id: &str) -> Result<MultipartPartsCursor> {
         let mut stmt = self.conn.prepare(
-            "SELECT part_number, data, size, etag FROM multipart_parts WHERE upload_id=?1 ORDER BY part_number",
+            "SELECT id, part_number, size, etag, spill_path FROM multipart_parts WHERE upload_id=?1 ORDER BY part_number",
         )?;
         let rows = stmt.query_map(params![upload_id], |row| {
             Ok((
-                row.get::<_, i32>(0)?,
-                row.get::<_, Vec<u8>>(1)?,
-                row.get::<_, u64>(2)?,
-                row.get::<_, String>(3)?,
+                row.get(0)?,
+                row.get(1)?,
+                row.get(2)?,
+                row.get(3)?,
+                row.get(4)?,
             ))
         })?;
-        Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+        Ok(MultipartPartsCursor {
+            parts: rows.collect::<std::result::Result<_, _>>()?,
+        })
     }
 
-    pub fn abort_multipart_upload(&self, upload_id: &str) -> Result<()> {
+    /// Track a chunk stored for `part_number` while completing an upload,
+    /// until [`ManifestDb::complete_multipart_upload`] hands it over to the
+    /// object.
+    pub fn insert_multipart_part_chunk(
+        &self,
+        upload_id: &str,
+        part_number: i32,
+        chunk_hash: &str,
+    ) -> Result<()> {
         self.conn.execute(
-            "DELETE FROM multipart_parts WHERE upload_id=?1",
+            "INSERT INTO multipart_part_chunks (upload_id, part_number, chunk_hash) VALUES (?1, ?2, ?3)",
+            params![upload_id, part_number, chunk_hash],
+        )?;
+        Ok(())
+    }
+
+    /// Chunks stored for an upload so far as (part_number, chunk_hash), in
+    /// the order they were stored.
+    pub fn get_multipart_part_chunks(&self, upload_id: &str) -> Result<Vec<(i32, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT part_number, chunk_hash FROM multipart_part_chunks WHERE upload_id=?1 ORDER BY rowid",
+        )?;
+        let rows = stmt.query_map(params![upload_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+    }
+
+    /// Drop the references held by the chunks stored for an upload, e.g.
+    /// by an interrupted completion. Returns the chunk locations that need
+    /// physical deletion.
+    pub fn release_multipart_part_chunks(&self, upload_id: &str) -> Result<Vec<(i64, String)>> {
+        let tx = self.conn.unchecked_transaction()?;
+        let to_delete = self.release_part_chunk_refs(upload_id)?;
+        tx.commit()?;
+        Ok(to_delete)
+    }
+
+    fn release_part_chunk_refs(&self, upload_id: &str) -> Result<Vec<(i64, String)>> {
+        let mut to_delete = Vec::new();
+        for (_, chunk_hash) in self.get_multipart_part_chunks(upload_id)? {
+            to_delete.extend(self.decrement_chunk_ref(&chunk_hash)?);
+        }
+        self.conn.execute(
+            "DELETE FROM multipart_part_chunks WHERE upload_id=?1",
             params![upload_id],
         )?;
+        Ok(to_delete)
+    }
+
+    /// Hand the chunks stored for an upload over to `object_id`, in the
+    /// order they were stored, then drop the upload with its parts.
+    pub fn complete_multipart_upload(&self, upload_id: &str, object_id: i64) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut offset = 0u64;
+        for (chunk_index, (_, chunk_hash)) in self
+            .get_multipart_part_chunks(upload_id)?
+            .iter()
+            .enumerate()
+        {
+            self.insert_object_chunk(object_id, chunk_hash, chunk_index as u32, offset)?;
+            let size: u64 = self.conn.query_row(
+                "SELECT size_plain FROM chunks WHERE hash=?1",
+                params![chunk_hash],
+                |row| row.get(0),
+            )?;
+            offset += size;
+        }
         self.conn.execute(
-            "DELETE FROM multipart_uploads WHERE id=?1",
+            "DELETE FROM multipart_part_chunks WHERE upload_id=?1",
             params![upload_id],
         )?;
+        let spilled = self.delete_multipart_upload_rows(upload_id)?;
+        tx.commit()?;
+        spilled.iter().for_each(|path| remove_spill_file(path));
         Ok(())
     }
 
+    /// Drop an upload with its parts, removing their spill files and
+    /// releasing the chunks already stored for it. Returns the chunk
+    /// locations that need physical deletion.
+    pub fn abort_multipart_upload(&self, upload_id: &str) -> Result<Vec<(i64, String)>> {
+        let tx = self.conn.unchecked_transaction()?;
+        let to_delete = self.release_part_chunk_refs(upload_id)?;
+        let spilled = self.delete_multipart_upload_rows(upload_id)?;
+        tx.commit()?;
+        spilled.iter().for_each(|path| remove_spill_file(path));
+        Ok(to_delete)
+    }
+
+    /// Delete an upload's rows. Returns its spill files, to be removed once
+    /// the deletion is committed.
+    fn delete_multipart_upload_rows(&self, upload_id: &str) -> Result<Vec<String>> {
+        let spilled = self.query_spill_paths(
+            "SELECT spill_path FROM multipart_parts WHERE upload_id=?1 AND spill_path IS NOT NULL",
+            params![upload_id],
+        )?;
+        self.conn.execute(
+            "DELETE FROM multipart_parts WHERE upload_id=?1",
+            params![upload_id],
+        )?;
+        self.conn.execute(
+            "DELETE FROM multipart_uploads WHERE id=?1",
+            params![upload_id],
+        )?;
+        Ok(spilled)
+    }
+
+    fn query_spill_paths(&self, sql: &str, params: impl rusqlite::Params) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(sql)?;
+        let rows = stmt.query_map(params, |row| row.get(0))?;
+        Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+    }
+
     pub fn list_multipart_uploads(
         &self,
         namespace_id: i64,
@@ -933,12 +2791,134 @@ impl ManifestDb {
         })?;
         Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
     }
+
+    /// IDs of multipart uploads, in any namespace, created at least
+    /// `max_age_hours` ago.
+    pub fn list_expired_multipart_uploads(&self, max_age_hours: u64) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id FROM multipart_uploads WHERE created_at <= datetime('now', ?1) ORDER BY created_at",
+        )?;
+        let rows = stmt.query_map(params![format!("-{max_age_hours} hours")], |row| row.get(0))?;
+        Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+    }
+}
+
+/// An object version or delete marker, as listed by
+/// [`ManifestDb::list_object_versions`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectVersionRecord {
+    pub key: String,
+    /// `"null"` for a version written while versioning was off.
+    pub version_id: String,
+    pub size: u64,
+    pub etag: String,
+    /// Whether this is the newest version of its key. A delete marker
+    /// that is latest hides the key.
+    pub is_latest: bool,
+    pub created_at: String,
+    pub is_delete_marker: bool,
+}
+
+/// A webhook delivery waiting in the queue, as listed by
+/// [`ManifestDb::pending_webhooks`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueuedWebhook {
+    pub id: i64,
+    pub url: String,
+    pub event: String,
+    /// The JSON payload, exactly as it will be POSTed and signed.
+    pub body: String,
+    pub created_at: String,
+}
+
+/// One part of a multipart upload, as read by [`MultipartPartsCursor`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultipartPart {
+    pub part_number: i32,
+    pub data: Vec<u8>,
+    pub etag: String,
+}
+
+/// Remaining parts of a multipart upload. Only the part being read is held
+/// in memory; the rest stay in their spill files or in the database.
+pub struct MultipartPartsCursor {
+    /// (row id, part number, size, etag, spill path), in part number order.
+    parts: std::collections::VecDeque<(i64, i32, u64, String, Option<String>)>,
+}
+
+impl MultipartPartsCursor {
+    /// Number of parts not read yet.
+    pub fn len(&self) -> usize {
+        self.parts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parts.is_empty()
+    }
+
+    /// Total size of the parts not read yet.
+    pub fn remaining_size(&self) -> u64 {
+        self.parts.iter().map(|(_, _, size, ..)| size).sum()
+    }
+
+    /// (part number, size) of the parts not read yet, in order.
+    pub fn sizes(&self) -> impl Iterator<Item = (i32, u64)> + '_ {
+        self.parts
+            .iter()
+            .map(|(_, part_number, size, ..)| (*part_number, *size))
+    }
+
+    /// Read the next part, from its spill file or, for parts stored
+    /// inline, from `db`.
+    pub fn next_part(&mut self, db: &ManifestDb) -> Result<Option<MultipartPart>> {
+        let Some((id, part_number, size, etag, spill_path)) = self.parts.pop_front() else {
+            return Ok(None);
+        };
+        let data = match spill_path {
+            Some(path) => std::fs::read(&path)?,
+            None => db.conn.query_row(
+                "SELECT data FROM multipart_parts WHERE id=?1",
+                params![id],
+                |row| row.get(0),
+            )?,
+        };
+        if data.len() as u64 != size {
+            return Err(EnigmaError::Integrity(format!(
+                "multipart part {part_number} has {} bytes, expected {size}",
+                data.len()
+            )));
+        }
+        Ok(Some(MultipartPart {
+            part_number,
+            data,
+            etag,
+        }))
+    }
+}
+
+/// Best-effort removal of a multipart spill file.
+fn remove_spill_file(path: &str) {
+    if let Err(e) = std::fs::remove_file(path)
+        && e.kind() != std::io::ErrorKind::NotFound
+    {
+        tracing::warn!("Failed to remove multipart spill file {path}: {e}");
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn provider_weight_update() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let pid = db
+            .insert_provider("a", ProviderType::Local, "/tmp/a", None, 1)
+            .unwrap();
+        db.set_provider_weight(pid, 5).unwrap();
+        assert_eq!(db.list_providers().unwrap()[0].weight, 5);
+    }
+
     #[test]
     fn full_backup_flow() {
         let db = ManifestDb::open_in_memory().unwrap();
@@ -970,50 +2950,583 @@ mod tests {
                 None,
             )
             .unwrap();
-        assert!(is_new);
+        assert!(is_new);
+
+        // Insert same chunk (dedup)
+        let is_new = db
+            .insert_or_dedup_chunk(
+                "deadbeef",
+                &[0u8; 12],
+                "key-1",
+                pid,
+                "enigma/chunks/de/ad/deadbeef",
+                1024,
+                1040,
+                None,
+            )
+            .unwrap();
+        assert!(!is_new);
+
+        // Map file -> chunk
+        db.insert_file_chunk(file_id, "deadbeef", 0, 0).unwrap();
+
+        // Complete backup
+        db.complete_backup(backup_id, 1, 1024, 1, 1).unwrap();
+
+        // Verify
+        let backup = db.get_backup(backup_id).unwrap();
+        assert_eq!(backup.status, BackupStatus::Completed);
+        assert_eq!(backup.total_files, 1);
+
+        let files = db.list_backup_files(backup_id).unwrap();
+        assert_eq!(files.len(), 1);
+
+        let chunks = db.get_file_chunks(file_id).unwrap();
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].0, "deadbeef");
+    }
+
+    #[test]
+    fn list_backups_ordered() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        db.create_backup("id-1", "/path1").unwrap();
+        db.create_backup("id-2", "/path2").unwrap();
+
+        let backups = db.list_backups().unwrap();
+        assert_eq!(backups.len(), 2);
+    }
+
+    #[test]
+    fn backup_tags_multi_filter() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        db.create_backup("b1", "/path1").unwrap();
+        db.create_backup("b2", "/path2").unwrap();
+        db.set_backup_tag("b1", "env", "prod").unwrap();
+        db.set_backup_tag("b1", "team", "infra").unwrap();
+        db.set_backup_tag("b2", "env", "prod").unwrap();
+        db.set_backup_tag("b2", "team", "web").unwrap();
+
+        let prod = db.list_backups_with_tag("env", "prod").unwrap();
+        assert_eq!(prod.len(), 2);
+
+        let infra = db.list_backups_with_tag("team", "infra").unwrap();
+        assert_eq!(infra.len(), 1);
+        assert_eq!(infra[0].id, "b1");
+
+        let tags = db.get_backup_tags("b1").unwrap();
+        assert_eq!(tags.len(), 2);
+        assert_eq!(tags["env"], "prod");
+        assert_eq!(tags["team"], "infra");
+
+        assert!(db.list_backups_with_tag("env", "dev").unwrap().is_empty());
+    }
+
+    #[test]
+    fn backup_tag_overwrite() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        db.create_backup("b1", "/path").unwrap();
+        db.set_backup_tag("b1", "env", "dev").unwrap();
+        db.set_backup_tag("b1", "env", "prod").unwrap();
+
+        let tags = db.get_backup_tags("b1").unwrap();
+        assert_eq!(tags.len(), 1);
+        assert_eq!(tags["env"], "prod");
+        assert!(db.list_backups_with_tag("env", "dev").unwrap().is_empty());
+        assert_eq!(db.list_backups_with_tag("env", "prod").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn backup_tag_removal() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        db.create_backup("b1", "/path").unwrap();
+        db.set_backup_tag("b1", "env", "prod").unwrap();
+
+        assert!(db.remove_backup_tag("b1", "env").unwrap());
+        assert!(!db.remove_backup_tag("b1", "env").unwrap());
+        assert!(db.get_backup_tags("b1").unwrap().is_empty());
+        assert!(db.list_backups_with_tag("env", "prod").unwrap().is_empty());
+    }
+
+    fn tags(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn object_tags_replace_and_get() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let ns = db.create_namespace("bucket").unwrap();
+        let oid = db
+            .insert_object(ns, "a.txt", 3, "etag", None, 1, "k1")
+            .unwrap();
+
+        db.set_object_tags(oid, &tags(&[("team", "ops"), ("env", "prod")]))
+            .unwrap();
+        assert_eq!(
+            db.get_object_tags(oid).unwrap(),
+            tags(&[("env", "prod"), ("team", "ops")])
+        );
+
+        // PutObjectTagging semantics: the new set replaces the old one
+        db.set_object_tags(oid, &tags(&[("env", "dev")])).unwrap();
+        assert_eq!(db.get_object_tags(oid).unwrap(), tags(&[("env", "dev")]));
+
+        db.set_object_tags(oid, &[]).unwrap();
+        assert!(db.get_object_tags(oid).unwrap().is_empty());
+    }
+
+    #[test]
+    fn inventory_configs_and_reports() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let ns = db.create_namespace("bucket").unwrap();
+        let other = db.create_namespace("other").unwrap();
+        let config = |id: &str, enabled| InventoryConfig {
+            id: id.to_string(),
+            enabled,
+            destination_bucket: "arn:aws:s3:::reports".to_string(),
+            destination_prefix: Some("inv".to_string()),
+            format: "CSV".to_string(),
+            frequency: "Daily".to_string(),
+            included_object_versions: "Current".to_string(),
+            filter_prefix: None,
+        };
+        assert_eq!(
+            db.get_namespace_inventory_config(ns, "daily").unwrap(),
+            None
+        );
+
+        db.put_namespace_inventory_config(ns, &config("daily", false))
+            .unwrap();
+        db.put_namespace_inventory_config(ns, &config("daily", true))
+            .unwrap();
+        db.put_namespace_inventory_config(other, &config("off", false))
+            .unwrap();
+        assert_eq!(
+            db.get_namespace_inventory_config(ns, "daily").unwrap(),
+            Some(config("daily", true))
+        );
+        assert_eq!(
+            db.list_enabled_inventory_configs().unwrap(),
+            vec![(ns, "bucket".to_string(), config("daily", true))]
+        );
+
+        // Deleted buckets are not inventoried
+        assert!(db.delete_namespace("bucket").unwrap());
+        assert!(db.list_enabled_inventory_configs().unwrap().is_empty());
+
+        assert_eq!(db.latest_inventory_report().unwrap(), None);
+        for (day, rows) in [("2026-10-15", 10), ("2026-10-16", 12)] {
+            db.insert_inventory_report(&InventoryReport {
+                key: format!("inventory/{day}/inventory.csv"),
+                size: rows * 100,
+                rows,
+                generated_at: format!("{day}T00:00:00Z"),
+            })
+            .unwrap();
+        }
+        let latest = db.latest_inventory_report().unwrap().unwrap();
+        assert_eq!(latest.key, "inventory/2026-10-16/inventory.csv");
+        assert_eq!((latest.size, latest.rows), (1200, 12));
+    }
+
+    #[test]
+    fn namespace_tags_replace_and_get() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let ns = db.create_namespace("bucket").unwrap();
+        let other = db.create_namespace("other").unwrap();
+        assert!(db.get_namespace_tags(ns).unwrap().is_empty());
+
+        db.set_namespace_tags(ns, &tags(&[("team", "ops"), ("env", "prod")]))
+            .unwrap();
+        db.set_namespace_tags(other, &tags(&[("env", "test")]))
+            .unwrap();
+        assert_eq!(
+            db.get_namespace_tags(ns).unwrap(),
+            tags(&[("env", "prod"), ("team", "ops")])
+        );
+
+        db.set_namespace_tags(ns, &tags(&[("env", "dev")])).unwrap();
+        assert_eq!(db.get_namespace_tags(ns).unwrap(), tags(&[("env", "dev")]));
+
+        db.set_namespace_tags(ns, &[]).unwrap();
+        assert!(db.get_namespace_tags(ns).unwrap().is_empty());
+        assert_eq!(
+            db.get_namespace_tags(other).unwrap(),
+            tags(&[("env", "test")])
+        );
+    }
+
+    #[test]
+    fn object_retention_roundtrip() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let ns = db.create_namespace("bucket").unwrap();
+        assert!(!db.get_namespace_object_lock(ns).unwrap());
+        db.enable_namespace_object_lock(ns).unwrap();
+        assert!(db.get_namespace_object_lock(ns).unwrap());
+
+        let oid = db.insert_object(ns, "k", 1, "e", None, 1, "k1").unwrap();
+        assert_eq!(db.get_object_retention(oid).unwrap(), None);
+        db.set_object_retention(oid, Some(("COMPLIANCE", "2030-01-01T00:00:00Z")))
+            .unwrap();
+        assert_eq!(
+            db.get_object_retention(oid).unwrap(),
+            Some(("COMPLIANCE".to_string(), "2030-01-01T00:00:00Z".to_string()))
+        );
+        db.set_object_retention(oid, None).unwrap();
+        assert_eq!(db.get_object_retention(oid).unwrap(), None);
+    }
+
+    #[test]
+    fn object_legal_hold_roundtrip() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let ns = db.create_namespace("bucket").unwrap();
+        let oid = db.insert_object(ns, "k", 1, "e", None, 1, "k1").unwrap();
+        assert!(!db.get_object_legal_hold(oid).unwrap());
+
+        db.set_object_legal_hold(oid, true).unwrap();
+        assert!(db.get_object_legal_hold(oid).unwrap());
+        // Independent of retention
+        assert_eq!(db.get_object_retention(oid).unwrap(), None);
+
+        db.set_object_legal_hold(oid, false).unwrap();
+        assert!(!db.get_object_legal_hold(oid).unwrap());
+        assert!(!db.get_object_legal_hold(oid + 1).unwrap());
+    }
+
+    #[test]
+    fn object_metadata_replaced_on_overwrite() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let ns = db.create_namespace("bucket").unwrap();
+        let oid = db.insert_object(ns, "k", 1, "e1", None, 1, "k1").unwrap();
+        db.set_object_metadata(oid, &tags(&[("owner", "alice"), ("color", "red")]))
+            .unwrap();
+        assert_eq!(
+            db.get_object_metadata(oid).unwrap(),
+            tags(&[("color", "red"), ("owner", "alice")])
+        );
+
+        // Overwriting the key drops the old row and its metadata
+        let new_oid = db.insert_object(ns, "k", 2, "e2", None, 1, "k1").unwrap();
+        db.set_object_metadata(new_oid, &tags(&[("owner", "bob")]))
+            .unwrap();
+        assert_eq!(
+            db.get_object_metadata(new_oid).unwrap(),
+            tags(&[("owner", "bob")])
+        );
+        assert!(db.get_object_metadata(oid).unwrap().is_empty());
+    }
+
+    #[test]
+    fn object_tags_cascade_on_delete() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let ns = db.create_namespace("bucket").unwrap();
+        let oid = db
+            .insert_object(ns, "a.txt", 3, "etag", None, 1, "k1")
+            .unwrap();
+        db.set_object_tags(oid, &tags(&[("env", "prod")])).unwrap();
+
+        db.delete_object_by_ns_key(ns, "a.txt").unwrap();
+        let remaining: i64 = db
+            .conn()
+            .query_row(
+                "SELECT COUNT(*) FROM object_tags WHERE object_id=?1",
+                params![oid],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(remaining, 0);
+    }
+
+    #[test]
+    fn versioned_put_keeps_previous_versions() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let ns = db.create_namespace("bucket").unwrap();
+        db.set_namespace_versioning(ns, true, false).unwrap();
+
+        let v1 = db
+            .insert_object(ns, "a.txt", 1, "e1", None, 0, "k1")
+            .unwrap();
+        let v2 = db
+            .insert_object(ns, "a.txt", 2, "e2", None, 0, "k1")
+            .unwrap();
+        db.insert_object(ns, "b.txt", 3, "e3", None, 0, "k1")
+            .unwrap();
+        let v1_id = db.get_object_version_id(v1).unwrap();
+        let v2_id = db.get_object_version_id(v2).unwrap();
+        assert_ne!(v1_id, v2_id);
+
+        // Latest version wins; older ones stay addressable
+        assert_eq!(db.get_object(ns, "a.txt").unwrap().unwrap().2, "e2");
+        assert_eq!(
+            db.get_object_version(ns, "a.txt", &v1_id)
+                .unwrap()
+                .unwrap()
+                .2,
+            "e1"
+        );
+        assert_eq!(db.list_objects(ns, "", 100, "").unwrap().len(), 2);
+
+        // Key ascending, newest version first
+        let versions = db.list_object_versions(ns, "", 100, "", "").unwrap();
+        let order: Vec<(&str, &str, bool)> = versions
+            .iter()
+            .map(|v| (v.key.as_str(), v.version_id.as_str(), v.is_latest))
+            .collect();
+        assert_eq!(order[0], ("a.txt", v2_id.as_str(), true));
+        assert_eq!(order[1], ("a.txt", v1_id.as_str(), false));
+        assert_eq!(order[2].0, "b.txt");
+
+        // Resume after the first version of a.txt
+        let rest = db
+            .list_object_versions(ns, "", 100, "a.txt", &v2_id)
+            .unwrap();
+        assert_eq!(rest.len(), 2);
+        assert_eq!(rest[0].version_id, v1_id);
+    }
+
+    #[test]
+    fn delete_marker_hides_object() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let ns = db.create_namespace("bucket").unwrap();
+        db.set_namespace_versioning(ns, true, false).unwrap();
+        let oid = db
+            .insert_object(ns, "a.txt", 1, "e1", None, 0, "k1")
+            .unwrap();
+        let version = db.get_object_version_id(oid).unwrap();
+
+        let (marker, to_delete) = db.insert_delete_marker(ns, "a.txt").unwrap();
+        assert!(to_delete.is_empty());
+        assert!(db.get_object(ns, "a.txt").unwrap().is_none());
+        assert!(db.list_objects(ns, "", 100, "").unwrap().is_empty());
+        assert!(
+            db.get_object_version(ns, "a.txt", &version)
+                .unwrap()
+                .is_some()
+        );
+        assert_eq!(db.count_object_versions(ns).unwrap(), 2);
+
+        let versions = db.list_object_versions(ns, "", 100, "", "").unwrap();
+        assert_eq!(versions[0].version_id, marker);
+        assert!(
+            versions[0].is_latest && versions[0].is_delete_marker,
+            "latest delete marker"
+        );
+
+        // Removing the marker restores the previous version
+        db.delete_object_version(ns, "a.txt", &marker)
+            .unwrap()
+            .unwrap();
+        assert_eq!(db.get_object(ns, "a.txt").unwrap().unwrap().0, oid);
+    }
+
+    #[test]
+    fn bucket_versioning_lifecycle() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        db.create_namespace("bucket").unwrap();
+        let status = |db: &ManifestDb| db.get_bucket_versioning_status("bucket").unwrap();
+        assert_eq!(status(&db), Some(VersioningStatus::Unversioned));
+
+        assert!(db.enable_bucket_versioning("bucket").unwrap());
+        assert_eq!(status(&db), Some(VersioningStatus::Enabled));
+        assert!(db.suspend_bucket_versioning("bucket").unwrap());
+        assert_eq!(status(&db), Some(VersioningStatus::Suspended));
+        assert!(db.enable_bucket_versioning("bucket").unwrap());
+        assert_eq!(status(&db), Some(VersioningStatus::Enabled));
+
+        assert!(!db.enable_bucket_versioning("missing").unwrap());
+        assert!(!db.suspend_bucket_versioning("missing").unwrap());
+        assert_eq!(db.get_bucket_versioning_status("missing").unwrap(), None);
+    }
+
+    #[test]
+    fn suspended_versioning_replaces_null_version() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let ns = db.create_namespace("bucket").unwrap();
+        db.insert_object(ns, "a.txt", 1, "null-1", None, 0, "k1")
+            .unwrap();
+        db.set_namespace_versioning(ns, true, false).unwrap();
+        db.insert_object(ns, "a.txt", 2, "versioned", None, 0, "k1")
+            .unwrap();
+        db.set_namespace_versioning(ns, false, true).unwrap();
+        db.insert_object(ns, "a.txt", 3, "null-2", None, 0, "k1")
+            .unwrap();
+
+        let etags: Vec<String> = db
+            .list_object_versions(ns, "", 100, "", "")
+            .unwrap()
+            .into_iter()
+            .map(|v| v.etag)
+            .collect();
+        assert_eq!(etags, ["null-2", "versioned"]);
+        assert_eq!(
+            db.get_object_version(ns, "a.txt", "null")
+                .unwrap()
+                .unwrap()
+                .2,
+            "null-2"
+        );
+    }
+
+    /// Backup with `n` single-chunk files, completed (root stored).
+    fn merkle_backup(db: &ManifestDb, n: usize) {
+        let pid = db
+            .insert_provider("local", ProviderType::Local, "/tmp", None, 1)
+            .unwrap();
+        db.create_backup("b1", "/src").unwrap();
+        for i in 0..n {
+            let hash = format!("{i:064x}");
+            db.insert_or_dedup_chunk(&hash, &[0; 12], "k1", pid, &hash, 10, 26, None)
+                .unwrap();
+            let fid = db
+                .insert_backup_file("b1", &format!("file-{i}"), 10, None, &hash, 1)
+                .unwrap();
+            db.insert_file_chunk(fid, &hash, 0, 0).unwrap();
+        }
+        db.complete_backup("b1", n as u64, 10 * n as u64, n as u64, 0)
+            .unwrap();
+    }
+
+    #[test]
+    fn merkle_root_stored_on_complete() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        merkle_backup(&db, 5);
+
+        let stored = db.get_backup_merkle_root("b1").unwrap().unwrap();
+        assert_eq!(stored, db.build_backup_merkle_root("b1").unwrap());
+        for i in 0..5 {
+            assert!(db.verify_merkle_path("b1", i).unwrap());
+        }
+        assert!(db.verify_merkle_path("b1", 5).is_err());
+    }
+
+    #[test]
+    fn merkle_path_detects_manifest_tampering() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        merkle_backup(&db, 4);
+
+        db.conn()
+            .execute(
+                "UPDATE file_chunks SET chunk_hash=?1 WHERE chunk_hash=?2",
+                params![format!("{:064x}", 3), format!("{:064x}", 2)],
+            )
+            .unwrap();
+        assert!(!db.verify_merkle_path("b1", 2).unwrap());
+    }
+
+    #[test]
+    fn backup_signature_verifies() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        merkle_backup(&db, 3);
+        let keypair = signing::generate_keypair();
+        let public = signing::public_key(&keypair).unwrap();
+
+        assert!(!db.verify_backup_signature("b1", &public).unwrap());
+        let signature = db.sign_backup("b1", &keypair).unwrap();
+        assert_eq!(signature.len(), 128);
+        assert!(db.verify_backup_signature("b1", &public).unwrap());
+
+        let other = signing::public_key(&signing::generate_keypair()).unwrap();
+        assert!(!db.verify_backup_signature("b1", &other).unwrap());
+        assert!(matches!(
+            db.verify_backup_signature("missing", &public),
+            Err(EnigmaError::BackupNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn backup_signature_detects_tampering() {
+        let keypair = signing::generate_keypair();
+        let public = signing::public_key(&keypair).unwrap();
+        let tampered = [
+            "UPDATE backups SET source_path='/elsewhere'",
+            "UPDATE backups SET total_bytes=total_bytes+1",
+            "UPDATE backups SET merkle_root=NULL",
+            "UPDATE backups SET signature=NULL",
+        ];
+        for statement in tampered {
+            let db = ManifestDb::open_in_memory().unwrap();
+            merkle_backup(&db, 3);
+            db.sign_backup("b1", &keypair).unwrap();
+            db.conn().execute(statement, []).unwrap();
+            assert!(
+                !db.verify_backup_signature("b1", &public).unwrap(),
+                "not detected: {statement}"
+            );
+        }
+
+        // A flipped signature bit
+        let db = ManifestDb::open_in_memory().unwrap();
+        merkle_backup(&db, 3);
+        let mut signature = hex::decode(db.sign_backup("b1", &keypair).unwrap()).unwrap();
+        signature[0] ^= 0x01;
+        db.conn()
+            .execute(
+                "UPDATE backups SET signature=?1",
+                params![hex::encode(signature)],
+            )
+            .unwrap();
+        assert!(!db.verify_backup_signature("b1", &public).unwrap());
 
-        // Insert same chunk (dedup)
-        let is_new = db
-            .insert_or_dedup_chunk(
-                "deadbeef",
-                &[0u8; 12],
-                "key-1",
-                pid,
-                "enigma/chunks/de/ad/deadbeef",
-                1024,
-                1040,
-                None,
+        // A swapped chunk changes the Merkle root once it is rebuilt
+        let db = ManifestDb::open_in_memory().unwrap();
+        merkle_backup(&db, 3);
+        db.sign_backup("b1", &keypair).unwrap();
+        db.conn()
+            .execute(
+                "UPDATE file_chunks SET chunk_hash=?1 WHERE chunk_hash=?2",
+                params![format!("{:064x}", 2), format!("{:064x}", 1)],
             )
             .unwrap();
-        assert!(!is_new);
-
-        // Map file -> chunk
-        db.insert_file_chunk(file_id, "deadbeef", 0, 0).unwrap();
+        db.build_backup_merkle_root("b1").unwrap();
+        assert!(!db.verify_backup_signature("b1", &public).unwrap());
+    }
 
-        // Complete backup
-        db.complete_backup(backup_id, 1, 1024, 1, 1).unwrap();
+    #[test]
+    fn sign_backup_requires_merkle_root() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        db.create_backup("b1", "/src").unwrap();
+        let keypair = signing::generate_keypair();
+        assert!(matches!(
+            db.sign_backup("b1", &keypair),
+            Err(EnigmaError::Integrity(_))
+        ));
+    }
 
-        // Verify
-        let backup = db.get_backup(backup_id).unwrap();
-        assert_eq!(backup.status, BackupStatus::Completed);
-        assert_eq!(backup.total_files, 1);
+    #[test]
+    fn chunk_hmac_tag_follows_storage_key() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let pid = db
+            .insert_provider("test", ProviderType::Local, "/tmp", None, 1)
+            .unwrap();
+        db.insert_or_dedup_chunk("aaa", &[0; 12], "k1", pid, "old", 100, 116, None)
+            .unwrap();
+        assert_eq!(db.get_chunk_hmac_tag("aaa").unwrap(), None);
+        assert_eq!(db.get_chunk_hmac_tag("missing").unwrap(), None);
 
-        let files = db.list_backup_files(backup_id).unwrap();
-        assert_eq!(files.len(), 1);
+        db.set_chunk_hmac_tag("aaa", &[9; 32]).unwrap();
+        assert_eq!(db.get_chunk_hmac_tag("aaa").unwrap(), Some([9; 32]));
 
-        let chunks = db.get_file_chunks(file_id).unwrap();
-        assert_eq!(chunks.len(), 1);
-        assert_eq!(chunks[0].0, "deadbeef");
+        // The tag signs the storage key; a moved chunk has none
+        db.rename_chunk_storage_key("aaa", "old", "new").unwrap();
+        assert_eq!(db.get_chunk_hmac_tag("aaa").unwrap(), None);
     }
 
     #[test]
-    fn list_backups_ordered() {
+    fn chunk_access_is_recorded() {
         let db = ManifestDb::open_in_memory().unwrap();
-        db.create_backup("id-1", "/path1").unwrap();
-        db.create_backup("id-2", "/path2").unwrap();
+        let pid = db
+            .insert_provider("test", ProviderType::Local, "/tmp", None, 1)
+            .unwrap();
+        db.insert_or_dedup_chunk("aaa", &[0; 12], "k1", pid, "key", 100, 116, None)
+            .unwrap();
+        assert_eq!(db.get_chunk_last_accessed("aaa").unwrap(), None);
 
-        let backups = db.list_backups().unwrap();
-        assert_eq!(backups.len(), 2);
+        db.record_chunk_access("aaa").unwrap();
+        let accessed = db.get_chunk_last_accessed("aaa").unwrap().unwrap();
+        assert!(accessed.starts_with(&chrono::Utc::now().format("%Y-").to_string()));
+        assert_eq!(db.get_chunk_last_accessed("missing").unwrap(), None);
     }
 
     #[test]
@@ -1137,6 +3650,23 @@ mod tests {
         assert!(replicas.is_empty());
     }
 
+    #[test]
+    fn add_chunk_ref_requires_live_chunk() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let pid = db
+            .insert_provider("p1", ProviderType::Local, "/a", None, 1)
+            .unwrap();
+        db.insert_or_dedup_chunk("hash5", &[3; 12], "k1", pid, "skey5", 500, 516, None)
+            .unwrap();
+
+        db.add_chunk_ref("hash5").unwrap();
+        assert!(db.decrement_chunk_ref("hash5").unwrap().is_empty());
+        assert_eq!(db.decrement_chunk_ref("hash5").unwrap().len(), 1);
+
+        assert!(db.add_chunk_ref("hash5").is_err());
+        assert!(db.add_chunk_ref("missing").is_err());
+    }
+
     #[test]
     fn cascade_delete_chunk_replicas() {
         let db = ManifestDb::open_in_memory().unwrap();
@@ -1158,4 +3688,664 @@ mod tests {
         let replicas = db.get_chunk_replicas("hash5").unwrap();
         assert!(replicas.is_empty());
     }
+
+    #[test]
+    fn key_rotation_history() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        db.record_key_rotation("k2", "k1", "schedule", false)
+            .unwrap();
+        db.record_key_rotation("k3", "k2", "max_key_age", true)
+            .unwrap();
+
+        let history = db.list_key_rotations().unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(
+            (history[0].key_id.as_str(), history[0].old_key_id.as_str()),
+            ("k3", "k2")
+        );
+        assert_eq!(history[0].triggered_by, "max_key_age");
+        assert!(history[0].rotated_at.ends_with('Z'));
+        assert!(!history[1].reencrypt_pending);
+
+        assert!(!db.key_reencryption_pending("k2").unwrap());
+        assert!(db.key_reencryption_pending("k3").unwrap());
+        db.finish_key_reencryption("k3").unwrap();
+        assert!(!db.key_reencryption_pending("k3").unwrap());
+        assert!(!db.list_key_rotations().unwrap()[0].reencrypt_pending);
+    }
+
+    #[test]
+    fn chunk_rekey_updates_nonce_and_key() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let pid = db
+            .insert_provider("local", ProviderType::Local, "/tmp", None, 1)
+            .unwrap();
+        for hash in ["aa", "bb", "cc"] {
+            db.insert_or_dedup_chunk(hash, &[0u8; 12], "old", pid, hash, 10, 26, None)
+                .unwrap();
+        }
+
+        let page = db.chunk_hashes_with_key("old", "", 2).unwrap();
+        assert_eq!(page, vec!["aa", "bb"]);
+        let page = db.chunk_hashes_with_key("old", "bb", 2).unwrap();
+        assert_eq!(page, vec!["cc"]);
+
+        db.begin_chunk_rekey("aa", &[1u8; 12], "new").unwrap();
+        assert_eq!(
+            db.get_chunk_rekey("aa").unwrap(),
+            Some((vec![1u8; 12], "new".to_string()))
+        );
+        db.set_chunk_hmac_tag("aa", &[2u8; 32]).unwrap();
+        db.finish_chunk_rekey("aa", &[1u8; 12], "new", Some(&[3u8; 32]))
+            .unwrap();
+        assert_eq!(db.get_chunk_rekey("aa").unwrap(), None);
+        assert_eq!(db.get_chunk_hmac_tag("aa").unwrap(), Some([3u8; 32]));
+
+        let (nonce, key_id, ..) = db.get_chunk_info("aa").unwrap().unwrap();
+        assert_eq!((nonce, key_id.as_str()), (vec![1u8; 12], "new"));
+        assert_eq!(
+            db.chunk_hashes_with_key("old", "", 10).unwrap(),
+            vec!["bb", "cc"]
+        );
+        assert_eq!(
+            db.chunk_key_ids().unwrap(),
+            vec![("new".to_string(), 1), ("old".to_string(), 2)]
+        );
+    }
+
+    #[test]
+    fn backup_file_mtimes_skip_missing() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        db.create_backup("b1", "/src").unwrap();
+        let with = db
+            .insert_backup_file("b1", "a.txt", 1, Some("1700000000"), "h1", 1)
+            .unwrap();
+        db.insert_backup_file("b1", "b.txt", 1, None, "h2", 1)
+            .unwrap();
+
+        let mtimes = db.list_backup_file_mtimes("b1").unwrap();
+        assert_eq!(mtimes.len(), 1);
+        assert_eq!(mtimes[&with], "1700000000");
+    }
+
+    #[test]
+    fn pending_object_commit_and_rollback() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let pid = db
+            .insert_provider("test", ProviderType::Local, "/tmp", None, 1)
+            .unwrap();
+        let ns = db.create_namespace("bucket").unwrap();
+        for hash in ["aa", "bb"] {
+            db.insert_or_dedup_chunk(hash, &[0; 12], "k1", pid, hash, 10, 26, None)
+                .unwrap();
+        }
+
+        // Committed: the object gets the chunks in order, with offsets
+        let pending = db.insert_object_pending(ns, "obj").unwrap();
+        db.add_pending_object_chunk(pending, 0, "aa", 10).unwrap();
+        db.add_pending_object_chunk(pending, 1, "bb", 4).unwrap();
+        assert!(db.get_object(ns, "obj").unwrap().is_none());
+        let object_id = db
+            .commit_pending_object(pending, 14, "etag", None, "k1")
+            .unwrap();
+        let (id, size, etag, _, chunk_count, ..) = db.get_object(ns, "obj").unwrap().unwrap();
+        assert_eq!(
+            (id, size, etag.as_str(), chunk_count),
+            (object_id, 14, "etag", 2)
+        );
+        assert_eq!(
+            db.get_object_chunks(object_id).unwrap(),
+            vec![("aa".to_string(), 0, 0), ("bb".to_string(), 1, 10)]
+        );
+
+        // Rolled back: the chunk it alone referenced is released
+        db.insert_or_dedup_chunk("cc", &[0; 12], "k1", pid, "cc", 10, 26, None)
+            .unwrap();
+        db.insert_or_dedup_chunk("aa", &[0; 12], "k1", pid, "aa", 10, 26, None)
+            .unwrap();
+        let pending = db.insert_object_pending(ns, "other").unwrap();
+        db.add_pending_object_chunk(pending, 0, "aa", 10).unwrap();
+        db.add_pending_object_chunk(pending, 1, "cc", 10).unwrap();
+        let released = db.rollback_pending_object(pending).unwrap();
+        assert_eq!(released, vec![(pid, "cc".to_string())]);
+        assert!(db.get_object(ns, "other").unwrap().is_none());
+        assert!(db.get_chunk_info("aa").unwrap().is_some());
+        assert!(db.get_chunk_info("cc").unwrap().is_none());
+    }
+
+    #[test]
+    fn multipart_part_chunks_complete_or_release() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let pid = db
+            .insert_provider("test", ProviderType::Local, "/tmp", None, 1)
+            .unwrap();
+        let ns = db.create_namespace("bucket").unwrap();
+        for (hash, size) in [("aa", 10), ("bb", 4)] {
+            db.insert_or_dedup_chunk(hash, &[0; 12], "k1", pid, hash, size, 26, None)
+                .unwrap();
+        }
+
+        // Completed: the object gets the chunks in the order they were stored
+        db.create_multipart_upload("u1", ns, "obj").unwrap();
+        db.insert_multipart_part_chunk("u1", 1, "aa").unwrap();
+        db.insert_multipart_part_chunk("u1", 2, "bb").unwrap();
+        let object_id = db
+            .insert_object(ns, "obj", 14, "etag", None, 2, "k1")
+            .unwrap();
+        db.complete_multipart_upload("u1", object_id).unwrap();
+        assert_eq!(
+            db.get_object_chunks(object_id).unwrap(),
+            vec![("aa".to_string(), 0, 0), ("bb".to_string(), 1, 10)]
+        );
+        assert!(db.get_multipart_part_chunks("u1").unwrap().is_empty());
+        assert!(db.list_multipart_uploads(ns).unwrap().is_empty());
+
+        // Aborted: the chunk only the upload referenced is released
+        db.insert_or_dedup_chunk("aa", &[0; 12], "k1", pid, "aa", 10, 26, None)
+            .unwrap();
+        db.insert_or_dedup_chunk("cc", &[0; 12], "k1", pid, "cc", 10, 26, None)
+            .unwrap();
+        db.create_multipart_upload("u2", ns, "other").unwrap();
+        db.insert_multipart_part_chunk("u2", 1, "aa").unwrap();
+        db.insert_multipart_part_chunk("u2", 1, "cc").unwrap();
+        assert_eq!(
+            db.get_multipart_part_chunks("u2").unwrap(),
+            vec![(1, "aa".to_string()), (1, "cc".to_string())]
+        );
+        let released = db.abort_multipart_upload("u2").unwrap();
+        assert_eq!(released, vec![(pid, "cc".to_string())]);
+        assert!(db.get_multipart_part_chunks("u2").unwrap().is_empty());
+        assert!(db.get_chunk_info("aa").unwrap().is_some());
+        assert!(db.get_chunk_info("cc").unwrap().is_none());
+    }
+
+    #[test]
+    fn expired_multipart_uploads() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let ns = db.create_namespace("bucket").unwrap();
+        db.create_multipart_upload("u1", ns, "a.bin").unwrap();
+        db.conn
+            .execute(
+                "UPDATE multipart_uploads SET created_at = datetime('now', '-2 days') WHERE id='u1'",
+                [],
+            )
+            .unwrap();
+        db.create_multipart_upload("u2", ns, "b.bin").unwrap();
+
+        assert_eq!(db.list_expired_multipart_uploads(24).unwrap(), vec!["u1"]);
+        assert_eq!(
+            db.list_expired_multipart_uploads(72).unwrap(),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn multipart_cursor_reads_spilled_and_inline_parts() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db = ManifestDb::open_in_memory().unwrap();
+        let ns = db.create_namespace("bucket").unwrap();
+        db.create_multipart_upload("u1", ns, "a.bin").unwrap();
+
+        let spilled = tmp.path().join("part-2");
+        std::fs::write(&spilled, b"second").unwrap();
+        db.insert_multipart_part_file("u1", 2, spilled.to_str().unwrap(), 6, "e2")
+            .unwrap();
+        db.insert_multipart_part("u1", 1, b"first", "e1").unwrap();
+
+        let mut cursor = db.get_multipart_parts_cursor("u1").unwrap();
+        assert_eq!(cursor.len(), 2);
+        assert_eq!(cursor.remaining_size(), 11);
+        let first = cursor.next_part(&db).unwrap().unwrap();
+        assert_eq!(
+            (first.part_number, first.data.as_slice()),
+            (1, &b"first"[..])
+        );
+        let second = cursor.next_part(&db).unwrap().unwrap();
+        assert_eq!((second.part_number, second.etag.as_str()), (2, "e2"));
+        assert_eq!(second.data, b"second");
+        assert!(cursor.next_part(&db).unwrap().is_none());
+
+        // Replacing a spilled part removes its old file; aborting removes the rest
+        let replacement = tmp.path().join("part-2b");
+        std::fs::write(&replacement, b"again").unwrap();
+        db.insert_multipart_part_file("u1", 2, replacement.to_str().unwrap(), 5, "e2b")
+            .unwrap();
+        assert!(!spilled.exists());
+        db.abort_multipart_upload("u1").unwrap();
+        assert!(!replacement.exists());
+        assert!(db.get_multipart_parts_cursor("u1").unwrap().is_empty());
+    }
+
+    #[test]
+    fn namespace_soft_delete_and_restore() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let ns = db.create_namespace("bucket").unwrap();
+        db.insert_object(ns, "a.txt", 1, "e1", None, 0, "k1")
+            .unwrap();
+
+        let created_at = db.namespace_created_at("bucket").unwrap().unwrap();
+        assert_eq!(created_at.len(), "2024-01-01 00:00:00".len());
+
+        assert!(db.delete_namespace("bucket").unwrap());
+        assert!(!db.delete_namespace("bucket").unwrap());
+        assert_eq!(db.get_namespace_id("bucket").unwrap(), None);
+        assert_eq!(db.namespace_created_at("bucket").unwrap(), None);
+        assert!(db.list_namespaces().unwrap().is_empty());
+        let all = db.list_namespaces_including_deleted().unwrap();
+        assert_eq!(all.len(), 1);
+        assert!(all[0].3.is_some());
+
+        assert!(db.restore_namespace("bucket").unwrap());
+        assert!(!db.restore_namespace("bucket").unwrap());
+        assert_eq!(db.get_namespace_id("bucket").unwrap(), Some(ns));
+        assert!(db.get_object(ns, "a.txt").unwrap().is_some());
+    }
+
+    #[test]
+    fn expired_namespace_purged_with_its_chunks() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let pid = db
+            .insert_provider("test", ProviderType::Local, "/tmp", None, 1)
+            .unwrap();
+        let ns = db.create_namespace("old").unwrap();
+        db.create_namespace("recent").unwrap();
+        db.insert_or_dedup_chunk("aaa", &[0; 12], "k1", pid, "key-a", 100, 116, None)
+            .unwrap();
+        let object_id = db
+            .insert_object(ns, "a.txt", 100, "e1", None, 1, "k1")
+            .unwrap();
+        db.insert_object_chunk(object_id, "aaa", 0, 0).unwrap();
+        db.create_multipart_upload("u1", ns, "b.bin").unwrap();
+
+        db.delete_namespace("old").unwrap();
+        db.delete_namespace("recent").unwrap();
+        db.conn
+            .execute(
+                "UPDATE namespaces SET deleted_at = datetime('now', '-8 days') WHERE name='old'",
+                [],
+            )
+            .unwrap();
+        assert_eq!(db.list_expired_namespaces(7).unwrap(), vec!["old"]);
+
+        let to_delete = db.delete_namespace_permanently("old").unwrap().unwrap();
+        assert_eq!(to_delete, vec![(pid, "key-a".to_string())]);
+        assert_eq!(db.list_namespaces_including_deleted().unwrap().len(), 1);
+        assert!(db.delete_namespace_permanently("old").unwrap().is_none());
+        assert!(db.list_expired_namespaces(7).unwrap().is_empty());
+    }
+
+    #[test]
+    fn remove_missing_chunk_drops_references() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let pid = db
+            .insert_provider("local", ProviderType::Local, "/tmp/enigma", None, 1)
+            .unwrap();
+        db.create_backup("b1", "/src").unwrap();
+        let file_id = db
+            .insert_backup_file("b1", "a.txt", 2, None, "h", 2)
+            .unwrap();
+        for (idx, hash) in ["aa", "bb"].iter().enumerate() {
+            db.insert_or_dedup_chunk(hash, &[0u8; 12], "k", pid, hash, 1, 1, None)
+                .unwrap();
+            db.insert_file_chunk(file_id, hash, idx as u32, idx as u64)
+                .unwrap();
+        }
+        db.complete_backup("b1", 1, 2, 2, 0).unwrap();
+        let ns = db.create_namespace("bucket").unwrap();
+        let object_id = db.insert_object(ns, "obj", 1, "e", None, 1, "k").unwrap();
+        db.insert_object_chunk(object_id, "aa", 0, 0).unwrap();
+
+        assert_eq!(db.chunk_hashes("", 10).unwrap(), vec!["aa", "bb"]);
+        assert_eq!(db.remove_missing_chunk("aa").unwrap(), (1, 1));
+
+        assert_eq!(db.chunk_hashes("", 10).unwrap(), vec!["bb"]);
+        assert_eq!(db.get_file_chunks(file_id).unwrap().len(), 1);
+        assert!(db.get_object_chunks(object_id).unwrap().is_empty());
+        assert_eq!(db.get_backup("b1").unwrap().total_chunks, 1);
+        assert!(db.verify_merkle_path("b1", 0).unwrap());
+    }
+
+    #[test]
+    fn dedup_revives_zero_ref_chunk_with_new_encryption() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let pid = db
+            .insert_provider("test", ProviderType::Local, "/tmp", None, 1)
+            .unwrap();
+        db.insert_or_dedup_chunk("aaa", &[1; 12], "k1", pid, "key", 100, 116, None)
+            .unwrap();
+        assert!(
+            !db.insert_or_dedup_chunk("aaa", &[2; 12], "k2", pid, "key", 100, 116, None)
+                .unwrap()
+        );
+        assert_eq!(db.get_chunk_info("aaa").unwrap().unwrap().0, vec![1; 12]);
+
+        db.conn()
+            .execute("UPDATE chunks SET ref_count = 0 WHERE hash = 'aaa'", [])
+            .unwrap();
+        assert!(
+            db.insert_or_dedup_chunk("aaa", &[3; 12], "k3", pid, "key2", 100, 120, Some(90))
+                .unwrap()
+        );
+        let (nonce, key_id, _, storage_key, size_encrypted, size_compressed) =
+            db.get_chunk_info("aaa").unwrap().unwrap();
+        assert_eq!(nonce, vec![3; 12]);
+        assert_eq!(key_id, "k3");
+        assert_eq!(storage_key, "key2");
+        assert_eq!(size_encrypted, 120);
+        assert_eq!(size_compressed, Some(90));
+    }
+
+    #[test]
+    fn gc_verify_progress_resumes_and_queues_reupload() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let pid = db
+            .insert_provider("test", ProviderType::Local, "/tmp", None, 1)
+            .unwrap();
+        for hash in ["aa", "bb", "cc"] {
+            db.insert_or_dedup_chunk(hash, &[0; 12], "k", pid, hash, 1, 1, None)
+                .unwrap();
+        }
+        db.insert_chunk_replicas("bb", &[(pid, "bb")]).unwrap();
+
+        db.record_gc_verify("aa", "ok", false).unwrap();
+        db.record_gc_verify("bb", "missing", true).unwrap();
+        // An interrupted pass picks up the chunks not checked yet
+        assert_eq!(db.unverified_chunk_hashes("", 10).unwrap(), vec!["cc"]);
+        assert!(db.get_chunk_replicas("bb").unwrap().is_empty());
+
+        db.record_gc_verify("cc", "mismatch", false).unwrap();
+        let (total, by_status, queued) = db.gc_verify_counts().unwrap();
+        assert_eq!(total, 3);
+        assert_eq!(by_status["ok"], 1);
+        assert_eq!(by_status["missing"], 1);
+        assert_eq!(by_status["mismatch"], 1);
+        assert_eq!(queued, 1);
+
+        db.clear_gc_verify_progress().unwrap();
+        assert_eq!(
+            db.unverified_chunk_hashes("", 10).unwrap(),
+            vec!["aa", "cc"]
+        );
+    }
+
+    #[test]
+    fn dedup_stats_for_backups_sharing_chunks() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let pid = db
+            .insert_provider("local", ProviderType::Local, "/tmp/enigma", None, 1)
+            .unwrap();
+
+        // b1 stores aa, bb, cc; b2 reuses aa and bb and adds dd
+        for (backup, hashes) in [("b1", ["aa", "bb", "cc"]), ("b2", ["aa", "bb", "dd"])] {
+            db.create_backup(backup, "/src").unwrap();
+            let file_id = db
+                .insert_backup_file(backup, "f.bin", 300, None, "h", 3)
+                .unwrap();
+            let mut dedup = 0;
+            for (idx, hash) in hashes.iter().enumerate() {
+                let is_new = db
+                    .insert_or_dedup_chunk(hash, &[0u8; 12], "k", pid, hash, 100, 116, None)
+                    .unwrap();
+                if !is_new {
+                    dedup += 1;
+                }
+                db.insert_file_chunk(file_id, hash, idx as u32, idx as u64 * 100)
+                    .unwrap();
+            }
+            db.complete_backup(backup, 1, 300, 3, dedup).unwrap();
+        }
+
+        let first = db.backup_dedup_stats("b1").unwrap();
+        assert_eq!(first.dedup_chunks, 0);
+        assert_eq!(first.dedup_ratio_percent, 0.0);
+
+        let second = db.backup_dedup_stats("b2").unwrap();
+        assert_eq!(second.total_chunks, 3);
+        assert_eq!(second.unique_chunks, 3);
+        assert_eq!(second.dedup_chunks, 2);
+        assert_eq!(second.total_bytes_logical, 300);
+        assert_eq!(second.total_bytes_stored, 3 * 116);
+        assert!((second.dedup_ratio_percent - 200.0 / 3.0).abs() < 1e-9);
+
+        let global = db.global_dedup_stats().unwrap();
+        assert_eq!(global.backups, 2);
+        assert_eq!(global.total_chunks, 6);
+        assert_eq!(global.dedup_chunks, 2);
+        assert_eq!(global.shared_chunks, 2);
+        assert_eq!(global.total_bytes_logical, 600);
+        assert_eq!(global.total_bytes_stored, 4 * 116);
+        assert!((global.dedup_ratio_percent - 100.0 / 3.0).abs() < 1e-9);
+
+        assert!(db.backup_dedup_stats("missing").is_err());
+    }
+
+    #[test]
+    fn cross_namespace_dedup_report_and_savings() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let pid = db
+            .insert_provider("p1", ProviderType::Local, "/tmp/p1", None, 1)
+            .unwrap();
+        assert!(db.cross_namespace_dedup_report().unwrap().is_empty());
+        assert_eq!(db.cross_namespace_savings_bytes().unwrap(), 0);
+
+        // photos and docs share aa and bb; cc is reused only within docs
+        let sizes = HashMap::from([("aa", 200), ("bb", 100), ("cc", 50), ("dd", 50)]);
+        let photos = db.create_namespace("photos").unwrap();
+        let docs = db.create_namespace("docs").unwrap();
+        for (ns, key, hashes) in [
+            (photos, "a.jpg", vec!["aa", "bb", "dd"]),
+            (photos, "b.jpg", vec!["aa"]),
+            (docs, "x.pdf", vec!["bb", "aa", "cc"]),
+            (docs, "y.pdf", vec!["cc"]),
+        ] {
+            let oid = db
+                .insert_object(ns, key, 1, "e", None, hashes.len() as u32, "k")
+                .unwrap();
+            for (idx, hash) in hashes.iter().enumerate() {
+                let size = sizes[hash];
+                db.insert_or_dedup_chunk(hash, &[0u8; 12], "k", pid, hash, size, size, None)
+                    .unwrap();
+                db.insert_object_chunk(oid, hash, idx as u32, idx as u64)
+                    .unwrap();
+            }
+        }
+
+        let report = db.cross_namespace_dedup_report().unwrap();
+        let hashes: Vec<&str> = report.iter().map(|e| e.chunk_hash.as_str()).collect();
+        assert_eq!(hashes, ["aa", "bb"]);
+        assert_eq!(report[0].namespaces, ["docs", "photos"]);
+        assert_eq!(report[0].size_bytes, 200);
+        assert_eq!(report[0].ref_count, 3);
+        assert_eq!(report[1].ref_count, 2);
+        assert_eq!(db.cross_namespace_savings_bytes().unwrap(), 300);
+
+        let page = db.cross_namespace_dedup_page(1, 1).unwrap();
+        assert_eq!(page, report[1..]);
+        assert!(db.cross_namespace_dedup_page(10, 2).unwrap().is_empty());
+    }
+
+    #[test]
+    fn chunk_size_histogram_and_compression() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let p1 = db
+            .insert_provider("p1", ProviderType::Local, "/tmp/p1", None, 1)
+            .unwrap();
+        let p2 = db
+            .insert_provider("p2", ProviderType::Local, "/tmp/p2", None, 1)
+            .unwrap();
+        db.insert_provider("empty", ProviderType::Local, "/tmp/p3", None, 1)
+            .unwrap();
+
+        let mb = 1024 * 1024;
+        let chunks: [(&str, i64, u64, Option<u64>); 6] = [
+            ("a", p1, 1000, Some(500)),
+            ("b", p1, 65_535, None),
+            ("c", p2, 65_536, Some(16_384)),
+            ("d", p2, 200_000, None),
+            ("e", p1, 32 * mb, None),
+            ("f", p2, 40 * mb, None),
+        ];
+        for (hash, pid, size, compressed) in chunks {
+            db.insert_or_dedup_chunk(hash, &[0u8; 12], "k", pid, hash, size, size, compressed)
+                .unwrap();
+        }
+
+        assert_eq!(
+            db.chunk_size_histogram(None).unwrap(),
+            vec![(0, 2), (64, 1), (192, 1), (32 * 1024 - 64, 2)]
+        );
+
+        // Backup b1 references a and c, c twice
+        db.create_backup("b1", "/src").unwrap();
+        let file_id = db
+            .insert_backup_file("b1", "f.bin", 0, None, "h", 3)
+            .unwrap();
+        for (idx, hash) in ["a", "c", "c"].iter().enumerate() {
+            db.insert_file_chunk(file_id, hash, idx as u32, 0).unwrap();
+        }
+        assert_eq!(
+            db.chunk_size_histogram(Some("b1")).unwrap(),
+            vec![(0, 1), (64, 1)]
+        );
+        assert!(db.chunk_size_histogram(Some("none")).unwrap().is_empty());
+
+        let ratio = db.compression_ratio(Some("b1")).unwrap();
+        assert!(
+            (ratio - (1.0 - 16_884.0 / 66_536.0)).abs() < 1e-9,
+            "{ratio}"
+        );
+        assert!(db.compression_ratio(None).unwrap() > 0.0);
+        assert_eq!(db.compression_ratio(Some("none")).unwrap(), 0.0);
+
+        let per_provider = db.provider_chunk_sizes(None).unwrap();
+        assert_eq!(per_provider.len(), 3);
+        assert_eq!(
+            per_provider[0],
+            (p1, "p1".to_string(), 3, (1000 + 65_535 + 32 * mb) / 3)
+        );
+        assert_eq!(per_provider[1].2, 3);
+        assert_eq!(per_provider[2].2, 0);
+        assert_eq!(per_provider[2].3, 0);
+
+        let in_backup = db.provider_chunk_sizes(Some("b1")).unwrap();
+        assert_eq!(in_backup[0].2, 1);
+        assert_eq!(in_backup[0].3, 1000);
+        assert_eq!(in_backup[1].2, 1);
+        assert_eq!(in_backup[1].3, 65_536);
+    }
+
+    #[test]
+    fn second_connection_reads_while_the_first_writes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("manifest.db");
+        // The proxy and the web server each open the manifest
+        let proxy = ManifestDb::open(&path).unwrap();
+        let web = ManifestDb::open(&path).unwrap();
+        let journal_mode: String = web
+            .conn()
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(journal_mode, "wal");
+
+        let names = |db: &ManifestDb| -> Vec<String> {
+            db.list_namespaces()
+                .unwrap()
+                .into_iter()
+                .map(|(_, name, _)| name)
+                .collect()
+        };
+        proxy.create_namespace("first").unwrap();
+
+        // An open write transaction does not hold up the reader, which
+        // sees the last committed state
+        proxy.begin_transaction().unwrap();
+        proxy.create_namespace("second").unwrap();
+        assert_eq!(names(&web), vec!["first"]);
+        proxy.commit_transaction().unwrap();
+        assert_eq!(names(&web), vec!["first", "second"]);
+
+        // Automatic checkpoints are off, so the writes are still in the WAL
+        let (frames, checkpointed) = web.checkpoint(CheckpointMode::Passive).unwrap();
+        assert!(frames > 0);
+        assert_eq!(checkpointed, frames);
+        proxy.checkpoint(CheckpointMode::Truncate).unwrap();
+        let wal = std::fs::metadata(tmp.path().join("manifest.db-wal")).unwrap();
+        assert_eq!(wal.len(), 0);
+        assert_eq!(names(&proxy), vec!["first", "second"]);
+    }
+
+    #[test]
+    fn s3_access_log_filter_and_purge() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let ns = db.create_namespace("logs").unwrap();
+        assert_eq!(db.get_namespace_logging(ns).unwrap(), None);
+        db.set_namespace_logging(ns, Some(("audit", "logs/")))
+            .unwrap();
+        assert_eq!(
+            db.get_namespace_logging(ns).unwrap(),
+            Some(("audit".to_string(), "logs/".to_string()))
+        );
+        db.set_namespace_logging(ns, None).unwrap();
+        assert_eq!(db.get_namespace_logging(ns).unwrap(), None);
+
+        let entry = |bucket: &str, timestamp: &str| S3AccessLogEntry {
+            bucket: bucket.to_string(),
+            key: Some("k".to_string()),
+            operation: "GetObject".to_string(),
+            requester_ip: Some("10.0.0.1".to_string()),
+            timestamp: timestamp.to_string(),
+            status_code: 200,
+            bytes_transferred: 42,
+        };
+        let old = entry("a", "2000-01-01T00:00:00Z");
+        let recent = entry(
+            "a",
+            &chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+        );
+        let other = entry("b", &recent.timestamp);
+        for e in [&old, &recent, &other] {
+            db.insert_s3_log(e).unwrap();
+        }
+
+        let all = db
+            .list_s3_logs(None, "2000-01-01T00:00:00Z", "9999-12-31T23:59:59Z", 10)
+            .unwrap();
+        assert_eq!(all, vec![old.clone(), recent.clone(), other.clone()]);
+        let a = db
+            .list_s3_logs(
+                Some("a"),
+                "2001-01-01T00:00:00Z",
+                "9999-12-31T23:59:59Z",
+                10,
+            )
+            .unwrap();
+        assert_eq!(a, vec![recent.clone()]);
+
+        assert_eq!(db.purge_s3_logs(30).unwrap(), 1);
+        let left = db
+            .list_s3_logs(None, "2000-01-01T00:00:00Z", "9999-12-31T23:59:59Z", 1)
+            .unwrap();
+        assert_eq!(left, vec![recent]);
+    }
+
+    #[test]
+    fn webhook_queue_is_fifo() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let first = db
+            .enqueue_webhook("https://a.example/hook", "backup.started", "{}")
+            .unwrap();
+        db.enqueue_webhook("https://b.example/hook", "backup.completed", "{\"n\":1}")
+            .unwrap();
+
+        let pending = db.pending_webhooks(10).unwrap();
+        let events: Vec<&str> = pending.iter().map(|w| w.event.as_str()).collect();
+        assert_eq!(events, vec!["backup.started", "backup.completed"]);
+        assert_eq!(pending[1].body, "{\"n\":1}");
+        assert_eq!(db.pending_webhooks(1).unwrap().len(), 1);
+
+        db.delete_webhook(first).unwrap();
+        let pending = db.pending_webhooks(10).unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].url, "https://b.example/hook");
+    }
 }
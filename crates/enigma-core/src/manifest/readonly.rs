@@ -0,0 +1,208 @@
+//! Read-only manifest connections.
+//!
+//! In WAL mode any number of readers run alongside the single writer, each
+//! seeing the last committed state. [`ReadonlyManifestDb`] opens the
+//! manifest read-only and only exposes queries, so code serving lookups
+//! cannot end up queueing behind, or competing with, backup writes.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use rusqlite::{Connection, OpenFlags};
+
+use super::{CachedManifestDb, ManifestDb, QueryCache};
+use crate::error::Result;
+use crate::types::{
+    BackupRecord, CrossNamespaceDedupEntry, DedupStats, GlobalDedupStats, InventoryReport,
+    ProviderInfo, S3AccessLogEntry,
+};
+
+impl ManifestDb {
+    /// Open an existing manifest read-only. The schema is not migrated:
+    /// open the database once with [`ManifestDb::open`] first. Writes
+    /// through this connection fail with `SQLITE_READONLY`.
+    pub fn open_readonly(path: &Path) -> Result<Self> {
+        let conn = Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )?;
+        Ok(Self::from_connection(conn))
+    }
+}
+
+/// Generates [`ReadonlyManifestDb`] methods forwarding to the
+/// [`ManifestDb`] query of the same name.
+macro_rules! forward_queries {
+    ($($(#[$attr:meta])* fn $name:ident(&self $(, $arg:ident: $ty:ty)*) -> $ret:ty;)*) => {
+        $(
+            $(#[$attr])*
+            pub fn $name(&self $(, $arg: $ty)*) -> $ret {
+                self.db.db().$name($($arg),*)
+            }
+        )*
+    };
+}
+
+/// A read-only manifest connection exposing only queries.
+pub struct ReadonlyManifestDb {
+    db: CachedManifestDb,
+}
+
+impl ReadonlyManifestDb {
+    /// Open an existing manifest read-only; see [`ManifestDb::open_readonly`].
+    pub fn open(path: &Path) -> Result<Self> {
+        Self::open_with_cache(path, Arc::default())
+    }
+
+    /// [`Self::open`], reading the queries `cache` supports through it.
+    pub fn open_with_cache(path: &Path, cache: Arc<QueryCache>) -> Result<Self> {
+        Ok(Self {
+            db: CachedManifestDb::new(ManifestDb::open_readonly(path)?, cache),
+        })
+    }
+
+    /// See [`ManifestDb::chunk_stats`]; cached.
+    pub fn chunk_stats(&self) -> Result<(u64, u64)> {
+        self.db.chunk_stats()
+    }
+
+    forward_queries! {
+        /// See [`ManifestDb::list_providers`].
+        fn list_providers(&self) -> Result<Vec<ProviderInfo>>;
+        /// See [`ManifestDb::list_backups`].
+        fn list_backups(&self) -> Result<Vec<BackupRecord>>;
+        /// See [`ManifestDb::latest_backup`].
+        fn latest_backup(&self) -> Result<Option<BackupRecord>>;
+        /// See [`ManifestDb::backup_dedup_stats`].
+        fn backup_dedup_stats(&self, backup_id: &str) -> Result<DedupStats>;
+        /// See [`ManifestDb::global_dedup_stats`].
+        fn global_dedup_stats(&self) -> Result<GlobalDedupStats>;
+        /// See [`ManifestDb::cross_namespace_dedup_page`].
+        fn cross_namespace_dedup_page(
+            &self,
+            limit: u32,
+            offset: u32
+        ) -> Result<Vec<CrossNamespaceDedupEntry>>;
+        /// See [`ManifestDb::cross_namespace_savings_bytes`].
+        fn cross_namespace_savings_bytes(&self) -> Result<u64>;
+        /// See [`ManifestDb::chunk_size_histogram`].
+        fn chunk_size_histogram(&self, backup_id: Option<&str>) -> Result<Vec<(u64, u64)>>;
+        /// See [`ManifestDb::compression_ratio`].
+        fn compression_ratio(&self, backup_id: Option<&str>) -> Result<f64>;
+        /// See [`ManifestDb::provider_chunk_sizes`].
+        fn provider_chunk_sizes(
+            &self,
+            backup_id: Option<&str>
+        ) -> Result<Vec<(i64, String, u64, u64)>>;
+        /// See [`ManifestDb::list_s3_logs`].
+        fn list_s3_logs(
+            &self,
+            bucket: Option<&str>,
+            from: &str,
+            to: &str,
+            limit: u32
+        ) -> Result<Vec<S3AccessLogEntry>>;
+        /// See [`ManifestDb::list_namespaces`].
+        fn list_namespaces(&self) -> Result<Vec<(i64, String, String)>>;
+        /// See [`ManifestDb::get_namespace_id`].
+        fn get_namespace_id(&self, name: &str) -> Result<Option<i64>>;
+        /// See [`ManifestDb::get_namespace_tags`].
+        fn get_namespace_tags(&self, namespace_id: i64) -> Result<Vec<(String, String)>>;
+        /// See [`ManifestDb::list_objects`].
+        fn list_objects(
+            &self,
+            namespace_id: i64,
+            prefix: &str,
+            max_keys: u32,
+            start_after: &str
+        ) -> Result<Vec<(String, u64, String, String)>>;
+        /// See [`ManifestDb::count_objects_with_prefix`].
+        fn count_objects_with_prefix(&self, namespace_id: i64, prefix: &str) -> Result<u64>;
+        /// See [`ManifestDb::latest_inventory_report`].
+        fn latest_inventory_report(&self) -> Result<Option<InventoryReport>>;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Barrier};
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    #[test]
+    fn readonly_connection_rejects_writes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("manifest.db");
+        ManifestDb::open(&path)
+            .unwrap()
+            .create_namespace("a")
+            .unwrap();
+
+        let ro = ManifestDb::open_readonly(&path).unwrap();
+        assert_eq!(ro.list_namespaces().unwrap().len(), 1);
+        assert!(ro.create_namespace("b").is_err());
+    }
+
+    #[test]
+    fn readers_are_not_starved_by_a_writer() {
+        const READERS: usize = 20;
+        const WRITES: usize = 200;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("manifest.db");
+        let writer = ManifestDb::open(&path).unwrap();
+        writer.create_namespace("bucket").unwrap();
+
+        let start = Arc::new(Barrier::new(READERS + 1));
+        let writing = Arc::new(AtomicBool::new(true));
+        let readers: Vec<_> = (0..READERS)
+            .map(|_| {
+                let path = path.clone();
+                let start = start.clone();
+                let writing = writing.clone();
+                std::thread::spawn(move || {
+                    let db = ReadonlyManifestDb::open(&path).unwrap();
+                    start.wait();
+                    // Every read while the writer is busy must go through,
+                    // and none may see fewer objects than the one before
+                    let (mut reads, mut seen) = (0u32, 0u64);
+                    let mut slowest = Duration::ZERO;
+                    while writing.load(Ordering::Relaxed) || reads == 0 {
+                        let started = Instant::now();
+                        let ns = db.get_namespace_id("bucket").unwrap().unwrap();
+                        let count = db.count_objects_with_prefix(ns, "").unwrap();
+                        slowest = slowest.max(started.elapsed());
+                        assert!(count >= seen);
+                        seen = count;
+                        reads += 1;
+                    }
+                    (reads, slowest)
+                })
+            })
+            .collect();
+
+        start.wait();
+        let ns = writer.get_namespace_id("bucket").unwrap().unwrap();
+        for i in 0..WRITES {
+            writer.begin_transaction().unwrap();
+            writer
+                .insert_object(ns, &format!("key-{i}"), 1, "etag", None, 0, "key-1")
+                .unwrap();
+            writer.commit_transaction().unwrap();
+        }
+        writing.store(false, Ordering::Relaxed);
+
+        for reader in readers {
+            let (reads, slowest) = reader.join().unwrap();
+            assert!(reads > 0);
+            assert!(
+                slowest < Duration::from_secs(5),
+                "reader stalled for {slowest:?}"
+            );
+        }
+        let db = ReadonlyManifestDb::open(&path).unwrap();
+        assert_eq!(db.count_objects_with_prefix(ns, "").unwrap(), WRITES as u64);
+    }
+}
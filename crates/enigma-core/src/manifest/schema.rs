@@ -3,7 +3,8 @@ use rusqlite::Connection;
 use crate::error::Result;
 
 /// Current schema version.
-const CURRENT_VERSION: u32 = 1;
+#[cfg(test)]
+const CURRENT_VERSION: u32 = 22;
 
 /// Get the current schema version from the database.
 /// Returns 0 if the schema_version table doesn't exist yet.
@@ -43,6 +44,10 @@ fn set_schema_version(conn: &Connection, version: u32) -> Result<()> {
 }
 
 /// Run all migrations on the database.
+///
+/// Also switches the connection to WAL mode with automatic checkpoints
+/// turned off: long-running processes call [`super::ManifestDb::checkpoint`]
+/// on their own schedule instead.
 pub fn migrate(conn: &Connection) -> Result<()> {
     let version = get_schema_version(conn)?;
 
@@ -51,6 +56,8 @@ pub fn migrate(conn: &Connection) -> Result<()> {
         conn.execute_batch(
             "
             PRAGMA journal_mode=WAL;
+            PRAGMA synchronous=NORMAL;
+            PRAGMA wal_autocheckpoint=0;
             PRAGMA foreign_keys=ON;
 
             CREATE TABLE IF NOT EXISTS providers (
@@ -182,19 +189,374 @@ pub fn migrate(conn: &Connection) -> Result<()> {
             ",
         )?;
 
-        set_schema_version(conn, CURRENT_VERSION)?;
+        set_schema_version(conn, 1)?;
     } else {
         // Ensure PRAGMAs are set even for existing databases
         conn.execute_batch(
             "
             PRAGMA journal_mode=WAL;
+            PRAGMA synchronous=NORMAL;
+            PRAGMA wal_autocheckpoint=0;
             PRAGMA foreign_keys=ON;
             ",
         )?;
     }
 
+    if version < 2 {
+        // v2: user-defined key/value tags on backups.
+        conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS backup_tags (
+                backup_id   TEXT NOT NULL REFERENCES backups(id) ON DELETE CASCADE,
+                key         TEXT NOT NULL,
+                value       TEXT NOT NULL,
+                PRIMARY KEY (backup_id, key)
+            );
+            CREATE INDEX IF NOT EXISTS idx_backup_tags_kv ON backup_tags(key, value);
+            ",
+        )?;
+        set_schema_version(conn, 2)?;
+    }
+
+    if version < 3 {
+        // v3: S3 object tags (removed together with their object).
+        conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS object_tags (
+                object_id   INTEGER NOT NULL REFERENCES objects(id) ON DELETE CASCADE,
+                key         TEXT NOT NULL,
+                value       TEXT NOT NULL,
+                PRIMARY KEY (object_id, key)
+            );
+            ",
+        )?;
+        set_schema_version(conn, 3)?;
+    }
+
+    if version < 4 {
+        // v4: Merkle root over (file_path, chunk_hash) leaves of a completed backup.
+        // Ignore "duplicate column name" error for idempotency.
+        let _ = conn.execute("ALTER TABLE backups ADD COLUMN merkle_root TEXT", []);
+        set_schema_version(conn, 4)?;
+    }
+
+    if version < 5 {
+        // v5: S3 bucket versioning. A key can now have several versions, so the
+        // objects table is rebuilt without UNIQUE(namespace_id, key); the partial
+        // index keeps at most one latest version per key. version_id is NULL for
+        // the "null" version (written while versioning is off or suspended).
+        // Ignore "duplicate column name" errors for idempotency.
+        let _ = conn.execute(
+            "ALTER TABLE namespaces ADD COLUMN versioning_enabled INTEGER NOT NULL DEFAULT 0",
+            [],
+        );
+        let _ = conn.execute(
+            "ALTER TABLE namespaces ADD COLUMN versioning_suspended INTEGER NOT NULL DEFAULT 0",
+            [],
+        );
+        conn.execute_batch(
+            "
+            PRAGMA foreign_keys=OFF;
+            BEGIN;
+            CREATE TABLE objects_v5 (
+                id                  INTEGER PRIMARY KEY AUTOINCREMENT,
+                namespace_id        INTEGER NOT NULL REFERENCES namespaces(id),
+                key                 TEXT NOT NULL,
+                version_id          TEXT,
+                is_latest           INTEGER NOT NULL DEFAULT 1,
+                is_delete_marker    INTEGER NOT NULL DEFAULT 0,
+                size                INTEGER NOT NULL,
+                etag                TEXT NOT NULL,
+                content_type        TEXT,
+                chunk_count         INTEGER NOT NULL,
+                key_id              TEXT NOT NULL,
+                created_at          TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+            INSERT INTO objects_v5 (id, namespace_id, key, size, etag, content_type, chunk_count, key_id, created_at)
+                SELECT id, namespace_id, key, size, etag, content_type, chunk_count, key_id, created_at FROM objects;
+            DROP TABLE objects;
+            ALTER TABLE objects_v5 RENAME TO objects;
+            CREATE UNIQUE INDEX IF NOT EXISTS idx_objects_latest ON objects(namespace_id, key) WHERE is_latest=1;
+            CREATE UNIQUE INDEX IF NOT EXISTS idx_objects_version ON objects(namespace_id, key, version_id);
+            COMMIT;
+            PRAGMA foreign_keys=ON;
+            ",
+        )?;
+        set_schema_version(conn, 5)?;
+    }
+
+    if version < 6 {
+        // v6: chunk re-encryption after key rotation. A chunk_rekeys row holds
+        // the new nonce/key while its copies are being rewritten, so an
+        // interrupted run can tell old-key copies from new-key ones.
+        conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS chunk_rekeys (
+                chunk_hash  TEXT PRIMARY KEY REFERENCES chunks(hash) ON DELETE CASCADE,
+                nonce       BLOB NOT NULL,
+                key_id      TEXT NOT NULL,
+                created_at  TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+            CREATE INDEX IF NOT EXISTS idx_chunks_key_id ON chunks(key_id);
+            ",
+        )?;
+        set_schema_version(conn, 6)?;
+    }
+
+    if version < 7 {
+        // v7: S3 user-defined metadata (x-amz-meta-*), removed with its object.
+        conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS object_metadata (
+                object_id   INTEGER NOT NULL REFERENCES objects(id) ON DELETE CASCADE,
+                key         TEXT NOT NULL,
+                value       TEXT NOT NULL,
+                PRIMARY KEY (object_id, key)
+            );
+            ",
+        )?;
+        set_schema_version(conn, 7)?;
+    }
+
+    if version < 8 {
+        // v8: progress of `gc --verify`, so an interrupted pass can resume.
+        conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS gc_verify_progress (
+                chunk_hash  TEXT PRIMARY KEY,
+                status      TEXT NOT NULL,
+                verified_at TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+            ",
+        )?;
+        set_schema_version(conn, 8)?;
+    }
+
+    if version < 9 {
+        // v9: soft-deleted namespaces stay restorable until they are purged.
+        // Ignore "duplicate column name" error for idempotency.
+        let _ = conn.execute("ALTER TABLE namespaces ADD COLUMN deleted_at TEXT", []);
+        set_schema_version(conn, 9)?;
+    }
+
+    if version < 10 {
+        // v10: multipart part data can live in a spill file instead of the
+        // data BLOB (left empty), so large uploads stay out of the database.
+        // Ignore "duplicate column name" error for idempotency.
+        let _ = conn.execute("ALTER TABLE multipart_parts ADD COLUMN spill_path TEXT", []);
+        set_schema_version(conn, 10)?;
+    }
+
+    if version < 11 {
+        // v11: S3 object lock. A namespace with object lock enabled accepts
+        // retention settings; a locked object version cannot be removed
+        // before its retain-until date (RFC 3339, UTC).
+        // Ignore "duplicate column name" errors for idempotency.
+        let _ = conn.execute(
+            "ALTER TABLE namespaces ADD COLUMN object_lock_enabled INTEGER NOT NULL DEFAULT 0",
+            [],
+        );
+        let _ = conn.execute(
+            "ALTER TABLE objects ADD COLUMN object_lock_enabled INTEGER NOT NULL DEFAULT 0",
+            [],
+        );
+        let _ = conn.execute("ALTER TABLE objects ADD COLUMN object_lock_mode TEXT", []);
+        let _ = conn.execute(
+            "ALTER TABLE objects ADD COLUMN object_lock_retain_until TEXT",
+            [],
+        );
+        set_schema_version(conn, 11)?;
+    }
+
+    if version < 12 {
+        // v12: S3 server access logging. A namespace with a logging target
+        // records its requests in s3_access_log (timestamps RFC 3339, UTC).
+        // Ignore "duplicate column name" errors for idempotency.
+        let _ = conn.execute(
+            "ALTER TABLE namespaces ADD COLUMN logging_target_bucket TEXT",
+            [],
+        );
+        let _ = conn.execute(
+            "ALTER TABLE namespaces ADD COLUMN logging_target_prefix TEXT",
+            [],
+        );
+        conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS s3_access_log (
+                id                  INTEGER PRIMARY KEY AUTOINCREMENT,
+                bucket              TEXT NOT NULL,
+                key                 TEXT,
+                operation           TEXT NOT NULL,
+                requester_ip        TEXT,
+                timestamp           TEXT NOT NULL,
+                status_code         INTEGER NOT NULL,
+                bytes_transferred   INTEGER NOT NULL DEFAULT 0
+            );
+            CREATE INDEX IF NOT EXISTS idx_s3_access_log_time ON s3_access_log(timestamp);
+            CREATE INDEX IF NOT EXISTS idx_s3_access_log_bucket ON s3_access_log(bucket, timestamp);
+            ",
+        )?;
+        set_schema_version(conn, 12)?;
+    }
+
+    if version < 13 {
+        // v13: HMAC tag of each chunk's stored ciphertext, checked on
+        // download before decrypting. NULL for chunks stored before v13.
+        // Ignore "duplicate column name" errors for idempotency.
+        let _ = conn.execute("ALTER TABLE chunks ADD COLUMN hmac_tag BLOB", []);
+        set_schema_version(conn, 13)?;
+    }
+
+    if version < 14 {
+        // v14: PutObject bodies are chunked as they stream in. The chunks
+        // stored so far are tracked against a pending object, which becomes
+        // a real object once the body is complete or is rolled back.
+        conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS pending_objects (
+                id              INTEGER PRIMARY KEY AUTOINCREMENT,
+                namespace_id    INTEGER NOT NULL REFERENCES namespaces(id),
+                key             TEXT NOT NULL,
+                created_at      TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+            CREATE TABLE IF NOT EXISTS pending_object_chunks (
+                pending_id      INTEGER NOT NULL REFERENCES pending_objects(id),
+                chunk_index     INTEGER NOT NULL,
+                chunk_hash      TEXT NOT NULL,
+                size            INTEGER NOT NULL,
+                PRIMARY KEY (pending_id, chunk_index)
+            );
+            ",
+        )?;
+        set_schema_version(conn, 14)?;
+    }
+
+    if version < 15 {
+        // v15: hex Ed25519 signature over a backup's identity and Merkle
+        // root. NULL for unsigned backups.
+        let _ = conn.execute("ALTER TABLE backups ADD COLUMN signature TEXT", []);
+        set_schema_version(conn, 15)?;
+    }
+
+    if version < 16 {
+        // v16: S3 legal hold. A held object version cannot be removed,
+        // whatever its retention.
+        // Ignore "duplicate column name" errors for idempotency.
+        let _ = conn.execute(
+            "ALTER TABLE objects ADD COLUMN legal_hold INTEGER NOT NULL DEFAULT 0",
+            [],
+        );
+        set_schema_version(conn, 16)?;
+    }
+
+    if version < 17 {
+        // v17: webhook deliveries not yet acknowledged by their endpoint,
+        // so they survive a restart.
+        conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS webhook_queue (
+                id              INTEGER PRIMARY KEY AUTOINCREMENT,
+                url             TEXT NOT NULL,
+                event           TEXT NOT NULL,
+                body            TEXT NOT NULL,
+                created_at      TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+            ",
+        )?;
+        set_schema_version(conn, 17)?;
+    }
+
+    if version < 18 {
+        // v18: when each chunk was last downloaded, to tell cold chunks
+        // from hot ones when picking storage tiers. NULL until the first
+        // download.
+        // Ignore "duplicate column name" errors for idempotency.
+        let _ = conn.execute("ALTER TABLE chunks ADD COLUMN last_accessed_at TEXT", []);
+        set_schema_version(conn, 18)?;
+    }
+
+    if version < 19 {
+        // v19: S3 bucket tags (removed together with their namespace).
+        conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS namespace_tags (
+                namespace_id    INTEGER NOT NULL REFERENCES namespaces(id) ON DELETE CASCADE,
+                key             TEXT NOT NULL,
+                value           TEXT NOT NULL,
+                PRIMARY KEY (namespace_id, key)
+            );
+            ",
+        )?;
+        set_schema_version(conn, 19)?;
+    }
+
+    if version < 20 {
+        // v20: history of encryption key rotations. `reencrypt_pending`
+        // marks rotations whose chunks still have to be re-encrypted to the
+        // new key.
+        conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS key_rotation_history (
+                id                  INTEGER PRIMARY KEY AUTOINCREMENT,
+                key_id              TEXT NOT NULL,
+                old_key_id          TEXT NOT NULL,
+                rotated_at          TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
+                triggered_by        TEXT NOT NULL,
+                reencrypt_pending   INTEGER NOT NULL DEFAULT 0
+            );
+            ",
+        )?;
+        set_schema_version(conn, 20)?;
+    }
+
+    if version < 21 {
+        // v21: chunks stored while completing a multipart upload, tracked
+        // against the upload until they are handed over to the object, so
+        // an aborted upload can release them.
+        conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS multipart_part_chunks (
+                upload_id       TEXT NOT NULL REFERENCES multipart_uploads(id),
+                part_number     INTEGER NOT NULL,
+                chunk_hash      TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_multipart_part_chunks_upload ON multipart_part_chunks(upload_id);
+            ",
+        )?;
+        set_schema_version(conn, 21)?;
+    }
+
+    if version < 22 {
+        // v22: S3 bucket inventory configurations (removed together with
+        // their namespace) and the reports generated from them.
+        conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS namespace_inventory_config (
+                namespace_id                INTEGER NOT NULL REFERENCES namespaces(id) ON DELETE CASCADE,
+                id                          TEXT NOT NULL,
+                enabled                     INTEGER NOT NULL,
+                destination_bucket          TEXT NOT NULL,
+                destination_prefix          TEXT,
+                format                      TEXT NOT NULL,
+                frequency                   TEXT NOT NULL,
+                included_object_versions    TEXT NOT NULL,
+                filter_prefix               TEXT,
+                PRIMARY KEY (namespace_id, id)
+            );
+            CREATE TABLE IF NOT EXISTS inventory_reports (
+                id              INTEGER PRIMARY KEY AUTOINCREMENT,
+                key             TEXT NOT NULL,
+                size            INTEGER NOT NULL,
+                row_count       INTEGER NOT NULL,
+                generated_at    TEXT NOT NULL
+            );
+            ",
+        )?;
+        set_schema_version(conn, 22)?;
+    }
+
     // Future migrations would go here:
-    // if version < 2 { ... set_schema_version(conn, 2)?; }
+    // if version < 23 { ... set_schema_version(conn, 23)?; }
 
     Ok(())
 }
@@ -228,7 +590,18 @@ mod tests {
         assert!(tables.contains(&"object_chunks".to_string()));
         assert!(tables.contains(&"multipart_uploads".to_string()));
         assert!(tables.contains(&"multipart_parts".to_string()));
+        assert!(tables.contains(&"multipart_part_chunks".to_string()));
+        assert!(tables.contains(&"namespace_inventory_config".to_string()));
+        assert!(tables.contains(&"inventory_reports".to_string()));
         assert!(tables.contains(&"chunk_replicas".to_string()));
+        assert!(tables.contains(&"backup_tags".to_string()));
+        assert!(tables.contains(&"object_tags".to_string()));
+        assert!(tables.contains(&"namespace_tags".to_string()));
+        assert!(tables.contains(&"chunk_rekeys".to_string()));
+        assert!(tables.contains(&"object_metadata".to_string()));
+        assert!(tables.contains(&"gc_verify_progress".to_string()));
+        assert!(tables.contains(&"webhook_queue".to_string()));
+        assert!(tables.contains(&"key_rotation_history".to_string()));
         assert!(tables.contains(&"schema_version".to_string()));
     }
 
@@ -239,6 +612,26 @@ mod tests {
         migrate(&conn).unwrap(); // Should not fail
     }
 
+    #[test]
+    fn objects_allow_one_latest_version_per_key() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn).unwrap();
+        conn.execute("INSERT INTO namespaces (name) VALUES ('b')", [])
+            .unwrap();
+
+        let insert = |version: &str, latest: bool| {
+            conn.execute(
+                "INSERT INTO objects (namespace_id, key, version_id, is_latest, size, etag, chunk_count, key_id)
+                 VALUES (1, 'k', ?1, ?2, 0, 'e', 0, 'k1')",
+                rusqlite::params![version, latest],
+            )
+        };
+        insert("v1", false).unwrap();
+        insert("v2", true).unwrap();
+        assert!(insert("v3", true).is_err());
+        assert!(insert("v1", false).is_err());
+    }
+
     #[test]
     fn schema_version_is_set() {
         let conn = Connection::open_in_memory().unwrap();
@@ -0,0 +1,159 @@
+//! Binary Merkle tree over the (file_path, chunk_hash) pairs of a backup.
+//!
+//! Leaves and inner nodes are domain-separated (0x00 / 0x01 prefix). When a
+//! level has an odd number of nodes the last one is promoted unchanged, so a
+//! node at level `l` and index `j` always covers leaves `j << l ..`.
+
+use sha2::{Digest, Sha256};
+
+/// Which side the sibling sits on in an audit path step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// Hash of a leaf.
+pub fn leaf_hash(file_path: &str, chunk_hash: &str) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([0x00]);
+    hasher.update(file_path.as_bytes());
+    hasher.update([0x00]);
+    hasher.update(chunk_hash.as_bytes());
+    hasher.finalize().into()
+}
+
+/// Hash of an inner node.
+pub fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([0x01]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+fn next_level(level: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [l, r] => node_hash(l, r),
+            [single] => *single,
+            _ => unreachable!(),
+        })
+        .collect()
+}
+
+/// Root of the tree. An empty tree has the hash of the empty string as root.
+pub fn root(leaves: &[[u8; 32]]) -> [u8; 32] {
+    if leaves.is_empty() {
+        return Sha256::digest(b"").into();
+    }
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = next_level(&level);
+    }
+    level[0]
+}
+
+/// Sibling hashes from leaf `index` up to the root. Levels where the node
+/// was promoted have no sibling and are skipped.
+pub fn audit_path(leaves: &[[u8; 32]], index: usize) -> Vec<([u8; 32], Side)> {
+    let mut path = Vec::new();
+    let mut level = leaves.to_vec();
+    let mut idx = index;
+    while level.len() > 1 {
+        let sibling = idx ^ 1;
+        if sibling < level.len() {
+            let side = if sibling < idx {
+                Side::Left
+            } else {
+                Side::Right
+            };
+            path.push((level[sibling], side));
+        }
+        level = next_level(&level);
+        idx /= 2;
+    }
+    path
+}
+
+/// Fold `leaf` up through `path` and compare with `expected_root`.
+pub fn verify_path(leaf: [u8; 32], path: &[([u8; 32], Side)], expected_root: &[u8; 32]) -> bool {
+    let computed = path.iter().fold(leaf, |acc, (sibling, side)| match side {
+        Side::Left => node_hash(sibling, &acc),
+        Side::Right => node_hash(&acc, sibling),
+    });
+    computed == *expected_root
+}
+
+/// Leaf indices to spot-check for leaf `index`: the leaf itself plus the
+/// first leaf under each sibling on its audit path (O(log n) leaves).
+pub fn audit_sample(leaf_count: usize, index: usize) -> Vec<usize> {
+    let mut sample = vec![index];
+    let mut width = leaf_count;
+    let mut idx = index;
+    let mut level = 0u32;
+    while width > 1 {
+        let sibling = idx ^ 1;
+        if sibling < width {
+            sample.push(sibling << level);
+        }
+        width = width.div_ceil(2);
+        idx /= 2;
+        level += 1;
+    }
+    sample
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaves(n: usize) -> Vec<[u8; 32]> {
+        (0..n)
+            .map(|i| leaf_hash(&format!("file-{i}"), &format!("{i:064x}")))
+            .collect()
+    }
+
+    #[test]
+    fn every_leaf_verifies_against_root() {
+        for n in 1..=17 {
+            let leaves = leaves(n);
+            let root = root(&leaves);
+            for (i, leaf) in leaves.iter().enumerate() {
+                let path = audit_path(&leaves, i);
+                assert!(verify_path(*leaf, &path, &root), "n={n} i={i}");
+            }
+        }
+    }
+
+    #[test]
+    fn tampered_leaf_fails() {
+        let leaves = leaves(8);
+        let root = root(&leaves);
+        let path = audit_path(&leaves, 3);
+        let forged = leaf_hash("file-3", &format!("{:064x}", 999));
+        assert!(!verify_path(forged, &path, &root));
+    }
+
+    #[test]
+    fn audit_sample_is_logarithmic() {
+        let sample = audit_sample(1024, 517);
+        assert_eq!(sample.len(), 11);
+        assert!(sample.iter().all(|&i| i < 1024));
+
+        // Odd sizes: every sampled index stays in range
+        for n in 1..=33 {
+            for i in 0..n {
+                assert!(audit_sample(n, i).iter().all(|&s| s < n));
+            }
+        }
+    }
+
+    #[test]
+    fn single_leaf_root_is_leaf() {
+        let leaves = leaves(1);
+        assert_eq!(root(&leaves), leaves[0]);
+        assert!(audit_path(&leaves, 0).is_empty());
+    }
+}
@@ -0,0 +1,348 @@
+//! Email notifications about finished backups.
+//!
+//! [`send_backup_notification`] mails a [`BackupNotificationEvent`] over
+//! SMTP when the [`NotificationConfig`] asks for that kind of event.
+//! Pipelines call [`spawn_backup_notification`] so delivery never holds
+//! up the backup itself.
+//!
+//! The same SMTP server also carries password reset links, see
+//! [`send_password_reset`].
+
+use std::time::Duration;
+
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde::{Deserialize, Serialize};
+
+use crate::types::BackupStatus;
+
+/// SMTP settings and which backup events to mail.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationConfig {
+    pub smtp_host: String,
+    #[serde(default = "default_smtp_port")]
+    pub smtp_port: u16,
+    /// Require STARTTLS (default: true). Only disable for a relay on a
+    /// trusted network.
+    #[serde(default = "default_smtp_tls")]
+    pub smtp_tls: bool,
+    #[serde(default)]
+    pub smtp_user: Option<String>,
+    #[serde(default)]
+    pub smtp_pass: Option<String>,
+    /// Sender address, e.g. "Enigma <enigma@example.com>".
+    pub from: String,
+    /// Recipient addresses.
+    pub to: Vec<String>,
+    /// Mail completed backups (default: false).
+    #[serde(default)]
+    pub on_success: bool,
+    /// Mail failed backups (default: true).
+    #[serde(default = "default_on_failure")]
+    pub on_failure: bool,
+    /// Mail any backup that takes longer than this many seconds.
+    #[serde(default)]
+    pub on_slow: Option<u64>,
+}
+
+fn default_smtp_port() -> u16 {
+    587
+}
+fn default_smtp_tls() -> bool {
+    true
+}
+fn default_on_failure() -> bool {
+    true
+}
+
+/// Outcome of one backup, as reported in a notification.
+#[derive(Debug, Clone)]
+pub struct BackupNotificationEvent {
+    pub backup_id: String,
+    pub status: BackupStatus,
+    pub duration: Duration,
+    pub total_bytes: u64,
+    /// Fraction of chunks that were already stored (0.0 to 1.0).
+    pub dedup_ratio: f64,
+    pub error: Option<String>,
+}
+
+impl NotificationConfig {
+    fn is_slow(&self, event: &BackupNotificationEvent) -> bool {
+        self.on_slow
+            .is_some_and(|secs| event.duration > Duration::from_secs(secs))
+    }
+
+    /// Whether `event` is one of the kinds this config mails.
+    pub fn wants(&self, event: &BackupNotificationEvent) -> bool {
+        let by_status = match event.status {
+            BackupStatus::Completed => self.on_success,
+            BackupStatus::Failed => self.on_failure,
+            BackupStatus::InProgress => false,
+        };
+        by_status || self.is_slow(event)
+    }
+}
+
+/// The notification email for `event`.
+fn build_message(
+    config: &NotificationConfig,
+    event: &BackupNotificationEvent,
+) -> anyhow::Result<Message> {
+    let slow = config.is_slow(event);
+    let subject = format!(
+        "[enigma] Backup {} {}{}",
+        event.backup_id,
+        event.status,
+        if slow { " (slow)" } else { "" }
+    );
+
+    let mut body = format!(
+        "Backup:      {}\n\
+         Status:      {}\n\
+         Duration:    {:.1}s\n\
+         Total bytes: {}\n\
+         Dedup ratio: {:.1}%\n",
+        event.backup_id,
+        event.status,
+        event.duration.as_secs_f64(),
+        event.total_bytes,
+        event.dedup_ratio * 100.0,
+    );
+    if let Some(error) = &event.error {
+        body.push_str(&format!("Error:       {error}\n"));
+    }
+    if let (true, Some(secs)) = (slow, config.on_slow) {
+        body.push_str(&format!(
+            "\nThe backup took longer than the {secs}s threshold.\n"
+        ));
+    }
+
+    let mut builder = Message::builder()
+        .from(config.from.parse()?)
+        .subject(subject)
+        .header(ContentType::TEXT_PLAIN);
+    for to in &config.to {
+        builder = builder.to(to.parse()?);
+    }
+    Ok(builder.body(body)?)
+}
+
+/// Mail `event` through `transport` if `config` asks for it.
+pub async fn deliver<T>(
+    transport: &T,
+    config: &NotificationConfig,
+    event: &BackupNotificationEvent,
+) -> anyhow::Result<()>
+where
+    T: AsyncTransport + Sync,
+    T::Error: std::error::Error + Send + Sync + 'static,
+{
+    if !config.wants(event) {
+        return Ok(());
+    }
+    transport.send(build_message(config, event)?).await?;
+    Ok(())
+}
+
+/// Mail `event` over the SMTP server in `config`, if `config` asks for it.
+pub async fn send_backup_notification(
+    config: &NotificationConfig,
+    event: BackupNotificationEvent,
+) -> anyhow::Result<()> {
+    if !config.wants(&event) {
+        return Ok(());
+    }
+    deliver(&smtp_transport(config)?, config, &event).await
+}
+
+/// The password reset email pointing `to` at `reset_link`. Sent from the
+/// configured sender; the backup recipients do not get a copy.
+fn build_password_reset_message(
+    config: &NotificationConfig,
+    to: &str,
+    reset_link: &str,
+    ttl_minutes: u32,
+) -> anyhow::Result<Message> {
+    let body = format!(
+        "A password reset was requested for your Enigma account.\n\n\
+         Choose a new password here within {ttl_minutes} minutes:\n\n\
+         {reset_link}\n\n\
+         If you did not ask for this, ignore this email; your password\n\
+         stays unchanged.\n"
+    );
+    Ok(Message::builder()
+        .from(config.from.parse()?)
+        .to(to.parse()?)
+        .subject("[enigma] Password reset")
+        .header(ContentType::TEXT_PLAIN)
+        .body(body)?)
+}
+
+/// Mail a password reset link to `to` through `transport`.
+pub async fn deliver_password_reset<T>(
+    transport: &T,
+    config: &NotificationConfig,
+    to: &str,
+    reset_link: &str,
+    ttl_minutes: u32,
+) -> anyhow::Result<()>
+where
+    T: AsyncTransport + Sync,
+    T::Error: std::error::Error + Send + Sync + 'static,
+{
+    let message = build_password_reset_message(config, to, reset_link, ttl_minutes)?;
+    transport.send(message).await?;
+    Ok(())
+}
+
+/// Mail a password reset link to `to` over the SMTP server in `config`.
+pub async fn send_password_reset(
+    config: &NotificationConfig,
+    to: &str,
+    reset_link: &str,
+    ttl_minutes: u32,
+) -> anyhow::Result<()> {
+    let transport = smtp_transport(config)?;
+    deliver_password_reset(&transport, config, to, reset_link, ttl_minutes).await
+}
+
+fn smtp_transport(
+    config: &NotificationConfig,
+) -> anyhow::Result<AsyncSmtpTransport<Tokio1Executor>> {
+    let mut builder = if config.smtp_tls {
+        AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.smtp_host)?
+    } else {
+        AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.smtp_host)
+    }
+    .port(config.smtp_port);
+    if let Some(user) = &config.smtp_user {
+        let pass = config.smtp_pass.clone().unwrap_or_default();
+        builder = builder.credentials(Credentials::new(user.clone(), pass));
+    }
+    Ok(builder.build())
+}
+
+/// Send the notification for `event` on a background task, logging
+/// delivery failures. `None` when notifications are not configured.
+pub fn spawn_backup_notification(
+    config: Option<&NotificationConfig>,
+    event: BackupNotificationEvent,
+) -> Option<tokio::task::JoinHandle<()>> {
+    let config = config?.clone();
+    Some(tokio::spawn(async move {
+        let backup_id = event.backup_id.clone();
+        if let Err(e) = send_backup_notification(&config, event).await {
+            tracing::warn!("Failed to send notification for backup {backup_id}: {e}");
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lettre::transport::stub::AsyncStubTransport;
+
+    fn config() -> NotificationConfig {
+        NotificationConfig {
+            smtp_host: "smtp.example.com".to_string(),
+            smtp_port: 587,
+            smtp_tls: true,
+            smtp_user: None,
+            smtp_pass: None,
+            from: "Enigma <enigma@example.com>".to_string(),
+            to: vec![
+                "ops@example.com".to_string(),
+                "oncall@example.com".to_string(),
+            ],
+            on_success: true,
+            on_failure: true,
+            on_slow: None,
+        }
+    }
+
+    fn event(status: BackupStatus, secs: u64) -> BackupNotificationEvent {
+        BackupNotificationEvent {
+            backup_id: "0192-backup".to_string(),
+            status,
+            duration: Duration::from_secs(secs),
+            total_bytes: 123_456,
+            dedup_ratio: 0.25,
+            error: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn email_contains_every_field() {
+        let transport = AsyncStubTransport::new_ok();
+        let mut failed = event(BackupStatus::Failed, 42);
+        failed.error = Some("provider unreachable".to_string());
+        deliver(&transport, &config(), &failed).await.unwrap();
+
+        let messages = transport.messages().await;
+        assert_eq!(messages.len(), 1);
+        let (envelope, email) = &messages[0];
+        assert_eq!(envelope.to().len(), 2);
+        assert!(email.contains("Subject: [enigma] Backup 0192-backup failed"));
+        assert!(email.contains("Backup:      0192-backup"));
+        assert!(email.contains("Status:      failed"));
+        assert!(email.contains("Duration:    42.0s"));
+        assert!(email.contains("Total bytes: 123456"));
+        assert!(email.contains("Dedup ratio: 25.0%"));
+        assert!(email.contains("Error:       provider unreachable"));
+    }
+
+    #[tokio::test]
+    async fn only_requested_events_are_sent() {
+        let transport = AsyncStubTransport::new_ok();
+        let config = NotificationConfig {
+            on_success: false,
+            on_slow: Some(60),
+            ..config()
+        };
+
+        deliver(&transport, &config, &event(BackupStatus::Completed, 10))
+            .await
+            .unwrap();
+        assert!(transport.messages().await.is_empty());
+
+        deliver(&transport, &config, &event(BackupStatus::Completed, 61))
+            .await
+            .unwrap();
+        let messages = transport.messages().await;
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].1.contains("completed (slow)"));
+        assert!(messages[0].1.contains("longer than the 60s threshold"));
+    }
+
+    #[tokio::test]
+    async fn password_reset_goes_to_the_user_only() {
+        let transport = AsyncStubTransport::new_ok();
+        let link = "https://enigma.example.com/reset-password?token=abc123";
+        deliver_password_reset(&transport, &config(), "alice@example.com", link, 30)
+            .await
+            .unwrap();
+
+        let messages = transport.messages().await;
+        assert_eq!(messages.len(), 1);
+        let (envelope, email) = &messages[0];
+        assert_eq!(envelope.to().len(), 1);
+        assert_eq!(envelope.to()[0].to_string(), "alice@example.com");
+        assert!(email.contains("Subject: [enigma] Password reset"));
+        assert!(email.contains(link));
+        assert!(email.contains("within 30 minutes"));
+    }
+
+    #[tokio::test]
+    async fn invalid_address_is_an_error() {
+        let transport = AsyncStubTransport::new_ok();
+        let config = NotificationConfig {
+            from: "not an address".to_string(),
+            ..config()
+        };
+        let result = deliver(&transport, &config, &event(BackupStatus::Failed, 1)).await;
+        assert!(result.is_err());
+        assert!(transport.messages().await.is_empty());
+    }
+}
@@ -0,0 +1,81 @@
+//! Tag-set limits shared by S3 object and bucket tagging and the web API,
+//! following AWS.
+
+/// Most tags an object may carry.
+pub const MAX_OBJECT_TAGS: usize = 10;
+/// Most tags a bucket (namespace) may carry.
+pub const MAX_BUCKET_TAGS: usize = 50;
+/// Longest tag key, in characters.
+pub const MAX_KEY_LEN: usize = 128;
+/// Longest tag value, in characters.
+pub const MAX_VALUE_LEN: usize = 256;
+
+/// Why a tag set was refused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagSetError {
+    /// More than the allowed number of tags.
+    TooMany,
+    /// An empty key, or one longer than [`MAX_KEY_LEN`].
+    InvalidKey,
+    /// A value longer than [`MAX_VALUE_LEN`].
+    InvalidValue,
+    /// The same key given twice.
+    DuplicateKey,
+}
+
+/// Check a tag set of at most `max_tags` tags: unique non-empty keys of up
+/// to 128 characters, values of up to 256 characters.
+pub fn validate_tag_set(tags: &[(String, String)], max_tags: usize) -> Result<(), TagSetError> {
+    if tags.len() > max_tags {
+        return Err(TagSetError::TooMany);
+    }
+    for (i, (key, value)) in tags.iter().enumerate() {
+        if key.is_empty() || key.chars().count() > MAX_KEY_LEN {
+            return Err(TagSetError::InvalidKey);
+        }
+        if value.chars().count() > MAX_VALUE_LEN {
+            return Err(TagSetError::InvalidValue);
+        }
+        if tags[..i].iter().any(|(k, _)| k == key) {
+            return Err(TagSetError::DuplicateKey);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn tag_set_limits() {
+        assert_eq!(
+            validate_tag_set(&tags(&[("env", "prod"), ("team", "")]), 10),
+            Ok(())
+        );
+        assert_eq!(
+            validate_tag_set(&tags(&[("a", ""), ("b", "")]), 1),
+            Err(TagSetError::TooMany)
+        );
+        assert_eq!(
+            validate_tag_set(&tags(&[("", "x")]), 10),
+            Err(TagSetError::InvalidKey)
+        );
+        let long_value = "v".repeat(MAX_VALUE_LEN + 1);
+        assert_eq!(
+            validate_tag_set(&tags(&[("k", &long_value)]), 10),
+            Err(TagSetError::InvalidValue)
+        );
+        assert_eq!(
+            validate_tag_set(&tags(&[("k", "1"), ("k", "2")]), 10),
+            Err(TagSetError::DuplicateKey)
+        );
+    }
+}
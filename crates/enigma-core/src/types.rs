@@ -160,6 +160,15 @@ impl std::str::FromStr for BackupStatus {
     }
 }
 
+/// Versioning state of a bucket (namespace). Once enabled, versioning can
+/// be suspended but never returns to `Unversioned`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VersioningStatus {
+    Unversioned,
+    Enabled,
+    Suspended,
+}
+
 /// Summary of a backup run.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BackupRecord {
@@ -174,6 +183,114 @@ pub struct BackupRecord {
     pub completed_at: Option<String>,
 }
 
+/// Deduplication summary of one backup.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DedupStats {
+    /// Chunk references written by the backup.
+    pub total_chunks: u64,
+    /// Distinct chunks the backup references.
+    pub unique_chunks: u64,
+    /// References to chunks that were already stored.
+    pub dedup_chunks: u64,
+    /// Size of the backed-up files.
+    pub total_bytes_logical: u64,
+    /// Encrypted size of the distinct chunks the backup references.
+    pub total_bytes_stored: u64,
+    pub dedup_ratio_percent: f64,
+}
+
+/// Deduplication summary across all completed backups.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GlobalDedupStats {
+    pub backups: u64,
+    pub total_chunks: u64,
+    /// Stored chunks referenced more than once.
+    pub shared_chunks: u64,
+    pub dedup_chunks: u64,
+    pub total_bytes_logical: u64,
+    /// Encrypted size of every stored chunk.
+    pub total_bytes_stored: u64,
+    pub dedup_ratio_percent: f64,
+}
+
+/// A stored chunk that objects in more than one namespace reference.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CrossNamespaceDedupEntry {
+    pub chunk_hash: String,
+    /// Referencing namespaces, sorted by name.
+    pub namespaces: Vec<String>,
+    pub ref_count: u32,
+    /// Encrypted (stored) size of the chunk.
+    pub size_bytes: u64,
+}
+
+/// One S3 request on a bucket with server access logging enabled.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct S3AccessLogEntry {
+    pub bucket: String,
+    /// Object key, for object operations.
+    pub key: Option<String>,
+    /// S3 operation name, such as "PutObject".
+    pub operation: String,
+    pub requester_ip: Option<String>,
+    /// When the request arrived (RFC 3339, UTC).
+    pub timestamp: String,
+    pub status_code: u16,
+    /// Object bytes received or sent.
+    pub bytes_transferred: u64,
+}
+
+/// S3 inventory configuration of a bucket, as set by
+/// PutBucketInventoryConfiguration.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InventoryConfig {
+    pub id: String,
+    pub enabled: bool,
+    pub destination_bucket: String,
+    pub destination_prefix: Option<String>,
+    /// Report format, such as "CSV".
+    pub format: String,
+    /// "Daily" or "Weekly".
+    pub frequency: String,
+    /// "All" or "Current".
+    pub included_object_versions: String,
+    /// Only objects under this prefix are listed.
+    pub filter_prefix: Option<String>,
+}
+
+/// One generated inventory report.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InventoryReport {
+    /// Key of the CSV object in the reports bucket.
+    pub key: String,
+    pub size: u64,
+    /// Data rows, not counting the header.
+    pub rows: u64,
+    /// When the report was generated (RFC 3339, UTC).
+    pub generated_at: String,
+}
+
+/// One rotation of the encryption key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyRotationRecord {
+    pub key_id: String,
+    pub old_key_id: String,
+    /// When the key was rotated (RFC 3339, UTC).
+    pub rotated_at: String,
+    /// What started the rotation, such as "schedule" or "max_key_age".
+    pub triggered_by: String,
+    /// Chunks still have to be re-encrypted to `key_id`.
+    pub reencrypt_pending: bool,
+}
+
+/// `dedup` as a percentage of `total` (0 when nothing was chunked).
+pub fn dedup_ratio_percent(dedup: u64, total: u64) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    dedup as f64 / total as f64 * 100.0
+}
+
 /// Chunking strategy selection.
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum ChunkStrategy {
@@ -190,7 +307,7 @@ impl Default for ChunkStrategy {
 }
 
 /// Distribution strategy.
-#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DistributionStrategy {
     #[default]
     RoundRobin,
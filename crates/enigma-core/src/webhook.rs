@@ -0,0 +1,318 @@
+//! Webhooks about backup and storage events.
+//!
+//! An event is POSTed as a JSON [`WebhookPayload`] to every configured
+//! [`WebhookConfig`] subscribed to it, with an
+//! `X-Enigma-Signature: sha256=<hex>` header holding the HMAC-SHA256 of the
+//! body under the webhook's secret. Pipelines [`enqueue`] events in the
+//! manifest's `webhook_queue` table and [`deliver_queued`] sends them, so a
+//! delivery cut short by a restart is retried on the next run.
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::manifest::ManifestDb;
+
+/// Every event a webhook can subscribe to.
+pub const EVENT_TYPES: &[&str] = &[
+    "backup.started",
+    "backup.completed",
+    "backup.failed",
+    "object.created",
+    "object.deleted",
+    "key.rotated",
+    "gc.completed",
+];
+
+/// Header carrying the body signature.
+pub const SIGNATURE_HEADER: &str = "X-Enigma-Signature";
+
+/// Delay before the first retry; doubled for each one after it.
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// Queued deliveries read per pass of [`deliver_queued`].
+const QUEUE_BATCH: u32 = 100;
+
+/// An HTTP endpoint and the events it receives.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// Key of the `X-Enigma-Signature` HMAC.
+    pub secret: String,
+    /// Events to POST, from [`EVENT_TYPES`].
+    pub events: Vec<String>,
+    /// Retries after a failed POST, with exponential backoff (default: 3).
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    /// Timeout of each POST in milliseconds (default: 5000).
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_max_retries() -> u32 {
+    3
+}
+fn default_timeout_ms() -> u64 {
+    5000
+}
+
+impl WebhookConfig {
+    /// Whether this webhook is subscribed to `event`.
+    pub fn wants(&self, event: &str) -> bool {
+        self.events.iter().any(|e| e == event)
+    }
+}
+
+/// JSON body of a webhook POST.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebhookPayload {
+    /// One of [`EVENT_TYPES`].
+    pub event: String,
+    /// When the event happened (RFC 3339, UTC).
+    pub timestamp: String,
+    /// Event details, e.g. the backup ID or bucket and key.
+    pub data: serde_json::Value,
+}
+
+impl WebhookPayload {
+    /// A payload for `event` happening now.
+    pub fn new(event: &str, data: serde_json::Value) -> Self {
+        Self {
+            event: event.to_string(),
+            timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            data,
+        }
+    }
+}
+
+/// `X-Enigma-Signature` value for `body`: `sha256=` and the hex
+/// HMAC-SHA256 of the body keyed with `secret`.
+pub fn signature(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// POST `payload` to the webhook in `config`, retrying up to
+/// `max_retries` times with exponential backoff. Fails with the last
+/// error once the retries are used up.
+pub async fn send_webhook(config: &WebhookConfig, payload: &WebhookPayload) -> anyhow::Result<()> {
+    post(config, serde_json::to_string(payload)?).await
+}
+
+async fn post(config: &WebhookConfig, body: String) -> anyhow::Result<()> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_millis(config.timeout_ms))
+        .build()?;
+    let signature = signature(&config.secret, body.as_bytes());
+
+    let mut attempt = 0;
+    loop {
+        let result = client
+            .post(&config.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(SIGNATURE_HEADER, &signature)
+            .body(body.clone())
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        match result {
+            Ok(_) => return Ok(()),
+            Err(e) if attempt >= config.max_retries => {
+                anyhow::bail!(
+                    "webhook {} failed after {} attempt(s): {e}",
+                    config.url,
+                    attempt + 1
+                );
+            }
+            Err(e) => {
+                tracing::debug!("Webhook {} attempt {} failed: {e}", config.url, attempt + 1);
+                tokio::time::sleep(RETRY_BASE_DELAY * 2u32.saturating_pow(attempt)).await;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// Queue `payload` for every webhook subscribed to its event. Returns the
+/// number of deliveries queued.
+pub fn enqueue(
+    db: &ManifestDb,
+    webhooks: &[WebhookConfig],
+    payload: &WebhookPayload,
+) -> anyhow::Result<usize> {
+    let mut queued = 0;
+    let mut body = None;
+    for webhook in webhooks.iter().filter(|w| w.wants(&payload.event)) {
+        let body = match &body {
+            Some(body) => body,
+            None => body.insert(serde_json::to_string(payload)?),
+        };
+        db.enqueue_webhook(&webhook.url, &payload.event, body)?;
+        queued += 1;
+    }
+    Ok(queued)
+}
+
+/// Send every queued delivery, removing it from the queue once its
+/// endpoint accepts it. A delivery that still fails after its webhook's
+/// retries, or whose webhook is no longer configured, is logged and
+/// dropped. Returns the number delivered.
+///
+/// The database lock is only held between POSTs.
+pub async fn deliver_queued(
+    db: &Mutex<ManifestDb>,
+    webhooks: &[WebhookConfig],
+) -> anyhow::Result<usize> {
+    let mut delivered = 0;
+    loop {
+        let pending = db
+            .lock()
+            .map_err(|e| anyhow::anyhow!("manifest lock poisoned: {e}"))?
+            .pending_webhooks(QUEUE_BATCH)?;
+        if pending.is_empty() {
+            return Ok(delivered);
+        }
+        for queued in pending {
+            match webhooks.iter().find(|w| w.url == queued.url) {
+                Some(webhook) => match post(webhook, queued.body).await {
+                    Ok(()) => delivered += 1,
+                    Err(e) => tracing::warn!("Dropping {} webhook: {e}", queued.event),
+                },
+                None => tracing::warn!(
+                    "Dropping {} webhook for {}: no longer configured",
+                    queued.event,
+                    queued.url
+                ),
+            }
+            db.lock()
+                .map_err(|e| anyhow::anyhow!("manifest lock poisoned: {e}"))?
+                .delete_webhook(queued.id)?;
+        }
+    }
+}
+
+/// [`deliver_queued`] for a command about to exit, which hands over its
+/// manifest connection. Failures are logged; what could not be read from
+/// the queue stays there for the next run.
+pub async fn flush_queue(db: ManifestDb, webhooks: &[WebhookConfig]) {
+    if webhooks.is_empty() {
+        return;
+    }
+    if let Err(e) = deliver_queued(&Mutex::new(db), webhooks).await {
+        tracing::warn!("Webhook delivery failed: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn config(url: String, max_retries: u32) -> WebhookConfig {
+        WebhookConfig {
+            url,
+            secret: "s3cret".to_string(),
+            events: vec!["backup.completed".to_string()],
+            max_retries,
+            timeout_ms: 2000,
+        }
+    }
+
+    fn payload() -> WebhookPayload {
+        WebhookPayload::new(
+            "backup.completed",
+            serde_json::json!({ "backup_id": "0192-backup" }),
+        )
+    }
+
+    #[test]
+    fn signature_is_hex_hmac_sha256() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            signature("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[tokio::test]
+    async fn delivers_signed_json() {
+        let server = MockServer::start().await;
+        let payload = payload();
+        let body = serde_json::to_string(&payload).unwrap();
+        Mock::given(method("POST"))
+            .and(path("/hook"))
+            .and(header("content-type", "application/json"))
+            .and(header(
+                SIGNATURE_HEADER,
+                signature("s3cret", body.as_bytes()),
+            ))
+            .and(body_json(&payload))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        send_webhook(&config(format!("{}/hook", server.uri()), 0), &payload)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn retries_until_accepted_or_out_of_retries() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        send_webhook(&config(server.uri(), 1), &payload())
+            .await
+            .unwrap();
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+
+        let failing = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&failing)
+            .await;
+        let err = send_webhook(&config(failing.uri(), 0), &payload())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("after 1 attempt(s)"));
+    }
+
+    #[tokio::test]
+    async fn queued_deliveries_go_to_subscribed_webhooks_only() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/completed"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let subscribed = config(format!("{}/completed", server.uri()), 0);
+        let other = WebhookConfig {
+            url: format!("{}/gc", server.uri()),
+            events: vec!["gc.completed".to_string()],
+            ..subscribed.clone()
+        };
+        let webhooks = [subscribed, other];
+
+        let db = ManifestDb::open_in_memory().unwrap();
+        assert_eq!(enqueue(&db, &webhooks, &payload()).unwrap(), 1);
+        let db = Mutex::new(db);
+        assert_eq!(deliver_queued(&db, &webhooks).await.unwrap(), 1);
+        assert!(db.lock().unwrap().pending_webhooks(10).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,444 @@
+//! AWS KMS envelope encryption KeyProvider implementation.
+//!
+//! Each Enigma key is a 32-byte data key (DEK) generated by KMS with
+//! `GenerateDataKey` under a customer master key. Only the DEK encrypted by
+//! KMS is persisted, either as a secret `{prefix}-{uuid}` in AWS Secrets
+//! Manager or in a JSON map file next to the config; `{prefix}-current`
+//! tracks the active key ID. Reading a key goes through `kms:Decrypt`, so the
+//! plaintext DEK never leaves memory. The key ID is bound to each ciphertext
+//! as KMS encryption context.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use aws_sdk_kms::Client;
+use aws_sdk_kms::primitives::Blob;
+use aws_sdk_kms::types::DataKeySpec;
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use zeroize::Zeroize;
+
+use crate::provider::{KeyProvider, ManagedKey};
+
+/// Encryption context key binding a wrapped DEK to its Enigma key ID.
+const CONTEXT_KEY_ID: &str = "enigma-key-id";
+
+/// Where the KMS-encrypted DEKs are kept.
+pub enum WrappedKeyStore {
+    /// One secret per key in AWS Secrets Manager.
+    SecretsManager(aws_sdk_secretsmanager::Client),
+    /// A JSON map file on local disk.
+    File(PathBuf),
+}
+
+/// Key ID → wrapped DEK map persisted by [`WrappedKeyStore::File`].
+#[derive(Serialize, Deserialize, Default)]
+struct KeyMap {
+    current_key_id: Option<String>,
+    keys: BTreeMap<String, WrappedKey>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct WrappedKey {
+    /// DEK encrypted by KMS (base64).
+    ciphertext: String,
+    created_at: String,
+}
+
+/// AWS KMS key provider.
+pub struct AwsKmsKeyProvider {
+    kms: Client,
+    kms_key_id: String,
+    prefix: String,
+    store: WrappedKeyStore,
+}
+
+impl AwsKmsKeyProvider {
+    /// Create a provider that wraps DEKs with the KMS key `key_id_or_arn`
+    /// (key ID, ARN or `alias/...`) and stores them in Secrets Manager.
+    ///
+    /// Uses default credential chain (env vars, AWS CLI profile, IAM role, etc.).
+    pub async fn new(region: &str, key_id_or_arn: &str, prefix: &str) -> anyhow::Result<Self> {
+        let config = Self::load_config(region).await;
+        let store = WrappedKeyStore::SecretsManager(aws_sdk_secretsmanager::Client::new(&config));
+        Ok(Self::with_store(
+            Client::new(&config),
+            key_id_or_arn,
+            prefix,
+            store,
+        ))
+    }
+
+    /// Like [`new`](Self::new), but keeps the wrapped DEKs in the JSON file
+    /// at `map_path` instead of Secrets Manager.
+    pub async fn with_key_file(
+        region: &str,
+        key_id_or_arn: &str,
+        prefix: &str,
+        map_path: &Path,
+    ) -> anyhow::Result<Self> {
+        let config = Self::load_config(region).await;
+        let store = WrappedKeyStore::File(map_path.to_path_buf());
+        Ok(Self::with_store(
+            Client::new(&config),
+            key_id_or_arn,
+            prefix,
+            store,
+        ))
+    }
+
+    /// Build a provider from an existing KMS client and store.
+    pub fn with_store(
+        kms: Client,
+        key_id_or_arn: &str,
+        prefix: &str,
+        store: WrappedKeyStore,
+    ) -> Self {
+        Self {
+            kms,
+            kms_key_id: key_id_or_arn.to_string(),
+            prefix: prefix.to_string(),
+            store,
+        }
+    }
+
+    async fn load_config(region: &str) -> aws_config::SdkConfig {
+        let region_provider = aws_config::Region::new(region.to_string());
+        aws_config::from_env().region(region_provider).load().await
+    }
+
+    fn secret_name(&self, key_id: &str) -> String {
+        format!("{}-{}", self.prefix, key_id)
+    }
+
+    fn meta_secret_name(&self) -> String {
+        format!("{}-current", self.prefix)
+    }
+
+    // ── KMS ───────────────────────────────────────────────────────
+
+    /// Ask KMS for a new DEK; returns the plaintext and the wrapped copy.
+    async fn generate_data_key(&self, key_id: &str) -> anyhow::Result<([u8; 32], Vec<u8>)> {
+        let resp = self
+            .kms
+            .generate_data_key()
+            .key_id(&self.kms_key_id)
+            .key_spec(DataKeySpec::Aes256)
+            .encryption_context(CONTEXT_KEY_ID, key_id)
+            .send()
+            .await
+            .map_err(|e| anyhow::anyhow!("AWS KMS generate_data_key failed: {e}"))?;
+
+        let plaintext = resp
+            .plaintext()
+            .ok_or_else(|| anyhow::anyhow!("KMS returned no plaintext data key"))?;
+        let ciphertext = resp
+            .ciphertext_blob()
+            .ok_or_else(|| anyhow::anyhow!("KMS returned no encrypted data key"))?;
+        Ok((to_key(plaintext.as_ref())?, ciphertext.as_ref().to_vec()))
+    }
+
+    /// Encrypt an existing DEK under the current version of the KMS key.
+    async fn wrap(&self, key_id: &str, key: &[u8; 32]) -> anyhow::Result<Vec<u8>> {
+        let resp = self
+            .kms
+            .encrypt()
+            .key_id(&self.kms_key_id)
+            .plaintext(Blob::new(key.to_vec()))
+            .encryption_context(CONTEXT_KEY_ID, key_id)
+            .send()
+            .await
+            .map_err(|e| anyhow::anyhow!("AWS KMS encrypt failed: {e}"))?;
+
+        resp.ciphertext_blob()
+            .map(|b| b.as_ref().to_vec())
+            .ok_or_else(|| anyhow::anyhow!("KMS returned no ciphertext"))
+    }
+
+    async fn unwrap(&self, key_id: &str, ciphertext: Vec<u8>) -> anyhow::Result<[u8; 32]> {
+        let resp = self
+            .kms
+            .decrypt()
+            .key_id(&self.kms_key_id)
+            .ciphertext_blob(Blob::new(ciphertext))
+            .encryption_context(CONTEXT_KEY_ID, key_id)
+            .send()
+            .await
+            .map_err(|e| anyhow::anyhow!("AWS KMS decrypt of key {key_id} failed: {e}"))?;
+
+        let plaintext = resp
+            .plaintext()
+            .ok_or_else(|| anyhow::anyhow!("KMS returned no plaintext for key {key_id}"))?;
+        to_key(plaintext.as_ref())
+    }
+
+    // ── Wrapped key store ─────────────────────────────────────────
+
+    async fn store_wrapped(&self, key_id: &str, ciphertext: &[u8]) -> anyhow::Result<String> {
+        let created_at = chrono::Utc::now().to_rfc3339();
+        let encoded = BASE64.encode(ciphertext);
+        match &self.store {
+            WrappedKeyStore::SecretsManager(sm) => {
+                put_secret(sm, &self.secret_name(key_id), &encoded).await?;
+            }
+            WrappedKeyStore::File(path) => {
+                let mut map = load_map(path)?;
+                map.keys.insert(
+                    key_id.to_string(),
+                    WrappedKey {
+                        ciphertext: encoded,
+                        created_at: created_at.clone(),
+                    },
+                );
+                save_map(path, &map)?;
+            }
+        }
+        Ok(created_at)
+    }
+
+    /// Read a wrapped DEK and its creation time.
+    async fn read_wrapped(&self, key_id: &str) -> anyhow::Result<(Vec<u8>, String)> {
+        let (encoded, created_at) = match &self.store {
+            WrappedKeyStore::SecretsManager(sm) => {
+                let name = self.secret_name(key_id);
+                let resp = sm
+                    .get_secret_value()
+                    .secret_id(&name)
+                    .send()
+                    .await
+                    .map_err(|e| anyhow::anyhow!("AWS get_secret_value({name}) failed: {e}"))?;
+                let value = resp
+                    .secret_string()
+                    .ok_or_else(|| anyhow::anyhow!("Secret {name} has no string value"))?
+                    .to_string();
+                let created_at = resp
+                    .created_date()
+                    .map(|d| d.to_string())
+                    .unwrap_or_else(|| chrono::Utc::now().to_rfc3339());
+                (value, created_at)
+            }
+            WrappedKeyStore::File(path) => {
+                let mut map = load_map(path)?;
+                let entry = map
+                    .keys
+                    .remove(key_id)
+                    .ok_or_else(|| anyhow::anyhow!("Key not found: {key_id}"))?;
+                (entry.ciphertext, entry.created_at)
+            }
+        };
+        Ok((BASE64.decode(encoded)?, created_at))
+    }
+
+    async fn get_current_key_id(&self) -> anyhow::Result<String> {
+        match &self.store {
+            WrappedKeyStore::SecretsManager(sm) => {
+                let resp = sm
+                    .get_secret_value()
+                    .secret_id(self.meta_secret_name())
+                    .send()
+                    .await
+                    .map_err(|e| anyhow::anyhow!("AWS get current key ID failed: {e}"))?;
+                resp.secret_string()
+                    .map(|s| s.to_string())
+                    .ok_or_else(|| anyhow::anyhow!("Metadata secret has no value"))
+            }
+            WrappedKeyStore::File(path) => load_map(path)?
+                .current_key_id
+                .ok_or_else(|| anyhow::anyhow!("No current key set")),
+        }
+    }
+
+    async fn set_current_key_id(&self, key_id: &str) -> anyhow::Result<()> {
+        match &self.store {
+            WrappedKeyStore::SecretsManager(sm) => {
+                put_secret(sm, &self.meta_secret_name(), key_id).await
+            }
+            WrappedKeyStore::File(path) => {
+                let mut map = load_map(path)?;
+                map.current_key_id = Some(key_id.to_string());
+                save_map(path, &map)
+            }
+        }
+    }
+
+    async fn read_key(&self, key_id: &str) -> anyhow::Result<ManagedKey> {
+        let (ciphertext, created_at) = self.read_wrapped(key_id).await?;
+        let key = self.unwrap(key_id, ciphertext).await?;
+        Ok(ManagedKey {
+            id: key_id.to_string(),
+            key,
+            created_at,
+        })
+    }
+}
+
+#[async_trait]
+impl KeyProvider for AwsKmsKeyProvider {
+    async fn get_current_key(&self) -> anyhow::Result<ManagedKey> {
+        let key_id = self.get_current_key_id().await?;
+        self.read_key(&key_id).await
+    }
+
+    async fn get_key_by_id(&self, id: &str) -> anyhow::Result<ManagedKey> {
+        self.read_key(id).await
+    }
+
+    async fn create_key(&mut self) -> anyhow::Result<ManagedKey> {
+        let key_id = Uuid::now_v7().to_string();
+        let (key, ciphertext) = self.generate_data_key(&key_id).await?;
+
+        let created_at = self.store_wrapped(&key_id, &ciphertext).await?;
+        self.set_current_key_id(&key_id).await?;
+
+        tracing::info!(key_id = %key_id, "Created new key with AWS KMS");
+
+        Ok(ManagedKey {
+            id: key_id,
+            key,
+            created_at,
+        })
+    }
+
+    /// KMS encrypts the new DEK under the current version of the master
+    /// key, so rotating the master key in KMS applies to new DEKs only.
+    async fn rotate_key(&mut self) -> anyhow::Result<ManagedKey> {
+        self.create_key().await
+    }
+
+    async fn import_key(&mut self, key: &ManagedKey) -> anyhow::Result<()> {
+        let ciphertext = self.wrap(&key.id, &key.key).await?;
+        self.store_wrapped(&key.id, &ciphertext).await?;
+        self.set_current_key_id(&key.id).await?;
+        tracing::info!(key_id = %key.id, "Imported key with AWS KMS");
+        Ok(())
+    }
+
+    async fn list_key_ids(&self) -> anyhow::Result<Vec<String>> {
+        let sm = match &self.store {
+            WrappedKeyStore::SecretsManager(sm) => sm,
+            WrappedKeyStore::File(path) => {
+                return Ok(load_map(path)?.keys.into_keys().collect());
+            }
+        };
+
+        let prefix_dash = format!("{}-", self.prefix);
+        let meta_name = self.meta_secret_name();
+        let mut ids = Vec::new();
+
+        let mut paginator = sm
+            .list_secrets()
+            .filters(
+                aws_sdk_secretsmanager::types::Filter::builder()
+                    .key(aws_sdk_secretsmanager::types::FilterNameStringType::Name)
+                    .values(&prefix_dash)
+                    .build(),
+            )
+            .into_paginator()
+            .send();
+
+        while let Some(page) = paginator.next().await {
+            let page = page.map_err(|e| anyhow::anyhow!("AWS list_secrets error: {e}"))?;
+            for secret in page.secret_list() {
+                if let Some(name) = &secret.name
+                    && *name != meta_name
+                    && let Some(key_id) = name.strip_prefix(&prefix_dash)
+                {
+                    ids.push(key_id.to_string());
+                }
+            }
+        }
+
+        Ok(ids)
+    }
+}
+
+/// Copy a KMS plaintext into a key, wiping the intermediate buffer.
+fn to_key(plaintext: &[u8]) -> anyhow::Result<[u8; 32]> {
+    let mut bytes = plaintext.to_vec();
+    let result = <[u8; 32]>::try_from(bytes.as_slice())
+        .map_err(|_| anyhow::anyhow!("KMS data key: expected 32 bytes, got {}", bytes.len()));
+    bytes.zeroize();
+    result
+}
+
+/// Create the secret if needed, then store `value` as its current version.
+async fn put_secret(
+    sm: &aws_sdk_secretsmanager::Client,
+    name: &str,
+    value: &str,
+) -> anyhow::Result<()> {
+    if let Err(e) = sm.create_secret().name(name).secret_string("").send().await
+        && !format!("{e}").contains("ResourceExistsException")
+    {
+        anyhow::bail!("AWS create_secret({name}) failed: {e}");
+    }
+
+    sm.put_secret_value()
+        .secret_id(name)
+        .secret_string(value)
+        .send()
+        .await
+        .map_err(|e| anyhow::anyhow!("AWS put_secret_value({name}) failed: {e}"))?;
+    Ok(())
+}
+
+fn load_map(path: &Path) -> anyhow::Result<KeyMap> {
+    if !path.exists() {
+        return Ok(KeyMap::default());
+    }
+    Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+}
+
+fn save_map(path: &Path, map: &KeyMap) -> anyhow::Result<()> {
+    let json = serde_json::to_vec_pretty(map)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp_path = path.with_extension("tmp");
+    std::fs::write(&tmp_path, &json)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&tmp_path, std::fs::Permissions::from_mode(0o600))?;
+    }
+    std::fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_map_round_trips_through_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("keys.kms.json");
+        assert!(load_map(&path).unwrap().keys.is_empty());
+
+        let mut map = KeyMap::default();
+        map.keys.insert(
+            "k1".to_string(),
+            WrappedKey {
+                ciphertext: BASE64.encode([1u8; 48]),
+                created_at: "2026-01-01T00:00:00Z".to_string(),
+            },
+        );
+        map.current_key_id = Some("k1".to_string());
+        save_map(&path, &map).unwrap();
+
+        let loaded = load_map(&path).unwrap();
+        assert_eq!(loaded.current_key_id.as_deref(), Some("k1"));
+        assert_eq!(
+            BASE64.decode(&loaded.keys["k1"].ciphertext).unwrap(),
+            vec![1u8; 48]
+        );
+    }
+
+    #[test]
+    fn data_key_must_be_32_bytes() {
+        assert_eq!(to_key(&[7u8; 32]).unwrap(), [7u8; 32]);
+        assert!(to_key(&[7u8; 16]).is_err());
+    }
+}
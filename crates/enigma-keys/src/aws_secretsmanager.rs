@@ -194,6 +194,13 @@ impl KeyProvider for AwsSecretManagerProvider {
         self.create_key().await
     }
 
+    async fn import_key(&mut self, key: &ManagedKey) -> anyhow::Result<()> {
+        self.store_key(&key.id, &key.key).await?;
+        self.set_current_key_id(&key.id).await?;
+        tracing::info!(key_id = %key.id, "Imported key into AWS Secrets Manager");
+        Ok(())
+    }
+
     async fn list_key_ids(&self) -> anyhow::Result<Vec<String>> {
         let prefix_dash = format!("{}-", self.prefix);
         let meta_name = self.meta_secret_name();
@@ -184,6 +184,13 @@ impl KeyProvider for AzureKeyVaultProvider {
         self.create_key().await
     }
 
+    async fn import_key(&mut self, key: &ManagedKey) -> anyhow::Result<()> {
+        self.store_key(&key.id, &key.key).await?;
+        self.set_current_key_id(&key.id).await?;
+        tracing::info!(key_id = %key.id, "Imported key into Azure Key Vault");
+        Ok(())
+    }
+
     async fn list_key_ids(&self) -> anyhow::Result<Vec<String>> {
         let mut ids = Vec::new();
         let mut pager = self
@@ -1,27 +1,198 @@
 //! Factory for creating the appropriate KeyProvider based on configuration.
 
+use std::collections::BTreeSet;
 use std::path::Path;
 
-use crate::local::LocalKeyProvider;
-use crate::provider::KeyProvider;
+use async_trait::async_trait;
+
+use crate::local::{Argon2Params, LocalKeyProvider};
+use crate::provider::{HealthStatus, KeyProvider, KeyProviderHealth, ManagedKey};
+
+/// Chains several key providers. Reads fall through the providers in order;
+/// new keys are created on the first (primary) provider and copied to the rest.
+pub struct AggregateKeyProvider {
+    providers: Vec<Box<dyn KeyProvider>>,
+}
+
+impl AggregateKeyProvider {
+    pub fn new(providers: Vec<Box<dyn KeyProvider>>) -> anyhow::Result<Self> {
+        if providers.is_empty() {
+            anyhow::bail!("Aggregate key provider needs at least one provider");
+        }
+        Ok(Self { providers })
+    }
+}
+
+#[async_trait]
+impl KeyProvider for AggregateKeyProvider {
+    async fn get_current_key(&self) -> anyhow::Result<ManagedKey> {
+        let mut errors = Vec::new();
+        for (i, provider) in self.providers.iter().enumerate() {
+            match provider.get_current_key().await {
+                Ok(key) => return Ok(key),
+                Err(e) => errors.push(format!("provider {i}: {e}")),
+            }
+        }
+        anyhow::bail!(
+            "No key provider returned a current key: {}",
+            errors.join("; ")
+        )
+    }
+
+    async fn get_key_by_id(&self, id: &str) -> anyhow::Result<ManagedKey> {
+        let mut errors = Vec::new();
+        for (i, provider) in self.providers.iter().enumerate() {
+            match provider.get_key_by_id(id).await {
+                Ok(key) => return Ok(key),
+                Err(e) => errors.push(format!("provider {i}: {e}")),
+            }
+        }
+        anyhow::bail!("Key {id} not found in any provider: {}", errors.join("; "))
+    }
+
+    async fn create_key(&mut self) -> anyhow::Result<ManagedKey> {
+        let (primary, replicas) = self
+            .providers
+            .split_first_mut()
+            .expect("aggregate has at least one provider");
+        let key = primary.create_key().await?;
+        for (i, provider) in replicas.iter_mut().enumerate() {
+            if let Err(e) = provider.import_key(&key).await {
+                tracing::warn!(
+                    key_id = %key.id,
+                    provider = i + 1,
+                    "Failed to replicate key: {e}"
+                );
+            }
+        }
+        Ok(key)
+    }
+
+    async fn rotate_key(&mut self) -> anyhow::Result<ManagedKey> {
+        self.create_key().await
+    }
+
+    async fn list_key_ids(&self) -> anyhow::Result<Vec<String>> {
+        let mut ids = BTreeSet::new();
+        let mut errors = Vec::new();
+        for (i, provider) in self.providers.iter().enumerate() {
+            match provider.list_key_ids().await {
+                Ok(list) => ids.extend(list),
+                Err(e) => errors.push(format!("provider {i}: {e}")),
+            }
+        }
+        if errors.len() == self.providers.len() {
+            anyhow::bail!("No key provider listed its keys: {}", errors.join("; "));
+        }
+        Ok(ids.into_iter().collect())
+    }
+
+    async fn import_key(&mut self, key: &ManagedKey) -> anyhow::Result<()> {
+        let mut imported = false;
+        for (i, provider) in self.providers.iter_mut().enumerate() {
+            match provider.import_key(key).await {
+                Ok(()) => imported = true,
+                Err(e) => {
+                    tracing::warn!(key_id = %key.id, provider = i, "Failed to import key: {e}")
+                }
+            }
+        }
+        if !imported {
+            anyhow::bail!("No key provider accepted key {}", key.id);
+        }
+        Ok(())
+    }
+
+    /// Check every provider: degraded if any is degraded or unavailable,
+    /// unavailable only if all of them are.
+    async fn health_check(&self) -> anyhow::Result<KeyProviderHealth> {
+        let mut latency_ms = 0;
+        let mut problems = Vec::new();
+        let mut unavailable = 0;
+        for (i, provider) in self.providers.iter().enumerate() {
+            let health = provider.health_check().await?;
+            latency_ms += health.latency_ms;
+            if health.status == HealthStatus::Unavailable {
+                unavailable += 1;
+            }
+            if health.status != HealthStatus::Ok {
+                let message = health.message.unwrap_or_default();
+                problems.push(format!(
+                    "provider {i} {}: {message}",
+                    health.status.as_str()
+                ));
+            }
+        }
+        let status = if unavailable == self.providers.len() {
+            HealthStatus::Unavailable
+        } else if problems.is_empty() {
+            HealthStatus::Ok
+        } else {
+            HealthStatus::Degraded
+        };
+        Ok(KeyProviderHealth {
+            status,
+            latency_ms,
+            message: (!problems.is_empty()).then(|| problems.join("; ")),
+        })
+    }
+}
+
+/// Settings for [`create_key_provider`]. Each provider type reads only the
+/// fields it needs; the rest can be left at their defaults.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KeyProviderOptions<'a> {
+    /// Member types of an `"aggregate"` provider, primary first.
+    pub key_providers: &'a [String],
+    /// Keyfile passphrase, or the user PIN for `"pkcs11"`.
+    pub passphrase: Option<&'a [u8]>,
+    pub keyfile_path: &'a str,
+    pub vault_url: Option<&'a str>,
+    pub gcp_project_id: Option<&'a str>,
+    pub aws_region: Option<&'a str>,
+    pub secret_prefix: Option<&'a str>,
+    pub pkcs11_library: Option<&'a str>,
+    pub pkcs11_slot: Option<u64>,
+    pub aws_kms_key_id: Option<&'a str>,
+    pub aws_kms_key_store: Option<&'a str>,
+    /// Argon2id cost of a newly created local keyfile.
+    pub argon2: Argon2Params,
+}
 
 /// Create a KeyProvider based on the provider type string from config.
 ///
 /// Supported types:
-/// - `"local"` — file-based encrypted keyfile (requires passphrase + keyfile_path)
+/// - `"local"` — file-based encrypted keyfile (requires passphrase + keyfile_path; a new
+///   keyfile is derived with `argon2`, an existing one with the params it records)
 /// - `"azure-keyvault"` — Azure Key Vault (requires vault_url, compile with `azure-keyvault` feature)
 /// - `"gcp-secretmanager"` — GCP Secret Manager (requires gcp_project_id, compile with `gcp-secretmanager` feature)
 /// - `"aws-secretsmanager"` — AWS Secrets Manager (requires aws_region, compile with `aws-secretsmanager` feature)
+/// - `"aws-kms"` — AWS KMS envelope encryption (requires aws_region + aws_kms_key_id; wrapped
+///   keys go to Secrets Manager, or next to keyfile_path with aws_kms_key_store = "file";
+///   compile with `aws-kms` feature)
+/// - `"pkcs11"` — PKCS#11 HSM (requires pkcs11_library + pkcs11_slot, passphrase is the user PIN,
+///   wrapped keys are recorded next to keyfile_path; compile with `pkcs11` feature)
+/// - `"aggregate"` — every type listed in `key_providers`, in order, combined into an
+///   [`AggregateKeyProvider`] (the first entry is the primary)
 #[allow(unused_variables)]
 pub async fn create_key_provider(
     provider_type: &str,
-    passphrase: Option<&[u8]>,
-    keyfile_path: &str,
-    vault_url: Option<&str>,
-    gcp_project_id: Option<&str>,
-    aws_region: Option<&str>,
-    secret_prefix: Option<&str>,
+    options: &KeyProviderOptions<'_>,
 ) -> anyhow::Result<Box<dyn KeyProvider>> {
+    let KeyProviderOptions {
+        key_providers,
+        passphrase,
+        keyfile_path,
+        vault_url,
+        gcp_project_id,
+        aws_region,
+        secret_prefix,
+        pkcs11_library,
+        pkcs11_slot,
+        aws_kms_key_id,
+        aws_kms_key_store,
+        argon2,
+    } = *options;
     match provider_type {
         "local" => {
             let passphrase = passphrase
@@ -30,7 +201,7 @@ pub async fn create_key_provider(
             let provider = if path.exists() {
                 LocalKeyProvider::open(path, passphrase)?
             } else {
-                LocalKeyProvider::create(path, passphrase)?
+                LocalKeyProvider::create_with_params(path, passphrase, argon2)?
             };
             Ok(Box::new(provider))
         }
@@ -93,6 +264,259 @@ pub async fn create_key_provider(
             )
         }
 
+        #[cfg(feature = "aws-kms")]
+        "aws-kms" => {
+            let region = aws_region
+                .ok_or_else(|| anyhow::anyhow!("aws_region required for aws-kms provider"))?;
+            let kms_key_id = aws_kms_key_id
+                .ok_or_else(|| anyhow::anyhow!("aws_kms_key_id required for aws-kms provider"))?;
+            let prefix = secret_prefix.unwrap_or("enigma-key");
+            let provider = match aws_kms_key_store.unwrap_or("secretsmanager") {
+                "secretsmanager" => {
+                    crate::aws_kms::AwsKmsKeyProvider::new(region, kms_key_id, prefix).await?
+                }
+                "file" => {
+                    let map_path = Path::new(keyfile_path).with_extension("kms.json");
+                    crate::aws_kms::AwsKmsKeyProvider::with_key_file(
+                        region, kms_key_id, prefix, &map_path,
+                    )
+                    .await?
+                }
+                other => anyhow::bail!(
+                    "Unknown aws_kms_key_store '{other}' (expected \"secretsmanager\" or \"file\")"
+                ),
+            };
+            Ok(Box::new(provider))
+        }
+
+        #[cfg(not(feature = "aws-kms"))]
+        "aws-kms" => {
+            anyhow::bail!("aws-kms feature not enabled. Recompile with --features aws-kms")
+        }
+
+        #[cfg(feature = "pkcs11")]
+        "pkcs11" => {
+            let library = pkcs11_library
+                .ok_or_else(|| anyhow::anyhow!("pkcs11_library required for pkcs11 provider"))?;
+            let slot = pkcs11_slot
+                .ok_or_else(|| anyhow::anyhow!("pkcs11_slot required for pkcs11 provider"))?;
+            let pin = passphrase
+                .ok_or_else(|| anyhow::anyhow!("PIN (passphrase) required for pkcs11 provider"))?;
+            let pin = std::str::from_utf8(pin)
+                .map_err(|_| anyhow::anyhow!("PKCS#11 PIN must be valid UTF-8"))?;
+            let map_path = Path::new(keyfile_path).with_extension("pkcs11.json");
+            let provider = crate::pkcs11::Pkcs11KeyProvider::new(
+                Path::new(library),
+                slot,
+                pin,
+                secret_prefix.unwrap_or("enigma-key"),
+                &map_path,
+            )?;
+            Ok(Box::new(provider))
+        }
+
+        #[cfg(not(feature = "pkcs11"))]
+        "pkcs11" => {
+            anyhow::bail!("pkcs11 feature not enabled. Recompile with --features pkcs11")
+        }
+
+        "aggregate" => {
+            if key_providers.is_empty() {
+                anyhow::bail!("key_providers required for aggregate key provider");
+            }
+            let mut providers = Vec::with_capacity(key_providers.len());
+            for member in key_providers {
+                if member == "aggregate" {
+                    anyhow::bail!("Aggregate key providers cannot be nested");
+                }
+                let provider = Box::pin(create_key_provider(member, options)).await?;
+                providers.push(provider);
+            }
+            Ok(Box::new(AggregateKeyProvider::new(providers)?))
+        }
+
         other => anyhow::bail!("Unknown key provider type: {other}"),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// In-memory provider; `failing` makes every call error out.
+    struct MockProvider {
+        keys: Vec<ManagedKey>,
+        failing: bool,
+    }
+
+    impl MockProvider {
+        fn boxed(ids: &[&str], failing: bool) -> Box<dyn KeyProvider> {
+            let keys = ids
+                .iter()
+                .map(|id| ManagedKey {
+                    id: id.to_string(),
+                    key: [id.len() as u8; 32],
+                    created_at: String::new(),
+                })
+                .collect();
+            Box::new(Self { keys, failing })
+        }
+
+        fn check(&self) -> anyhow::Result<()> {
+            if self.failing {
+                anyhow::bail!("provider unavailable");
+            }
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl KeyProvider for MockProvider {
+        async fn get_current_key(&self) -> anyhow::Result<ManagedKey> {
+            self.check()?;
+            self.keys
+                .last()
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("no keys"))
+        }
+
+        async fn get_key_by_id(&self, id: &str) -> anyhow::Result<ManagedKey> {
+            self.check()?;
+            self.keys
+                .iter()
+                .find(|k| k.id == id)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("Key {id} not found"))
+        }
+
+        async fn create_key(&mut self) -> anyhow::Result<ManagedKey> {
+            self.check()?;
+            let key = ManagedKey {
+                id: format!("created-{}", self.keys.len()),
+                key: [0xAB; 32],
+                created_at: String::new(),
+            };
+            self.keys.push(key.clone());
+            Ok(key)
+        }
+
+        async fn rotate_key(&mut self) -> anyhow::Result<ManagedKey> {
+            self.create_key().await
+        }
+
+        async fn list_key_ids(&self) -> anyhow::Result<Vec<String>> {
+            self.check()?;
+            Ok(self.keys.iter().map(|k| k.id.clone()).collect())
+        }
+
+        async fn import_key(&mut self, key: &ManagedKey) -> anyhow::Result<()> {
+            self.check()?;
+            self.keys.push(key.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_next_working_provider() {
+        let aggregate = AggregateKeyProvider::new(vec![
+            MockProvider::boxed(&["k1"], true),
+            MockProvider::boxed(&["k1", "k2"], false),
+            MockProvider::boxed(&["k3"], false),
+        ])
+        .unwrap();
+
+        assert_eq!(aggregate.get_current_key().await.unwrap().id, "k2");
+        assert_eq!(aggregate.get_key_by_id("k3").await.unwrap().id, "k3");
+        assert_eq!(
+            aggregate.list_key_ids().await.unwrap(),
+            vec!["k1", "k2", "k3"]
+        );
+    }
+
+    #[tokio::test]
+    async fn all_failures_are_reported() {
+        let aggregate = AggregateKeyProvider::new(vec![
+            MockProvider::boxed(&[], true),
+            MockProvider::boxed(&[], false),
+        ])
+        .unwrap();
+
+        let err = aggregate.get_current_key().await.unwrap_err().to_string();
+        assert!(err.contains("provider 0: provider unavailable"), "{err}");
+        assert!(err.contains("provider 1: no keys"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn create_key_replicates_to_other_providers() {
+        let mut aggregate = AggregateKeyProvider::new(vec![
+            MockProvider::boxed(&[], false),
+            MockProvider::boxed(&[], true),
+            MockProvider::boxed(&[], false),
+        ])
+        .unwrap();
+
+        let key = aggregate.rotate_key().await.unwrap();
+        let replica = aggregate.providers[2].get_current_key().await.unwrap();
+        assert_eq!(replica.id, key.id);
+        assert_eq!(replica.key, key.key);
+    }
+
+    #[tokio::test]
+    async fn create_key_fails_when_primary_fails() {
+        let mut aggregate = AggregateKeyProvider::new(vec![
+            MockProvider::boxed(&[], true),
+            MockProvider::boxed(&[], false),
+        ])
+        .unwrap();
+
+        assert!(aggregate.create_key().await.is_err());
+        assert!(
+            aggregate.providers[1]
+                .list_key_ids()
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn health_check_reports_failed_members() {
+        let healthy = MockProvider::boxed(&["k1"], false);
+        let health = healthy.health_check().await.unwrap();
+        assert_eq!(health.status, HealthStatus::Ok);
+        assert_eq!(health.message, None);
+
+        let degraded = AggregateKeyProvider::new(vec![
+            MockProvider::boxed(&["k1"], false),
+            MockProvider::boxed(&["k1"], true),
+        ])
+        .unwrap();
+        let health = degraded.health_check().await.unwrap();
+        assert_eq!(health.status, HealthStatus::Degraded);
+        let message = health.message.unwrap();
+        assert!(
+            message.contains("provider 1 unavailable: provider unavailable"),
+            "{message}"
+        );
+
+        let down = AggregateKeyProvider::new(vec![
+            MockProvider::boxed(&["k1"], true),
+            MockProvider::boxed(&[], false),
+        ])
+        .unwrap();
+        let health = down.health_check().await.unwrap();
+        assert_eq!(health.status, HealthStatus::Unavailable);
+        assert!(
+            health
+                .message
+                .unwrap()
+                .contains("provider 1 unavailable: no keys")
+        );
+    }
+
+    #[tokio::test]
+    async fn aggregate_requires_members() {
+        let result = create_key_provider("aggregate", &KeyProviderOptions::default()).await;
+        assert!(result.is_err());
+    }
+}
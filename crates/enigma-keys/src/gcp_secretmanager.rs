@@ -208,6 +208,13 @@ impl KeyProvider for GcpSecretManagerProvider {
         self.create_key().await
     }
 
+    async fn import_key(&mut self, key: &ManagedKey) -> anyhow::Result<()> {
+        self.store_key(&key.id, &key.key).await?;
+        self.set_current_key_id(&key.id).await?;
+        tracing::info!(key_id = %key.id, "Imported key into GCP Secret Manager");
+        Ok(())
+    }
+
     async fn list_key_ids(&self) -> anyhow::Result<Vec<String>> {
         use google_cloud_gax::paginator::ItemPaginator as _;
 
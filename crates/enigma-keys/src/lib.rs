@@ -11,3 +11,9 @@ pub mod gcp_secretmanager;
 
 #[cfg(feature = "aws-secretsmanager")]
 pub mod aws_secretsmanager;
+
+#[cfg(feature = "aws-kms")]
+pub mod aws_kms;
+
+#[cfg(feature = "pkcs11")]
+pub mod pkcs11;
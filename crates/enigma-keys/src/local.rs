@@ -13,10 +13,11 @@ use rand::rngs::OsRng;
 use serde::{Deserialize, Serialize};
 use sha2::Sha256;
 use std::path::{Path, PathBuf};
+use std::time::Instant;
 use uuid::Uuid;
 use zeroize::Zeroize;
 
-use crate::provider::{KeyProvider, ManagedKey};
+use crate::provider::{KeyProvider, KeyProviderHealth, ManagedKey};
 
 /// Hybrid post-quantum local key provider.
 ///
@@ -39,6 +40,14 @@ struct KeyStore {
     version: u32,
     /// Argon2id salt.
     salt: [u8; 32],
+    /// Argon2id parameters the master key was derived with. Keyfiles written
+    /// before these were recorded used the legacy defaults.
+    #[serde(default = "legacy_memory_kib")]
+    argon2_memory_kib: u32,
+    #[serde(default = "legacy_iterations")]
+    argon2_iterations: u32,
+    #[serde(default = "legacy_parallelism")]
+    argon2_parallelism: u32,
     /// ML-KEM-768 encapsulation key (public), serialized.
     #[serde(with = "base64_bytes")]
     ml_kem_ek: Vec<u8>,
@@ -57,6 +66,7 @@ impl std::fmt::Debug for KeyStore {
         f.debug_struct("KeyStore")
             .field("version", &self.version)
             .field("salt", &"[REDACTED]")
+            .field("argon2", &self.argon2_params())
             .field("ml_kem_ek", &format!("[{}B]", self.ml_kem_ek.len()))
             .field("ml_kem_dk", &"[REDACTED]")
             .field("current_key_id", &self.current_key_id)
@@ -106,17 +116,75 @@ mod base64_bytes {
     }
 }
 
-/// Build hardened Argon2id instance: 64 MiB memory, 3 iterations, 4 lanes, 32-byte output.
-fn hardened_argon2() -> Argon2<'static> {
-    let params = Params::new(65536, 3, 4, Some(32)).expect("valid argon2 params");
-    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+/// Argon2id cost parameters for deriving the master key from the passphrase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Argon2Params {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
 }
 
+impl Default for Argon2Params {
+    /// 64 MiB memory, 3 iterations, 4 lanes — used by every keyfile created
+    /// before the parameters became configurable.
+    fn default() -> Self {
+        Self {
+            memory_kib: 65536,
+            iterations: 3,
+            parallelism: 4,
+        }
+    }
+}
+
+impl Argon2Params {
+    /// Build the Argon2id instance (32-byte output).
+    fn argon2(&self) -> anyhow::Result<Argon2<'static>> {
+        let params = Params::new(self.memory_kib, self.iterations, self.parallelism, Some(32))
+            .map_err(|e| anyhow::anyhow!("Invalid Argon2id parameters {self:?}: {e}"))?;
+        Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+    }
+}
+
+fn legacy_memory_kib() -> u32 {
+    Argon2Params::default().memory_kib
+}
+
+fn legacy_iterations() -> u32 {
+    Argon2Params::default().iterations
+}
+
+fn legacy_parallelism() -> u32 {
+    Argon2Params::default().parallelism
+}
+
+impl KeyStore {
+    fn argon2_params(&self) -> Argon2Params {
+        Argon2Params {
+            memory_kib: self.argon2_memory_kib,
+            iterations: self.argon2_iterations,
+            parallelism: self.argon2_parallelism,
+        }
+    }
+}
+
+/// Magic prefix of keyfiles that carry their Argon2id parameters in a
+/// plaintext header. Older keyfiles start directly with the salt.
+const KEYFILE_MAGIC: &[u8; 4] = b"EKA2";
+/// Magic + memory_kib + iterations + parallelism (u32 little-endian each).
+const HEADER_LEN: usize = 16;
+/// Largest Argon2id memory cost accepted from a keyfile header.
+const MAX_HEADER_MEMORY_KIB: u32 = 4 * 1024 * 1024;
+
 impl LocalKeyProvider {
     /// Derive a master key from passphrase using Argon2id.
-    fn derive_master_key(passphrase: &[u8], salt: &[u8; 32]) -> anyhow::Result<[u8; 32]> {
+    fn derive_master_key(
+        passphrase: &[u8],
+        salt: &[u8; 32],
+        params: &Argon2Params,
+    ) -> anyhow::Result<[u8; 32]> {
         let mut key = [0u8; 32];
-        hardened_argon2()
+        params
+            .argon2()?
             .hash_password_into(passphrase, salt, &mut key)
             .map_err(|e| anyhow::anyhow!("Argon2id key derivation failed: {e}"))?;
         Ok(key)
@@ -156,8 +224,16 @@ impl LocalKeyProvider {
             .encrypt(nonce, plaintext.as_ref())
             .map_err(|e| anyhow::anyhow!("Keystore encryption failed: {e}"))?;
 
-        // Format: salt (32) + nonce (12) + ciphertext
-        let mut output = Vec::with_capacity(32 + 12 + ciphertext.len());
+        // Format: header (16) + salt (32) + nonce (12) + ciphertext. The
+        // Argon2id parameters must be readable before the keystore is
+        // decrypted, so they are duplicated in the plaintext header; a
+        // tampered header only yields a wrong master key.
+        let params = keystore.argon2_params();
+        let mut output = Vec::with_capacity(HEADER_LEN + 32 + 12 + ciphertext.len());
+        output.extend_from_slice(KEYFILE_MAGIC);
+        output.extend_from_slice(&params.memory_kib.to_le_bytes());
+        output.extend_from_slice(&params.iterations.to_le_bytes());
+        output.extend_from_slice(&params.parallelism.to_le_bytes());
         output.extend_from_slice(&keystore.salt);
         output.extend_from_slice(&nonce_bytes);
         output.extend_from_slice(&ciphertext);
@@ -166,6 +242,37 @@ impl LocalKeyProvider {
 
     /// Decrypt keystore from bytes.
     fn decrypt_keystore(data: &[u8], passphrase: &[u8]) -> anyhow::Result<(KeyStore, [u8; 32])> {
+        if let Some(params) = Self::read_header(data) {
+            return Self::decrypt_body(&data[HEADER_LEN..], passphrase, &params).or_else(|e| {
+                // A headerless keyfile whose salt happens to start with the
+                // magic still opens; otherwise report the original error
+                Self::decrypt_body(data, passphrase, &Argon2Params::default()).map_err(|_| e)
+            });
+        }
+        Self::decrypt_body(data, passphrase, &Argon2Params::default())
+    }
+
+    /// Parse the Argon2id header, if `data` has one.
+    fn read_header(data: &[u8]) -> Option<Argon2Params> {
+        if data.len() < HEADER_LEN + 44 || !data.starts_with(KEYFILE_MAGIC) {
+            return None;
+        }
+        let field = |i: usize| u32::from_le_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]);
+        let params = Argon2Params {
+            memory_kib: field(4),
+            iterations: field(8),
+            parallelism: field(12),
+        };
+        // Refuse to allocate more than 4 GiB on the word of an unauthenticated header
+        (params.memory_kib <= MAX_HEADER_MEMORY_KIB).then_some(params)
+    }
+
+    /// Decrypt `salt (32) + nonce (12) + ciphertext` with the given parameters.
+    fn decrypt_body(
+        data: &[u8],
+        passphrase: &[u8],
+        params: &Argon2Params,
+    ) -> anyhow::Result<(KeyStore, [u8; 32])> {
         if data.len() < 44 {
             anyhow::bail!("Keyfile too short");
         }
@@ -174,7 +281,7 @@ impl LocalKeyProvider {
         let nonce_bytes: [u8; 12] = data[32..44].try_into()?;
         let ciphertext = &data[44..];
 
-        let master_key = Self::derive_master_key(passphrase, &salt)?;
+        let master_key = Self::derive_master_key(passphrase, &salt, params)?;
         let cipher = Aes256Gcm::new_from_slice(&master_key)
             .map_err(|e| anyhow::anyhow!("Invalid master key: {e}"))?;
 
@@ -186,6 +293,9 @@ impl LocalKeyProvider {
         );
 
         let keystore: KeyStore = serde_json::from_slice(&decrypted)?;
+        if keystore.argon2_params() != *params {
+            anyhow::bail!("Keyfile header does not match the keystore's Argon2id parameters");
+        }
         Ok((keystore, master_key))
     }
 
@@ -239,8 +349,19 @@ impl LocalKeyProvider {
         Ok((stored, hybrid_key))
     }
 
-    /// Create a new local key provider with a fresh keyfile and ML-KEM-768 keypair.
+    /// Create a new local key provider with a fresh keyfile and ML-KEM-768 keypair,
+    /// using the default Argon2id parameters.
     pub fn create(keyfile_path: &Path, passphrase: &[u8]) -> anyhow::Result<Self> {
+        Self::create_with_params(keyfile_path, passphrase, Argon2Params::default())
+    }
+
+    /// Like [`Self::create`], deriving the master key with `argon2`. The
+    /// parameters are stored in the keyfile and reused by [`Self::open`].
+    pub fn create_with_params(
+        keyfile_path: &Path,
+        passphrase: &[u8],
+        argon2: Argon2Params,
+    ) -> anyhow::Result<Self> {
         if passphrase.is_empty() {
             anyhow::bail!("passphrase must not be empty");
         }
@@ -248,7 +369,7 @@ impl LocalKeyProvider {
         let mut salt = [0u8; 32];
         OsRng.fill_bytes(&mut salt);
 
-        let master_key = Self::derive_master_key(passphrase, &salt)?;
+        let master_key = Self::derive_master_key(passphrase, &salt, &argon2)?;
 
         // Generate ML-KEM-768 keypair
         let (dk, ek) = MlKem768::generate(&mut OsRng);
@@ -258,6 +379,9 @@ impl LocalKeyProvider {
         let keystore = KeyStore {
             version: 2, // v2 = hybrid PQ
             salt,
+            argon2_memory_kib: argon2.memory_kib,
+            argon2_iterations: argon2.iterations,
+            argon2_parallelism: argon2.parallelism,
             ml_kem_ek: ek_bytes,
             ml_kem_dk: dk_bytes,
             current_key_id: String::new(),
@@ -280,7 +404,7 @@ impl LocalKeyProvider {
         Ok(provider)
     }
 
-    /// Open an existing keyfile.
+    /// Open an existing keyfile with the Argon2id parameters it was created with.
     pub fn open(keyfile_path: &Path, passphrase: &[u8]) -> anyhow::Result<Self> {
         let data = std::fs::read(keyfile_path)?;
         let (keystore, master_key) = Self::decrypt_keystore(&data, passphrase)?;
@@ -352,9 +476,48 @@ impl KeyProvider for LocalKeyProvider {
         self.create_key().await
     }
 
+    async fn import_key(&mut self, key: &ManagedKey) -> anyhow::Result<()> {
+        // An imported key has no ML-KEM component of its own
+        if !self.keystore.keys.iter().any(|k| k.id == key.id) {
+            self.keystore.keys.push(StoredKey {
+                id: key.id.clone(),
+                key: key.key,
+                ml_kem_ct: Vec::new(),
+                created_at: key.created_at.clone(),
+            });
+        }
+        self.keystore.current_key_id = key.id.clone();
+        self.save()
+    }
+
     async fn list_key_ids(&self) -> anyhow::Result<Vec<String>> {
         Ok(self.keystore.keys.iter().map(|k| k.id.clone()).collect())
     }
+
+    /// Encrypt and decrypt a probe with the current key.
+    async fn health_check(&self) -> anyhow::Result<KeyProviderHealth> {
+        let started = Instant::now();
+        let result = self.get_current_key().await.and_then(|key| {
+            const PROBE: &[u8] = b"enigma-health-check";
+            let cipher = Aes256Gcm::new_from_slice(&key.key)
+                .map_err(|e| anyhow::anyhow!("Invalid current key: {e}"))?;
+            let mut nonce_bytes = [0u8; 12];
+            OsRng.fill_bytes(&mut nonce_bytes);
+            let nonce = Nonce::from_slice(&nonce_bytes);
+            let ciphertext = cipher
+                .encrypt(nonce, PROBE)
+                .map_err(|e| anyhow::anyhow!("Probe encryption failed: {e}"))?;
+            let plaintext = cipher
+                .decrypt(nonce, ciphertext.as_slice())
+                .map_err(|e| anyhow::anyhow!("Probe decryption failed: {e}"))?;
+            anyhow::ensure!(
+                plaintext == PROBE,
+                "Probe round trip returned different data"
+            );
+            Ok(())
+        });
+        Ok(KeyProviderHealth::from_probe(result, started.elapsed()))
+    }
 }
 
 #[cfg(test)]
@@ -385,6 +548,48 @@ mod tests {
         assert_eq!(key1.key, key2.key);
     }
 
+    #[tokio::test]
+    async fn imported_key_persists_and_becomes_current() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("keys.enc");
+        let passphrase = b"test-passphrase-123";
+
+        let mut provider = LocalKeyProvider::create(&path, passphrase).unwrap();
+        let imported = ManagedKey {
+            id: "imported-key".to_string(),
+            key: [7u8; 32],
+            created_at: "2024-01-01T00:00:00Z".to_string(),
+        };
+        provider.import_key(&imported).await.unwrap();
+        provider.import_key(&imported).await.unwrap();
+
+        let reopened = LocalKeyProvider::open(&path, passphrase).unwrap();
+        let current = reopened.get_current_key().await.unwrap();
+        assert_eq!(current.id, "imported-key");
+        assert_eq!(current.key, [7u8; 32]);
+        assert_eq!(reopened.list_key_ids().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn health_check_round_trips_current_key() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("keys.enc");
+
+        let mut provider = LocalKeyProvider::create(&path, b"pass").unwrap();
+        let health = provider.health_check().await.unwrap();
+        assert_eq!(health.status, crate::provider::HealthStatus::Ok);
+        assert_eq!(health.message, None);
+
+        // A keystore whose current key is missing cannot encrypt
+        provider.keystore.current_key_id = "gone".to_string();
+        let health = provider.health_check().await.unwrap();
+        assert_eq!(health.status, crate::provider::HealthStatus::Unavailable);
+        assert_eq!(
+            health.message.as_deref(),
+            Some("Current key not found in keystore")
+        );
+    }
+
     #[tokio::test]
     async fn wrong_passphrase_fails() {
         let tmp = TempDir::new().unwrap();
@@ -456,6 +661,51 @@ mod tests {
         assert_eq!(dk_bytes.len(), 2400);
     }
 
+    #[tokio::test]
+    async fn custom_argon2_params_persist_across_open() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("keys.enc");
+        let params = Argon2Params {
+            memory_kib: 19 * 1024,
+            iterations: 2,
+            parallelism: 1,
+        };
+
+        let provider = LocalKeyProvider::create_with_params(&path, b"pass", params).unwrap();
+        let key1 = provider.get_current_key().await.unwrap();
+
+        // The header lets the keyfile be opened without knowing the params
+        let data = std::fs::read(&path).unwrap();
+        assert_eq!(LocalKeyProvider::read_header(&data), Some(params));
+
+        let provider2 = LocalKeyProvider::open(&path, b"pass").unwrap();
+        assert_eq!(provider2.keystore.argon2_params(), params);
+        assert_eq!(provider2.get_current_key().await.unwrap().key, key1.key);
+
+        // Rewriting the keystore keeps the params it was created with
+        let mut provider2 = provider2;
+        provider2.rotate_key().await.unwrap();
+        let provider3 = LocalKeyProvider::open(&path, b"pass").unwrap();
+        assert_eq!(provider3.keystore.argon2_params(), params);
+        assert!(LocalKeyProvider::open(&path, b"wrong").is_err());
+    }
+
+    #[tokio::test]
+    async fn opens_keyfile_without_argon2_header() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("keys.enc");
+        let provider = LocalKeyProvider::create(&path, b"pass").unwrap();
+        let key = provider.get_current_key().await.unwrap();
+
+        // Keyfiles written before the header existed start with the salt
+        let data = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &data[HEADER_LEN..]).unwrap();
+
+        let reopened = LocalKeyProvider::open(&path, b"pass").unwrap();
+        assert_eq!(reopened.keystore.argon2_params(), Argon2Params::default());
+        assert_eq!(reopened.get_current_key().await.unwrap().key, key.key);
+    }
+
     #[tokio::test]
     async fn empty_passphrase_rejected() {
         let tmp = TempDir::new().unwrap();
@@ -0,0 +1,251 @@
+//! PKCS#11 (HSM) KeyProvider implementation.
+//!
+//! Each Enigma key is a random 32-byte DEK wrapped by a dedicated non-extractable
+//! AES-256 key generated on the HSM. The HSM key never leaves the device; only the
+//! wrapped DEK and the HSM object's CKA_ID/CKA_LABEL are persisted, in a JSON map
+//! file next to the config. Unwrapping goes through `C_DecryptInit`/`C_Decrypt`.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use cryptoki::context::{CInitializeArgs, Pkcs11};
+use cryptoki::mechanism::Mechanism;
+use cryptoki::object::{Attribute, KeyType, ObjectClass, ObjectHandle};
+use cryptoki::session::{Session, UserType};
+use cryptoki::types::AuthPin;
+use rand::RngCore;
+use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+use zeroize::Zeroize;
+
+use crate::provider::{KeyProvider, ManagedKey};
+
+/// Key provider backed by a PKCS#11 token (YubiHSM 2, Thales Luna, CloudHSM, SoftHSM).
+pub struct Pkcs11KeyProvider {
+    // Kept alive for the lifetime of the session.
+    _pkcs11: Pkcs11,
+    session: Mutex<Session>,
+    label_prefix: String,
+    map_path: PathBuf,
+    map: KeyMap,
+}
+
+/// Label → key-id map persisted outside the HSM.
+#[derive(Serialize, Deserialize, Default)]
+struct KeyMap {
+    current_key_id: Option<String>,
+    keys: BTreeMap<String, WrappedKey>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct WrappedKey {
+    /// CKA_LABEL of the HSM wrapping key.
+    label: String,
+    /// CKA_ID of the HSM wrapping key (hex).
+    cka_id: String,
+    /// AES-CBC IV used to wrap the DEK (base64).
+    iv: String,
+    /// DEK encrypted by the HSM key (base64).
+    wrapped_dek: String,
+    /// SHA-256 of the DEK (hex), checked after unwrapping.
+    dek_sha256: String,
+    created_at: String,
+}
+
+impl Pkcs11KeyProvider {
+    /// Load the PKCS#11 library, open a R/W session on `slot_id` and log in with `pin`.
+    /// `map_path` is where wrapped DEKs are recorded (created on first `create_key`).
+    pub fn new(
+        library_path: &Path,
+        slot_id: u64,
+        pin: &str,
+        key_label_prefix: &str,
+        map_path: &Path,
+    ) -> anyhow::Result<Self> {
+        let pkcs11 = Pkcs11::new(library_path).map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to load PKCS#11 library {}: {e}",
+                library_path.display()
+            )
+        })?;
+        pkcs11.initialize(CInitializeArgs::OsThreads)?;
+
+        let slot = pkcs11
+            .get_slots_with_token()?
+            .into_iter()
+            .find(|s| s.id() == slot_id)
+            .ok_or_else(|| anyhow::anyhow!("PKCS#11 slot {slot_id} not found or has no token"))?;
+
+        let session = pkcs11.open_rw_session(slot)?;
+        session
+            .login(UserType::User, Some(&AuthPin::new(pin.into())))
+            .map_err(|e| anyhow::anyhow!("PKCS#11 login failed: {e}"))?;
+
+        let map = if map_path.exists() {
+            serde_json::from_slice(&std::fs::read(map_path)?)?
+        } else {
+            KeyMap::default()
+        };
+
+        Ok(Self {
+            _pkcs11: pkcs11,
+            session: Mutex::new(session),
+            label_prefix: key_label_prefix.to_string(),
+            map_path: map_path.to_path_buf(),
+            map,
+        })
+    }
+
+    fn save_map(&self) -> anyhow::Result<()> {
+        let json = serde_json::to_vec_pretty(&self.map)?;
+        if let Some(parent) = self.map_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp_path = self.map_path.with_extension("tmp");
+        std::fs::write(&tmp_path, &json)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&tmp_path, std::fs::Permissions::from_mode(0o600))?;
+        }
+        std::fs::rename(&tmp_path, &self.map_path)?;
+        Ok(())
+    }
+
+    fn find_key(session: &Session, cka_id: &[u8]) -> anyhow::Result<ObjectHandle> {
+        session
+            .find_objects(&[
+                Attribute::Class(ObjectClass::SECRET_KEY),
+                Attribute::Id(cka_id.to_vec()),
+            ])?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("HSM key with CKA_ID {} not found", hex::encode(cka_id)))
+    }
+
+    fn unwrap_key(&self, id: &str) -> anyhow::Result<ManagedKey> {
+        let entry = self
+            .map
+            .keys
+            .get(id)
+            .ok_or_else(|| anyhow::anyhow!("Key not found: {id}"))?;
+
+        let cka_id = hex::decode(&entry.cka_id)?;
+        let iv: [u8; 16] = BASE64
+            .decode(&entry.iv)?
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Invalid IV length for key {id}"))?;
+        let wrapped = BASE64.decode(&entry.wrapped_dek)?;
+
+        let mut dek_bytes = {
+            let session = self
+                .session
+                .lock()
+                .map_err(|_| anyhow::anyhow!("PKCS#11 session lock poisoned"))?;
+            let handle = Self::find_key(&session, &cka_id)?;
+            session.decrypt(&Mechanism::AesCbcPad(iv), handle, &wrapped)?
+        };
+
+        if dek_bytes.len() != 32 || hex::encode(Sha256::digest(&dek_bytes)) != entry.dek_sha256 {
+            dek_bytes.zeroize();
+            anyhow::bail!("Unwrapped DEK for key {id} failed integrity check");
+        }
+
+        let mut key = [0u8; 32];
+        key.copy_from_slice(&dek_bytes);
+        dek_bytes.zeroize();
+
+        Ok(ManagedKey {
+            id: id.to_string(),
+            key,
+            created_at: entry.created_at.clone(),
+        })
+    }
+}
+
+#[async_trait]
+impl KeyProvider for Pkcs11KeyProvider {
+    async fn get_current_key(&self) -> anyhow::Result<ManagedKey> {
+        let id = self
+            .map
+            .current_key_id
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("No current key in PKCS#11 key map"))?;
+        self.unwrap_key(id)
+    }
+
+    async fn get_key_by_id(&self, id: &str) -> anyhow::Result<ManagedKey> {
+        self.unwrap_key(id)
+    }
+
+    async fn create_key(&mut self) -> anyhow::Result<ManagedKey> {
+        let key_id = Uuid::now_v7().to_string();
+        let label = format!("{}-{}", self.label_prefix, key_id);
+        let cka_id = Uuid::parse_str(&key_id)?.as_bytes().to_vec();
+
+        let mut dek = [0u8; 32];
+        OsRng.fill_bytes(&mut dek);
+        let mut iv = [0u8; 16];
+        OsRng.fill_bytes(&mut iv);
+
+        let wrapped = {
+            let session = self
+                .session
+                .lock()
+                .map_err(|_| anyhow::anyhow!("PKCS#11 session lock poisoned"))?;
+            let template = [
+                Attribute::Class(ObjectClass::SECRET_KEY),
+                Attribute::KeyType(KeyType::AES),
+                Attribute::ValueLen(32.into()),
+                Attribute::Token(true),
+                Attribute::Private(true),
+                Attribute::Sensitive(true),
+                Attribute::Extractable(false),
+                Attribute::Encrypt(true),
+                Attribute::Decrypt(true),
+                Attribute::Label(label.clone().into_bytes()),
+                Attribute::Id(cka_id.clone()),
+            ];
+            let handle = session.generate_key(&Mechanism::AesKeyGen, &template)?;
+            session.encrypt(&Mechanism::AesCbcPad(iv), handle, &dek)?
+        };
+
+        let created_at = chrono::Utc::now().to_rfc3339();
+        self.map.keys.insert(
+            key_id.clone(),
+            WrappedKey {
+                label,
+                cka_id: hex::encode(&cka_id),
+                iv: BASE64.encode(iv),
+                wrapped_dek: BASE64.encode(&wrapped),
+                dek_sha256: hex::encode(Sha256::digest(dek)),
+                created_at: created_at.clone(),
+            },
+        );
+        self.map.current_key_id = Some(key_id.clone());
+        self.save_map()?;
+
+        tracing::info!(key_id = %key_id, "Created new HSM-wrapped key");
+
+        let managed = ManagedKey {
+            id: key_id,
+            key: dek,
+            created_at,
+        };
+        dek.zeroize();
+        Ok(managed)
+    }
+
+    async fn rotate_key(&mut self) -> anyhow::Result<ManagedKey> {
+        self.create_key().await
+    }
+
+    async fn list_key_ids(&self) -> anyhow::Result<Vec<String>> {
+        Ok(self.map.keys.keys().cloned().collect())
+    }
+}
@@ -1,6 +1,12 @@
+use std::time::{Duration, Instant};
+
 use async_trait::async_trait;
+use serde::Serialize;
 use zeroize::ZeroizeOnDrop;
 
+/// A health check slower than this reports the provider as degraded.
+pub const DEGRADED_LATENCY: Duration = Duration::from_secs(2);
+
 /// A 256-bit encryption key with metadata.
 #[derive(Clone, ZeroizeOnDrop)]
 pub struct ManagedKey {
@@ -21,6 +27,59 @@ impl std::fmt::Debug for ManagedKey {
     }
 }
 
+/// Outcome of a [`KeyProvider::health_check`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Ok,
+    /// Reachable, but slow or partly failing.
+    Degraded,
+    Unavailable,
+}
+
+impl HealthStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Ok => "ok",
+            Self::Degraded => "degraded",
+            Self::Unavailable => "unavailable",
+        }
+    }
+}
+
+/// Result of probing a key provider.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct KeyProviderHealth {
+    pub status: HealthStatus,
+    pub latency_ms: u64,
+    pub message: Option<String>,
+}
+
+impl KeyProviderHealth {
+    /// Health of a probe that took `elapsed`: unavailable if it failed,
+    /// degraded if it took longer than [`DEGRADED_LATENCY`].
+    pub fn from_probe(result: anyhow::Result<()>, elapsed: Duration) -> Self {
+        let latency_ms = elapsed.as_millis() as u64;
+        match result {
+            Err(e) => Self {
+                status: HealthStatus::Unavailable,
+                latency_ms,
+                message: Some(e.to_string()),
+            },
+            Ok(()) if elapsed > DEGRADED_LATENCY => Self {
+                status: HealthStatus::Degraded,
+                latency_ms,
+                message: Some(format!("slow response ({latency_ms} ms)")),
+            },
+            Ok(()) => Self {
+                status: HealthStatus::Ok,
+                latency_ms,
+                message: None,
+            },
+        }
+    }
+}
+
 /// Trait for key management backends.
 #[async_trait]
 pub trait KeyProvider: Send + Sync {
@@ -38,4 +97,20 @@ pub trait KeyProvider: Send + Sync {
 
     /// List all key IDs.
     async fn list_key_ids(&self) -> anyhow::Result<Vec<String>>;
+
+    /// Store a key created elsewhere under its existing ID and make it current.
+    /// Used to replicate keys across providers; backends that generate their
+    /// own key material can't import and keep this default.
+    async fn import_key(&mut self, key: &ManagedKey) -> anyhow::Result<()> {
+        anyhow::bail!("Key provider does not support importing key {}", key.id)
+    }
+
+    /// Probe the backend. The default fetches the current key and measures
+    /// the round trip. A failed probe is reported as unavailable, not as an
+    /// error.
+    async fn health_check(&self) -> anyhow::Result<KeyProviderHealth> {
+        let started = Instant::now();
+        let result = self.get_current_key().await.map(|_| ());
+        Ok(KeyProviderHealth::from_probe(result, started.elapsed()))
+    }
 }
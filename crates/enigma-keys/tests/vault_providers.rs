@@ -8,6 +8,9 @@
 ///
 ///   GCP_PROJECT_ID=eastern-rider-263712 \
 ///   cargo test -p enigma-keys --features gcp-secretmanager --test vault_providers -- --nocapture
+///
+///   AWS_REGION=eu-west-1 AWS_KMS_KEY_ID=alias/enigma-test \
+///   cargo test -p enigma-keys --features aws-kms --test vault_providers -- --nocapture
 #[allow(unused_imports)]
 use enigma_keys::provider::KeyProvider;
 
@@ -293,3 +296,60 @@ mod aws_tests {
         println!("OK: AWS Secrets Manager rotation test passed");
     }
 }
+
+#[cfg(feature = "aws-kms")]
+mod aws_kms_tests {
+    use super::*;
+    use enigma_keys::aws_kms::AwsKmsKeyProvider;
+
+    fn get_kms_config() -> Option<(String, String)> {
+        let region = std::env::var("AWS_REGION").ok()?;
+        let key_id = std::env::var("AWS_KMS_KEY_ID").ok()?;
+        if region.is_empty() || key_id.is_empty() {
+            return None;
+        }
+        Some((region, key_id))
+    }
+
+    #[tokio::test]
+    async fn aws_kms_create_rotate_and_unwrap() {
+        let Some((region, kms_key_id)) = get_kms_config() else {
+            eprintln!("SKIP: AWS_REGION / AWS_KMS_KEY_ID not set");
+            return;
+        };
+
+        let tmp = tempfile::tempdir().unwrap();
+        let map_path = tmp.path().join("keys.kms.json");
+        let mut provider =
+            AwsKmsKeyProvider::with_key_file(&region, &kms_key_id, "enigma-kms-test", &map_path)
+                .await
+                .expect("init failed");
+
+        let key1 = provider.create_key().await.expect("create_key failed");
+        let key2 = provider.rotate_key().await.expect("rotate_key failed");
+        assert_ne!(key1.key, key2.key);
+        println!("OK: Created keys {} and {} with AWS KMS", key1.id, key2.id);
+
+        // Only the wrapped data keys are written to disk
+        let stored = std::fs::read(&map_path).unwrap();
+        assert!(!stored.windows(32).any(|w| w == key1.key));
+
+        let current = provider
+            .get_current_key()
+            .await
+            .expect("get_current_key failed");
+        assert_eq!(current.id, key2.id);
+        assert_eq!(current.key, key2.key);
+
+        let old = provider
+            .get_key_by_id(&key1.id)
+            .await
+            .expect("get old key failed");
+        assert_eq!(old.key, key1.key);
+
+        let ids = provider.list_key_ids().await.expect("list failed");
+        assert_eq!(ids.len(), 2);
+
+        println!("OK: AWS KMS envelope encryption test passed");
+    }
+}
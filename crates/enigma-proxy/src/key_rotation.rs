@@ -0,0 +1,408 @@
+//! Scheduled key rotation.
+//!
+//! On each tick of the `[key_rotation]` cron schedule the current key is
+//! rotated if it is at least `max_key_age_days` old. Each rotation is
+//! recorded in the manifest's key rotation history and sent to the
+//! `key.rotated` webhooks.
+//!
+//! The running proxy keeps encrypting and decrypting with the key it
+//! started with, so a new key takes effect at the next start. With
+//! `reencrypt_after_rotation`, that start re-encrypts the chunks still
+//! under older keys to it in the background.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::broadcast;
+
+use enigma_core::manifest::ManifestDb;
+use enigma_core::types::KeyMaterial;
+use enigma_keys::provider::KeyProvider;
+use enigma_s3::SharedState;
+use enigma_storage::provider::StorageProvider;
+use enigma_storage::reencrypt::{ReencryptStats, reencrypt_all_chunks};
+
+/// Environment variable turning on a daily key age check without a
+/// `[key_rotation]` section.
+pub const MAX_KEY_AGE_ENV: &str = "ENIGMA_MAX_KEY_AGE_DAYS";
+
+/// Schedule of the [`MAX_KEY_AGE_ENV`] check: every day at 03:00 UTC.
+const DAILY: &str = "0 0 3 * * *";
+
+/// `[key_rotation]` section of the proxy config.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyRotationConfig {
+    /// Cron expression with a seconds field, in UTC: "0 0 3 * * Sun" checks
+    /// every Sunday at 03:00.
+    pub schedule: String,
+    /// Only rotate a key at least this many days old; 0 rotates on every
+    /// tick.
+    #[serde(default)]
+    pub max_key_age_days: u32,
+    /// Re-encrypt the chunks under older keys at the next start.
+    #[serde(default)]
+    pub reencrypt_after_rotation: bool,
+}
+
+impl KeyRotationConfig {
+    /// Daily check for keys older than [`MAX_KEY_AGE_ENV`] days, if set.
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let Ok(days) = std::env::var(MAX_KEY_AGE_ENV) else {
+            return Ok(None);
+        };
+        let max_key_age_days = days
+            .parse()
+            .map_err(|e| anyhow::anyhow!("invalid {MAX_KEY_AGE_ENV} \"{days}\": {e}"))?;
+        Ok(Some(Self {
+            schedule: DAILY.to_string(),
+            max_key_age_days,
+            reencrypt_after_rotation: false,
+        }))
+    }
+
+    pub fn parse_schedule(&self) -> anyhow::Result<cron::Schedule> {
+        cron::Schedule::from_str(&self.schedule).map_err(|e| {
+            anyhow::anyhow!("invalid key_rotation.schedule \"{}\": {e}", self.schedule)
+        })
+    }
+}
+
+/// When rotation checks are due.
+pub trait Schedule: Send {
+    /// The first check after `now`, or `None` when there are no more.
+    fn next_after(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>>;
+}
+
+impl Schedule for cron::Schedule {
+    fn next_after(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.after(&now).next()
+    }
+}
+
+/// Rotates the key of a key provider and records each rotation.
+pub struct KeyRotator {
+    key_provider: Box<dyn KeyProvider>,
+    state: SharedState,
+    max_key_age: chrono::Duration,
+    reencrypt_after_rotation: bool,
+}
+
+impl KeyRotator {
+    pub fn new(
+        key_provider: Box<dyn KeyProvider>,
+        state: SharedState,
+        config: &KeyRotationConfig,
+    ) -> Self {
+        Self {
+            key_provider,
+            state,
+            max_key_age: chrono::Duration::days(i64::from(config.max_key_age_days)),
+            reencrypt_after_rotation: config.reencrypt_after_rotation,
+        }
+    }
+
+    /// Rotate the current key if it is old enough. Returns the new key's ID.
+    pub async fn rotate_if_due(&mut self, triggered_by: &str) -> anyhow::Result<Option<String>> {
+        let current = self.key_provider.get_current_key().await?;
+        if !self.is_due(&current.created_at, Utc::now()) {
+            return Ok(None);
+        }
+
+        let new_key = self.key_provider.rotate_key().await?;
+        let db = self
+            .state
+            .db
+            .lock()
+            .map_err(|e| anyhow::anyhow!("db lock poisoned: {e}"))?;
+        db.record_key_rotation(
+            &new_key.id,
+            &current.id,
+            triggered_by,
+            self.reencrypt_after_rotation,
+        )?;
+        tracing::info!(
+            key_id = %new_key.id,
+            old_key_id = %current.id,
+            triggered_by,
+            "Rotated encryption key; it takes effect at the next start"
+        );
+        self.state.queue_webhook(
+            &db,
+            "key.rotated",
+            json!({
+                "key_id": new_key.id,
+                "old_key_ids": [current.id],
+                "triggered_by": triggered_by,
+            }),
+        );
+        Ok(Some(new_key.id.clone()))
+    }
+
+    /// Whether a key created at `created_at` (RFC 3339) is old enough to
+    /// rotate. A key of unknown age is only rotated without an age limit.
+    fn is_due(&self, created_at: &str, now: DateTime<Utc>) -> bool {
+        if self.max_key_age.is_zero() {
+            return true;
+        }
+        match DateTime::parse_from_rfc3339(created_at) {
+            Ok(created) => now - created.with_timezone(&Utc) >= self.max_key_age,
+            Err(e) => {
+                tracing::warn!("Cannot tell the age of the current key ({created_at:?}): {e}");
+                false
+            }
+        }
+    }
+}
+
+/// Rotate on every tick of `schedule` until it ends or shutdown.
+pub async fn run(
+    mut rotator: KeyRotator,
+    schedule: impl Schedule,
+    mut shutdown: broadcast::Receiver<()>,
+) {
+    while let Some(next) = schedule.next_after(Utc::now()) {
+        let wait = (next - Utc::now()).to_std().unwrap_or_default();
+        tokio::select! {
+            _ = tokio::time::sleep(wait) => {}
+            _ = shutdown.recv() => break,
+        }
+        match rotator.rotate_if_due("schedule").await {
+            Ok(Some(_)) => {}
+            Ok(None) => tracing::debug!("Current key is not due for rotation"),
+            Err(e) => tracing::error!("Scheduled key rotation failed: {e}"),
+        }
+    }
+}
+
+/// If a rotation to the current key asked for it, re-encrypt the chunks
+/// still under older keys in the background. The rotation stays pending
+/// until every chunk made it, so failures are retried at the next start.
+pub async fn spawn_pending_reencryption(
+    state: &SharedState,
+    key_provider: &dyn KeyProvider,
+) -> anyhow::Result<()> {
+    let new_key = state.key_material.clone();
+    let old_key_ids: Vec<String> = {
+        let db = state
+            .db
+            .lock()
+            .map_err(|e| anyhow::anyhow!("db lock poisoned: {e}"))?;
+        if !db.key_reencryption_pending(&new_key.id)? {
+            return Ok(());
+        }
+        db.chunk_key_ids()?
+            .into_iter()
+            .map(|(key_id, _)| key_id)
+            .filter(|key_id| *key_id != new_key.id)
+            .collect()
+    };
+    let mut old_keys = Vec::new();
+    for key_id in &old_key_ids {
+        let managed = key_provider.get_key_by_id(key_id).await?;
+        old_keys.push(KeyMaterial {
+            id: managed.id.clone(),
+            key: managed.key,
+        });
+    }
+
+    // ManifestDb is not Sync, so the run gets its own connection on a
+    // blocking thread instead of holding the shared one across awaits
+    let db_path = state.config.enigma.db_path.clone();
+    let providers: HashMap<i64, Box<dyn StorageProvider>> = state
+        .providers
+        .snapshot()
+        .iter()
+        .map(|(id, provider)| (*id, Box::new(provider.clone()) as Box<dyn StorageProvider>))
+        .collect();
+    let handle = tokio::runtime::Handle::current();
+    tracing::info!(
+        key_id = %new_key.id,
+        old_keys = old_keys.len(),
+        "Re-encrypting chunks after key rotation"
+    );
+    tokio::task::spawn_blocking(move || {
+        let result = (|| -> anyhow::Result<ReencryptStats> {
+            let db = ManifestDb::open(Path::new(&db_path))?;
+            let mut stats = ReencryptStats::default();
+            for old_key in &old_keys {
+                stats +=
+                    handle.block_on(reencrypt_all_chunks(&db, &providers, old_key, &new_key))?;
+            }
+            if stats.failed == 0 {
+                db.finish_key_reencryption(&new_key.id)?;
+            }
+            Ok(stats)
+        })();
+        match result {
+            Ok(stats) => tracing::info!(
+                reencrypted = stats.reencrypted,
+                failed = stats.failed,
+                "Re-encryption after key rotation finished"
+            ),
+            Err(e) => tracing::error!("Re-encryption after key rotation failed: {e}"),
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    use async_trait::async_trait;
+    use enigma_core::config::EnigmaConfig;
+    use enigma_core::distributor::Distributor;
+    use enigma_core::types::ProviderType;
+    use enigma_keys::provider::ManagedKey;
+    use enigma_s3::EnigmaS3State;
+
+    /// Key provider holding its keys in memory; the last one is current.
+    struct MemoryKeys(Vec<ManagedKey>);
+
+    impl MemoryKeys {
+        fn with_key_created(created_at: DateTime<Utc>) -> Self {
+            Self(vec![ManagedKey {
+                id: "key-0".to_string(),
+                key: [0; 32],
+                created_at: created_at.to_rfc3339(),
+            }])
+        }
+    }
+
+    #[async_trait]
+    impl KeyProvider for MemoryKeys {
+        async fn get_current_key(&self) -> anyhow::Result<ManagedKey> {
+            Ok(self.0.last().unwrap().clone())
+        }
+
+        async fn get_key_by_id(&self, id: &str) -> anyhow::Result<ManagedKey> {
+            self.0
+                .iter()
+                .find(|k| k.id == id)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("no key {id}"))
+        }
+
+        async fn create_key(&mut self) -> anyhow::Result<ManagedKey> {
+            let key = ManagedKey {
+                id: format!("key-{}", self.0.len()),
+                key: [self.0.len() as u8; 32],
+                created_at: Utc::now().to_rfc3339(),
+            };
+            self.0.push(key.clone());
+            Ok(key)
+        }
+
+        async fn rotate_key(&mut self) -> anyhow::Result<ManagedKey> {
+            self.create_key().await
+        }
+
+        async fn list_key_ids(&self) -> anyhow::Result<Vec<String>> {
+            Ok(self.0.iter().map(|k| k.id.clone()).collect())
+        }
+    }
+
+    /// Fires right away, `ticks` times.
+    struct Immediately {
+        ticks: Mutex<u32>,
+    }
+
+    impl Schedule for Immediately {
+        fn next_after(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+            let mut ticks = self.ticks.lock().unwrap();
+            *ticks = ticks.checked_sub(1)?;
+            Some(now)
+        }
+    }
+
+    fn test_state(dir: &std::path::Path) -> SharedState {
+        let db = ManifestDb::open_in_memory().unwrap();
+        db.insert_provider("a", ProviderType::Local, dir.to_str().unwrap(), None, 1)
+            .unwrap();
+        let distributor = Distributor::weighted(db.list_providers().unwrap()).unwrap();
+        Arc::new(EnigmaS3State {
+            db: Arc::new(Mutex::new(db)),
+            providers: HashMap::<i64, Box<dyn StorageProvider>>::new().into(),
+            distributor: distributor.into(),
+            key_material: KeyMaterial {
+                id: "key-0".to_string(),
+                key: [0; 32],
+            },
+            config: EnigmaConfig::default_config(dir),
+            raft: Default::default(),
+            events: Default::default(),
+            usage: Default::default(),
+            access_control: Default::default(),
+            multipart_limits: Default::default(),
+            chunk_cache: Default::default(),
+            upload_semaphore: Default::default(),
+            provider_limiters: Default::default(),
+        })
+    }
+
+    fn config(max_key_age_days: u32) -> KeyRotationConfig {
+        KeyRotationConfig {
+            schedule: "0 0 3 * * *".to_string(),
+            max_key_age_days,
+            reencrypt_after_rotation: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn scheduled_rotation_records_history() {
+        let tmp = tempfile::tempdir().unwrap();
+        let state = test_state(tmp.path());
+        let keys = MemoryKeys::with_key_created(Utc::now() - chrono::Duration::days(40));
+        let rotator = KeyRotator::new(Box::new(keys), state.clone(), &config(30));
+        let (_shutdown_tx, shutdown) = broadcast::channel(1);
+
+        // The first tick rotates the 40-day-old key; the new one is too
+        // young for the second
+        let schedule = Immediately {
+            ticks: Mutex::new(2),
+        };
+        run(rotator, schedule, shutdown).await;
+
+        let db = state.db.lock().unwrap();
+        let history = db.list_key_rotations().unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(
+            (history[0].key_id.as_str(), history[0].old_key_id.as_str()),
+            ("key-1", "key-0")
+        );
+        assert_eq!(history[0].triggered_by, "schedule");
+        assert!(db.key_reencryption_pending("key-1").unwrap());
+    }
+
+    #[tokio::test]
+    async fn young_keys_are_not_rotated() {
+        let tmp = tempfile::tempdir().unwrap();
+        let state = test_state(tmp.path());
+        let keys = MemoryKeys::with_key_created(Utc::now() - chrono::Duration::days(1));
+        let mut rotator = KeyRotator::new(Box::new(keys), state.clone(), &config(30));
+        assert_eq!(rotator.rotate_if_due("schedule").await.unwrap(), None);
+
+        let mut rotator = KeyRotator::new(
+            Box::new(MemoryKeys::with_key_created(Utc::now())),
+            state.clone(),
+            &config(0),
+        );
+        assert_eq!(
+            rotator.rotate_if_due("schedule").await.unwrap().as_deref(),
+            Some("key-1")
+        );
+    }
+
+    #[test]
+    fn schedule_must_be_a_cron_expression() {
+        assert!(config(0).parse_schedule().is_ok());
+        let mut bad = config(0);
+        bad.schedule = "every day".to_string();
+        assert!(bad.parse_schedule().is_err());
+    }
+}
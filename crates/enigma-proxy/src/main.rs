@@ -1,24 +1,40 @@
 #[cfg(feature = "metrics")]
 mod metrics;
+#[cfg(unix)]
+mod key_rotation;
+mod reload;
+mod s3_auth;
+mod server;
 
 #[allow(unused_imports)]
 use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use clap::Parser;
 use s3s::service::S3ServiceBuilder;
 use serde::{Deserialize, Serialize};
 
+use enigma_auth::AuthStore;
 use enigma_core::config::{EnigmaConfig, ProviderConfig};
-use enigma_core::distributor::Distributor;
-use enigma_core::manifest::ManifestDb;
+use enigma_core::distributor::{Distributor, HealthCheck};
+use enigma_core::manifest::{CheckpointMode, ManifestDb};
+use enigma_core::notify::NotificationConfig;
 use enigma_core::types::{DistributionStrategy, KeyMaterial, ProviderType};
+use enigma_core::webhook::{self, WebhookConfig};
 use enigma_s3::EnigmaS3State;
-use enigma_s3::auth::EnigmaS3Auth;
+use enigma_s3::access_log::{AccessLog, AccessLogged};
+use enigma_s3::auth::EnigmaS3Access;
+use enigma_s3::multipart::MultipartLimits;
+use enigma_s3::ops::{ChunkCache, UploadSemaphore};
 use enigma_s3::service::EnigmaS3Service;
-use enigma_storage::provider::StorageProvider;
+use enigma_storage::credentials::{CredentialSource, RotatingCredentialProvider};
+use enigma_storage::provider::{
+    CircuitBreaker, CircuitBreakerStorageProvider, CredentialProvider, StorageProvider,
+    TimeoutConfig,
+};
 use enigma_storage::s3::S3StorageProvider;
 
 #[cfg(feature = "azure")]
@@ -33,6 +49,10 @@ struct RaftClusterHandle {
     raft: Arc<enigma_raft::EnigmaRaft>,
     node_id: u64,
     peers: Arc<Mutex<HashMap<u64, String>>>,
+    /// `enable_prevote` of the Raft config.
+    prevote: bool,
+    /// `leader_lease_ms` of the Raft config.
+    leader_lease_ms: Option<u64>,
 }
 
 #[cfg(feature = "web")]
@@ -59,6 +79,12 @@ impl enigma_web::cluster_handle::ClusterHandle for RaftClusterHandle {
             "snapshot_index": m.snapshot.map(|l| l.index),
             "membership": format!("{:?}", m.membership_config),
             "peers": peers,
+            "prevote": self.prevote,
+            "leader_lease": {
+                "lease_ms": self.leader_lease_ms,
+                "held": enigma_raft::read::holds_lease(&self.raft),
+                "millis_since_quorum_ack": m.millis_since_quorum_ack,
+            },
         })
     }
 
@@ -82,6 +108,23 @@ impl enigma_web::cluster_handle::ClusterHandle for RaftClusterHandle {
         Ok(())
     }
 
+    async fn add_learner(&self, node_id: u64, addr: String) -> anyhow::Result<()> {
+        // Known to the network before replication to it starts
+        self.peers
+            .lock()
+            .map_err(|e| anyhow::anyhow!("peers lock poisoned: {e}"))?
+            .insert(node_id, addr.clone());
+
+        // Blocks until the learner has caught up; voters are left as they are
+        self.raft
+            .add_learner(node_id, openraft::BasicNode { addr: addr.clone() }, true)
+            .await?;
+        self.raft
+            .client_write(enigma_raft::types::RaftRequest::AddLearner { node_id, addr })
+            .await?;
+        Ok(())
+    }
+
     async fn remove_node(&self, node_id: u64) -> anyhow::Result<()> {
         // Get current voter IDs and remove the node
         let m = self.raft.metrics().borrow().clone();
@@ -105,8 +148,66 @@ impl enigma_web::cluster_handle::ClusterHandle for RaftClusterHandle {
         self.raft.trigger().snapshot().await?;
         Ok(())
     }
+
+    async fn peer_health(&self) -> anyhow::Result<Vec<enigma_web::cluster_handle::PeerHealth>> {
+        let m = self.raft.metrics().borrow().clone();
+        let membership = m.membership_config.membership();
+        let voters: BTreeSet<u64> = membership.voter_ids().collect();
+        // Replication progress is only tracked while this node leads
+        let replication = m.replication.clone().unwrap_or_default();
+        let peers: Vec<(u64, String)> = membership
+            .nodes()
+            .filter(|(id, _)| **id != self.node_id)
+            .map(|(id, node)| (*id, node.addr.clone()))
+            .collect();
+
+        // Ping every peer at once so one dead node costs a single timeout
+        let checks: Vec<_> = peers
+            .iter()
+            .map(|(_, addr)| {
+                let addr = addr.clone();
+                tokio::spawn(async move {
+                    enigma_raft::health::check_peer(&addr, PEER_HEALTH_TIMEOUT).await
+                })
+            })
+            .collect();
+
+        let mut health = Vec::with_capacity(peers.len());
+        for ((node_id, addr), check) in peers.into_iter().zip(checks) {
+            let last_contact_ms = match check.await? {
+                Ok(rtt) => Some(rtt.as_millis() as u64),
+                Err(e) => {
+                    tracing::debug!("Health check of node {node_id} at {addr} failed: {e}");
+                    None
+                }
+            };
+            let state = if m.current_leader == Some(node_id) {
+                "leader"
+            } else if voters.contains(&node_id) {
+                "follower"
+            } else {
+                "learner"
+            };
+            health.push(enigma_web::cluster_handle::PeerHealth {
+                node_id,
+                addr,
+                state: state.to_string(),
+                last_log_index: replication
+                    .get(&node_id)
+                    .copied()
+                    .flatten()
+                    .map_or(0, |log_id| log_id.index),
+                last_contact_ms,
+            });
+        }
+        Ok(health)
+    }
 }
 
+/// How long `/api/cluster/peers` waits for a peer's health check.
+#[cfg(feature = "web")]
+const PEER_HEALTH_TIMEOUT: Duration = Duration::from_secs(2);
+
 #[derive(Parser)]
 #[command(name = "enigma-proxy")]
 #[command(about = "Enigma S3-compatible proxy — encrypted, deduplicated, multi-cloud storage")]
@@ -130,6 +231,12 @@ struct ProxyConfig {
     s3_proxy: S3ProxyConfig,
     #[serde(default)]
     raft: Option<enigma_raft::config::RaftConfig>,
+    #[serde(default)]
+    notifications: Option<NotificationConfig>,
+    #[serde(default)]
+    webhooks: Vec<WebhookConfig>,
+    #[serde(default)]
+    key_rotation: Option<key_rotation::KeyRotationConfig>,
     #[cfg(feature = "web")]
     #[serde(default)]
     web: Option<enigma_web::WebConfig>,
@@ -154,6 +261,25 @@ struct S3ProxyConfig {
     /// Address for the Prometheus metrics endpoint (e.g. "0.0.0.0:9090").
     #[serde(default)]
     metrics_addr: Option<String>,
+    /// Abort multipart uploads left incomplete for this many hours.
+    #[serde(default = "default_multipart_expiry_hours")]
+    multipart_expiry_hours: u64,
+    /// Minimum size of every multipart part except the last (default: 5 MiB).
+    #[serde(default = "default_multipart_min_part_size_bytes")]
+    multipart_min_part_size_bytes: u64,
+    /// Maximum number of parts in a multipart upload (default: 10000).
+    #[serde(default = "default_multipart_max_parts")]
+    multipart_max_parts: u32,
+    /// Maximum size of a single multipart part (default: 5 GiB).
+    #[serde(default = "default_upload_part_max_size_bytes")]
+    upload_part_max_size_bytes: u64,
+    /// On shutdown, wait this long for in-flight requests before closing
+    /// the remaining connections.
+    #[serde(default = "default_shutdown_timeout_seconds")]
+    shutdown_timeout_seconds: u64,
+    /// Checkpoint the manifest's write-ahead log this often (default: 300).
+    #[serde(default = "default_wal_checkpoint_interval_seconds")]
+    wal_checkpoint_interval_seconds: u64,
 }
 
 impl Default for S3ProxyConfig {
@@ -166,6 +292,12 @@ impl Default for S3ProxyConfig {
             tls_cert: None,
             tls_key: None,
             metrics_addr: None,
+            multipart_expiry_hours: default_multipart_expiry_hours(),
+            multipart_min_part_size_bytes: default_multipart_min_part_size_bytes(),
+            multipart_max_parts: default_multipart_max_parts(),
+            upload_part_max_size_bytes: default_upload_part_max_size_bytes(),
+            shutdown_timeout_seconds: default_shutdown_timeout_seconds(),
+            wal_checkpoint_interval_seconds: default_wal_checkpoint_interval_seconds(),
         }
     }
 }
@@ -182,6 +314,24 @@ fn default_secret_key() -> String {
 fn default_region() -> String {
     "us-east-1".to_string()
 }
+fn default_multipart_expiry_hours() -> u64 {
+    24
+}
+fn default_multipart_min_part_size_bytes() -> u64 {
+    MultipartLimits::default().min_part_size_bytes
+}
+fn default_multipart_max_parts() -> u32 {
+    MultipartLimits::default().max_parts
+}
+fn default_upload_part_max_size_bytes() -> u64 {
+    MultipartLimits::default().max_part_size_bytes
+}
+fn default_shutdown_timeout_seconds() -> u64 {
+    30
+}
+fn default_wal_checkpoint_interval_seconds() -> u64 {
+    300
+}
 
 fn get_passphrase(cli_passphrase: &Option<String>) -> anyhow::Result<String> {
     if let Some(p) = cli_passphrase {
@@ -191,6 +341,130 @@ fn get_passphrase(cli_passphrase: &Option<String>) -> anyhow::Result<String> {
     Ok(passphrase)
 }
 
+/// Credentials re-read from `credentials_file` or the environment, for a
+/// provider that rotates them.
+fn rotating_credentials(
+    pc: &ProviderConfig,
+    env: CredentialSource,
+) -> anyhow::Result<Option<Box<dyn CredentialProvider>>> {
+    if pc.credentials_file.is_none() && pc.credential_refresh_interval_seconds.is_none() {
+        return Ok(None);
+    }
+    let source = match &pc.credentials_file {
+        Some(path) => CredentialSource::File(path.into()),
+        None => env,
+    };
+    let interval = pc
+        .credential_refresh_interval_seconds
+        .map(Duration::from_secs);
+    let credentials = RotatingCredentialProvider::new(source, interval)
+        .map_err(|e| anyhow::anyhow!("provider '{}': {e}", pc.name))?;
+    Ok(Some(Box::new(credentials)))
+}
+
+/// Build the storage backend described by a `[[providers]]` entry.
+async fn open_provider(pc: &ProviderConfig) -> anyhow::Result<Box<dyn StorageProvider>> {
+    let timeouts = TimeoutConfig::from(pc);
+    let provider: Box<dyn StorageProvider> = match pc.provider_type {
+        ProviderType::S3Compatible => {
+            let endpoint = pc.endpoint_url.as_deref().ok_or_else(|| {
+                anyhow::anyhow!("S3Compatible provider '{}' requires endpoint_url", pc.name)
+            })?;
+            let mut provider = S3StorageProvider::s3_compatible(
+                &pc.bucket,
+                endpoint,
+                pc.region.as_deref(),
+                &pc.name,
+                pc.access_key.as_deref(),
+                pc.secret_key.as_deref(),
+                timeouts,
+            )
+            .await?
+            .with_storage_class(pc.storage_class.as_deref());
+            if let Some(credentials) = rotating_credentials(pc, CredentialSource::aws_env())? {
+                provider = provider.with_credential_provider(credentials);
+            }
+            Box::new(provider)
+        }
+        ProviderType::S3 => {
+            let mut provider =
+                S3StorageProvider::new(&pc.bucket, pc.region.as_deref(), &pc.name, timeouts)
+                    .await?
+                    .with_storage_class(pc.storage_class.as_deref());
+            if let Some(credentials) = rotating_credentials(pc, CredentialSource::aws_env())? {
+                provider = provider.with_credential_provider(credentials);
+            }
+            Box::new(provider)
+        }
+        ProviderType::Local => {
+            if timeouts.is_set() {
+                tracing::warn!(provider = %pc.name, "Local provider ignores timeouts");
+            }
+            if pc.storage_class.is_some() {
+                tracing::warn!(provider = %pc.name, "Local provider ignores storage_class");
+            }
+            Box::new(enigma_storage::local::LocalStorageProvider::new(
+                Path::new(&pc.bucket),
+                &pc.name,
+            )?)
+        }
+        #[cfg(feature = "azure")]
+        ProviderType::Azure => {
+            if let Some(credentials) = rotating_credentials(pc, CredentialSource::azure_env())? {
+                let initial = credentials.get_credentials().await?;
+                return Ok(Box::new(
+                    AzureStorageProvider::new(
+                        &initial.access_key,
+                        &initial.secret_key,
+                        &pc.bucket,
+                        &pc.name,
+                        timeouts,
+                    )?
+                    .with_storage_class(pc.storage_class.as_deref())?
+                    .with_credential_provider(credentials),
+                ));
+            }
+            let account = pc.access_key.as_deref().ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Azure provider '{}' requires access_key (storage account name)",
+                    pc.name
+                )
+            })?;
+            let key = pc.secret_key.as_deref().ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Azure provider '{}' requires secret_key (storage account key)",
+                    pc.name
+                )
+            })?;
+            Box::new(
+                AzureStorageProvider::new(account, key, &pc.bucket, &pc.name, timeouts)?
+                    .with_storage_class(pc.storage_class.as_deref())?,
+            )
+        }
+        #[cfg(feature = "gcs")]
+        ProviderType::Gcs => Box::new(
+            GcsStorageProvider::new(&pc.bucket, &pc.name, timeouts)
+                .await?
+                .with_storage_class(pc.storage_class.as_deref())
+                .with_signing_key_path(pc.gcs_signing_key_path.as_deref())?,
+        ),
+        _ => {
+            anyhow::bail!("Unsupported provider type: {:?}", pc.provider_type);
+        }
+    };
+    Ok(provider)
+}
+
+/// Wrap a provider in a circuit breaker; the returned check reports
+/// whether its circuit lets requests through.
+fn with_circuit_breaker(
+    provider: Box<dyn StorageProvider>,
+) -> (Box<dyn StorageProvider>, HealthCheck) {
+    let wrapped = CircuitBreakerStorageProvider::new(provider, CircuitBreaker::default());
+    let breaker = wrapped.breaker();
+    (Box::new(wrapped), Arc::new(move || breaker.is_available()))
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt()
@@ -204,7 +478,21 @@ async fn main() -> anyhow::Result<()> {
 
     // Load config
     let config_content = std::fs::read_to_string(&cli.config)?;
-    let proxy_config: ProxyConfig = toml::from_str(&config_content)?;
+    #[cfg_attr(not(feature = "web"), allow(unused_mut))]
+    let mut proxy_config: ProxyConfig = toml::from_str(&config_content)?;
+
+    // Password reset links go out through the notification SMTP server
+    #[cfg(feature = "web")]
+    if let Some(reset) = proxy_config
+        .web
+        .as_mut()
+        .and_then(|web| web.password_reset.as_mut())
+    {
+        reset.smtp = proxy_config.notifications.clone();
+        if reset.smtp.is_none() {
+            tracing::warn!("web.password_reset needs a [notifications] section; reset is disabled");
+        }
+    }
 
     tracing::info!("Loading configuration from {}", cli.config.display());
 
@@ -216,24 +504,40 @@ async fn main() -> anyhow::Result<()> {
         );
     }
 
+    if let Some(priority) = &proxy_config.enigma.io_priority {
+        enigma_core::limits::set_io_priority(priority)?;
+    }
+
     // Open manifest DB (shared between S3 state and Raft state machine)
     let db = ManifestDb::open(Path::new(&proxy_config.enigma.db_path))?;
     let shared_db = Arc::new(Mutex::new(db));
 
     // Get encryption key via factory
-    let passphrase = if proxy_config.enigma.key_provider == "local" {
+    let passphrase = if proxy_config.enigma.needs_passphrase() {
         Some(get_passphrase(&cli.passphrase)?)
     } else {
         None
     };
     let mut key_provider = enigma_keys::factory::create_key_provider(
         &proxy_config.enigma.key_provider,
-        passphrase.as_deref().map(|s| s.as_bytes()),
-        &proxy_config.enigma.keyfile_path,
-        proxy_config.enigma.vault_url.as_deref(),
-        proxy_config.enigma.gcp_project_id.as_deref(),
-        proxy_config.enigma.aws_region.as_deref(),
-        proxy_config.enigma.secret_prefix.as_deref(),
+        &enigma_keys::factory::KeyProviderOptions {
+            key_providers: &proxy_config.enigma.key_providers,
+            passphrase: passphrase.as_deref().map(|s| s.as_bytes()),
+            keyfile_path: &proxy_config.enigma.keyfile_path,
+            vault_url: proxy_config.enigma.vault_url.as_deref(),
+            gcp_project_id: proxy_config.enigma.gcp_project_id.as_deref(),
+            aws_region: proxy_config.enigma.aws_region.as_deref(),
+            secret_prefix: proxy_config.enigma.secret_prefix.as_deref(),
+            pkcs11_library: proxy_config.enigma.pkcs11_library.as_deref(),
+            pkcs11_slot: proxy_config.enigma.pkcs11_slot,
+            aws_kms_key_id: proxy_config.enigma.aws_kms_key_id.as_deref(),
+            aws_kms_key_store: proxy_config.enigma.aws_kms_key_store.as_deref(),
+            argon2: enigma_keys::local::Argon2Params {
+                memory_kib: proxy_config.enigma.argon2.memory_kib,
+                iterations: proxy_config.enigma.argon2.iterations,
+                parallelism: proxy_config.enigma.argon2.parallelism,
+            },
+        },
     )
     .await?;
 
@@ -286,54 +590,7 @@ async fn main() -> anyhow::Result<()> {
             }
         };
 
-        let provider: Box<dyn StorageProvider> = match pc.provider_type {
-            ProviderType::S3Compatible => {
-                let endpoint = pc.endpoint_url.as_deref().ok_or_else(|| {
-                    anyhow::anyhow!("S3Compatible provider '{}' requires endpoint_url", pc.name)
-                })?;
-                Box::new(
-                    S3StorageProvider::s3_compatible(
-                        &pc.bucket,
-                        endpoint,
-                        pc.region.as_deref(),
-                        &pc.name,
-                        pc.access_key.as_deref(),
-                        pc.secret_key.as_deref(),
-                    )
-                    .await?,
-                )
-            }
-            ProviderType::S3 => {
-                Box::new(S3StorageProvider::new(&pc.bucket, pc.region.as_deref(), &pc.name).await?)
-            }
-            ProviderType::Local => Box::new(enigma_storage::local::LocalStorageProvider::new(
-                Path::new(&pc.bucket),
-                &pc.name,
-            )?),
-            #[cfg(feature = "azure")]
-            ProviderType::Azure => {
-                let account = pc.access_key.as_deref().ok_or_else(|| {
-                    anyhow::anyhow!(
-                        "Azure provider '{}' requires access_key (storage account name)",
-                        pc.name
-                    )
-                })?;
-                let key = pc.secret_key.as_deref().ok_or_else(|| {
-                    anyhow::anyhow!(
-                        "Azure provider '{}' requires secret_key (storage account key)",
-                        pc.name
-                    )
-                })?;
-                Box::new(AzureStorageProvider::new(
-                    account, key, &pc.bucket, &pc.name,
-                )?)
-            }
-            #[cfg(feature = "gcs")]
-            ProviderType::Gcs => Box::new(GcsStorageProvider::new(&pc.bucket, &pc.name).await?),
-            _ => {
-                anyhow::bail!("Unsupported provider type: {:?}", pc.provider_type);
-            }
-        };
+        let provider = open_provider(pc).await?;
 
         tracing::info!("Testing connection to provider '{}'...", pc.name);
         provider.test_connection().await?;
@@ -400,47 +657,261 @@ async fn main() -> anyhow::Result<()> {
         }
     }
 
+    let provider_limiters =
+        enigma_s3::bandwidth::limiters(&proxy_config.providers, &provider_infos);
+
     // Setup distributor (reuse cached provider_infos — no extra DB lock needed)
-    let distributor = match proxy_config.enigma.distribution {
+    let mut distributor = match proxy_config.enigma.distribution {
         DistributionStrategy::RoundRobin => Distributor::round_robin(provider_infos)?,
         DistributionStrategy::Weighted => Distributor::weighted(provider_infos)?,
     };
 
+    // Wrap providers in circuit breakers so a dead backend fails fast; the
+    // distributor steers new chunks away from providers with an open circuit.
+    // The health checks are kept for the distributors built on config reload.
+    let mut health_checks: HashMap<i64, HealthCheck> = HashMap::new();
+    let storage_providers: HashMap<i64, Box<dyn StorageProvider>> = storage_providers
+        .into_iter()
+        .map(|(pid, provider)| {
+            let (wrapped, check) = with_circuit_breaker(provider);
+            distributor.set_health_check(pid, check.clone());
+            health_checks.insert(pid, check);
+            (pid, wrapped)
+        })
+        .collect();
+
     // Build the EnigmaConfig for the state
     let enigma_config = EnigmaConfig {
         enigma: proxy_config.enigma.clone(),
         providers: proxy_config.providers.clone(),
+        notifications: proxy_config.notifications.clone(),
+        webhooks: proxy_config.webhooks.clone(),
     };
 
+    // S3 callers other than the static key pair are API tokens of the web
+    // UI's users, kept in the same database
+    let auth_store = enigma_auth::SqliteAuthStore::open(&proxy_config.enigma.db_path)?;
+    auth_store.migrate().await?;
+    auth_store.seed_defaults().await?;
+    let store_auth = s3_auth::StoreAuth::new(
+        proxy_config.s3_proxy.access_key.clone(),
+        proxy_config.s3_proxy.secret_key.clone(),
+        Arc::new(auth_store),
+    );
+
     // Create shared state
     let state = Arc::new(EnigmaS3State {
         db: shared_db.clone(),
-        providers: storage_providers,
-        distributor,
+        providers: storage_providers.into(),
+        distributor: distributor.into(),
         key_material,
         config: enigma_config,
+        raft: Default::default(),
+        events: Default::default(),
+        usage: Default::default(),
+        access_control: Default::default(),
+        multipart_limits: MultipartLimits {
+            min_part_size_bytes: proxy_config.s3_proxy.multipart_min_part_size_bytes,
+            max_parts: proxy_config.s3_proxy.multipart_max_parts,
+            max_part_size_bytes: proxy_config.s3_proxy.upload_part_max_size_bytes,
+        },
+        chunk_cache: ChunkCache::from_settings(&proxy_config.enigma),
+        upload_semaphore: UploadSemaphore::from_settings(&proxy_config.enigma),
+        provider_limiters,
     });
+    let _ = state.usage.set(Arc::new(store_auth.clone()));
+    let _ = state.access_control.set(Arc::new(store_auth.clone()));
 
     // Build S3 service
-    let s3_service = EnigmaS3Service::new(state.clone());
+    let s3_service = EnigmaS3Service::new(state.clone())
+        .with_region(proxy_config.s3_proxy.default_region.clone());
+
+    // Log requests to buckets with server access logging on
+    let s3_service = AccessLogged::new(s3_service, AccessLog::start(shared_db.clone()));
 
     let mut s3_builder = S3ServiceBuilder::new(s3_service);
 
     // Setup auth
-    let auth = EnigmaS3Auth::new(
-        proxy_config.s3_proxy.access_key.clone(),
-        proxy_config.s3_proxy.secret_key.clone(),
-    );
-    s3_builder.set_auth(auth);
+    s3_builder.set_auth(store_auth);
+    s3_builder.set_access(EnigmaS3Access::new(state.clone()));
 
     let s3_service = s3_builder.build();
 
+    // Ctrl+C stops the listener and every background task
+    let (shutdown_tx, _) = tokio::sync::broadcast::channel::<()>(1);
+    {
+        let shutdown_tx = shutdown_tx.clone();
+        tokio::spawn(async move {
+            if let Err(e) = tokio::signal::ctrl_c().await {
+                tracing::error!("Failed to listen for Ctrl+C: {e}");
+                return;
+            }
+            tracing::info!("Received Ctrl+C — shutting down");
+            let _ = shutdown_tx.send(());
+        });
+    }
+
+    // SIGUSR1 re-reads the config file and applies provider changes
+    #[cfg(unix)]
+    reload::spawn_on_sigusr1(
+        reload::ConfigReloader::new(
+            cli.config.clone(),
+            state.clone(),
+            proxy_config.enigma.distribution,
+            health_checks,
+        ),
+        shutdown_tx.subscribe(),
+    )?;
+
+    // Abort multipart uploads abandoned by crashed clients
+    {
+        let state = state.clone();
+        let expiry_hours = proxy_config.s3_proxy.multipart_expiry_hours;
+        let mut shutdown = shutdown_tx.subscribe();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(3600));
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = shutdown.recv() => break,
+                }
+                match enigma_s3::multipart::abort_expired_uploads(&state, expiry_hours).await {
+                    Ok(0) => {}
+                    Ok(n) => tracing::info!("Aborted {n} expired multipart upload(s)"),
+                    Err(e) => tracing::error!("Multipart upload cleanup failed: {e}"),
+                }
+            }
+        });
+    }
+
+    // Purge deleted buckets once their recovery period is over
+    {
+        let state = state.clone();
+        let recovery_days = proxy_config.enigma.namespace_recovery_days;
+        let mut shutdown = shutdown_tx.subscribe();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(3600));
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = shutdown.recv() => break,
+                }
+                match enigma_s3::ops::purge_deleted_namespaces(&state, recovery_days).await {
+                    Ok(0) => {}
+                    Ok(n) => tracing::info!("Purged {n} deleted namespace(s)"),
+                    Err(e) => tracing::error!("Deleted namespace cleanup failed: {e}"),
+                }
+            }
+        });
+    }
+
+    // Drop S3 access log entries once their retention period is over
+    {
+        let state = state.clone();
+        let retention_days = proxy_config.enigma.access_log_retention_days;
+        let mut shutdown = shutdown_tx.subscribe();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(3600));
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = shutdown.recv() => break,
+                }
+                match enigma_s3::access_log::purge_expired(&state, retention_days) {
+                    Ok(0) => {}
+                    Ok(n) => tracing::info!("Purged {n} expired S3 access log entries"),
+                    Err(e) => tracing::error!("S3 access log cleanup failed: {e}"),
+                }
+            }
+        });
+    }
+
+    // Write the S3 inventory report of buckets with an inventory configuration
+    #[cfg(feature = "inventory")]
+    {
+        let state = state.clone();
+        let mut shutdown = shutdown_tx.subscribe();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(24 * 3600));
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = shutdown.recv() => break,
+                }
+                match enigma_s3::inventory::generate_report(&state).await {
+                    Ok(None) => {}
+                    Ok(Some(report)) => tracing::info!(
+                        "Wrote inventory report {} ({} objects)",
+                        report.key,
+                        report.rows
+                    ),
+                    Err(e) => tracing::error!("Inventory report failed: {e}"),
+                }
+            }
+        });
+    }
+
+    // POST queued webhook events
+    if !proxy_config.webhooks.is_empty() {
+        let state = state.clone();
+        let mut shutdown = shutdown_tx.subscribe();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(5));
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = shutdown.recv() => break,
+                }
+                match webhook::deliver_queued(&state.db, &state.config.webhooks).await {
+                    Ok(0) => {}
+                    Ok(n) => tracing::debug!("Delivered {n} webhook(s)"),
+                    Err(e) => tracing::error!("Webhook delivery failed: {e}"),
+                }
+            }
+        });
+    }
+
+    // The manifest has automatic checkpoints off; keep its WAL from growing
+    {
+        let state = state.clone();
+        let every =
+            Duration::from_secs(proxy_config.s3_proxy.wal_checkpoint_interval_seconds.max(1));
+        let mut shutdown = shutdown_tx.subscribe();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(every);
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = shutdown.recv() => break,
+                }
+                let result = match state.db.lock() {
+                    Ok(db) => db
+                        .checkpoint(CheckpointMode::Passive)
+                        .map_err(anyhow::Error::from),
+                    Err(_) => Err(anyhow::anyhow!("db lock")),
+                };
+                match result {
+                    Ok((frames, checkpointed)) => {
+                        tracing::debug!("WAL checkpoint: {checkpointed}/{frames} frames")
+                    }
+                    Err(e) => tracing::error!("WAL checkpoint failed: {e}"),
+                }
+            }
+        });
+    }
+
     // Optionally start Prometheus metrics server
     #[cfg(feature = "metrics")]
     if let Some(ref metrics_addr) = proxy_config.s3_proxy.metrics_addr {
         let addr: SocketAddr = metrics_addr.parse()?;
         tracing::info!("Starting metrics server on {addr}");
-        tokio::spawn(metrics::serve_metrics(addr));
+        let mut shutdown = shutdown_tx.subscribe();
+        tokio::spawn(async move {
+            tokio::select! {
+                _ = metrics::serve_metrics(addr) => {}
+                _ = shutdown.recv() => {}
+            }
+        });
     }
 
     // Determine if we're in multi-node Raft mode
@@ -449,6 +920,34 @@ async fn main() -> anyhow::Result<()> {
         .as_ref()
         .is_some_and(|rc| !rc.is_single_node());
 
+    // Rotate the encryption key on a schedule. Every node of a cluster would
+    // rotate on its own, so this is single-node only.
+    let rotation = match proxy_config.key_rotation.clone() {
+        Some(config) => Some(config),
+        None => key_rotation::KeyRotationConfig::from_env()?,
+    };
+    if let Err(e) = key_rotation::spawn_pending_reencryption(&state, key_provider.as_ref()).await {
+        tracing::error!("Cannot resume re-encryption after key rotation: {e}");
+    }
+    if let Some(config) = rotation {
+        if is_multi_node {
+            tracing::warn!("Scheduled key rotation is not supported in multi-node mode; ignoring");
+        } else {
+            let schedule = config.parse_schedule()?;
+            tracing::info!(
+                "Key rotation scheduled \"{}\" (max key age: {} days)",
+                config.schedule,
+                config.max_key_age_days
+            );
+            let rotator = key_rotation::KeyRotator::new(key_provider, state.clone(), &config);
+            tokio::spawn(key_rotation::run(
+                rotator,
+                schedule,
+                shutdown_tx.subscribe(),
+            ));
+        }
+    }
+
     if let Some(raft_config) = proxy_config.raft.as_ref().filter(|_| is_multi_node) {
         let node_id = raft_config.node_id;
 
@@ -501,9 +1000,21 @@ async fn main() -> anyhow::Result<()> {
         let state_machine = enigma_raft::state_machine::EnigmaStateMachine::new(
             shared_db.clone(),
             proxy_config.enigma.db_path.clone(),
-        );
-        let network = enigma_raft::network::EnigmaNetworkFactory::new(peer_map.clone());
-        #[allow(unused_variables)]
+        )
+        .with_snapshot_compression_level(raft_config.raft_snapshot_compression_level);
+        let raft_tls = match &raft_config.tls {
+            Some(tls) => {
+                let (server, client) = enigma_raft::tls::load_raft_tls(tls)?;
+                enigma_raft::tls::set_forward_tls(client.clone());
+                tracing::info!("Raft gRPC mutual TLS enabled");
+                Some((server, client))
+            }
+            None => None,
+        };
+        let mut network = enigma_raft::network::EnigmaNetworkFactory::new(peer_map.clone());
+        if let Some((_, client)) = &raft_tls {
+            network = network.with_tls(client.clone());
+        }
         let shared_peers = network.peers.clone();
         tracing::info!("Creating Raft engine...");
 
@@ -530,17 +1041,46 @@ async fn main() -> anyhow::Result<()> {
         .await?;
         tracing::info!("Raft engine created successfully");
         let raft = Arc::new(raft);
+        let _ = state.raft.set(raft.clone());
+
+        if raft_config.enable_prevote {
+            tokio::spawn(enigma_raft::prevote::run(
+                raft.clone(),
+                shared_peers.clone(),
+                Duration::from_millis(raft_config.heartbeat_interval_ms),
+            ));
+        }
+        if let Some(lease) = raft_config.leader_lease()? {
+            enigma_raft::read::set_leader_lease(lease);
+            tracing::info!("Raft leader lease: {lease:?}");
+        }
+
+        #[cfg(feature = "metrics")]
+        tokio::spawn(enigma_raft::metrics::track_state(raft.metrics()));
 
         // Start gRPC server for inter-node communication
         let grpc_addr: SocketAddr = raft_config.grpc_addr.parse()?;
-        let grpc_server = enigma_raft::grpc_server::EnigmaRaftGrpcServer::new(raft.clone());
+        let grpc_server =
+            enigma_raft::grpc_server::EnigmaRaftGrpcServer::new(raft.clone(), shared_db.clone());
         let grpc_svc = enigma_raft::proto::raft_service_server::RaftServiceServer::new(grpc_server);
+        let health_svc = enigma_raft::health::proto::health_server::HealthServer::new(
+            enigma_raft::health::HealthService,
+        );
 
         tracing::info!("Starting Raft gRPC server on {grpc_addr}");
+        let mut grpc_builder = tonic::transport::Server::builder();
+        if let Some((server, _)) = raft_tls {
+            grpc_builder = grpc_builder.tls_config(server)?;
+        }
+        let mut shutdown = shutdown_tx.subscribe();
         tokio::spawn(async move {
-            if let Err(e) = tonic::transport::Server::builder()
+            let stopped = async move {
+                let _ = shutdown.recv().await;
+            };
+            if let Err(e) = grpc_builder
                 .add_service(grpc_svc)
-                .serve(grpc_addr)
+                .add_service(health_svc)
+                .serve_with_shutdown(grpc_addr, stopped)
                 .await
             {
                 tracing::error!("Raft gRPC server error: {e}");
@@ -575,6 +1115,8 @@ async fn main() -> anyhow::Result<()> {
                 raft: raft.clone(),
                 node_id,
                 peers: shared_peers,
+                prevote: raft_config.enable_prevote,
+                leader_lease_ms: raft_config.leader_lease_ms,
             }));
 
         // Spawn leadership watch — start/stop web UI based on leadership
@@ -586,6 +1128,7 @@ async fn main() -> anyhow::Result<()> {
             let s3_state_for_web = state.clone();
             let cluster_handle_for_web = cluster_handle.clone();
             let mut metrics_rx = raft.metrics();
+            let mut shutdown = shutdown_tx.subscribe();
 
             tokio::spawn(async move {
                 let mut web_handle: Option<(
@@ -632,9 +1175,22 @@ async fn main() -> anyhow::Result<()> {
                         }
                     }
 
-                    if metrics_rx.changed().await.is_err() {
-                        tracing::warn!("Raft metrics channel closed — exiting leadership watch");
-                        break;
+                    tokio::select! {
+                        changed = metrics_rx.changed() => {
+                            if changed.is_err() {
+                                tracing::warn!(
+                                    "Raft metrics channel closed — exiting leadership watch"
+                                );
+                                break;
+                            }
+                        }
+                        _ = shutdown.recv() => {
+                            if let Some((handle, tx)) = web_handle.take() {
+                                let _ = tx.send(());
+                                let _ = handle.await;
+                            }
+                            break;
+                        }
                     }
                 }
             });
@@ -652,13 +1208,19 @@ async fn main() -> anyhow::Result<()> {
             let db_path = proxy_config.enigma.db_path.clone();
             let enigma_settings = proxy_config.enigma.clone();
             let s3_state_for_web = Some(state.clone());
+            let (web_shutdown_tx, web_shutdown_rx) = tokio::sync::oneshot::channel();
+            let mut shutdown = shutdown_tx.subscribe();
+            tokio::spawn(async move {
+                let _ = shutdown.recv().await;
+                let _ = web_shutdown_tx.send(());
+            });
             tokio::spawn(async move {
                 if let Err(e) = enigma_web::start_web_server(
                     web_config,
                     &db_path,
                     enigma_settings,
                     s3_state_for_web,
-                    None,
+                    Some(web_shutdown_rx),
                     None,
                 )
                 .await
@@ -694,40 +1256,25 @@ async fn main() -> anyhow::Result<()> {
         _ => None,
     };
 
-    loop {
-        let (stream, _remote_addr) = listener.accept().await?;
-        let service = shared_service.clone();
-
+    server::serve(
+        listener,
+        shared_service,
         #[cfg(feature = "tls")]
-        let tls_acceptor = tls_acceptor.clone();
-
-        tokio::spawn(async move {
-            let builder =
-                hyper_util::server::conn::auto::Builder::new(hyper_util::rt::TokioExecutor::new());
-
-            #[cfg(feature = "tls")]
-            if let Some(ref acceptor) = tls_acceptor {
-                match acceptor.accept(stream).await {
-                    Ok(tls_stream) => {
-                        let io = hyper_util::rt::TokioIo::new(tls_stream);
-                        if let Err(e) = builder.serve_connection(io, service).await {
-                            tracing::error!("TLS connection error: {e}");
-                        }
-                        return;
-                    }
-                    Err(e) => {
-                        tracing::error!("TLS handshake error: {e}");
-                        return;
-                    }
-                }
-            }
+        tls_acceptor,
+        &shutdown_tx,
+        Duration::from_secs(proxy_config.s3_proxy.shutdown_timeout_seconds),
+    )
+    .await?;
 
-            let io = hyper_util::rt::TokioIo::new(stream);
-            if let Err(e) = builder.serve_connection(io, service).await {
-                tracing::error!("Connection error: {e}");
-            }
-        });
+    // Leave the Raft cluster cleanly so peers stop sending to this node
+    if let Some(raft) = state.raft.get() {
+        tracing::info!("Shutting down Raft");
+        if let Err(e) = raft.shutdown().await {
+            tracing::error!("Raft shutdown failed: {e}");
+        }
     }
+    tracing::info!("Enigma S3 proxy stopped");
+    Ok(())
 }
 
 #[cfg(feature = "tls")]
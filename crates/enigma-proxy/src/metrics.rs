@@ -81,9 +81,25 @@ pub static METRICS: LazyLock<Metrics> = LazyLock::new(|| {
     }
 });
 
+/// Counts a connection in `enigma_active_connections` until dropped.
+pub struct ConnectionGuard(());
+
+pub fn track_connection() -> ConnectionGuard {
+    METRICS.active_connections.inc();
+    ConnectionGuard(())
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        METRICS.active_connections.dec();
+    }
+}
+
+/// Proxy metrics plus those the S3 and Raft crates register globally.
 fn render_metrics() -> Vec<u8> {
     let encoder = TextEncoder::new();
-    let metric_families = METRICS.registry.gather();
+    let mut metric_families = METRICS.registry.gather();
+    metric_families.extend(prometheus::gather());
     let mut buffer = Vec::new();
     encoder.encode(&metric_families, &mut buffer).unwrap();
     buffer
@@ -95,6 +111,8 @@ pub async fn serve_metrics(addr: SocketAddr) {
     use hyper::service::service_fn;
     use hyper::{Request, Response};
 
+    enigma_s3::metrics::register();
+
     let listener = match tokio::net::TcpListener::bind(addr).await {
         Ok(l) => l,
         Err(e) => {
@@ -126,3 +144,150 @@ pub async fn serve_metrics(addr: SocketAddr) {
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use enigma_core::config::EnigmaConfig;
+    use enigma_core::distributor::Distributor;
+    use enigma_core::manifest::ManifestDb;
+    use enigma_core::types::{KeyMaterial, ProviderType};
+    use enigma_s3::EnigmaS3State;
+    use enigma_s3::ops;
+    use enigma_storage::local::LocalStorageProvider;
+    use enigma_storage::provider::StorageProvider;
+
+    fn free_addr() -> SocketAddr {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap()
+    }
+
+    /// GET /metrics and return the response (headers and body).
+    async fn scrape(addr: SocketAddr) -> String {
+        let mut stream = loop {
+            match tokio::net::TcpStream::connect(addr).await {
+                Ok(s) => break s,
+                Err(_) => tokio::time::sleep(std::time::Duration::from_millis(20)).await,
+            }
+        };
+        stream
+            .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    /// Sum of all samples of `name`, across label sets.
+    fn sample(text: &str, name: &str) -> f64 {
+        text.lines()
+            .filter(|l| !l.starts_with('#'))
+            .filter_map(|l| {
+                let (series, value) = l.rsplit_once(' ')?;
+                let metric = series.split('{').next()?;
+                (metric == name).then(|| value.parse::<f64>().ok())?
+            })
+            .sum()
+    }
+
+    fn generate_data(size: usize) -> Vec<u8> {
+        let mut data = Vec::with_capacity(size);
+        let mut state: u64 = 0x1234_5678_9abc_def0;
+        while data.len() < size {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            data.extend_from_slice(&state.to_le_bytes());
+        }
+        data.truncate(size);
+        data
+    }
+
+    #[tokio::test]
+    async fn put_get_cycle_updates_counters() {
+        let addr = free_addr();
+        tokio::spawn(serve_metrics(addr));
+        let before = scrape(addr).await;
+        assert!(before.contains("text/plain; version=0.0.4"));
+        assert!(before.contains("# TYPE enigma_chunks_uploaded_total counter"));
+
+        let tmp = tempfile::tempdir().unwrap();
+        let db = ManifestDb::open_in_memory().unwrap();
+        let pid = db
+            .insert_provider(
+                "local",
+                ProviderType::Local,
+                tmp.path().to_str().unwrap(),
+                None,
+                1,
+            )
+            .unwrap();
+        let ns = db.create_namespace("bucket").unwrap();
+        let distributor = Distributor::round_robin(db.list_providers().unwrap()).unwrap();
+        let mut providers: HashMap<i64, Box<dyn StorageProvider>> = HashMap::new();
+        providers.insert(
+            pid,
+            Box::new(LocalStorageProvider::new(&tmp.path().join("chunks"), "local").unwrap()),
+        );
+        let mut config = EnigmaConfig::default_config(tmp.path());
+        config.enigma.compression.enabled = false;
+        let state = Arc::new(EnigmaS3State {
+            db: Arc::new(Mutex::new(db)),
+            providers: providers.into(),
+            distributor: distributor.into(),
+            key_material: KeyMaterial {
+                id: "test-key-1".to_string(),
+                key: [0x42; 32],
+            },
+            config,
+            raft: Default::default(),
+            events: Default::default(),
+            usage: Default::default(),
+            access_control: Default::default(),
+            multipart_limits: Default::default(),
+            chunk_cache: Default::default(),
+            upload_semaphore: Default::default(),
+            provider_limiters: Default::default(),
+        });
+
+        // The second PUT of identical data is fully deduplicated
+        let data = generate_data(1024 * 1024);
+        ops::store_object(&state, "bucket", "a.bin", &data, None)
+            .await
+            .unwrap();
+        ops::store_object(&state, "bucket", "b.bin", &data, None)
+            .await
+            .unwrap();
+        let read = ops::retrieve_object_parallel(&state, "bucket", "a.bin")
+            .await
+            .unwrap();
+        assert_eq!(read, data);
+
+        // Both objects share every chunk: only the last delete orphans them
+        ops::remove_object(&state, "bucket", "b.bin").await.unwrap();
+        let chunks = {
+            let db = state.db.lock().unwrap();
+            db.get_object(ns, "a.bin").unwrap().unwrap().4 as f64
+        };
+        ops::remove_object(&state, "bucket", "a.bin").await.unwrap();
+
+        let after = scrape(addr).await;
+        let delta = |name: &str| sample(&after, name) - sample(&before, name);
+
+        assert!(chunks >= 1.0);
+        assert_eq!(delta("enigma_chunks_uploaded_total"), chunks);
+        assert_eq!(delta("enigma_chunks_deduped_total"), chunks);
+        assert_eq!(delta("enigma_chunk_upload_duration_seconds_count"), chunks);
+        let uploaded = delta("enigma_bytes_uploaded_total");
+        assert!(uploaded > data.len() as f64, "ciphertext adds a tag");
+        assert_eq!(delta("enigma_bytes_downloaded_total"), uploaded);
+        assert_eq!(delta("enigma_gc_orphans_total"), chunks);
+        assert_eq!(delta("enigma_provider_errors_total"), 0.0);
+        assert!(after.contains("enigma_chunk_upload_duration_seconds_count{provider=\"local\"}"));
+    }
+}
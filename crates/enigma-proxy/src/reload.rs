@@ -0,0 +1,438 @@
+//! Config hot-reload. On SIGUSR1 the proxy re-reads its config file and
+//! applies changes to `[[providers]]` without a restart:
+//!
+//! - new providers are opened, their connection tested, recorded in the
+//!   manifest if missing, and added to the S3 state;
+//! - changed weights are saved and a new distributor takes over;
+//! - providers removed from the file are only logged. They stay in use
+//!   until the next restart, so requests in flight keep their backend.
+//!
+//! Everything else (listen addresses, S3 credentials, `[web]`, `[raft]`)
+//! needs a full restart.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use serde::Deserialize;
+use tokio::signal::unix::{SignalKind, signal};
+use tokio::sync::broadcast;
+
+use enigma_core::config::ProviderConfig;
+use enigma_core::distributor::{Distributor, HealthCheck};
+use enigma_core::types::{DistributionStrategy, ProviderInfo};
+use enigma_s3::SharedState;
+use enigma_storage::provider::StorageProvider;
+
+/// The part of the proxy config a reload looks at; other sections are ignored.
+#[derive(Deserialize)]
+struct ProvidersSection {
+    #[serde(default)]
+    providers: Vec<ProviderConfig>,
+}
+
+/// Provider names affected by a reload.
+#[derive(Debug, Default)]
+pub struct ReloadSummary {
+    pub added: Vec<String>,
+    pub reweighted: Vec<String>,
+    /// In the running proxy but no longer in the file; kept until restart.
+    pub removed: Vec<String>,
+}
+
+/// Applies provider changes from the config file to the running proxy.
+pub struct ConfigReloader {
+    config_path: PathBuf,
+    state: SharedState,
+    strategy: DistributionStrategy,
+    /// Circuit breaker checks of every provider, for the next distributor.
+    health_checks: HashMap<i64, HealthCheck>,
+}
+
+impl ConfigReloader {
+    pub fn new(
+        config_path: PathBuf,
+        state: SharedState,
+        strategy: DistributionStrategy,
+        health_checks: HashMap<i64, HealthCheck>,
+    ) -> Self {
+        Self {
+            config_path,
+            state,
+            strategy,
+            health_checks,
+        }
+    }
+
+    /// Re-read the config file and apply its provider changes. Fails
+    /// without changing anything if the file cannot be read or parsed, or
+    /// if a weighted distributor would be left with only zero weights.
+    pub async fn reload(&mut self) -> anyhow::Result<ReloadSummary> {
+        let content = tokio::fs::read_to_string(&self.config_path).await?;
+        let section: ProvidersSection = toml::from_str(&content)?;
+
+        let mut infos = self.state.distributor.load().providers().to_vec();
+        let mut summary = ReloadSummary::default();
+        let mut new_weights = Vec::new();
+
+        // Open and test new providers first; nothing is changed until the
+        // resulting weights are known to be usable
+        let mut opened = Vec::new();
+        for pc in &section.providers {
+            let known = infos.iter().position(|info| info.name == pc.name);
+            if let Some(idx) = known
+                && self.state.providers.contains(&infos[idx].id)
+            {
+                if infos[idx].weight != pc.weight {
+                    infos[idx].weight = pc.weight;
+                    new_weights.push((infos[idx].id, pc.weight));
+                    summary.reweighted.push(pc.name.clone());
+                }
+                continue;
+            }
+
+            match open_and_test(pc).await {
+                Ok(provider) => {
+                    if let Some(idx) = known {
+                        infos[idx].weight = pc.weight;
+                    }
+                    opened.push((known, pc, provider));
+                }
+                Err(e) => tracing::error!("Reload: provider '{}' not added: {e}", pc.name),
+            }
+        }
+
+        for info in &infos {
+            if self.state.providers.contains(&info.id)
+                && !section.providers.iter().any(|pc| pc.name == info.name)
+            {
+                summary.removed.push(info.name.clone());
+            }
+        }
+
+        if opened.is_empty() && summary.reweighted.is_empty() {
+            return Ok(summary);
+        }
+        if self.strategy == DistributionStrategy::Weighted
+            && infos.iter().all(|info| info.weight == 0)
+            && opened
+                .iter()
+                .all(|(known, pc, _)| known.is_some() || pc.weight == 0)
+        {
+            anyhow::bail!("every provider has weight 0; keeping the current distributor");
+        }
+
+        for (known, pc, provider) in opened {
+            match self.add_provider(pc, provider) {
+                Ok(info) => {
+                    match known {
+                        Some(idx) => infos[idx] = info,
+                        None => infos.push(info),
+                    }
+                    summary.added.push(pc.name.clone());
+                }
+                Err(e) => tracing::error!("Reload: provider '{}' not added: {e}", pc.name),
+            }
+        }
+
+        {
+            let db = self
+                .state
+                .db
+                .lock()
+                .map_err(|e| anyhow::anyhow!("db lock poisoned: {e}"))?;
+            for (id, weight) in new_weights {
+                db.set_provider_weight(id, weight)?;
+            }
+        }
+        let mut distributor = match self.strategy {
+            DistributionStrategy::RoundRobin => Distributor::round_robin(infos)?,
+            DistributionStrategy::Weighted => Distributor::weighted(infos)?,
+        };
+        for (id, check) in &self.health_checks {
+            distributor.set_health_check(*id, check.clone());
+        }
+        self.state.distributor.store(distributor);
+        Ok(summary)
+    }
+
+    /// Add an opened provider to the manifest (unless it is already there)
+    /// and the S3 state.
+    fn add_provider(
+        &mut self,
+        pc: &ProviderConfig,
+        provider: Box<dyn StorageProvider>,
+    ) -> anyhow::Result<ProviderInfo> {
+        let id = {
+            let db = self
+                .state
+                .db
+                .lock()
+                .map_err(|e| anyhow::anyhow!("db lock poisoned: {e}"))?;
+            match db.list_providers()?.into_iter().find(|p| p.name == pc.name) {
+                Some(existing) => {
+                    if existing.weight != pc.weight {
+                        db.set_provider_weight(existing.id, pc.weight)?;
+                    }
+                    existing.id
+                }
+                None => db.insert_provider(
+                    &pc.name,
+                    pc.provider_type,
+                    &pc.bucket,
+                    pc.region.as_deref(),
+                    pc.weight,
+                )?,
+            }
+        };
+
+        let (provider, check) = crate::with_circuit_breaker(provider);
+        self.health_checks.insert(id, check);
+        self.state.providers.insert(id, Arc::from(provider));
+        Ok(ProviderInfo {
+            id,
+            name: pc.name.clone(),
+            provider_type: pc.provider_type,
+            bucket: pc.bucket.clone(),
+            region: pc.region.clone(),
+            weight: pc.weight,
+        })
+    }
+}
+
+/// Open a configured provider and test its connection.
+async fn open_and_test(pc: &ProviderConfig) -> anyhow::Result<Box<dyn StorageProvider>> {
+    let provider = crate::open_provider(pc).await?;
+    provider.test_connection().await?;
+    Ok(provider)
+}
+
+/// Reload the config on every SIGUSR1 until shutdown. The handler is
+/// installed before this returns, so a signal sent afterwards never takes
+/// the default action (terminating the process).
+pub fn spawn_on_sigusr1(
+    mut reloader: ConfigReloader,
+    mut shutdown: broadcast::Receiver<()>,
+) -> std::io::Result<tokio::task::JoinHandle<()>> {
+    let mut signals = signal(SignalKind::user_defined1())?;
+    Ok(tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                received = signals.recv() => {
+                    if received.is_none() {
+                        break;
+                    }
+                }
+                _ = shutdown.recv() => break,
+            }
+            tracing::info!(
+                "Received SIGUSR1 — reloading providers from {}",
+                reloader.config_path.display()
+            );
+            match reloader.reload().await {
+                Ok(summary) => {
+                    for name in &summary.added {
+                        tracing::info!("Reload: added provider '{name}'");
+                    }
+                    for name in &summary.reweighted {
+                        tracing::info!("Reload: updated weight of provider '{name}'");
+                    }
+                    for name in &summary.removed {
+                        tracing::warn!(
+                            "Reload: provider '{name}' is no longer configured but stays in use until restart"
+                        );
+                    }
+                }
+                Err(e) => tracing::error!("Config reload failed: {e}"),
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use enigma_core::config::EnigmaConfig;
+    use enigma_core::manifest::ManifestDb;
+    use enigma_core::types::{KeyMaterial, ProviderType};
+    use enigma_s3::EnigmaS3State;
+    use enigma_storage::local::LocalStorageProvider;
+
+    fn provider_toml(name: &str, dir: &std::path::Path, weight: u32) -> String {
+        format!(
+            "[[providers]]\nname = \"{name}\"\ntype = \"Local\"\nbucket = \"{}\"\nweight = {weight}\n\n",
+            dir.join(name).display()
+        )
+    }
+
+    /// State with a single local provider "a".
+    fn state_with_provider_a(dir: &std::path::Path) -> SharedState {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let bucket = dir.join("a");
+        let pid = db
+            .insert_provider("a", ProviderType::Local, bucket.to_str().unwrap(), None, 1)
+            .unwrap();
+        let distributor = Distributor::weighted(db.list_providers().unwrap()).unwrap();
+        let mut providers: HashMap<i64, Box<dyn StorageProvider>> = HashMap::new();
+        providers.insert(
+            pid,
+            Box::new(LocalStorageProvider::new(&bucket, "a").unwrap()),
+        );
+        Arc::new(EnigmaS3State {
+            db: Arc::new(Mutex::new(db)),
+            providers: providers.into(),
+            distributor: distributor.into(),
+            key_material: KeyMaterial {
+                id: "test-key-1".to_string(),
+                key: [0x42; 32],
+            },
+            config: EnigmaConfig::default_config(dir),
+            raft: Default::default(),
+            events: Default::default(),
+            usage: Default::default(),
+            access_control: Default::default(),
+            multipart_limits: Default::default(),
+            chunk_cache: Default::default(),
+            upload_semaphore: Default::default(),
+            provider_limiters: Default::default(),
+        })
+    }
+
+    fn provider_names(state: &SharedState) -> Vec<(String, u32)> {
+        let mut names: Vec<_> = state
+            .distributor
+            .load()
+            .providers()
+            .iter()
+            .map(|p| (p.name.clone(), p.weight))
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[tokio::test]
+    async fn sigusr1_adds_new_providers() {
+        let tmp = tempfile::tempdir().unwrap();
+        let config_path = tmp.path().join("proxy.toml");
+        std::fs::write(&config_path, provider_toml("a", tmp.path(), 1)).unwrap();
+        let state = state_with_provider_a(tmp.path());
+
+        let (shutdown_tx, _) = broadcast::channel(1);
+        let reloader = ConfigReloader::new(
+            config_path.clone(),
+            state.clone(),
+            DistributionStrategy::Weighted,
+            HashMap::new(),
+        );
+        let task = spawn_on_sigusr1(reloader, shutdown_tx.subscribe()).unwrap();
+
+        // Add "b", reweight "a"
+        std::fs::write(
+            &config_path,
+            provider_toml("a", tmp.path(), 3) + &provider_toml("b", tmp.path(), 2),
+        )
+        .unwrap();
+        assert_eq!(unsafe { libc::raise(libc::SIGUSR1) }, 0);
+
+        // The new distributor is the last thing a reload stores
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+        while state.distributor.load().providers().len() < 2 {
+            assert!(tokio::time::Instant::now() < deadline, "provider not added");
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        assert_eq!(
+            provider_names(&state),
+            vec![("a".to_string(), 3), ("b".to_string(), 2)]
+        );
+        let db_providers = state.db.lock().unwrap().list_providers().unwrap();
+        let b = db_providers.iter().find(|p| p.name == "b").unwrap();
+        let provider = state.providers.get(&b.id).unwrap();
+        provider.upload_chunk("k", b"data").await.unwrap();
+        assert!(tmp.path().join("b").exists());
+        assert_eq!(
+            db_providers.iter().find(|p| p.name == "a").unwrap().weight,
+            3
+        );
+
+        let _ = shutdown_tx.send(());
+        task.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn removed_providers_stay_until_restart() {
+        let tmp = tempfile::tempdir().unwrap();
+        let config_path = tmp.path().join("proxy.toml");
+        std::fs::write(&config_path, provider_toml("b", tmp.path(), 1)).unwrap();
+        let state = state_with_provider_a(tmp.path());
+        let mut reloader = ConfigReloader::new(
+            config_path,
+            state.clone(),
+            DistributionStrategy::Weighted,
+            HashMap::new(),
+        );
+
+        let summary = reloader.reload().await.unwrap();
+        assert_eq!(summary.added, vec!["b"]);
+        assert_eq!(summary.removed, vec!["a"]);
+        assert_eq!(
+            provider_names(&state),
+            vec![("a".to_string(), 1), ("b".to_string(), 1)]
+        );
+        assert_eq!(state.providers.snapshot().len(), 2);
+
+        // Nothing changed since: no new distributor, "a" still reported
+        let before = state.distributor.load();
+        let summary = reloader.reload().await.unwrap();
+        assert!(summary.added.is_empty() && summary.reweighted.is_empty());
+        assert!(Arc::ptr_eq(&before, &state.distributor.load()));
+    }
+
+    #[tokio::test]
+    async fn unreadable_config_changes_nothing() {
+        let tmp = tempfile::tempdir().unwrap();
+        let config_path = tmp.path().join("proxy.toml");
+        std::fs::write(&config_path, "[[providers]]\nname = ").unwrap();
+        let state = state_with_provider_a(tmp.path());
+        let before = state.distributor.load();
+        let mut reloader = ConfigReloader::new(
+            config_path,
+            state.clone(),
+            DistributionStrategy::Weighted,
+            HashMap::new(),
+        );
+
+        assert!(reloader.reload().await.is_err());
+        assert!(Arc::ptr_eq(&before, &state.distributor.load()));
+        assert_eq!(state.providers.snapshot().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn all_zero_weights_add_nothing() {
+        let tmp = tempfile::tempdir().unwrap();
+        let config_path = tmp.path().join("proxy.toml");
+        std::fs::write(
+            &config_path,
+            provider_toml("a", tmp.path(), 0) + &provider_toml("b", tmp.path(), 0),
+        )
+        .unwrap();
+        let state = state_with_provider_a(tmp.path());
+        let before = state.distributor.load();
+        let mut reloader = ConfigReloader::new(
+            config_path,
+            state.clone(),
+            DistributionStrategy::Weighted,
+            HashMap::new(),
+        );
+
+        assert!(reloader.reload().await.is_err());
+        assert!(Arc::ptr_eq(&before, &state.distributor.load()));
+        assert_eq!(state.providers.snapshot().len(), 1);
+        let db_providers = state.db.lock().unwrap().list_providers().unwrap();
+        assert_eq!(db_providers.len(), 1);
+        assert_eq!(db_providers[0].weight, 1);
+    }
+}
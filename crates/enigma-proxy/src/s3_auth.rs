@@ -0,0 +1,467 @@
+//! S3 callers, their permissions and their storage usage, from the auth
+//! store the web UI manages. The static key pair of `[s3_proxy]` belongs to
+//! the operator and holds every permission; any other access key is the ID
+//! of an API token, which holds its user's permissions narrowed by its
+//! scopes.
+
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use enigma_auth::{
+    ApiToken, AuthError, AuthStore, User, has_permission, token_allows_ip, token_has_scope,
+};
+use enigma_s3::auth::AccessControl;
+use enigma_s3::usage::UsageAccounting;
+use s3s::auth::{S3Auth, SecretKey};
+use s3s::s3_error;
+
+#[derive(Clone)]
+pub struct StoreAuth {
+    access_key: String,
+    secret_key: String,
+    store: Arc<dyn AuthStore>,
+}
+
+impl StoreAuth {
+    pub fn new(access_key: String, secret_key: String, store: Arc<dyn AuthStore>) -> Self {
+        Self {
+            access_key,
+            secret_key,
+            store,
+        }
+    }
+
+    /// The token behind `access_key` and its user; `None` for the static
+    /// key.
+    async fn caller(&self, access_key: &str) -> Result<Option<(ApiToken, User)>, AuthError> {
+        if access_key == self.access_key {
+            return Ok(None);
+        }
+        let (token, user, _) = self.store.verify_s3_access_key(access_key).await?;
+        Ok(Some((token, user)))
+    }
+}
+
+/// Requests made with a token are signed with the token's hash, the secret
+/// the server keeps.
+#[async_trait]
+impl S3Auth for StoreAuth {
+    async fn get_secret_key(&self, access_key: &str) -> s3s::S3Result<SecretKey> {
+        if access_key == self.access_key {
+            return Ok(SecretKey::from(self.secret_key.clone()));
+        }
+        let (token, _, token_hash) = self
+            .store
+            .verify_s3_access_key(access_key)
+            .await
+            .map_err(|_| s3_error!(InvalidAccessKeyId))?;
+        // Touch last_used_at and count the request in background; the body
+        // size is not known yet, so no bytes are counted
+        let store = self.store.clone();
+        tokio::spawn(async move {
+            if let Err(e) = store.touch_token(&token.id).await {
+                tracing::warn!("Failed to update token last_used_at: {e}");
+            }
+            if let Err(e) = store.record_token_usage(&token.id, 0).await {
+                tracing::warn!("Failed to record token usage: {e}");
+            }
+        });
+        Ok(SecretKey::from(token_hash))
+    }
+}
+
+#[async_trait]
+impl AccessControl for StoreAuth {
+    async fn has_permission(&self, access_key: &str, permission: &str) -> anyhow::Result<bool> {
+        let (token, user) = match self.caller(access_key).await {
+            Ok(Some(caller)) => caller,
+            Ok(None) => return Ok(true),
+            Err(AuthError::NotFound(_) | AuthError::Unauthorized) => return Ok(false),
+            Err(e) => return Err(e.into()),
+        };
+        let permissions = self.store.get_user_permissions(&user.id).await?;
+        Ok(has_permission(&permissions, permission) && token_has_scope(&token, permission))
+    }
+
+    async fn log_audit(&self, access_key: &str, action: &str, target: &str) -> anyhow::Result<()> {
+        let user_id = self.caller(access_key).await?.map(|(_, user)| user.id);
+        self.store
+            .log_audit(user_id.as_deref(), action, Some(target), None, "")
+            .await?;
+        Ok(())
+    }
+
+    async fn namespace_prefix(&self, access_key: &str) -> anyhow::Result<Option<String>> {
+        match self.caller(access_key).await {
+            Ok(caller) => Ok(caller.and_then(|(token, _)| token.namespace_prefix)),
+            Err(AuthError::NotFound(_) | AuthError::Unauthorized) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn allows_ip(&self, access_key: &str, client_ip: Option<IpAddr>) -> anyhow::Result<bool> {
+        match self.caller(access_key).await {
+            Ok(caller) => Ok(caller.is_none_or(|(token, _)| token_allows_ip(&token, client_ip))),
+            Err(AuthError::NotFound(_) | AuthError::Unauthorized) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Storage is counted against the token's user; the static key is
+/// unlimited and not counted.
+#[async_trait]
+impl UsageAccounting for StoreAuth {
+    async fn remaining_quota(&self, access_key: &str) -> anyhow::Result<Option<u64>> {
+        let Some((_, user)) = self.caller(access_key).await? else {
+            return Ok(None);
+        };
+        Ok(self.store.remaining_user_quota(&user.id).await?)
+    }
+
+    async fn record_usage(
+        &self,
+        access_key: &str,
+        namespace: &str,
+        delta_bytes: i64,
+        delta_objects: i32,
+    ) -> anyhow::Result<()> {
+        let Some((_, user)) = self.caller(access_key).await? else {
+            return Ok(());
+        };
+        self.store
+            .update_user_usage(&user.id, namespace, delta_bytes, delta_objects)
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::path::Path;
+    use std::sync::Mutex;
+
+    use aws_sdk_s3::config::{BehaviorVersion, Credentials, Region};
+    use aws_sdk_s3::error::ProvideErrorMetadata;
+    use aws_sdk_s3::primitives::ByteStream;
+    use enigma_auth::SqliteAuthStore;
+    use enigma_core::config::EnigmaConfig;
+    use enigma_core::distributor::Distributor;
+    use enigma_core::manifest::ManifestDb;
+    use enigma_core::types::{KeyMaterial, ProviderType};
+    use enigma_s3::auth::{EnigmaS3Access, READ_PERMISSION, WRITE_PERMISSION};
+    use enigma_s3::object_lock::BYPASS_GOVERNANCE_PERMISSION;
+    use enigma_s3::service::EnigmaS3Service;
+    use enigma_s3::{EnigmaS3State, SharedState};
+    use enigma_storage::local::LocalStorageProvider;
+    use enigma_storage::provider::StorageProvider;
+    use s3s::service::S3ServiceBuilder;
+
+    /// A migrated in-memory store where `alice` holds `permissions` and has
+    /// a token with `scopes`. Returns the store and the token's ID.
+    async fn store_with_token(permissions: &[&str], scopes: &str) -> (Arc<dyn AuthStore>, String) {
+        let store = SqliteAuthStore::open_in_memory().unwrap();
+        store.migrate().await.unwrap();
+        store.seed_defaults().await.unwrap();
+        let user = store
+            .create_user("alice", &enigma_auth::NewPassword::disabled(), None)
+            .await
+            .unwrap();
+        let group = store.create_group("tenants", "", false).await.unwrap();
+        for permission in store.list_permissions().await.unwrap() {
+            if permissions.contains(&permission.action.as_str()) {
+                store
+                    .add_group_permission(&group.id, &permission.id)
+                    .await
+                    .unwrap();
+            }
+        }
+        store.add_user_group(&user.id, &group.id).await.unwrap();
+        let token = store
+            .create_token(&user.id, "s3", "token-hash", "egt_00000000", scopes, None)
+            .await
+            .unwrap();
+        (Arc::new(store), token.id)
+    }
+
+    /// State with one local provider under `dir`, checking callers against
+    /// `store`.
+    fn state_with_auth(dir: &Path, store: Arc<dyn AuthStore>) -> SharedState {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let pid = db
+            .insert_provider("local", ProviderType::Local, dir.to_str().unwrap(), None, 1)
+            .unwrap();
+        let distributor = Distributor::round_robin(db.list_providers().unwrap()).unwrap();
+        let mut providers: HashMap<i64, Box<dyn StorageProvider>> = HashMap::new();
+        providers.insert(
+            pid,
+            Box::new(LocalStorageProvider::new(&dir.join("chunks"), "local").unwrap()),
+        );
+        let state = EnigmaS3State {
+            db: Arc::new(Mutex::new(db)),
+            providers: providers.into(),
+            distributor: distributor.into(),
+            key_material: KeyMaterial {
+                id: "test-key-1".to_string(),
+                key: [0x42; 32],
+            },
+            config: EnigmaConfig::default_config(dir),
+            raft: Default::default(),
+            events: Default::default(),
+            usage: Default::default(),
+            access_control: Default::default(),
+            multipart_limits: Default::default(),
+            chunk_cache: Default::default(),
+            upload_semaphore: Default::default(),
+            provider_limiters: Default::default(),
+        };
+        let auth = StoreAuth::new("static-key".into(), "static-secret".into(), store);
+        let _ = state.access_control.set(Arc::new(auth));
+        Arc::new(state)
+    }
+
+    /// AWS SDK client signing with the token `token_id`, whose requests go
+    /// straight to an in-process S3 service with the proxy's auth and
+    /// access checks.
+    fn sdk_client(
+        state: SharedState,
+        store: Arc<dyn AuthStore>,
+        token_id: &str,
+    ) -> aws_sdk_s3::Client {
+        let mut builder = S3ServiceBuilder::new(EnigmaS3Service::new(state.clone()));
+        builder.set_auth(StoreAuth::new(
+            "static-key".into(),
+            "static-secret".into(),
+            store,
+        ));
+        builder.set_access(EnigmaS3Access::new(state));
+        let service = builder.build().into_shared();
+
+        let config = aws_sdk_s3::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .credentials_provider(Credentials::new(token_id, "token-hash", None, None, "test"))
+            .region(Region::new("us-east-1"))
+            .endpoint_url("http://localhost:9000")
+            .force_path_style(true)
+            .http_client(s3s_aws::Client::from(service))
+            .build();
+        aws_sdk_s3::Client::from_conf(config)
+    }
+
+    #[tokio::test]
+    async fn token_holds_its_users_permissions_within_its_scopes() {
+        let (store, token_id) = store_with_token(
+            &["objects:legal-hold:manage", "objects:lock:bypass"],
+            "objects:legal-hold:manage",
+        )
+        .await;
+        let tmp = tempfile::tempdir().unwrap();
+        let state = state_with_auth(tmp.path(), store);
+        let access = state.access_control.get().unwrap();
+
+        assert!(
+            access
+                .has_permission(&token_id, "objects:legal-hold:manage")
+                .await
+                .unwrap()
+        );
+        // Granted to the user, but not in the token's scopes
+        assert!(
+            !access
+                .has_permission(&token_id, "objects:lock:bypass")
+                .await
+                .unwrap()
+        );
+        assert!(
+            !access
+                .has_permission("unknown-key", "objects:legal-hold:manage")
+                .await
+                .unwrap()
+        );
+        assert!(
+            access
+                .has_permission("static-key", "objects:lock:bypass")
+                .await
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn token_requests_are_signed_with_the_token_hash() {
+        let (store, token_id) = store_with_token(&[], "*").await;
+        store
+            .update_token_namespace_prefix(&token_id, Some("team-a/"))
+            .await
+            .unwrap();
+        let auth = StoreAuth::new("static-key".into(), "static-secret".into(), store);
+
+        let secret = auth.get_secret_key(&token_id).await.unwrap();
+        assert_eq!(secret.expose(), "token-hash");
+        let secret = auth.get_secret_key("static-key").await.unwrap();
+        assert_eq!(secret.expose(), "static-secret");
+        assert!(auth.get_secret_key("unknown-key").await.is_err());
+
+        assert_eq!(
+            auth.namespace_prefix(&token_id).await.unwrap().as_deref(),
+            Some("team-a/")
+        );
+        assert_eq!(auth.namespace_prefix("static-key").await.unwrap(), None);
+        assert_eq!(auth.namespace_prefix("unknown-key").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn token_requests_are_counted() {
+        let (store, token_id) = store_with_token(&[], "*").await;
+        let auth = StoreAuth::new("static-key".into(), "static-secret".into(), store.clone());
+
+        auth.get_secret_key(&token_id).await.unwrap();
+        auth.get_secret_key(&token_id).await.unwrap();
+
+        let mut requests = 0;
+        for _ in 0..50 {
+            let usage = store.get_token_usage_history(&token_id, 1).await.unwrap();
+            requests = usage.iter().map(|day| day.request_count).sum();
+            if requests == 2 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(requests, 2);
+        let (token, _, _) = store.verify_s3_access_key(&token_id).await.unwrap();
+        assert!(token.last_used_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn usage_is_counted_against_the_tokens_user() {
+        let (store, token_id) = store_with_token(&[], "*").await;
+        let alice = store.get_user_by_username("alice").await.unwrap();
+        store.set_user_quota(&alice.id, Some(100)).await.unwrap();
+        let auth = StoreAuth::new("static-key".into(), "static-secret".into(), store.clone());
+
+        auth.record_usage(&token_id, "logs", 60, 1).await.unwrap();
+        assert_eq!(auth.remaining_quota(&token_id).await.unwrap(), Some(40));
+        let usage = store.get_user_usage(&alice.id).await.unwrap();
+        assert_eq!(usage.len(), 1);
+        assert_eq!(usage[0].bytes_stored, 60);
+
+        // The operator's key is neither limited nor counted
+        auth.record_usage("static-key", "logs", 500, 1)
+            .await
+            .unwrap();
+        assert_eq!(auth.remaining_quota("static-key").await.unwrap(), None);
+        assert_eq!(auth.remaining_quota(&token_id).await.unwrap(), Some(40));
+    }
+
+    #[tokio::test]
+    async fn governance_bypass_needs_the_lock_bypass_permission() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (store, allowed) = store_with_token(&[BYPASS_GOVERNANCE_PERMISSION], "*").await;
+        let state = state_with_auth(tmp.path(), store);
+        assert!(
+            state
+                .access_control
+                .get()
+                .unwrap()
+                .has_permission(&allowed, BYPASS_GOVERNANCE_PERMISSION)
+                .await
+                .unwrap()
+        );
+
+        let (store, denied) = store_with_token(&["objects:legal-hold:manage"], "*").await;
+        let state = state_with_auth(tmp.path(), store);
+        assert!(
+            !state
+                .access_control
+                .get()
+                .unwrap()
+                .has_permission(&denied, BYPASS_GOVERNANCE_PERMISSION)
+                .await
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn audit_entries_name_the_tokens_user() {
+        let (store, token_id) = store_with_token(&[], "*").await;
+        let tmp = tempfile::tempdir().unwrap();
+        let state = state_with_auth(tmp.path(), store.clone());
+        let access = state.access_control.get().unwrap();
+
+        access
+            .log_audit(&token_id, "object.legal_hold.set", "logs/a.txt")
+            .await
+            .unwrap();
+        access
+            .log_audit("static-key", "object.legal_hold.clear", "logs/a.txt")
+            .await
+            .unwrap();
+
+        let alice = store.get_user_by_username("alice").await.unwrap();
+        let audit = store.list_audit(10, 0).await.unwrap();
+        let entries: Vec<(Option<&str>, &str)> = audit
+            .iter()
+            .map(|e| (e.user_id.as_deref(), e.action.as_str()))
+            .collect();
+        assert!(entries.contains(&(Some(alice.id.as_str()), "object.legal_hold.set")));
+        assert!(entries.contains(&(None, "object.legal_hold.clear")));
+    }
+
+    #[tokio::test]
+    async fn read_only_token_cannot_write_objects() {
+        let (store, token_id) =
+            store_with_token(&[READ_PERMISSION, WRITE_PERMISSION], READ_PERMISSION).await;
+        let tmp = tempfile::tempdir().unwrap();
+        let state = state_with_auth(tmp.path(), store.clone());
+        state.db.lock().unwrap().create_namespace("logs").unwrap();
+        let client = sdk_client(state, store, &token_id);
+
+        client
+            .list_objects_v2()
+            .bucket("logs")
+            .send()
+            .await
+            .unwrap();
+        // The user may write, but the token is scoped to reads
+        let err = client
+            .put_object()
+            .bucket("logs")
+            .key("a.txt")
+            .body(ByteStream::from_static(b"data"))
+            .send()
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Some("AccessDenied"));
+    }
+
+    #[tokio::test]
+    async fn token_requests_are_limited_to_its_allowed_ips() {
+        let (store, token_id) = store_with_token(&[READ_PERMISSION], "*").await;
+        store
+            .update_token_allowed_ips(&token_id, Some("10.0.0.0/8"))
+            .await
+            .unwrap();
+        let auth = StoreAuth::new("static-key".into(), "static-secret".into(), store.clone());
+
+        let inside = Some("10.1.2.3".parse().unwrap());
+        let outside = Some("192.0.2.1".parse().unwrap());
+        assert!(auth.allows_ip(&token_id, inside).await.unwrap());
+        assert!(!auth.allows_ip(&token_id, outside).await.unwrap());
+        assert!(!auth.allows_ip(&token_id, None).await.unwrap());
+        assert!(auth.allows_ip("static-key", outside).await.unwrap());
+
+        // In-process requests carry no client address
+        let tmp = tempfile::tempdir().unwrap();
+        let state = state_with_auth(tmp.path(), store.clone());
+        state.db.lock().unwrap().create_namespace("logs").unwrap();
+        let err = sdk_client(state, store, &token_id)
+            .list_objects_v2()
+            .bucket("logs")
+            .send()
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Some("AccessDenied"));
+    }
+}
@@ -0,0 +1,268 @@
+//! S3 listener with graceful shutdown.
+//!
+//! On shutdown the listener stops accepting, idle keep-alive connections
+//! are closed and requests already in flight get a grace period to finish
+//! so uploads are not cut off halfway. Connections still open when the
+//! grace period ends are dropped.
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use enigma_s3::access_log::RemoteAddr;
+use hyper::service::{Service, service_fn};
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto;
+use s3s::service::SharedS3Service;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio::sync::broadcast;
+use tokio::task::JoinSet;
+
+/// Serve `service` on `listener` until `shutdown` fires, then drain open
+/// connections for at most `drain_timeout`.
+pub async fn serve(
+    listener: TcpListener,
+    service: SharedS3Service,
+    #[cfg(feature = "tls")] tls_acceptor: Option<tokio_rustls::TlsAcceptor>,
+    shutdown: &broadcast::Sender<()>,
+    drain_timeout: Duration,
+) -> anyhow::Result<()> {
+    let mut stop = shutdown.subscribe();
+    let mut connections = JoinSet::new();
+
+    loop {
+        let (stream, peer) = tokio::select! {
+            accepted = listener.accept() => accepted?,
+            _ = stop.recv() => break,
+        };
+        // Reap connections that have already closed
+        while connections.try_join_next().is_some() {}
+
+        let service = service.clone();
+        let stop = shutdown.subscribe();
+
+        #[cfg(feature = "tls")]
+        let tls_acceptor = tls_acceptor.clone();
+
+        connections.spawn(async move {
+            #[cfg(feature = "metrics")]
+            let _connection = crate::metrics::track_connection();
+
+            #[cfg(feature = "tls")]
+            if let Some(ref acceptor) = tls_acceptor {
+                match acceptor.accept(stream).await {
+                    Ok(tls_stream) => {
+                        serve_connection(tls_stream, peer, service, stop, "TLS connection error")
+                            .await;
+                    }
+                    Err(e) => tracing::error!("TLS handshake error: {e}"),
+                }
+                return;
+            }
+
+            serve_connection(stream, peer, service, stop, "Connection error").await;
+        });
+    }
+
+    drop(listener);
+    tracing::info!(
+        "Shutting down — draining {} connection(s) for up to {}s",
+        connections.len(),
+        drain_timeout.as_secs()
+    );
+    let drained = tokio::time::timeout(drain_timeout, async {
+        while connections.join_next().await.is_some() {}
+    })
+    .await;
+    if drained.is_err() {
+        tracing::warn!(
+            "Shutdown timeout reached — closing {} connection(s)",
+            connections.len()
+        );
+        connections.shutdown().await;
+    }
+    Ok(())
+}
+
+/// Serve one connection; on shutdown let the request in flight finish and
+/// close instead of waiting for the next one. Requests carry the client's
+/// address for the access log.
+async fn serve_connection<I>(
+    io: I,
+    peer: SocketAddr,
+    service: SharedS3Service,
+    mut stop: broadcast::Receiver<()>,
+    error_context: &str,
+) where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let service = service_fn(move |mut req: hyper::Request<hyper::body::Incoming>| {
+        req.extensions_mut().insert(RemoteAddr(peer));
+        service.call(req)
+    });
+    let builder = auto::Builder::new(TokioExecutor::new());
+    let conn = builder.serve_connection(TokioIo::new(io), service);
+    tokio::pin!(conn);
+
+    let result = tokio::select! {
+        result = conn.as_mut() => result,
+        _ = stop.recv() => {
+            conn.as_mut().graceful_shutdown();
+            conn.await
+        }
+    };
+    if let Err(e) = result {
+        tracing::error!("{error_context}: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::net::SocketAddr;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use enigma_core::config::EnigmaConfig;
+    use enigma_core::distributor::Distributor;
+    use enigma_core::manifest::ManifestDb;
+    use enigma_core::types::{KeyMaterial, ProviderType};
+    use enigma_s3::service::EnigmaS3Service;
+    use enigma_s3::{EnigmaS3State, SharedState};
+    use enigma_storage::mock::{MockMethod, MockStorageProvider};
+    use enigma_storage::provider::StorageProvider;
+    use s3s::service::S3ServiceBuilder;
+
+    /// State whose only provider takes 100 ms per chunk upload.
+    fn slow_state(dir: &std::path::Path) -> (SharedState, MockStorageProvider) {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let pid = db
+            .insert_provider("slow", ProviderType::Local, dir.to_str().unwrap(), None, 1)
+            .unwrap();
+        db.create_namespace("bucket").unwrap();
+        let distributor = Distributor::round_robin(db.list_providers().unwrap()).unwrap();
+        let mock = MockStorageProvider::new("slow");
+        mock.set_delay(MockMethod::UploadChunk, Duration::from_millis(100));
+        let mut providers: HashMap<i64, Box<dyn StorageProvider>> = HashMap::new();
+        providers.insert(pid, Box::new(mock.clone()));
+        let state = Arc::new(EnigmaS3State {
+            db: Arc::new(Mutex::new(db)),
+            providers: providers.into(),
+            distributor: distributor.into(),
+            key_material: KeyMaterial {
+                id: "test-key-1".to_string(),
+                key: [0x42; 32],
+            },
+            config: EnigmaConfig::default_config(dir),
+            raft: Default::default(),
+            events: Default::default(),
+            usage: Default::default(),
+            access_control: Default::default(),
+            multipart_limits: Default::default(),
+            chunk_cache: Default::default(),
+            upload_semaphore: Default::default(),
+            provider_limiters: Default::default(),
+        });
+        (state, mock)
+    }
+
+    /// Start the listener on a free port and return its address, the
+    /// shutdown sender and the server task.
+    async fn start(
+        state: SharedState,
+        drain_timeout: Duration,
+    ) -> (
+        SocketAddr,
+        broadcast::Sender<()>,
+        tokio::task::JoinHandle<anyhow::Result<()>>,
+    ) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let service = S3ServiceBuilder::new(EnigmaS3Service::new(state))
+            .build()
+            .into_shared();
+        let (shutdown, _) = broadcast::channel(1);
+        let tx = shutdown.clone();
+        let server = tokio::spawn(async move {
+            serve(
+                listener,
+                service,
+                #[cfg(feature = "tls")]
+                None,
+                &tx,
+                drain_timeout,
+            )
+            .await
+        });
+        (addr, shutdown, server)
+    }
+
+    /// Send an unsigned PUT and return the response status line, or `None`
+    /// if the connection was closed before a response arrived.
+    async fn put(addr: SocketAddr, key: &str, body: &[u8]) -> Option<String> {
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let head = format!(
+            "PUT /bucket/{key} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n",
+            body.len()
+        );
+        stream.write_all(head.as_bytes()).await.unwrap();
+        stream.write_all(body).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.ok()?;
+        response.lines().next().map(String::from)
+    }
+
+    fn object_exists(state: &SharedState, key: &str) -> bool {
+        let db = state.db.lock().unwrap();
+        let ns = db.get_namespace_id("bucket").unwrap().unwrap();
+        db.get_object(ns, key).unwrap().is_some()
+    }
+
+    #[tokio::test]
+    async fn in_flight_upload_completes_before_shutdown() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (state, mock) = slow_state(tmp.path());
+        let (addr, shutdown, server) = start(state.clone(), Duration::from_secs(30)).await;
+
+        let upload = tokio::spawn(async move { put(addr, "slow.bin", &[7u8; 4096]).await });
+        // Let the request reach the slow provider, then ask for shutdown
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        shutdown.send(()).unwrap();
+
+        let status = upload.await.unwrap().expect("response before close");
+        assert!(status.contains("200"), "unexpected status: {status}");
+        tokio::time::timeout(Duration::from_secs(5), server)
+            .await
+            .expect("server did not stop after draining")
+            .unwrap()
+            .unwrap();
+        assert!(object_exists(&state, "slow.bin"));
+        assert_eq!(mock.len(), 1);
+
+        // The listener is closed
+        assert!(tokio::net::TcpStream::connect(addr).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn drain_timeout_drops_unfinished_upload() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (state, mock) = slow_state(tmp.path());
+        let (addr, shutdown, server) = start(state.clone(), Duration::from_millis(10)).await;
+
+        let upload = tokio::spawn(async move { put(addr, "cut.bin", &[9u8; 4096]).await });
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        shutdown.send(()).unwrap();
+
+        tokio::time::timeout(Duration::from_secs(5), server)
+            .await
+            .expect("server did not stop after the drain timeout")
+            .unwrap()
+            .unwrap();
+        assert_eq!(upload.await.unwrap(), None);
+        // The aborted request never committed an object or stored a chunk
+        assert!(!object_exists(&state, "cut.bin"));
+        assert_eq!(mock.call_count(MockMethod::UploadChunk), 1);
+        assert!(mock.is_empty());
+    }
+}
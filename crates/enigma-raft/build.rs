@@ -1,4 +1,5 @@
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     tonic_build::compile_protos("src/proto/raft.proto")?;
+    tonic_build::compile_protos("src/proto/health.proto")?;
     Ok(())
 }
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
 
 /// Raft cluster configuration, embedded in the TOML config.
@@ -21,10 +23,32 @@ pub struct RaftConfig {
     /// Number of log entries before triggering a snapshot.
     #[serde(default = "default_snapshot_threshold")]
     pub snapshot_threshold: u64,
+    /// Zstd level used to compress snapshots before they are sent to peers.
+    #[serde(default = "default_snapshot_compression_level")]
+    pub raft_snapshot_compression_level: i32,
     /// Recovery mode: wipe Raft log and bootstrap as single node.
     /// Data in ManifestDb is preserved. Use this when quorum is lost.
     #[serde(default)]
     pub force_new_cluster: bool,
+    /// Mutual TLS for inter-node gRPC. Unset: plain TCP.
+    #[serde(default)]
+    pub tls: Option<RaftTlsConfig>,
+    /// Only start an election while a quorum of voters is reachable (see
+    /// [`crate::prevote`]), so a node cut off from the cluster does not
+    /// depose the leader when it comes back.
+    #[serde(default = "default_enable_prevote")]
+    pub enable_prevote: bool,
+    /// Milliseconds after its last quorum acknowledgement during which the
+    /// leader serves linearizable reads without a heartbeat round. Unset:
+    /// every read confirms leadership first. At most `election_timeout_ms`.
+    ///
+    /// A leader that is cut off but does not know it yet keeps serving
+    /// reads until its lease runs out, which may miss writes made by a
+    /// newly elected leader in the meantime (and clock drift between nodes
+    /// shortens the real margin). Keep it at `heartbeat_interval_ms` or
+    /// below where stale reads matter.
+    #[serde(default)]
+    pub leader_lease_ms: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,6 +57,18 @@ pub struct PeerConfig {
     pub addr: String,
 }
 
+/// PEM files for Raft mutual TLS. Every node presents `cert_pem` and only
+/// accepts peers whose certificate chains to `ca_pem`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RaftTlsConfig {
+    /// Path to this node's certificate (must cover its gRPC address).
+    pub cert_pem: String,
+    /// Path to this node's private key.
+    pub key_pem: String,
+    /// Path to the CA certificate that signed every peer's certificate.
+    pub ca_pem: String,
+}
+
 fn default_election_timeout() -> u64 {
     1000
 }
@@ -45,9 +81,69 @@ fn default_snapshot_threshold() -> u64 {
     10000
 }
 
+fn default_snapshot_compression_level() -> i32 {
+    3
+}
+
+fn default_enable_prevote() -> bool {
+    true
+}
+
 impl RaftConfig {
     /// Returns true if this is a single-node deployment (no Raft needed).
     pub fn is_single_node(&self) -> bool {
         self.peers.len() <= 1
     }
+
+    /// The leader lease, if set. A lease longer than the election timeout
+    /// would outlive the followers' wait before electing a new leader.
+    pub fn leader_lease(&self) -> anyhow::Result<Option<Duration>> {
+        match self.leader_lease_ms {
+            Some(ms) if ms > self.election_timeout_ms => anyhow::bail!(
+                "leader_lease_ms ({ms}) must not exceed election_timeout_ms ({})",
+                self.election_timeout_ms
+            ),
+            lease => Ok(lease.map(Duration::from_millis)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(extra: serde_json::Value) -> RaftConfig {
+        let mut config = serde_json::json!({
+            "node_id": 1,
+            "data_dir": "/data/raft",
+            "grpc_addr": "0.0.0.0:9000",
+        });
+        config
+            .as_object_mut()
+            .unwrap()
+            .extend(extra.as_object().cloned().unwrap_or_default());
+        serde_json::from_value(config).unwrap()
+    }
+
+    #[test]
+    fn prevote_is_on_and_lease_off_by_default() {
+        let defaults = config(serde_json::json!({}));
+        assert!(defaults.enable_prevote);
+        assert_eq!(defaults.leader_lease().unwrap(), None);
+
+        assert!(!config(serde_json::json!({ "enable_prevote": false })).enable_prevote);
+        assert_eq!(
+            config(serde_json::json!({ "leader_lease_ms": 300 }))
+                .leader_lease()
+                .unwrap(),
+            Some(Duration::from_millis(300))
+        );
+        let err = config(serde_json::json!({ "leader_lease_ms": 1500 }))
+            .leader_lease()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "leader_lease_ms (1500) must not exceed election_timeout_ms (1000)"
+        );
+    }
 }
@@ -1,27 +1,33 @@
 use std::io::Cursor;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use openraft::{BasicNode, Snapshot, SnapshotMeta, Vote};
 use tokio_stream::StreamExt;
 use tonic::{Request, Response, Status};
 
+use enigma_core::manifest::ManifestDb;
+
 use crate::EnigmaRaft;
 use crate::proto::raft_service_server::RaftService;
 use crate::proto::{
     AppendEntriesRequest as ProtoAppendReq, AppendEntriesResponse as ProtoAppendResp,
-    InstallSnapshotResponse as ProtoSnapshotResp, SnapshotChunk, VoteRequest as ProtoVoteReq,
-    VoteResponse as ProtoVoteResp, WriteRequest, WriteResponse,
+    InstallSnapshotResponse as ProtoSnapshotResp, ReadRequest, ReadResponse as ProtoReadResp,
+    SnapshotChunk, VoteRequest as ProtoVoteReq, VoteResponse as ProtoVoteResp, WriteRequest,
+    WriteResponse,
 };
+use crate::read::{ReadQuery, execute};
 use crate::types::RaftRequest;
 
 /// gRPC server implementing the Raft service.
 pub struct EnigmaRaftGrpcServer {
     pub raft: Arc<EnigmaRaft>,
+    /// State machine DB, queried by `LinearizableRead`.
+    pub db: Arc<Mutex<ManifestDb>>,
 }
 
 impl EnigmaRaftGrpcServer {
-    pub fn new(raft: Arc<EnigmaRaft>) -> Self {
-        Self { raft }
+    pub fn new(raft: Arc<EnigmaRaft>, db: Arc<Mutex<ManifestDb>>) -> Self {
+        Self { raft, db }
     }
 }
 
@@ -143,4 +149,29 @@ impl RaftService for EnigmaRaftGrpcServer {
 
         Ok(Response::new(WriteResponse { data }))
     }
+
+    async fn linearizable_read(
+        &self,
+        request: Request<ReadRequest>,
+    ) -> Result<Response<ProtoReadResp>, Status> {
+        let query: ReadQuery = serde_json::from_slice(&request.into_inner().data)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+        // Fails if this node is no longer the leader
+        let read_index = crate::read::leader_read_index(&self.raft)
+            .await
+            .map_err(|e| Status::failed_precondition(e.to_string()))?;
+
+        let resp = {
+            let db = self
+                .db
+                .lock()
+                .map_err(|_| Status::internal("DB lock poisoned"))?;
+            execute(&db, &query).map_err(|e| Status::internal(e.to_string()))?
+        };
+
+        let data = serde_json::to_vec(&resp).map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(Response::new(ProtoReadResp { data, read_index }))
+    }
 }
@@ -0,0 +1,88 @@
+//! gRPC health checking (`grpc.health.v1.Health/Check`) served next to the
+//! Raft service, so peers and load balancers can probe a node.
+
+use std::time::{Duration, Instant};
+
+use tonic::{Request, Response, Status};
+
+pub mod proto {
+    tonic::include_proto!("grpc.health.v1");
+}
+
+use proto::health_check_response::ServingStatus;
+use proto::health_client::HealthClient;
+use proto::health_server::Health;
+use proto::{HealthCheckRequest, HealthCheckResponse};
+
+/// Service name of the Raft service, as reported by health checks.
+pub const RAFT_SERVICE: &str = "enigma.raft.RaftService";
+
+/// Reports the node as serving for the whole server ("") and the Raft
+/// service; any other service is unknown.
+#[derive(Default)]
+pub struct HealthService;
+
+#[tonic::async_trait]
+impl Health for HealthService {
+    async fn check(
+        &self,
+        request: Request<HealthCheckRequest>,
+    ) -> Result<Response<HealthCheckResponse>, Status> {
+        match request.into_inner().service.as_str() {
+            "" | RAFT_SERVICE => Ok(Response::new(HealthCheckResponse {
+                status: ServingStatus::Serving as i32,
+            })),
+            other => Err(Status::not_found(format!("unknown service {other}"))),
+        }
+    }
+}
+
+/// Health-check the Raft service of the peer at `addr` (`host:port`) and
+/// return the round-trip time. Uses the Raft client TLS config when set.
+pub async fn check_peer(addr: &str, timeout: Duration) -> anyhow::Result<Duration> {
+    let started = Instant::now();
+    let status = tokio::time::timeout(timeout, async {
+        let channel = crate::tls::connect(addr, crate::tls::forward_tls()).await?;
+        let resp = HealthClient::new(channel)
+            .check(HealthCheckRequest {
+                service: RAFT_SERVICE.to_string(),
+            })
+            .await?
+            .into_inner();
+        anyhow::Ok(resp.status())
+    })
+    .await
+    .map_err(|_| anyhow::anyhow!("health check of {addr} timed out"))??;
+
+    if status != ServingStatus::Serving {
+        anyhow::bail!("{addr} is {}", status.as_str_name());
+    }
+    Ok(started.elapsed())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn status_of(service: &str) -> Result<i32, Status> {
+        HealthService
+            .check(Request::new(HealthCheckRequest {
+                service: service.to_string(),
+            }))
+            .await
+            .map(|resp| resp.into_inner().status)
+    }
+
+    #[tokio::test]
+    async fn reports_raft_service_as_serving() {
+        assert_eq!(status_of("").await.unwrap(), ServingStatus::Serving as i32);
+        assert_eq!(
+            status_of(RAFT_SERVICE).await.unwrap(),
+            ServingStatus::Serving as i32
+        );
+        assert_eq!(
+            status_of("other.Service").await.unwrap_err().code(),
+            tonic::Code::NotFound
+        );
+    }
+}
@@ -1,8 +1,14 @@
 pub mod config;
 pub mod grpc_server;
+pub mod health;
 pub mod log_store;
+#[cfg(feature = "metrics")]
+pub mod metrics;
 pub mod network;
+pub mod prevote;
+pub mod read;
 pub mod state_machine;
+pub mod tls;
 pub mod types;
 
 pub mod proto {
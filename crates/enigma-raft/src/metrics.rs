@@ -0,0 +1,61 @@
+//! Prometheus gauge for the local Raft server state.
+
+use std::sync::LazyLock;
+
+use openraft::{BasicNode, RaftMetrics, ServerState};
+use prometheus::{IntGaugeVec, register_int_gauge_vec};
+use tokio::sync::watch;
+
+const STATES: [(ServerState, &str); 5] = [
+    (ServerState::Learner, "learner"),
+    (ServerState::Follower, "follower"),
+    (ServerState::Candidate, "candidate"),
+    (ServerState::Leader, "leader"),
+    (ServerState::Shutdown, "shutdown"),
+];
+
+/// `enigma_raft_state{state}`: 1 for the node's current state, 0 for the others.
+static RAFT_STATE: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        "enigma_raft_state",
+        "Current Raft server state of this node (1 = active)",
+        &["state"]
+    )
+    .unwrap()
+});
+
+pub fn record_state(current: ServerState) {
+    for (state, label) in STATES {
+        RAFT_STATE
+            .with_label_values(&[label])
+            .set(i64::from(state == current));
+    }
+}
+
+/// Keep `enigma_raft_state` in sync with `raft.metrics()` until the Raft
+/// instance shuts down.
+pub async fn track_state(mut metrics: watch::Receiver<RaftMetrics<u64, BasicNode>>) {
+    loop {
+        let state = metrics.borrow().state;
+        record_state(state);
+        if metrics.changed().await.is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_current_state_is_set() {
+        record_state(ServerState::Leader);
+        assert_eq!(RAFT_STATE.with_label_values(&["leader"]).get(), 1);
+        assert_eq!(RAFT_STATE.with_label_values(&["follower"]).get(), 0);
+
+        record_state(ServerState::Follower);
+        assert_eq!(RAFT_STATE.with_label_values(&["leader"]).get(), 0);
+        assert_eq!(RAFT_STATE.with_label_values(&["follower"]).get(), 1);
+    }
+}
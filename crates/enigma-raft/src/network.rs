@@ -8,6 +8,7 @@ use openraft::raft::{
     SnapshotResponse, VoteRequest, VoteResponse,
 };
 use openraft::{BasicNode, Snapshot, Vote};
+use tonic::transport::ClientTlsConfig;
 
 use crate::TypeConfig;
 use crate::proto::raft_service_client::RaftServiceClient;
@@ -15,15 +16,23 @@ use crate::proto::raft_service_client::RaftServiceClient;
 /// Factory that creates gRPC network connections to peers.
 pub struct EnigmaNetworkFactory {
     pub peers: Arc<Mutex<HashMap<u64, String>>>,
+    tls: Option<ClientTlsConfig>,
 }
 
 impl EnigmaNetworkFactory {
     pub fn new(peers: HashMap<u64, String>) -> Self {
         Self {
             peers: Arc::new(Mutex::new(peers)),
+            tls: None,
         }
     }
 
+    /// Connect to peers over mutual TLS (see `tls::load_raft_tls`).
+    pub fn with_tls(mut self, tls: ClientTlsConfig) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
     pub fn add_peer(&self, id: u64, addr: String) {
         self.peers
             .lock()
@@ -39,14 +48,20 @@ impl EnigmaNetworkFactory {
 impl RaftNetworkFactory<TypeConfig> for EnigmaNetworkFactory {
     type Network = EnigmaNetwork;
 
-    async fn new_client(&mut self, target: u64, _node: &BasicNode) -> Self::Network {
+    async fn new_client(&mut self, target: u64, node: &BasicNode) -> Self::Network {
+        // Nodes added at runtime, such as learners, are only known by the
+        // address in their membership entry
         let addr = {
             let peers = self.peers.lock().expect("peers mutex poisoned");
-            peers.get(&target).cloned().unwrap_or_default()
+            peers
+                .get(&target)
+                .cloned()
+                .unwrap_or_else(|| node.addr.clone())
         };
         EnigmaNetwork {
             target,
             addr,
+            tls: self.tls.clone(),
             client: tokio::sync::Mutex::new(None),
         }
     }
@@ -57,6 +72,7 @@ pub struct EnigmaNetwork {
     #[allow(dead_code)]
     target: u64,
     addr: String,
+    tls: Option<ClientTlsConfig>,
     client: tokio::sync::Mutex<Option<RaftServiceClient<tonic::transport::Channel>>>,
 }
 
@@ -71,10 +87,10 @@ impl EnigmaNetwork {
         if let Some(client) = cached.as_ref() {
             return Ok(client.clone());
         }
-        let endpoint = format!("http://{}", self.addr);
-        let client = RaftServiceClient::connect(endpoint)
+        let channel = crate::tls::connect(&self.addr, self.tls.as_ref())
             .await
             .map_err(|e| RPCError::Unreachable(Unreachable::new(&e)))?;
+        let client = RaftServiceClient::new(channel);
         *cached = Some(client.clone());
         Ok(client)
     }
@@ -0,0 +1,95 @@
+//! Pre-vote. openraft 0.9 has no pre-vote phase: a follower whose election
+//! timer fires raises its term and asks for votes, whether or not anyone
+//! can hear it. A node cut off from the cluster therefore keeps raising its
+//! term, and when the partition heals its first reply to the leader carries
+//! that higher term and deposes a leader nothing was wrong with.
+//!
+//! [`run`] does the pre-vote check out of band instead: it probes the other
+//! voters every interval and only lets the election timer run while a
+//! quorum of them answers, so a node never raises its term for an election
+//! it could not win.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::EnigmaRaft;
+
+/// Probe the other voters every `interval` and enable elections on `raft`
+/// only while, counting this node, a quorum of them is reachable. `peers`
+/// maps node IDs to gRPC addresses, as the network uses them. Returns once
+/// the Raft node has shut down.
+pub async fn run(
+    raft: Arc<EnigmaRaft>,
+    peers: Arc<Mutex<HashMap<u64, String>>>,
+    interval: Duration,
+) {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut can_elect = true;
+    loop {
+        ticker.tick().await;
+        let (id, voters) = {
+            let metrics = raft.metrics();
+            let m = metrics.borrow();
+            if m.running_state.is_err() {
+                return;
+            }
+            let voters: Vec<u64> = m.membership_config.membership().voter_ids().collect();
+            (m.id, voters)
+        };
+
+        let addrs: Vec<String> = {
+            let peers = peers.lock().unwrap_or_else(|e| e.into_inner());
+            voters
+                .iter()
+                .filter(|voter| **voter != id)
+                .filter_map(|voter| peers.get(voter).cloned())
+                .collect()
+        };
+        let probes = addrs
+            .iter()
+            .map(|addr| crate::health::check_peer(addr, interval));
+        let answered = futures::future::join_all(probes)
+            .await
+            .iter()
+            .filter(|probe| probe.is_ok())
+            .count();
+        let reachable = answered + usize::from(voters.contains(&id));
+
+        let quorum = has_quorum(reachable, voters.len());
+        if quorum != can_elect {
+            if quorum {
+                tracing::info!("Quorum reachable again, elections enabled");
+            } else {
+                tracing::warn!(
+                    "Only {reachable} of {} voters reachable, elections paused",
+                    voters.len()
+                );
+            }
+            raft.runtime_config().elect(quorum);
+            can_elect = quorum;
+        }
+    }
+}
+
+/// Whether `reachable` voters out of `voters` form a majority. A node that
+/// is not yet part of a membership may always elect, as openraft decides.
+fn has_quorum(reachable: usize, voters: usize) -> bool {
+    voters == 0 || reachable > voters / 2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quorum_is_a_strict_majority() {
+        assert!(has_quorum(1, 1));
+        assert!(has_quorum(2, 3));
+        assert!(!has_quorum(1, 3));
+        assert!(!has_quorum(2, 4));
+        assert!(has_quorum(3, 4));
+        assert!(has_quorum(0, 0));
+    }
+}
@@ -0,0 +1,291 @@
+use std::future::Future;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use openraft::error::{CheckIsLeaderError, ForwardToLeader, RaftError};
+use openraft::{BasicNode, ServerState};
+use serde::{Deserialize, Serialize};
+
+use enigma_core::manifest::ManifestDb;
+
+use crate::EnigmaRaft;
+use crate::proto::raft_service_client::RaftServiceClient;
+
+/// How long a follower waits to apply up to the leader's read index.
+const APPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Upper bound on objects returned by `ListObjects`.
+const LIST_LIMIT: u32 = 10_000;
+
+/// Leader lease (`leader_lease_ms`); unset, every read confirms leadership.
+static LEADER_LEASE: OnceLock<Duration> = OnceLock::new();
+
+/// Let the leader serve reads without a heartbeat round for `lease` after
+/// its last quorum acknowledgement. Set once at startup.
+pub fn set_leader_lease(lease: Duration) {
+    let _ = LEADER_LEASE.set(lease);
+}
+
+/// Whether `raft` is the leader and its lease is still running: a quorum
+/// acknowledged it less than the lease ago.
+pub fn holds_lease(raft: &EnigmaRaft) -> bool {
+    let Some(lease) = LEADER_LEASE.get() else {
+        return false;
+    };
+    let m = raft.metrics().borrow().clone();
+    lease_valid(m.state, m.millis_since_quorum_ack, *lease)
+}
+
+fn lease_valid(state: ServerState, millis_since_quorum_ack: Option<u64>, lease: Duration) -> bool {
+    state == ServerState::Leader
+        && millis_since_quorum_ack.is_some_and(|ms| u128::from(ms) < lease.as_millis())
+}
+
+/// Confirm this node is the leader and return the log index a read is
+/// served at, once the state machine has applied it. Within its lease the
+/// leader answers at its applied index without a heartbeat round.
+pub(crate) async fn leader_read_index(
+    raft: &EnigmaRaft,
+) -> Result<Option<u64>, RaftError<u64, CheckIsLeaderError<u64, BasicNode>>> {
+    if holds_lease(raft) {
+        return Ok(raft.metrics().borrow().last_applied.map(|id| id.index));
+    }
+    Ok(raft.ensure_linearizable().await?.map(|id| id.index))
+}
+
+/// Read-only metadata queries that can be served linearizably.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ReadQuery {
+    ListObjects {
+        namespace: String,
+        prefix: String,
+    },
+    GetObject {
+        namespace: String,
+        key: String,
+    },
+    GetObjectVersion {
+        namespace: String,
+        key: String,
+        version_id: String,
+    },
+    GetNamespace {
+        name: String,
+    },
+}
+
+/// Object metadata as read for GET/HEAD.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ObjectRow {
+    pub id: i64,
+    pub size: u64,
+    pub etag: String,
+    pub content_type: Option<String>,
+    pub chunk_count: u32,
+    pub key_id: String,
+    pub created_at: String,
+}
+
+/// From the row `ManifestDb::get_object` returns.
+impl From<(i64, u64, String, Option<String>, u32, String, String)> for ObjectRow {
+    fn from(
+        (id, size, etag, content_type, chunk_count, key_id, created_at): (
+            i64,
+            u64,
+            String,
+            Option<String>,
+            u32,
+            String,
+            String,
+        ),
+    ) -> Self {
+        Self {
+            id,
+            size,
+            etag,
+            content_type,
+            chunk_count,
+            key_id,
+            created_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ReadResponse {
+    /// (key, size, etag, created_at)
+    Objects(Vec<(String, u64, String, String)>),
+    Object(Option<ObjectRow>),
+    /// Namespace ID
+    Namespace(Option<i64>),
+    NamespaceNotFound,
+}
+
+/// Execute `query` against a local ManifestDb.
+pub fn execute(db: &ManifestDb, query: &ReadQuery) -> enigma_core::error::Result<ReadResponse> {
+    match query {
+        ReadQuery::GetNamespace { name } => Ok(ReadResponse::Namespace(db.get_namespace_id(name)?)),
+        ReadQuery::GetObject { namespace, key } => match db.get_namespace_id(namespace)? {
+            Some(ns_id) => Ok(ReadResponse::Object(
+                db.get_object(ns_id, key)?.map(ObjectRow::from),
+            )),
+            None => Ok(ReadResponse::NamespaceNotFound),
+        },
+        ReadQuery::GetObjectVersion {
+            namespace,
+            key,
+            version_id,
+        } => match db.get_namespace_id(namespace)? {
+            Some(ns_id) => Ok(ReadResponse::Object(
+                db.get_object_version(ns_id, key, version_id)?
+                    .map(ObjectRow::from),
+            )),
+            None => Ok(ReadResponse::NamespaceNotFound),
+        },
+        ReadQuery::ListObjects { namespace, prefix } => match db.get_namespace_id(namespace)? {
+            Some(ns_id) => Ok(ReadResponse::Objects(
+                db.list_objects(ns_id, prefix, LIST_LIMIT, "")?,
+            )),
+            None => Ok(ReadResponse::NamespaceNotFound),
+        },
+    }
+}
+
+/// Raft read-index reads: the result reflects every write committed before
+/// the call, on whichever node it runs.
+pub trait LinearizableRead {
+    /// On the leader, confirm leadership with a heartbeat round (skipped
+    /// within the leader lease), wait for the state machine to catch up,
+    /// then query `db`. On a follower, ask
+    /// the leader over gRPC (`LinearizableRead`) and wait until the local
+    /// state machine has applied the leader's read index, so later local
+    /// reads (e.g. the chunk map of a GET) are at least as fresh.
+    fn linearizable_read(
+        &self,
+        db: &Mutex<ManifestDb>,
+        query: ReadQuery,
+    ) -> impl Future<Output = anyhow::Result<ReadResponse>> + Send;
+}
+
+impl LinearizableRead for EnigmaRaft {
+    async fn linearizable_read(
+        &self,
+        db: &Mutex<ManifestDb>,
+        query: ReadQuery,
+    ) -> anyhow::Result<ReadResponse> {
+        let err = match leader_read_index(self).await {
+            Ok(_) => {
+                let db = db.lock().map_err(|_| anyhow::anyhow!("db lock"))?;
+                return Ok(execute(&db, &query)?);
+            }
+            Err(e) => e,
+        };
+
+        let leader_addr = match err.forward_to_leader::<BasicNode>() {
+            Some(ForwardToLeader {
+                leader_node: Some(node),
+                ..
+            }) => node.addr.clone(),
+            _ => anyhow::bail!("no Raft leader for linearizable read: {err}"),
+        };
+
+        let (response, read_index) = forward_read(&leader_addr, &query).await?;
+        if let Some(index) = read_index {
+            self.wait(Some(APPLY_TIMEOUT))
+                .applied_index_at_least(Some(index), "linearizable read")
+                .await?;
+        }
+        Ok(response)
+    }
+}
+
+/// Run `query` on the leader at `addr`. Returns the leader's answer and the
+/// read index it was served at.
+async fn forward_read(
+    addr: &str,
+    query: &ReadQuery,
+) -> anyhow::Result<(ReadResponse, Option<u64>)> {
+    let channel = crate::tls::connect(addr, crate::tls::forward_tls()).await?;
+    let mut client = RaftServiceClient::new(channel);
+    let data = serde_json::to_vec(query)?;
+    let resp = client
+        .linearizable_read(crate::proto::ReadRequest { data })
+        .await?
+        .into_inner();
+    Ok((serde_json::from_slice(&resp.data)?, resp.read_index))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lease_covers_reads_shortly_after_a_quorum_ack() {
+        let lease = Duration::from_millis(300);
+        assert!(lease_valid(ServerState::Leader, Some(120), lease));
+        assert!(!lease_valid(ServerState::Leader, Some(300), lease));
+        assert!(!lease_valid(ServerState::Leader, None, lease));
+        assert!(!lease_valid(ServerState::Follower, Some(10), lease));
+    }
+
+    #[test]
+    fn execute_queries() {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let ns = db.create_namespace("bucket").unwrap();
+        db.insert_object(ns, "a/1.txt", 5, "etag1", None, 1, "k1")
+            .unwrap();
+        db.insert_object(ns, "b/2.txt", 7, "etag2", None, 1, "k1")
+            .unwrap();
+
+        let resp = execute(
+            &db,
+            &ReadQuery::GetNamespace {
+                name: "bucket".into(),
+            },
+        )
+        .unwrap();
+        assert!(matches!(resp, ReadResponse::Namespace(Some(id)) if id == ns));
+
+        let resp = execute(
+            &db,
+            &ReadQuery::GetObject {
+                namespace: "bucket".into(),
+                key: "a/1.txt".into(),
+            },
+        )
+        .unwrap();
+        match resp {
+            ReadResponse::Object(Some(object)) => {
+                assert_eq!(object.size, 5);
+                assert_eq!(object.etag, "etag1");
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+
+        let resp = execute(
+            &db,
+            &ReadQuery::ListObjects {
+                namespace: "bucket".into(),
+                prefix: "b/".into(),
+            },
+        )
+        .unwrap();
+        match resp {
+            ReadResponse::Objects(objects) => {
+                assert_eq!(objects.len(), 1);
+                assert_eq!(objects[0].0, "b/2.txt");
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+
+        let resp = execute(
+            &db,
+            &ReadQuery::GetObject {
+                namespace: "missing".into(),
+                key: "a/1.txt".into(),
+            },
+        )
+        .unwrap();
+        assert!(matches!(resp, ReadResponse::NamespaceNotFound));
+    }
+}
@@ -14,6 +14,46 @@ use enigma_core::manifest::ManifestDb;
 use crate::TypeConfig;
 use crate::types::{RaftRequest, RaftResponse};
 
+/// Snapshot payload header: magic, format version, uncompressed size (u32 BE).
+const SNAPSHOT_MAGIC: &[u8; 4] = b"ESNT";
+const SNAPSHOT_VERSION: u8 = 1;
+const SNAPSHOT_HEADER_LEN: usize = 9;
+
+/// Zstd-compress a SQLite dump into a snapshot payload.
+pub fn compress_snapshot(db_bytes: &[u8], level: i32) -> std::io::Result<Vec<u8>> {
+    let size = u32::try_from(db_bytes.len())
+        .map_err(|_| std::io::Error::other("snapshot larger than 4 GiB"))?;
+    let compressed = zstd::bulk::compress(db_bytes, level)?;
+    let mut payload = Vec::with_capacity(SNAPSHOT_HEADER_LEN + compressed.len());
+    payload.extend_from_slice(SNAPSHOT_MAGIC);
+    payload.push(SNAPSHOT_VERSION);
+    payload.extend_from_slice(&size.to_be_bytes());
+    payload.extend_from_slice(&compressed);
+    Ok(payload)
+}
+
+/// Check a snapshot payload's header and return the SQLite dump.
+pub fn decompress_snapshot(payload: &[u8]) -> std::io::Result<Vec<u8>> {
+    if payload.len() < SNAPSHOT_HEADER_LEN || &payload[..4] != SNAPSHOT_MAGIC {
+        return Err(std::io::Error::other("not an Enigma snapshot"));
+    }
+    if payload[4] != SNAPSHOT_VERSION {
+        return Err(std::io::Error::other(format!(
+            "unsupported snapshot version {}",
+            payload[4]
+        )));
+    }
+    let size = u32::from_be_bytes(payload[5..9].try_into().unwrap()) as usize;
+    let db_bytes = zstd::bulk::decompress(&payload[SNAPSHOT_HEADER_LEN..], size)?;
+    if db_bytes.len() != size {
+        return Err(std::io::Error::other(format!(
+            "snapshot decompressed to {} bytes, header says {size}",
+            db_bytes.len()
+        )));
+    }
+    Ok(db_bytes)
+}
+
 /// Enigma Raft state machine wrapping ManifestDb.
 pub struct EnigmaStateMachine {
     pub db: Arc<Mutex<ManifestDb>>,
@@ -21,6 +61,7 @@ pub struct EnigmaStateMachine {
     last_membership: Arc<Mutex<StoredMembership<u64, BasicNode>>>,
     cached_snapshot: Arc<Mutex<Option<Snapshot<TypeConfig>>>>,
     db_path: String,
+    snapshot_compression_level: i32,
 }
 
 /// Snapshot builder — holds shared refs to the same state as the state machine.
@@ -29,6 +70,7 @@ pub struct EnigmaSnapshotBuilder {
     last_applied: Arc<Mutex<Option<LogId<u64>>>>,
     last_membership: Arc<Mutex<StoredMembership<u64, BasicNode>>>,
     cached_snapshot: Arc<Mutex<Option<Snapshot<TypeConfig>>>>,
+    compression_level: i32,
 }
 
 impl EnigmaStateMachine {
@@ -39,9 +81,16 @@ impl EnigmaStateMachine {
             last_membership: Arc::new(Mutex::new(StoredMembership::default())),
             cached_snapshot: Arc::new(Mutex::new(None)),
             db_path,
+            snapshot_compression_level: 3,
         }
     }
 
+    /// Zstd level for snapshots built by this node (default: 3).
+    pub fn with_snapshot_compression_level(mut self, level: i32) -> Self {
+        self.snapshot_compression_level = level;
+        self
+    }
+
     /// Apply a single RaftRequest to the ManifestDb.
     fn apply_request(&self, req: &RaftRequest) -> RaftResponse {
         let db = match self.db.lock() {
@@ -164,6 +213,13 @@ impl EnigmaStateMachine {
                     Err(e) => RaftResponse::Error(e.to_string()),
                 }
             }
+            RaftRequest::AddLearner { node_id, addr } => {
+                let message = format!("Raft learner {node_id} added at {addr}");
+                match db.log(None, "INFO", &message) {
+                    Ok(()) => RaftResponse::Ok,
+                    Err(e) => RaftResponse::Error(e.to_string()),
+                }
+            }
             RaftRequest::CreateMultipartUpload {
                 upload_id,
                 namespace_id,
@@ -183,7 +239,7 @@ impl EnigmaStateMachine {
             },
             RaftRequest::AbortMultipartUpload { upload_id } => {
                 match db.abort_multipart_upload(upload_id) {
-                    Ok(()) => RaftResponse::Ok,
+                    Ok(_) => RaftResponse::Ok,
                     Err(e) => RaftResponse::Error(e.to_string()),
                 }
             }
@@ -254,6 +310,7 @@ impl RaftStateMachine<TypeConfig> for EnigmaStateMachine {
             last_applied: self.last_applied.clone(),
             last_membership: self.last_membership.clone(),
             cached_snapshot: self.cached_snapshot.clone(),
+            compression_level: self.snapshot_compression_level,
         }
     }
 
@@ -277,19 +334,20 @@ impl RaftStateMachine<TypeConfig> for EnigmaStateMachine {
             "Installing snapshot — restoring ManifestDb"
         );
 
-        let new_db =
-            ManifestDb::restore_from_bytes(&bytes, Path::new(&self.db_path)).map_err(|e| {
-                let io_err = std::io::Error::other(e.to_string());
-                StorageError::from_io_error(
-                    openraft::ErrorSubject::Snapshot(Some(SnapshotSignature {
-                        last_log_id: meta.last_log_id,
-                        last_membership_log_id: *meta.last_membership.log_id(),
-                        snapshot_id: meta.snapshot_id.clone(),
-                    })),
-                    openraft::ErrorVerb::Write,
-                    io_err,
-                )
-            })?;
+        let snapshot_error = |io_err: std::io::Error| {
+            StorageError::from_io_error(
+                openraft::ErrorSubject::Snapshot(Some(SnapshotSignature {
+                    last_log_id: meta.last_log_id,
+                    last_membership_log_id: *meta.last_membership.log_id(),
+                    snapshot_id: meta.snapshot_id.clone(),
+                })),
+                openraft::ErrorVerb::Write,
+                io_err,
+            )
+        };
+        let db_bytes = decompress_snapshot(&bytes).map_err(snapshot_error)?;
+        let new_db = ManifestDb::restore_from_bytes(&db_bytes, Path::new(&self.db_path))
+            .map_err(|e| snapshot_error(std::io::Error::other(e.to_string())))?;
 
         *self.db.lock().map_err(|e| StorageError::IO {
             source: StorageIOError::write(&std::io::Error::other(format!("mutex poisoned: {e}"))),
@@ -371,7 +429,20 @@ impl openraft::storage::RaftSnapshotBuilder<TypeConfig> for EnigmaSnapshotBuilde
             })?
         };
 
-        tracing::info!(bytes = bytes.len(), "Snapshot built successfully");
+        let raw_len = bytes.len();
+        let bytes = compress_snapshot(&bytes, self.compression_level).map_err(|e| {
+            StorageError::from_io_error(
+                openraft::ErrorSubject::Snapshot(None::<SnapshotSignature<u64>>),
+                openraft::ErrorVerb::Write,
+                e,
+            )
+        })?;
+
+        tracing::info!(
+            raw_bytes = raw_len,
+            bytes = bytes.len(),
+            "Snapshot built successfully"
+        );
 
         let meta = SnapshotMeta {
             last_log_id: last_applied,
@@ -394,3 +465,87 @@ impl openraft::storage::RaftSnapshotBuilder<TypeConfig> for EnigmaSnapshotBuilde
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(sm: &EnigmaStateMachine, req: RaftRequest) -> RaftResponse {
+        let resp = sm.apply_request(&req);
+        assert!(!matches!(resp, RaftResponse::Error(_)), "{resp:?}");
+        resp
+    }
+
+    fn provider(sm: &EnigmaStateMachine, name: &str) -> i64 {
+        let req = RaftRequest::InsertProvider {
+            name: name.to_string(),
+            provider_type: "Local".to_string(),
+            bucket: format!("/tmp/{name}"),
+            region: None,
+            weight: 1,
+        };
+        match apply(sm, req) {
+            RaftResponse::ProviderId(id) => id,
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+
+    #[test]
+    fn last_decrement_returns_every_replica() {
+        let db = Arc::new(Mutex::new(ManifestDb::open_in_memory().unwrap()));
+        let sm = EnigmaStateMachine::new(db, ":memory:".to_string());
+        let p1 = provider(&sm, "p1");
+        let p2 = provider(&sm, "p2");
+
+        apply(
+            &sm,
+            RaftRequest::InsertOrDedupChunk {
+                hash: "h1".to_string(),
+                nonce: vec![0; 12],
+                key_id: "k1".to_string(),
+                provider_id: p1,
+                storage_key: "chunks/p1/h1".to_string(),
+                size_plain: 10,
+                size_encrypted: 38,
+                size_compressed: None,
+            },
+        );
+        apply(
+            &sm,
+            RaftRequest::InsertChunkReplicas {
+                chunk_hash: "h1".to_string(),
+                replicas: vec![
+                    (p1, "chunks/p1/h1".to_string()),
+                    (p2, "chunks/p2/h1".to_string()),
+                ],
+            },
+        );
+
+        let resp = apply(
+            &sm,
+            RaftRequest::DecrementChunkRef {
+                hash: "h1".to_string(),
+            },
+        );
+        let RaftResponse::ChunksDeleted { mut deletions } = resp else {
+            panic!("unexpected {resp:?}");
+        };
+        deletions.sort();
+        assert_eq!(
+            deletions,
+            vec![
+                (p1, "chunks/p1/h1".to_string()),
+                (p2, "chunks/p2/h1".to_string()),
+            ]
+        );
+
+        // The chunk is gone, so there is nothing left to delete
+        let resp = apply(
+            &sm,
+            RaftRequest::DecrementChunkRef {
+                hash: "h1".to_string(),
+            },
+        );
+        assert!(matches!(resp, RaftResponse::Ok), "{resp:?}");
+    }
+}
@@ -0,0 +1,65 @@
+use std::sync::OnceLock;
+
+use anyhow::Context;
+use tonic::transport::{
+    Certificate, Channel, ClientTlsConfig, Endpoint, Identity, ServerTlsConfig,
+};
+
+use crate::config::RaftTlsConfig;
+
+/// Client TLS used by follower-to-leader read forwarding. `LinearizableRead`
+/// is implemented on `EnigmaRaft` itself, so there is nowhere else to keep it.
+static FORWARD_TLS: OnceLock<ClientTlsConfig> = OnceLock::new();
+
+/// Load the node certificate, key and peer CA. The server config requires a
+/// client certificate signed by the CA; the client config presents this
+/// node's certificate and only trusts servers signed by the same CA.
+pub fn load_raft_tls(config: &RaftTlsConfig) -> anyhow::Result<(ServerTlsConfig, ClientTlsConfig)> {
+    // The AWS SDK also enables rustls' aws-lc-rs backend, and with two
+    // backends compiled in rustls refuses to pick one itself
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    let read = |path: &str, what: &str| {
+        std::fs::read(path).with_context(|| format!("failed to read Raft TLS {what} '{path}'"))
+    };
+    let identity = Identity::from_pem(
+        read(&config.cert_pem, "cert")?,
+        read(&config.key_pem, "key")?,
+    );
+    let ca = Certificate::from_pem(read(&config.ca_pem, "CA")?);
+
+    let server = ServerTlsConfig::new()
+        .identity(identity.clone())
+        .client_ca_root(ca.clone());
+    let client = ClientTlsConfig::new().identity(identity).ca_certificate(ca);
+    Ok((server, client))
+}
+
+/// Use `tls` for read forwarding to the leader. Only the first call wins.
+pub fn set_forward_tls(tls: ClientTlsConfig) {
+    let _ = FORWARD_TLS.set(tls);
+}
+
+pub(crate) fn forward_tls() -> Option<&'static ClientTlsConfig> {
+    FORWARD_TLS.get()
+}
+
+/// Connect to a peer's gRPC address (`host:port`), over TLS when `tls` is set.
+pub async fn connect(
+    addr: &str,
+    tls: Option<&ClientTlsConfig>,
+) -> Result<Channel, tonic::transport::Error> {
+    let channel = match tls {
+        Some(tls) => {
+            Endpoint::from_shared(format!("https://{addr}"))?
+                .tls_config(tls.clone())?
+                .connect()
+                .await?
+        }
+        None => {
+            Endpoint::from_shared(format!("http://{addr}"))?
+                .connect()
+                .await?
+        }
+    };
+    Ok(channel)
+}
@@ -63,6 +63,14 @@ pub enum RaftRequest {
         weight: u32,
     },
 
+    // Cluster ops
+    /// Recorded once openraft has added a learner, so every node's manifest
+    /// log shows it. Membership itself is changed by openraft.
+    AddLearner {
+        node_id: u64,
+        addr: String,
+    },
+
     // Multipart ops
     CreateMultipartUpload {
         upload_id: String,
@@ -80,6 +88,10 @@ pub enum RaftRequest {
     },
 }
 
+/// Result of applying a [`RaftRequest`]. `ChunksDeleted` holds the
+/// `(provider_id, storage_key)` of every copy, primary and replicas, of the
+/// chunks whose last reference went away; the caller deletes them from
+/// storage.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum RaftResponse {
     Ok,
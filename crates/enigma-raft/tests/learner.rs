@@ -0,0 +1,194 @@
+/// Learner test: a three-node cluster on localhost gRPC gains a fourth node
+/// as a learner. The learner replicates the manifest but never becomes a
+/// voter, so the quorum stays at the original three nodes.
+///
+/// Run:
+///   cargo test -p enigma-raft --test learner -- --nocapture
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use openraft::BasicNode;
+
+use enigma_core::manifest::ManifestDb;
+use enigma_raft::EnigmaRaft;
+use enigma_raft::grpc_server::EnigmaRaftGrpcServer;
+use enigma_raft::log_store::SqliteLogStore;
+use enigma_raft::network::EnigmaNetworkFactory;
+use enigma_raft::proto::raft_service_server::RaftServiceServer;
+use enigma_raft::state_machine::EnigmaStateMachine;
+use enigma_raft::types::{RaftRequest, RaftResponse};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+struct Node {
+    id: u64,
+    raft: Arc<EnigmaRaft>,
+    db: Arc<Mutex<ManifestDb>>,
+    _dir: tempfile::TempDir,
+}
+
+fn free_addr() -> String {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap().to_string()
+}
+
+async fn start_node(id: u64, peers: &HashMap<u64, String>) -> Node {
+    let dir = tempfile::tempdir().unwrap();
+    let db_path = dir.path().join("enigma.db");
+    let db = Arc::new(Mutex::new(ManifestDb::open(&db_path).unwrap()));
+    let log_store = SqliteLogStore::new(dir.path().join("raft-log.db").to_str().unwrap()).unwrap();
+    let state_machine = EnigmaStateMachine::new(db.clone(), db_path.display().to_string());
+    let network = EnigmaNetworkFactory::new(peers.clone());
+
+    let config = openraft::Config {
+        election_timeout_min: 300,
+        election_timeout_max: 600,
+        heartbeat_interval: 100,
+        ..Default::default()
+    };
+    let raft = openraft::Raft::new(
+        id,
+        Arc::new(config.validate().unwrap()),
+        network,
+        log_store,
+        state_machine,
+    )
+    .await
+    .unwrap();
+    let raft = Arc::new(raft);
+
+    let addr = peers[&id].parse().unwrap();
+    let svc = RaftServiceServer::new(EnigmaRaftGrpcServer::new(raft.clone(), db.clone()));
+    tokio::spawn(async move {
+        tonic::transport::Server::builder()
+            .add_service(svc)
+            .serve(addr)
+            .await
+            .unwrap();
+    });
+
+    Node {
+        id,
+        raft,
+        db,
+        _dir: dir,
+    }
+}
+
+async fn start_cluster(peers: &HashMap<u64, String>) -> Vec<Node> {
+    let mut nodes = Vec::new();
+    for id in 1..=3 {
+        nodes.push(start_node(id, peers).await);
+    }
+    // Let the gRPC servers bind
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let members: BTreeMap<u64, BasicNode> = peers
+        .iter()
+        .filter(|(id, _)| **id <= 3)
+        .map(|(id, addr)| (*id, BasicNode { addr: addr.clone() }))
+        .collect();
+    nodes[0].raft.initialize(members).await.unwrap();
+    nodes
+}
+
+async fn wait_for_leader(nodes: &[Node]) -> u64 {
+    let deadline = tokio::time::Instant::now() + TIMEOUT;
+    while tokio::time::Instant::now() < deadline {
+        for node in nodes {
+            if let Some(leader) = node.raft.metrics().borrow().current_leader {
+                return leader;
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("no leader elected");
+}
+
+async fn write(node: &Node, req: RaftRequest) -> RaftResponse {
+    let resp = node.raft.client_write(req).await.unwrap().data;
+    assert!(!matches!(resp, RaftResponse::Error(_)), "{resp:?}");
+    resp
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn learner_replicates_without_voting() {
+    let peers: HashMap<u64, String> = (1..=4).map(|id| (id, free_addr())).collect();
+    // Voters only know each other: the learner is reached through the
+    // address in the membership config
+    let voter_peers: HashMap<u64, String> = peers
+        .iter()
+        .filter(|(id, _)| **id <= 3)
+        .map(|(id, addr)| (*id, addr.clone()))
+        .collect();
+    let nodes = start_cluster(&voter_peers).await;
+    let learner = start_node(4, &peers).await;
+    let leader_id = wait_for_leader(&nodes).await;
+    let leader = &nodes[(leader_id - 1) as usize];
+
+    leader
+        .raft
+        .add_learner(
+            learner.id,
+            BasicNode {
+                addr: peers[&learner.id].clone(),
+            },
+            true,
+        )
+        .await
+        .unwrap();
+    write(
+        leader,
+        RaftRequest::AddLearner {
+            node_id: learner.id,
+            addr: peers[&learner.id].clone(),
+        },
+    )
+    .await;
+    write(
+        leader,
+        RaftRequest::CreateNamespace {
+            name: "bucket".into(),
+        },
+    )
+    .await;
+    let applied = leader
+        .raft
+        .client_write(RaftRequest::InsertObject {
+            namespace: "bucket".into(),
+            key: "replicated.txt".into(),
+            size: 7,
+            etag: "etag-learner".into(),
+            content_type: None,
+            chunk_count: 0,
+            key_id: "k1".into(),
+        })
+        .await
+        .unwrap()
+        .log_id;
+
+    learner
+        .raft
+        .wait(Some(TIMEOUT))
+        .applied_index_at_least(Some(applied.index), "learner caught up")
+        .await
+        .unwrap();
+    {
+        let db = learner.db.lock().unwrap();
+        let ns = db.get_namespace_id("bucket").unwrap().unwrap();
+        assert!(db.get_object(ns, "replicated.txt").unwrap().is_some());
+    }
+
+    let metrics = learner.raft.metrics().borrow().clone();
+    assert!(metrics.state.is_learner(), "{:?}", metrics.state);
+    let voters: BTreeSet<u64> = metrics.membership_config.membership().voter_ids().collect();
+    assert_eq!(voters, BTreeSet::from([1, 2, 3]));
+    assert!(
+        metrics
+            .membership_config
+            .membership()
+            .learner_ids()
+            .any(|id| id == learner.id)
+    );
+}
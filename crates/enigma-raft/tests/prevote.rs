@@ -0,0 +1,236 @@
+/// Pre-vote test: a three-node cluster on localhost gRPC, each link between
+/// two nodes going through a TCP forwarder that can be cut. A leader cut
+/// off for less than the election timeout keeps its leadership, and a
+/// follower cut off for several election timeouts neither raises its term
+/// nor deposes the leader when it comes back.
+///
+/// Run:
+///   cargo test -p enigma-raft --test prevote -- --nocapture
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use openraft::BasicNode;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+
+use enigma_core::manifest::ManifestDb;
+use enigma_raft::EnigmaRaft;
+use enigma_raft::grpc_server::EnigmaRaftGrpcServer;
+use enigma_raft::health::HealthService;
+use enigma_raft::health::proto::health_server::HealthServer;
+use enigma_raft::log_store::SqliteLogStore;
+use enigma_raft::network::EnigmaNetworkFactory;
+use enigma_raft::proto::raft_service_server::RaftServiceServer;
+use enigma_raft::state_machine::EnigmaStateMachine;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+const HEARTBEAT: Duration = Duration::from_millis(100);
+
+/// One direction of the link between two nodes: a TCP forwarder to the
+/// target node. Cutting it closes its open connections and refuses new ones.
+struct Link {
+    addr: String,
+    cut: watch::Sender<bool>,
+}
+
+async fn link_to(target: String) -> Link {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    let (cut, cut_rx) = watch::channel(false);
+    tokio::spawn(async move {
+        while let Ok((mut inbound, _)) = listener.accept().await {
+            if *cut_rx.borrow() {
+                continue;
+            }
+            let target = target.clone();
+            let mut cut_rx = cut_rx.clone();
+            tokio::spawn(async move {
+                let Ok(mut outbound) = TcpStream::connect(&target).await else {
+                    return;
+                };
+                tokio::select! {
+                    _ = tokio::io::copy_bidirectional(&mut inbound, &mut outbound) => {}
+                    _ = cut_rx.wait_for(|cut| *cut) => {}
+                }
+            });
+        }
+    });
+    Link { addr, cut }
+}
+
+struct Node {
+    id: u64,
+    raft: Arc<EnigmaRaft>,
+    _dir: tempfile::TempDir,
+}
+
+struct Cluster {
+    nodes: Vec<Node>,
+    /// (from, to) → link
+    links: HashMap<(u64, u64), Link>,
+}
+
+impl Cluster {
+    /// Cut or restore every link to and from `id`.
+    fn isolate(&self, id: u64, isolated: bool) {
+        for ((from, to), link) in &self.links {
+            if *from == id || *to == id {
+                link.cut.send_replace(isolated);
+            }
+        }
+    }
+
+    fn node(&self, id: u64) -> &Node {
+        self.nodes.iter().find(|node| node.id == id).unwrap()
+    }
+
+    /// (current leader, term) as seen by every node.
+    fn views(&self) -> Vec<(Option<u64>, u64)> {
+        self.nodes
+            .iter()
+            .map(|node| {
+                let m = node.raft.metrics().borrow().clone();
+                (m.current_leader, m.current_term)
+            })
+            .collect()
+    }
+}
+
+fn free_addr() -> String {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap().to_string()
+}
+
+/// Start node `id`, reaching every other node through its links.
+async fn start_node(id: u64, listen: &str, peers: HashMap<u64, String>) -> Node {
+    let dir = tempfile::tempdir().unwrap();
+    let db_path = dir.path().join("enigma.db");
+    let db = Arc::new(Mutex::new(ManifestDb::open(&db_path).unwrap()));
+    let log_store = SqliteLogStore::new(dir.path().join("raft-log.db").to_str().unwrap()).unwrap();
+    let state_machine = EnigmaStateMachine::new(db.clone(), db_path.display().to_string());
+    let network = EnigmaNetworkFactory::new(peers);
+    let shared_peers = network.peers.clone();
+
+    let config = openraft::Config {
+        election_timeout_min: 300,
+        election_timeout_max: 600,
+        heartbeat_interval: HEARTBEAT.as_millis() as u64,
+        ..Default::default()
+    };
+    let raft = openraft::Raft::new(
+        id,
+        Arc::new(config.validate().unwrap()),
+        network,
+        log_store,
+        state_machine,
+    )
+    .await
+    .unwrap();
+    let raft = Arc::new(raft);
+    tokio::spawn(enigma_raft::prevote::run(
+        raft.clone(),
+        shared_peers,
+        HEARTBEAT,
+    ));
+
+    let addr = listen.parse().unwrap();
+    let svc = RaftServiceServer::new(EnigmaRaftGrpcServer::new(raft.clone(), db));
+    tokio::spawn(async move {
+        tonic::transport::Server::builder()
+            .add_service(svc)
+            .add_service(HealthServer::new(HealthService))
+            .serve(addr)
+            .await
+            .unwrap();
+    });
+
+    Node {
+        id,
+        raft,
+        _dir: dir,
+    }
+}
+
+async fn start_cluster() -> Cluster {
+    let listen: HashMap<u64, String> = (1..=3).map(|id| (id, free_addr())).collect();
+    let mut links = HashMap::new();
+    for from in 1..=3 {
+        for to in (1..=3).filter(|to| *to != from) {
+            links.insert((from, to), link_to(listen[&to].clone()).await);
+        }
+    }
+
+    let mut nodes = Vec::new();
+    for id in 1..=3 {
+        let mut peers: HashMap<u64, String> = links
+            .iter()
+            .filter(|((from, _), _)| *from == id)
+            .map(|((_, to), link)| (*to, link.addr.clone()))
+            .collect();
+        peers.insert(id, listen[&id].clone());
+        nodes.push(start_node(id, &listen[&id], peers).await);
+    }
+    // Let the gRPC servers bind
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    // Membership addresses are only used for nodes missing from the peer
+    // maps, which every node has
+    let members: BTreeMap<u64, BasicNode> = listen
+        .iter()
+        .map(|(id, addr)| (*id, BasicNode { addr: addr.clone() }))
+        .collect();
+    nodes[0].raft.initialize(members).await.unwrap();
+    Cluster { nodes, links }
+}
+
+/// Wait until every node agrees on a leader, and return it with its term.
+async fn wait_for_stable_leader(cluster: &Cluster) -> (u64, u64) {
+    let deadline = tokio::time::Instant::now() + TIMEOUT;
+    while tokio::time::Instant::now() < deadline {
+        let views = cluster.views();
+        if let (Some(leader), term) = views[0]
+            && views.iter().all(|view| *view == (Some(leader), term))
+        {
+            return (leader, term);
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("no stable leader: {:?}", cluster.views());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn partitions_do_not_cause_spurious_elections() {
+    let cluster = start_cluster().await;
+    let (leader, term) = wait_for_stable_leader(&cluster).await;
+    // Let every prevote task see the full cluster once
+    tokio::time::sleep(HEARTBEAT * 5).await;
+
+    // A leader partition shorter than the election timeout goes unnoticed
+    cluster.isolate(leader, true);
+    tokio::time::sleep(Duration::from_millis(250)).await;
+    cluster.isolate(leader, false);
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    assert_eq!(wait_for_stable_leader(&cluster).await, (leader, term));
+
+    // A follower cut off for several election timeouts does not campaign,
+    // so it has no higher term to depose the leader with when it returns
+    let follower = (1..=3).find(|id| *id != leader).unwrap();
+    cluster.isolate(follower, true);
+    tokio::time::sleep(Duration::from_secs(3)).await;
+    assert_eq!(
+        cluster.node(follower).raft.metrics().borrow().current_term,
+        term
+    );
+    cluster.isolate(follower, false);
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    assert_eq!(wait_for_stable_leader(&cluster).await, (leader, term));
+    assert!(
+        cluster
+            .nodes
+            .iter()
+            .all(|node| node.raft.metrics().borrow().current_term == term),
+        "{:?}",
+        cluster.views()
+    );
+}
@@ -0,0 +1,231 @@
+/// Read-index test: a three-node cluster on localhost gRPC. After a leader
+/// change, linearizable reads on every node see the new leader's writes, and
+/// a leader that lost its quorum refuses to answer instead of serving stale
+/// data.
+///
+/// Run:
+///   cargo test -p enigma-raft --test read_index -- --nocapture
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use openraft::BasicNode;
+
+use enigma_core::manifest::ManifestDb;
+use enigma_raft::EnigmaRaft;
+use enigma_raft::grpc_server::EnigmaRaftGrpcServer;
+use enigma_raft::log_store::SqliteLogStore;
+use enigma_raft::network::EnigmaNetworkFactory;
+use enigma_raft::proto::raft_service_server::RaftServiceServer;
+use enigma_raft::read::{LinearizableRead, ReadQuery, ReadResponse};
+use enigma_raft::state_machine::EnigmaStateMachine;
+use enigma_raft::types::{RaftRequest, RaftResponse};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+struct Node {
+    id: u64,
+    raft: Arc<EnigmaRaft>,
+    db: Arc<Mutex<ManifestDb>>,
+    _dir: tempfile::TempDir,
+}
+
+fn free_addr() -> String {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap().to_string()
+}
+
+async fn start_node(id: u64, peers: &HashMap<u64, String>) -> Node {
+    let dir = tempfile::tempdir().unwrap();
+    let db_path = dir.path().join("enigma.db");
+    let db = Arc::new(Mutex::new(ManifestDb::open(&db_path).unwrap()));
+    let log_store = SqliteLogStore::new(dir.path().join("raft-log.db").to_str().unwrap()).unwrap();
+    let state_machine = EnigmaStateMachine::new(db.clone(), db_path.display().to_string());
+    let network = EnigmaNetworkFactory::new(peers.clone());
+
+    let config = openraft::Config {
+        election_timeout_min: 300,
+        election_timeout_max: 600,
+        heartbeat_interval: 100,
+        ..Default::default()
+    };
+    let raft = openraft::Raft::new(
+        id,
+        Arc::new(config.validate().unwrap()),
+        network,
+        log_store,
+        state_machine,
+    )
+    .await
+    .unwrap();
+    let raft = Arc::new(raft);
+
+    let addr = peers[&id].parse().unwrap();
+    let svc = RaftServiceServer::new(EnigmaRaftGrpcServer::new(raft.clone(), db.clone()));
+    tokio::spawn(async move {
+        tonic::transport::Server::builder()
+            .add_service(svc)
+            .serve(addr)
+            .await
+            .unwrap();
+    });
+
+    Node {
+        id,
+        raft,
+        db,
+        _dir: dir,
+    }
+}
+
+async fn start_cluster() -> Vec<Node> {
+    let peers: HashMap<u64, String> = (1..=3).map(|id| (id, free_addr())).collect();
+    let mut nodes = Vec::new();
+    for id in 1..=3 {
+        nodes.push(start_node(id, &peers).await);
+    }
+    // Let the gRPC servers bind
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let members: BTreeMap<u64, BasicNode> = peers
+        .iter()
+        .map(|(id, addr)| (*id, BasicNode { addr: addr.clone() }))
+        .collect();
+    nodes[0].raft.initialize(members).await.unwrap();
+    nodes
+}
+
+async fn wait_for_leader(nodes: &[Node]) -> u64 {
+    let deadline = tokio::time::Instant::now() + TIMEOUT;
+    while tokio::time::Instant::now() < deadline {
+        for node in nodes {
+            if let Some(leader) = node.raft.metrics().borrow().current_leader {
+                return leader;
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("no leader elected");
+}
+
+async fn write(node: &Node, req: RaftRequest) -> RaftResponse {
+    let resp = node.raft.client_write(req).await.unwrap().data;
+    assert!(!matches!(resp, RaftResponse::Error(_)), "{resp:?}");
+    resp
+}
+
+/// Make `leader` the leader of `nodes`. Its campaign can lose to voters
+/// that still heard from the old leader, which then wins the next term
+/// back, so it campaigns again until it is elected.
+async fn move_leadership(nodes: &[Node], leader: &Node) {
+    for _ in 0..5 {
+        leader.raft.trigger().elect().await.unwrap();
+        let elected = leader
+            .raft
+            .wait(Some(Duration::from_secs(2)))
+            .current_leader(leader.id, "leader change")
+            .await;
+        if elected.is_ok() {
+            break;
+        }
+    }
+    for node in nodes {
+        node.raft
+            .wait(Some(TIMEOUT))
+            .current_leader(leader.id, "leader change")
+            .await
+            .unwrap();
+    }
+}
+
+fn get_object(key: &str) -> ReadQuery {
+    ReadQuery::GetObject {
+        namespace: "bucket".into(),
+        key: key.into(),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn linearizable_reads_see_writes_after_leader_change() {
+    let nodes = start_cluster().await;
+    let old_leader = wait_for_leader(&nodes).await;
+    let old = &nodes[(old_leader - 1) as usize];
+    write(
+        old,
+        RaftRequest::CreateNamespace {
+            name: "bucket".into(),
+        },
+    )
+    .await;
+
+    // Move leadership to another node
+    let new_leader = nodes.iter().find(|n| n.id != old_leader).unwrap();
+    move_leadership(&nodes, new_leader).await;
+
+    write(
+        new_leader,
+        RaftRequest::InsertObject {
+            namespace: "bucket".into(),
+            key: "fresh.txt".into(),
+            size: 5,
+            etag: "etag-fresh".into(),
+            content_type: None,
+            chunk_count: 0,
+            key_id: "k1".into(),
+        },
+    )
+    .await;
+
+    // Followers — including the deposed leader — return the new write, and
+    // their local state machine has caught up to the read index.
+    for node in nodes.iter().filter(|n| n.id != new_leader.id) {
+        let resp = node
+            .raft
+            .linearizable_read(&node.db, get_object("fresh.txt"))
+            .await
+            .unwrap();
+        match resp {
+            ReadResponse::Object(Some(object)) => {
+                assert_eq!(object.size, 5);
+                assert_eq!(object.etag, "etag-fresh");
+            }
+            other => panic!("node {}: stale read {other:?}", node.id),
+        }
+
+        let db = node.db.lock().unwrap();
+        let ns = db.get_namespace_id("bucket").unwrap().unwrap();
+        assert!(db.get_object(ns, "fresh.txt").unwrap().is_some());
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn leader_without_quorum_refuses_reads() {
+    let nodes = start_cluster().await;
+    let leader_id = wait_for_leader(&nodes).await;
+    let leader = &nodes[(leader_id - 1) as usize];
+    write(
+        leader,
+        RaftRequest::CreateNamespace {
+            name: "bucket".into(),
+        },
+    )
+    .await;
+
+    for node in nodes.iter().filter(|n| n.id != leader_id) {
+        node.raft.shutdown().await.unwrap();
+    }
+
+    // The isolated leader can no longer prove it is the leader, so it must
+    // not answer from its possibly stale state.
+    let result = tokio::time::timeout(
+        Duration::from_secs(3),
+        leader
+            .raft
+            .linearizable_read(&leader.db, get_object("any.txt")),
+    )
+    .await;
+    assert!(
+        !matches!(result, Ok(Ok(_))),
+        "isolated leader served a read: {result:?}"
+    );
+}
@@ -0,0 +1,100 @@
+/// Snapshot compression test: a snapshot of a ~10 MB manifest is smaller
+/// than the raw SQLite file, carries the `ESNT` header, and installs on
+/// another node with its records intact.
+///
+/// Run:
+///   cargo test -p enigma-raft --test snapshot_compression -- --nocapture
+use std::io::Cursor;
+use std::sync::{Arc, Mutex};
+
+use openraft::storage::{RaftSnapshotBuilder, RaftStateMachine};
+
+use enigma_core::manifest::ManifestDb;
+use enigma_raft::state_machine::{EnigmaStateMachine, decompress_snapshot};
+
+const OBJECTS: usize = 20_000;
+
+fn state_machine(dir: &std::path::Path) -> EnigmaStateMachine {
+    let db_path = dir.join("enigma.db");
+    let db = Arc::new(Mutex::new(ManifestDb::open(&db_path).unwrap()));
+    EnigmaStateMachine::new(db, db_path.display().to_string())
+}
+
+#[tokio::test]
+async fn compressed_snapshot_installs_on_another_node() {
+    let leader_dir = tempfile::tempdir().unwrap();
+    let mut leader = state_machine(leader_dir.path());
+    let raw_len = {
+        let db = leader.db.lock().unwrap();
+        let ns = db.create_namespace("bucket").unwrap();
+        db.begin_transaction().unwrap();
+        for i in 0..OBJECTS {
+            let key = format!("photos/2024/{i:08}/{}", "x".repeat(180));
+            db.insert_object(ns, &key, i as u64, &format!("{i:064x}"), None, 1, "k1")
+                .unwrap();
+        }
+        db.commit_transaction().unwrap();
+        db.snapshot_to_bytes().unwrap().len()
+    };
+    assert!(raw_len >= 10 * 1024 * 1024, "raw DB only {raw_len} bytes");
+
+    let snapshot = leader
+        .get_snapshot_builder()
+        .await
+        .build_snapshot()
+        .await
+        .unwrap();
+    let payload = snapshot.snapshot.into_inner();
+    println!("snapshot: {raw_len} -> {} bytes", payload.len());
+    assert!(payload.starts_with(b"ESNT"));
+    assert!(payload.len() < raw_len);
+    assert_eq!(decompress_snapshot(&payload).unwrap().len(), raw_len);
+
+    let follower_dir = tempfile::tempdir().unwrap();
+    let mut follower = state_machine(follower_dir.path());
+    follower
+        .install_snapshot(&snapshot.meta, Box::new(Cursor::new(payload)))
+        .await
+        .unwrap();
+
+    let db = follower.db.lock().unwrap();
+    let ns = db.get_namespace_id("bucket").unwrap().unwrap();
+    let key = format!("photos/2024/{:08}/{}", 1234, "x".repeat(180));
+    let (_, size, etag, ..) = db.get_object(ns, &key).unwrap().unwrap();
+    assert_eq!(size, 1234);
+    assert_eq!(etag, format!("{:064x}", 1234));
+}
+
+#[tokio::test]
+async fn corrupted_snapshot_is_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut leader = state_machine(dir.path());
+    leader
+        .db
+        .lock()
+        .unwrap()
+        .create_namespace("bucket")
+        .unwrap();
+    let snapshot = leader
+        .get_snapshot_builder()
+        .await
+        .build_snapshot()
+        .await
+        .unwrap();
+    let mut payload = snapshot.snapshot.into_inner();
+
+    // Wrong version byte
+    payload[4] = 9;
+    assert!(decompress_snapshot(&payload).is_err());
+
+    // Raw SQLite bytes without the header
+    let raw = leader.db.lock().unwrap().snapshot_to_bytes().unwrap();
+    let follower_dir = tempfile::tempdir().unwrap();
+    let mut follower = state_machine(follower_dir.path());
+    assert!(
+        follower
+            .install_snapshot(&snapshot.meta, Box::new(Cursor::new(raw)))
+            .await
+            .is_err()
+    );
+}
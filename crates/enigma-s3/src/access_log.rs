@@ -0,0 +1,336 @@
+//! S3 server access logging.
+//!
+//! PutBucketLogging turns logging on for a bucket. [`AccessLogged`] wraps
+//! the S3 service and reports every request to an [`AccessLog`], whose
+//! writer task keeps the entries of buckets with logging on in the
+//! manifest's `s3_access_log` table. Requests only queue their entry, so a
+//! busy database never holds up a response.
+
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use chrono::{SecondsFormat, Utc};
+use http::{Extensions, HeaderMap};
+use s3s::dto::*;
+use s3s::s3_error;
+use s3s::{S3, S3Request, S3Response, S3Result};
+use tokio::sync::{mpsc, oneshot};
+
+use enigma_core::manifest::ManifestDb;
+use enigma_core::types::S3AccessLogEntry;
+
+use crate::SharedState;
+
+/// Entries waiting for the writer; more are dropped rather than block.
+const QUEUE_CAPACITY: usize = 10_000;
+
+/// Address of the client connection, put in the request extensions by the
+/// listener.
+#[derive(Debug, Clone, Copy)]
+pub struct RemoteAddr(pub SocketAddr);
+
+/// Handle PutBucketLogging: a `LoggingEnabled` element turns logging on,
+/// an empty `BucketLoggingStatus` turns it off. The target bucket must
+/// exist. Entries are kept in the manifest, not written to the target.
+pub async fn handle_put_bucket_logging(
+    state: &SharedState,
+    bucket: &str,
+    status: BucketLoggingStatus,
+) -> S3Result<S3Response<PutBucketLoggingOutput>> {
+    let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
+    let ns_id = db
+        .get_namespace_id(bucket)
+        .map_err(|_| s3_error!(InternalError))?
+        .ok_or_else(|| s3_error!(NoSuchBucket))?;
+    let target = status
+        .logging_enabled
+        .map(|enabled| (enabled.target_bucket, enabled.target_prefix));
+    if let Some((target_bucket, _)) = &target
+        && !db
+            .namespace_exists(target_bucket)
+            .map_err(|_| s3_error!(InternalError))?
+    {
+        return Err(s3_error!(
+            InvalidTargetBucketForLogging,
+            "The target bucket for logging does not exist"
+        ));
+    }
+    db.set_namespace_logging(
+        ns_id,
+        target.as_ref().map(|(b, p)| (b.as_str(), p.as_str())),
+    )
+    .map_err(|_| s3_error!(InternalError))?;
+
+    Ok(S3Response::new(PutBucketLoggingOutput::default()))
+}
+
+/// Handle GetBucketLogging. A bucket without logging has no `LoggingEnabled`.
+pub async fn handle_get_bucket_logging(
+    state: &SharedState,
+    bucket: &str,
+) -> S3Result<S3Response<GetBucketLoggingOutput>> {
+    let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
+    let ns_id = db
+        .get_namespace_id(bucket)
+        .map_err(|_| s3_error!(InternalError))?
+        .ok_or_else(|| s3_error!(NoSuchBucket))?;
+    let target = db
+        .get_namespace_logging(ns_id)
+        .map_err(|_| s3_error!(InternalError))?;
+
+    Ok(S3Response::new(GetBucketLoggingOutput {
+        logging_enabled: target.map(|(target_bucket, target_prefix)| LoggingEnabled {
+            target_bucket,
+            target_grants: None,
+            target_object_key_format: None,
+            target_prefix,
+        }),
+    }))
+}
+
+enum Message {
+    Entry(S3AccessLogEntry),
+    Flush(oneshot::Sender<()>),
+}
+
+/// Queue of access log entries, drained into the manifest by a background
+/// task. Clones share the queue; the task ends when the last one is dropped.
+#[derive(Clone)]
+pub struct AccessLog {
+    tx: mpsc::Sender<Message>,
+}
+
+impl AccessLog {
+    /// Spawn the writer task on the current Tokio runtime.
+    pub fn start(db: Arc<Mutex<ManifestDb>>) -> Self {
+        let (tx, mut rx) = mpsc::channel(QUEUE_CAPACITY);
+        tokio::spawn(async move {
+            while let Some(message) = rx.recv().await {
+                match message {
+                    Message::Entry(entry) => {
+                        if let Err(e) = write_entry(&db, &entry) {
+                            tracing::warn!("Failed to write S3 access log entry: {e}");
+                        }
+                    }
+                    Message::Flush(done) => {
+                        let _ = done.send(());
+                    }
+                }
+            }
+        });
+        Self { tx }
+    }
+
+    /// Queue `entry` without waiting; it is dropped if the queue is full.
+    pub fn record(&self, entry: S3AccessLogEntry) {
+        if self.tx.try_send(Message::Entry(entry)).is_err() {
+            tracing::warn!("S3 access log queue full, dropping entry");
+        }
+    }
+
+    /// Wait until every entry queued so far has been written.
+    pub async fn flush(&self) {
+        let (done, wait) = oneshot::channel();
+        if self.tx.send(Message::Flush(done)).await.is_ok() {
+            let _ = wait.await;
+        }
+    }
+}
+
+/// Store `entry` if its bucket has logging on.
+fn write_entry(db: &Mutex<ManifestDb>, entry: &S3AccessLogEntry) -> anyhow::Result<()> {
+    let db = db.lock().map_err(|_| anyhow::anyhow!("db lock"))?;
+    let Some(ns_id) = db.get_namespace_id(&entry.bucket)? else {
+        return Ok(());
+    };
+    if db.get_namespace_logging(ns_id)?.is_some() {
+        db.insert_s3_log(entry)?;
+    }
+    Ok(())
+}
+
+/// Delete access log entries older than `retention_days`. Returns the
+/// number of entries deleted.
+pub fn purge_expired(state: &SharedState, retention_days: u32) -> anyhow::Result<usize> {
+    let db = state.db.lock().map_err(|_| anyhow::anyhow!("db lock"))?;
+    Ok(db.purge_s3_logs(retention_days)?)
+}
+
+/// Client address: the first `X-Forwarded-For` hop when behind a proxy,
+/// else the connection's peer.
+fn requester_ip(headers: &HeaderMap, extensions: &Extensions) -> Option<String> {
+    headers
+        .get("x-forwarded-for")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(',').next())
+        .map(|ip| ip.trim().to_string())
+        .filter(|ip| !ip.is_empty())
+        .or_else(|| extensions.get::<RemoteAddr>().map(|a| a.0.ip().to_string()))
+}
+
+fn status_code<T>(result: &S3Result<S3Response<T>>) -> u16 {
+    match result {
+        Ok(_) => 200,
+        Err(e) => e.status_code().map_or(500, |s| s.as_u16()),
+    }
+}
+
+fn byte_count(length: Option<i64>) -> u64 {
+    length.unwrap_or(0).max(0) as u64
+}
+
+/// S3 service that logs every request of `inner` to an [`AccessLog`].
+pub struct AccessLogged<S> {
+    inner: S,
+    log: AccessLog,
+}
+
+impl<S: S3> AccessLogged<S> {
+    pub fn new(inner: S, log: AccessLog) -> Self {
+        Self { inner, log }
+    }
+
+    /// Entry for a request, before its outcome is known.
+    fn begin<I>(
+        &self,
+        req: &S3Request<I>,
+        operation: &str,
+        bucket: &str,
+        key: Option<&str>,
+    ) -> S3AccessLogEntry {
+        S3AccessLogEntry {
+            bucket: bucket.to_string(),
+            key: key.map(str::to_string),
+            operation: operation.to_string(),
+            requester_ip: requester_ip(&req.headers, &req.extensions),
+            timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            status_code: 0,
+            bytes_transferred: 0,
+        }
+    }
+
+    fn finish<T>(&self, mut entry: S3AccessLogEntry, result: &S3Result<S3Response<T>>, bytes: u64) {
+        entry.status_code = status_code(result);
+        entry.bytes_transferred = bytes;
+        self.log.record(entry);
+    }
+}
+
+/// Implement `S3` for [`AccessLogged`]: each listed operation is forwarded
+/// to the inner service and logged, with the object key for `key`
+/// operations and no transferred bytes. The trailing items are added to
+/// the impl as they are.
+macro_rules! logged_ops {
+    (@key $req:ident) => { None };
+    (@key $req:ident, $key:ident) => { Some($req.input.$key.as_str()) };
+    (
+        $($op:ident($input:ty) -> $output:ty, $name:literal $(, $key:ident)?;)*
+        { $($extra:tt)* }
+    ) => {
+        #[async_trait::async_trait]
+        impl<S: S3> S3 for AccessLogged<S> {
+            $(
+                async fn $op(&self, req: S3Request<$input>) -> S3Result<S3Response<$output>> {
+                    let key = logged_ops!(@key req $(, $key)?);
+                    let entry = self.begin(&req, $name, &req.input.bucket, key);
+                    let result = self.inner.$op(req).await;
+                    self.finish(entry, &result, 0);
+                    result
+                }
+            )*
+
+            $($extra)*
+        }
+    };
+}
+
+logged_ops! {
+    create_bucket(CreateBucketInput) -> CreateBucketOutput, "CreateBucket";
+    delete_bucket(DeleteBucketInput) -> DeleteBucketOutput, "DeleteBucket";
+    head_bucket(HeadBucketInput) -> HeadBucketOutput, "HeadBucket";
+    get_bucket_location(GetBucketLocationInput) -> GetBucketLocationOutput, "GetBucketLocation";
+    get_bucket_accelerate_configuration(GetBucketAccelerateConfigurationInput)
+        -> GetBucketAccelerateConfigurationOutput, "GetBucketAccelerateConfiguration";
+    get_bucket_request_payment(GetBucketRequestPaymentInput)
+        -> GetBucketRequestPaymentOutput, "GetBucketRequestPayment";
+    get_bucket_logging(GetBucketLoggingInput) -> GetBucketLoggingOutput, "GetBucketLogging";
+    put_bucket_logging(PutBucketLoggingInput) -> PutBucketLoggingOutput, "PutBucketLogging";
+    get_bucket_encryption(GetBucketEncryptionInput)
+        -> GetBucketEncryptionOutput, "GetBucketEncryption";
+    copy_object(CopyObjectInput) -> CopyObjectOutput, "CopyObject", key;
+    head_object(HeadObjectInput) -> HeadObjectOutput, "HeadObject", key;
+    get_object_attributes(GetObjectAttributesInput)
+        -> GetObjectAttributesOutput, "GetObjectAttributes", key;
+    delete_object(DeleteObjectInput) -> DeleteObjectOutput, "DeleteObject", key;
+    get_object_tagging(GetObjectTaggingInput)
+        -> GetObjectTaggingOutput, "GetObjectTagging", key;
+    put_object_tagging(PutObjectTaggingInput)
+        -> PutObjectTaggingOutput, "PutObjectTagging", key;
+    delete_object_tagging(DeleteObjectTaggingInput)
+        -> DeleteObjectTaggingOutput, "DeleteObjectTagging", key;
+    put_object_retention(PutObjectRetentionInput)
+        -> PutObjectRetentionOutput, "PutObjectRetention", key;
+    get_object_retention(GetObjectRetentionInput)
+        -> GetObjectRetentionOutput, "GetObjectRetention", key;
+    put_bucket_versioning(PutBucketVersioningInput)
+        -> PutBucketVersioningOutput, "PutBucketVersioning";
+    get_bucket_versioning(GetBucketVersioningInput)
+        -> GetBucketVersioningOutput, "GetBucketVersioning";
+    list_object_versions(ListObjectVersionsInput)
+        -> ListObjectVersionsOutput, "ListObjectVersions";
+    list_objects_v2(ListObjectsV2Input) -> ListObjectsV2Output, "ListObjectsV2";
+    list_objects(ListObjectsInput) -> ListObjectsOutput, "ListObjects";
+    create_multipart_upload(CreateMultipartUploadInput)
+        -> CreateMultipartUploadOutput, "CreateMultipartUpload", key;
+    complete_multipart_upload(CompleteMultipartUploadInput)
+        -> CompleteMultipartUploadOutput, "CompleteMultipartUpload", key;
+    abort_multipart_upload(AbortMultipartUploadInput)
+        -> AbortMultipartUploadOutput, "AbortMultipartUpload", key;
+    list_multipart_uploads(ListMultipartUploadsInput)
+        -> ListMultipartUploadsOutput, "ListMultipartUploads";
+
+    {
+        // ListBuckets has no bucket to log against
+        async fn list_buckets(
+            &self,
+            req: S3Request<ListBucketsInput>,
+        ) -> S3Result<S3Response<ListBucketsOutput>> {
+            self.inner.list_buckets(req).await
+        }
+
+        async fn put_object(
+            &self,
+            req: S3Request<PutObjectInput>,
+        ) -> S3Result<S3Response<PutObjectOutput>> {
+            let entry = self.begin(&req, "PutObject", &req.input.bucket, Some(&req.input.key));
+            let received = byte_count(req.input.content_length);
+            let result = self.inner.put_object(req).await;
+            self.finish(entry, &result, received);
+            result
+        }
+
+        async fn get_object(
+            &self,
+            req: S3Request<GetObjectInput>,
+        ) -> S3Result<S3Response<GetObjectOutput>> {
+            let entry = self.begin(&req, "GetObject", &req.input.bucket, Some(&req.input.key));
+            let result = self.inner.get_object(req).await;
+            let sent = result
+                .as_ref()
+                .map_or(0, |resp| byte_count(resp.output.content_length));
+            self.finish(entry, &result, sent);
+            result
+        }
+
+        async fn upload_part(
+            &self,
+            req: S3Request<UploadPartInput>,
+        ) -> S3Result<S3Response<UploadPartOutput>> {
+            let entry = self.begin(&req, "UploadPart", &req.input.bucket, Some(&req.input.key));
+            let received = byte_count(req.input.content_length);
+            let result = self.inner.upload_part(req).await;
+            self.finish(entry, &result, received);
+            result
+        }
+    }
+}
@@ -1,5 +1,77 @@
-use s3s::auth::{S3Auth, SecretKey};
-use s3s::s3_error;
+use std::net::IpAddr;
+
+use async_trait::async_trait;
+use s3s::access::{S3Access, S3AccessContext};
+use s3s::auth::{Credentials, S3Auth, SecretKey};
+use s3s::dto::{CopyObjectInput, CopySource};
+use s3s::{S3Request, S3Result, s3_error};
+
+use crate::SharedState;
+use crate::access_log::RemoteAddr;
+
+/// Permission needed for reads: Get, Head and List operations.
+pub const READ_PERMISSION: &str = "s3:read";
+/// Permission needed to write and delete objects.
+pub const WRITE_PERMISSION: &str = "s3:write";
+/// Permission needed to create and delete buckets and change their
+/// configuration.
+pub const ADMIN_PERMISSION: &str = "s3:admin";
+
+/// The permission an S3 operation needs, by its name.
+pub fn required_permission(operation: &str) -> &'static str {
+    if ["Get", "Head", "List"]
+        .iter()
+        .any(|verb| operation.starts_with(verb))
+        || operation == "SelectObjectContent"
+    {
+        READ_PERMISSION
+    } else if matches!(operation, "CreateBucket" | "DeleteBucket")
+        || operation.starts_with("PutBucket")
+        || operation.starts_with("DeleteBucket")
+    {
+        ADMIN_PERMISSION
+    } else {
+        WRITE_PERMISSION
+    }
+}
+
+/// Permission lookup for callers, identified by the access key their
+/// request was signed with.
+#[async_trait]
+pub trait AccessControl: Send + Sync {
+    /// Whether the owner of `access_key` holds `permission`.
+    async fn has_permission(&self, access_key: &str, permission: &str) -> anyhow::Result<bool>;
+
+    /// Record in the audit log that the owner of `access_key` performed
+    /// `action` on `target`. Does nothing by default.
+    async fn log_audit(
+        &self,
+        _access_key: &str,
+        _action: &str,
+        _target: &str,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Namespace name prefix the owner of `access_key` is restricted to:
+    /// `ListBuckets` only returns namespaces starting with it and
+    /// [`EnigmaS3Access`] refuses requests for any other bucket. `None`, the
+    /// default, allows every namespace.
+    async fn namespace_prefix(&self, _access_key: &str) -> anyhow::Result<Option<String>> {
+        Ok(None)
+    }
+
+    /// Whether the owner of `access_key` may send requests from
+    /// `client_ip`, `None` when the address is unknown. Any address may by
+    /// default.
+    async fn allows_ip(
+        &self,
+        _access_key: &str,
+        _client_ip: Option<IpAddr>,
+    ) -> anyhow::Result<bool> {
+        Ok(true)
+    }
+}
 
 /// Simple static credential auth for Enigma S3 proxy.
 pub struct EnigmaS3Auth {
@@ -26,3 +98,108 @@ impl S3Auth for EnigmaS3Auth {
         }
     }
 }
+
+/// Request access checks: callers must sign their requests from an
+/// address [`AccessControl::allows_ip`] accepts and hold the
+/// [`required_permission`] of the operation, and callers with a
+/// [`AccessControl::namespace_prefix`] may only reach buckets starting with
+/// it, as target or as copy source. Without
+/// [`crate::EnigmaS3State::access_control`] any signed request passes.
+pub struct EnigmaS3Access {
+    state: SharedState,
+}
+
+impl EnigmaS3Access {
+    pub fn new(state: SharedState) -> Self {
+        Self { state }
+    }
+
+    /// Refuse with `AccessDenied` if the caller may not send requests from
+    /// `client_ip`.
+    async fn check_ip(&self, credentials: &Credentials, client_ip: Option<IpAddr>) -> S3Result<()> {
+        let Some(access) = self.state.access_control.get() else {
+            return Ok(());
+        };
+        let allowed = access
+            .allows_ip(&credentials.access_key, client_ip)
+            .await
+            .map_err(|e| s3_error!(InternalError, "IP check failed: {e}"))?;
+        if !allowed {
+            return Err(s3_error!(
+                AccessDenied,
+                "Requests from this address are not allowed for this access key"
+            ));
+        }
+        Ok(())
+    }
+
+    /// Refuse with `AccessDenied` if the caller lacks the permission
+    /// `operation` needs.
+    async fn check_operation(&self, credentials: &Credentials, operation: &str) -> S3Result<()> {
+        let Some(access) = self.state.access_control.get() else {
+            return Ok(());
+        };
+        let permission = required_permission(operation);
+        let allowed = access
+            .has_permission(&credentials.access_key, permission)
+            .await
+            .map_err(|e| s3_error!(InternalError, "permission lookup failed: {e}"))?;
+        if !allowed {
+            return Err(s3_error!(
+                AccessDenied,
+                "{operation} needs the {permission} permission"
+            ));
+        }
+        Ok(())
+    }
+
+    /// Refuse with `AccessDenied` if `bucket` is outside the caller's
+    /// namespace prefix.
+    async fn check_bucket(&self, credentials: &Credentials, bucket: &str) -> S3Result<()> {
+        let Some(access) = self.state.access_control.get() else {
+            return Ok(());
+        };
+        let prefix = access
+            .namespace_prefix(&credentials.access_key)
+            .await
+            .map_err(|e| s3_error!(InternalError, "namespace lookup failed: {e}"))?;
+        match prefix {
+            Some(prefix) if !bucket.starts_with(&prefix) => Err(s3_error!(
+                AccessDenied,
+                "Bucket {bucket} is outside namespace prefix {prefix}"
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl S3Access for EnigmaS3Access {
+    async fn check(&self, cx: &mut S3AccessContext<'_>) -> S3Result<()> {
+        // The S3 listener has no trusted proxies, so X-Forwarded-For is
+        // ignored. IPv4 peers on a dual-stack socket show up as ::ffff:a.b.c.d
+        let client_ip = cx
+            .extensions_mut()
+            .get::<RemoteAddr>()
+            .map(|addr| addr.0.ip().to_canonical());
+        let Some(credentials) = cx.credentials() else {
+            return Err(s3_error!(AccessDenied, "Signature is required"));
+        };
+        self.check_ip(credentials, client_ip).await?;
+        self.check_operation(credentials, cx.s3_op().name()).await?;
+        match cx.s3_path().get_bucket_name() {
+            Some(bucket) => self.check_bucket(credentials, bucket).await,
+            None => Ok(()),
+        }
+    }
+
+    async fn copy_object(&self, req: &mut S3Request<CopyObjectInput>) -> S3Result<()> {
+        // CopyObject rejects access point sources itself
+        match (&req.credentials, &req.input.copy_source) {
+            (Some(credentials), CopySource::Bucket { bucket, .. }) => {
+                self.check_bucket(credentials, bucket).await
+            }
+            _ => Ok(()),
+        }
+    }
+}
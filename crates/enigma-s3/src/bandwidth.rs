@@ -0,0 +1,144 @@
+//! Per-provider bandwidth limits on chunk transfers.
+//!
+//! Each limited provider gets a token bucket holding one token per byte,
+//! refilled at the configured rate and shared by every concurrent upload
+//! (or download) to that provider. A transfer takes tokens for its
+//! ciphertext size; the bucket holds one second's worth, so short bursts
+//! go through at full speed.
+
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+use std::sync::Arc;
+
+use enigma_core::config::ProviderConfig;
+use enigma_core::types::ProviderInfo;
+use governor::{DefaultDirectRateLimiter, Quota, RateLimiter};
+
+/// Limiters by provider ID. Providers without an entry are unlimited.
+pub type ProviderLimiters = HashMap<i64, Arc<BandwidthLimiter>>;
+
+/// Upload and download limits of one provider.
+pub struct BandwidthLimiter {
+    upload: Option<Bucket>,
+    download: Option<Bucket>,
+}
+
+impl BandwidthLimiter {
+    /// Limit transfers to the given bytes per second; `None` means no limit.
+    pub fn new(upload_bps: Option<u64>, download_bps: Option<u64>) -> Self {
+        Self {
+            upload: upload_bps.and_then(Bucket::new),
+            download: download_bps.and_then(Bucket::new),
+        }
+    }
+
+    /// Wait until `bytes` may be uploaded.
+    pub async fn upload(&self, bytes: usize) {
+        if let Some(bucket) = &self.upload {
+            bucket.take(bytes).await;
+        }
+    }
+
+    /// Wait until `bytes` may be downloaded.
+    pub async fn download(&self, bytes: usize) {
+        if let Some(bucket) = &self.download {
+            bucket.take(bytes).await;
+        }
+    }
+}
+
+/// Limiters for the configured providers with a bandwidth limit, matched
+/// to their IDs in `providers` by name.
+pub fn limiters(configs: &[ProviderConfig], providers: &[ProviderInfo]) -> ProviderLimiters {
+    configs
+        .iter()
+        .filter(|c| c.upload_bandwidth_bps.is_some() || c.download_bandwidth_bps.is_some())
+        .filter_map(|c| {
+            let info = providers.iter().find(|p| p.name == c.name)?;
+            let limiter = BandwidthLimiter::new(c.upload_bandwidth_bps, c.download_bandwidth_bps);
+            Some((info.id, Arc::new(limiter)))
+        })
+        .collect()
+}
+
+/// Wait for upload bandwidth to provider `id`, if it is limited.
+pub(crate) async fn throttle_upload(limiters: &ProviderLimiters, id: i64, bytes: usize) {
+    if let Some(limiter) = limiters.get(&id) {
+        limiter.upload(bytes).await;
+    }
+}
+
+/// Wait for download bandwidth from provider `id`, if it is limited.
+pub(crate) async fn throttle_download(limiters: &ProviderLimiters, id: i64, bytes: usize) {
+    if let Some(limiter) = limiters.get(&id) {
+        limiter.download(bytes).await;
+    }
+}
+
+struct Bucket {
+    limiter: DefaultDirectRateLimiter,
+    capacity: NonZeroU32,
+}
+
+impl Bucket {
+    fn new(bps: u64) -> Option<Self> {
+        let capacity = NonZeroU32::new(bps.min(u64::from(u32::MAX)) as u32)?;
+        Some(Self {
+            limiter: RateLimiter::direct(Quota::per_second(capacity)),
+            capacity,
+        })
+    }
+
+    /// Take `bytes` tokens, in bucket-sized pieces since a chunk can be
+    /// larger than the bucket.
+    async fn take(&self, bytes: usize) {
+        let mut remaining = bytes;
+        while remaining > 0 {
+            let piece = remaining.min(self.capacity.get() as usize);
+            let n = NonZeroU32::new(piece as u32).unwrap_or(NonZeroU32::MIN);
+            // Only fails for more tokens than the bucket holds
+            let _ = self.limiter.until_n_ready(n).await;
+            remaining -= piece;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    const MB: usize = 1024 * 1024;
+
+    async fn upload_5mb(limiter: &BandwidthLimiter) -> Duration {
+        let started = Instant::now();
+        for _ in 0..5 {
+            limiter.upload(MB).await;
+        }
+        started.elapsed()
+    }
+
+    #[tokio::test]
+    async fn upload_is_held_to_the_limit() {
+        let limited = BandwidthLimiter::new(Some(MB as u64), None);
+        // The first second's worth goes through at once, the rest at 1 MB/s
+        let elapsed = upload_5mb(&limited).await;
+        assert!(elapsed >= Duration::from_millis(3900), "{elapsed:?}");
+
+        // A download limit leaves uploads alone
+        let unlimited = BandwidthLimiter::new(None, Some(MB as u64));
+        let fast = upload_5mb(&unlimited).await;
+        assert!(fast < elapsed / 10, "{fast:?}");
+    }
+
+    #[tokio::test]
+    async fn chunks_larger_than_the_bucket_are_split() {
+        let limiter = BandwidthLimiter::new(Some(1000), None);
+        let started = Instant::now();
+        limiter.upload(2500).await;
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(1400), "{elapsed:?}");
+        assert!(elapsed < Duration::from_secs(3), "{elapsed:?}");
+    }
+}
@@ -1,113 +1,373 @@
+use http::header::{CONTENT_LENGTH, ETAG, IF_MODIFIED_SINCE, IF_UNMODIFIED_SINCE, LAST_MODIFIED};
+use http::{HeaderMap, HeaderName, HeaderValue};
 use s3s::dto::*;
 use s3s::s3_error;
-use s3s::{S3Response, S3Result};
+use s3s::{S3Error, S3ErrorCode, S3Response, S3Result};
 
-use enigma_core::compression::decompress_chunk;
-use enigma_core::crypto::decrypt_chunk;
-use enigma_core::dedup::compute_hash;
-use enigma_core::types::{ChunkHash, EncryptedChunk};
+use enigma_raft::read::{LinearizableRead, ObjectRow, ReadQuery, ReadResponse};
 
 use crate::SharedState;
 
-/// Handle GetObject: query metadata → download chunks → decrypt → reassemble.
-pub async fn handle_get_object(
+/// Look up object metadata for GET/HEAD: the latest version, or
+/// `version_id` if given. In Raft mode this is a linearizable read, so a
+/// follower never serves an object older than the last committed write (or
+/// one already deleted).
+pub async fn lookup_object(
     state: &SharedState,
     bucket: &str,
     key: &str,
-) -> S3Result<S3Response<GetObjectOutput>> {
-    // Get object metadata
-    let (object_id, size, etag, content_type, _chunk_count, _key_id, _last_modified) = {
-        let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
-        let ns_id = db
-            .get_namespace_id(bucket)
-            .map_err(|_| s3_error!(InternalError))?
-            .ok_or_else(|| s3_error!(NoSuchBucket))?;
-
-        db.get_object(ns_id, key)
-            .map_err(|_| s3_error!(InternalError))?
-            .ok_or_else(|| s3_error!(NoSuchKey))?
+    version_id: Option<&str>,
+) -> S3Result<ObjectRow> {
+    let missing = || match version_id {
+        Some(_) => s3_error!(NoSuchVersion),
+        None => s3_error!(NoSuchKey),
     };
 
-    // Get ordered chunks
-    let chunk_list = {
-        let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
-        db.get_object_chunks(object_id)
-            .map_err(|_| s3_error!(InternalError))?
-    };
-
-    // Fetch all chunk info in one pass under a single DB lock
-    let chunk_infos: Vec<_> = {
-        let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
-        chunk_list
-            .iter()
-            .map(|(chunk_hash_hex, _chunk_index, _offset)| {
-                db.get_chunk_info(chunk_hash_hex)
-                    .map_err(|_| s3_error!(InternalError))?
-                    .ok_or_else(|| s3_error!(InternalError))
-            })
-            .collect::<S3Result<Vec<_>>>()?
-    };
-    // DB lock is released here — I/O below does not hold it
+    if let Some(raft) = state.raft.get() {
+        let query = match version_id {
+            Some(version_id) => ReadQuery::GetObjectVersion {
+                namespace: bucket.to_string(),
+                key: key.to_string(),
+                version_id: version_id.to_string(),
+            },
+            None => ReadQuery::GetObject {
+                namespace: bucket.to_string(),
+                key: key.to_string(),
+            },
+        };
+        return match raft.linearizable_read(&state.db, query).await {
+            Ok(ReadResponse::Object(Some(obj))) => Ok(obj),
+            Ok(ReadResponse::Object(None)) => Err(missing()),
+            Ok(ReadResponse::NamespaceNotFound) => Err(s3_error!(NoSuchBucket)),
+            Ok(other) => {
+                tracing::error!("Unexpected read response for {bucket}/{key}: {other:?}");
+                Err(s3_error!(InternalError))
+            }
+            Err(e) => {
+                tracing::warn!("Linearizable read failed for {bucket}/{key}: {e:#}");
+                Err(s3_error!(ServiceUnavailable))
+            }
+        };
+    }
 
-    // Download, decrypt, and reassemble
-    let mut file_data = Vec::with_capacity(size as usize);
+    let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
+    let ns_id = db
+        .get_namespace_id(bucket)
+        .map_err(|_| s3_error!(InternalError))?
+        .ok_or_else(|| s3_error!(NoSuchBucket))?;
 
-    for ((chunk_hash_hex, _chunk_index, _offset), chunk_info) in chunk_list.iter().zip(chunk_infos)
-    {
-        let (nonce, _chunk_key_id, provider_id, storage_key, _size_enc, size_compressed) =
-            chunk_info;
-
-        // Download from storage
-        let provider = state
-            .providers
-            .get(&provider_id)
-            .ok_or_else(|| s3_error!(InternalError))?;
-        let ciphertext = provider
-            .download_chunk(&storage_key)
-            .await
-            .map_err(|_| s3_error!(InternalError))?;
+    match version_id {
+        Some(version_id) => db.get_object_version(ns_id, key, version_id),
+        None => db.get_object(ns_id, key),
+    }
+    .map_err(|_| s3_error!(InternalError))?
+    .map(ObjectRow::from)
+    .ok_or_else(missing)
+}
 
-        // Decrypt
-        let nonce_arr: [u8; 12] = nonce.try_into().map_err(|_| s3_error!(InternalError))?;
+// ── Conditional requests ───────────────────────────────────
 
-        let hash_bytes: [u8; 32] = hex::decode(chunk_hash_hex)
-            .map_err(|_| s3_error!(InternalError))?
-            .try_into()
-            .map_err(|_| s3_error!(InternalError))?;
+/// `If-*` request headers of GetObject / HeadObject.
+#[derive(Debug, Default, Clone)]
+pub struct Preconditions {
+    pub if_match: Option<String>,
+    pub if_none_match: Option<String>,
+    pub if_modified_since: Option<Timestamp>,
+    pub if_unmodified_since: Option<Timestamp>,
+}
 
-        let encrypted = EncryptedChunk {
-            hash: ChunkHash(hash_bytes),
-            nonce: nonce_arr,
-            ciphertext,
-            key_id: state.key_material.id.clone(),
+impl Preconditions {
+    /// `If-Modified-Since` / `If-Unmodified-Since` of a request whose input
+    /// does not model them, e.g. HeadBucket. Dates that are not HTTP dates
+    /// are ignored, as S3 does.
+    pub fn from_date_headers(headers: &HeaderMap) -> Self {
+        let date = |name: HeaderName| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| Timestamp::parse(TimestampFormat::HttpDate, v).ok())
         };
+        Self {
+            if_modified_since: date(IF_MODIFIED_SINCE),
+            if_unmodified_since: date(IF_UNMODIFIED_SINCE),
+            ..Default::default()
+        }
+    }
 
-        let decrypted =
-            decrypt_chunk(&encrypted, &state.key_material).map_err(|_| s3_error!(InternalError))?;
+    /// Evaluate against the stored object, in RFC 9110 order: a failed
+    /// `If-Match` (or, without it, `If-Unmodified-Since`) is 412; a matching
+    /// `If-None-Match` (or, without it, an unmet `If-Modified-Since`) is 304.
+    pub fn check(&self, etag: &str, created_at: &str) -> S3Result<()> {
+        let modified = parse_created_at(created_at);
 
-        // Decompress if this chunk was compressed
-        let plaintext = if size_compressed.is_some() {
-            decompress_chunk(&decrypted).map_err(|_| s3_error!(InternalError))?
-        } else {
-            decrypted
-        };
+        if let Some(if_match) = &self.if_match {
+            if !etag_matches(if_match, etag) {
+                return Err(s3_error!(PreconditionFailed));
+            }
+        } else if let (Some(since), Some(modified)) = (&self.if_unmodified_since, modified)
+            && timestamp_secs(since).is_some_and(|since| modified > since)
+        {
+            return Err(s3_error!(PreconditionFailed));
+        }
 
-        // Verify chunk hash
-        let computed = compute_hash(&plaintext);
-        if computed.to_hex() != *chunk_hash_hex {
-            return Err(s3_error!(InternalError));
+        if let Some(if_none_match) = &self.if_none_match {
+            if etag_matches(if_none_match, etag) {
+                return Err(s3_error!(NotModified));
+            }
+        } else if let (Some(since), Some(modified)) = (&self.if_modified_since, modified)
+            && timestamp_secs(since).is_some_and(|since| modified <= since)
+        {
+            return Err(s3_error!(NotModified));
         }
 
-        file_data.extend_from_slice(&plaintext);
+        Ok(())
     }
+}
+
+/// Add the validators a 304 carries, so the client can refresh its cached
+/// copy: `ETag`, `Content-Length` and `Last-Modified`. Other errors pass
+/// through.
+fn with_validators(mut err: S3Error, etag: &str, size: u64, created_at: &str) -> S3Error {
+    if *err.code() != S3ErrorCode::NotModified {
+        return err;
+    }
+    let mut headers = HeaderMap::new();
+    if let Ok(value) = HeaderValue::from_str(&format!("\"{etag}\"")) {
+        headers.insert(ETAG, value);
+    }
+    headers.insert(CONTENT_LENGTH, HeaderValue::from(size));
+    if let Some(modified) = parse_created_at(created_at)
+        .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0))
+        .and_then(|t| {
+            HeaderValue::from_str(&t.format("%a, %d %b %Y %H:%M:%S GMT").to_string()).ok()
+        })
+    {
+        headers.insert(LAST_MODIFIED, modified);
+    }
+    err.set_headers(headers);
+    err
+}
+
+/// Whether an `If-Match` / `If-None-Match` value matches `etag`. The value
+/// is `*` or a comma-separated list of ETags, each optionally quoted (the
+/// AWS convention) and optionally weak (`W/"..."`).
+fn etag_matches(header: &str, etag: &str) -> bool {
+    header.split(',').map(str::trim).any(|candidate| {
+        candidate == "*"
+            || candidate
+                .trim_start_matches("W/")
+                .trim_matches('"')
+                .eq(etag.trim_matches('"'))
+    })
+}
+
+/// Seconds since the Unix epoch of an `objects.created_at` value
+/// (SQLite `datetime('now')`, UTC).
+fn parse_created_at(created_at: &str) -> Option<i64> {
+    chrono::NaiveDateTime::parse_from_str(created_at, "%Y-%m-%d %H:%M:%S")
+        .ok()
+        .map(|t| t.and_utc().timestamp())
+}
+
+pub(crate) fn timestamp_secs(ts: &Timestamp) -> Option<i64> {
+    let mut buf = Vec::new();
+    ts.format(TimestampFormat::EpochSeconds, &mut buf).ok()?;
+    let secs: f64 = std::str::from_utf8(&buf).ok()?.parse().ok()?;
+    Some(secs.floor() as i64)
+}
+
+/// `Last-Modified` for an object (or `Initiated` for an upload), from its
+/// `created_at`.
+pub(crate) fn last_modified(created_at: &str) -> Option<Timestamp> {
+    let secs = parse_created_at(created_at)?;
+    Timestamp::parse(TimestampFormat::EpochSeconds, &secs.to_string()).ok()
+}
+
+/// User-defined metadata of an object, for the `x-amz-meta-*` response headers.
+fn user_metadata(state: &SharedState, object_id: i64) -> S3Result<Option<Metadata>> {
+    let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
+    let meta = db
+        .get_object_metadata(object_id)
+        .map_err(|_| s3_error!(InternalError))?;
+    Ok((!meta.is_empty()).then(|| meta.into_iter().collect()))
+}
+
+/// Handle GetObject: query metadata → stream chunks (download → decrypt → verify).
+/// Only one decrypted chunk is held in memory at a time, and the next one is
+/// fetched when the client has taken it; a chunk that fails verification
+/// aborts the response body. Objects under `stream_response_threshold_bytes`
+/// are read in full first, so their errors are reported as a 500 instead.
+pub async fn handle_get_object(
+    state: &SharedState,
+    bucket: &str,
+    key: &str,
+    version_id: Option<&str>,
+    preconditions: &Preconditions,
+) -> S3Result<S3Response<GetObjectOutput>> {
+    // Get object metadata
+    let ObjectRow {
+        id: object_id,
+        size,
+        etag,
+        content_type,
+        created_at,
+        ..
+    } = lookup_object(state, bucket, key, version_id).await?;
+    preconditions
+        .check(&etag, &created_at)
+        .map_err(|e| with_validators(e, &etag, size, &created_at))?;
+    let metadata = user_metadata(state, object_id)?;
+
+    let body = if size < state.config.enigma.stream_response_threshold_bytes {
+        let data = crate::ops::read_object(state, object_id, size)
+            .await
+            .map_err(|e| {
+                tracing::error!("Reading object {bucket}/{key} failed: {e:#}");
+                s3_error!(InternalError)
+            })?;
+        StreamingBlob::from(s3s::Body::from(data))
+    } else {
+        StreamingBlob::wrap(crate::ops::retrieve_object_streaming(state, object_id))
+    };
 
     let output = GetObjectOutput {
         content_length: Some(size as i64),
         e_tag: Some(format!("\"{etag}\"")),
         content_type: content_type.and_then(|ct| ct.parse().ok()),
-        body: Some(StreamingBlob::from(s3s::Body::from(file_data))),
+        last_modified: last_modified(&created_at),
+        body: Some(body),
+        metadata,
+        version_id: version_id.map(str::to_string),
+        ..Default::default()
+    };
+
+    Ok(S3Response::new(output))
+}
+
+/// Handle HeadObject: GetObject's metadata and preconditions, without a body.
+pub async fn handle_head_object(
+    state: &SharedState,
+    bucket: &str,
+    key: &str,
+    version_id: Option<&str>,
+    preconditions: &Preconditions,
+) -> S3Result<S3Response<HeadObjectOutput>> {
+    let ObjectRow {
+        id: object_id,
+        size,
+        etag,
+        content_type,
+        created_at,
+        ..
+    } = lookup_object(state, bucket, key, version_id).await?;
+    preconditions
+        .check(&etag, &created_at)
+        .map_err(|e| with_validators(e, &etag, size, &created_at))?;
+
+    let output = HeadObjectOutput {
+        content_length: Some(size as i64),
+        e_tag: Some(format!("\"{etag}\"")),
+        content_type: content_type.and_then(|ct| ct.parse().ok()),
+        last_modified: last_modified(&created_at),
+        metadata: user_metadata(state, object_id)?,
+        version_id: version_id.map(str::to_string),
+        ..Default::default()
+    };
+
+    Ok(S3Response::new(output))
+}
+
+/// Handle GetObjectAttributes: only the requested attributes are filled in.
+/// Enigma has no record of S3 multipart parts once an upload completes, so
+/// `ObjectParts` reports the object's chunks, paginated like parts.
+pub async fn handle_get_object_attributes(
+    state: &SharedState,
+    bucket: &str,
+    key: &str,
+    version_id: Option<&str>,
+    attributes: &[ObjectAttributes],
+    max_parts: i32,
+    part_number_marker: Option<&str>,
+) -> S3Result<S3Response<GetObjectAttributesOutput>> {
+    let ObjectRow {
+        id: object_id,
+        size,
+        etag,
+        chunk_count,
+        created_at,
+        ..
+    } = lookup_object(state, bucket, key, version_id).await?;
+    // Clients such as boto3 send the list as one comma-separated header,
+    // which the AWS SDKs quote
+    let wants = |attr: &str| {
+        attributes
+            .iter()
+            .flat_map(|a| a.as_str().split(','))
+            .any(|a| a.trim().trim_matches('"') == attr)
+    };
+
+    let mut output = GetObjectAttributesOutput {
+        last_modified: last_modified(&created_at),
+        version_id: version_id.map(str::to_string),
         ..Default::default()
     };
+    if wants(ObjectAttributes::ETAG) {
+        output.e_tag = Some(etag.clone());
+    }
+    if wants(ObjectAttributes::OBJECT_SIZE) {
+        output.object_size = Some(size as i64);
+    }
+    if wants(ObjectAttributes::STORAGE_CLASS) {
+        output.storage_class = Some(StorageClass::from_static(StorageClass::STANDARD));
+    }
+    if wants(ObjectAttributes::CHECKSUM) {
+        // The ETag is the hex SHA-256 of the whole object
+        use base64::Engine;
+        let digest = hex::decode(&etag).map_err(|_| s3_error!(InternalError))?;
+        output.checksum = Some(Checksum {
+            checksum_sha256: Some(base64::engine::general_purpose::STANDARD.encode(digest)),
+            ..Default::default()
+        });
+    }
+    if wants(ObjectAttributes::OBJECT_PARTS) {
+        let chunks = {
+            let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
+            db.get_object_chunks(object_id)
+                .map_err(|_| s3_error!(InternalError))?
+        };
+        let sizes = chunks.iter().enumerate().map(|(i, (_, _, offset))| {
+            let end = chunks.get(i + 1).map_or(size, |(_, _, next)| *next);
+            end - offset
+        });
+        let marker: usize = match part_number_marker {
+            Some(m) => m.parse().map_err(|_| s3_error!(InvalidArgument))?,
+            None => 0,
+        };
+        let mut parts: Vec<ObjectPart> = sizes
+            .enumerate()
+            .map(|(i, part_size)| ObjectPart {
+                part_number: Some(i as i32 + 1),
+                size: Some(part_size as i64),
+                ..Default::default()
+            })
+            .skip(marker)
+            .take(max_parts as usize + 1)
+            .collect();
+        let is_truncated = parts.len() > max_parts as usize;
+        parts.truncate(max_parts as usize);
+
+        output.object_parts = Some(GetObjectAttributesParts {
+            total_parts_count: Some(chunk_count as i32),
+            part_number_marker: Some(marker.to_string()),
+            next_part_number_marker: parts
+                .last()
+                .and_then(|p| p.part_number)
+                .map(|n| n.to_string()),
+            max_parts: Some(max_parts),
+            is_truncated: Some(is_truncated),
+            parts: Some(parts),
+        });
+    }
 
     Ok(S3Response::new(output))
 }
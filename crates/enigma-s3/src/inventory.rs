@@ -0,0 +1,318 @@
+//! S3 bucket inventory.
+//!
+//! PutBucketInventoryConfiguration keeps a bucket's configuration in the
+//! manifest's `namespace_inventory_config` table. Once a day,
+//! [`generate_report`] lists the objects of every bucket with an enabled
+//! configuration into one CSV report, stored encrypted in the
+//! [`REPORTS_BUCKET`] bucket like any other object, and rewrites
+//! [`MANIFEST_KEY`] to point at it. The destination and frequency of a
+//! configuration are only echoed back by GetBucketInventoryConfiguration.
+
+use std::collections::VecDeque;
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use bytes::Bytes;
+use chrono::{SecondsFormat, Utc};
+use s3s::dto::*;
+use s3s::s3_error;
+use s3s::{S3Error, S3ErrorCode, S3Response, S3Result};
+use serde_json::json;
+
+use enigma_core::types::{InventoryConfig, InventoryReport};
+
+use crate::SharedState;
+
+/// Bucket the inventory reports are stored in.
+pub const REPORTS_BUCKET: &str = "reports";
+
+/// Object in [`REPORTS_BUCKET`] describing the latest report.
+pub const MANIFEST_KEY: &str = "latest_inventory_manifest.json";
+
+const CSV_HEADER: &str = "bucket,key,size,etag,storage_class,is_latest,last_modified\n";
+
+/// Objects read from the manifest per CSV piece.
+const PAGE_SIZE: u32 = 1000;
+
+/// Handle PutBucketInventoryConfiguration. The configuration replaces any
+/// other with the same ID.
+pub async fn handle_put_bucket_inventory_configuration(
+    state: &SharedState,
+    bucket: &str,
+    id: &str,
+    config: InventoryConfiguration,
+) -> S3Result<S3Response<PutBucketInventoryConfigurationOutput>> {
+    if config.id != id {
+        return Err(s3_error!(
+            InvalidArgument,
+            "The inventory configuration ID does not match the id parameter"
+        ));
+    }
+    let destination = config.destination.s3_bucket_destination;
+    let record = InventoryConfig {
+        id: config.id,
+        enabled: config.is_enabled,
+        destination_bucket: destination.bucket,
+        destination_prefix: destination.prefix,
+        format: destination.format.as_str().to_string(),
+        frequency: config.schedule.frequency.as_str().to_string(),
+        included_object_versions: config.included_object_versions.as_str().to_string(),
+        filter_prefix: config.filter.map(|filter| filter.prefix),
+    };
+
+    let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
+    let ns_id = db
+        .get_namespace_id(bucket)
+        .map_err(|_| s3_error!(InternalError))?
+        .ok_or_else(|| s3_error!(NoSuchBucket))?;
+    db.put_namespace_inventory_config(ns_id, &record)
+        .map_err(|_| s3_error!(InternalError))?;
+
+    Ok(S3Response::new(
+        PutBucketInventoryConfigurationOutput::default(),
+    ))
+}
+
+/// Handle GetBucketInventoryConfiguration: NoSuchConfiguration when the
+/// bucket has no configuration with this ID.
+pub async fn handle_get_bucket_inventory_configuration(
+    state: &SharedState,
+    bucket: &str,
+    id: &str,
+) -> S3Result<S3Response<GetBucketInventoryConfigurationOutput>> {
+    let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
+    let ns_id = db
+        .get_namespace_id(bucket)
+        .map_err(|_| s3_error!(InternalError))?
+        .ok_or_else(|| s3_error!(NoSuchBucket))?;
+    let config = db
+        .get_namespace_inventory_config(ns_id, id)
+        .map_err(|_| s3_error!(InternalError))?
+        .ok_or_else(no_such_configuration)?;
+
+    Ok(S3Response::new(GetBucketInventoryConfigurationOutput {
+        inventory_configuration: Some(InventoryConfiguration {
+            destination: InventoryDestination {
+                s3_bucket_destination: InventoryS3BucketDestination {
+                    account_id: None,
+                    bucket: config.destination_bucket,
+                    encryption: None,
+                    format: InventoryFormat::from(config.format),
+                    prefix: config.destination_prefix,
+                },
+            },
+            filter: config
+                .filter_prefix
+                .map(|prefix| InventoryFilter { prefix }),
+            id: config.id,
+            included_object_versions: InventoryIncludedObjectVersions::from(
+                config.included_object_versions,
+            ),
+            is_enabled: config.enabled,
+            optional_fields: None,
+            schedule: InventorySchedule {
+                frequency: InventoryFrequency::from(config.frequency),
+            },
+        }),
+    }))
+}
+
+fn no_such_configuration() -> S3Error {
+    let mut err = S3Error::with_message(
+        S3ErrorCode::Custom("NoSuchConfiguration".into()),
+        "The specified configuration does not exist",
+    );
+    err.set_status_code(http::StatusCode::NOT_FOUND);
+    err
+}
+
+/// Write an inventory report of every bucket with an enabled
+/// configuration, then point [`MANIFEST_KEY`] at it. Returns `None`, and
+/// stores nothing, when no bucket has one.
+pub async fn generate_report(state: &SharedState) -> anyhow::Result<Option<InventoryReport>> {
+    let configs = {
+        let db = state.db.lock().map_err(|_| anyhow::anyhow!("db lock"))?;
+        db.list_enabled_inventory_configs()?
+    };
+    if configs.is_empty() {
+        return Ok(None);
+    }
+    crate::ops::ensure_namespace(state, REPORTS_BUCKET)?;
+
+    let generated_at = Utc::now();
+    let key = format!(
+        "inventory/{}/inventory.csv",
+        generated_at.format("%Y-%m-%d")
+    );
+    let mut buckets: Vec<String> = configs.iter().map(|(_, name, _)| name.clone()).collect();
+    buckets.dedup();
+    let cursor = CsvCursor {
+        state: state.clone(),
+        lists: configs
+            .into_iter()
+            .map(|(ns_id, name, config)| (ns_id, name, config.filter_prefix.unwrap_or_default()))
+            .collect(),
+        start_after: String::new(),
+        header_sent: false,
+        rows: Arc::default(),
+        size: Arc::default(),
+    };
+    let (rows, size) = (cursor.rows.clone(), cursor.size.clone());
+    crate::put::handle_put_object(
+        state,
+        REPORTS_BUCKET,
+        &key,
+        Some("text/csv".to_string()),
+        None,
+        &[],
+        Some(StreamingBlob::wrap(csv_stream(cursor))),
+    )
+    .await
+    .map_err(|e| anyhow::anyhow!("cannot store {key}: {e:?}"))?;
+
+    let report = InventoryReport {
+        key,
+        size: size.load(Ordering::Relaxed),
+        rows: rows.load(Ordering::Relaxed),
+        generated_at: generated_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+    };
+    let manifest = json!({
+        "sourceBuckets": buckets,
+        "destinationBucket": REPORTS_BUCKET,
+        "fileFormat": "CSV",
+        "fileSchema": CSV_HEADER.trim_end(),
+        "files": [{ "key": report.key, "size": report.size, "rows": report.rows }],
+        "generatedAt": report.generated_at,
+    });
+    crate::ops::store_object(
+        state,
+        REPORTS_BUCKET,
+        MANIFEST_KEY,
+        &serde_json::to_vec_pretty(&manifest)?,
+        Some("application/json"),
+    )
+    .await?;
+
+    let db = state.db.lock().map_err(|_| anyhow::anyhow!("db lock"))?;
+    db.insert_inventory_report(&report)?;
+    Ok(Some(report))
+}
+
+/// Reads the objects to report a page at a time, so the report is never
+/// held in memory.
+struct CsvCursor {
+    state: SharedState,
+    /// (namespace ID, bucket, key prefix) left to list.
+    lists: VecDeque<(i64, String, String)>,
+    start_after: String,
+    header_sent: bool,
+    rows: Arc<AtomicU64>,
+    size: Arc<AtomicU64>,
+}
+
+impl CsvCursor {
+    /// The CSV lines of the next page of objects, or `None` once every
+    /// bucket is listed.
+    fn next_piece(&mut self) -> anyhow::Result<Option<Bytes>> {
+        if !self.header_sent {
+            self.header_sent = true;
+            return Ok(Some(self.count(CSV_HEADER.to_string(), 0)));
+        }
+        while let Some((ns_id, bucket, prefix)) = self.lists.front().cloned() {
+            let page = {
+                let db = self
+                    .state
+                    .db
+                    .lock()
+                    .map_err(|_| anyhow::anyhow!("db lock"))?;
+                db.list_objects(ns_id, &prefix, PAGE_SIZE, &self.start_after)?
+            };
+            match page.last() {
+                Some((key, ..)) if page.len() == PAGE_SIZE as usize => {
+                    self.start_after.clone_from(key)
+                }
+                _ => {
+                    self.lists.pop_front();
+                    self.start_after.clear();
+                }
+            }
+            let mut csv = String::new();
+            let mut rows = 0;
+            for (key, size, etag, created_at) in &page {
+                // LIKE matches ASCII case-insensitively; S3 prefixes do not
+                if !key.starts_with(&prefix) {
+                    continue;
+                }
+                let fields: [&str; 7] = [
+                    bucket.as_str(),
+                    key,
+                    &size.to_string(),
+                    etag,
+                    "STANDARD",
+                    "true",
+                    &last_modified(created_at),
+                ];
+                csv.push_str(&fields.map(csv_field).join(","));
+                csv.push('\n');
+                rows += 1;
+            }
+            if rows > 0 {
+                return Ok(Some(self.count(csv, rows)));
+            }
+        }
+        Ok(None)
+    }
+
+    fn count(&self, csv: String, rows: u64) -> Bytes {
+        self.rows.fetch_add(rows, Ordering::Relaxed);
+        self.size.fetch_add(csv.len() as u64, Ordering::Relaxed);
+        Bytes::from(csv)
+    }
+}
+
+fn csv_stream(
+    cursor: CsvCursor,
+) -> impl futures::Stream<Item = io::Result<Bytes>> + Send + 'static {
+    futures::stream::try_unfold(cursor, |mut cursor| async move {
+        match cursor.next_piece() {
+            Ok(piece) => Ok(piece.map(|piece| (piece, cursor))),
+            Err(e) => {
+                tracing::error!("Listing objects for the inventory report failed: {e}");
+                Err(io::Error::other(e.to_string()))
+            }
+        }
+    })
+}
+
+/// A quoted CSV field, with its quotes doubled.
+fn csv_field(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\"\""))
+}
+
+/// `created_at` as an ISO 8601 timestamp.
+fn last_modified(created_at: &str) -> String {
+    chrono::NaiveDateTime::parse_from_str(created_at, "%Y-%m-%d %H:%M:%S")
+        .map(|t| t.and_utc().to_rfc3339_opts(SecondsFormat::Millis, true))
+        .unwrap_or_else(|_| created_at.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_fields_are_quoted() {
+        assert_eq!(csv_field("photos/a.jpg"), "\"photos/a.jpg\"");
+        assert_eq!(csv_field("say \"hi\", twice"), "\"say \"\"hi\"\", twice\"");
+    }
+
+    #[test]
+    fn last_modified_is_iso_8601() {
+        assert_eq!(
+            last_modified("2026-10-16 08:30:00"),
+            "2026-10-16T08:30:00.000Z"
+        );
+        assert_eq!(last_modified("unknown"), "unknown");
+    }
+}
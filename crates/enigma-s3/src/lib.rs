@@ -1,26 +1,91 @@
+pub mod access_log;
 pub mod auth;
+pub mod bandwidth;
 pub mod get;
+#[cfg(feature = "inventory")]
+pub mod inventory;
 pub mod list;
+pub mod metrics;
 pub mod multipart;
+pub mod object_lock;
+pub mod ops;
+pub mod providers;
 pub mod put;
 pub mod service;
+pub mod tagging;
+pub mod usage;
+pub mod versioning;
 
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, OnceLock};
 
 use enigma_core::config::EnigmaConfig;
-use enigma_core::distributor::Distributor;
+use enigma_core::events::BackupEvents;
 use enigma_core::manifest::ManifestDb;
 use enigma_core::types::KeyMaterial;
-use enigma_storage::provider::StorageProvider;
+use enigma_core::webhook::{self, WebhookPayload};
+use enigma_raft::EnigmaRaft;
+use s3s::{S3Result, s3_error};
 
 /// Shared state for the Enigma S3 service.
 pub struct EnigmaS3State {
     pub db: Arc<Mutex<ManifestDb>>,
-    pub providers: HashMap<i64, Box<dyn StorageProvider>>,
-    pub distributor: Distributor,
+    /// Storage providers; the proxy adds new ones on config reload.
+    pub providers: providers::ProviderMap,
+    /// Picks providers for new chunks; replaced on config reload.
+    pub distributor: providers::SharedDistributor,
     pub key_material: KeyMaterial,
     pub config: EnigmaConfig,
+    /// Set in multi-node Raft mode; GET/HEAD metadata lookups then go
+    /// through a Raft read-index instead of the local DB.
+    pub raft: OnceLock<Arc<EnigmaRaft>>,
+    /// Progress events for objects stored through [`ops::store_object`].
+    pub events: BackupEvents,
+    /// Per-user storage accounting and quotas; unset means no accounting.
+    pub usage: OnceLock<Arc<dyn usage::UsageAccounting>>,
+    /// Permissions of S3 callers; unset means no caller holds any (such as
+    /// the object lock governance bypass).
+    pub access_control: OnceLock<Arc<dyn auth::AccessControl>>,
+    /// Part size and count limits enforced on multipart uploads.
+    pub multipart_limits: multipart::MultipartLimits,
+    /// Recently downloaded chunks; disabled unless `chunk_cache_enabled`.
+    pub chunk_cache: ops::ChunkCache,
+    /// Chunk uploads allowed at once across requests (`backup_workers`).
+    pub upload_semaphore: ops::UploadSemaphore,
+    /// Upload and download rate limits of providers with
+    /// `upload_bandwidth_bps` or `download_bandwidth_bps` set. Built at
+    /// startup: providers added on config reload are not limited.
+    pub provider_limiters: bandwidth::ProviderLimiters,
 }
 
 pub type SharedState = Arc<EnigmaS3State>;
+
+impl EnigmaS3State {
+    /// Queue webhook `event` with `data` for the configured webhooks
+    /// subscribed to it. Queueing failures are only logged: the change the
+    /// event reports has already been made.
+    pub fn queue_webhook(&self, db: &ManifestDb, event: &str, data: serde_json::Value) {
+        if self.config.webhooks.is_empty() {
+            return;
+        }
+        let payload = WebhookPayload::new(event, data);
+        if let Err(e) = webhook::enqueue(db, &self.config.webhooks, &payload) {
+            tracing::warn!("Failed to queue {event} webhook: {e}");
+        }
+    }
+
+    /// Refuse a write with 503 on a Raft learner: it replicates the manifest
+    /// to serve reads but takes no part in committing changes.
+    pub fn check_writable(&self) -> S3Result<()> {
+        if self
+            .raft
+            .get()
+            .is_some_and(|raft| raft.metrics().borrow().state.is_learner())
+        {
+            return Err(s3_error!(
+                ServiceUnavailable,
+                "This node is a read-only Raft learner; send writes to a voting node"
+            ));
+        }
+        Ok(())
+    }
+}
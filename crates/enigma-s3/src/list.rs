@@ -1,11 +1,137 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use enigma_core::manifest::ManifestDb;
 use s3s::dto::*;
 use s3s::s3_error;
 use s3s::{S3Response, S3Result};
-use std::collections::BTreeSet;
 
 use crate::SharedState;
 
-/// Handle ListObjectsV2 with prefix and delimiter support.
+/// Most entries S3 returns in one listing page.
+const MAX_KEYS: u32 = 1000;
+
+/// Manifest rows read per query while a page is filled.
+const BATCH_SIZE: u32 = 1000;
+
+/// One page of a listing. Keys and common prefixes share the `max_keys`
+/// budget and are returned in key order, as S3 does.
+#[derive(Default)]
+struct ListPage {
+    contents: Vec<Object>,
+    common_prefixes: Vec<CommonPrefix>,
+    /// Last key or common prefix returned, when more entries follow.
+    next_marker: Option<String>,
+}
+
+/// The common prefix `key` is rolled up into: `prefix` plus everything up
+/// to and including the first `delimiter` after it.
+fn common_prefix(prefix: &str, delimiter: &str, key: &str) -> Option<String> {
+    if delimiter.is_empty() {
+        return None;
+    }
+    let rest = key.strip_prefix(prefix)?;
+    let end = rest.find(delimiter)? + delimiter.len();
+    Some(format!("{prefix}{}", &rest[..end]))
+}
+
+/// A `start_after` bound that skips every key under common prefix `p`.
+/// U+10FFFF is the largest code point, so it sorts after any key that
+/// merely extends `p`.
+fn past_common_prefix(p: &str) -> String {
+    format!("{p}\u{10FFFF}")
+}
+
+fn object_entry(key: String, size: u64, etag: &str) -> Object {
+    Object {
+        key: Some(key),
+        size: Some(size as i64),
+        e_tag: Some(format!("\"{etag}\"")),
+        last_modified: None,
+        storage_class: Some(ObjectStorageClass::from_static(
+            ObjectStorageClass::STANDARD,
+        )),
+        owner: None,
+        checksum_algorithm: None,
+        checksum_type: None,
+        restore_status: None,
+    }
+}
+
+/// Entries of `ns_id` under `prefix` after `marker`, keys containing
+/// `delimiter` past the prefix rolled up into common prefixes.
+fn list_page(
+    db: &ManifestDb,
+    ns_id: i64,
+    prefix: &str,
+    delimiter: &str,
+    marker: &str,
+    max_keys: u32,
+) -> S3Result<ListPage> {
+    let mut page = ListPage::default();
+    if max_keys == 0 {
+        return Ok(page);
+    }
+
+    // A marker that is itself a common prefix resumes after everything
+    // rolled up into it
+    let mut cursor = match common_prefix(prefix, delimiter, marker) {
+        Some(p) if p == marker => past_common_prefix(marker),
+        _ => marker.to_string(),
+    };
+    let mut returned = 0u32;
+    let mut last = String::new();
+
+    'fetch: loop {
+        let batch = db
+            .list_objects(ns_id, prefix, BATCH_SIZE, &cursor)
+            .map_err(|_| s3_error!(InternalError))?;
+        let exhausted = batch.len() < BATCH_SIZE as usize;
+
+        for (key, size, etag, _created_at) in batch {
+            // LIKE matches ASCII case-insensitively; S3 prefixes do not
+            if !key.starts_with(prefix) {
+                cursor = key;
+                continue;
+            }
+            if returned == max_keys {
+                page.next_marker = Some(last);
+                return Ok(page);
+            }
+            returned += 1;
+            match common_prefix(prefix, delimiter, &key) {
+                Some(p) => {
+                    // Skip the rest of this "directory" in the manifest
+                    cursor = past_common_prefix(&p);
+                    last = p.clone();
+                    page.common_prefixes.push(CommonPrefix { prefix: Some(p) });
+                    continue 'fetch;
+                }
+                None => {
+                    cursor.clone_from(&key);
+                    last.clone_from(&key);
+                    page.contents.push(object_entry(key, size, &etag));
+                }
+            }
+        }
+
+        if exhausted {
+            return Ok(page);
+        }
+    }
+}
+
+fn lookup_bucket(db: &ManifestDb, bucket: &str) -> S3Result<i64> {
+    db.get_namespace_id(bucket)
+        .map_err(|_| s3_error!(InternalError))?
+        .ok_or_else(|| s3_error!(NoSuchBucket))
+}
+
+fn non_empty(s: &str) -> Option<String> {
+    (!s.is_empty()).then(|| s.to_string())
+}
+
+/// Handle ListObjectsV2 with prefix and delimiter support. The
+/// continuation token encodes the last key or common prefix returned.
 pub async fn handle_list_objects_v2(
     state: &SharedState,
     bucket: &str,
@@ -16,95 +142,72 @@ pub async fn handle_list_objects_v2(
     continuation_token: &str,
 ) -> S3Result<S3Response<ListObjectsV2Output>> {
     let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
+    let ns_id = lookup_bucket(&db, bucket)?;
 
-    let ns_id = db
-        .get_namespace_id(bucket)
-        .map_err(|_| s3_error!(InternalError))?
-        .ok_or_else(|| s3_error!(NoSuchBucket))?;
-
-    // Use continuation_token as start_after if provided
-    let effective_start_after = if !continuation_token.is_empty() {
-        continuation_token
+    // The continuation token takes precedence over start_after
+    let marker = if continuation_token.is_empty() {
+        start_after.to_string()
     } else {
-        start_after
+        URL_SAFE_NO_PAD
+            .decode(continuation_token)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .ok_or_else(|| {
+                s3_error!(
+                    InvalidArgument,
+                    "The continuation token provided is incorrect"
+                )
+            })?
     };
 
-    // Fetch more than max_keys to handle delimiter grouping
-    let objects = db
-        .list_objects(ns_id, prefix, max_keys + 100, effective_start_after)
-        .map_err(|_| s3_error!(InternalError))?;
-
-    let mut contents = Vec::new();
-    let mut common_prefixes: BTreeSet<String> = BTreeSet::new();
-    let mut count = 0u32;
-    let mut is_truncated = false;
-    let mut last_key = String::new();
-
-    for (key, size, etag, _created_at) in &objects {
-        if count >= max_keys {
-            is_truncated = true;
-            break;
-        }
+    let max_keys = max_keys.min(MAX_KEYS);
+    let page = list_page(&db, ns_id, prefix, delimiter, &marker, max_keys)?;
+    let key_count = page.contents.len() + page.common_prefixes.len();
 
-        // Handle delimiter-based grouping (virtual directories)
-        if !delimiter.is_empty() {
-            let after_prefix = key.strip_prefix(prefix).unwrap_or(key);
-            if let Some(pos) = after_prefix.find(delimiter) {
-                let common_prefix = format!("{}{}{}", prefix, &after_prefix[..pos], delimiter);
-                common_prefixes.insert(common_prefix);
-                continue;
-            }
-        }
+    let output = ListObjectsV2Output {
+        name: Some(bucket.to_string()),
+        prefix: non_empty(prefix),
+        delimiter: non_empty(delimiter),
+        start_after: non_empty(start_after),
+        continuation_token: non_empty(continuation_token),
+        max_keys: Some(max_keys as i32),
+        key_count: Some(key_count as i32),
+        contents: (!page.contents.is_empty()).then_some(page.contents),
+        common_prefixes: (!page.common_prefixes.is_empty()).then_some(page.common_prefixes),
+        is_truncated: Some(page.next_marker.is_some()),
+        next_continuation_token: page.next_marker.map(|m| URL_SAFE_NO_PAD.encode(m)),
+        ..Default::default()
+    };
 
-        contents.push(Object {
-            key: Some(key.clone()),
-            size: Some(*size as i64),
-            e_tag: Some(format!("\"{etag}\"")),
-            last_modified: None,
-            storage_class: Some(ObjectStorageClass::from_static(
-                ObjectStorageClass::STANDARD,
-            )),
-            owner: None,
-            checksum_algorithm: None,
-            checksum_type: None,
-            restore_status: None,
-        });
-
-        last_key = key.clone();
-        count += 1;
-    }
+    Ok(S3Response::new(output))
+}
+
+/// Handle ListObjects (v1): like ListObjectsV2, but paginated by `marker`,
+/// the last key or common prefix of the previous page.
+pub async fn handle_list_objects(
+    state: &SharedState,
+    bucket: &str,
+    prefix: &str,
+    delimiter: &str,
+    max_keys: u32,
+    marker: &str,
+) -> S3Result<S3Response<ListObjectsOutput>> {
+    let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
+    let ns_id = lookup_bucket(&db, bucket)?;
 
-    let common_prefix_list: Vec<CommonPrefix> = common_prefixes
-        .into_iter()
-        .map(|p| CommonPrefix { prefix: Some(p) })
-        .collect();
+    let max_keys = max_keys.min(MAX_KEYS);
+    let page = list_page(&db, ns_id, prefix, delimiter, marker, max_keys)?;
 
-    let output = ListObjectsV2Output {
+    let output = ListObjectsOutput {
         name: Some(bucket.to_string()),
-        prefix: if prefix.is_empty() {
-            None
-        } else {
-            Some(prefix.to_string())
-        },
-        delimiter: if delimiter.is_empty() {
-            None
-        } else {
-            Some(delimiter.to_string())
-        },
+        prefix: non_empty(prefix),
+        delimiter: non_empty(delimiter),
+        marker: non_empty(marker),
         max_keys: Some(max_keys as i32),
-        key_count: Some(contents.len() as i32),
-        contents: if contents.is_empty() {
-            None
-        } else {
-            Some(contents)
-        },
-        common_prefixes: if common_prefix_list.is_empty() {
-            None
-        } else {
-            Some(common_prefix_list)
-        },
-        is_truncated: Some(is_truncated),
-        next_continuation_token: if is_truncated { Some(last_key) } else { None },
+        contents: (!page.contents.is_empty()).then_some(page.contents),
+        common_prefixes: (!page.common_prefixes.is_empty()).then_some(page.common_prefixes),
+        is_truncated: Some(page.next_marker.is_some()),
+        next_marker: page.next_marker,
         ..Default::default()
     };
 
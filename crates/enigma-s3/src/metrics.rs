@@ -0,0 +1,164 @@
+//! Prometheus counters for the S3 data path.
+//!
+//! Registered in the global `prometheus` registry so the proxy's `/metrics`
+//! endpoint picks them up. Without the `metrics` feature every recorder is
+//! a no-op.
+
+use std::time::{Duration, Instant};
+
+use enigma_storage::provider::StorageProvider;
+
+#[cfg(feature = "metrics")]
+mod imp {
+    use prometheus::{
+        HistogramOpts, HistogramVec, IntCounter, IntCounterVec, Opts, register_histogram_vec,
+        register_int_counter, register_int_counter_vec,
+    };
+    use std::sync::LazyLock;
+
+    pub static CHUNKS_UPLOADED: LazyLock<IntCounter> = LazyLock::new(|| {
+        register_int_counter!(
+            "enigma_chunks_uploaded_total",
+            "Chunks uploaded to storage providers (replicas included)"
+        )
+        .unwrap()
+    });
+
+    pub static CHUNKS_DEDUPED: LazyLock<IntCounter> = LazyLock::new(|| {
+        register_int_counter!(
+            "enigma_chunks_deduped_total",
+            "Chunks skipped because they were already stored"
+        )
+        .unwrap()
+    });
+
+    pub static BYTES_UPLOADED: LazyLock<IntCounter> = LazyLock::new(|| {
+        register_int_counter!(
+            "enigma_bytes_uploaded_total",
+            "Encrypted bytes uploaded to storage providers"
+        )
+        .unwrap()
+    });
+
+    pub static BYTES_DOWNLOADED: LazyLock<IntCounter> = LazyLock::new(|| {
+        register_int_counter!(
+            "enigma_bytes_downloaded_total",
+            "Encrypted bytes downloaded from storage providers"
+        )
+        .unwrap()
+    });
+
+    pub static PROVIDER_ERRORS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+        register_int_counter_vec!(
+            Opts::new(
+                "enigma_provider_errors_total",
+                "Failed storage provider operations"
+            ),
+            &["provider"]
+        )
+        .unwrap()
+    });
+
+    pub static UPLOAD_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+        register_histogram_vec!(
+            HistogramOpts::new(
+                "enigma_chunk_upload_duration_seconds",
+                "Time to upload one chunk to a provider"
+            ),
+            &["provider"]
+        )
+        .unwrap()
+    });
+
+    pub static GC_ORPHANS: LazyLock<IntCounter> = LazyLock::new(|| {
+        register_int_counter!(
+            "enigma_gc_orphans_total",
+            "Orphaned chunk copies deleted from storage providers"
+        )
+        .unwrap()
+    });
+}
+
+/// A chunk copy of `bytes` was uploaded to `provider` in `elapsed`.
+#[cfg(feature = "metrics")]
+fn chunk_uploaded(provider: &str, bytes: usize, elapsed: Duration) {
+    imp::CHUNKS_UPLOADED.inc();
+    imp::BYTES_UPLOADED.inc_by(bytes as u64);
+    imp::UPLOAD_DURATION
+        .with_label_values(&[provider])
+        .observe(elapsed.as_secs_f64());
+}
+
+/// A chunk was already stored and was not uploaded again.
+#[cfg(feature = "metrics")]
+pub(crate) fn chunk_deduped() {
+    imp::CHUNKS_DEDUPED.inc();
+}
+
+/// `bytes` were downloaded from a provider.
+#[cfg(feature = "metrics")]
+pub(crate) fn bytes_downloaded(bytes: usize) {
+    imp::BYTES_DOWNLOADED.inc_by(bytes as u64);
+}
+
+/// An upload, download or delete on `provider` failed.
+#[cfg(feature = "metrics")]
+pub(crate) fn provider_error(provider: &str) {
+    imp::PROVIDER_ERRORS.with_label_values(&[provider]).inc();
+}
+
+/// `count` orphaned chunk copies were deleted.
+#[cfg(feature = "metrics")]
+pub(crate) fn gc_orphans(count: usize) {
+    imp::GC_ORPHANS.inc_by(count as u64);
+}
+
+/// Create every metric up front so they show up (at zero) before the
+/// first request.
+#[cfg(feature = "metrics")]
+pub fn register() {
+    use std::sync::LazyLock;
+    LazyLock::force(&imp::CHUNKS_UPLOADED);
+    LazyLock::force(&imp::CHUNKS_DEDUPED);
+    LazyLock::force(&imp::BYTES_UPLOADED);
+    LazyLock::force(&imp::BYTES_DOWNLOADED);
+    LazyLock::force(&imp::PROVIDER_ERRORS);
+    LazyLock::force(&imp::UPLOAD_DURATION);
+    LazyLock::force(&imp::GC_ORPHANS);
+}
+
+/// Upload a chunk copy to `provider`, recording its size and duration, or
+/// a provider error on failure.
+pub(crate) async fn upload_chunk(
+    provider: &dyn StorageProvider,
+    key: &str,
+    data: &[u8],
+) -> anyhow::Result<()> {
+    let started = Instant::now();
+    let result = provider.upload_chunk(key, data).await;
+    match &result {
+        Ok(()) => chunk_uploaded(provider.name(), data.len(), started.elapsed()),
+        Err(_) => provider_error(provider.name()),
+    }
+    result
+}
+
+// ── No-op recorders (metrics feature disabled) ──────────────
+
+#[cfg(not(feature = "metrics"))]
+fn chunk_uploaded(_provider: &str, _bytes: usize, _elapsed: Duration) {}
+
+#[cfg(not(feature = "metrics"))]
+pub(crate) fn chunk_deduped() {}
+
+#[cfg(not(feature = "metrics"))]
+pub(crate) fn bytes_downloaded(_bytes: usize) {}
+
+#[cfg(not(feature = "metrics"))]
+pub(crate) fn provider_error(_provider: &str) {}
+
+#[cfg(not(feature = "metrics"))]
+pub(crate) fn gc_orphans(_count: usize) {}
+
+#[cfg(not(feature = "metrics"))]
+pub fn register() {}
@@ -1,11 +1,41 @@
+use std::io::Write;
+use std::path::PathBuf;
+
+use futures::StreamExt;
 use md5::{Digest as Md5Digest, Md5};
 use s3s::dto::*;
 use s3s::s3_error;
 use s3s::{S3Response, S3Result};
+use serde_json::json;
 use sha2::Sha256;
 
+use enigma_core::manifest::MultipartPartsCursor;
+
 use crate::SharedState;
-use crate::put::read_body;
+use crate::get::last_modified;
+use crate::put::{StreamChunker, store_chunk};
+
+/// Part size and count limits for multipart uploads. The defaults are the
+/// AWS S3 limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MultipartLimits {
+    /// Minimum size of every part except the last, checked on completion.
+    pub min_part_size_bytes: u64,
+    /// Maximum number of parts in a completed upload.
+    pub max_parts: u32,
+    /// Maximum size of a single part, checked on upload.
+    pub max_part_size_bytes: u64,
+}
+
+impl Default for MultipartLimits {
+    fn default() -> Self {
+        Self {
+            min_part_size_bytes: 5 * 1024 * 1024,
+            max_parts: 10_000,
+            max_part_size_bytes: 5 * 1024 * 1024 * 1024,
+        }
+    }
+}
 
 /// Handle CreateMultipartUpload: create a pending upload entry.
 pub async fn handle_create_multipart_upload(
@@ -34,7 +64,7 @@ pub async fn handle_create_multipart_upload(
     Ok(S3Response::new(output))
 }
 
-/// Handle UploadPart: buffer part data in the database.
+/// Handle UploadPart: stream the part data to a spill file and record it.
 pub async fn handle_upload_part(
     state: &SharedState,
     upload_id: &str,
@@ -45,23 +75,24 @@ pub async fn handle_upload_part(
         return Err(s3_error!(InvalidArgument));
     }
 
-    let data = read_body(body).await?;
+    // Verify upload exists
+    {
+        let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
+        db.get_multipart_upload(upload_id)
+            .map_err(|_| s3_error!(InternalError))?
+            .ok_or_else(|| s3_error!(NoSuchUpload))?;
+    }
 
-    // Compute MD5 for ETag (S3 convention for parts)
-    let etag = {
-        let hash = Md5::digest(&data);
-        format!("{:x}", hash)
-    };
+    let (spill_path, size, etag) = spill_part(state, body).await?;
 
     let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
-
-    // Verify upload exists
-    db.get_multipart_upload(upload_id)
-        .map_err(|_| s3_error!(InternalError))?
-        .ok_or_else(|| s3_error!(NoSuchUpload))?;
-
-    db.insert_multipart_part(upload_id, part_number, &data, &etag)
-        .map_err(|_| s3_error!(InternalError))?;
+    if db
+        .insert_multipart_part_file(upload_id, part_number, &spill_path, size, &etag)
+        .is_err()
+    {
+        let _ = std::fs::remove_file(&spill_path);
+        return Err(s3_error!(InternalError));
+    }
 
     let output = UploadPartOutput {
         e_tag: Some(format!("\"{etag}\"")),
@@ -71,15 +102,63 @@ pub async fn handle_upload_part(
     Ok(S3Response::new(output))
 }
 
-/// Handle CompleteMultipartUpload: assemble parts, chunk, encrypt, upload.
+/// Write a part body to a new file in the spill directory as it arrives.
+/// Returns the file path, the part size and its MD5 ETag (S3 convention
+/// for parts).
+async fn spill_part(
+    state: &SharedState,
+    body: Option<StreamingBlob>,
+) -> S3Result<(String, u64, String)> {
+    let dir = match &state.config.enigma.multipart_part_spill_dir {
+        Some(dir) => PathBuf::from(dir),
+        None => std::env::temp_dir(),
+    };
+    std::fs::create_dir_all(&dir).map_err(|_| s3_error!(InternalError))?;
+    // Removed on drop unless kept below
+    let mut file = tempfile::Builder::new()
+        .prefix("enigma-part-")
+        .tempfile_in(&dir)
+        .map_err(|_| s3_error!(InternalError))?;
+
+    let mut hasher = Md5::new();
+    let mut size = 0u64;
+    if let Some(mut body) = body {
+        while let Some(chunk) = body.next().await {
+            let chunk = chunk.map_err(|_| s3_error!(InternalError))?;
+            size += chunk.len() as u64;
+            if size > state.multipart_limits.max_part_size_bytes {
+                return Err(s3_error!(EntityTooLarge));
+            }
+            hasher.update(&chunk);
+            file.write_all(&chunk)
+                .map_err(|_| s3_error!(InternalError))?;
+        }
+    }
+    file.flush().map_err(|_| s3_error!(InternalError))?;
+
+    let path = file
+        .into_temp_path()
+        .keep()
+        .map_err(|_| s3_error!(InternalError))?;
+    Ok((
+        path.to_string_lossy().into_owned(),
+        size,
+        format!("{:x}", hasher.finalize()),
+    ))
+}
+
+/// Handle CompleteMultipartUpload: stream the parts, in order, through the
+/// chunker, encrypting and uploading each chunk as it is cut. Only one part
+/// and one chunk's worth of data are held in memory at a time. Each stored
+/// chunk is tracked against the upload until the object is written, so an
+/// interrupted completion does not leak it.
 pub async fn handle_complete_multipart_upload(
     state: &SharedState,
     bucket: &str,
     key: &str,
     upload_id: &str,
 ) -> S3Result<S3Response<CompleteMultipartUploadOutput>> {
-    // Get all parts and assemble
-    let (ns_id, assembled_data) = {
+    let (ns_id, mut parts) = {
         let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
         let ns_id = db
             .get_namespace_id(bucket)
@@ -87,85 +166,56 @@ pub async fn handle_complete_multipart_upload(
             .ok_or_else(|| s3_error!(NoSuchBucket))?;
 
         let parts = db
-            .get_multipart_parts(upload_id)
+            .get_multipart_parts_cursor(upload_id)
             .map_err(|_| s3_error!(InternalError))?;
 
         if parts.is_empty() {
             return Err(s3_error!(InvalidPart));
         }
+        check_part_sizes(&state.multipart_limits, &parts)?;
 
-        let mut assembled = Vec::new();
-        for (_part_number, data, _size, _etag) in parts {
-            assembled.extend_from_slice(&data);
-        }
-
-        (ns_id, assembled)
+        (ns_id, parts)
     };
 
-    // Now process like a PutObject: chunk, encrypt, dedup, upload
-    let total_size = assembled_data.len() as u64;
-    let etag = {
-        let mut hasher = Sha256::new();
-        hasher.update(&assembled_data);
-        format!("{:x}", hasher.finalize())
+    // Release what an earlier, interrupted completion stored
+    let to_delete = {
+        let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
+        db.release_multipart_part_chunks(upload_id)
+            .map_err(|_| s3_error!(InternalError))?
     };
+    delete_released_chunks(state, to_delete).await;
 
-    // Chunk the assembled data
-    let raw_chunks = crate::put::chunk_data_owned(&assembled_data);
-
-    let mut chunk_records = Vec::new();
-
-    let compression = &state.config.enigma.compression;
-
-    for (idx, chunk_data) in raw_chunks.iter().enumerate() {
-        let chunk_hash = enigma_core::dedup::compute_hash(chunk_data);
-        let hash_hex = chunk_hash.to_hex();
-        let storage_key = chunk_hash.storage_key();
-
-        // Compress (optional, before encryption)
-        let (data_to_encrypt, size_compressed) = if compression.enabled {
-            let compressed =
-                enigma_core::compression::compress_chunk(chunk_data, compression.level)
-                    .map_err(|_| s3_error!(InternalError))?;
-            let sz = compressed.len() as u64;
-            (compressed, Some(sz))
-        } else {
-            (chunk_data.clone(), None)
-        };
-
-        let encrypted =
-            enigma_core::crypto::encrypt_chunk(&data_to_encrypt, &chunk_hash, &state.key_material)
-                .map_err(|_| s3_error!(InternalError))?;
-
-        let target_provider = state.distributor.next_provider();
-
-        let is_new = {
+    // Now process like a PutObject: chunk, encrypt, dedup, upload
+    let mut hasher = Sha256::new();
+    let mut total_size = 0u64;
+    let mut chunker = StreamChunker::default();
+    let mut chunk_count = 0u32;
+    let mut last_part_number = 0;
+
+    loop {
+        let part = {
             let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
-            db.insert_or_dedup_chunk(
-                &hash_hex,
-                &encrypted.nonce,
-                &state.key_material.id,
-                target_provider.id,
-                &storage_key,
-                chunk_data.len() as u64,
-                encrypted.ciphertext.len() as u64,
-                size_compressed,
-            )
-            .map_err(|_| s3_error!(InternalError))?
+            parts.next_part(&db).map_err(|_| s3_error!(InternalError))?
         };
-
-        if is_new && let Some(provider) = state.providers.get(&target_provider.id) {
-            provider
-                .upload_chunk(&storage_key, &encrypted.ciphertext)
-                .await
-                .map_err(|_| s3_error!(InternalError))?;
+        let Some(part) = part else {
+            break;
+        };
+        hasher.update(&part.data);
+        total_size += part.data.len() as u64;
+        last_part_number = part.part_number;
+        for chunk_data in chunker.push(&part.data) {
+            store_part_chunk(state, upload_id, part.part_number, &chunk_data).await?;
+            chunk_count += 1;
         }
-
-        chunk_records.push((hash_hex, idx as u32, chunk_data.len() as u64));
     }
+    if let Some(chunk_data) = chunker.finish() {
+        store_part_chunk(state, upload_id, last_part_number, &chunk_data).await?;
+        chunk_count += 1;
+    }
+    let etag = format!("{:x}", hasher.finalize());
 
     // Insert object + cleanup multipart
-    {
+    let version_id = {
         let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
 
         let object_id = db
@@ -175,30 +225,189 @@ pub async fn handle_complete_multipart_upload(
                 total_size,
                 &etag,
                 None,
-                chunk_records.len() as u32,
+                chunk_count,
                 &state.key_material.id,
             )
             .map_err(|_| s3_error!(InternalError))?;
 
-        let mut offset = 0u64;
-        for (hash_hex, chunk_index, size) in &chunk_records {
-            db.insert_object_chunk(object_id, hash_hex, *chunk_index, offset)
-                .map_err(|_| s3_error!(InternalError))?;
-            offset += size;
-        }
+        // Hand the chunks over to the object and clean up the upload
+        // (removes the spill files)
+        db.complete_multipart_upload(upload_id, object_id)
+            .map_err(|_| s3_error!(InternalError))?;
 
-        // Cleanup multipart
-        db.abort_multipart_upload(upload_id)
+        let version_id = db
+            .get_object_version_id(object_id)
             .map_err(|_| s3_error!(InternalError))?;
-    }
+        state.queue_webhook(
+            &db,
+            "object.created",
+            json!({ "bucket": bucket, "key": key, "version_id": version_id }),
+        );
+        version_id
+    };
 
     let output = CompleteMultipartUploadOutput {
         bucket: Some(bucket.to_string()),
         key: Some(key.to_string()),
         e_tag: Some(format!("\"{etag}\"")),
         location: Some(format!("/{bucket}/{key}")),
+        version_id: (version_id != "null").then_some(version_id),
         ..Default::default()
     };
 
     Ok(S3Response::new(output))
 }
+
+/// Store one chunk cut from `part_number` and track it against the upload.
+async fn store_part_chunk(
+    state: &SharedState,
+    upload_id: &str,
+    part_number: i32,
+    chunk: &[u8],
+) -> S3Result<()> {
+    let hash_hex = store_chunk(state, chunk).await?;
+    let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
+    db.insert_multipart_part_chunk(upload_id, part_number, &hash_hex)
+        .map_err(|_| s3_error!(InternalError))
+}
+
+/// Delete chunks released by an aborted or restarted upload from their
+/// providers.
+async fn delete_released_chunks(state: &SharedState, to_delete: Vec<(i64, String)>) {
+    for (provider_id, storage_key) in to_delete {
+        if let Some(provider) = state.providers.get(&provider_id)
+            && let Err(e) = provider.delete_chunk(&storage_key).await
+        {
+            tracing::warn!("Failed to delete chunk {storage_key} from provider {provider_id}: {e}");
+        }
+    }
+}
+
+/// Total size of the parts uploaded so far.
+pub(crate) fn uploaded_size(state: &SharedState, upload_id: &str) -> S3Result<u64> {
+    let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
+    let parts = db
+        .get_multipart_parts_cursor(upload_id)
+        .map_err(|_| s3_error!(InternalError))?;
+    Ok(parts.remaining_size())
+}
+
+/// Drop an upload with its parts and delete the chunks already stored for
+/// it that nothing else references.
+pub async fn abort_upload(state: &SharedState, upload_id: &str) -> anyhow::Result<()> {
+    let to_delete = {
+        let db = state.db.lock().map_err(|_| anyhow::anyhow!("db lock"))?;
+        db.abort_multipart_upload(upload_id)?
+    };
+    delete_released_chunks(state, to_delete).await;
+    Ok(())
+}
+
+/// Reject an upload with more parts than allowed, or with a part other than
+/// the last one below the minimum size. Part sizes are only known to be
+/// final once the upload is completed.
+fn check_part_sizes(limits: &MultipartLimits, parts: &MultipartPartsCursor) -> S3Result<()> {
+    if parts.len() > limits.max_parts as usize {
+        return Err(s3_error!(
+            InvalidRequest,
+            "upload has {} parts, at most {} are allowed",
+            parts.len(),
+            limits.max_parts
+        ));
+    }
+    let sizes: Vec<(i32, u64)> = parts.sizes().collect();
+    if let Some((_, non_last)) = sizes.split_last()
+        && let Some((part_number, size)) = non_last
+            .iter()
+            .find(|(_, size)| *size < limits.min_part_size_bytes)
+    {
+        return Err(s3_error!(
+            EntityTooSmall,
+            "part {part_number} is {size} bytes, the minimum is {}",
+            limits.min_part_size_bytes
+        ));
+    }
+    Ok(())
+}
+
+/// Handle ListMultipartUploads: pending uploads ordered by key, then upload ID.
+pub async fn handle_list_multipart_uploads(
+    state: &SharedState,
+    bucket: &str,
+    prefix: &str,
+    key_marker: &str,
+    upload_id_marker: &str,
+    max_uploads: u32,
+) -> S3Result<S3Response<ListMultipartUploadsOutput>> {
+    let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
+    let ns_id = db
+        .get_namespace_id(bucket)
+        .map_err(|_| s3_error!(InternalError))?
+        .ok_or_else(|| s3_error!(NoSuchBucket))?;
+
+    let mut pending = db
+        .list_multipart_uploads(ns_id)
+        .map_err(|_| s3_error!(InternalError))?;
+    drop(db);
+    pending.sort_by(|a, b| (&a.1, &a.0).cmp(&(&b.1, &b.0)));
+
+    // Without an upload ID marker, the key marker skips that key entirely
+    let after_marker = |key: &str, upload_id: &str| {
+        key_marker.is_empty()
+            || key > key_marker
+            || (key == key_marker && !upload_id_marker.is_empty() && upload_id > upload_id_marker)
+    };
+    let mut matching = pending
+        .into_iter()
+        .filter(|(id, key, _)| key.starts_with(prefix) && after_marker(key, id));
+
+    let mut uploads = Vec::new();
+    for (upload_id, key, created_at) in matching.by_ref().take(max_uploads as usize) {
+        uploads.push(MultipartUpload {
+            key: Some(key),
+            upload_id: Some(upload_id),
+            initiated: last_modified(&created_at),
+            owner: Some(Owner {
+                display_name: Some("enigma".to_string()),
+                id: Some("enigma".to_string()),
+            }),
+            storage_class: Some(StorageClass::from_static(StorageClass::STANDARD)),
+            ..Default::default()
+        });
+    }
+    let is_truncated = matching.next().is_some();
+    let last = uploads.last().filter(|_| is_truncated);
+
+    let output = ListMultipartUploadsOutput {
+        bucket: Some(bucket.to_string()),
+        prefix: (!prefix.is_empty()).then(|| prefix.to_string()),
+        key_marker: Some(key_marker.to_string()),
+        upload_id_marker: Some(upload_id_marker.to_string()),
+        next_key_marker: last.and_then(|u| u.key.clone()),
+        next_upload_id_marker: last.and_then(|u| u.upload_id.clone()),
+        max_uploads: Some(max_uploads as i32),
+        is_truncated: Some(is_truncated),
+        uploads: (!uploads.is_empty()).then_some(uploads),
+        ..Default::default()
+    };
+
+    Ok(S3Response::new(output))
+}
+
+/// Abort every multipart upload started at least `max_age_hours` ago,
+/// dropping its buffered parts and stored chunks. Returns the number of
+/// uploads aborted.
+pub async fn abort_expired_uploads(
+    state: &SharedState,
+    max_age_hours: u64,
+) -> anyhow::Result<usize> {
+    let expired = {
+        let db = state.db.lock().map_err(|_| anyhow::anyhow!("db lock"))?;
+        db.list_expired_multipart_uploads(max_age_hours)?
+    };
+    for upload_id in &expired {
+        abort_upload(state, upload_id).await?;
+        tracing::info!("Aborted expired multipart upload {upload_id}");
+    }
+    Ok(expired.len())
+}
@@ -0,0 +1,376 @@
+//! S3 object lock (write-once-read-many).
+//!
+//! An object version with a retention period cannot be overwritten or
+//! deleted before its retain-until date. In `COMPLIANCE` mode nobody can
+//! remove it or shorten the period; in `GOVERNANCE` mode callers holding
+//! [`BYPASS_GOVERNANCE_PERMISSION`] may, by sending
+//! `x-amz-bypass-governance-retention: true`.
+//!
+//! A legal hold protects an object version the same way, independently of
+//! its retention and with no bypass, until a caller holding
+//! [`LEGAL_HOLD_PERMISSION`] clears it.
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use http::HeaderMap;
+use s3s::auth::Credentials;
+use s3s::dto::*;
+use s3s::s3_error;
+use s3s::{S3Response, S3Result};
+
+use enigma_core::manifest::ManifestDb;
+
+use crate::SharedState;
+
+/// Permission needed to override a `GOVERNANCE` retention.
+pub const BYPASS_GOVERNANCE_PERMISSION: &str = "objects:lock:bypass";
+
+/// Permission needed to place or clear a legal hold.
+pub const LEGAL_HOLD_PERMISSION: &str = "objects:legal-hold:manage";
+
+const BYPASS_GOVERNANCE_HEADER: &str = "x-amz-bypass-governance-retention";
+
+/// Whether `x-amz-bypass-governance-retention: true` was sent, for requests
+/// whose input does not carry the flag.
+pub(crate) fn bypass_requested(headers: &HeaderMap) -> bool {
+    headers
+        .get(BYPASS_GOVERNANCE_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.trim().eq_ignore_ascii_case("true"))
+}
+
+/// Whether the caller asked for a governance bypass and may use one.
+/// Without [`crate::EnigmaS3State::access_control`] nobody may.
+pub(crate) async fn may_bypass_governance(
+    state: &SharedState,
+    credentials: Option<&Credentials>,
+    requested: bool,
+) -> S3Result<bool> {
+    if !requested {
+        return Ok(false);
+    }
+    has_permission(state, credentials, BYPASS_GOVERNANCE_PERMISSION).await
+}
+
+/// Whether the caller holds `permission`. Without
+/// [`crate::EnigmaS3State::access_control`] nobody does.
+async fn has_permission(
+    state: &SharedState,
+    credentials: Option<&Credentials>,
+    permission: &str,
+) -> S3Result<bool> {
+    let (Some(access), Some(credentials)) = (state.access_control.get(), credentials) else {
+        return Ok(false);
+    };
+    access
+        .has_permission(&credentials.access_key, permission)
+        .await
+        .map_err(|e| s3_error!(InternalError, "permission lookup failed: {e}"))
+}
+
+/// Refuse with `AccessDenied` to remove a locked version of `key`: the
+/// given `version_id`, or else the "null" version that a write or delete
+/// replaces when versioning is not enabled. With versioning enabled a
+/// write adds a version and a delete adds a marker, so nothing is removed.
+///
+/// An unknown bucket or version passes; the operation itself reports it.
+pub(crate) fn check_removable(
+    state: &SharedState,
+    bucket: &str,
+    key: &str,
+    version_id: Option<&str>,
+    bypass_governance: bool,
+) -> S3Result<()> {
+    let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
+    let Some(ns_id) = db
+        .get_namespace_id(bucket)
+        .map_err(|_| s3_error!(InternalError))?
+    else {
+        return Ok(());
+    };
+    let version_id = match version_id {
+        Some(version_id) => version_id,
+        None => {
+            let (enabled, _) = db
+                .get_namespace_versioning(ns_id)
+                .map_err(|_| s3_error!(InternalError))?;
+            if enabled {
+                return Ok(());
+            }
+            "null"
+        }
+    };
+    let Some((object_id, ..)) = db
+        .get_object_version(ns_id, key, version_id)
+        .map_err(|_| s3_error!(InternalError))?
+    else {
+        return Ok(());
+    };
+
+    // A legal hold applies whatever the retention says
+    if db
+        .get_object_legal_hold(object_id)
+        .map_err(|_| s3_error!(InternalError))?
+    {
+        return Err(s3_error!(
+            AccessDenied,
+            "Object is under legal hold and cannot be overwritten or deleted"
+        ));
+    }
+    let locked = match active_retention(&db, object_id)? {
+        Some((mode, _)) => mode == ObjectLockRetentionMode::COMPLIANCE || !bypass_governance,
+        None => false,
+    };
+    if locked {
+        return Err(s3_error!(
+            AccessDenied,
+            "Object is WORM protected and cannot be overwritten or deleted"
+        ));
+    }
+    Ok(())
+}
+
+/// Handle PutObjectRetention: set, extend or (in `GOVERNANCE` mode, with a
+/// bypass) shorten or remove the retention of an object version.
+pub async fn handle_put_object_retention(
+    state: &SharedState,
+    bucket: &str,
+    key: &str,
+    version_id: Option<&str>,
+    retention: Option<ObjectLockRetention>,
+    bypass_governance: bool,
+) -> S3Result<S3Response<PutObjectRetentionOutput>> {
+    let requested = match retention {
+        Some(ObjectLockRetention {
+            mode: Some(mode),
+            retain_until_date: Some(date),
+        }) => {
+            let mode = match mode.as_str() {
+                ObjectLockRetentionMode::COMPLIANCE | ObjectLockRetentionMode::GOVERNANCE => {
+                    mode.as_str().to_string()
+                }
+                _ => {
+                    return Err(s3_error!(
+                        MalformedXML,
+                        "Retention mode must be COMPLIANCE or GOVERNANCE"
+                    ));
+                }
+            };
+            let retain_until = crate::get::timestamp_secs(&date)
+                .and_then(|secs| DateTime::from_timestamp(secs, 0))
+                .ok_or_else(|| s3_error!(InvalidArgument, "Invalid retain until date"))?;
+            if retain_until <= Utc::now() {
+                return Err(s3_error!(
+                    InvalidArgument,
+                    "The retain until date must be in the future"
+                ));
+            }
+            Some((mode, retain_until))
+        }
+        Some(ObjectLockRetention {
+            mode: None,
+            retain_until_date: None,
+        })
+        | None => None,
+        Some(_) => {
+            return Err(s3_error!(
+                MalformedXML,
+                "Retention needs both a mode and a retain until date"
+            ));
+        }
+    };
+
+    let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
+    let object_id = lookup_locked_object(&db, bucket, key, version_id)?;
+
+    // A retention can only be made stricter, unless a governance bypass
+    // applies
+    if let Some((mode, until)) = active_retention(&db, object_id)? {
+        let weakened = match &requested {
+            None => true,
+            Some((new_mode, new_until)) => {
+                *new_until < until
+                    || (mode == ObjectLockRetentionMode::COMPLIANCE
+                        && new_mode != ObjectLockRetentionMode::COMPLIANCE)
+            }
+        };
+        let allowed = mode == ObjectLockRetentionMode::GOVERNANCE && bypass_governance;
+        if weakened && !allowed {
+            return Err(s3_error!(
+                AccessDenied,
+                "Retention cannot be shortened or removed while the object is locked"
+            ));
+        }
+    }
+
+    let requested =
+        requested.map(|(mode, until)| (mode, until.to_rfc3339_opts(SecondsFormat::Secs, true)));
+    db.set_object_retention(
+        object_id,
+        requested
+            .as_ref()
+            .map(|(mode, until)| (mode.as_str(), until.as_str())),
+    )
+    .map_err(|_| s3_error!(InternalError))?;
+
+    Ok(S3Response::new(PutObjectRetentionOutput::default()))
+}
+
+/// Handle GetObjectRetention: the retention of an object version, expired
+/// or not.
+pub async fn handle_get_object_retention(
+    state: &SharedState,
+    bucket: &str,
+    key: &str,
+    version_id: Option<&str>,
+) -> S3Result<S3Response<GetObjectRetentionOutput>> {
+    let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
+    let object_id = lookup_locked_object(&db, bucket, key, version_id)?;
+    let (mode, until) = db
+        .get_object_retention(object_id)
+        .map_err(|_| s3_error!(InternalError))?
+        .ok_or_else(|| s3_error!(NoSuchObjectLockConfiguration))?;
+    let until = parse_retain_until(&until)?;
+
+    let output = GetObjectRetentionOutput {
+        retention: Some(ObjectLockRetention {
+            mode: Some(ObjectLockRetentionMode::from(mode)),
+            retain_until_date: Timestamp::parse(
+                TimestampFormat::EpochSeconds,
+                &until.timestamp().to_string(),
+            )
+            .ok(),
+        }),
+    };
+    Ok(S3Response::new(output))
+}
+
+/// Handle PutObjectLegalHold: place (`ON`) or clear (`OFF`) the legal
+/// hold on an object version. Every change is audited.
+pub async fn handle_put_object_legal_hold(
+    state: &SharedState,
+    credentials: Option<&Credentials>,
+    bucket: &str,
+    key: &str,
+    version_id: Option<&str>,
+    legal_hold: Option<ObjectLockLegalHold>,
+) -> S3Result<S3Response<PutObjectLegalHoldOutput>> {
+    let hold = match legal_hold.and_then(|h| h.status) {
+        Some(status) if status.as_str() == ObjectLockLegalHoldStatus::ON => true,
+        Some(status) if status.as_str() == ObjectLockLegalHoldStatus::OFF => false,
+        _ => {
+            return Err(s3_error!(
+                MalformedXML,
+                "Legal hold status must be ON or OFF"
+            ));
+        }
+    };
+    if !has_permission(state, credentials, LEGAL_HOLD_PERMISSION).await? {
+        return Err(s3_error!(
+            AccessDenied,
+            "Managing legal holds requires the {LEGAL_HOLD_PERMISSION} permission"
+        ));
+    }
+
+    let object_id = {
+        let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
+        let object_id = lookup_locked_object(&db, bucket, key, version_id)?;
+        db.set_object_legal_hold(object_id, hold)
+            .map_err(|_| s3_error!(InternalError))?;
+        object_id
+    };
+
+    let action = if hold {
+        "object.legal_hold.set"
+    } else {
+        "object.legal_hold.clear"
+    };
+    let target = format!("{bucket}/{key} (object {object_id})");
+    tracing::info!("{action}: {target}");
+    // Only reachable with access control and credentials, checked above
+    if let (Some(access), Some(credentials)) = (state.access_control.get(), credentials) {
+        let _ = access
+            .log_audit(&credentials.access_key, action, &target)
+            .await;
+    }
+
+    Ok(S3Response::new(PutObjectLegalHoldOutput::default()))
+}
+
+/// Handle GetObjectLegalHold: whether an object version is under legal
+/// hold.
+pub async fn handle_get_object_legal_hold(
+    state: &SharedState,
+    bucket: &str,
+    key: &str,
+    version_id: Option<&str>,
+) -> S3Result<S3Response<GetObjectLegalHoldOutput>> {
+    let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
+    let object_id = lookup_locked_object(&db, bucket, key, version_id)?;
+    let hold = db
+        .get_object_legal_hold(object_id)
+        .map_err(|_| s3_error!(InternalError))?;
+
+    let status = ObjectLockLegalHoldStatus::from_static(if hold {
+        ObjectLockLegalHoldStatus::ON
+    } else {
+        ObjectLockLegalHoldStatus::OFF
+    });
+    let output = GetObjectLegalHoldOutput {
+        legal_hold: Some(ObjectLockLegalHold {
+            status: Some(status),
+        }),
+    };
+    Ok(S3Response::new(output))
+}
+
+/// Object version in a bucket with object lock enabled.
+fn lookup_locked_object(
+    db: &ManifestDb,
+    bucket: &str,
+    key: &str,
+    version_id: Option<&str>,
+) -> S3Result<i64> {
+    let ns_id = db
+        .get_namespace_id(bucket)
+        .map_err(|_| s3_error!(InternalError))?
+        .ok_or_else(|| s3_error!(NoSuchBucket))?;
+    if !db
+        .get_namespace_object_lock(ns_id)
+        .map_err(|_| s3_error!(InternalError))?
+    {
+        return Err(s3_error!(
+            InvalidRequest,
+            "Bucket is missing Object Lock Configuration"
+        ));
+    }
+    let object = match version_id {
+        Some(version_id) => db
+            .get_object_version(ns_id, key, version_id)
+            .map_err(|_| s3_error!(InternalError))?
+            .ok_or_else(|| s3_error!(NoSuchVersion))?,
+        None => db
+            .get_object(ns_id, key)
+            .map_err(|_| s3_error!(InternalError))?
+            .ok_or_else(|| s3_error!(NoSuchKey))?,
+    };
+    Ok(object.0)
+}
+
+/// Retention of an object version as `(mode, retain_until)`, if it has not
+/// expired yet.
+fn active_retention(db: &ManifestDb, object_id: i64) -> S3Result<Option<(String, DateTime<Utc>)>> {
+    let Some((mode, until)) = db
+        .get_object_retention(object_id)
+        .map_err(|_| s3_error!(InternalError))?
+    else {
+        return Ok(None);
+    };
+    let until = parse_retain_until(&until)?;
+    Ok((until > Utc::now()).then_some((mode, until)))
+}
+
+fn parse_retain_until(until: &str) -> S3Result<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(until)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|_| s3_error!(InternalError, "invalid retain until date: {until}"))
+}
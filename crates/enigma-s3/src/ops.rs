@@ -1,12 +1,32 @@
+use std::io;
+use std::num::NonZeroUsize;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::{Context, Poll, ready};
+use std::time::Instant;
+
+use bytes::Bytes;
+use futures::stream::BoxStream;
+use futures::{Stream, StreamExt, TryStreamExt};
+use lru::LruCache;
 use serde::Serialize;
+use serde_json::json;
 use sha2::{Digest, Sha256};
+use tokio::io::{AsyncRead, ReadBuf};
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tokio::task::JoinHandle;
 
-use enigma_core::compression::{compress_chunk, decompress_chunk};
-use enigma_core::crypto::{decrypt_chunk, encrypt_chunk};
+use enigma_core::compression::{compress, decompress_chunk};
+use enigma_core::config::EnigmaSettings;
+use enigma_core::crypto::{chunk_hmac_tag, decrypt_chunk, encrypt_chunk, verify_chunk_hmac};
 use enigma_core::dedup::compute_hash;
-use enigma_core::types::{ChunkHash, EncryptedChunk};
+use enigma_core::events::{BackupPhase, BackupProgress};
+use enigma_core::limits::worker_semaphore;
+use enigma_core::notify::{BackupNotificationEvent, spawn_backup_notification};
+use enigma_core::types::{BackupStatus, ChunkHash, EncryptedChunk};
 
-use crate::EnigmaS3State;
+use crate::{EnigmaS3State, SharedState};
+use crate::{bandwidth, metrics};
 
 // ── Public types ─────────────────────────────────────────────
 
@@ -33,21 +53,194 @@ pub struct FolderListing {
 }
 
 pub struct FileData {
-    pub data: Vec<u8>,
+    pub reader: ObjectReader,
     pub size: u64,
     pub etag: String,
     pub content_type: Option<String>,
 }
 
+/// The object written by [`copy_object_server_side`].
+pub struct CopiedObject {
+    pub etag: String,
+    /// Version ID of the copy ("null" when the bucket is unversioned).
+    pub version_id: String,
+    /// Version ID of the source that was copied.
+    pub source_version_id: String,
+    pub created_at: String,
+}
+
+/// Decrypted object contents, one verified chunk at a time (see
+/// [`object_stream`]). Readable either as `AsyncRead` or as a `Stream` of
+/// `Bytes` (for HTTP response bodies). A chunk that fails to download,
+/// decrypt or verify surfaces as an `io::Error` and ends the stream.
+pub struct ObjectReader {
+    chunks: BoxStream<'static, io::Result<Bytes>>,
+    pending: Bytes,
+}
+
+impl AsyncRead for ObjectReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        loop {
+            if !self.pending.is_empty() {
+                let n = self.pending.len().min(buf.remaining());
+                let head = self.pending.split_to(n);
+                buf.put_slice(&head);
+                return Poll::Ready(Ok(()));
+            }
+            match ready!(self.chunks.poll_next_unpin(cx)) {
+                Some(Ok(bytes)) => self.pending = bytes,
+                Some(Err(e)) => return Poll::Ready(Err(e)),
+                None => return Poll::Ready(Ok(())),
+            }
+        }
+    }
+}
+
+impl Stream for ObjectReader {
+    type Item = io::Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if !self.pending.is_empty() {
+            return Poll::Ready(Some(Ok(std::mem::take(&mut self.pending))));
+        }
+        self.chunks.poll_next_unpin(cx)
+    }
+}
+
+// ── Chunk cache ──────────────────────────────────────────────
+
+/// The LRU behind an enabled [`ChunkCache`].
+type ChunkLru = LruCache<(i64, String), Bytes>;
+
+/// Recently downloaded chunk ciphertext, keyed by `(provider_id, storage_key)`.
+/// While a streamed object sends chunk N, chunk N+1 is pre-fetched into it.
+/// The default cache is disabled and never holds anything.
+#[derive(Clone, Default)]
+pub struct ChunkCache {
+    entries: Option<Arc<Mutex<ChunkLru>>>,
+}
+
+impl ChunkCache {
+    /// A cache of up to `max_entries` chunks; 0 disables it.
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            entries: NonZeroUsize::new(max_entries)
+                .map(|cap| Arc::new(Mutex::new(LruCache::new(cap)))),
+        }
+    }
+
+    /// The cache set up by `chunk_cache_enabled` and `chunk_cache_max_entries`.
+    pub fn from_settings(settings: &EnigmaSettings) -> Self {
+        if settings.chunk_cache_enabled {
+            Self::new(settings.chunk_cache_max_entries)
+        } else {
+            Self::default()
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.entries.is_some()
+    }
+
+    fn get(&self, provider_id: i64, storage_key: &str) -> Option<Bytes> {
+        let mut entries = self.entries.as_ref()?.lock().ok()?;
+        entries.get(&(provider_id, storage_key.to_string())).cloned()
+    }
+
+    fn insert(&self, provider_id: i64, storage_key: &str, data: Bytes) {
+        if let Some(entries) = &self.entries
+            && let Ok(mut entries) = entries.lock()
+        {
+            entries.put((provider_id, storage_key.to_string()), data);
+        }
+    }
+}
+
+// ── Upload limit ─────────────────────────────────────────────
+
+/// Caps the chunk uploads in flight across all S3 requests. The default
+/// has no cap.
+#[derive(Clone)]
+pub struct UploadSemaphore(Arc<Semaphore>);
+
+impl UploadSemaphore {
+    /// At most `workers` uploads at once; `None` means no limit.
+    pub fn new(workers: Option<usize>) -> Self {
+        Self(worker_semaphore(workers))
+    }
+
+    /// The limit set by `backup_workers`.
+    pub fn from_settings(settings: &EnigmaSettings) -> Self {
+        Self::new(settings.backup_workers)
+    }
+
+    /// Wait for a free upload slot, held until the permit is dropped.
+    pub async fn acquire(&self) -> SemaphorePermit<'_> {
+        self.0
+            .acquire()
+            .await
+            .expect("upload semaphore is never closed")
+    }
+}
+
+impl Default for UploadSemaphore {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
 // ── Operations ───────────────────────────────────────────────
 
-/// Store an object (chunk → encrypt → dedup → upload).
+/// Store an object (chunk → encrypt → dedup → upload), publishing
+/// progress on `state.events` under the backup id `{bucket}/{key}` and
+/// mailing the outcome when notifications are configured.
 pub async fn store_object(
     state: &EnigmaS3State,
     bucket: &str,
     key: &str,
     data: &[u8],
     content_type: Option<&str>,
+) -> anyhow::Result<String> {
+    let started = Instant::now();
+    let mut progress = BackupProgress::new(&format!("{bucket}/{key}"), 1, data.len() as u64);
+    let result = store_object_inner(state, bucket, key, data, content_type, &mut progress).await;
+    let event = match &result {
+        Ok(_) => {
+            progress.files_done = 1;
+            progress.event(BackupPhase::Done)
+        }
+        Err(_) => progress.event(BackupPhase::Error),
+    };
+    spawn_backup_notification(
+        state.config.notifications.as_ref(),
+        BackupNotificationEvent {
+            backup_id: event.backup_id.clone(),
+            status: if result.is_ok() {
+                BackupStatus::Completed
+            } else {
+                BackupStatus::Failed
+            },
+            duration: started.elapsed(),
+            total_bytes: event.bytes_total,
+            dedup_ratio: event.dedup_ratio,
+            error: result.as_ref().err().map(|e| e.to_string()),
+        },
+    );
+    state.events.publish(event);
+    result
+}
+
+async fn store_object_inner(
+    state: &EnigmaS3State,
+    bucket: &str,
+    key: &str,
+    data: &[u8],
+    content_type: Option<&str>,
+    progress: &mut BackupProgress,
 ) -> anyhow::Result<String> {
     let total_size = data.len() as u64;
 
@@ -57,6 +250,7 @@ pub async fn store_object(
         format!("{:x}", hasher.finalize())
     };
 
+    state.events.publish(progress.event(BackupPhase::Chunking));
     let raw_chunks = crate::put::chunk_data_owned(data);
     let chunk_count = raw_chunks.len() as u32;
 
@@ -74,8 +268,9 @@ pub async fn store_object(
         let hash_hex = chunk_hash.to_hex();
         let storage_key = chunk_hash.storage_key();
 
+        state.events.publish(progress.event(BackupPhase::Encrypting));
         let (data_to_encrypt, size_compressed) = if compression.enabled {
-            let compressed = compress_chunk(chunk_bytes, compression.level)?;
+            let compressed = compress(chunk_bytes, compression.algorithm, compression.level)?;
             let sz = compressed.len() as u64;
             (compressed, Some(sz))
         } else {
@@ -84,12 +279,13 @@ pub async fn store_object(
 
         let encrypted = encrypt_chunk(&data_to_encrypt, &chunk_hash, &state.key_material)?;
         let replication = state.config.enigma.replication_factor.max(1) as usize;
-        let targets = state.distributor.next_providers(replication);
+        let distributor = state.distributor.load();
+        let targets = distributor.next_providers(replication);
         let primary = targets[0];
 
         let is_new = {
             let db = state.db.lock().map_err(|_| anyhow::anyhow!("db lock"))?;
-            db.insert_or_dedup_chunk(
+            let is_new = db.insert_or_dedup_chunk(
                 &hash_hex,
                 &encrypted.nonce,
                 &state.key_material.id,
@@ -98,15 +294,28 @@ pub async fn store_object(
                 chunk_bytes.len() as u64,
                 encrypted.ciphertext.len() as u64,
                 size_compressed,
-            )?
+            )?;
+            if is_new {
+                let tag = chunk_hmac_tag(&state.key_material, &storage_key, &encrypted.ciphertext);
+                db.set_chunk_hmac_tag(&hash_hex, &tag)?;
+            }
+            is_new
         };
 
         if is_new {
+            state.events.publish(progress.event(BackupPhase::Uploading));
             for target in &targets {
                 if let Some(provider) = state.providers.get(&target.id) {
-                    match provider.upload_chunk(&storage_key, &encrypted.ciphertext).await {
+                    let _permit = state.upload_semaphore.acquire().await;
+                    bandwidth::throttle_upload(
+                        &state.provider_limiters,
+                        target.id,
+                        encrypted.ciphertext.len(),
+                    )
+                    .await;
+                    match metrics::upload_chunk(provider.as_ref(), &storage_key, &encrypted.ciphertext).await {
                         Ok(_) => {}
-                        Err(e) if target.id == primary.id => return Err(e.into()),
+                        Err(e) if target.id == primary.id => return Err(e),
                         Err(e) => {
                             tracing::warn!("Replica upload to provider {} failed: {e}", target.id);
                         }
@@ -118,7 +327,10 @@ pub async fn store_object(
                 let db = state.db.lock().map_err(|_| anyhow::anyhow!("db lock"))?;
                 db.insert_chunk_replicas(&hash_hex, &replicas)?;
             }
+        } else {
+            metrics::chunk_deduped();
         }
+        progress.chunk_done(chunk_bytes.len() as u64, !is_new);
 
         chunk_records.push((hash_hex, idx as u32, chunk_bytes.len() as u64));
     }
@@ -139,14 +351,20 @@ pub async fn store_object(
             db.insert_object_chunk(object_id, hash_hex, *chunk_index, offset)?;
             offset += size;
         }
+        let version_id = db.get_object_version_id(object_id)?;
+        state.queue_webhook(
+            &db,
+            "object.created",
+            json!({ "bucket": bucket, "key": key, "version_id": version_id }),
+        );
     }
 
     Ok(etag)
 }
 
-/// Retrieve an object (download chunks → decrypt → reassemble).
+/// Retrieve an object as a stream (download chunks → decrypt → verify → forward).
 pub async fn retrieve_object(
-    state: &EnigmaS3State,
+    state: &SharedState,
     bucket: &str,
     key: &str,
 ) -> anyhow::Result<FileData> {
@@ -159,75 +377,306 @@ pub async fn retrieve_object(
             .ok_or_else(|| anyhow::anyhow!("object not found: {key}"))?
     };
 
+    Ok(FileData {
+        reader: stream_object(state.clone(), object_id),
+        size,
+        etag,
+        content_type,
+    })
+}
+
+/// Retrieve an object fully into memory, downloading up to
+/// `download_concurrency` chunks at once. Each chunk is verified before
+/// being reassembled in chunk-index order.
+pub async fn retrieve_object_parallel(
+    state: &EnigmaS3State,
+    bucket: &str,
+    key: &str,
+) -> anyhow::Result<Vec<u8>> {
+    let (object_id, size, ..) = {
+        let db = state.db.lock().map_err(|_| anyhow::anyhow!("db lock"))?;
+        let ns_id = db
+            .get_namespace_id(bucket)?
+            .ok_or_else(|| anyhow::anyhow!("namespace not found: {bucket}"))?;
+        db.get_object(ns_id, key)?
+            .ok_or_else(|| anyhow::anyhow!("object not found: {key}"))?
+    };
+    read_object(state, object_id, size).await
+}
+
+/// The contents of `object_id`, `size` bytes, read fully into memory as
+/// [`retrieve_object_parallel`] does.
+pub async fn read_object(
+    state: &EnigmaS3State,
+    object_id: i64,
+    size: u64,
+) -> anyhow::Result<Vec<u8>> {
     let chunk_list = {
         let db = state.db.lock().map_err(|_| anyhow::anyhow!("db lock"))?;
         db.get_object_chunks(object_id)?
     };
 
-    let mut file_data = Vec::with_capacity(size as usize);
+    let concurrency = state.config.enigma.download_concurrency.max(1);
+    let mut parts: Vec<(u32, Vec<u8>)> = futures::stream::iter(chunk_list)
+        .map(|(chunk_hash_hex, chunk_index, _offset)| async move {
+            let plaintext = fetch_chunk(state, &chunk_hash_hex).await?;
+            Ok::<_, anyhow::Error>((chunk_index, plaintext))
+        })
+        .buffered(concurrency)
+        .try_collect()
+        .await?;
+    parts.sort_unstable_by_key(|(idx, _)| *idx);
+
+    let mut data = Vec::with_capacity(size as usize);
+    for (_idx, plaintext) in parts {
+        data.extend_from_slice(&plaintext);
+    }
+    Ok(data)
+}
 
-    for (chunk_hash_hex, _chunk_index, _offset) in &chunk_list {
-        let chunk_locations = {
-            let db = state.db.lock().map_err(|_| anyhow::anyhow!("db lock"))?;
-            db.get_chunk_locations(chunk_hash_hex)?
-                .ok_or_else(|| anyhow::anyhow!("chunk not found: {chunk_hash_hex}"))?
+/// Stream the chunks of `object_id` in order; see [`object_stream`].
+pub fn stream_object(state: SharedState, object_id: i64) -> ObjectReader {
+    ObjectReader {
+        chunks: object_stream(state, object_id).boxed(),
+        pending: Bytes::new(),
+    }
+}
+
+/// The chunks of `object_id` in order, each downloaded, decrypted and
+/// verified only when the consumer polls for it. A slow client therefore
+/// holds the downloads back, and memory stays bounded by chunk size, not
+/// object size. With the chunk cache enabled, the next chunk is
+/// pre-fetched while the current one downloads.
+pub fn object_stream(
+    state: SharedState,
+    object_id: i64,
+) -> impl Stream<Item = io::Result<Bytes>> + Send + 'static {
+    let cursor = ChunkCursor {
+        state,
+        object_id,
+        chunks: None,
+        next: 0,
+        prefetch: None,
+    };
+    futures::stream::try_unfold(cursor, |mut cursor| async move {
+        match cursor.next_chunk().await {
+            Ok(chunk) => Ok(chunk.map(|chunk| (chunk, cursor))),
+            Err(e) => {
+                tracing::error!("Streaming object {} failed: {e}", cursor.object_id);
+                Err(io::Error::other(e.to_string()))
+            }
+        }
+    })
+}
+
+/// Retrieve object `object_id` as a response body: [`object_stream`],
+/// made `Sync` as `StreamingBlob` requires. Takes the ID GetObject already
+/// looked up, so the body is the version its preconditions were checked
+/// against.
+pub fn retrieve_object_streaming(
+    state: &SharedState,
+    object_id: i64,
+) -> impl Stream<Item = io::Result<Bytes>> + Send + Sync + 'static {
+    SyncStream(Mutex::new(object_stream(state.clone(), object_id).boxed()))
+}
+
+/// A `Send` stream made `Sync`: it is only ever polled through `&mut`, so
+/// the mutex is never contended.
+struct SyncStream<S>(Mutex<S>);
+
+impl<S: Stream + Unpin> Stream for SyncStream<S> {
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<S::Item>> {
+        self.get_mut()
+            .0
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .poll_next_unpin(cx)
+    }
+}
+
+/// Position of an [`object_stream`] in its object.
+struct ChunkCursor {
+    state: SharedState,
+    object_id: i64,
+    /// Chunk hashes in order, looked up on the first poll.
+    chunks: Option<Vec<String>>,
+    next: usize,
+    prefetch: Option<JoinHandle<()>>,
+}
+
+impl ChunkCursor {
+    async fn next_chunk(&mut self) -> anyhow::Result<Option<Bytes>> {
+        if self.chunks.is_none() {
+            let db = self
+                .state
+                .db
+                .lock()
+                .map_err(|_| anyhow::anyhow!("db lock"))?;
+            let chunk_list = db.get_object_chunks(self.object_id)?;
+            self.chunks = Some(chunk_list.into_iter().map(|(hash, ..)| hash).collect());
+        }
+        let chunks = self.chunks.as_deref().unwrap_or_default();
+        let Some(chunk_hash_hex) = chunks.get(self.next) else {
+            return Ok(None);
         };
-        let (nonce, _chunk_key_id, locations, _size_enc, size_compressed) = chunk_locations;
-
-        // Download with fallback across replicas
-        let mut ciphertext = None;
-        for (pid, skey) in &locations {
-            if let Some(provider) = state.providers.get(pid) {
-                match provider.download_chunk(skey).await {
-                    Ok(data) => {
-                        ciphertext = Some(data);
-                        break;
-                    }
-                    Err(e) => {
-                        tracing::warn!("Provider {pid} failed for chunk {chunk_hash_hex}: {e}, trying next");
+
+        // Wait for this chunk's pre-fetch so it is served from the cache
+        if let Some(handle) = self.prefetch.take() {
+            let _ = handle.await;
+        }
+        self.prefetch = chunks
+            .get(self.next + 1)
+            .and_then(|next_hash| prefetch_chunk(&self.state, next_hash));
+        self.next += 1;
+
+        let plaintext = fetch_chunk(&self.state, chunk_hash_hex).await?;
+        Ok(Some(Bytes::from(plaintext)))
+    }
+}
+
+impl Drop for ChunkCursor {
+    fn drop(&mut self) {
+        // Reader dropped (client went away) — stop downloading.
+        if let Some(handle) = self.prefetch.take() {
+            handle.abort();
+        }
+    }
+}
+
+/// Download `chunk_hash_hex` into the chunk cache in the background.
+/// Returns `None` when the cache is disabled.
+fn prefetch_chunk(state: &SharedState, chunk_hash_hex: &str) -> Option<JoinHandle<()>> {
+    if !state.chunk_cache.is_enabled() {
+        return None;
+    }
+    let state = state.clone();
+    let chunk_hash_hex = chunk_hash_hex.to_string();
+    Some(tokio::spawn(async move {
+        let locations = match state.db.lock() {
+            Ok(db) => db.get_chunk_locations(&chunk_hash_hex),
+            Err(_) => return,
+        };
+        let Ok(Some((_nonce, _key_id, locations, _size_enc, _size_compressed))) = locations else {
+            return;
+        };
+        // A failure here is retried (and reported) by the real fetch
+        if let Err(e) = download_chunk_replica(&state, &chunk_hash_hex, &locations).await {
+            tracing::debug!("Pre-fetch of chunk {chunk_hash_hex} failed: {e}");
+        }
+    }))
+}
+
+/// Download a chunk's ciphertext from the first replica that answers.
+/// A copy of any replica already in the chunk cache is used instead, and
+/// fresh downloads are added to it.
+async fn download_chunk_replica(
+    state: &EnigmaS3State,
+    chunk_hash_hex: &str,
+    locations: &[(i64, String)],
+) -> anyhow::Result<(Bytes, i64, String)> {
+    for (pid, skey) in locations {
+        if let Some(data) = state.chunk_cache.get(*pid, skey) {
+            return Ok((data, *pid, skey.clone()));
+        }
+    }
+
+    for (pid, skey) in locations {
+        if let Some(provider) = state.providers.get(pid) {
+            match provider.download_chunk(skey).await {
+                Ok(data) => {
+                    bandwidth::throttle_download(&state.provider_limiters, *pid, data.len()).await;
+                    metrics::bytes_downloaded(data.len());
+                    if let Ok(db) = state.db.lock() {
+                        let _ = db.record_chunk_access(chunk_hash_hex);
                     }
+                    let data = Bytes::from(data);
+                    state.chunk_cache.insert(*pid, skey, data.clone());
+                    return Ok((data, *pid, skey.clone()));
+                }
+                Err(e) => {
+                    metrics::provider_error(provider.name());
+                    tracing::warn!(
+                        "Provider {pid} failed for chunk {chunk_hash_hex}: {e}, trying next"
+                    );
                 }
             }
         }
-        let ciphertext =
-            ciphertext.ok_or_else(|| anyhow::anyhow!("all providers failed for chunk {chunk_hash_hex}"))?;
-
-        let nonce_arr: [u8; 12] = nonce
-            .try_into()
-            .map_err(|_| anyhow::anyhow!("invalid nonce length"))?;
-        let hash_bytes: [u8; 32] = hex::decode(chunk_hash_hex)?
-            .try_into()
-            .map_err(|_| anyhow::anyhow!("invalid hash length"))?;
-
-        let encrypted = EncryptedChunk {
-            hash: ChunkHash(hash_bytes),
-            nonce: nonce_arr,
-            ciphertext,
-            key_id: state.key_material.id.clone(),
-        };
-
-        let decrypted = decrypt_chunk(&encrypted, &state.key_material)?;
+    }
+    anyhow::bail!("all providers failed for chunk {chunk_hash_hex}")
+}
 
-        let plaintext = if size_compressed.is_some() {
-            decompress_chunk(&decrypted)?
+/// Download one chunk (with replica fallback), check its HMAC tag,
+/// decrypt, decompress and verify its hash.
+async fn fetch_chunk(state: &EnigmaS3State, chunk_hash_hex: &str) -> anyhow::Result<Vec<u8>> {
+    let (chunk_locations, hmac_tag) = {
+        let db = state.db.lock().map_err(|_| anyhow::anyhow!("db lock"))?;
+        let locations = db
+            .get_chunk_locations(chunk_hash_hex)?
+            .ok_or_else(|| anyhow::anyhow!("chunk not found: {chunk_hash_hex}"))?;
+        let tag = if state.config.enigma.verify_hmac {
+            db.get_chunk_hmac_tag(chunk_hash_hex)?
         } else {
-            decrypted
+            None
         };
+        (locations, tag)
+    };
+    let (nonce, _chunk_key_id, locations, _size_enc, size_compressed) = chunk_locations;
+
+    // Download with fallback across replicas
+    let (ciphertext, provider_id, storage_key) =
+        download_chunk_replica(state, chunk_hash_hex, &locations).await?;
+
+    if let Some(tag) = hmac_tag
+        && let Err(e) = verify_chunk_hmac(&state.key_material, &storage_key, &ciphertext, &tag)
+    {
+        tracing::error!(
+            provider_id,
+            storage_key = storage_key.as_str(),
+            chunk = chunk_hash_hex,
+            "Chunk HMAC mismatch on read"
+        );
+        return Err(e.into());
+    }
+
+    let nonce_arr: [u8; 12] = nonce
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("invalid nonce length"))?;
+    let hash_bytes: [u8; 32] = hex::decode(chunk_hash_hex)?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("invalid hash length"))?;
+
+    let encrypted = EncryptedChunk {
+        hash: ChunkHash(hash_bytes),
+        nonce: nonce_arr,
+        ciphertext: ciphertext.into(),
+        key_id: state.key_material.id.clone(),
+    };
 
-        let computed = compute_hash(&plaintext);
-        if computed.to_hex() != *chunk_hash_hex {
+    let decrypted = decrypt_chunk(&encrypted, &state.key_material)?;
+
+    let plaintext = if size_compressed.is_some() {
+        decompress_chunk(&decrypted)?
+    } else {
+        decrypted
+    };
+
+    if state.config.enigma.verify_on_read {
+        let computed = compute_hash(&plaintext).to_hex();
+        if computed != chunk_hash_hex {
+            tracing::error!(
+                provider_id,
+                storage_key = storage_key.as_str(),
+                expected = chunk_hash_hex,
+                computed = %computed,
+                "Chunk hash mismatch on read"
+            );
             anyhow::bail!("chunk hash mismatch for {chunk_hash_hex}");
         }
-
-        file_data.extend_from_slice(&plaintext);
     }
 
-    Ok(FileData {
-        data: file_data,
-        size,
-        etag,
-        content_type,
-    })
+    Ok(plaintext)
 }
 
 /// Remove an object and its orphaned chunks.
@@ -241,20 +690,163 @@ pub async fn remove_object(
         let ns_id = db
             .get_namespace_id(bucket)?
             .ok_or_else(|| anyhow::anyhow!("namespace not found: {bucket}"))?;
-        db.delete_object_by_ns_key(ns_id, key)?
+        let to_delete = db.delete_object_by_ns_key(ns_id, key)?;
+        state.queue_webhook(
+            &db,
+            "object.deleted",
+            json!({ "bucket": bucket, "key": key, "version_id": null, "delete_marker": false }),
+        );
+        to_delete
     };
 
+    let mut deleted = 0;
     for (provider_id, storage_key) in to_delete {
         if let Some(provider) = state.providers.get(&provider_id) {
-            if let Err(e) = provider.delete_chunk(&storage_key).await {
-                tracing::warn!("Failed to delete chunk {storage_key}: {e}");
+            match provider.delete_chunk(&storage_key).await {
+                Ok(_) => deleted += 1,
+                Err(e) => {
+                    metrics::provider_error(provider.name());
+                    tracing::warn!("Failed to delete chunk {storage_key}: {e}");
+                }
             }
         }
     }
+    metrics::gc_orphans(deleted);
 
     Ok(())
 }
 
+/// Permanently remove namespaces soft-deleted at least `recovery_days`
+/// ago, with their objects and orphaned chunks. Returns the number of
+/// namespaces purged.
+pub async fn purge_deleted_namespaces(
+    state: &EnigmaS3State,
+    recovery_days: u32,
+) -> anyhow::Result<usize> {
+    let (purged, to_delete) = {
+        let db = state.db.lock().map_err(|_| anyhow::anyhow!("db lock"))?;
+        let expired = db.list_expired_namespaces(recovery_days)?;
+        let mut to_delete = Vec::new();
+        for name in &expired {
+            if let Some(locations) = db.delete_namespace_permanently(name)? {
+                to_delete.extend(locations);
+            }
+            tracing::info!("Purged deleted namespace {name}");
+        }
+        (expired.len(), to_delete)
+    };
+
+    let mut deleted = 0;
+    for (provider_id, storage_key) in to_delete {
+        if let Some(provider) = state.providers.get(&provider_id) {
+            match provider.delete_chunk(&storage_key).await {
+                Ok(_) => deleted += 1,
+                Err(e) => {
+                    metrics::provider_error(provider.name());
+                    tracing::warn!("Failed to delete chunk {storage_key}: {e}");
+                }
+            }
+        }
+    }
+    metrics::gc_orphans(deleted);
+
+    Ok(purged)
+}
+
+/// Copy an object without touching storage: the copy references the
+/// source's chunks (each gaining a ref) and takes its content type and
+/// tags. `metadata` replaces the user metadata; `None` keeps the source's.
+/// The ETag is the SHA-256 of the source ETag, so copies of the same
+/// source agree. Source and destination may be in different buckets.
+#[allow(clippy::too_many_arguments)]
+pub fn copy_object_server_side(
+    state: &EnigmaS3State,
+    src_bucket: &str,
+    src_key: &str,
+    src_version_id: Option<&str>,
+    dst_bucket: &str,
+    dst_key: &str,
+    metadata: Option<&[(String, String)]>,
+) -> anyhow::Result<CopiedObject> {
+    let db = state.db.lock().map_err(|_| anyhow::anyhow!("db lock"))?;
+    let src_ns = db
+        .get_namespace_id(src_bucket)?
+        .ok_or_else(|| anyhow::anyhow!("namespace not found: {src_bucket}"))?;
+    let dst_ns = db
+        .get_namespace_id(dst_bucket)?
+        .ok_or_else(|| anyhow::anyhow!("namespace not found: {dst_bucket}"))?;
+
+    db.begin_transaction()?;
+    let copied = (|| -> anyhow::Result<CopiedObject> {
+        let source = match src_version_id {
+            Some(version_id) => db.get_object_version(src_ns, src_key, version_id)?,
+            None => db.get_object(src_ns, src_key)?,
+        };
+        let (src_id, size, src_etag, content_type, chunk_count, key_id, _) =
+            source.ok_or_else(|| anyhow::anyhow!("object not found: {src_key}"))?;
+        let chunks = db.get_object_chunks(src_id)?;
+        let tags = db.get_object_tags(src_id)?;
+        let metadata = match metadata {
+            Some(metadata) => metadata.to_vec(),
+            None => db.get_object_metadata(src_id)?,
+        };
+        let source_version_id = db.get_object_version_id(src_id)?;
+
+        // Take the new refs first: replacing the destination may drop the
+        // source's own refs when copying an object onto itself
+        for (hash_hex, _, _) in &chunks {
+            db.add_chunk_ref(hash_hex)?;
+        }
+
+        let etag = format!("{:x}", Sha256::digest(src_etag.as_bytes()));
+        let object_id = db.insert_object(
+            dst_ns,
+            dst_key,
+            size,
+            &etag,
+            content_type.as_deref(),
+            chunk_count,
+            &key_id,
+        )?;
+        for (hash_hex, chunk_index, offset) in &chunks {
+            db.insert_object_chunk(object_id, hash_hex, *chunk_index, *offset)?;
+        }
+        if !tags.is_empty() {
+            db.set_object_tags(object_id, &tags)?;
+        }
+        if !metadata.is_empty() {
+            db.set_object_metadata(object_id, &metadata)?;
+        }
+
+        let version_id = db.get_object_version_id(object_id)?;
+        let (.., created_at) = db
+            .get_object_version(dst_ns, dst_key, &version_id)?
+            .ok_or_else(|| anyhow::anyhow!("copied object not found: {dst_key}"))?;
+        Ok(CopiedObject {
+            etag,
+            version_id,
+            source_version_id,
+            created_at,
+        })
+    })();
+
+    match copied {
+        Ok(copied) => {
+            db.commit_transaction()?;
+            state.queue_webhook(
+                &db,
+                "object.created",
+                json!({ "bucket": dst_bucket, "key": dst_key, "version_id": copied.version_id }),
+            );
+            Ok(copied)
+        }
+        Err(e) => {
+            let _ = db.rollback_transaction();
+            Err(e)
+        }
+    }
+}
+
 /// List folder contents at a given prefix.
 pub async fn list_folder(
     state: &EnigmaS3State,
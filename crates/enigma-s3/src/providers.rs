@@ -0,0 +1,111 @@
+//! Storage providers and chunk distributor of the S3 state, swapped
+//! atomically when the proxy reloads its config. Readers take a snapshot
+//! and never block; a request in flight keeps using the snapshot it took.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+use enigma_core::distributor::Distributor;
+use enigma_storage::provider::StorageProvider;
+
+/// Storage providers by ID. Providers can be added at runtime but are
+/// never removed, so in-flight requests always find theirs.
+pub struct ProviderMap(ArcSwap<HashMap<i64, Arc<dyn StorageProvider>>>);
+
+impl ProviderMap {
+    pub fn get(&self, id: &i64) -> Option<Arc<dyn StorageProvider>> {
+        self.0.load().get(id).cloned()
+    }
+
+    pub fn contains(&self, id: &i64) -> bool {
+        self.0.load().contains_key(id)
+    }
+
+    /// The current providers.
+    pub fn snapshot(&self) -> Arc<HashMap<i64, Arc<dyn StorageProvider>>> {
+        self.0.load_full()
+    }
+
+    /// Add (or replace) a provider.
+    pub fn insert(&self, id: i64, provider: Arc<dyn StorageProvider>) {
+        self.0.rcu(|providers| {
+            let mut providers = HashMap::clone(providers);
+            providers.insert(id, provider.clone());
+            providers
+        });
+    }
+}
+
+impl From<HashMap<i64, Box<dyn StorageProvider>>> for ProviderMap {
+    fn from(providers: HashMap<i64, Box<dyn StorageProvider>>) -> Self {
+        Self(ArcSwap::from_pointee(
+            providers
+                .into_iter()
+                .map(|(id, provider)| (id, Arc::from(provider)))
+                .collect(),
+        ))
+    }
+}
+
+/// The distributor placing new chunks, replaced as a whole on reload.
+pub struct SharedDistributor(ArcSwap<Distributor>);
+
+impl SharedDistributor {
+    /// The current distributor. Hold on to it for the whole placement so
+    /// the providers picked stay consistent.
+    pub fn load(&self) -> Arc<Distributor> {
+        self.0.load_full()
+    }
+
+    pub fn store(&self, distributor: Distributor) {
+        self.0.store(Arc::new(distributor));
+    }
+}
+
+impl From<Distributor> for SharedDistributor {
+    fn from(distributor: Distributor) -> Self {
+        Self(ArcSwap::from_pointee(distributor))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use enigma_core::types::{ProviderInfo, ProviderType};
+    use enigma_storage::mock::MockStorageProvider;
+
+    fn info(id: i64) -> ProviderInfo {
+        ProviderInfo {
+            id,
+            name: format!("p{id}"),
+            provider_type: ProviderType::Local,
+            bucket: "/tmp".to_string(),
+            region: None,
+            weight: 1,
+        }
+    }
+
+    #[test]
+    fn snapshot_is_unaffected_by_later_inserts() {
+        let mut initial: HashMap<i64, Box<dyn StorageProvider>> = HashMap::new();
+        initial.insert(1, Box::new(MockStorageProvider::new("one")));
+        let providers = ProviderMap::from(initial);
+
+        let before = providers.snapshot();
+        providers.insert(2, Arc::new(MockStorageProvider::new("two")));
+        assert_eq!(before.len(), 1);
+        assert_eq!(providers.snapshot().len(), 2);
+        assert_eq!(providers.get(&2).unwrap().name(), "two");
+        assert!(providers.contains(&1));
+    }
+
+    #[test]
+    fn stored_distributor_replaces_the_old_one() {
+        let distributor = SharedDistributor::from(Distributor::round_robin(vec![info(1)]).unwrap());
+        let old = distributor.load();
+        distributor.store(Distributor::round_robin(vec![info(1), info(2)]).unwrap());
+        assert_eq!(old.providers().len(), 1);
+        assert_eq!(distributor.load().providers().len(), 2);
+    }
+}
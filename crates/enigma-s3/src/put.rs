@@ -1,149 +1,296 @@
+use bytes::Bytes;
 use futures::StreamExt;
 use s3s::dto::*;
 use s3s::s3_error;
 use s3s::{S3Response, S3Result};
+use serde_json::json;
 use sha2::{Digest, Sha256};
+use tokio::sync::mpsc;
 
-use enigma_core::compression::compress_chunk;
-use enigma_core::crypto::encrypt_chunk;
+use enigma_core::compression::compress;
+use enigma_core::crypto::{chunk_hmac_tag, encrypt_chunk};
 use enigma_core::dedup::compute_hash;
 
 use crate::SharedState;
+use crate::{bandwidth, metrics};
+
+/// S3 limit on user-defined metadata: total bytes of all keys and values.
+pub const MAX_METADATA_SIZE: usize = 2 * 1024;
+
+/// Reject user-defined metadata over [`MAX_METADATA_SIZE`].
+pub fn validate_metadata(meta: &[(String, String)]) -> S3Result<()> {
+    let size: usize = meta.iter().map(|(k, v)| k.len() + v.len()).sum();
+    if size > MAX_METADATA_SIZE {
+        return Err(s3_error!(
+            MetadataTooLarge,
+            "Your metadata headers exceed the maximum allowed metadata size"
+        ));
+    }
+    Ok(())
+}
 
 /// Handle PutObject: chunk → encrypt → dedup → distribute → record metadata.
+/// The body is chunked as it streams in and each chunk is uploaded as soon
+/// as it is cut, so memory use is bounded by the chunker's window rather
+/// than the object size. Tags from an `x-amz-tagging` header and
+/// `x-amz-meta-*` metadata are recorded in the same transaction as the
+/// object.
 pub async fn handle_put_object(
     state: &SharedState,
     bucket: &str,
     key: &str,
     content_type: Option<String>,
+    tagging: Option<&str>,
+    metadata: &[(String, String)],
     body: Option<StreamingBlob>,
 ) -> S3Result<S3Response<PutObjectOutput>> {
-    // Read the full body
-    let data = read_body(body).await?;
-    let total_size = data.len() as u64;
+    // Reject invalid tags and metadata before any chunk is uploaded
+    let tags = match tagging {
+        Some(header) => crate::tagging::parse_tagging_header(header)?,
+        None => Vec::new(),
+    };
+    validate_metadata(metadata)?;
 
-    // Compute overall SHA-256 for the ETag
-    let etag = {
-        let mut hasher = Sha256::new();
-        hasher.update(&data);
-        format!("{:x}", hasher.finalize())
+    // Chunks stored while the body streams in are held by a pending object
+    let pending_id = {
+        let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
+        let ns_id = db
+            .get_namespace_id(bucket)
+            .map_err(|_| s3_error!(InternalError))?
+            .ok_or_else(|| s3_error!(NoSuchBucket))?;
+        db.insert_object_pending(ns_id, key)
+            .map_err(|_| s3_error!(InternalError))?
     };
 
-    // Chunk the data
-    let raw_chunks = chunk_data(&data);
-    let chunk_count = raw_chunks.len() as u32;
+    let (total_size, etag) = match stream_body(state, pending_id, body).await {
+        Ok(streamed) => streamed,
+        Err(e) => {
+            rollback_pending_object(state, pending_id).await;
+            return Err(e);
+        }
+    };
 
-    // Get namespace
-    let ns_id = {
+    // Insert object record + chunk mappings + tags atomically
+    let version_id = {
         let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
-        db.get_namespace_id(bucket)
-            .map_err(|_| s3_error!(InternalError))?
-            .ok_or_else(|| s3_error!(NoSuchBucket))?
+        db.begin_transaction()
+            .map_err(|_| s3_error!(InternalError))?;
+
+        let recorded = (|| -> enigma_core::error::Result<String> {
+            let object_id = db.commit_pending_object(
+                pending_id,
+                total_size,
+                &etag,
+                content_type.as_deref(),
+                &state.key_material.id,
+            )?;
+
+            if !tags.is_empty() {
+                db.set_object_tags(object_id, &tags)?;
+            }
+            if !metadata.is_empty() {
+                db.set_object_metadata(object_id, metadata)?;
+            }
+            db.get_object_version_id(object_id)
+        })();
+
+        match recorded {
+            Ok(version_id) => {
+                db.commit_transaction()
+                    .map_err(|_| s3_error!(InternalError))?;
+                state.queue_webhook(
+                    &db,
+                    "object.created",
+                    json!({ "bucket": bucket, "key": key, "version_id": version_id }),
+                );
+                Some(version_id)
+            }
+            Err(_) => {
+                let _ = db.rollback_transaction();
+                None
+            }
+        }
+    };
+    let Some(version_id) = version_id else {
+        rollback_pending_object(state, pending_id).await;
+        return Err(s3_error!(InternalError));
     };
 
-    // Process each chunk: encrypt, dedup, upload
-    let mut chunk_records = Vec::with_capacity(raw_chunks.len());
+    let output = PutObjectOutput {
+        e_tag: Some(format!("\"{etag}\"")),
+        version_id: (version_id != "null").then_some(version_id),
+        ..Default::default()
+    };
+    Ok(S3Response::new(output))
+}
 
-    let compression = &state.config.enigma.compression;
+pub(crate) const MAX_BODY_SIZE: usize = 5 * 1024 * 1024 * 1024; // 5 GB
 
-    for (idx, chunk_bytes) in raw_chunks.iter().enumerate() {
-        let chunk_hash = compute_hash(chunk_bytes);
-        let hash_hex = chunk_hash.to_hex();
-        let storage_key = chunk_hash.storage_key();
+/// Body pieces queued for the ETag hasher.
+const HASH_QUEUE_LEN: usize = 16;
 
-        // Compress (optional, before encryption)
-        let (data_to_encrypt, size_compressed) = if compression.enabled {
-            let compressed = compress_chunk(chunk_bytes, compression.level)
+/// Feed the body through a [`StreamChunker`], storing each chunk of the
+/// pending object as soon as it is cut, while a blocking task hashes the
+/// body for the ETag. Returns the body size and ETag.
+async fn stream_body(
+    state: &SharedState,
+    pending_id: i64,
+    body: Option<StreamingBlob>,
+) -> S3Result<(u64, String)> {
+    let (pieces, mut queued) = mpsc::channel::<Bytes>(HASH_QUEUE_LEN);
+    let hasher = tokio::task::spawn_blocking(move || {
+        let mut hasher = Sha256::new();
+        while let Some(piece) = queued.blocking_recv() {
+            hasher.update(&piece);
+        }
+        format!("{:x}", hasher.finalize())
+    });
+
+    let mut chunker = StreamChunker::default();
+    let mut total_size = 0u64;
+    let mut chunk_index = 0u32;
+    if let Some(mut body) = body {
+        while let Some(piece) = body.next().await {
+            let piece = piece.map_err(|_| s3_error!(InternalError))?;
+            total_size += piece.len() as u64;
+            if total_size > MAX_BODY_SIZE as u64 {
+                return Err(s3_error!(EntityTooLarge));
+            }
+            chunker.feed(&piece);
+            pieces
+                .send(piece)
+                .await
                 .map_err(|_| s3_error!(InternalError))?;
-            let sz = compressed.len() as u64;
-            (compressed, Some(sz))
-        } else {
-            (chunk_bytes.to_vec(), None)
+            while let Some(chunk) = chunker.next_chunk() {
+                store_pending_chunk(state, pending_id, chunk_index, chunk).await?;
+                chunk_index += 1;
+            }
+        }
+    }
+    if let Some(chunk) = chunker.finish() {
+        store_pending_chunk(state, pending_id, chunk_index, &chunk).await?;
+    }
+
+    drop(pieces);
+    let etag = hasher.await.map_err(|_| s3_error!(InternalError))?;
+    Ok((total_size, etag))
+}
+
+/// Store one chunk and record it as chunk `chunk_index` of a pending object.
+async fn store_pending_chunk(
+    state: &SharedState,
+    pending_id: i64,
+    chunk_index: u32,
+    chunk: &[u8],
+) -> S3Result<()> {
+    let hash_hex = store_chunk(state, chunk).await?;
+    let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
+    db.add_pending_object_chunk(pending_id, chunk_index, &hash_hex, chunk.len() as u64)
+        .map_err(|_| s3_error!(InternalError))
+}
+
+/// Drop a pending object after a failed upload and delete the chunks only
+/// it referenced.
+async fn rollback_pending_object(state: &SharedState, pending_id: i64) {
+    let to_delete = {
+        let Ok(db) = state.db.lock() else {
+            return;
         };
+        match db.rollback_pending_object(pending_id) {
+            Ok(to_delete) => to_delete,
+            Err(e) => {
+                tracing::warn!("Failed to roll back pending object {pending_id}: {e}");
+                return;
+            }
+        }
+    };
+    for (provider_id, storage_key) in to_delete {
+        if let Some(provider) = state.providers.get(&provider_id)
+            && let Err(e) = provider.delete_chunk(&storage_key).await
+        {
+            tracing::warn!("Failed to delete chunk {storage_key} from provider {provider_id}: {e}");
+        }
+    }
+}
+
+/// Compress (optionally), encrypt, dedup and upload one chunk. Returns the
+/// chunk's hash.
+pub(crate) async fn store_chunk(state: &SharedState, chunk_data: &[u8]) -> S3Result<String> {
+    let chunk_hash = compute_hash(chunk_data);
+    let hash_hex = chunk_hash.to_hex();
+    let storage_key = chunk_hash.storage_key();
+    let compression = &state.config.enigma.compression;
 
-        // Encrypt
-        let encrypted = encrypt_chunk(&data_to_encrypt, &chunk_hash, &state.key_material)
+    // Compress (optional, before encryption)
+    let compressed;
+    let (data_to_encrypt, size_compressed) = if compression.enabled {
+        compressed = compress(chunk_data, compression.algorithm, compression.level)
             .map_err(|_| s3_error!(InternalError))?;
+        (compressed.as_slice(), Some(compressed.len() as u64))
+    } else {
+        (chunk_data, None)
+    };
+
+    let encrypted = encrypt_chunk(data_to_encrypt, &chunk_hash, &state.key_material)
+        .map_err(|_| s3_error!(InternalError))?;
 
-        // Pick provider
-        let target_provider = state.distributor.next_provider();
+    let distributor = state.distributor.load();
+    let target_provider = distributor.next_provider();
 
-        // Dedup check + insert in DB
-        let is_new = {
-            let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
-            db.insert_or_dedup_chunk(
+    // Dedup check + insert in DB
+    let is_new = {
+        let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
+        let is_new = db
+            .insert_or_dedup_chunk(
                 &hash_hex,
                 &encrypted.nonce,
                 &state.key_material.id,
                 target_provider.id,
                 &storage_key,
-                chunk_bytes.len() as u64,
+                chunk_data.len() as u64,
                 encrypted.ciphertext.len() as u64,
                 size_compressed,
             )
-            .map_err(|_| s3_error!(InternalError))?
-        };
-
-        if is_new && let Some(provider) = state.providers.get(&target_provider.id) {
-            provider
-                .upload_chunk(&storage_key, &encrypted.ciphertext)
-                .await
-                .map_err(|_| s3_error!(InternalError))?;
-        }
-
-        chunk_records.push((hash_hex, idx as u32, chunk_bytes.len() as u64));
-    }
-
-    // Insert object record + chunk mappings
-    {
-        let mut offset = 0u64;
-        let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
-
-        let object_id = db
-            .insert_object(
-                ns_id,
-                key,
-                total_size,
-                &etag,
-                content_type.as_deref(),
-                chunk_count,
-                &state.key_material.id,
-            )
             .map_err(|_| s3_error!(InternalError))?;
-
-        for (hash_hex, chunk_index, size) in &chunk_records {
-            db.insert_object_chunk(object_id, hash_hex, *chunk_index, offset)
+        if is_new {
+            let tag = chunk_hmac_tag(&state.key_material, &storage_key, &encrypted.ciphertext);
+            db.set_chunk_hmac_tag(&hash_hex, &tag)
                 .map_err(|_| s3_error!(InternalError))?;
-            offset += size;
         }
-    }
-
-    let output = PutObjectOutput {
-        e_tag: Some(format!("\"{etag}\"")),
-        ..Default::default()
+        is_new
     };
-    Ok(S3Response::new(output))
-}
-
-const MAX_BODY_SIZE: usize = 5 * 1024 * 1024 * 1024; // 5 GB
 
-/// Read the full body from a StreamingBlob into a Vec<u8>.
-pub async fn read_body(body: Option<StreamingBlob>) -> S3Result<Vec<u8>> {
-    let Some(mut body) = body else {
-        return Ok(vec![]);
-    };
-    let mut data = Vec::new();
-    while let Some(chunk) = body.next().await {
-        let chunk = chunk.map_err(|_| s3_error!(InternalError))?;
-        data.extend_from_slice(&chunk);
-        if data.len() > MAX_BODY_SIZE {
-            return Err(s3_error!(EntityTooLarge));
+    if !is_new {
+        metrics::chunk_deduped();
+    } else if let Some(provider) = state.providers.get(&target_provider.id) {
+        let _permit = state.upload_semaphore.acquire().await;
+        bandwidth::throttle_upload(
+            &state.provider_limiters,
+            target_provider.id,
+            encrypted.ciphertext.len(),
+        )
+        .await;
+        if metrics::upload_chunk(provider.as_ref(), &storage_key, &encrypted.ciphertext)
+            .await
+            .is_err()
+        {
+            // Drop the record so the chunk is not deduplicated against
+            // ciphertext that never reached the provider
+            if let Ok(db) = state.db.lock() {
+                let _ = db.decrement_chunk_ref(&hash_hex);
+            }
+            return Err(s3_error!(InternalError));
         }
     }
-    Ok(data)
+
+    Ok(hash_hex)
 }
 
-/// Chunk data and return owned Vec<Vec<u8>> — used by multipart completion.
+const TARGET_CHUNK_SIZE: usize = 4 * 1024 * 1024; // 4MB
+pub const MAX_CHUNK_SIZE: usize = TARGET_CHUNK_SIZE * 4; // 16MB
+const MIN_CHUNK_SIZE: usize = TARGET_CHUNK_SIZE / 4; // 1MB
+
+/// Chunk data and return owned Vec<Vec<u8>> — used by the ops layer.
 pub fn chunk_data_owned(data: &[u8]) -> Vec<Vec<u8>> {
     chunk_data(data).into_iter().map(|s| s.to_vec()).collect()
 }
@@ -154,23 +301,24 @@ fn chunk_data(data: &[u8]) -> Vec<&[u8]> {
         return vec![];
     }
 
-    let target = 4 * 1024 * 1024; // 4MB
-    let max_size = target * 4; // 16MB
-
-    if data.len() <= max_size {
+    if data.len() <= MAX_CHUNK_SIZE {
         return vec![data];
     }
 
-    let min_size = target / 4; // 1MB
     let mut chunks = Vec::new();
     let mut offset = 0;
 
     while offset < data.len() {
         let remaining = data.len() - offset;
-        let chunk_size = if remaining <= max_size {
+        let chunk_size = if remaining <= MAX_CHUNK_SIZE {
             remaining
         } else {
-            find_boundary(&data[offset..], min_size, target, max_size)
+            find_boundary(
+                &data[offset..],
+                MIN_CHUNK_SIZE,
+                TARGET_CHUNK_SIZE,
+                MAX_CHUNK_SIZE,
+            )
         };
 
         chunks.push(&data[offset..offset + chunk_size]);
@@ -180,6 +328,71 @@ fn chunk_data(data: &[u8]) -> Vec<&[u8]> {
     chunks
 }
 
+/// [`chunk_data`] for data arriving in pieces: yields the same chunks as
+/// chunking everything at once, while buffering at most one maximum-size
+/// chunk plus the latest piece.
+#[derive(Default)]
+pub struct StreamChunker {
+    buf: Vec<u8>,
+    /// Start of the bytes not yet handed out as a chunk.
+    start: usize,
+}
+
+impl StreamChunker {
+    /// Append `data` and return the chunks it completes.
+    pub fn push(&mut self, data: &[u8]) -> Vec<Vec<u8>> {
+        self.feed(data);
+        let mut chunks = Vec::new();
+        while let Some(chunk) = self.next_chunk() {
+            chunks.push(chunk.to_vec());
+        }
+        chunks
+    }
+
+    /// Append `data`; completed chunks are then taken with [`next_chunk`].
+    ///
+    /// [`next_chunk`]: StreamChunker::next_chunk
+    pub fn feed(&mut self, data: &[u8]) {
+        self.buf.drain(..self.start);
+        self.start = 0;
+        // Grow by doubling, but never past the window a chunk can need, so
+        // the buffer stays within one maximum-size chunk plus a piece
+        let needed = self.buf.len() + data.len();
+        if needed > self.buf.capacity() {
+            let capacity = (self.buf.capacity() * 2)
+                .min(MAX_CHUNK_SIZE + data.len())
+                .max(needed);
+            self.buf.reserve_exact(capacity - self.buf.len());
+        }
+        self.buf.extend_from_slice(data);
+    }
+
+    /// The next completed chunk, borrowed from the buffer until the next
+    /// [`feed`](StreamChunker::feed).
+    pub fn next_chunk(&mut self) -> Option<&[u8]> {
+        // A boundary only looks at the next MAX_CHUNK_SIZE bytes, so it is
+        // final once more than that is buffered
+        if self.buf.len() - self.start <= MAX_CHUNK_SIZE {
+            return None;
+        }
+        let offset = self.start;
+        let chunk_size = find_boundary(
+            &self.buf[offset..],
+            MIN_CHUNK_SIZE,
+            TARGET_CHUNK_SIZE,
+            MAX_CHUNK_SIZE,
+        );
+        self.start += chunk_size;
+        Some(&self.buf[offset..offset + chunk_size])
+    }
+
+    /// The last chunk, if any data is left.
+    pub fn finish(mut self) -> Option<Vec<u8>> {
+        self.buf.drain(..self.start);
+        (!self.buf.is_empty()).then_some(self.buf)
+    }
+}
+
 /// Simple hash-based boundary finder for in-memory CDC.
 fn find_boundary(data: &[u8], min_size: usize, target: usize, max_size: usize) -> usize {
     let len = data.len().min(max_size);
@@ -1,17 +1,57 @@
+use s3s::auth::Credentials;
 use s3s::dto::*;
 use s3s::s3_error;
 use s3s::{S3, S3Request, S3Response, S3Result};
 
 use crate::SharedState;
+use crate::usage::UsageScope;
 
 /// The Enigma S3 service implementing the s3s S3 trait.
 pub struct EnigmaS3Service {
     pub state: SharedState,
+    /// Region reported by GetBucketLocation.
+    pub region: String,
 }
 
 impl EnigmaS3Service {
     pub fn new(state: SharedState) -> Self {
-        Self { state }
+        Self {
+            state,
+            region: "us-east-1".to_string(),
+        }
+    }
+
+    pub fn with_region(mut self, region: impl Into<String>) -> Self {
+        self.region = region.into();
+        self
+    }
+
+    fn require_bucket(&self, bucket: &str) -> S3Result<()> {
+        let db = self.state.db.lock().map_err(|_| s3_error!(InternalError))?;
+        if !db
+            .namespace_exists(bucket)
+            .map_err(|_| s3_error!(InternalError))?
+        {
+            return Err(s3_error!(NoSuchBucket));
+        }
+        Ok(())
+    }
+
+    /// Refuse to overwrite or delete an object version under legal hold,
+    /// or a locked one unless the caller asked for and may use a
+    /// governance bypass.
+    async fn check_object_lock(
+        &self,
+        credentials: Option<&Credentials>,
+        bypass_requested: bool,
+        bucket: &str,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> S3Result<()> {
+        let bypass =
+            crate::object_lock::may_bypass_governance(&self.state, credentials, bypass_requested)
+                .await?;
+        crate::object_lock::check_removable(&self.state, bucket, key, version_id, bypass)
     }
 }
 
@@ -23,6 +63,7 @@ impl S3 for EnigmaS3Service {
         &self,
         req: S3Request<CreateBucketInput>,
     ) -> S3Result<S3Response<CreateBucketOutput>> {
+        self.state.check_writable()?;
         let bucket = &req.input.bucket;
         tracing::info!("CreateBucket: {bucket}");
 
@@ -33,6 +74,18 @@ impl S3 for EnigmaS3Service {
         {
             return Err(s3_error!(BucketAlreadyOwnedByYou));
         }
+        // A soft-deleted bucket keeps its name until it is purged
+        let pending_delete = db
+            .list_namespaces_including_deleted()
+            .map_err(|_| s3_error!(InternalError))?
+            .into_iter()
+            .any(|(_, name, ..)| name == *bucket);
+        if pending_delete {
+            return Err(s3_error!(
+                BucketAlreadyExists,
+                "Bucket was deleted and can still be restored"
+            ));
+        }
         db.create_namespace(bucket)
             .map_err(|_| s3_error!(InternalError))?;
 
@@ -46,6 +99,7 @@ impl S3 for EnigmaS3Service {
         &self,
         req: S3Request<DeleteBucketInput>,
     ) -> S3Result<S3Response<DeleteBucketOutput>> {
+        self.state.check_writable()?;
         let bucket = &req.input.bucket;
         tracing::info!("DeleteBucket: {bucket}");
 
@@ -55,9 +109,9 @@ impl S3 for EnigmaS3Service {
             .map_err(|_| s3_error!(InternalError))?
             .ok_or_else(|| s3_error!(NoSuchBucket))?;
 
-        // Check if bucket is empty
+        // Check if bucket is empty (including old versions and delete markers)
         let count = db
-            .count_objects_with_prefix(ns_id, "")
+            .count_object_versions(ns_id)
             .map_err(|_| s3_error!(InternalError))?;
         if count > 0 {
             return Err(s3_error!(BucketNotEmpty));
@@ -75,26 +129,41 @@ impl S3 for EnigmaS3Service {
     ) -> S3Result<S3Response<HeadBucketOutput>> {
         let bucket = &req.input.bucket;
 
-        let db = self.state.db.lock().map_err(|_| s3_error!(InternalError))?;
-        if !db
-            .namespace_exists(bucket)
-            .map_err(|_| s3_error!(InternalError))?
-        {
-            return Err(s3_error!(NoSuchBucket));
-        }
+        let created_at = {
+            let db = self.state.db.lock().map_err(|_| s3_error!(InternalError))?;
+            db.namespace_created_at(bucket)
+                .map_err(|_| s3_error!(InternalError))?
+                .ok_or_else(|| s3_error!(NoSuchBucket))?
+        };
+        // Dates only: a bucket has no ETag
+        crate::get::Preconditions::from_date_headers(&req.headers).check("", &created_at)?;
 
         Ok(S3Response::new(HeadBucketOutput::default()))
     }
 
     async fn list_buckets(
         &self,
-        _req: S3Request<ListBucketsInput>,
+        req: S3Request<ListBucketsInput>,
     ) -> S3Result<S3Response<ListBucketsOutput>> {
+        // Tenants only see their own namespaces; no prefix means admin
+        let prefix = match (self.state.access_control.get(), &req.credentials) {
+            (Some(access), Some(credentials)) => access
+                .namespace_prefix(&credentials.access_key)
+                .await
+                .map_err(|e| s3_error!(InternalError, "namespace lookup failed: {e}"))?,
+            _ => None,
+        };
+
         let db = self.state.db.lock().map_err(|_| s3_error!(InternalError))?;
         let namespaces = db.list_namespaces().map_err(|_| s3_error!(InternalError))?;
 
         let buckets: Vec<Bucket> = namespaces
             .into_iter()
+            .filter(|(_id, name, _created_at)| {
+                prefix
+                    .as_deref()
+                    .is_none_or(|prefix| name.starts_with(prefix))
+            })
             .map(|(_id, name, _created_at)| Bucket {
                 bucket_region: None,
                 creation_date: None,
@@ -113,19 +182,264 @@ impl S3 for EnigmaS3Service {
         Ok(S3Response::new(output))
     }
 
+    // ── Bucket configuration ────────────────────────────────
+
+    async fn get_bucket_location(
+        &self,
+        req: S3Request<GetBucketLocationInput>,
+    ) -> S3Result<S3Response<GetBucketLocationOutput>> {
+        self.require_bucket(&req.input.bucket)?;
+
+        // Like AWS, buckets in us-east-1 report an empty location constraint
+        let location_constraint = (self.region != "us-east-1")
+            .then(|| BucketLocationConstraint::from(self.region.clone()));
+        Ok(S3Response::new(GetBucketLocationOutput {
+            location_constraint,
+        }))
+    }
+
+    async fn get_bucket_accelerate_configuration(
+        &self,
+        req: S3Request<GetBucketAccelerateConfigurationInput>,
+    ) -> S3Result<S3Response<GetBucketAccelerateConfigurationOutput>> {
+        self.require_bucket(&req.input.bucket)?;
+
+        // Transfer acceleration is never configured
+        Ok(S3Response::new(
+            GetBucketAccelerateConfigurationOutput::default(),
+        ))
+    }
+
+    async fn get_bucket_request_payment(
+        &self,
+        req: S3Request<GetBucketRequestPaymentInput>,
+    ) -> S3Result<S3Response<GetBucketRequestPaymentOutput>> {
+        self.require_bucket(&req.input.bucket)?;
+
+        Ok(S3Response::new(GetBucketRequestPaymentOutput {
+            payer: Some(Payer::from_static(Payer::BUCKET_OWNER)),
+        }))
+    }
+
+    async fn get_bucket_logging(
+        &self,
+        req: S3Request<GetBucketLoggingInput>,
+    ) -> S3Result<S3Response<GetBucketLoggingOutput>> {
+        crate::access_log::handle_get_bucket_logging(&self.state, &req.input.bucket).await
+    }
+
+    async fn put_bucket_logging(
+        &self,
+        req: S3Request<PutBucketLoggingInput>,
+    ) -> S3Result<S3Response<PutBucketLoggingOutput>> {
+        self.state.check_writable()?;
+        let bucket = req.input.bucket.clone();
+        tracing::info!("PutBucketLogging: {bucket}");
+
+        crate::access_log::handle_put_bucket_logging(
+            &self.state,
+            &bucket,
+            req.input.bucket_logging_status,
+        )
+        .await
+    }
+
+    #[cfg(feature = "inventory")]
+    async fn get_bucket_inventory_configuration(
+        &self,
+        req: S3Request<GetBucketInventoryConfigurationInput>,
+    ) -> S3Result<S3Response<GetBucketInventoryConfigurationOutput>> {
+        crate::inventory::handle_get_bucket_inventory_configuration(
+            &self.state,
+            &req.input.bucket,
+            &req.input.id,
+        )
+        .await
+    }
+
+    #[cfg(feature = "inventory")]
+    async fn put_bucket_inventory_configuration(
+        &self,
+        req: S3Request<PutBucketInventoryConfigurationInput>,
+    ) -> S3Result<S3Response<PutBucketInventoryConfigurationOutput>> {
+        self.state.check_writable()?;
+        let bucket = req.input.bucket.clone();
+        tracing::info!("PutBucketInventoryConfiguration: {bucket}");
+
+        crate::inventory::handle_put_bucket_inventory_configuration(
+            &self.state,
+            &bucket,
+            &req.input.id,
+            req.input.inventory_configuration,
+        )
+        .await
+    }
+
+    async fn get_bucket_encryption(
+        &self,
+        req: S3Request<GetBucketEncryptionInput>,
+    ) -> S3Result<S3Response<GetBucketEncryptionOutput>> {
+        self.require_bucket(&req.input.bucket)?;
+
+        // Every chunk is encrypted client-side before it reaches a provider
+        let rule = ServerSideEncryptionRule {
+            apply_server_side_encryption_by_default: Some(ServerSideEncryptionByDefault {
+                kms_master_key_id: None,
+                sse_algorithm: ServerSideEncryption::from_static(ServerSideEncryption::AES256),
+            }),
+            bucket_key_enabled: None,
+        };
+        Ok(S3Response::new(GetBucketEncryptionOutput {
+            server_side_encryption_configuration: Some(ServerSideEncryptionConfiguration {
+                rules: vec![rule],
+            }),
+        }))
+    }
+
     // ── Object operations ───────────────────────────────────
 
     async fn put_object(
         &self,
         req: S3Request<PutObjectInput>,
     ) -> S3Result<S3Response<PutObjectOutput>> {
+        self.state.check_writable()?;
         let bucket = req.input.bucket.clone();
         let key = req.input.key.clone();
         let content_type = req.input.content_type.map(|m| m.to_string());
+        let mut metadata: Vec<(String, String)> =
+            req.input.metadata.unwrap_or_default().into_iter().collect();
+        metadata.sort();
         tracing::info!("PutObject: {bucket}/{key}");
+        self.check_object_lock(
+            req.credentials.as_ref(),
+            crate::object_lock::bypass_requested(&req.headers),
+            &bucket,
+            &key,
+            None,
+        )
+        .await?;
 
-        crate::put::handle_put_object(&self.state, &bucket, &key, content_type, req.input.body)
-            .await
+        let usage = UsageScope::begin(&self.state, req.credentials.as_ref(), &bucket, &key)?;
+        if let Some(usage) = &usage
+            && let Some(length) = req.input.content_length
+        {
+            usage.check_quota(&self.state, length.max(0) as u64).await?;
+        }
+
+        let resp = crate::put::handle_put_object(
+            &self.state,
+            &bucket,
+            &key,
+            content_type,
+            req.input.tagging.as_deref(),
+            &metadata,
+            req.input.body,
+        )
+        .await?;
+        if let Some(usage) = usage {
+            usage.finish(&self.state).await;
+        }
+        Ok(resp)
+    }
+
+    async fn copy_object(
+        &self,
+        req: S3Request<CopyObjectInput>,
+    ) -> S3Result<S3Response<CopyObjectOutput>> {
+        self.state.check_writable()?;
+        let CopySource::Bucket {
+            bucket: src_bucket,
+            key: src_key,
+            version_id: src_version_id,
+        } = &req.input.copy_source
+        else {
+            return Err(s3_error!(
+                NotImplemented,
+                "Copying from an access point is not supported"
+            ));
+        };
+        let bucket = &req.input.bucket;
+        let key = &req.input.key;
+        tracing::info!("CopyObject: {src_bucket}/{src_key} -> {bucket}/{key}");
+
+        let replace_metadata = req
+            .input
+            .metadata_directive
+            .as_ref()
+            .is_some_and(|d| d.as_str() == MetadataDirective::REPLACE);
+        if !replace_metadata
+            && **src_bucket == **bucket
+            && **src_key == **key
+            && src_version_id.is_none()
+        {
+            return Err(s3_error!(
+                InvalidRequest,
+                "This copy request is illegal because it is trying to copy an object to itself without changing the object's metadata"
+            ));
+        }
+        let metadata = replace_metadata.then(|| {
+            let mut metadata: Vec<(String, String)> = req
+                .input
+                .metadata
+                .clone()
+                .unwrap_or_default()
+                .into_iter()
+                .collect();
+            metadata.sort();
+            metadata
+        });
+        if let Some(metadata) = &metadata {
+            crate::put::validate_metadata(metadata)?;
+        }
+
+        let size =
+            crate::get::lookup_object(&self.state, src_bucket, src_key, src_version_id.as_deref())
+                .await?
+                .size;
+        self.require_bucket(bucket)?;
+        self.check_object_lock(
+            req.credentials.as_ref(),
+            crate::object_lock::bypass_requested(&req.headers),
+            bucket,
+            key,
+            None,
+        )
+        .await?;
+
+        let usage = UsageScope::begin(&self.state, req.credentials.as_ref(), bucket, key)?;
+        if let Some(usage) = &usage {
+            usage.check_quota(&self.state, size).await?;
+        }
+
+        let copied = crate::ops::copy_object_server_side(
+            &self.state,
+            src_bucket,
+            src_key,
+            src_version_id.as_deref(),
+            bucket,
+            key,
+            metadata.as_deref(),
+        )
+        .map_err(|e| {
+            tracing::error!("CopyObject {src_bucket}/{src_key} -> {bucket}/{key} failed: {e:#}");
+            s3_error!(InternalError)
+        })?;
+        if let Some(usage) = usage {
+            usage.finish(&self.state).await;
+        }
+
+        let output = CopyObjectOutput {
+            copy_object_result: Some(CopyObjectResult {
+                e_tag: Some(format!("\"{}\"", copied.etag)),
+                last_modified: crate::get::last_modified(&copied.created_at),
+                ..Default::default()
+            }),
+            copy_source_version_id: (copied.source_version_id != "null")
+                .then_some(copied.source_version_id),
+            version_id: (copied.version_id != "null").then_some(copied.version_id),
+            ..Default::default()
+        };
+        Ok(S3Response::new(output))
     }
 
     async fn get_object(
@@ -134,9 +448,16 @@ impl S3 for EnigmaS3Service {
     ) -> S3Result<S3Response<GetObjectOutput>> {
         let bucket = &req.input.bucket;
         let key = &req.input.key;
+        let version_id = req.input.version_id.as_deref();
         tracing::info!("GetObject: {bucket}/{key}");
 
-        crate::get::handle_get_object(&self.state, bucket, key).await
+        let preconditions = crate::get::Preconditions {
+            if_match: req.input.if_match.clone(),
+            if_none_match: req.input.if_none_match.clone(),
+            if_modified_since: req.input.if_modified_since.clone(),
+            if_unmodified_since: req.input.if_unmodified_since.clone(),
+        };
+        crate::get::handle_get_object(&self.state, bucket, key, version_id, &preconditions).await
     }
 
     async fn head_object(
@@ -145,59 +466,268 @@ impl S3 for EnigmaS3Service {
     ) -> S3Result<S3Response<HeadObjectOutput>> {
         let bucket = &req.input.bucket;
         let key = &req.input.key;
+        let version_id = req.input.version_id.as_deref();
+        let preconditions = crate::get::Preconditions {
+            if_match: req.input.if_match.clone(),
+            if_none_match: req.input.if_none_match.clone(),
+            if_modified_since: req.input.if_modified_since.clone(),
+            if_unmodified_since: req.input.if_unmodified_since.clone(),
+        };
 
-        let db = self.state.db.lock().map_err(|_| s3_error!(InternalError))?;
-        let ns_id = db
-            .get_namespace_id(bucket)
-            .map_err(|_| s3_error!(InternalError))?
-            .ok_or_else(|| s3_error!(NoSuchBucket))?;
-
-        let obj = db
-            .get_object(ns_id, key)
-            .map_err(|_| s3_error!(InternalError))?
-            .ok_or_else(|| s3_error!(NoSuchKey))?;
-
-        let (_obj_id, size, etag, content_type, _chunk_count, _key_id, _last_modified) = obj;
+        crate::get::handle_head_object(&self.state, bucket, key, version_id, &preconditions).await
+    }
 
-        let output = HeadObjectOutput {
-            content_length: Some(size as i64),
-            e_tag: Some(format!("\"{etag}\"")),
-            content_type: content_type.and_then(|ct| ct.parse().ok()),
-            ..Default::default()
-        };
+    async fn get_object_attributes(
+        &self,
+        req: S3Request<GetObjectAttributesInput>,
+    ) -> S3Result<S3Response<GetObjectAttributesOutput>> {
+        let bucket = &req.input.bucket;
+        let key = &req.input.key;
+        let version_id = req.input.version_id.as_deref();
+        let max_parts = req.input.max_parts.unwrap_or(1000).clamp(1, 1000);
+        tracing::info!("GetObjectAttributes: {bucket}/{key}");
 
-        Ok(S3Response::new(output))
+        crate::get::handle_get_object_attributes(
+            &self.state,
+            bucket,
+            key,
+            version_id,
+            &req.input.object_attributes,
+            max_parts,
+            req.input.part_number_marker.as_deref(),
+        )
+        .await
     }
 
     async fn delete_object(
         &self,
         req: S3Request<DeleteObjectInput>,
     ) -> S3Result<S3Response<DeleteObjectOutput>> {
+        self.state.check_writable()?;
         let bucket = &req.input.bucket;
         let key = &req.input.key;
+        let version_id = req.input.version_id.as_deref();
         tracing::info!("DeleteObject: {bucket}/{key}");
+        self.check_object_lock(
+            req.credentials.as_ref(),
+            req.input.bypass_governance_retention.unwrap_or(false),
+            bucket,
+            key,
+            version_id,
+        )
+        .await?;
 
-        let to_delete = {
-            let db = self.state.db.lock().map_err(|_| s3_error!(InternalError))?;
-            let ns_id = db
-                .get_namespace_id(bucket)
-                .map_err(|_| s3_error!(InternalError))?
-                .ok_or_else(|| s3_error!(NoSuchBucket))?;
+        let usage = UsageScope::begin(&self.state, req.credentials.as_ref(), bucket, key)?;
+        let resp =
+            crate::versioning::handle_delete_object(&self.state, bucket, key, version_id).await?;
+        if let Some(usage) = usage {
+            usage.finish(&self.state).await;
+        }
+        Ok(resp)
+    }
 
-            db.delete_object_by_ns_key(ns_id, key)
-                .map_err(|_| s3_error!(InternalError))?
-        };
+    // ── Object tagging ──────────────────────────────────────
 
-        // Delete chunks from storage providers
-        for (provider_id, storage_key) in to_delete {
-            if let Some(provider) = self.state.providers.get(&provider_id)
-                && let Err(e) = provider.delete_chunk(&storage_key).await
-            {
-                tracing::warn!("Failed to delete chunk {storage_key}: {e}");
-            }
-        }
+    async fn get_object_tagging(
+        &self,
+        req: S3Request<GetObjectTaggingInput>,
+    ) -> S3Result<S3Response<GetObjectTaggingOutput>> {
+        let bucket = &req.input.bucket;
+        let key = &req.input.key;
+        tracing::info!("GetObjectTagging: {bucket}/{key}");
+
+        crate::tagging::handle_get_object_tagging(&self.state, bucket, key).await
+    }
+
+    async fn put_object_tagging(
+        &self,
+        req: S3Request<PutObjectTaggingInput>,
+    ) -> S3Result<S3Response<PutObjectTaggingOutput>> {
+        self.state.check_writable()?;
+        let bucket = req.input.bucket.clone();
+        let key = req.input.key.clone();
+        tracing::info!("PutObjectTagging: {bucket}/{key}");
+
+        crate::tagging::handle_put_object_tagging(&self.state, &bucket, &key, req.input.tagging)
+            .await
+    }
+
+    async fn delete_object_tagging(
+        &self,
+        req: S3Request<DeleteObjectTaggingInput>,
+    ) -> S3Result<S3Response<DeleteObjectTaggingOutput>> {
+        self.state.check_writable()?;
+        let bucket = &req.input.bucket;
+        let key = &req.input.key;
+        tracing::info!("DeleteObjectTagging: {bucket}/{key}");
+
+        crate::tagging::handle_delete_object_tagging(&self.state, bucket, key).await
+    }
+
+    // ── Bucket tagging ──────────────────────────────────────
+
+    async fn get_bucket_tagging(
+        &self,
+        req: S3Request<GetBucketTaggingInput>,
+    ) -> S3Result<S3Response<GetBucketTaggingOutput>> {
+        let bucket = &req.input.bucket;
+        tracing::info!("GetBucketTagging: {bucket}");
+
+        crate::tagging::handle_get_bucket_tagging(&self.state, bucket).await
+    }
+
+    async fn put_bucket_tagging(
+        &self,
+        req: S3Request<PutBucketTaggingInput>,
+    ) -> S3Result<S3Response<PutBucketTaggingOutput>> {
+        self.state.check_writable()?;
+        let bucket = req.input.bucket.clone();
+        tracing::info!("PutBucketTagging: {bucket}");
+
+        crate::tagging::handle_put_bucket_tagging(&self.state, &bucket, req.input.tagging).await
+    }
+
+    async fn delete_bucket_tagging(
+        &self,
+        req: S3Request<DeleteBucketTaggingInput>,
+    ) -> S3Result<S3Response<DeleteBucketTaggingOutput>> {
+        self.state.check_writable()?;
+        let bucket = &req.input.bucket;
+        tracing::info!("DeleteBucketTagging: {bucket}");
+
+        crate::tagging::handle_delete_bucket_tagging(&self.state, bucket).await
+    }
+
+    // ── Object lock ─────────────────────────────────────────
 
-        Ok(S3Response::new(DeleteObjectOutput::default()))
+    async fn put_object_retention(
+        &self,
+        req: S3Request<PutObjectRetentionInput>,
+    ) -> S3Result<S3Response<PutObjectRetentionOutput>> {
+        self.state.check_writable()?;
+        let bucket = req.input.bucket.clone();
+        let key = req.input.key.clone();
+        tracing::info!("PutObjectRetention: {bucket}/{key}");
+
+        let bypass = crate::object_lock::may_bypass_governance(
+            &self.state,
+            req.credentials.as_ref(),
+            req.input.bypass_governance_retention.unwrap_or(false),
+        )
+        .await?;
+        crate::object_lock::handle_put_object_retention(
+            &self.state,
+            &bucket,
+            &key,
+            req.input.version_id.as_deref(),
+            req.input.retention,
+            bypass,
+        )
+        .await
+    }
+
+    async fn get_object_retention(
+        &self,
+        req: S3Request<GetObjectRetentionInput>,
+    ) -> S3Result<S3Response<GetObjectRetentionOutput>> {
+        let bucket = &req.input.bucket;
+        let key = &req.input.key;
+        tracing::info!("GetObjectRetention: {bucket}/{key}");
+
+        crate::object_lock::handle_get_object_retention(
+            &self.state,
+            bucket,
+            key,
+            req.input.version_id.as_deref(),
+        )
+        .await
+    }
+
+    async fn put_object_legal_hold(
+        &self,
+        req: S3Request<PutObjectLegalHoldInput>,
+    ) -> S3Result<S3Response<PutObjectLegalHoldOutput>> {
+        self.state.check_writable()?;
+        let bucket = req.input.bucket.clone();
+        let key = req.input.key.clone();
+        tracing::info!("PutObjectLegalHold: {bucket}/{key}");
+
+        crate::object_lock::handle_put_object_legal_hold(
+            &self.state,
+            req.credentials.as_ref(),
+            &bucket,
+            &key,
+            req.input.version_id.as_deref(),
+            req.input.legal_hold,
+        )
+        .await
+    }
+
+    async fn get_object_legal_hold(
+        &self,
+        req: S3Request<GetObjectLegalHoldInput>,
+    ) -> S3Result<S3Response<GetObjectLegalHoldOutput>> {
+        let bucket = &req.input.bucket;
+        let key = &req.input.key;
+        tracing::info!("GetObjectLegalHold: {bucket}/{key}");
+
+        crate::object_lock::handle_get_object_legal_hold(
+            &self.state,
+            bucket,
+            key,
+            req.input.version_id.as_deref(),
+        )
+        .await
+    }
+
+    // ── Versioning ──────────────────────────────────────────
+
+    async fn put_bucket_versioning(
+        &self,
+        req: S3Request<PutBucketVersioningInput>,
+    ) -> S3Result<S3Response<PutBucketVersioningOutput>> {
+        self.state.check_writable()?;
+        let bucket = req.input.bucket.clone();
+        tracing::info!("PutBucketVersioning: {bucket}");
+
+        crate::versioning::handle_put_bucket_versioning(
+            &self.state,
+            &bucket,
+            req.input.versioning_configuration,
+        )
+        .await
+    }
+
+    async fn get_bucket_versioning(
+        &self,
+        req: S3Request<GetBucketVersioningInput>,
+    ) -> S3Result<S3Response<GetBucketVersioningOutput>> {
+        let bucket = &req.input.bucket;
+
+        crate::versioning::handle_get_bucket_versioning(&self.state, bucket).await
+    }
+
+    async fn list_object_versions(
+        &self,
+        req: S3Request<ListObjectVersionsInput>,
+    ) -> S3Result<S3Response<ListObjectVersionsOutput>> {
+        let bucket = &req.input.bucket;
+        let prefix = req.input.prefix.as_deref().unwrap_or("");
+        let key_marker = req.input.key_marker.as_deref().unwrap_or("");
+        let version_id_marker = req.input.version_id_marker.as_deref().unwrap_or("");
+        let max_keys = req.input.max_keys.unwrap_or(1000);
+
+        tracing::info!("ListObjectVersions: {bucket} prefix={prefix}");
+
+        crate::versioning::handle_list_object_versions(
+            &self.state,
+            bucket,
+            prefix,
+            key_marker,
+            version_id_marker,
+            max_keys as u32,
+        )
+        .await
     }
 
     // ── List operations ─────────────────────────────────────
@@ -227,12 +757,36 @@ impl S3 for EnigmaS3Service {
         .await
     }
 
+    async fn list_objects(
+        &self,
+        req: S3Request<ListObjectsInput>,
+    ) -> S3Result<S3Response<ListObjectsOutput>> {
+        let bucket = &req.input.bucket;
+        let prefix = req.input.prefix.as_deref().unwrap_or("");
+        let max_keys = req.input.max_keys.unwrap_or(1000);
+        let marker = req.input.marker.as_deref().unwrap_or("");
+        let delimiter = req.input.delimiter.as_deref().unwrap_or("");
+
+        tracing::info!("ListObjects: {bucket} prefix={prefix}");
+
+        crate::list::handle_list_objects(
+            &self.state,
+            bucket,
+            prefix,
+            delimiter,
+            max_keys as u32,
+            marker,
+        )
+        .await
+    }
+
     // ── Multipart operations ────────────────────────────────
 
     async fn create_multipart_upload(
         &self,
         req: S3Request<CreateMultipartUploadInput>,
     ) -> S3Result<S3Response<CreateMultipartUploadOutput>> {
+        self.state.check_writable()?;
         let bucket = &req.input.bucket;
         let key = &req.input.key;
         tracing::info!("CreateMultipartUpload: {bucket}/{key}");
@@ -244,6 +798,7 @@ impl S3 for EnigmaS3Service {
         &self,
         req: S3Request<UploadPartInput>,
     ) -> S3Result<S3Response<UploadPartOutput>> {
+        self.state.check_writable()?;
         let upload_id = &req.input.upload_id;
         let part_number = req.input.part_number;
         tracing::info!("UploadPart: upload_id={upload_id} part={part_number}");
@@ -256,26 +811,69 @@ impl S3 for EnigmaS3Service {
         &self,
         req: S3Request<CompleteMultipartUploadInput>,
     ) -> S3Result<S3Response<CompleteMultipartUploadOutput>> {
+        self.state.check_writable()?;
         let bucket = &req.input.bucket;
         let key = &req.input.key;
         let upload_id = &req.input.upload_id;
         tracing::info!("CompleteMultipartUpload: {bucket}/{key} upload_id={upload_id}");
+        self.check_object_lock(
+            req.credentials.as_ref(),
+            crate::object_lock::bypass_requested(&req.headers),
+            bucket,
+            key,
+            None,
+        )
+        .await?;
 
-        crate::multipart::handle_complete_multipart_upload(&self.state, bucket, key, upload_id)
-            .await
+        let usage = UsageScope::begin(&self.state, req.credentials.as_ref(), bucket, key)?;
+        if let Some(usage) = &usage {
+            let size = crate::multipart::uploaded_size(&self.state, upload_id)?;
+            usage.check_quota(&self.state, size).await?;
+        }
+
+        let resp =
+            crate::multipart::handle_complete_multipart_upload(&self.state, bucket, key, upload_id)
+                .await?;
+        if let Some(usage) = usage {
+            usage.finish(&self.state).await;
+        }
+        Ok(resp)
     }
 
     async fn abort_multipart_upload(
         &self,
         req: S3Request<AbortMultipartUploadInput>,
     ) -> S3Result<S3Response<AbortMultipartUploadOutput>> {
+        self.state.check_writable()?;
         let upload_id = &req.input.upload_id;
         tracing::info!("AbortMultipartUpload: upload_id={upload_id}");
 
-        let db = self.state.db.lock().map_err(|_| s3_error!(InternalError))?;
-        db.abort_multipart_upload(upload_id)
+        crate::multipart::abort_upload(&self.state, upload_id)
+            .await
             .map_err(|_| s3_error!(InternalError))?;
 
         Ok(S3Response::new(AbortMultipartUploadOutput::default()))
     }
+
+    async fn list_multipart_uploads(
+        &self,
+        req: S3Request<ListMultipartUploadsInput>,
+    ) -> S3Result<S3Response<ListMultipartUploadsOutput>> {
+        let bucket = &req.input.bucket;
+        let prefix = req.input.prefix.as_deref().unwrap_or("");
+        let key_marker = req.input.key_marker.as_deref().unwrap_or("");
+        let upload_id_marker = req.input.upload_id_marker.as_deref().unwrap_or("");
+        let max_uploads = req.input.max_uploads.unwrap_or(1000).clamp(1, 1000);
+        tracing::info!("ListMultipartUploads: {bucket} prefix={prefix}");
+
+        crate::multipart::handle_list_multipart_uploads(
+            &self.state,
+            bucket,
+            prefix,
+            key_marker,
+            upload_id_marker,
+            max_uploads as u32,
+        )
+        .await
+    }
 }
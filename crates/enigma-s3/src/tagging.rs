@@ -0,0 +1,214 @@
+use enigma_core::tagging::{MAX_BUCKET_TAGS, MAX_OBJECT_TAGS, TagSetError, validate_tag_set};
+use s3s::dto::*;
+use s3s::s3_error;
+use s3s::{S3Error, S3Response, S3Result};
+
+use crate::SharedState;
+
+/// Handle GetObjectTagging: return the tag set of an existing object.
+pub async fn handle_get_object_tagging(
+    state: &SharedState,
+    bucket: &str,
+    key: &str,
+) -> S3Result<S3Response<GetObjectTaggingOutput>> {
+    let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
+    let object_id = lookup_object_id(&db, bucket, key)?;
+    let tags = db
+        .get_object_tags(object_id)
+        .map_err(|_| s3_error!(InternalError))?;
+
+    let output = GetObjectTaggingOutput {
+        tag_set: tags
+            .into_iter()
+            .map(|(key, value)| Tag {
+                key: Some(key),
+                value: Some(value),
+            })
+            .collect(),
+        ..Default::default()
+    };
+    Ok(S3Response::new(output))
+}
+
+/// Handle PutObjectTagging: replace the tag set of an existing object.
+pub async fn handle_put_object_tagging(
+    state: &SharedState,
+    bucket: &str,
+    key: &str,
+    tagging: Tagging,
+) -> S3Result<S3Response<PutObjectTaggingOutput>> {
+    let tags = tagging
+        .tag_set
+        .into_iter()
+        .map(|tag| (tag.key.unwrap_or_default(), tag.value.unwrap_or_default()))
+        .collect::<Vec<_>>();
+    validate_tags(&tags)?;
+
+    let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
+    let object_id = lookup_object_id(&db, bucket, key)?;
+    db.set_object_tags(object_id, &tags)
+        .map_err(|_| s3_error!(InternalError))?;
+
+    Ok(S3Response::new(PutObjectTaggingOutput::default()))
+}
+
+/// Handle DeleteObjectTagging: remove all tags from an existing object.
+pub async fn handle_delete_object_tagging(
+    state: &SharedState,
+    bucket: &str,
+    key: &str,
+) -> S3Result<S3Response<DeleteObjectTaggingOutput>> {
+    let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
+    let object_id = lookup_object_id(&db, bucket, key)?;
+    db.set_object_tags(object_id, &[])
+        .map_err(|_| s3_error!(InternalError))?;
+
+    Ok(S3Response::new(DeleteObjectTaggingOutput::default()))
+}
+
+/// Handle GetBucketTagging: return the tag set of a bucket, or
+/// NoSuchTagSet when it has none.
+pub async fn handle_get_bucket_tagging(
+    state: &SharedState,
+    bucket: &str,
+) -> S3Result<S3Response<GetBucketTaggingOutput>> {
+    let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
+    let ns_id = lookup_namespace_id(&db, bucket)?;
+    let tags = db
+        .get_namespace_tags(ns_id)
+        .map_err(|_| s3_error!(InternalError))?;
+    if tags.is_empty() {
+        return Err(s3_error!(NoSuchTagSet));
+    }
+
+    let output = GetBucketTaggingOutput {
+        tag_set: tags
+            .into_iter()
+            .map(|(key, value)| Tag {
+                key: Some(key),
+                value: Some(value),
+            })
+            .collect(),
+    };
+    Ok(S3Response::new(output))
+}
+
+/// Handle PutBucketTagging: replace the tag set of a bucket.
+pub async fn handle_put_bucket_tagging(
+    state: &SharedState,
+    bucket: &str,
+    tagging: Tagging,
+) -> S3Result<S3Response<PutBucketTaggingOutput>> {
+    let tags = tagging
+        .tag_set
+        .into_iter()
+        .map(|tag| (tag.key.unwrap_or_default(), tag.value.unwrap_or_default()))
+        .collect::<Vec<_>>();
+    validate_bucket_tags(&tags)?;
+
+    let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
+    let ns_id = lookup_namespace_id(&db, bucket)?;
+    db.set_namespace_tags(ns_id, &tags)
+        .map_err(|_| s3_error!(InternalError))?;
+
+    Ok(S3Response::new(PutBucketTaggingOutput::default()))
+}
+
+/// Handle DeleteBucketTagging: remove all tags from a bucket.
+pub async fn handle_delete_bucket_tagging(
+    state: &SharedState,
+    bucket: &str,
+) -> S3Result<S3Response<DeleteBucketTaggingOutput>> {
+    let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
+    let ns_id = lookup_namespace_id(&db, bucket)?;
+    db.set_namespace_tags(ns_id, &[])
+        .map_err(|_| s3_error!(InternalError))?;
+
+    Ok(S3Response::new(DeleteBucketTaggingOutput::default()))
+}
+
+/// Parse an `x-amz-tagging` header (URL query encoding: `k1=v1&k2=v2`).
+pub fn parse_tagging_header(header: &str) -> S3Result<Vec<(String, String)>> {
+    let mut tags = Vec::new();
+    for pair in header.split('&').filter(|p| !p.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let key = percent_decode(key).ok_or_else(|| s3_error!(InvalidTag, "Malformed tag key"))?;
+        let value =
+            percent_decode(value).ok_or_else(|| s3_error!(InvalidTag, "Malformed tag value"))?;
+        tags.push((key, value));
+    }
+    validate_tags(&tags)?;
+    Ok(tags)
+}
+
+/// Enforce the S3 tag-set constraints: at most 10 tags, unique non-empty keys
+/// of up to 128 characters, values of up to 256 characters.
+pub fn validate_tags(tags: &[(String, String)]) -> S3Result<()> {
+    validate_tag_set(tags, MAX_OBJECT_TAGS).map_err(|e| invalid_tag(e, "Object", MAX_OBJECT_TAGS))
+}
+
+/// Enforce the S3 bucket tag-set constraints: as for objects, but up to 50
+/// tags.
+pub fn validate_bucket_tags(tags: &[(String, String)]) -> S3Result<()> {
+    validate_tag_set(tags, MAX_BUCKET_TAGS).map_err(|e| invalid_tag(e, "Bucket", MAX_BUCKET_TAGS))
+}
+
+/// InvalidTag error for a refused tag set of `kind` tags, at most `max_tags`.
+fn invalid_tag(e: TagSetError, kind: &str, max_tags: usize) -> S3Error {
+    match e {
+        TagSetError::TooMany => {
+            s3_error!(InvalidTag, "{kind} tags cannot be greater than {max_tags}")
+        }
+        TagSetError::InvalidKey => s3_error!(InvalidTag, "The TagKey you have provided is invalid"),
+        TagSetError::InvalidValue => {
+            s3_error!(InvalidTag, "The TagValue you have provided is invalid")
+        }
+        TagSetError::DuplicateKey => {
+            s3_error!(InvalidTag, "Cannot provide multiple Tags with the same key")
+        }
+    }
+}
+
+fn lookup_namespace_id(db: &enigma_core::manifest::ManifestDb, bucket: &str) -> S3Result<i64> {
+    db.get_namespace_id(bucket)
+        .map_err(|_| s3_error!(InternalError))?
+        .ok_or_else(|| s3_error!(NoSuchBucket))
+}
+
+fn lookup_object_id(
+    db: &enigma_core::manifest::ManifestDb,
+    bucket: &str,
+    key: &str,
+) -> S3Result<i64> {
+    let ns_id = lookup_namespace_id(db, bucket)?;
+    let (object_id, ..) = db
+        .get_object(ns_id, key)
+        .map_err(|_| s3_error!(InternalError))?
+        .ok_or_else(|| s3_error!(NoSuchKey))?;
+    Ok(object_id)
+}
+
+/// Decode `%XX` escapes and `+` (space) in a query-string component.
+fn percent_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+                out.push(u8::from_str_radix(hex, 16).ok()?);
+                i += 3;
+            }
+            b'+' => {
+                out.push(b' ');
+                i += 1;
+            }
+            b => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(out).ok()
+}
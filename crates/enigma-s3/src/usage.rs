@@ -0,0 +1,145 @@
+//! Per-user storage accounting for S3 writes.
+//!
+//! Callers are identified by the access key their request was signed with;
+//! an [`UsageAccounting`] implementation maps it to a user, tracks what that
+//! user stores in each bucket and enforces their quota.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use s3s::auth::Credentials;
+use s3s::{S3Error, S3ErrorCode, S3Result, s3_error};
+
+use crate::SharedState;
+
+#[async_trait]
+pub trait UsageAccounting: Send + Sync {
+    /// Bytes the owner of `access_key` may still store; `None` for unlimited.
+    async fn remaining_quota(&self, access_key: &str) -> anyhow::Result<Option<u64>>;
+
+    /// Adjust the bytes and objects the owner of `access_key` stores in
+    /// `namespace`.
+    async fn record_usage(
+        &self,
+        access_key: &str,
+        namespace: &str,
+        delta_bytes: i64,
+        delta_objects: i32,
+    ) -> anyhow::Result<()>;
+}
+
+/// Usage of one key around a write: taken before the write, compared with
+/// the usage after it.
+pub(crate) struct UsageScope {
+    accounting: Arc<dyn UsageAccounting>,
+    access_key: String,
+    bucket: String,
+    key: String,
+    /// (bytes, versions) stored under the key before the write.
+    before: (u64, u64),
+}
+
+impl UsageScope {
+    /// `None` when accounting is off or the request is anonymous.
+    pub(crate) fn begin(
+        state: &SharedState,
+        credentials: Option<&Credentials>,
+        bucket: &str,
+        key: &str,
+    ) -> S3Result<Option<Self>> {
+        let (Some(accounting), Some(credentials)) = (state.usage.get(), credentials) else {
+            return Ok(None);
+        };
+        Ok(Some(Self {
+            accounting: accounting.clone(),
+            access_key: credentials.access_key.clone(),
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            before: key_usage(state, bucket, key)?,
+        }))
+    }
+
+    /// Fail with 413 when storing `incoming` bytes would take the caller over
+    /// their quota. In an unversioned bucket the current object is replaced,
+    /// so its size is freed.
+    pub(crate) async fn check_quota(&self, state: &SharedState, incoming: u64) -> S3Result<()> {
+        let remaining = self
+            .accounting
+            .remaining_quota(&self.access_key)
+            .await
+            .map_err(|e| s3_error!(InternalError, "quota lookup failed: {e}"))?;
+        let Some(remaining) = remaining else {
+            return Ok(());
+        };
+        let replaced = if versioning_off(state, &self.bucket)? {
+            self.before.0
+        } else {
+            0
+        };
+        if incoming.saturating_sub(replaced) > remaining {
+            return Err(quota_exceeded());
+        }
+        Ok(())
+    }
+
+    /// Record the change in what is stored under the key. Failures are
+    /// logged; the write itself already succeeded.
+    pub(crate) async fn finish(self, state: &SharedState) {
+        let after = match key_usage(state, &self.bucket, &self.key) {
+            Ok(after) => after,
+            Err(e) => {
+                tracing::warn!("Usage lookup failed for {}/{}: {e}", self.bucket, self.key);
+                return;
+            }
+        };
+        let delta_bytes = after.0 as i64 - self.before.0 as i64;
+        let delta_objects = after.1 as i32 - self.before.1 as i32;
+        if delta_bytes == 0 && delta_objects == 0 {
+            return;
+        }
+        if let Err(e) = self
+            .accounting
+            .record_usage(&self.access_key, &self.bucket, delta_bytes, delta_objects)
+            .await
+        {
+            tracing::warn!("Failed to record storage usage for {}: {e}", self.bucket);
+        }
+    }
+}
+
+/// (bytes, versions) stored under a key; nothing for an unknown bucket.
+fn key_usage(state: &SharedState, bucket: &str, key: &str) -> S3Result<(u64, u64)> {
+    let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
+    let Some(ns_id) = db
+        .get_namespace_id(bucket)
+        .map_err(|_| s3_error!(InternalError))?
+    else {
+        return Ok((0, 0));
+    };
+    db.object_key_usage(ns_id, key)
+        .map_err(|_| s3_error!(InternalError))
+}
+
+/// Whether versioning was never turned on for the bucket.
+fn versioning_off(state: &SharedState, bucket: &str) -> S3Result<bool> {
+    let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
+    let Some(ns_id) = db
+        .get_namespace_id(bucket)
+        .map_err(|_| s3_error!(InternalError))?
+    else {
+        return Ok(true);
+    };
+    let (enabled, suspended) = db
+        .get_namespace_versioning(ns_id)
+        .map_err(|_| s3_error!(InternalError))?;
+    Ok(!enabled && !suspended)
+}
+
+fn quota_exceeded() -> S3Error {
+    let mut err = S3Error::with_message(
+        S3ErrorCode::Custom("QuotaExceeded".into()),
+        "Storing this object would exceed your storage quota",
+    );
+    err.set_status_code(http::StatusCode::PAYLOAD_TOO_LARGE);
+    err
+}
@@ -0,0 +1,208 @@
+use s3s::dto::*;
+use s3s::s3_error;
+use s3s::{S3Response, S3Result};
+use serde_json::json;
+
+use enigma_core::manifest::ManifestDb;
+use enigma_core::types::VersioningStatus;
+
+use crate::SharedState;
+use crate::metrics;
+
+fn namespace_id(db: &ManifestDb, bucket: &str) -> S3Result<i64> {
+    db.get_namespace_id(bucket)
+        .map_err(|_| s3_error!(InternalError))?
+        .ok_or_else(|| s3_error!(NoSuchBucket))
+}
+
+/// Handle PutBucketVersioning: `Enabled` or `Suspended`. Once enabled, a
+/// bucket can be suspended but not returned to the unversioned state.
+pub async fn handle_put_bucket_versioning(
+    state: &SharedState,
+    bucket: &str,
+    config: VersioningConfiguration,
+) -> S3Result<S3Response<PutBucketVersioningOutput>> {
+    let enable = match config.status.as_ref().map(|s| s.as_str()) {
+        Some(BucketVersioningStatus::ENABLED) => true,
+        Some(BucketVersioningStatus::SUSPENDED) => false,
+        _ => {
+            return Err(s3_error!(
+                MalformedXML,
+                "Versioning status must be Enabled or Suspended"
+            ));
+        }
+    };
+
+    let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
+    let updated = if enable {
+        db.enable_bucket_versioning(bucket)
+    } else {
+        db.suspend_bucket_versioning(bucket)
+    }
+    .map_err(|_| s3_error!(InternalError))?;
+    if !updated {
+        return Err(s3_error!(NoSuchBucket));
+    }
+
+    Ok(S3Response::new(PutBucketVersioningOutput::default()))
+}
+
+/// Handle GetBucketVersioning. A bucket that was never versioned has no status.
+pub async fn handle_get_bucket_versioning(
+    state: &SharedState,
+    bucket: &str,
+) -> S3Result<S3Response<GetBucketVersioningOutput>> {
+    let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
+    let status = match db
+        .get_bucket_versioning_status(bucket)
+        .map_err(|_| s3_error!(InternalError))?
+        .ok_or_else(|| s3_error!(NoSuchBucket))?
+    {
+        VersioningStatus::Enabled => Some(BucketVersioningStatus::ENABLED),
+        VersioningStatus::Suspended => Some(BucketVersioningStatus::SUSPENDED),
+        VersioningStatus::Unversioned => None,
+    };
+
+    let output = GetBucketVersioningOutput {
+        status: status.map(BucketVersioningStatus::from_static),
+        ..Default::default()
+    };
+    Ok(S3Response::new(output))
+}
+
+/// Handle ListObjectVersions: versions and delete markers, by key and then
+/// newest first.
+pub async fn handle_list_object_versions(
+    state: &SharedState,
+    bucket: &str,
+    prefix: &str,
+    key_marker: &str,
+    version_id_marker: &str,
+    max_keys: u32,
+) -> S3Result<S3Response<ListObjectVersionsOutput>> {
+    let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
+    let ns_id = namespace_id(&db, bucket)?;
+
+    // One extra row tells whether the listing is truncated
+    let mut rows = db
+        .list_object_versions(ns_id, prefix, max_keys + 1, key_marker, version_id_marker)
+        .map_err(|_| s3_error!(InternalError))?;
+    let is_truncated = rows.len() > max_keys as usize;
+    rows.truncate(max_keys as usize);
+    let (next_key_marker, next_version_id_marker) = match rows.last() {
+        Some(last) if is_truncated => (Some(last.key.clone()), Some(last.version_id.clone())),
+        _ => (None, None),
+    };
+
+    let mut versions = Vec::new();
+    let mut delete_markers = Vec::new();
+    for row in rows {
+        let last_modified = crate::get::last_modified(&row.created_at);
+        if row.is_delete_marker {
+            delete_markers.push(DeleteMarkerEntry {
+                key: Some(row.key),
+                version_id: Some(row.version_id),
+                is_latest: Some(row.is_latest),
+                last_modified,
+                ..Default::default()
+            });
+        } else {
+            versions.push(ObjectVersion {
+                key: Some(row.key),
+                version_id: Some(row.version_id),
+                is_latest: Some(row.is_latest),
+                size: Some(row.size as i64),
+                e_tag: Some(format!("\"{}\"", row.etag)),
+                last_modified,
+                storage_class: Some(ObjectVersionStorageClass::from_static(
+                    ObjectVersionStorageClass::STANDARD,
+                )),
+                ..Default::default()
+            });
+        }
+    }
+
+    let output = ListObjectVersionsOutput {
+        name: Some(bucket.to_string()),
+        prefix: (!prefix.is_empty()).then(|| prefix.to_string()),
+        key_marker: (!key_marker.is_empty()).then(|| key_marker.to_string()),
+        version_id_marker: (!version_id_marker.is_empty()).then(|| version_id_marker.to_string()),
+        max_keys: Some(max_keys as i32),
+        is_truncated: Some(is_truncated),
+        next_key_marker,
+        next_version_id_marker,
+        versions: (!versions.is_empty()).then_some(versions),
+        delete_markers: (!delete_markers.is_empty()).then_some(delete_markers),
+        ..Default::default()
+    };
+    Ok(S3Response::new(output))
+}
+
+/// Handle DeleteObject. With a `version_id` that version is removed for
+/// good; otherwise a versioned bucket gets a delete marker and an
+/// unversioned one loses the object. Chunks no longer referenced are
+/// deleted from the providers.
+pub async fn handle_delete_object(
+    state: &SharedState,
+    bucket: &str,
+    key: &str,
+    version_id: Option<&str>,
+) -> S3Result<S3Response<DeleteObjectOutput>> {
+    let mut output = DeleteObjectOutput::default();
+    let to_delete = {
+        let db = state.db.lock().map_err(|_| s3_error!(InternalError))?;
+        let ns_id = namespace_id(&db, bucket)?;
+
+        let to_delete = if let Some(version_id) = version_id {
+            output.version_id = Some(version_id.to_string());
+            db.delete_object_version(ns_id, key, version_id)
+                .map_err(|_| s3_error!(InternalError))?
+                .ok_or_else(|| s3_error!(NoSuchVersion))?
+        } else {
+            match db
+                .get_namespace_versioning(ns_id)
+                .map_err(|_| s3_error!(InternalError))?
+            {
+                (false, false) => db
+                    .delete_object_by_ns_key(ns_id, key)
+                    .map_err(|_| s3_error!(InternalError))?,
+                _ => {
+                    let (marker, to_delete) = db
+                        .insert_delete_marker(ns_id, key)
+                        .map_err(|_| s3_error!(InternalError))?;
+                    output.delete_marker = Some(true);
+                    output.version_id = Some(marker);
+                    to_delete
+                }
+            }
+        };
+        state.queue_webhook(
+            &db,
+            "object.deleted",
+            json!({
+                "bucket": bucket,
+                "key": key,
+                "version_id": output.version_id,
+                "delete_marker": output.delete_marker.unwrap_or(false),
+            }),
+        );
+        to_delete
+    };
+
+    // Delete chunks from storage providers
+    let mut deleted = 0;
+    for (provider_id, storage_key) in to_delete {
+        if let Some(provider) = state.providers.get(&provider_id) {
+            match provider.delete_chunk(&storage_key).await {
+                Ok(()) => deleted += 1,
+                Err(e) => {
+                    metrics::provider_error(provider.name());
+                    tracing::warn!("Failed to delete chunk {storage_key}: {e}");
+                }
+            }
+        }
+    }
+    metrics::gc_orphans(deleted);
+
+    Ok(S3Response::new(output))
+}
@@ -0,0 +1,192 @@
+/// Server access logging tests: once PutBucketLogging is on, PUT and GET
+/// requests against the bucket land in the manifest's access log with
+/// their status and byte counts; buckets without logging record nothing.
+///
+/// Run:
+///   cargo test -p enigma-s3 --test access_log -- --nocapture
+use aws_sdk_s3::error::ProvideErrorMetadata;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{BucketLoggingStatus, LoggingEnabled};
+
+use enigma_s3::SharedState;
+use enigma_s3::access_log::{AccessLog, AccessLogged};
+use enigma_s3::service::EnigmaS3Service;
+
+mod common;
+
+use common::TestState;
+
+fn test_state(dir: &std::path::Path) -> SharedState {
+    TestState::new(dir)
+        .local_provider()
+        .bucket("photos")
+        .bucket("logs")
+        .bucket("quiet")
+        .build()
+}
+
+/// AWS SDK client for an in-process S3 service that reports to `log`.
+fn sdk_client(state: SharedState, log: AccessLog) -> aws_sdk_s3::Client {
+    common::service_client(AccessLogged::new(EnigmaS3Service::new(state), log))
+}
+
+async fn enable_logging(client: &aws_sdk_s3::Client, bucket: &str) {
+    client
+        .put_bucket_logging()
+        .bucket(bucket)
+        .bucket_logging_status(
+            BucketLoggingStatus::builder()
+                .logging_enabled(
+                    LoggingEnabled::builder()
+                        .target_bucket("logs")
+                        .target_prefix(format!("{bucket}/"))
+                        .build()
+                        .unwrap(),
+                )
+                .build(),
+        )
+        .send()
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn put_and_get_are_logged() {
+    let dir = tempfile::tempdir().unwrap();
+    let state = test_state(dir.path());
+    let log = AccessLog::start(state.db.clone());
+    let client = sdk_client(state.clone(), log.clone());
+
+    enable_logging(&client, "photos").await;
+    client
+        .put_object()
+        .bucket("photos")
+        .key("cat.jpg")
+        .body(ByteStream::from_static(b"meow meow"))
+        .send()
+        .await
+        .unwrap();
+    let body = client
+        .get_object()
+        .bucket("photos")
+        .key("cat.jpg")
+        .send()
+        .await
+        .unwrap()
+        .body
+        .collect()
+        .await
+        .unwrap()
+        .into_bytes();
+    assert_eq!(&body[..], b"meow meow");
+    let err = client
+        .get_object()
+        .bucket("photos")
+        .key("dog.jpg")
+        .send()
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Some("NoSuchKey"));
+    log.flush().await;
+
+    let entries = state
+        .db
+        .lock()
+        .unwrap()
+        .list_s3_logs(Some("photos"), "", "9999", 100)
+        .unwrap();
+    let summary: Vec<_> = entries
+        .iter()
+        .map(|e| {
+            (
+                e.operation.as_str(),
+                e.key.as_deref(),
+                e.status_code,
+                e.bytes_transferred,
+            )
+        })
+        .collect();
+    // Logging is on by the time the request turning it on is written
+    assert_eq!(
+        summary,
+        [
+            ("PutBucketLogging", None, 200, 0),
+            ("PutObject", Some("cat.jpg"), 200, 9),
+            ("GetObject", Some("cat.jpg"), 200, 9),
+            ("GetObject", Some("dog.jpg"), 404, 0),
+        ]
+    );
+    assert!(entries.iter().all(|e| e.bucket == "photos"));
+}
+
+#[tokio::test]
+async fn buckets_without_logging_record_nothing() {
+    let dir = tempfile::tempdir().unwrap();
+    let state = test_state(dir.path());
+    let log = AccessLog::start(state.db.clone());
+    let client = sdk_client(state.clone(), log.clone());
+
+    client
+        .put_object()
+        .bucket("quiet")
+        .key("a.txt")
+        .body(ByteStream::from_static(b"hush"))
+        .send()
+        .await
+        .unwrap();
+    log.flush().await;
+
+    let entries = state
+        .db
+        .lock()
+        .unwrap()
+        .list_s3_logs(None, "", "9999", 100)
+        .unwrap();
+    assert!(entries.is_empty());
+}
+
+#[tokio::test]
+async fn logging_config_round_trips() {
+    let dir = tempfile::tempdir().unwrap();
+    let state = test_state(dir.path());
+    let client = sdk_client(state.clone(), AccessLog::start(state.db.clone()));
+
+    let before = client
+        .get_bucket_logging()
+        .bucket("photos")
+        .send()
+        .await
+        .unwrap();
+    assert!(before.logging_enabled().is_none());
+
+    enable_logging(&client, "photos").await;
+    let after = client
+        .get_bucket_logging()
+        .bucket("photos")
+        .send()
+        .await
+        .unwrap();
+    let enabled = after.logging_enabled().unwrap();
+    assert_eq!(enabled.target_bucket(), "logs");
+    assert_eq!(enabled.target_prefix(), "photos/");
+
+    // Logging into a bucket that does not exist is refused
+    let err = client
+        .put_bucket_logging()
+        .bucket("photos")
+        .bucket_logging_status(
+            BucketLoggingStatus::builder()
+                .logging_enabled(
+                    LoggingEnabled::builder()
+                        .target_bucket("missing")
+                        .target_prefix("")
+                        .build()
+                        .unwrap(),
+                )
+                .build(),
+        )
+        .send()
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Some("InvalidTargetBucketForLogging"));
+}
@@ -0,0 +1,137 @@
+/// Bucket configuration stubs: an AWS SDK client, wired to the S3 service
+/// in-process, reads the location, encryption, accelerate, request payment
+/// and logging configuration that SDKs and tools probe for on startup.
+///
+/// Run:
+///   cargo test -p enigma-s3 --test bucket_config -- --nocapture
+use aws_sdk_s3::error::ProvideErrorMetadata;
+use aws_sdk_s3::types::{BucketLocationConstraint, Payer, ServerSideEncryption};
+
+use enigma_s3::SharedState;
+use enigma_s3::service::EnigmaS3Service;
+
+mod common;
+
+use common::TestState;
+
+fn test_state(dir: &std::path::Path) -> SharedState {
+    TestState::new(dir)
+        .local_provider()
+        .bucket("bucket")
+        .build()
+}
+
+/// AWS SDK client whose requests go straight to an in-process S3 service
+/// reporting `region`.
+fn sdk_client(state: SharedState, region: &str) -> aws_sdk_s3::Client {
+    common::service_client(EnigmaS3Service::new(state).with_region(region))
+}
+
+#[tokio::test]
+async fn location_reports_configured_region() {
+    let dir = tempfile::tempdir().unwrap();
+    let client = sdk_client(test_state(dir.path()), "eu-west-1");
+
+    let out = client
+        .get_bucket_location()
+        .bucket("bucket")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        out.location_constraint(),
+        Some(&BucketLocationConstraint::EuWest1)
+    );
+}
+
+#[tokio::test]
+async fn location_is_empty_in_us_east_1() {
+    let dir = tempfile::tempdir().unwrap();
+    let client = sdk_client(test_state(dir.path()), "us-east-1");
+
+    let out = client
+        .get_bucket_location()
+        .bucket("bucket")
+        .send()
+        .await
+        .unwrap();
+    // The SDK reads the empty element as an empty constraint
+    assert_eq!(out.location_constraint().map_or("", |c| c.as_str()), "");
+}
+
+#[tokio::test]
+async fn config_stubs_describe_defaults() {
+    let dir = tempfile::tempdir().unwrap();
+    let client = sdk_client(test_state(dir.path()), "us-east-1");
+
+    let accelerate = client
+        .get_bucket_accelerate_configuration()
+        .bucket("bucket")
+        .send()
+        .await
+        .unwrap();
+    assert!(accelerate.status().is_none());
+
+    let payment = client
+        .get_bucket_request_payment()
+        .bucket("bucket")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(payment.payer(), Some(&Payer::BucketOwner));
+
+    let logging = client
+        .get_bucket_logging()
+        .bucket("bucket")
+        .send()
+        .await
+        .unwrap();
+    assert!(logging.logging_enabled().is_none());
+
+    let encryption = client
+        .get_bucket_encryption()
+        .bucket("bucket")
+        .send()
+        .await
+        .unwrap();
+    let rules = encryption
+        .server_side_encryption_configuration()
+        .unwrap()
+        .rules();
+    assert_eq!(rules.len(), 1);
+    assert_eq!(
+        rules[0]
+            .apply_server_side_encryption_by_default()
+            .unwrap()
+            .sse_algorithm(),
+        &ServerSideEncryption::Aes256
+    );
+}
+
+#[tokio::test]
+async fn stubs_reject_unknown_bucket() {
+    let dir = tempfile::tempdir().unwrap();
+    let client = sdk_client(test_state(dir.path()), "us-east-1");
+
+    let err = client
+        .get_bucket_location()
+        .bucket("missing")
+        .send()
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.as_service_error().and_then(|e| e.code()),
+        Some("NoSuchBucket")
+    );
+
+    let err = client
+        .get_bucket_encryption()
+        .bucket("missing")
+        .send()
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.as_service_error().and_then(|e| e.code()),
+        Some("NoSuchBucket")
+    );
+}
@@ -0,0 +1,109 @@
+use enigma_s3::SharedState;
+/// Bucket tagging test: Get/Put/DeleteBucketTagging replace and clear the
+/// tag set of a namespace, with the S3 limit of 50 tags per bucket.
+///
+/// Run:
+///   cargo test -p enigma-s3 --test bucket_tagging -- --nocapture
+use enigma_s3::tagging::{
+    handle_delete_bucket_tagging, handle_get_bucket_tagging, handle_put_bucket_tagging,
+};
+use s3s::S3ErrorCode;
+use s3s::dto::{Tag, Tagging};
+
+mod common;
+
+use common::TestState;
+
+fn test_state(dir: &std::path::Path) -> SharedState {
+    TestState::new(dir)
+        .local_provider()
+        .bucket("bucket")
+        .build()
+}
+
+async fn get_tags(state: &SharedState) -> Vec<(String, String)> {
+    let resp = handle_get_bucket_tagging(state, "bucket").await.unwrap();
+    resp.output
+        .tag_set
+        .into_iter()
+        .map(|t| (t.key.unwrap(), t.value.unwrap()))
+        .collect()
+}
+
+async fn put_tags(state: &SharedState, tags: &[(&str, &str)]) -> s3s::S3Result<()> {
+    let tagging = Tagging {
+        tag_set: tags
+            .iter()
+            .map(|(key, value)| Tag {
+                key: Some(key.to_string()),
+                value: Some(value.to_string()),
+            })
+            .collect(),
+    };
+    handle_put_bucket_tagging(state, "bucket", tagging)
+        .await
+        .map(|_| ())
+}
+
+#[tokio::test]
+async fn put_get_update_delete_bucket_tagging() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = test_state(tmp.path());
+
+    // A bucket without tags has no tag set at all
+    let err = handle_get_bucket_tagging(&state, "bucket")
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(*err.code(), S3ErrorCode::NoSuchTagSet);
+
+    put_tags(&state, &[("team", "ops"), ("env", "prod")])
+        .await
+        .unwrap();
+    assert_eq!(
+        get_tags(&state).await,
+        vec![
+            ("env".to_string(), "prod".to_string()),
+            ("team".to_string(), "ops".to_string()),
+        ]
+    );
+
+    // The new set replaces the old one
+    put_tags(&state, &[("env", "dev")]).await.unwrap();
+    assert_eq!(
+        get_tags(&state).await,
+        vec![("env".to_string(), "dev".to_string())]
+    );
+
+    handle_delete_bucket_tagging(&state, "bucket")
+        .await
+        .unwrap();
+    assert!(handle_get_bucket_tagging(&state, "bucket").await.is_err());
+
+    let err = handle_delete_bucket_tagging(&state, "missing")
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(*err.code(), S3ErrorCode::NoSuchBucket);
+}
+
+#[tokio::test]
+async fn bucket_tag_limit_is_enforced() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = test_state(tmp.path());
+    let keys: Vec<String> = (0..51).map(|i| format!("k{i}")).collect();
+    let tags: Vec<(&str, &str)> = keys.iter().map(|k| (k.as_str(), "v")).collect();
+
+    let err = put_tags(&state, &tags).await.unwrap_err();
+    assert_eq!(*err.code(), S3ErrorCode::InvalidTag);
+    assert!(handle_get_bucket_tagging(&state, "bucket").await.is_err());
+
+    // Unlike objects, buckets take up to 50 tags
+    put_tags(&state, &tags[..50]).await.unwrap();
+    assert_eq!(get_tags(&state).await.len(), 50);
+
+    let err = put_tags(&state, &[("k", "a"), ("k", "b")])
+        .await
+        .unwrap_err();
+    assert_eq!(*err.code(), S3ErrorCode::InvalidTag);
+}
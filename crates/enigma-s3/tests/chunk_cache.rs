@@ -0,0 +1,140 @@
+/// Chunk cache tests: with `chunk_cache_enabled`, streamed reads pre-fetch
+/// the next chunk while the current one is sent, and chunks already in the
+/// cache are not downloaded again, whichever replica they came from.
+///
+/// Run:
+///   cargo test -p enigma-s3 --test chunk_cache -- --nocapture
+use std::sync::Arc;
+use std::time::Duration;
+
+use enigma_s3::ops::{self, ChunkCache};
+use enigma_s3::{EnigmaS3State, SharedState};
+use enigma_storage::mock::{MockMethod, MockStorageProvider};
+use tokio::io::AsyncReadExt;
+
+mod common;
+
+use common::{TestState, generate_data};
+
+/// Large enough to be split into several chunks by put::chunk_data.
+const OBJECT_SIZE: usize = 40 * 1024 * 1024;
+
+/// State replicating every chunk to each of `mocks`.
+fn test_state(
+    dir: &std::path::Path,
+    mocks: &[MockStorageProvider],
+    cache: ChunkCache,
+) -> SharedState {
+    let mut state = TestState::new(dir);
+    for mock in mocks {
+        state = state.mock_provider(mock);
+    }
+    let state = state
+        .bucket("bucket")
+        .configure(|c| c.replication_factor = mocks.len() as u32)
+        .into_state();
+    Arc::new(EnigmaS3State {
+        chunk_cache: cache,
+        ..state
+    })
+}
+
+async fn read_object(state: &SharedState, key: &str) -> Vec<u8> {
+    let mut reader = ops::retrieve_object(state, "bucket", key)
+        .await
+        .unwrap()
+        .reader;
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data).await.unwrap();
+    data
+}
+
+#[tokio::test]
+async fn cached_chunks_are_not_downloaded_again() {
+    let tmp = tempfile::tempdir().unwrap();
+    let mock = MockStorageProvider::new("mock");
+    let state = test_state(tmp.path(), std::slice::from_ref(&mock), ChunkCache::new(64));
+
+    let data = generate_data(OBJECT_SIZE, 0);
+    ops::store_object(&state, "bucket", "obj.bin", &data, None)
+        .await
+        .unwrap();
+    let chunks = mock.len() as u32;
+    assert!(chunks > 1, "expected a multi-chunk object");
+
+    assert!(read_object(&state, "obj.bin").await == data);
+    assert_eq!(mock.call_count(MockMethod::DownloadChunk), chunks);
+    assert!(read_object(&state, "obj.bin").await == data);
+    assert_eq!(mock.call_count(MockMethod::DownloadChunk), chunks);
+}
+
+#[tokio::test]
+async fn disabled_cache_downloads_every_read() {
+    let tmp = tempfile::tempdir().unwrap();
+    let mock = MockStorageProvider::new("mock");
+    let state = test_state(
+        tmp.path(),
+        std::slice::from_ref(&mock),
+        ChunkCache::default(),
+    );
+
+    let data = generate_data(OBJECT_SIZE, 0);
+    ops::store_object(&state, "bucket", "obj.bin", &data, None)
+        .await
+        .unwrap();
+    let chunks = mock.len() as u32;
+
+    assert!(read_object(&state, "obj.bin").await == data);
+    assert!(read_object(&state, "obj.bin").await == data);
+    assert_eq!(mock.call_count(MockMethod::DownloadChunk), 2 * chunks);
+}
+
+#[tokio::test]
+async fn next_chunk_is_prefetched_during_streaming() {
+    let tmp = tempfile::tempdir().unwrap();
+    let mock = MockStorageProvider::new("mock");
+    let state = test_state(tmp.path(), std::slice::from_ref(&mock), ChunkCache::new(64));
+
+    let data = generate_data(OBJECT_SIZE, 0);
+    ops::store_object(&state, "bucket", "obj.bin", &data, None)
+        .await
+        .unwrap();
+    mock.set_delay(MockMethod::DownloadChunk, Duration::from_millis(100));
+
+    assert!(read_object(&state, "obj.bin").await == data);
+    // Chunk N+1 downloads while chunk N does, and is then served from
+    // the cache rather than downloaded a second time
+    assert_eq!(mock.max_concurrent_calls(MockMethod::DownloadChunk), 2);
+    assert_eq!(
+        mock.call_count(MockMethod::DownloadChunk),
+        mock.len() as u32
+    );
+}
+
+#[tokio::test]
+async fn cache_is_filled_from_the_replica_that_answered() {
+    let tmp = tempfile::tempdir().unwrap();
+    let broken = MockStorageProvider::new("broken");
+    let healthy = MockStorageProvider::new("healthy");
+    let state = test_state(
+        tmp.path(),
+        &[broken.clone(), healthy.clone()],
+        ChunkCache::new(64),
+    );
+
+    let data = generate_data(OBJECT_SIZE, 0);
+    ops::store_object(&state, "bucket", "obj.bin", &data, None)
+        .await
+        .unwrap();
+    let chunks = healthy.len() as u32;
+    broken.inject_failure(MockMethod::DownloadChunk, 0, "connection reset");
+
+    assert!(read_object(&state, "obj.bin").await == data);
+    assert_eq!(healthy.call_count(MockMethod::DownloadChunk), chunks);
+    let broken_calls = broken.call_count(MockMethod::DownloadChunk);
+
+    // Every chunk is now cached under the healthy replica's key
+    assert!(read_object(&state, "obj.bin").await == data);
+    assert_eq!(healthy.call_count(MockMethod::DownloadChunk), chunks);
+    assert_eq!(broken.call_count(MockMethod::DownloadChunk), broken_calls);
+}
@@ -0,0 +1,164 @@
+//! Helpers shared by the integration tests. Each test binary uses only
+//! some of them.
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use aws_sdk_s3::config::{BehaviorVersion, Credentials, Region};
+use enigma_core::config::{EnigmaConfig, EnigmaSettings};
+use enigma_core::distributor::Distributor;
+use enigma_core::manifest::ManifestDb;
+use enigma_core::types::{KeyMaterial, ProviderType};
+use enigma_s3::auth::EnigmaS3Auth;
+use enigma_s3::service::EnigmaS3Service;
+use enigma_s3::{EnigmaS3State, SharedState};
+use enigma_storage::local::LocalStorageProvider;
+use enigma_storage::mock::MockStorageProvider;
+use enigma_storage::provider::StorageProvider;
+use s3s::S3;
+use s3s::service::S3ServiceBuilder;
+
+/// Credentials of the single caller [`sdk_client`] signs as.
+pub const ACCESS_KEY: &str = "enigma-test";
+pub const SECRET_KEY: &str = "enigma-test-secret";
+
+/// Builds an [`EnigmaS3State`] over an in-memory manifest, encrypting with
+/// a fixed test key and distributing chunks round-robin.
+pub struct TestState {
+    dir: PathBuf,
+    db: ManifestDb,
+    providers: HashMap<i64, Box<dyn StorageProvider>>,
+    config: EnigmaConfig,
+}
+
+impl TestState {
+    /// No providers or buckets, and the default config rooted at `dir`.
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            db: ManifestDb::open_in_memory().unwrap(),
+            providers: HashMap::new(),
+            config: EnigmaConfig::default_config(dir),
+        }
+    }
+
+    /// Add a provider named "local" storing chunks under `<dir>/chunks`.
+    pub fn local_provider(mut self) -> Self {
+        let pid = self
+            .db
+            .insert_provider(
+                "local",
+                ProviderType::Local,
+                self.dir.to_str().unwrap(),
+                None,
+                1,
+            )
+            .unwrap();
+        let provider = LocalStorageProvider::new(&self.dir.join("chunks"), "local").unwrap();
+        self.providers.insert(pid, Box::new(provider));
+        self
+    }
+
+    /// Add `mock` as a provider under its own name.
+    pub fn mock_provider(mut self, mock: &MockStorageProvider) -> Self {
+        let pid = self
+            .db
+            .insert_provider(mock.name(), ProviderType::Local, mock.name(), None, 1)
+            .unwrap();
+        self.providers.insert(pid, Box::new(mock.clone()));
+        self
+    }
+
+    /// Create the bucket (namespace) `name`.
+    pub fn bucket(self, name: &str) -> Self {
+        self.db.create_namespace(name).unwrap();
+        self
+    }
+
+    /// Change the `[enigma]` settings.
+    pub fn configure(mut self, f: impl FnOnce(&mut EnigmaSettings)) -> Self {
+        f(&mut self.config.enigma);
+        self
+    }
+
+    /// The manifest, to record data before the state is built.
+    pub fn db(&self) -> &ManifestDb {
+        &self.db
+    }
+
+    /// The state with every other field at its default; use struct update
+    /// syntax to set more. Needs at least one provider for the distributor.
+    pub fn into_state(self) -> EnigmaS3State {
+        let distributor = Distributor::round_robin(self.db.list_providers().unwrap()).unwrap();
+        EnigmaS3State {
+            db: Arc::new(Mutex::new(self.db)),
+            providers: self.providers.into(),
+            distributor: distributor.into(),
+            key_material: KeyMaterial {
+                id: "test-key-1".to_string(),
+                key: [0x42; 32],
+            },
+            config: self.config,
+            raft: Default::default(),
+            events: Default::default(),
+            usage: Default::default(),
+            access_control: Default::default(),
+            multipart_limits: Default::default(),
+            chunk_cache: Default::default(),
+            upload_semaphore: Default::default(),
+            provider_limiters: Default::default(),
+        }
+    }
+
+    pub fn build(self) -> SharedState {
+        Arc::new(self.into_state())
+    }
+}
+
+/// AWS SDK client whose requests go straight to an in-process S3 service
+/// over `state`.
+pub fn sdk_client(state: SharedState) -> aws_sdk_s3::Client {
+    service_client(EnigmaS3Service::new(state))
+}
+
+/// Like [`sdk_client`], for a configured or wrapped service.
+pub fn service_client(s3: impl S3) -> aws_sdk_s3::Client {
+    let mut builder = S3ServiceBuilder::new(s3);
+    builder.set_auth(EnigmaS3Auth::new(
+        ACCESS_KEY.to_string(),
+        SECRET_KEY.to_string(),
+    ));
+    client_for(builder, ACCESS_KEY)
+}
+
+/// AWS SDK client signing as `access_key` with [`SECRET_KEY`], for the
+/// service `builder` builds.
+pub fn client_for(builder: S3ServiceBuilder, access_key: &str) -> aws_sdk_s3::Client {
+    let service = builder.build().into_shared();
+    let config = aws_sdk_s3::Config::builder()
+        .behavior_version(BehaviorVersion::latest())
+        .credentials_provider(Credentials::new(access_key, SECRET_KEY, None, None, "test"))
+        .region(Region::new("us-east-1"))
+        .endpoint_url("http://localhost:9000")
+        .force_path_style(true)
+        .http_client(s3s_aws::Client::from(service))
+        .build();
+    aws_sdk_s3::Client::from_conf(config)
+}
+
+/// Generate pseudo-random data (deterministic, fast); different seeds give
+/// different data.
+pub fn generate_data(size: usize, seed: u64) -> Vec<u8> {
+    let mut data = Vec::with_capacity(size);
+    let mut state: u64 = 0xdeadbeefcafe1234 ^ seed;
+    while data.len() < size {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        data.extend_from_slice(&state.to_le_bytes());
+    }
+    data.truncate(size);
+    data
+}
@@ -0,0 +1,201 @@
+use enigma_s3::SharedState;
+/// Conditional GET/HEAD test: If-Match, If-None-Match, If-Modified-Since and
+/// If-Unmodified-Since against the stored ETag and creation time.
+///
+/// Run:
+///   cargo test -p enigma-s3 --test conditional_get -- --nocapture
+use enigma_s3::get::{Preconditions, handle_get_object, handle_head_object};
+use s3s::S3ErrorCode;
+use s3s::dto::{StreamingBlob, Timestamp, TimestampFormat};
+
+mod common;
+
+use common::TestState;
+
+fn test_state(dir: &std::path::Path) -> SharedState {
+    TestState::new(dir)
+        .local_provider()
+        .bucket("bucket")
+        .build()
+}
+
+/// Store `key` and return its ETag without quotes.
+async fn put(state: &SharedState, key: &str) -> String {
+    let resp = enigma_s3::put::handle_put_object(
+        state,
+        "bucket",
+        key,
+        None,
+        None,
+        &[],
+        Some(StreamingBlob::from(s3s::Body::from(b"hello".to_vec()))),
+    )
+    .await
+    .unwrap();
+    resp.output.e_tag.unwrap().trim_matches('"').to_string()
+}
+
+fn ts(secs: i64) -> Timestamp {
+    Timestamp::parse(TimestampFormat::EpochSeconds, &secs.to_string()).unwrap()
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+/// Outcome of GET and HEAD with `pre`: `None` for 200, else the error code.
+/// Asserts both agree.
+async fn check(state: &SharedState, pre: Preconditions) -> Option<S3ErrorCode> {
+    let get = handle_get_object(state, "bucket", "obj", None, &pre)
+        .await
+        .err()
+        .map(|e| e.code().clone());
+    let head = handle_head_object(state, "bucket", "obj", None, &pre)
+        .await
+        .err()
+        .map(|e| e.code().clone());
+    assert_eq!(get, head, "GET and HEAD disagree for {pre:?}");
+    get
+}
+
+#[tokio::test]
+async fn if_match() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = test_state(tmp.path());
+    let etag = put(&state, "obj").await;
+
+    for value in [
+        format!("\"{etag}\""),
+        etag.clone(),
+        "*".to_string(),
+        format!("\"other\", \"{etag}\""),
+    ] {
+        let pre = Preconditions {
+            if_match: Some(value),
+            ..Default::default()
+        };
+        assert_eq!(check(&state, pre).await, None);
+    }
+
+    let pre = Preconditions {
+        if_match: Some("\"other\"".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(
+        check(&state, pre).await,
+        Some(S3ErrorCode::PreconditionFailed)
+    );
+}
+
+#[tokio::test]
+async fn if_none_match() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = test_state(tmp.path());
+    let etag = put(&state, "obj").await;
+
+    for value in [
+        format!("\"{etag}\""),
+        etag.clone(),
+        format!("W/\"{etag}\""),
+        "*".into(),
+    ] {
+        let pre = Preconditions {
+            if_none_match: Some(value),
+            ..Default::default()
+        };
+        assert_eq!(check(&state, pre).await, Some(S3ErrorCode::NotModified));
+    }
+
+    let pre = Preconditions {
+        if_none_match: Some("\"other\"".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(check(&state, pre).await, None);
+}
+
+#[tokio::test]
+async fn if_modified_since() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = test_state(tmp.path());
+    put(&state, "obj").await;
+
+    let pre = Preconditions {
+        if_modified_since: Some(ts(now() - 3600)),
+        ..Default::default()
+    };
+    assert_eq!(check(&state, pre).await, None);
+
+    let pre = Preconditions {
+        if_modified_since: Some(ts(now() + 3600)),
+        ..Default::default()
+    };
+    assert_eq!(check(&state, pre).await, Some(S3ErrorCode::NotModified));
+}
+
+#[tokio::test]
+async fn if_unmodified_since() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = test_state(tmp.path());
+    put(&state, "obj").await;
+
+    let pre = Preconditions {
+        if_unmodified_since: Some(ts(now() + 3600)),
+        ..Default::default()
+    };
+    assert_eq!(check(&state, pre).await, None);
+
+    let pre = Preconditions {
+        if_unmodified_since: Some(ts(now() - 3600)),
+        ..Default::default()
+    };
+    assert_eq!(
+        check(&state, pre).await,
+        Some(S3ErrorCode::PreconditionFailed)
+    );
+}
+
+#[tokio::test]
+async fn etag_conditions_take_precedence_over_dates() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = test_state(tmp.path());
+    let etag = put(&state, "obj").await;
+
+    // Matching If-Match wins over a failing If-Unmodified-Since
+    let pre = Preconditions {
+        if_match: Some(format!("\"{etag}\"")),
+        if_unmodified_since: Some(ts(now() - 3600)),
+        ..Default::default()
+    };
+    assert_eq!(check(&state, pre).await, None);
+
+    // Non-matching If-None-Match wins over an unmet If-Modified-Since
+    let pre = Preconditions {
+        if_none_match: Some("\"other\"".to_string()),
+        if_modified_since: Some(ts(now() + 3600)),
+        ..Default::default()
+    };
+    assert_eq!(check(&state, pre).await, None);
+
+    // A failed If-Match is 412 even when If-None-Match would be 304
+    let pre = Preconditions {
+        if_match: Some("\"other\"".to_string()),
+        if_none_match: Some(format!("\"{etag}\"")),
+        ..Default::default()
+    };
+    assert_eq!(
+        check(&state, pre).await,
+        Some(S3ErrorCode::PreconditionFailed)
+    );
+}
+
+#[tokio::test]
+async fn responses_carry_last_modified() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = test_state(tmp.path());
+    put(&state, "obj").await;
+
+    let resp = handle_head_object(&state, "bucket", "obj", None, &Default::default())
+        .await
+        .unwrap();
+    assert!(resp.output.last_modified.is_some());
+}
@@ -0,0 +1,178 @@
+/// Conditional HeadObject, GetObject and HeadBucket over the wire: an AWS
+/// SDK client, wired to the S3 service in-process, sends If-Modified-Since
+/// and If-Unmodified-Since and gets 304 (with the object's validators) or
+/// 412 back, compared at second granularity.
+///
+/// Run:
+///   cargo test -p enigma-s3 --test conditional_head -- --nocapture
+use aws_sdk_s3::primitives::{ByteStream, DateTime};
+
+use enigma_s3::SharedState;
+
+mod common;
+
+use common::{TestState, sdk_client};
+
+fn test_state(dir: &std::path::Path) -> SharedState {
+    TestState::new(dir)
+        .local_provider()
+        .bucket("bucket")
+        .build()
+}
+
+const HOUR: i64 = 3600;
+
+/// Client with "a.txt" stored, and the current time in seconds.
+async fn setup(dir: &std::path::Path) -> (aws_sdk_s3::Client, i64) {
+    let client = sdk_client(test_state(dir));
+    client
+        .put_object()
+        .bucket("bucket")
+        .key("a.txt")
+        .body(ByteStream::from_static(b"hello"))
+        .send()
+        .await
+        .unwrap();
+    (client, chrono::Utc::now().timestamp())
+}
+
+fn status<E>(err: &aws_sdk_s3::error::SdkError<E>) -> u16 {
+    err.raw_response().unwrap().status().as_u16()
+}
+
+#[tokio::test]
+async fn head_object_if_modified_since() {
+    let dir = tempfile::tempdir().unwrap();
+    let (client, now) = setup(dir.path()).await;
+
+    let head = client
+        .head_object()
+        .bucket("bucket")
+        .key("a.txt")
+        .if_modified_since(DateTime::from_secs(now - HOUR))
+        .send()
+        .await
+        .unwrap();
+    let etag = head.e_tag().unwrap().to_string();
+    assert_eq!(head.content_length(), Some(5));
+
+    // Not modified since: 304 with the validators but no body
+    let err = client
+        .head_object()
+        .bucket("bucket")
+        .key("a.txt")
+        .if_modified_since(DateTime::from_secs(now + HOUR))
+        .send()
+        .await
+        .unwrap_err();
+    assert_eq!(status(&err), 304);
+    let headers = err.raw_response().unwrap().headers();
+    assert_eq!(headers.get("etag"), Some(etag.as_str()));
+    assert_eq!(headers.get("content-length"), Some("5"));
+    assert!(headers.get("last-modified").is_some());
+}
+
+#[tokio::test]
+async fn head_object_if_unmodified_since() {
+    let dir = tempfile::tempdir().unwrap();
+    let (client, now) = setup(dir.path()).await;
+
+    client
+        .head_object()
+        .bucket("bucket")
+        .key("a.txt")
+        .if_unmodified_since(DateTime::from_secs(now + HOUR))
+        .send()
+        .await
+        .unwrap();
+
+    let err = client
+        .head_object()
+        .bucket("bucket")
+        .key("a.txt")
+        .if_unmodified_since(DateTime::from_secs(now - HOUR))
+        .send()
+        .await
+        .unwrap_err();
+    assert_eq!(status(&err), 412);
+}
+
+#[tokio::test]
+async fn get_object_conditions_match_head_object() {
+    let dir = tempfile::tempdir().unwrap();
+    let (client, now) = setup(dir.path()).await;
+
+    let body = client
+        .get_object()
+        .bucket("bucket")
+        .key("a.txt")
+        .if_modified_since(DateTime::from_secs(now - HOUR))
+        .send()
+        .await
+        .unwrap()
+        .body
+        .collect()
+        .await
+        .unwrap()
+        .into_bytes();
+    assert_eq!(&body[..], b"hello");
+
+    let err = client
+        .get_object()
+        .bucket("bucket")
+        .key("a.txt")
+        .if_modified_since(DateTime::from_secs(now + HOUR))
+        .send()
+        .await
+        .unwrap_err();
+    assert_eq!(status(&err), 304);
+
+    let err = client
+        .get_object()
+        .bucket("bucket")
+        .key("a.txt")
+        .if_unmodified_since(DateTime::from_secs(now - HOUR))
+        .send()
+        .await
+        .unwrap_err();
+    assert_eq!(status(&err), 412);
+}
+
+#[tokio::test]
+async fn head_bucket_compares_against_bucket_creation() {
+    let dir = tempfile::tempdir().unwrap();
+    let (client, now) = setup(dir.path()).await;
+    let http_date = |secs: i64| {
+        chrono::DateTime::from_timestamp(secs, 0)
+            .unwrap()
+            .format("%a, %d %b %Y %H:%M:%S GMT")
+            .to_string()
+    };
+    // HeadBucket does not model the date conditions, so set the headers
+    let head_bucket = |header: &'static str, date: String| {
+        client
+            .head_bucket()
+            .bucket("bucket")
+            .customize()
+            .mutate_request(move |req| {
+                req.headers_mut().insert(header, date.clone());
+            })
+            .send()
+    };
+
+    head_bucket("if-modified-since", http_date(now - HOUR))
+        .await
+        .unwrap();
+    let err = head_bucket("if-modified-since", http_date(now + HOUR))
+        .await
+        .unwrap_err();
+    assert_eq!(status(&err), 304);
+
+    head_bucket("if-unmodified-since", http_date(now + HOUR))
+        .await
+        .unwrap();
+    let err = head_bucket("if-unmodified-since", http_date(now - HOUR))
+        .await
+        .unwrap_err();
+    assert_eq!(status(&err), 412);
+}
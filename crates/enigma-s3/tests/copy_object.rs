@@ -0,0 +1,218 @@
+/// Server-side CopyObject tests: copies reference the source's chunks
+/// instead of re-uploading them. Exercised through the ops layer and
+/// through an AWS SDK client wired to the S3 service in-process.
+///
+/// Run:
+///   cargo test -p enigma-s3 --test copy_object -- --nocapture
+use aws_sdk_s3::error::ProvideErrorMetadata;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::MetadataDirective;
+use sha2::{Digest, Sha256};
+
+use enigma_s3::SharedState;
+use enigma_s3::ops::copy_object_server_side;
+
+mod common;
+
+use common::{TestState, sdk_client};
+
+fn test_state(dir: &std::path::Path) -> SharedState {
+    TestState::new(dir)
+        .local_provider()
+        .bucket("bucket")
+        .bucket("other")
+        .build()
+}
+
+fn ref_count(state: &SharedState, hash: &str) -> i64 {
+    let db = state.db.lock().unwrap();
+    db.conn()
+        .query_row(
+            "SELECT ref_count FROM chunks WHERE hash = ?1",
+            [hash],
+            |row| row.get(0),
+        )
+        .unwrap()
+}
+
+async fn get_body(client: &aws_sdk_s3::Client, bucket: &str, key: &str) -> Vec<u8> {
+    client
+        .get_object()
+        .bucket(bucket)
+        .key(key)
+        .send()
+        .await
+        .unwrap()
+        .body
+        .collect()
+        .await
+        .unwrap()
+        .into_bytes()
+        .to_vec()
+}
+
+#[test]
+fn copy_references_every_chunk_without_io() {
+    let dir = tempfile::tempdir().unwrap();
+    let state = test_state(dir.path());
+
+    // A 100-chunk object recorded in the manifest only: the copy must not
+    // need the chunk data
+    let hashes: Vec<String> = (0..100).map(|i| format!("{i:064x}")).collect();
+    {
+        let db = state.db.lock().unwrap();
+        let ns_id = db.get_namespace_id("bucket").unwrap().unwrap();
+        let pid = db.list_providers().unwrap()[0].id;
+        for hash in &hashes {
+            db.insert_or_dedup_chunk(hash, &[0; 12], "test-key-1", pid, hash, 10, 26, None)
+                .unwrap();
+        }
+        let object_id = db
+            .insert_object(ns_id, "big", 1000, "src-etag", None, 100, "test-key-1")
+            .unwrap();
+        for (idx, hash) in hashes.iter().enumerate() {
+            db.insert_object_chunk(object_id, hash, idx as u32, idx as u64 * 10)
+                .unwrap();
+        }
+    }
+
+    let copied =
+        copy_object_server_side(&state, "bucket", "big", None, "other", "copy", None).unwrap();
+    assert_eq!(copied.etag, format!("{:x}", Sha256::digest(b"src-etag")));
+    assert_eq!(copied.version_id, "null");
+
+    for hash in &hashes {
+        assert_eq!(ref_count(&state, hash), 2);
+    }
+    let db = state.db.lock().unwrap();
+    let ns_id = db.get_namespace_id("other").unwrap().unwrap();
+    let (object_id, size, ..) = db.get_object(ns_id, "copy").unwrap().unwrap();
+    assert_eq!(size, 1000);
+    let chunks = db.get_object_chunks(object_id).unwrap();
+    assert_eq!(chunks.len(), 100);
+    assert_eq!(chunks[42], (hashes[42].clone(), 42, 420));
+}
+
+#[tokio::test]
+async fn copy_object_across_buckets() {
+    let dir = tempfile::tempdir().unwrap();
+    let client = sdk_client(test_state(dir.path()));
+    let data = b"copy me without downloading".to_vec();
+
+    let put = client
+        .put_object()
+        .bucket("bucket")
+        .key("src")
+        .metadata("origin", "upload")
+        .body(ByteStream::from(data.clone()))
+        .send()
+        .await
+        .unwrap();
+    let src_etag = put.e_tag().unwrap().trim_matches('"').to_string();
+
+    let copy = client
+        .copy_object()
+        .copy_source("bucket/src")
+        .bucket("other")
+        .key("dst")
+        .send()
+        .await
+        .unwrap();
+    let result = copy.copy_object_result().unwrap();
+    assert_eq!(
+        result.e_tag().unwrap().trim_matches('"'),
+        format!("{:x}", Sha256::digest(src_etag.as_bytes()))
+    );
+    assert!(result.last_modified().is_some());
+    assert_eq!(get_body(&client, "other", "dst").await, data);
+
+    // Metadata is copied unless replaced
+    let head = client
+        .head_object()
+        .bucket("other")
+        .key("dst")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        head.metadata().unwrap().get("origin").map(String::as_str),
+        Some("upload")
+    );
+
+    client
+        .copy_object()
+        .copy_source("bucket/src")
+        .bucket("bucket")
+        .key("src")
+        .metadata_directive(MetadataDirective::Replace)
+        .metadata("origin", "copy")
+        .send()
+        .await
+        .unwrap();
+    let head = client
+        .head_object()
+        .bucket("bucket")
+        .key("src")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        head.metadata().unwrap().get("origin").map(String::as_str),
+        Some("copy")
+    );
+    assert_eq!(get_body(&client, "bucket", "src").await, data);
+}
+
+#[tokio::test]
+async fn copy_object_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    let client = sdk_client(test_state(dir.path()));
+    client
+        .put_object()
+        .bucket("bucket")
+        .key("src")
+        .body(ByteStream::from_static(b"data"))
+        .send()
+        .await
+        .unwrap();
+
+    let err = client
+        .copy_object()
+        .copy_source("bucket/missing")
+        .bucket("bucket")
+        .key("dst")
+        .send()
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.as_service_error().and_then(|e| e.code()),
+        Some("NoSuchKey")
+    );
+
+    let err = client
+        .copy_object()
+        .copy_source("bucket/src")
+        .bucket("missing")
+        .key("dst")
+        .send()
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.as_service_error().and_then(|e| e.code()),
+        Some("NoSuchBucket")
+    );
+
+    // Copying onto itself needs new metadata
+    let err = client
+        .copy_object()
+        .copy_source("bucket/src")
+        .bucket("bucket")
+        .key("src")
+        .send()
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.as_service_error().and_then(|e| e.code()),
+        Some("InvalidRequest")
+    );
+}
@@ -0,0 +1,140 @@
+use enigma_s3::SharedState;
+/// S3 inventory test: a bucket with an inventory configuration is listed,
+/// one CSV row per object, into an encrypted report in the reports bucket,
+/// and the latest inventory manifest points at it.
+///
+/// Run:
+///   cargo test -p enigma-s3 --features inventory --test inventory -- --nocapture
+use enigma_s3::inventory::{
+    MANIFEST_KEY, REPORTS_BUCKET, generate_report, handle_get_bucket_inventory_configuration,
+    handle_put_bucket_inventory_configuration,
+};
+use enigma_storage::mock::MockStorageProvider;
+use s3s::dto::*;
+use tokio::io::AsyncReadExt;
+
+mod common;
+
+use common::TestState;
+
+const OBJECT_COUNT: usize = 100;
+
+fn test_state(dir: &std::path::Path, mock: &MockStorageProvider) -> SharedState {
+    TestState::new(dir)
+        .mock_provider(mock)
+        .bucket("photos")
+        .build()
+}
+
+fn daily_inventory(id: &str) -> InventoryConfiguration {
+    InventoryConfiguration {
+        destination: InventoryDestination {
+            s3_bucket_destination: InventoryS3BucketDestination {
+                account_id: None,
+                bucket: "arn:aws:s3:::reports".to_string(),
+                encryption: None,
+                format: InventoryFormat::from_static(InventoryFormat::CSV),
+                prefix: Some("photos".to_string()),
+            },
+        },
+        filter: None,
+        id: id.to_string(),
+        included_object_versions: InventoryIncludedObjectVersions::from_static(
+            InventoryIncludedObjectVersions::CURRENT,
+        ),
+        is_enabled: true,
+        optional_fields: None,
+        schedule: InventorySchedule {
+            frequency: InventoryFrequency::from_static(InventoryFrequency::DAILY),
+        },
+    }
+}
+
+async fn read_object(state: &SharedState, bucket: &str, key: &str) -> String {
+    let mut file = enigma_s3::ops::retrieve_object(state, bucket, key)
+        .await
+        .unwrap();
+    let mut data = String::new();
+    file.reader.read_to_string(&mut data).await.unwrap();
+    data
+}
+
+#[tokio::test]
+async fn report_has_one_row_per_object() {
+    let tmp = tempfile::tempdir().unwrap();
+    let mock = MockStorageProvider::new("mock");
+    let state = test_state(tmp.path(), &mock);
+
+    // No configured bucket, no report
+    assert!(generate_report(&state).await.unwrap().is_none());
+
+    for i in 0..OBJECT_COUNT {
+        let key = format!("2026/img-{i:03}.jpg");
+        enigma_s3::ops::store_object(&state, "photos", &key, key.as_bytes(), None)
+            .await
+            .unwrap();
+    }
+    handle_put_bucket_inventory_configuration(&state, "photos", "daily", daily_inventory("daily"))
+        .await
+        .unwrap();
+    let stored = handle_get_bucket_inventory_configuration(&state, "photos", "daily")
+        .await
+        .unwrap()
+        .output
+        .inventory_configuration
+        .unwrap();
+    assert_eq!(stored.id, "daily");
+    assert!(stored.is_enabled);
+    assert_eq!(
+        stored.schedule.frequency.as_str(),
+        InventoryFrequency::DAILY
+    );
+    assert_eq!(
+        stored.destination.s3_bucket_destination.prefix.as_deref(),
+        Some("photos")
+    );
+
+    let report = generate_report(&state).await.unwrap().unwrap();
+    assert_eq!(report.rows, OBJECT_COUNT as u64);
+
+    let csv = read_object(&state, REPORTS_BUCKET, &report.key).await;
+    assert_eq!(report.size, csv.len() as u64);
+    let mut lines = csv.lines();
+    assert_eq!(
+        lines.next(),
+        Some("bucket,key,size,etag,storage_class,is_latest,last_modified")
+    );
+    let rows: Vec<&str> = lines.collect();
+    assert_eq!(rows.len(), OBJECT_COUNT);
+    assert!(rows[0].starts_with("\"photos\",\"2026/img-000.jpg\",\"16\","));
+    assert!(
+        rows.iter()
+            .all(|row| row.contains(",\"STANDARD\",\"true\","))
+    );
+
+    let manifest: serde_json::Value =
+        serde_json::from_str(&read_object(&state, REPORTS_BUCKET, MANIFEST_KEY).await).unwrap();
+    assert_eq!(manifest["files"][0]["key"], report.key.as_str());
+    assert_eq!(manifest["files"][0]["rows"], OBJECT_COUNT);
+    assert_eq!(
+        state.db.lock().unwrap().latest_inventory_report().unwrap(),
+        Some(report)
+    );
+}
+
+#[tokio::test]
+async fn unknown_configuration_is_not_found() {
+    let tmp = tempfile::tempdir().unwrap();
+    let mock = MockStorageProvider::new("mock");
+    let state = test_state(tmp.path(), &mock);
+
+    let err = handle_get_bucket_inventory_configuration(&state, "photos", "daily")
+        .await
+        .unwrap_err();
+    assert_eq!(err.code().as_str(), "NoSuchConfiguration");
+
+    // The ID in the body must match the one in the query
+    handle_put_bucket_inventory_configuration(&state, "photos", "daily", daily_inventory("weekly"))
+        .await
+        .unwrap_err();
+}
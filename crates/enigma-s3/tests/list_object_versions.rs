@@ -0,0 +1,166 @@
+/// ListObjectVersions over the wire: an AWS SDK client, wired to the S3
+/// service in-process, parses the `Version` and `DeleteMarker` elements of
+/// a bucket holding a key with 3 versions and a delete marker, and pages
+/// through it with `key-marker` and `version-id-marker`.
+///
+/// Run:
+///   cargo test -p enigma-s3 --test list_object_versions -- --nocapture
+use aws_sdk_s3::operation::list_object_versions::ListObjectVersionsOutput;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{BucketVersioningStatus, VersioningConfiguration};
+
+use enigma_s3::SharedState;
+
+mod common;
+
+use common::{TestState, sdk_client};
+
+fn test_state(dir: &std::path::Path) -> SharedState {
+    TestState::new(dir)
+        .local_provider()
+        .bucket("bucket")
+        .build()
+}
+
+async fn put(client: &aws_sdk_s3::Client, key: &str, data: &'static [u8]) -> String {
+    client
+        .put_object()
+        .bucket("bucket")
+        .key(key)
+        .body(ByteStream::from_static(data))
+        .send()
+        .await
+        .unwrap()
+        .version_id
+        .unwrap()
+}
+
+/// "a.txt" with 3 versions under a delete marker, and "b.txt" with one
+/// version. Returns the version IDs of a.txt (oldest first), its delete
+/// marker and b.txt.
+async fn populate(client: &aws_sdk_s3::Client) -> (Vec<String>, String, String) {
+    client
+        .put_bucket_versioning()
+        .bucket("bucket")
+        .versioning_configuration(
+            VersioningConfiguration::builder()
+                .status(BucketVersioningStatus::Enabled)
+                .build(),
+        )
+        .send()
+        .await
+        .unwrap();
+
+    let mut a = Vec::new();
+    for data in [&b"one"[..], b"two!", b"three"] {
+        a.push(put(client, "a.txt", data).await);
+    }
+    let marker = client
+        .delete_object()
+        .bucket("bucket")
+        .key("a.txt")
+        .send()
+        .await
+        .unwrap()
+        .version_id
+        .unwrap();
+    let b = put(client, "b.txt", b"bee").await;
+    (a, marker, b)
+}
+
+async fn list(
+    client: &aws_sdk_s3::Client,
+    max_keys: i32,
+    markers: Option<(String, String)>,
+) -> ListObjectVersionsOutput {
+    let (key_marker, version_id_marker) = markers.unzip();
+    client
+        .list_object_versions()
+        .bucket("bucket")
+        .max_keys(max_keys)
+        .set_key_marker(key_marker)
+        .set_version_id_marker(version_id_marker)
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn versions_and_delete_markers_are_listed_separately() {
+    let dir = tempfile::tempdir().unwrap();
+    let client = sdk_client(test_state(dir.path()));
+    let (a, marker, b) = populate(&client).await;
+
+    let resp = list(&client, 1000, None).await;
+    assert_eq!(resp.is_truncated, Some(false));
+
+    let versions: Vec<(&str, &str, i64, bool)> = resp
+        .versions()
+        .iter()
+        .map(|v| {
+            (
+                v.key().unwrap(),
+                v.version_id().unwrap(),
+                v.size().unwrap(),
+                v.is_latest().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        versions,
+        vec![
+            ("a.txt", a[2].as_str(), 5, false),
+            ("a.txt", a[1].as_str(), 4, false),
+            ("a.txt", a[0].as_str(), 3, false),
+            ("b.txt", b.as_str(), 3, true),
+        ]
+    );
+    assert!(
+        resp.versions().iter().all(|v| {
+            v.e_tag().is_some_and(|e| e.starts_with('"')) && v.last_modified().is_some()
+        })
+    );
+
+    // The delete marker is the newest entry for a.txt, so it is latest
+    let markers = resp.delete_markers();
+    assert_eq!(markers.len(), 1);
+    assert_eq!(markers[0].key(), Some("a.txt"));
+    assert_eq!(markers[0].version_id(), Some(marker.as_str()));
+    assert_eq!(markers[0].is_latest(), Some(true));
+    assert!(markers[0].last_modified().is_some());
+}
+
+#[tokio::test]
+async fn key_and_version_id_markers_page_through_versions() {
+    let dir = tempfile::tempdir().unwrap();
+    let client = sdk_client(test_state(dir.path()));
+    let (a, marker, b) = populate(&client).await;
+
+    // Newest first across versions and delete markers alike
+    let mut listed = Vec::new();
+    let mut markers = None;
+    loop {
+        let resp = list(&client, 2, markers).await;
+        listed.extend(
+            resp.delete_markers()
+                .iter()
+                .filter_map(|m| m.version_id().map(str::to_string)),
+        );
+        listed.extend(
+            resp.versions()
+                .iter()
+                .filter_map(|v| v.version_id().map(str::to_string)),
+        );
+        if resp.is_truncated != Some(true) {
+            break;
+        }
+        markers = Some((
+            resp.next_key_marker.unwrap(),
+            resp.next_version_id_marker.unwrap(),
+        ));
+    }
+    assert_eq!(
+        listed,
+        vec![marker, a[2].clone(), a[1].clone(), a[0].clone(), b]
+    );
+}
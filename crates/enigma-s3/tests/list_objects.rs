@@ -0,0 +1,225 @@
+/// Listing tests: `delimiter` rolls keys up into common prefixes (virtual
+/// directories), the way `aws s3 ls s3://bucket/prefix/` lists a folder,
+/// and both ListObjectsV2 and ListObjects (v1) paginate across them.
+///
+/// Run:
+///   cargo test -p enigma-s3 --test list_objects -- --nocapture
+use aws_sdk_s3::error::ProvideErrorMetadata;
+
+use enigma_s3::SharedState;
+
+mod common;
+
+use common::{TestState, sdk_client};
+
+const KEYS: &[&str] = &[
+    "README.md",
+    "docs/guide.md",
+    "docs/api/v1.md",
+    "docs/api/v2.md",
+    "photos/2024/a.jpg",
+    "photos/2024/b.jpg",
+    "photos/2025/c.jpg",
+    "photos/cover.jpg",
+    "photos/index.html",
+];
+
+/// State whose manifest holds `KEYS` in "bucket". Listing only reads the
+/// manifest, so no chunk data is written.
+fn test_state(dir: &std::path::Path) -> SharedState {
+    let state = TestState::new(dir).local_provider();
+    let ns_id = state.db().create_namespace("bucket").unwrap();
+    for key in KEYS {
+        state
+            .db()
+            .insert_object(ns_id, key, 5, "etag", None, 0, "test-key-1")
+            .unwrap();
+    }
+    state.build()
+}
+
+/// What `aws s3 ls s3://bucket/<prefix>` prints: common prefixes then keys,
+/// gathered across every page of a ListObjectsV2 with delimiter `/`.
+async fn s3_ls(
+    client: &aws_sdk_s3::Client,
+    prefix: &str,
+    page_size: i32,
+) -> (Vec<String>, Vec<String>) {
+    let (mut dirs, mut files) = (Vec::new(), Vec::new());
+    let mut token = None;
+    loop {
+        let resp = client
+            .list_objects_v2()
+            .bucket("bucket")
+            .prefix(prefix)
+            .delimiter("/")
+            .max_keys(page_size)
+            .set_continuation_token(token)
+            .send()
+            .await
+            .unwrap();
+        dirs.extend(
+            resp.common_prefixes()
+                .iter()
+                .map(|p| p.prefix().unwrap().to_string()),
+        );
+        files.extend(resp.contents().iter().map(|o| o.key().unwrap().to_string()));
+        token = resp.next_continuation_token().map(str::to_string);
+        if !resp.is_truncated().unwrap_or(false) {
+            assert!(token.is_none());
+            return (dirs, files);
+        }
+    }
+}
+
+#[tokio::test]
+async fn ls_bucket_root_shows_top_level_folders() {
+    let dir = tempfile::tempdir().unwrap();
+    let client = sdk_client(test_state(dir.path()));
+
+    let (dirs, files) = s3_ls(&client, "", 1000).await;
+    assert_eq!(dirs, ["docs/", "photos/"]);
+    assert_eq!(files, ["README.md"]);
+}
+
+#[tokio::test]
+async fn ls_folder_lists_direct_children() {
+    let dir = tempfile::tempdir().unwrap();
+    let client = sdk_client(test_state(dir.path()));
+
+    let (dirs, files) = s3_ls(&client, "photos/", 1000).await;
+    assert_eq!(dirs, ["photos/2024/", "photos/2025/"]);
+    assert_eq!(files, ["photos/cover.jpg", "photos/index.html"]);
+
+    let (dirs, files) = s3_ls(&client, "docs/api/", 1000).await;
+    assert!(dirs.is_empty());
+    assert_eq!(files, ["docs/api/v1.md", "docs/api/v2.md"]);
+
+    // A prefix that stops mid-name groups on the rest of the key
+    let (dirs, files) = s3_ls(&client, "pho", 1000).await;
+    assert_eq!(dirs, ["photos/"]);
+    assert!(files.is_empty());
+}
+
+#[tokio::test]
+async fn common_prefixes_count_toward_page_size() {
+    let dir = tempfile::tempdir().unwrap();
+    let client = sdk_client(test_state(dir.path()));
+
+    let resp = client
+        .list_objects_v2()
+        .bucket("bucket")
+        .prefix("photos/")
+        .delimiter("/")
+        .max_keys(1)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.key_count(), Some(1));
+    assert_eq!(resp.common_prefixes()[0].prefix(), Some("photos/2024/"));
+    assert!(resp.contents().is_empty());
+    assert_eq!(resp.is_truncated(), Some(true));
+
+    // Every page size walks the same entries, each exactly once
+    for page_size in 1..=5 {
+        let (dirs, files) = s3_ls(&client, "photos/", page_size).await;
+        assert_eq!(
+            dirs,
+            ["photos/2024/", "photos/2025/"],
+            "page size {page_size}"
+        );
+        assert_eq!(
+            files,
+            ["photos/cover.jpg", "photos/index.html"],
+            "page size {page_size}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn no_delimiter_lists_recursively() {
+    let dir = tempfile::tempdir().unwrap();
+    let client = sdk_client(test_state(dir.path()));
+
+    let resp = client
+        .list_objects_v2()
+        .bucket("bucket")
+        .prefix("photos/")
+        .send()
+        .await
+        .unwrap();
+    assert!(resp.common_prefixes().is_empty());
+    let keys: Vec<&str> = resp.contents().iter().map(|o| o.key().unwrap()).collect();
+    assert_eq!(
+        keys,
+        [
+            "photos/2024/a.jpg",
+            "photos/2024/b.jpg",
+            "photos/2025/c.jpg",
+            "photos/cover.jpg",
+            "photos/index.html",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn malformed_continuation_token_is_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let client = sdk_client(test_state(dir.path()));
+
+    let err = client
+        .list_objects_v2()
+        .bucket("bucket")
+        .continuation_token("not base64!")
+        .send()
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Some("InvalidArgument"));
+}
+
+#[tokio::test]
+async fn list_objects_v1_paginates_by_marker() {
+    let dir = tempfile::tempdir().unwrap();
+    let client = sdk_client(test_state(dir.path()));
+
+    let mut entries = Vec::new();
+    let mut marker: Option<String> = None;
+    loop {
+        let resp = client
+            .list_objects()
+            .bucket("bucket")
+            .delimiter("/")
+            .max_keys(1)
+            .set_marker(marker.clone())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.marker(), marker.as_deref());
+        entries.extend(
+            resp.common_prefixes()
+                .iter()
+                .map(|p| p.prefix().unwrap().to_string()),
+        );
+        entries.extend(resp.contents().iter().map(|o| o.key().unwrap().to_string()));
+        if !resp.is_truncated().unwrap_or(false) {
+            assert!(resp.next_marker().is_none());
+            break;
+        }
+        marker = resp.next_marker().map(str::to_string);
+    }
+    assert_eq!(entries, ["README.md", "docs/", "photos/"]);
+}
+
+#[tokio::test]
+async fn list_unknown_bucket_fails() {
+    let dir = tempfile::tempdir().unwrap();
+    let client = sdk_client(test_state(dir.path()));
+
+    let err = client
+        .list_objects_v2()
+        .bucket("missing")
+        .send()
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Some("NoSuchBucket"));
+}
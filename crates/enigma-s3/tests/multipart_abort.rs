@@ -0,0 +1,123 @@
+use enigma_s3::SharedState;
+/// Multipart abort test: chunks stored by a CompleteMultipartUpload that
+/// failed partway are tracked against the upload, so aborting it deletes
+/// them from the provider and a retried completion does not reference them
+/// twice.
+///
+/// Run:
+///   cargo test -p enigma-s3 --test multipart_abort -- --nocapture
+use enigma_s3::multipart::{
+    abort_upload, handle_complete_multipart_upload, handle_create_multipart_upload,
+    handle_upload_part,
+};
+use enigma_storage::mock::{MockMethod, MockStorageProvider};
+use s3s::dto::StreamingBlob;
+use tokio::io::AsyncReadExt;
+
+mod common;
+
+use common::{TestState, generate_data};
+
+const PART_SIZE: usize = 5 * 1024 * 1024;
+const PART_COUNT: usize = 6;
+
+fn test_state(dir: &std::path::Path, mock: &MockStorageProvider) -> SharedState {
+    TestState::new(dir)
+        .mock_provider(mock)
+        .bucket("bucket")
+        .configure(|c| {
+            c.compression.enabled = false;
+            c.multipart_part_spill_dir = Some(dir.join("spill").display().to_string());
+        })
+        .build()
+}
+
+/// Start an upload and upload all its parts. Returns the upload ID and the
+/// object's content.
+async fn upload_parts(state: &SharedState, key: &str) -> (String, Vec<u8>) {
+    let upload_id = handle_create_multipart_upload(state, "bucket", key)
+        .await
+        .unwrap()
+        .output
+        .upload_id
+        .unwrap();
+    let mut content = Vec::new();
+    for part_number in 1..=PART_COUNT {
+        let data = generate_data(PART_SIZE, part_number as u64);
+        content.extend_from_slice(&data);
+        let body = StreamingBlob::from(s3s::Body::from(data));
+        handle_upload_part(state, &upload_id, part_number as i32, Some(body))
+            .await
+            .unwrap();
+    }
+    (upload_id, content)
+}
+
+/// Start a completion that fails once two chunks reached the provider.
+async fn fail_completion(state: &SharedState, mock: &MockStorageProvider, upload_id: &str) {
+    mock.inject_failure(MockMethod::UploadChunk, 2, "connection reset");
+    handle_complete_multipart_upload(state, "bucket", "big.bin", upload_id)
+        .await
+        .err()
+        .unwrap();
+    mock.clear_failure(MockMethod::UploadChunk);
+}
+
+fn count(state: &SharedState, sql: &str) -> i64 {
+    let db = state.db.lock().unwrap();
+    db.conn().query_row(sql, [], |row| row.get(0)).unwrap()
+}
+
+#[tokio::test]
+async fn abort_deletes_chunks_of_a_failed_completion() {
+    let tmp = tempfile::tempdir().unwrap();
+    let mock = MockStorageProvider::new("mock");
+    let state = test_state(tmp.path(), &mock);
+
+    let (upload_id, _) = upload_parts(&state, "big.bin").await;
+    fail_completion(&state, &mock, &upload_id).await;
+    assert_eq!(mock.len(), 2);
+    assert_eq!(
+        count(&state, "SELECT COUNT(*) FROM multipart_part_chunks"),
+        2
+    );
+
+    abort_upload(&state, &upload_id).await.unwrap();
+    assert!(mock.is_empty());
+    assert_eq!(count(&state, "SELECT COUNT(*) FROM chunks"), 0);
+    assert_eq!(
+        count(&state, "SELECT COUNT(*) FROM multipart_part_chunks"),
+        0
+    );
+    assert_eq!(count(&state, "SELECT COUNT(*) FROM multipart_uploads"), 0);
+}
+
+#[tokio::test]
+async fn retried_completion_references_each_chunk_once() {
+    let tmp = tempfile::tempdir().unwrap();
+    let mock = MockStorageProvider::new("mock");
+    let state = test_state(tmp.path(), &mock);
+
+    let (upload_id, content) = upload_parts(&state, "big.bin").await;
+    fail_completion(&state, &mock, &upload_id).await;
+
+    handle_complete_multipart_upload(&state, "bucket", "big.bin", &upload_id)
+        .await
+        .unwrap();
+    let chunks = count(&state, "SELECT COUNT(*) FROM chunks");
+    assert!(chunks > 2);
+    assert_eq!(mock.len() as i64, chunks);
+    assert_eq!(count(&state, "SELECT MAX(ref_count) FROM chunks"), 1);
+    assert_eq!(count(&state, "SELECT COUNT(*) FROM object_chunks"), chunks);
+    assert_eq!(
+        count(&state, "SELECT COUNT(*) FROM multipart_part_chunks"),
+        0
+    );
+
+    let mut file = enigma_s3::ops::retrieve_object(&state, "bucket", "big.bin")
+        .await
+        .unwrap();
+    let mut data = Vec::new();
+    file.reader.read_to_end(&mut data).await.unwrap();
+    assert!(data == content);
+}
@@ -0,0 +1,140 @@
+/// Multipart limit tests: parts other than the last must reach the minimum
+/// part size, an upload may not have more than the maximum number of parts,
+/// and a single part may not exceed the maximum part size.
+///
+/// Run:
+///   cargo test -p enigma-s3 --test multipart_limits -- --nocapture
+use std::sync::Arc;
+
+use enigma_s3::multipart::{
+    MultipartLimits, handle_complete_multipart_upload, handle_create_multipart_upload,
+    handle_upload_part,
+};
+use enigma_s3::{EnigmaS3State, SharedState};
+use s3s::S3ErrorCode;
+use s3s::dto::StreamingBlob;
+
+mod common;
+
+use common::TestState;
+
+/// Small limits so the tests run on a few KB of data.
+const LIMITS: MultipartLimits = MultipartLimits {
+    min_part_size_bytes: 1024,
+    max_parts: 3,
+    max_part_size_bytes: 4096,
+};
+
+fn test_state(dir: &std::path::Path) -> SharedState {
+    let state = TestState::new(dir)
+        .local_provider()
+        .bucket("bucket")
+        .configure(|c| {
+            c.multipart_part_spill_dir = Some(dir.join("spill").display().to_string());
+        })
+        .into_state();
+    Arc::new(EnigmaS3State {
+        multipart_limits: LIMITS,
+        ..state
+    })
+}
+
+/// Start an upload for `key` and upload one part of each given size.
+async fn upload(state: &SharedState, key: &str, part_sizes: &[usize]) -> String {
+    let upload_id = handle_create_multipart_upload(state, "bucket", key)
+        .await
+        .unwrap()
+        .output
+        .upload_id
+        .unwrap();
+    for (i, size) in part_sizes.iter().enumerate() {
+        let body = StreamingBlob::from(s3s::Body::from(vec![i as u8; *size]));
+        handle_upload_part(state, &upload_id, i as i32 + 1, Some(body))
+            .await
+            .unwrap();
+    }
+    upload_id
+}
+
+async fn complete(state: &SharedState, key: &str, upload_id: &str) -> Result<(), S3ErrorCode> {
+    handle_complete_multipart_upload(state, "bucket", key, upload_id)
+        .await
+        .map(|_| ())
+        .map_err(|e| e.code().clone())
+}
+
+fn object_exists(state: &SharedState, key: &str) -> bool {
+    let db = state.db.lock().unwrap();
+    let ns = db.get_namespace_id("bucket").unwrap().unwrap();
+    db.get_object(ns, key).unwrap().is_some()
+}
+
+#[test]
+fn default_limits_match_s3() {
+    let limits = MultipartLimits::default();
+    assert_eq!(limits.min_part_size_bytes, 5 * 1024 * 1024);
+    assert_eq!(limits.max_parts, 10_000);
+    assert_eq!(limits.max_part_size_bytes, 5 * 1024 * 1024 * 1024);
+}
+
+#[tokio::test]
+async fn small_last_part_is_accepted() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = test_state(tmp.path());
+
+    let upload_id = upload(&state, "ok.bin", &[1024, 1024, 10]).await;
+    complete(&state, "ok.bin", &upload_id).await.unwrap();
+    assert!(object_exists(&state, "ok.bin"));
+}
+
+#[tokio::test]
+async fn small_non_last_part_is_rejected() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = test_state(tmp.path());
+
+    let upload_id = upload(&state, "small.bin", &[1024, 1023, 2048]).await;
+    let err = complete(&state, "small.bin", &upload_id).await.unwrap_err();
+    assert_eq!(err, S3ErrorCode::EntityTooSmall);
+    assert!(!object_exists(&state, "small.bin"));
+
+    // The upload is left in place so the client can re-upload the part
+    let body = StreamingBlob::from(s3s::Body::from(vec![1u8; 1024]));
+    handle_upload_part(&state, &upload_id, 2, Some(body))
+        .await
+        .unwrap();
+    complete(&state, "small.bin", &upload_id).await.unwrap();
+    assert!(object_exists(&state, "small.bin"));
+}
+
+#[tokio::test]
+async fn too_many_parts_are_rejected() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = test_state(tmp.path());
+
+    let upload_id = upload(&state, "many.bin", &[1024, 1024, 1024, 1024]).await;
+    let err = complete(&state, "many.bin", &upload_id).await.unwrap_err();
+    assert_eq!(err, S3ErrorCode::InvalidRequest);
+    assert!(!object_exists(&state, "many.bin"));
+}
+
+#[tokio::test]
+async fn oversized_part_is_rejected_on_upload() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = test_state(tmp.path());
+
+    let upload_id = upload(&state, "big.bin", &[4096]).await;
+    let body = StreamingBlob::from(s3s::Body::from(vec![0u8; 4097]));
+    let err = handle_upload_part(&state, &upload_id, 2, Some(body))
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(*err.code(), S3ErrorCode::EntityTooLarge);
+
+    // The rejected part was not recorded and its spill file is gone
+    assert_eq!(
+        std::fs::read_dir(tmp.path().join("spill")).unwrap().count(),
+        1
+    );
+    complete(&state, "big.bin", &upload_id).await.unwrap();
+    assert!(object_exists(&state, "big.bin"));
+}
@@ -0,0 +1,160 @@
+/// Streaming multipart completion test: parts are spilled to disk as they
+/// are uploaded, and CompleteMultipartUpload reads them back one at a time,
+/// so heap usage while completing 10 × 1 MB parts stays within a 50 MB
+/// budget. Heap usage is measured with a counting global allocator.
+///
+/// Run:
+///   cargo test -p enigma-s3 --test multipart_streaming -- --nocapture
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use enigma_s3::multipart::{
+    MultipartLimits, handle_complete_multipart_upload, handle_create_multipart_upload,
+    handle_upload_part,
+};
+use enigma_s3::put::{StreamChunker, chunk_data_owned};
+use enigma_s3::{EnigmaS3State, SharedState};
+use s3s::dto::StreamingBlob;
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncReadExt;
+
+mod common;
+
+use common::{TestState, generate_data};
+
+const PART_SIZE: usize = 1024 * 1024;
+const PART_COUNT: usize = 10;
+const MEMORY_BUDGET: usize = 50 * 1024 * 1024;
+
+/// Tracks live heap bytes and their high-water mark.
+struct CountingAlloc;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            let now = ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(now, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+fn test_state(dir: &std::path::Path) -> SharedState {
+    let state = TestState::new(dir)
+        .local_provider()
+        .bucket("bucket")
+        .configure(|c| {
+            c.compression.enabled = false;
+            c.multipart_part_spill_dir = Some(dir.join("spill").display().to_string());
+        })
+        .into_state();
+    Arc::new(EnigmaS3State {
+        // The parts here are 1 MiB, below the S3 default minimum
+        multipart_limits: MultipartLimits {
+            min_part_size_bytes: PART_SIZE as u64,
+            ..Default::default()
+        },
+        ..state
+    })
+}
+
+fn spill_files(dir: &std::path::Path) -> usize {
+    std::fs::read_dir(dir.join("spill")).unwrap().count()
+}
+
+#[tokio::test]
+async fn complete_streams_parts_within_memory_budget() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = test_state(tmp.path());
+
+    let upload_id = handle_create_multipart_upload(&state, "bucket", "big.bin")
+        .await
+        .unwrap()
+        .output
+        .upload_id
+        .unwrap();
+
+    let mut expected = Sha256::new();
+    for part_number in 1..=PART_COUNT {
+        let data = generate_data(PART_SIZE, part_number as u64);
+        expected.update(&data);
+        let body = StreamingBlob::from(s3s::Body::from(data));
+        handle_upload_part(&state, &upload_id, part_number as i32, Some(body))
+            .await
+            .unwrap();
+    }
+    let expected = format!("{:x}", expected.finalize());
+
+    // Part data lives in spill files, not in the manifest
+    assert_eq!(spill_files(tmp.path()), PART_COUNT);
+    let inline: i64 = state
+        .db
+        .lock()
+        .unwrap()
+        .conn()
+        .query_row(
+            "SELECT IFNULL(SUM(length(data)), 0) FROM multipart_parts",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(inline, 0);
+
+    let baseline = ALLOCATED.load(Ordering::Relaxed);
+    PEAK.store(baseline, Ordering::Relaxed);
+    let resp = handle_complete_multipart_upload(&state, "bucket", "big.bin", &upload_id)
+        .await
+        .unwrap();
+    let growth = PEAK.load(Ordering::Relaxed).saturating_sub(baseline);
+    println!(
+        "Heap growth during completion: {} MB (budget {} MB)",
+        growth / (1024 * 1024),
+        MEMORY_BUDGET / (1024 * 1024)
+    );
+    assert!(
+        growth <= MEMORY_BUDGET,
+        "completion allocated {growth} bytes (budget {MEMORY_BUDGET})"
+    );
+
+    assert_eq!(
+        resp.output.e_tag.unwrap().trim_matches('"'),
+        expected.as_str()
+    );
+    assert_eq!(spill_files(tmp.path()), 0);
+
+    let mut file = enigma_s3::ops::retrieve_object(&state, "bucket", "big.bin")
+        .await
+        .unwrap();
+    assert_eq!(file.size, (PART_SIZE * PART_COUNT) as u64);
+    let mut data = Vec::new();
+    file.reader.read_to_end(&mut data).await.unwrap();
+    assert_eq!(format!("{:x}", Sha256::digest(&data)), expected);
+}
+
+#[test]
+fn stream_chunker_matches_in_memory_chunking() {
+    let data = generate_data(40 * 1024 * 1024, 7);
+
+    let mut chunker = StreamChunker::default();
+    let mut streamed = Vec::new();
+    for piece in data.chunks(PART_SIZE + 17) {
+        streamed.extend(chunker.push(piece));
+    }
+    streamed.extend(chunker.finish());
+
+    assert!(streamed.len() > 1);
+    assert_eq!(streamed, chunk_data_owned(&data));
+}
@@ -0,0 +1,147 @@
+use enigma_s3::SharedState;
+/// ListMultipartUploads test: pending uploads are listed with their key,
+/// upload ID and initiation time, paginate by key/upload-ID marker, and
+/// disappear once aborted or expired.
+///
+/// Run:
+///   cargo test -p enigma-s3 --test multipart_uploads -- --nocapture
+use enigma_s3::multipart::{
+    abort_expired_uploads, handle_create_multipart_upload, handle_list_multipart_uploads,
+};
+use s3s::S3ErrorCode;
+use s3s::dto::ListMultipartUploadsOutput;
+
+mod common;
+
+use common::TestState;
+
+fn test_state(dir: &std::path::Path) -> SharedState {
+    TestState::new(dir)
+        .local_provider()
+        .bucket("bucket")
+        .build()
+}
+
+/// Start an upload for `key` and return its upload ID.
+async fn create(state: &SharedState, key: &str) -> String {
+    let resp = handle_create_multipart_upload(state, "bucket", key)
+        .await
+        .unwrap();
+    resp.output.upload_id.unwrap()
+}
+
+async fn list(
+    state: &SharedState,
+    key_marker: &str,
+    upload_id_marker: &str,
+    max_uploads: u32,
+) -> ListMultipartUploadsOutput {
+    handle_list_multipart_uploads(
+        state,
+        "bucket",
+        "",
+        key_marker,
+        upload_id_marker,
+        max_uploads,
+    )
+    .await
+    .unwrap()
+    .output
+}
+
+fn keys(output: &ListMultipartUploadsOutput) -> Vec<String> {
+    output
+        .uploads
+        .iter()
+        .flatten()
+        .map(|u| u.key.clone().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn list_shrinks_after_abort() {
+    let dir = tempfile::tempdir().unwrap();
+    let state = test_state(dir.path());
+
+    let mut ids = Vec::new();
+    for key in ["e.bin", "a.bin", "d.bin", "b.bin", "c.bin"] {
+        ids.push((key, create(&state, key).await));
+    }
+
+    let output = list(&state, "", "", 1000).await;
+    assert_eq!(output.bucket.as_deref(), Some("bucket"));
+    assert_eq!(output.is_truncated, Some(false));
+    assert_eq!(output.max_uploads, Some(1000));
+    assert_eq!(keys(&output), ["a.bin", "b.bin", "c.bin", "d.bin", "e.bin"]);
+    for upload in output.uploads.as_ref().unwrap() {
+        let key = upload.key.as_deref().unwrap();
+        let (_, id) = ids.iter().find(|(k, _)| *k == key).unwrap();
+        assert_eq!(upload.upload_id.as_ref(), Some(id));
+        assert!(upload.initiated.is_some());
+        assert!(upload.owner.is_some());
+    }
+
+    {
+        let db = state.db.lock().unwrap();
+        db.abort_multipart_upload(&ids[0].1).unwrap();
+        db.abort_multipart_upload(&ids[1].1).unwrap();
+    }
+
+    let output = list(&state, "", "", 1000).await;
+    assert_eq!(keys(&output), ["b.bin", "c.bin", "d.bin"]);
+}
+
+#[tokio::test]
+async fn list_paginates_by_marker() {
+    let dir = tempfile::tempdir().unwrap();
+    let state = test_state(dir.path());
+    for key in ["a.bin", "b.bin", "c.bin"] {
+        create(&state, key).await;
+    }
+
+    let page = list(&state, "", "", 2).await;
+    assert_eq!(keys(&page), ["a.bin", "b.bin"]);
+    assert_eq!(page.is_truncated, Some(true));
+    assert_eq!(page.next_key_marker.as_deref(), Some("b.bin"));
+
+    let page = list(
+        &state,
+        page.next_key_marker.as_deref().unwrap(),
+        page.next_upload_id_marker.as_deref().unwrap(),
+        2,
+    )
+    .await;
+    assert_eq!(keys(&page), ["c.bin"]);
+    assert_eq!(page.is_truncated, Some(false));
+
+    // A key marker alone skips every upload of that key
+    assert_eq!(
+        keys(&list(&state, "a.bin", "", 10).await),
+        ["b.bin", "c.bin"]
+    );
+}
+
+#[tokio::test]
+async fn list_unknown_bucket_fails() {
+    let dir = tempfile::tempdir().unwrap();
+    let state = test_state(dir.path());
+    let err = handle_list_multipart_uploads(&state, "missing", "", "", "", 1000)
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(*err.code(), S3ErrorCode::NoSuchBucket);
+}
+
+#[tokio::test]
+async fn expired_uploads_are_aborted() {
+    let dir = tempfile::tempdir().unwrap();
+    let state = test_state(dir.path());
+    create(&state, "fresh.bin").await;
+
+    assert_eq!(abort_expired_uploads(&state, 24).await.unwrap(), 0);
+    assert_eq!(keys(&list(&state, "", "", 1000).await), ["fresh.bin"]);
+
+    // A zero-hour expiry treats every pending upload as abandoned
+    assert_eq!(abort_expired_uploads(&state, 0).await.unwrap(), 1);
+    assert!(list(&state, "", "", 1000).await.uploads.is_none());
+}
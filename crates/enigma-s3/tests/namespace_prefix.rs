@@ -0,0 +1,227 @@
+/// Tenant restriction tests: an AWS SDK client per tenant, wired to the S3
+/// service in-process, checks that a caller restricted to a namespace
+/// prefix only lists and reaches the namespaces under it, while an
+/// unrestricted caller sees all of them.
+///
+/// Run:
+///   cargo test -p enigma-s3 --test namespace_prefix -- --nocapture
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use aws_sdk_s3::error::ProvideErrorMetadata;
+use aws_sdk_s3::primitives::ByteStream;
+use s3s::auth::{S3Auth, SecretKey};
+use s3s::s3_error;
+use s3s::service::S3ServiceBuilder;
+
+use enigma_s3::SharedState;
+use enigma_s3::auth::{AccessControl, EnigmaS3Access};
+use enigma_s3::service::EnigmaS3Service;
+
+mod common;
+
+use common::{SECRET_KEY, TestState};
+
+/// Accepts any access key in `tenants`, each with [`SECRET_KEY`], and
+/// restricts it to the mapped namespace prefix.
+struct Tenants(HashMap<&'static str, Option<&'static str>>);
+
+#[async_trait]
+impl S3Auth for Tenants {
+    async fn get_secret_key(&self, access_key: &str) -> s3s::S3Result<SecretKey> {
+        if self.0.contains_key(access_key) {
+            Ok(SecretKey::from(SECRET_KEY.to_string()))
+        } else {
+            Err(s3_error!(InvalidAccessKeyId))
+        }
+    }
+}
+
+#[async_trait]
+impl AccessControl for Tenants {
+    async fn has_permission(&self, _access_key: &str, _permission: &str) -> anyhow::Result<bool> {
+        Ok(true)
+    }
+
+    async fn namespace_prefix(&self, access_key: &str) -> anyhow::Result<Option<String>> {
+        Ok(self.0.get(access_key).copied().flatten().map(String::from))
+    }
+}
+
+fn tenants() -> Tenants {
+    Tenants(HashMap::from([
+        ("admin", None),
+        ("team-a", Some("team-a/")),
+        ("team-b", Some("team-b/")),
+        ("team-c", Some("team-c-")),
+    ]))
+}
+
+fn test_state(dir: &std::path::Path) -> SharedState {
+    let mut state = TestState::new(dir).local_provider();
+    for name in [
+        "team-a/logs",
+        "team-a/media",
+        "team-b/logs",
+        "team-c-data",
+        "shared",
+    ] {
+        state = state.bucket(name);
+    }
+    let state = state.build();
+    let _ = state.access_control.set(Arc::new(tenants()));
+    state
+}
+
+/// AWS SDK client signing as `access_key`, whose requests go straight to
+/// an in-process S3 service.
+fn sdk_client(state: SharedState, access_key: &str) -> aws_sdk_s3::Client {
+    let mut builder = S3ServiceBuilder::new(EnigmaS3Service::new(state.clone()));
+    builder.set_auth(tenants());
+    builder.set_access(EnigmaS3Access::new(state));
+    common::client_for(builder, access_key)
+}
+
+async fn bucket_names(state: &SharedState, access_key: &str) -> Vec<String> {
+    let out = sdk_client(state.clone(), access_key)
+        .list_buckets()
+        .send()
+        .await
+        .unwrap();
+    let mut names: Vec<String> = out
+        .buckets()
+        .iter()
+        .filter_map(|b| b.name().map(String::from))
+        .collect();
+    names.sort();
+    names
+}
+
+#[tokio::test]
+async fn prefixed_caller_only_sees_its_namespaces() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = test_state(tmp.path());
+
+    assert_eq!(
+        bucket_names(&state, "team-a").await,
+        vec!["team-a/logs", "team-a/media"]
+    );
+    assert_eq!(bucket_names(&state, "team-b").await, vec!["team-b/logs"]);
+}
+
+#[tokio::test]
+async fn caller_without_prefix_sees_every_namespace() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = test_state(tmp.path());
+
+    assert_eq!(
+        bucket_names(&state, "admin").await,
+        vec![
+            "shared",
+            "team-a/logs",
+            "team-a/media",
+            "team-b/logs",
+            "team-c-data"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn prefixed_caller_reaches_its_buckets() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = test_state(tmp.path());
+    let client = sdk_client(state, "team-c");
+
+    client
+        .put_object()
+        .bucket("team-c-data")
+        .key("notes.txt")
+        .body(ByteStream::from_static(b"ours"))
+        .send()
+        .await
+        .unwrap();
+    let out = client
+        .get_object()
+        .bucket("team-c-data")
+        .key("notes.txt")
+        .send()
+        .await
+        .unwrap();
+    let body = out.body.collect().await.unwrap().into_bytes();
+    assert_eq!(&body[..], b"ours");
+}
+
+#[tokio::test]
+async fn prefixed_caller_is_denied_other_buckets() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = test_state(tmp.path());
+    sdk_client(state.clone(), "admin")
+        .put_object()
+        .bucket("shared")
+        .key("secret.txt")
+        .body(ByteStream::from_static(b"theirs"))
+        .send()
+        .await
+        .unwrap();
+    let client = sdk_client(state, "team-c");
+
+    let err = client
+        .get_object()
+        .bucket("shared")
+        .key("secret.txt")
+        .send()
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Some("AccessDenied"));
+
+    let err = client
+        .put_object()
+        .bucket("shared")
+        .key("mine.txt")
+        .body(ByteStream::from_static(b"ours"))
+        .send()
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Some("AccessDenied"));
+
+    let err = client
+        .list_objects_v2()
+        .bucket("shared")
+        .send()
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Some("AccessDenied"));
+
+    let err = client
+        .create_bucket()
+        .bucket("other")
+        .send()
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Some("AccessDenied"));
+}
+
+#[tokio::test]
+async fn prefixed_caller_cannot_copy_from_other_buckets() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = test_state(tmp.path());
+    sdk_client(state.clone(), "admin")
+        .put_object()
+        .bucket("shared")
+        .key("secret.txt")
+        .body(ByteStream::from_static(b"theirs"))
+        .send()
+        .await
+        .unwrap();
+
+    let err = sdk_client(state, "team-c")
+        .copy_object()
+        .copy_source("shared/secret.txt")
+        .bucket("team-c-data")
+        .key("stolen.txt")
+        .send()
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Some("AccessDenied"));
+}
@@ -0,0 +1,455 @@
+/// Object lock tests: an AWS SDK client, wired to the S3 service in-process,
+/// sets retention on objects and checks that COMPLIANCE locks cannot be
+/// broken, GOVERNANCE locks only with a permitted bypass, and expired
+/// retention no longer protects anything. Legal holds protect objects
+/// regardless of retention until a permitted caller clears them.
+///
+/// Run:
+///   cargo test -p enigma-s3 --test object_lock -- --nocapture
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use aws_sdk_s3::error::ProvideErrorMetadata;
+use aws_sdk_s3::primitives::{ByteStream, DateTime};
+use aws_sdk_s3::types::ObjectLockRetentionMode::{self, Compliance, Governance};
+use aws_sdk_s3::types::{
+    BucketVersioningStatus, ObjectLockLegalHold, ObjectLockLegalHoldStatus, ObjectLockRetention,
+    VersioningConfiguration,
+};
+
+use enigma_core::distributor::Distributor;
+use enigma_core::manifest::ManifestDb;
+use enigma_core::types::ProviderType;
+use enigma_s3::auth::AccessControl;
+use enigma_s3::object_lock::{BYPASS_GOVERNANCE_PERMISSION, LEGAL_HOLD_PERMISSION};
+use enigma_s3::{EnigmaS3State, SharedState};
+use enigma_storage::local::LocalStorageProvider;
+use enigma_storage::provider::StorageProvider;
+
+mod common;
+
+use common::{TestState, sdk_client};
+
+const DAY: i64 = 24 * 60 * 60;
+
+/// Grants every caller the same permissions and records audited actions
+/// as `"action target"`.
+struct StaticPermissions {
+    permissions: Vec<&'static str>,
+    audit: Arc<Mutex<Vec<String>>>,
+}
+
+#[async_trait]
+impl AccessControl for StaticPermissions {
+    async fn has_permission(&self, _access_key: &str, permission: &str) -> anyhow::Result<bool> {
+        Ok(self.permissions.contains(&permission))
+    }
+
+    async fn log_audit(&self, _access_key: &str, action: &str, target: &str) -> anyhow::Result<()> {
+        self.audit
+            .lock()
+            .unwrap()
+            .push(format!("{action} {target}"));
+        Ok(())
+    }
+}
+
+/// Manifest with a "locked" bucket (object lock enabled) and a "plain" one.
+fn manifest(dir: &std::path::Path) -> Arc<Mutex<ManifestDb>> {
+    let db = ManifestDb::open_in_memory().unwrap();
+    db.insert_provider("local", ProviderType::Local, dir.to_str().unwrap(), None, 1)
+        .unwrap();
+    let locked = db.create_namespace("locked").unwrap();
+    db.enable_namespace_object_lock(locked).unwrap();
+    db.create_namespace("plain").unwrap();
+    Arc::new(Mutex::new(db))
+}
+
+/// State over `db` whose callers hold `permissions`.
+fn test_state(
+    dir: &std::path::Path,
+    db: &Arc<Mutex<ManifestDb>>,
+    permissions: Vec<&'static str>,
+) -> SharedState {
+    audited_state(dir, db, permissions).0
+}
+
+/// Like [`test_state`], also returning the audit log.
+fn audited_state(
+    dir: &std::path::Path,
+    db: &Arc<Mutex<ManifestDb>>,
+    permissions: Vec<&'static str>,
+) -> (SharedState, Arc<Mutex<Vec<String>>>) {
+    let provider_infos = db.lock().unwrap().list_providers().unwrap();
+    let mut providers: HashMap<i64, Box<dyn StorageProvider>> = HashMap::new();
+    providers.insert(
+        provider_infos[0].id,
+        Box::new(LocalStorageProvider::new(&dir.join("chunks"), "local").unwrap()),
+    );
+
+    let state = Arc::new(EnigmaS3State {
+        db: db.clone(),
+        providers: providers.into(),
+        distributor: Distributor::round_robin(provider_infos).unwrap().into(),
+        ..TestState::new(dir).local_provider().into_state()
+    });
+    let audit = Arc::new(Mutex::new(Vec::new()));
+    let _ = state.access_control.set(Arc::new(StaticPermissions {
+        permissions,
+        audit: audit.clone(),
+    }));
+    (state, audit)
+}
+
+fn in_days(days: i64) -> DateTime {
+    DateTime::from_secs(chrono::Utc::now().timestamp() + days * DAY)
+}
+
+async fn put(client: &aws_sdk_s3::Client, bucket: &str, key: &str) -> Result<(), String> {
+    client
+        .put_object()
+        .bucket(bucket)
+        .key(key)
+        .body(ByteStream::from(vec![7u8; 64]))
+        .send()
+        .await
+        .map(|_| ())
+        .map_err(|e| e.code().unwrap_or_default().to_string())
+}
+
+async fn lock(
+    client: &aws_sdk_s3::Client,
+    key: &str,
+    mode: ObjectLockRetentionMode,
+    until: DateTime,
+    bypass: bool,
+) -> Result<(), String> {
+    client
+        .put_object_retention()
+        .bucket("locked")
+        .key(key)
+        .retention(
+            ObjectLockRetention::builder()
+                .mode(mode)
+                .retain_until_date(until)
+                .build(),
+        )
+        .bypass_governance_retention(bypass)
+        .send()
+        .await
+        .map(|_| ())
+        .map_err(|e| e.code().unwrap_or_default().to_string())
+}
+
+async fn delete(client: &aws_sdk_s3::Client, key: &str, bypass: bool) -> Result<(), String> {
+    client
+        .delete_object()
+        .bucket("locked")
+        .key(key)
+        .bypass_governance_retention(bypass)
+        .send()
+        .await
+        .map(|_| ())
+        .map_err(|e| e.code().unwrap_or_default().to_string())
+}
+
+async fn hold(client: &aws_sdk_s3::Client, key: &str, on: bool) -> Result<(), String> {
+    let status = if on {
+        ObjectLockLegalHoldStatus::On
+    } else {
+        ObjectLockLegalHoldStatus::Off
+    };
+    client
+        .put_object_legal_hold()
+        .bucket("locked")
+        .key(key)
+        .legal_hold(ObjectLockLegalHold::builder().status(status).build())
+        .send()
+        .await
+        .map(|_| ())
+        .map_err(|e| e.code().unwrap_or_default().to_string())
+}
+
+async fn hold_status(client: &aws_sdk_s3::Client, key: &str) -> Option<ObjectLockLegalHoldStatus> {
+    client
+        .get_object_legal_hold()
+        .bucket("locked")
+        .key(key)
+        .send()
+        .await
+        .unwrap()
+        .legal_hold
+        .and_then(|h| h.status)
+}
+
+async fn exists(client: &aws_sdk_s3::Client, key: &str) -> bool {
+    client
+        .head_object()
+        .bucket("locked")
+        .key(key)
+        .send()
+        .await
+        .is_ok()
+}
+
+#[tokio::test]
+async fn compliance_lock_blocks_overwrite_and_delete() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = manifest(dir.path());
+    let client = sdk_client(test_state(
+        dir.path(),
+        &db,
+        vec![BYPASS_GOVERNANCE_PERMISSION],
+    ));
+
+    put(&client, "locked", "ledger.csv").await.unwrap();
+    let until = in_days(30);
+    lock(&client, "ledger.csv", Compliance, until, false)
+        .await
+        .unwrap();
+
+    let retention = client
+        .get_object_retention()
+        .bucket("locked")
+        .key("ledger.csv")
+        .send()
+        .await
+        .unwrap()
+        .retention
+        .unwrap();
+    assert_eq!(retention.mode, Some(Compliance));
+    assert_eq!(retention.retain_until_date, Some(until));
+
+    // Not even a caller allowed to bypass governance can break it
+    let denied = Err("AccessDenied".to_string());
+    assert_eq!(delete(&client, "ledger.csv", false).await, denied);
+    assert_eq!(delete(&client, "ledger.csv", true).await, denied);
+    assert_eq!(put(&client, "locked", "ledger.csv").await, denied);
+    assert_eq!(
+        lock(&client, "ledger.csv", Compliance, in_days(1), true).await,
+        denied
+    );
+    assert_eq!(
+        lock(&client, "ledger.csv", Governance, in_days(60), true).await,
+        denied
+    );
+    assert!(exists(&client, "ledger.csv").await);
+
+    // Extending the retention is always allowed
+    lock(&client, "ledger.csv", Compliance, in_days(60), false)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn governance_lock_needs_bypass_and_permission() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = manifest(dir.path());
+    let client = sdk_client(test_state(
+        dir.path(),
+        &db,
+        vec![BYPASS_GOVERNANCE_PERMISSION],
+    ));
+    let unprivileged = sdk_client(test_state(dir.path(), &db, Vec::new()));
+
+    put(&client, "locked", "report.pdf").await.unwrap();
+    lock(&client, "report.pdf", Governance, in_days(30), false)
+        .await
+        .unwrap();
+
+    let denied = Err("AccessDenied".to_string());
+    assert_eq!(delete(&client, "report.pdf", false).await, denied);
+    assert_eq!(delete(&unprivileged, "report.pdf", true).await, denied);
+    assert_eq!(
+        lock(&unprivileged, "report.pdf", Governance, in_days(1), true).await,
+        denied
+    );
+    assert!(exists(&client, "report.pdf").await);
+
+    delete(&client, "report.pdf", true).await.unwrap();
+    assert!(!exists(&client, "report.pdf").await);
+}
+
+#[tokio::test]
+async fn expired_retention_no_longer_protects() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = manifest(dir.path());
+    let client = sdk_client(test_state(dir.path(), &db, Vec::new()));
+
+    put(&client, "locked", "old.log").await.unwrap();
+    // A retain-until date must lie in the future when it is set
+    assert_eq!(
+        lock(&client, "old.log", Compliance, in_days(-1), false).await,
+        Err("InvalidArgument".to_string())
+    );
+    {
+        let db = db.lock().unwrap();
+        let ns = db.get_namespace_id("locked").unwrap().unwrap();
+        let (object_id, ..) = db.get_object(ns, "old.log").unwrap().unwrap();
+        db.set_object_retention(object_id, Some(("COMPLIANCE", "2000-01-01T00:00:00Z")))
+            .unwrap();
+    }
+
+    put(&client, "locked", "old.log").await.unwrap();
+    delete(&client, "old.log", false).await.unwrap();
+    assert!(!exists(&client, "old.log").await);
+}
+
+#[tokio::test]
+async fn versioned_bucket_keeps_locked_versions() {
+    let dir = tempfile::tempdir().unwrap();
+    let client = sdk_client(test_state(dir.path(), &manifest(dir.path()), Vec::new()));
+    client
+        .put_bucket_versioning()
+        .bucket("locked")
+        .versioning_configuration(
+            VersioningConfiguration::builder()
+                .status(BucketVersioningStatus::Enabled)
+                .build(),
+        )
+        .send()
+        .await
+        .unwrap();
+
+    let version_id = client
+        .put_object()
+        .bucket("locked")
+        .key("doc.txt")
+        .body(ByteStream::from_static(b"v1"))
+        .send()
+        .await
+        .unwrap()
+        .version_id
+        .unwrap();
+    lock(&client, "doc.txt", Compliance, in_days(30), false)
+        .await
+        .unwrap();
+
+    // New versions and delete markers leave the locked version in place
+    put(&client, "locked", "doc.txt").await.unwrap();
+    delete(&client, "doc.txt", false).await.unwrap();
+
+    let err = client
+        .delete_object()
+        .bucket("locked")
+        .key("doc.txt")
+        .version_id(&version_id)
+        .send()
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Some("AccessDenied"));
+    client
+        .get_object()
+        .bucket("locked")
+        .key("doc.txt")
+        .version_id(&version_id)
+        .send()
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn retention_needs_object_lock_on_the_bucket() {
+    let dir = tempfile::tempdir().unwrap();
+    let client = sdk_client(test_state(dir.path(), &manifest(dir.path()), Vec::new()));
+
+    put(&client, "plain", "a.txt").await.unwrap();
+    let err = client
+        .put_object_retention()
+        .bucket("plain")
+        .key("a.txt")
+        .retention(
+            ObjectLockRetention::builder()
+                .mode(Compliance)
+                .retain_until_date(in_days(1))
+                .build(),
+        )
+        .send()
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Some("InvalidRequest"));
+
+    put(&client, "locked", "b.txt").await.unwrap();
+    let err = client
+        .get_object_retention()
+        .bucket("locked")
+        .key("b.txt")
+        .send()
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Some("NoSuchObjectLockConfiguration"));
+}
+
+#[tokio::test]
+async fn legal_hold_blocks_delete_and_overwrite_until_cleared() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = manifest(dir.path());
+    let (state, audit) = audited_state(dir.path(), &db, vec![LEGAL_HOLD_PERMISSION]);
+    let client = sdk_client(state);
+
+    put(&client, "locked", "evidence.eml").await.unwrap();
+    put(&client, "locked", "other.eml").await.unwrap();
+    assert_eq!(
+        hold_status(&client, "evidence.eml").await,
+        Some(ObjectLockLegalHoldStatus::Off)
+    );
+    hold(&client, "evidence.eml", true).await.unwrap();
+    assert_eq!(
+        hold_status(&client, "evidence.eml").await,
+        Some(ObjectLockLegalHoldStatus::On)
+    );
+
+    let denied = Err("AccessDenied".to_string());
+    assert_eq!(delete(&client, "evidence.eml", false).await, denied);
+    assert_eq!(put(&client, "locked", "evidence.eml").await, denied);
+    let copied = client
+        .copy_object()
+        .copy_source("locked/other.eml")
+        .bucket("locked")
+        .key("evidence.eml")
+        .send()
+        .await
+        .map_err(|e| e.code().unwrap_or_default().to_string());
+    assert_eq!(copied.map(|_| ()), denied);
+    assert!(exists(&client, "evidence.eml").await);
+
+    hold(&client, "evidence.eml", false).await.unwrap();
+    delete(&client, "evidence.eml", false).await.unwrap();
+    assert!(!exists(&client, "evidence.eml").await);
+
+    let audit = audit.lock().unwrap();
+    assert_eq!(audit.len(), 2);
+    assert!(audit[0].starts_with("object.legal_hold.set locked/evidence.eml"));
+    assert!(audit[1].starts_with("object.legal_hold.clear locked/evidence.eml"));
+}
+
+#[tokio::test]
+async fn legal_hold_needs_permission_and_beats_governance_bypass() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = manifest(dir.path());
+    let client = sdk_client(test_state(
+        dir.path(),
+        &db,
+        vec![BYPASS_GOVERNANCE_PERMISSION, LEGAL_HOLD_PERMISSION],
+    ));
+    let (state, audit) = audited_state(dir.path(), &db, vec![BYPASS_GOVERNANCE_PERMISSION]);
+    let unprivileged = sdk_client(state);
+
+    put(&client, "locked", "case.zip").await.unwrap();
+    let denied = Err("AccessDenied".to_string());
+    assert_eq!(hold(&unprivileged, "case.zip", true).await, denied);
+    assert!(audit.lock().unwrap().is_empty());
+
+    lock(&client, "case.zip", Governance, in_days(30), false)
+        .await
+        .unwrap();
+    hold(&client, "case.zip", true).await.unwrap();
+    assert_eq!(hold(&unprivileged, "case.zip", false).await, denied);
+
+    // The bypass lifts the retention but not the hold
+    assert_eq!(delete(&client, "case.zip", true).await, denied);
+    hold(&client, "case.zip", false).await.unwrap();
+    delete(&client, "case.zip", true).await.unwrap();
+    assert!(!exists(&client, "case.zip").await);
+}
@@ -0,0 +1,141 @@
+/// Object metadata test: `x-amz-meta-*` pairs stored on PutObject come back
+/// on GetObject and HeadObject, are replaced by an overwrite, and are capped
+/// at the S3 limit of 2 KB per object.
+///
+/// Run:
+///   cargo test -p enigma-s3 --test object_metadata -- --nocapture
+use std::collections::HashMap;
+
+use enigma_s3::SharedState;
+use enigma_s3::get::{Preconditions, handle_get_object, handle_head_object};
+use enigma_s3::put::{MAX_METADATA_SIZE, handle_put_object};
+use s3s::S3ErrorCode;
+use s3s::dto::StreamingBlob;
+
+mod common;
+
+use common::TestState;
+
+fn test_state(dir: &std::path::Path) -> SharedState {
+    TestState::new(dir)
+        .local_provider()
+        .bucket("bucket")
+        .build()
+}
+
+fn meta(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+async fn put(state: &SharedState, key: &str, metadata: &[(String, String)]) {
+    handle_put_object(
+        state,
+        "bucket",
+        key,
+        None,
+        None,
+        metadata,
+        Some(StreamingBlob::from(s3s::Body::from(b"hello".to_vec()))),
+    )
+    .await
+    .unwrap();
+}
+
+async fn head_metadata(state: &SharedState, key: &str) -> HashMap<String, String> {
+    handle_head_object(state, "bucket", key, None, &Preconditions::default())
+        .await
+        .unwrap()
+        .output
+        .metadata
+        .unwrap_or_default()
+}
+
+#[tokio::test]
+async fn metadata_returned_on_get_and_head() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = test_state(tmp.path());
+    put(
+        &state,
+        "a.txt",
+        &meta(&[("author", "alice"), ("project", "enigma")]),
+    )
+    .await;
+
+    let head = head_metadata(&state, "a.txt").await;
+    assert_eq!(head.len(), 2);
+    assert_eq!(head["author"], "alice");
+    assert_eq!(head["project"], "enigma");
+
+    let get = handle_get_object(&state, "bucket", "a.txt", None, &Preconditions::default())
+        .await
+        .unwrap();
+    assert_eq!(get.output.metadata.unwrap(), head);
+}
+
+#[tokio::test]
+async fn object_without_metadata_has_none() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = test_state(tmp.path());
+    put(&state, "plain.txt", &[]).await;
+
+    let head = handle_head_object(
+        &state,
+        "bucket",
+        "plain.txt",
+        None,
+        &Preconditions::default(),
+    )
+    .await
+    .unwrap();
+    assert!(head.output.metadata.is_none());
+}
+
+#[tokio::test]
+async fn overwrite_replaces_metadata() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = test_state(tmp.path());
+    put(
+        &state,
+        "a.txt",
+        &meta(&[("author", "alice"), ("draft", "yes")]),
+    )
+    .await;
+    put(&state, "a.txt", &meta(&[("author", "bob")])).await;
+
+    let head = head_metadata(&state, "a.txt").await;
+    assert_eq!(head.len(), 1);
+    assert_eq!(head["author"], "bob");
+}
+
+#[tokio::test]
+async fn oversized_metadata_rejected() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = test_state(tmp.path());
+
+    // Exactly at the limit is accepted
+    let at_limit = meta(&[("k", &"v".repeat(MAX_METADATA_SIZE - 1))]);
+    put(&state, "ok.txt", &at_limit).await;
+
+    let too_big = meta(&[("a", &"v".repeat(1024)), ("b", &"v".repeat(1024))]);
+    let err = handle_put_object(
+        &state,
+        "bucket",
+        "big.txt",
+        None,
+        None,
+        &too_big,
+        Some(StreamingBlob::from(s3s::Body::from(b"hello".to_vec()))),
+    )
+    .await
+    .err()
+    .unwrap();
+    assert_eq!(*err.code(), S3ErrorCode::MetadataTooLarge);
+    assert_eq!(err.status_code().map(|s| s.as_u16()), Some(400));
+
+    let db = state.db.lock().unwrap();
+    let ns = db.get_namespace_id("bucket").unwrap().unwrap();
+    assert!(db.get_object(ns, "big.txt").unwrap().is_none());
+}
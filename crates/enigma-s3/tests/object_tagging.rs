@@ -0,0 +1,156 @@
+use enigma_s3::SharedState;
+/// Object tagging test: tags set through the `x-amz-tagging` header on
+/// PutObject and through Get/Put/DeleteObjectTagging, plus S3 tag limits.
+///
+/// Run:
+///   cargo test -p enigma-s3 --test object_tagging -- --nocapture
+use enigma_s3::tagging::{
+    handle_delete_object_tagging, handle_get_object_tagging, handle_put_object_tagging,
+    parse_tagging_header,
+};
+use s3s::dto::{StreamingBlob, Tag, Tagging};
+
+mod common;
+
+use common::TestState;
+
+fn test_state(dir: &std::path::Path) -> SharedState {
+    TestState::new(dir)
+        .local_provider()
+        .bucket("bucket")
+        .build()
+}
+
+async fn put(state: &SharedState, key: &str, tagging: Option<&str>) {
+    enigma_s3::put::handle_put_object(
+        state,
+        "bucket",
+        key,
+        None,
+        tagging,
+        &[],
+        Some(StreamingBlob::from(s3s::Body::from(b"hello".to_vec()))),
+    )
+    .await
+    .unwrap();
+}
+
+async fn get_tags(state: &SharedState, key: &str) -> Vec<(String, String)> {
+    let resp = handle_get_object_tagging(state, "bucket", key)
+        .await
+        .unwrap();
+    resp.output
+        .tag_set
+        .into_iter()
+        .map(|t| (t.key.unwrap(), t.value.unwrap()))
+        .collect()
+}
+
+fn tag(key: &str, value: &str) -> Tag {
+    Tag {
+        key: Some(key.to_string()),
+        value: Some(value.to_string()),
+    }
+}
+
+#[tokio::test]
+async fn put_object_with_tagging_header_sets_tags() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = test_state(tmp.path());
+
+    put(&state, "a.txt", Some("project=enigma&cost%20center=r%26d")).await;
+
+    assert_eq!(
+        get_tags(&state, "a.txt").await,
+        vec![
+            ("cost center".to_string(), "r&d".to_string()),
+            ("project".to_string(), "enigma".to_string()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn put_get_delete_object_tagging() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = test_state(tmp.path());
+    put(&state, "a.txt", None).await;
+    assert!(get_tags(&state, "a.txt").await.is_empty());
+
+    let tagging = Tagging {
+        tag_set: vec![tag("env", "prod")],
+    };
+    handle_put_object_tagging(&state, "bucket", "a.txt", tagging)
+        .await
+        .unwrap();
+    assert_eq!(
+        get_tags(&state, "a.txt").await,
+        vec![("env".to_string(), "prod".to_string())]
+    );
+
+    handle_delete_object_tagging(&state, "bucket", "a.txt")
+        .await
+        .unwrap();
+    assert!(get_tags(&state, "a.txt").await.is_empty());
+
+    // Tagging a missing object is NoSuchKey
+    let tagging = Tagging {
+        tag_set: vec![tag("env", "prod")],
+    };
+    assert!(
+        handle_put_object_tagging(&state, "bucket", "missing", tagging)
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn overwriting_object_drops_old_tags() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = test_state(tmp.path());
+
+    put(&state, "a.txt", Some("env=prod")).await;
+    put(&state, "a.txt", None).await;
+    assert!(get_tags(&state, "a.txt").await.is_empty());
+}
+
+#[test]
+fn tag_limits_are_enforced() {
+    let eleven = (0..11)
+        .map(|i| format!("k{i}=v"))
+        .collect::<Vec<_>>()
+        .join("&");
+    assert!(parse_tagging_header(&eleven).is_err());
+
+    let long_key = format!("{}=v", "k".repeat(129));
+    assert!(parse_tagging_header(&long_key).is_err());
+
+    let long_value = format!("k={}", "v".repeat(257));
+    assert!(parse_tagging_header(&long_value).is_err());
+
+    assert!(parse_tagging_header("k=a&k=b").is_err());
+
+    let max_key = format!("{}={}", "k".repeat(128), "v".repeat(256));
+    assert_eq!(parse_tagging_header(&max_key).unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn invalid_tagging_header_rejects_put() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = test_state(tmp.path());
+
+    let result = enigma_s3::put::handle_put_object(
+        &state,
+        "bucket",
+        "a.txt",
+        None,
+        Some("k=a&k=b"),
+        &[],
+        Some(StreamingBlob::from(s3s::Body::from(b"hello".to_vec()))),
+    )
+    .await;
+    assert!(result.is_err());
+
+    let db = state.db.lock().unwrap();
+    let ns = db.get_namespace_id("bucket").unwrap().unwrap();
+    assert!(db.get_object(ns, "a.txt").unwrap().is_none());
+}
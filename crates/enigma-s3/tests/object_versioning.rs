@@ -0,0 +1,327 @@
+/// Object versioning test: PutBucketVersioning, overwrites creating new
+/// versions, ListObjectVersions order, GetObject with `versionId` and
+/// delete markers.
+///
+/// Run:
+///   cargo test -p enigma-s3 --test object_versioning -- --nocapture
+use futures::StreamExt;
+
+use enigma_s3::SharedState;
+use enigma_s3::get::handle_get_object;
+use enigma_s3::versioning::{
+    handle_delete_object, handle_get_bucket_versioning, handle_list_object_versions,
+    handle_put_bucket_versioning,
+};
+use s3s::S3ErrorCode;
+use s3s::dto::{BucketVersioningStatus, StreamingBlob, VersioningConfiguration};
+
+mod common;
+
+use common::TestState;
+
+fn test_state(dir: &std::path::Path) -> SharedState {
+    TestState::new(dir)
+        .local_provider()
+        .bucket("bucket")
+        .build()
+}
+
+async fn enable_versioning(state: &SharedState) {
+    let config = VersioningConfiguration {
+        status: Some(BucketVersioningStatus::from_static(
+            BucketVersioningStatus::ENABLED,
+        )),
+        ..Default::default()
+    };
+    handle_put_bucket_versioning(state, "bucket", config)
+        .await
+        .unwrap();
+}
+
+/// PutObject `data` and return the new version ID.
+async fn put(state: &SharedState, key: &str, data: &[u8]) -> String {
+    let resp = enigma_s3::put::handle_put_object(
+        state,
+        "bucket",
+        key,
+        None,
+        None,
+        &[],
+        Some(StreamingBlob::from(s3s::Body::from(data.to_vec()))),
+    )
+    .await
+    .unwrap();
+    resp.output
+        .version_id
+        .expect("versioned PUT returns a version ID")
+}
+
+async fn get(state: &SharedState, key: &str, version_id: Option<&str>) -> Vec<u8> {
+    let resp = handle_get_object(state, "bucket", key, version_id, &Default::default())
+        .await
+        .unwrap();
+    let mut body = resp.output.body.unwrap();
+    let mut data = Vec::new();
+    while let Some(chunk) = body.next().await {
+        data.extend_from_slice(&chunk.unwrap());
+    }
+    data
+}
+
+async fn get_err(state: &SharedState, key: &str, version_id: Option<&str>) -> S3ErrorCode {
+    match handle_get_object(state, "bucket", key, version_id, &Default::default()).await {
+        Ok(_) => panic!("GET {key} {version_id:?} succeeded"),
+        Err(e) => e.code().clone(),
+    }
+}
+
+#[tokio::test]
+async fn bucket_versioning_status() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = test_state(tmp.path());
+
+    let resp = handle_get_bucket_versioning(&state, "bucket")
+        .await
+        .unwrap();
+    assert!(resp.output.status.is_none());
+
+    enable_versioning(&state).await;
+    let resp = handle_get_bucket_versioning(&state, "bucket")
+        .await
+        .unwrap();
+    assert_eq!(
+        resp.output.status.unwrap().as_str(),
+        BucketVersioningStatus::ENABLED
+    );
+
+    let config = VersioningConfiguration {
+        status: Some(BucketVersioningStatus::from_static(
+            BucketVersioningStatus::SUSPENDED,
+        )),
+        ..Default::default()
+    };
+    handle_put_bucket_versioning(&state, "bucket", config)
+        .await
+        .unwrap();
+    let resp = handle_get_bucket_versioning(&state, "bucket")
+        .await
+        .unwrap();
+    assert_eq!(
+        resp.output.status.unwrap().as_str(),
+        BucketVersioningStatus::SUSPENDED
+    );
+}
+
+#[tokio::test]
+async fn suspended_bucket_keeps_versions_and_can_be_reenabled() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = test_state(tmp.path());
+    enable_versioning(&state).await;
+    let v1 = put(&state, "a.txt", b"one").await;
+
+    let suspend = VersioningConfiguration {
+        status: Some(BucketVersioningStatus::from_static(
+            BucketVersioningStatus::SUSPENDED,
+        )),
+        ..Default::default()
+    };
+    handle_put_bucket_versioning(&state, "bucket", suspend)
+        .await
+        .unwrap();
+    // Writes the "null" version, which PutObject reports without an ID
+    let resp = enigma_s3::put::handle_put_object(
+        &state,
+        "bucket",
+        "a.txt",
+        None,
+        None,
+        &[],
+        Some(StreamingBlob::from(s3s::Body::from(b"two".to_vec()))),
+    )
+    .await
+    .unwrap();
+    assert!(resp.output.version_id.is_none());
+    assert_eq!(get(&state, "a.txt", None).await, b"two");
+    assert_eq!(get(&state, "a.txt", Some(&v1)).await, b"one");
+
+    enable_versioning(&state).await;
+    let resp = handle_get_bucket_versioning(&state, "bucket")
+        .await
+        .unwrap();
+    assert_eq!(
+        resp.output.status.unwrap().as_str(),
+        BucketVersioningStatus::ENABLED
+    );
+    let v3 = put(&state, "a.txt", b"three").await;
+    assert_eq!(get(&state, "a.txt", Some(&v3)).await, b"three");
+    assert_eq!(get(&state, "a.txt", Some(&v1)).await, b"one");
+}
+
+#[tokio::test]
+async fn versioning_rejects_bad_status_and_unknown_bucket() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = test_state(tmp.path());
+
+    // An empty status would mean "off", which S3 does not allow
+    let err = handle_put_bucket_versioning(&state, "bucket", Default::default())
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(*err.code(), S3ErrorCode::MalformedXML);
+
+    let enabled = VersioningConfiguration {
+        status: Some(BucketVersioningStatus::from_static(
+            BucketVersioningStatus::ENABLED,
+        )),
+        ..Default::default()
+    };
+    let err = handle_put_bucket_versioning(&state, "missing", enabled)
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(*err.code(), S3ErrorCode::NoSuchBucket);
+    let err = handle_get_bucket_versioning(&state, "missing")
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(*err.code(), S3ErrorCode::NoSuchBucket);
+}
+
+#[tokio::test]
+async fn overwrite_creates_versions_listed_newest_first() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = test_state(tmp.path());
+    enable_versioning(&state).await;
+
+    let v1 = put(&state, "a.txt", b"first").await;
+    let v2 = put(&state, "a.txt", b"second").await;
+    let v3 = put(&state, "a.txt", b"third").await;
+    let b1 = put(&state, "b.txt", b"other").await;
+
+    let resp = handle_list_object_versions(&state, "bucket", "", "", "", 1000)
+        .await
+        .unwrap();
+    let versions = resp.output.versions.unwrap();
+    let listed: Vec<(&str, &str, bool)> = versions
+        .iter()
+        .map(|v| {
+            (
+                v.key.as_deref().unwrap(),
+                v.version_id.as_deref().unwrap(),
+                v.is_latest.unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        listed,
+        vec![
+            ("a.txt", v3.as_str(), true),
+            ("a.txt", v2.as_str(), false),
+            ("a.txt", v1.as_str(), false),
+            ("b.txt", b1.as_str(), true),
+        ]
+    );
+    assert_eq!(resp.output.is_truncated, Some(false));
+
+    // Paginate one version at a time
+    let resp = handle_list_object_versions(&state, "bucket", "", "", "", 2)
+        .await
+        .unwrap();
+    assert_eq!(resp.output.is_truncated, Some(true));
+    assert_eq!(resp.output.next_key_marker.as_deref(), Some("a.txt"));
+    assert_eq!(resp.output.next_version_id_marker, Some(v2.clone()));
+    let resp = handle_list_object_versions(&state, "bucket", "", "a.txt", &v2, 2)
+        .await
+        .unwrap();
+    let rest: Vec<String> = resp
+        .output
+        .versions
+        .unwrap()
+        .into_iter()
+        .map(|v| v.version_id.unwrap())
+        .collect();
+    assert_eq!(rest, vec![v1, b1]);
+}
+
+#[tokio::test]
+async fn get_object_by_version_id() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = test_state(tmp.path());
+    enable_versioning(&state).await;
+
+    let v1 = put(&state, "a.txt", b"first").await;
+    let v2 = put(&state, "a.txt", b"second").await;
+
+    assert_eq!(get(&state, "a.txt", None).await, b"second");
+    assert_eq!(get(&state, "a.txt", Some(&v1)).await, b"first");
+    assert_eq!(get(&state, "a.txt", Some(&v2)).await, b"second");
+
+    assert_eq!(
+        get_err(&state, "a.txt", Some("no-such-version")).await,
+        S3ErrorCode::NoSuchVersion
+    );
+}
+
+#[tokio::test]
+async fn delete_creates_delete_marker() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = test_state(tmp.path());
+    enable_versioning(&state).await;
+    let v1 = put(&state, "a.txt", b"keep me").await;
+
+    let resp = handle_delete_object(&state, "bucket", "a.txt", None)
+        .await
+        .unwrap();
+    assert_eq!(resp.output.delete_marker, Some(true));
+    let marker = resp.output.version_id.unwrap();
+    assert_ne!(marker, v1);
+
+    // The key is gone, but the old version is still readable
+    assert_eq!(get_err(&state, "a.txt", None).await, S3ErrorCode::NoSuchKey);
+    assert_eq!(get(&state, "a.txt", Some(&v1)).await, b"keep me");
+
+    let resp = handle_list_object_versions(&state, "bucket", "", "", "", 1000)
+        .await
+        .unwrap();
+    let markers = resp.output.delete_markers.unwrap();
+    assert_eq!(markers.len(), 1);
+    assert_eq!(markers[0].version_id.as_deref(), Some(marker.as_str()));
+    assert_eq!(markers[0].is_latest, Some(true));
+    let versions = resp.output.versions.unwrap();
+    assert_eq!(versions.len(), 1);
+    assert_eq!(versions[0].is_latest, Some(false));
+
+    // Deleting the marker itself brings the object back
+    handle_delete_object(&state, "bucket", "a.txt", Some(&marker))
+        .await
+        .unwrap();
+    assert_eq!(get(&state, "a.txt", None).await, b"keep me");
+}
+
+#[tokio::test]
+async fn unversioned_bucket_deletes_physically() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = test_state(tmp.path());
+    enigma_s3::put::handle_put_object(
+        &state,
+        "bucket",
+        "a.txt",
+        None,
+        None,
+        &[],
+        Some(StreamingBlob::from(s3s::Body::from(b"data".to_vec()))),
+    )
+    .await
+    .unwrap();
+
+    let resp = handle_delete_object(&state, "bucket", "a.txt", None)
+        .await
+        .unwrap();
+    assert_eq!(resp.output.delete_marker, None);
+
+    let resp = handle_list_object_versions(&state, "bucket", "", "", "", 1000)
+        .await
+        .unwrap();
+    assert!(resp.output.versions.is_none());
+    assert!(resp.output.delete_markers.is_none());
+}
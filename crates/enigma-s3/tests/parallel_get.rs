@@ -0,0 +1,50 @@
+/// Parallel chunk download test: a mock provider with slow downloads tracks
+/// how many `download_chunk` calls run at once, and
+/// `ops::retrieve_object_parallel` must keep several of them in flight.
+///
+/// Run:
+///   cargo test -p enigma-s3 --test parallel_get -- --nocapture
+use std::time::Duration;
+
+use enigma_storage::mock::{MockMethod, MockStorageProvider};
+
+mod common;
+
+use common::{TestState, generate_data};
+
+#[tokio::test]
+async fn retrieve_object_parallel_overlaps_downloads() {
+    let tmp = tempfile::tempdir().unwrap();
+
+    let mock = MockStorageProvider::default();
+    mock.set_delay(MockMethod::DownloadChunk, Duration::from_millis(100));
+    let state = TestState::new(tmp.path())
+        .mock_provider(&mock)
+        .bucket("bucket")
+        .configure(|c| c.download_concurrency = 4)
+        .build();
+
+    // Large enough to be split into several chunks by put::chunk_data.
+    let data = generate_data(40 * 1024 * 1024, 0);
+    enigma_s3::ops::store_object(&state, "bucket", "obj.bin", &data, None)
+        .await
+        .unwrap();
+
+    let restored = enigma_s3::ops::retrieve_object_parallel(&state, "bucket", "obj.bin")
+        .await
+        .unwrap();
+    assert!(restored == data, "reassembled object differs from original");
+
+    let downloads = mock.call_count(MockMethod::DownloadChunk);
+    assert!(downloads > 1, "expected a multi-chunk object");
+    assert_eq!(downloads as usize, mock.len());
+    let overlapping = mock.max_concurrent_calls(MockMethod::DownloadChunk);
+    assert!(
+        overlapping > 1,
+        "no overlapping downloads across {downloads} calls"
+    );
+    assert!(
+        overlapping <= 4,
+        "download_concurrency exceeded: {overlapping}"
+    );
+}
@@ -0,0 +1,189 @@
+/// Streaming PutObject tests: the body is chunked and uploaded as it
+/// arrives, so heap usage while storing a 50 MB object stays within about
+/// two maximum-size chunks (the chunker window plus one ciphertext), and a
+/// body that fails part-way leaves neither an object nor orphaned chunks.
+/// Heap usage is measured with a counting global allocator.
+///
+/// Run:
+///   cargo test -p enigma-s3 --test put_streaming -- --nocapture
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use bytes::Bytes;
+use enigma_s3::SharedState;
+use enigma_s3::put::{MAX_CHUNK_SIZE, handle_put_object};
+use s3s::dto::StreamingBlob;
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncReadExt;
+
+mod common;
+
+use common::TestState;
+
+const BODY_SIZE: usize = 50 * 1024 * 1024;
+const PIECE_SIZE: usize = 64 * 1024;
+/// The chunker window and one encrypted chunk, plus slack for pieces
+/// queued for the ETag hasher and bookkeeping.
+const MEMORY_BUDGET: usize = 2 * MAX_CHUNK_SIZE + 4 * 1024 * 1024;
+
+/// Tracks live heap bytes and their high-water mark.
+struct CountingAlloc;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            let now = ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(now, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// Pseudo-random body pieces (deterministic, fast), generated as they are
+/// consumed. With `fail_at`, the stream errors once that many bytes are out.
+fn body_pieces(
+    size: usize,
+    fail_at: Option<usize>,
+) -> impl Iterator<Item = Result<Bytes, std::io::Error>> + Send + Sync + 'static {
+    let mut state: u64 = 0xdeadbeefcafe1234;
+    let mut sent = 0;
+    std::iter::from_fn(move || {
+        if fail_at.is_some_and(|at| sent >= at) {
+            sent = size;
+            return Some(Err(std::io::Error::other("connection reset")));
+        }
+        if sent >= size {
+            return None;
+        }
+        let len = PIECE_SIZE.min(size - sent);
+        let mut piece = Vec::with_capacity(len + 8);
+        while piece.len() < len {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            piece.extend_from_slice(&state.to_le_bytes());
+        }
+        piece.truncate(len);
+        sent += len;
+        Some(Ok(Bytes::from(piece)))
+    })
+}
+
+fn streaming_body(size: usize, fail_at: Option<usize>) -> StreamingBlob {
+    StreamingBlob::wrap(futures::stream::iter(body_pieces(size, fail_at)))
+}
+
+fn test_state(dir: &std::path::Path) -> SharedState {
+    TestState::new(dir)
+        .local_provider()
+        .bucket("bucket")
+        .configure(|c| c.compression.enabled = false)
+        .build()
+}
+
+fn count_rows(state: &SharedState, table: &str) -> i64 {
+    state
+        .db
+        .lock()
+        .unwrap()
+        .conn()
+        .query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+            row.get(0)
+        })
+        .unwrap()
+}
+
+#[tokio::test]
+async fn put_streams_body_within_memory_budget() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = test_state(tmp.path());
+
+    let mut expected = Sha256::new();
+    for piece in body_pieces(BODY_SIZE, None) {
+        expected.update(piece.unwrap());
+    }
+    let expected = format!("{:x}", expected.finalize());
+
+    let baseline = ALLOCATED.load(Ordering::Relaxed);
+    PEAK.store(baseline, Ordering::Relaxed);
+    let resp = handle_put_object(
+        &state,
+        "bucket",
+        "big.bin",
+        None,
+        None,
+        &[],
+        Some(streaming_body(BODY_SIZE, None)),
+    )
+    .await
+    .unwrap();
+    let growth = PEAK.load(Ordering::Relaxed).saturating_sub(baseline);
+    println!(
+        "Heap growth during PutObject: {} MB (budget {} MB)",
+        growth / (1024 * 1024),
+        MEMORY_BUDGET / (1024 * 1024)
+    );
+    assert!(
+        growth <= MEMORY_BUDGET,
+        "PutObject allocated {growth} bytes (budget {MEMORY_BUDGET})"
+    );
+
+    assert_eq!(
+        resp.output.e_tag.unwrap().trim_matches('"'),
+        expected.as_str()
+    );
+    assert!(count_rows(&state, "chunks") > 1);
+    assert_eq!(count_rows(&state, "pending_objects"), 0);
+    assert_eq!(count_rows(&state, "pending_object_chunks"), 0);
+
+    let mut file = enigma_s3::ops::retrieve_object(&state, "bucket", "big.bin")
+        .await
+        .unwrap();
+    assert_eq!(file.size, BODY_SIZE as u64);
+    let mut data = Vec::new();
+    file.reader.read_to_end(&mut data).await.unwrap();
+    assert_eq!(format!("{:x}", Sha256::digest(&data)), expected);
+}
+
+#[tokio::test]
+async fn failed_body_rolls_back_stored_chunks() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = test_state(tmp.path());
+
+    // Fail well past the first chunk so some chunks were already uploaded
+    let fail_at = 2 * MAX_CHUNK_SIZE;
+    let result = handle_put_object(
+        &state,
+        "bucket",
+        "broken.bin",
+        None,
+        None,
+        &[],
+        Some(streaming_body(BODY_SIZE, Some(fail_at))),
+    )
+    .await;
+    assert!(result.is_err());
+
+    assert_eq!(count_rows(&state, "objects"), 0);
+    assert_eq!(count_rows(&state, "chunks"), 0);
+    assert_eq!(count_rows(&state, "chunk_replicas"), 0);
+    assert_eq!(count_rows(&state, "pending_objects"), 0);
+    assert_eq!(count_rows(&state, "pending_object_chunks"), 0);
+    assert!(
+        enigma_s3::ops::retrieve_object(&state, "bucket", "broken.bin")
+            .await
+            .is_err()
+    );
+}
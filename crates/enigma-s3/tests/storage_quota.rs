@@ -0,0 +1,270 @@
+/// Per-user storage accounting tests: an AWS SDK client, wired to the S3
+/// service in-process, writes and deletes objects while an in-memory
+/// `UsageAccounting` tracks the deltas and enforces a quota.
+///
+/// Run:
+///   cargo test -p enigma-s3 --test storage_quota -- --nocapture
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use aws_sdk_s3::error::ProvideErrorMetadata;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{
+    BucketVersioningStatus, CompletedMultipartUpload, CompletedPart, VersioningConfiguration,
+};
+
+use enigma_s3::SharedState;
+use enigma_s3::usage::UsageAccounting;
+
+mod common;
+
+use common::{ACCESS_KEY, TestState, sdk_client};
+
+/// Usage per (access key, namespace) as (bytes, objects), with one quota
+/// for every caller.
+#[derive(Default)]
+struct MemoryAccounting {
+    quota: Option<u64>,
+    usage: Mutex<HashMap<(String, String), (i64, i64)>>,
+}
+
+impl MemoryAccounting {
+    fn usage(&self, namespace: &str) -> (i64, i64) {
+        self.usage
+            .lock()
+            .unwrap()
+            .get(&(ACCESS_KEY.to_string(), namespace.to_string()))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+#[async_trait]
+impl UsageAccounting for MemoryAccounting {
+    async fn remaining_quota(&self, access_key: &str) -> anyhow::Result<Option<u64>> {
+        let used: i64 = self
+            .usage
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|((key, _), _)| key == access_key)
+            .map(|(_, (bytes, _))| bytes)
+            .sum();
+        Ok(self.quota.map(|q| q.saturating_sub(used as u64)))
+    }
+
+    async fn record_usage(
+        &self,
+        access_key: &str,
+        namespace: &str,
+        delta_bytes: i64,
+        delta_objects: i32,
+    ) -> anyhow::Result<()> {
+        let mut usage = self.usage.lock().unwrap();
+        let entry = usage
+            .entry((access_key.to_string(), namespace.to_string()))
+            .or_default();
+        entry.0 += delta_bytes;
+        entry.1 += i64::from(delta_objects);
+        Ok(())
+    }
+}
+
+fn test_state(dir: &std::path::Path, accounting: Arc<MemoryAccounting>) -> SharedState {
+    let state = TestState::new(dir)
+        .local_provider()
+        .bucket("bucket")
+        .bucket("other")
+        .build();
+    let _ = state.usage.set(accounting);
+    state
+}
+
+async fn put(client: &aws_sdk_s3::Client, bucket: &str, key: &str, len: usize) {
+    client
+        .put_object()
+        .bucket(bucket)
+        .key(key)
+        .body(ByteStream::from(vec![7u8; len]))
+        .send()
+        .await
+        .unwrap();
+}
+
+async fn delete(client: &aws_sdk_s3::Client, bucket: &str, key: &str) {
+    client
+        .delete_object()
+        .bucket(bucket)
+        .key(key)
+        .send()
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn puts_and_deletes_adjust_usage() {
+    let dir = tempfile::tempdir().unwrap();
+    let accounting = Arc::new(MemoryAccounting::default());
+    let client = sdk_client(test_state(dir.path(), accounting.clone()));
+
+    put(&client, "bucket", "a", 100).await;
+    assert_eq!(accounting.usage("bucket"), (100, 1));
+
+    // Overwrite replaces the old bytes without adding an object
+    put(&client, "bucket", "a", 40).await;
+    assert_eq!(accounting.usage("bucket"), (40, 1));
+
+    put(&client, "bucket", "b", 10).await;
+    assert_eq!(accounting.usage("bucket"), (50, 2));
+
+    delete(&client, "bucket", "a").await;
+    assert_eq!(accounting.usage("bucket"), (10, 1));
+
+    // Deleting a missing key changes nothing
+    delete(&client, "bucket", "a").await;
+    assert_eq!(accounting.usage("bucket"), (10, 1));
+}
+
+#[tokio::test]
+async fn usage_is_kept_per_namespace() {
+    let dir = tempfile::tempdir().unwrap();
+    let accounting = Arc::new(MemoryAccounting::default());
+    let client = sdk_client(test_state(dir.path(), accounting.clone()));
+
+    put(&client, "bucket", "a", 30).await;
+    put(&client, "other", "a", 20).await;
+    put(&client, "other", "b", 5).await;
+
+    assert_eq!(accounting.usage("bucket"), (30, 1));
+    assert_eq!(accounting.usage("other"), (25, 2));
+}
+
+#[tokio::test]
+async fn versioned_bucket_counts_every_version() {
+    let dir = tempfile::tempdir().unwrap();
+    let accounting = Arc::new(MemoryAccounting::default());
+    let client = sdk_client(test_state(dir.path(), accounting.clone()));
+    client
+        .put_bucket_versioning()
+        .bucket("bucket")
+        .versioning_configuration(
+            VersioningConfiguration::builder()
+                .status(BucketVersioningStatus::Enabled)
+                .build(),
+        )
+        .send()
+        .await
+        .unwrap();
+
+    put(&client, "bucket", "a", 100).await;
+    put(&client, "bucket", "a", 40).await;
+    assert_eq!(accounting.usage("bucket"), (140, 2));
+
+    // A delete marker frees nothing
+    delete(&client, "bucket", "a").await;
+    assert_eq!(accounting.usage("bucket"), (140, 2));
+}
+
+#[tokio::test]
+async fn put_over_quota_is_rejected_with_413() {
+    let dir = tempfile::tempdir().unwrap();
+    let accounting = Arc::new(MemoryAccounting {
+        quota: Some(100),
+        ..Default::default()
+    });
+    let client = sdk_client(test_state(dir.path(), accounting.clone()));
+
+    put(&client, "bucket", "a", 80).await;
+
+    let err = client
+        .put_object()
+        .bucket("other")
+        .key("b")
+        .body(ByteStream::from(vec![7u8; 30]))
+        .send()
+        .await
+        .unwrap_err();
+    assert_eq!(err.raw_response().unwrap().status().as_u16(), 413);
+    assert_eq!(
+        err.as_service_error().and_then(|e| e.code()),
+        Some("QuotaExceeded")
+    );
+    assert!(
+        client
+            .head_object()
+            .bucket("other")
+            .key("b")
+            .send()
+            .await
+            .is_err()
+    );
+    assert_eq!(accounting.usage("other"), (0, 0));
+
+    // Replacing the existing object only needs room for the difference
+    put(&client, "bucket", "a", 95).await;
+    assert_eq!(accounting.usage("bucket"), (95, 1));
+}
+
+/// Upload `len` bytes under `key` as a one-part multipart upload.
+async fn multipart(
+    client: &aws_sdk_s3::Client,
+    bucket: &str,
+    key: &str,
+    len: usize,
+) -> Result<(), String> {
+    let upload_id = client
+        .create_multipart_upload()
+        .bucket(bucket)
+        .key(key)
+        .send()
+        .await
+        .unwrap()
+        .upload_id
+        .unwrap();
+    let etag = client
+        .upload_part()
+        .bucket(bucket)
+        .key(key)
+        .upload_id(&upload_id)
+        .part_number(1)
+        .body(ByteStream::from(vec![7u8; len]))
+        .send()
+        .await
+        .unwrap()
+        .e_tag
+        .unwrap();
+    client
+        .complete_multipart_upload()
+        .bucket(bucket)
+        .key(key)
+        .upload_id(&upload_id)
+        .multipart_upload(
+            CompletedMultipartUpload::builder()
+                .parts(CompletedPart::builder().part_number(1).e_tag(etag).build())
+                .build(),
+        )
+        .send()
+        .await
+        .map(|_| ())
+        .map_err(|e| e.code().unwrap_or_default().to_string())
+}
+
+#[tokio::test]
+async fn multipart_uploads_count_against_the_quota() {
+    let dir = tempfile::tempdir().unwrap();
+    let accounting = Arc::new(MemoryAccounting {
+        quota: Some(100),
+        ..Default::default()
+    });
+    let client = sdk_client(test_state(dir.path(), accounting.clone()));
+
+    multipart(&client, "bucket", "a", 60).await.unwrap();
+    assert_eq!(accounting.usage("bucket"), (60, 1));
+
+    assert_eq!(
+        multipart(&client, "bucket", "b", 50).await,
+        Err("QuotaExceeded".to_string())
+    );
+    assert_eq!(accounting.usage("bucket"), (60, 1));
+}
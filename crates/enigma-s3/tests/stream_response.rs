@@ -0,0 +1,232 @@
+/// GetObject response streaming: a 1 GiB object is delivered chunk by
+/// chunk, in order, without the gateway holding it in memory, and chunks
+/// are only downloaded as fast as the client reads them.
+///
+/// The object is assembled in the manifest from a few stored 4 MiB parts,
+/// so only those parts take space in the mock provider.
+///
+/// Run:
+///   cargo test -p enigma-s3 --test stream_response -- --nocapture
+use std::time::Duration;
+
+use futures::StreamExt;
+
+use enigma_s3::SharedState;
+use enigma_s3::get::handle_get_object;
+use enigma_s3::ops;
+use enigma_storage::mock::{MockMethod, MockStorageProvider};
+
+mod common;
+
+use common::{TestState, generate_data};
+
+const PART_SIZE: usize = 4 * 1024 * 1024;
+const PARTS: usize = 4;
+/// 1 GiB of 4 MiB chunks.
+const BIG_CHUNKS: usize = 256;
+
+fn test_state(dir: &std::path::Path, mock: &MockStorageProvider) -> SharedState {
+    TestState::new(dir)
+        .mock_provider(mock)
+        .bucket("bucket")
+        .build()
+}
+
+/// Which part chunk `i` of the big object repeats; not periodic, so
+/// chunks delivered out of order are noticed.
+fn part_of(i: usize) -> usize {
+    (i ^ (i >> 2) ^ (i >> 5)) % PARTS
+}
+
+/// Store the parts and record "big.bin" as `BIG_CHUNKS` of them.
+async fn store_big_object(state: &SharedState) -> Vec<Vec<u8>> {
+    let parts: Vec<Vec<u8>> = (0..PARTS)
+        .map(|p| generate_data(PART_SIZE, p as u64))
+        .collect();
+    let mut part_hashes = Vec::new();
+    for (p, data) in parts.iter().enumerate() {
+        let key = format!("part-{p}");
+        ops::store_object(state, "bucket", &key, data, None)
+            .await
+            .unwrap();
+        let db = state.db.lock().unwrap();
+        let ns_id = db.get_namespace_id("bucket").unwrap().unwrap();
+        let (object_id, ..) = db.get_object(ns_id, &key).unwrap().unwrap();
+        let chunks = db.get_object_chunks(object_id).unwrap();
+        assert_eq!(chunks.len(), 1, "expected a single-chunk part");
+        part_hashes.push(chunks[0].0.clone());
+    }
+
+    let db = state.db.lock().unwrap();
+    let ns_id = db.get_namespace_id("bucket").unwrap().unwrap();
+    let size = (BIG_CHUNKS * PART_SIZE) as u64;
+    let object_id = db
+        .insert_object(
+            ns_id,
+            "big.bin",
+            size,
+            "big",
+            None,
+            BIG_CHUNKS as u32,
+            "test-key-1",
+        )
+        .unwrap();
+    for i in 0..BIG_CHUNKS {
+        db.insert_object_chunk(
+            object_id,
+            &part_hashes[part_of(i)],
+            i as u32,
+            (i * PART_SIZE) as u64,
+        )
+        .unwrap();
+    }
+    parts
+}
+
+/// Resident set size of this process, in bytes.
+#[cfg(target_os = "linux")]
+fn rss_bytes() -> u64 {
+    let statm = std::fs::read_to_string("/proc/self/statm").unwrap();
+    let pages: u64 = statm.split_whitespace().nth(1).unwrap().parse().unwrap();
+    pages * 4096
+}
+
+#[tokio::test]
+async fn one_gib_object_is_streamed_in_order() {
+    let tmp = tempfile::tempdir().unwrap();
+    let mock = MockStorageProvider::new("mock");
+    let state = test_state(tmp.path(), &mock);
+    let parts = store_big_object(&state).await;
+    #[cfg(target_os = "linux")]
+    let rss_before = rss_bytes();
+
+    let resp = handle_get_object(&state, "bucket", "big.bin", None, &Default::default())
+        .await
+        .unwrap();
+    assert_eq!(
+        resp.output.content_length,
+        Some((BIG_CHUNKS * PART_SIZE) as i64)
+    );
+    let mut body = resp.output.body.unwrap();
+
+    // Compare as the body arrives; nothing keeps more than one part
+    let mut chunk = 0;
+    let mut pos = 0;
+    #[cfg(target_os = "linux")]
+    let mut rss_peak = rss_before;
+    while let Some(piece) = body.next().await {
+        let mut piece = &piece.unwrap()[..];
+        while !piece.is_empty() {
+            let expected = &parts[part_of(chunk)][pos..];
+            let n = piece.len().min(expected.len());
+            assert!(piece[..n] == expected[..n], "chunk {chunk} differs");
+            piece = &piece[n..];
+            pos += n;
+            if pos == PART_SIZE {
+                chunk += 1;
+                pos = 0;
+            }
+        }
+        #[cfg(target_os = "linux")]
+        {
+            rss_peak = rss_peak.max(rss_bytes());
+        }
+    }
+    assert_eq!((chunk, pos), (BIG_CHUNKS, 0));
+    assert_eq!(
+        mock.call_count(MockMethod::DownloadChunk) as usize,
+        BIG_CHUNKS
+    );
+    #[cfg(target_os = "linux")]
+    {
+        let growth = rss_peak.saturating_sub(rss_before);
+        println!("RSS grew by {} MiB", growth / (1024 * 1024));
+        assert!(
+            growth < 256 * 1024 * 1024,
+            "streaming 1 GiB grew RSS by {growth} bytes"
+        );
+    }
+}
+
+#[tokio::test]
+async fn slow_client_holds_back_downloads() {
+    let tmp = tempfile::tempdir().unwrap();
+    let mock = MockStorageProvider::new("mock");
+    let state = test_state(tmp.path(), &mock);
+    store_big_object(&state).await;
+
+    let resp = handle_get_object(&state, "bucket", "big.bin", None, &Default::default())
+        .await
+        .unwrap();
+    let mut body = resp.output.body.unwrap();
+    let downloads_before = mock.call_count(MockMethod::DownloadChunk);
+
+    let mut received = 0;
+    while received < 3 * PART_SIZE {
+        received += body.next().await.unwrap().unwrap().len();
+    }
+    // The client stops reading: no chunk is downloaded ahead of it
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let downloaded = mock.call_count(MockMethod::DownloadChunk) - downloads_before;
+    assert_eq!(downloaded, 3);
+
+    // Dropping the body ends the download
+    drop(body);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(
+        mock.call_count(MockMethod::DownloadChunk) - downloads_before,
+        3
+    );
+}
+
+#[tokio::test]
+async fn small_object_is_read_before_responding() {
+    let tmp = tempfile::tempdir().unwrap();
+    let mock = MockStorageProvider::new("mock");
+    let state = test_state(tmp.path(), &mock);
+    let data = generate_data(64 * 1024, 7);
+    ops::store_object(&state, "bucket", "small.bin", &data, None)
+        .await
+        .unwrap();
+    assert!((data.len() as u64) < state.config.enigma.stream_response_threshold_bytes);
+
+    let resp = handle_get_object(&state, "bucket", "small.bin", None, &Default::default())
+        .await
+        .unwrap();
+    // Already downloaded before the body is polled
+    assert_eq!(mock.call_count(MockMethod::DownloadChunk), 1);
+    let mut body = resp.output.body.unwrap();
+    let mut received = Vec::new();
+    while let Some(piece) = body.next().await {
+        received.extend_from_slice(&piece.unwrap());
+    }
+    assert!(received == data);
+}
+
+#[tokio::test]
+async fn object_above_threshold_is_downloaded_as_the_body_is_read() {
+    let tmp = tempfile::tempdir().unwrap();
+    let mock = MockStorageProvider::new("mock");
+    let state = TestState::new(tmp.path())
+        .mock_provider(&mock)
+        .bucket("bucket")
+        .configure(|c| c.stream_response_threshold_bytes = 1024 * 1024)
+        .build();
+    let data = generate_data(3 * 1024 * 1024, 9);
+    ops::store_object(&state, "bucket", "medium.bin", &data, None)
+        .await
+        .unwrap();
+
+    let resp = handle_get_object(&state, "bucket", "medium.bin", None, &Default::default())
+        .await
+        .unwrap();
+    // Nothing is downloaded until the body is polled
+    assert_eq!(mock.call_count(MockMethod::DownloadChunk), 0);
+    let mut body = resp.output.body.unwrap();
+    let mut received = Vec::new();
+    while let Some(piece) = body.next().await {
+        received.extend_from_slice(&piece.unwrap());
+    }
+    assert!(received == data);
+    assert!(mock.call_count(MockMethod::DownloadChunk) > 0);
+}
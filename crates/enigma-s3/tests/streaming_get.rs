@@ -0,0 +1,96 @@
+/// Streaming GetObject test: a 64 MB object is read back through
+/// `ops::retrieve_object` and heap usage must stay bounded by the chunk
+/// size rather than the object size. Heap usage is measured with a
+/// counting global allocator.
+///
+/// Run:
+///   cargo test -p enigma-s3 --test streaming_get -- --nocapture
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncReadExt;
+
+mod common;
+
+use common::{TestState, generate_data};
+
+const OBJECT_SIZE: usize = 64 * 1024 * 1024;
+/// Largest chunk produced by `put::chunk_data` (4 × 4 MB target).
+const MAX_CHUNK_SIZE: usize = 16 * 1024 * 1024;
+/// One chunk's ciphertext and plaintext, plus slack for the read buffer
+/// and bookkeeping.
+const MEMORY_BUDGET: usize = 2 * MAX_CHUNK_SIZE + 4 * 1024 * 1024;
+
+/// Tracks live heap bytes and their high-water mark.
+struct CountingAlloc;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            let now = ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(now, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+#[tokio::test]
+async fn get_object_streams_with_bounded_memory() {
+    let tmp = tempfile::tempdir().unwrap();
+
+    let state = TestState::new(tmp.path())
+        .local_provider()
+        .bucket("bucket")
+        .configure(|c| c.compression.enabled = false)
+        .build();
+
+    let etag = {
+        let data = generate_data(OBJECT_SIZE, 0);
+        enigma_s3::ops::store_object(&state, "bucket", "big.bin", &data, None)
+            .await
+            .unwrap()
+    };
+
+    let baseline = ALLOCATED.load(Ordering::Relaxed);
+    PEAK.store(baseline, Ordering::Relaxed);
+
+    let mut file = enigma_s3::ops::retrieve_object(&state, "bucket", "big.bin")
+        .await
+        .unwrap();
+    assert_eq!(file.size, OBJECT_SIZE as u64);
+
+    let mut hasher = Sha256::new();
+    let mut total = 0usize;
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.reader.read(&mut buf).await.unwrap();
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        total += n;
+    }
+
+    assert_eq!(total, OBJECT_SIZE);
+    assert_eq!(format!("{:x}", hasher.finalize()), etag);
+
+    let growth = PEAK.load(Ordering::Relaxed).saturating_sub(baseline);
+    println!("Heap grew by {} MB during download", growth / (1024 * 1024));
+    assert!(
+        growth <= MEMORY_BUDGET,
+        "heap grew by {growth} bytes during download (limit {MEMORY_BUDGET})"
+    );
+}
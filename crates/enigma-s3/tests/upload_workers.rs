@@ -0,0 +1,76 @@
+/// Upload limit test: a burst of parallel PUTs against a mock provider with
+/// slow uploads must never have more than `backup_workers` chunk uploads
+/// in flight at once.
+///
+/// Run:
+///   cargo test -p enigma-s3 --test upload_workers -- --nocapture
+use std::sync::Arc;
+use std::time::Duration;
+
+use enigma_s3::ops::UploadSemaphore;
+use enigma_s3::{EnigmaS3State, SharedState};
+use enigma_storage::mock::{MockMethod, MockStorageProvider};
+
+mod common;
+
+use common::TestState;
+
+fn test_state(
+    dir: &std::path::Path,
+    mock: &MockStorageProvider,
+    workers: Option<usize>,
+) -> SharedState {
+    let state = TestState::new(dir)
+        .mock_provider(mock)
+        .bucket("bucket")
+        .configure(|c| c.backup_workers = workers)
+        .into_state();
+    let upload_semaphore = UploadSemaphore::from_settings(&state.config.enigma);
+    Arc::new(EnigmaS3State {
+        upload_semaphore,
+        ..state
+    })
+}
+
+/// Store `count` distinct small objects at once.
+async fn put_burst(state: &SharedState, count: usize) {
+    let puts = (0..count).map(|i| {
+        let state = state.clone();
+        tokio::spawn(async move {
+            let data = format!("object number {i}").into_bytes();
+            enigma_s3::ops::store_object(&state, "bucket", &format!("obj-{i}"), &data, None)
+                .await
+                .unwrap();
+        })
+    });
+    for put in futures::future::join_all(puts).await {
+        put.unwrap();
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn burst_respects_backup_workers() {
+    let dir = tempfile::tempdir().unwrap();
+    let mock = MockStorageProvider::default();
+    mock.set_delay(MockMethod::UploadChunk, Duration::from_millis(50));
+    let state = test_state(dir.path(), &mock, Some(2));
+
+    put_burst(&state, 16).await;
+
+    assert_eq!(mock.call_count(MockMethod::UploadChunk), 16);
+    let overlapping = mock.max_concurrent_calls(MockMethod::UploadChunk);
+    assert!(overlapping <= 2, "backup_workers exceeded: {overlapping}");
+    assert_eq!(overlapping, 2, "uploads never overlapped");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn no_limit_by_default() {
+    let dir = tempfile::tempdir().unwrap();
+    let mock = MockStorageProvider::default();
+    mock.set_delay(MockMethod::UploadChunk, Duration::from_millis(50));
+    let state = test_state(dir.path(), &mock, None);
+
+    put_burst(&state, 16).await;
+
+    assert!(mock.max_concurrent_calls(MockMethod::UploadChunk) > 2);
+}
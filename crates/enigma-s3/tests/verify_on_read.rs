@@ -0,0 +1,113 @@
+/// Paranoid-mode test: a chunk whose plaintext was altered before encryption
+/// (so AES-GCM still authenticates it against the recorded hash) must be
+/// rejected on read when `verify_on_read` is enabled.
+///
+/// Run:
+///   cargo test -p enigma-s3 --test verify_on_read -- --nocapture
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use enigma_core::crypto::encrypt_chunk;
+use enigma_core::dedup::compute_hash;
+use enigma_core::distributor::Distributor;
+use enigma_core::manifest::ManifestDb;
+use enigma_core::types::{KeyMaterial, ProviderType};
+use enigma_s3::EnigmaS3State;
+use enigma_storage::local::LocalStorageProvider;
+use enigma_storage::provider::StorageProvider;
+
+mod common;
+
+use common::TestState;
+
+/// Store `plaintext` as a single-chunk object, recorded under the hash of
+/// the original data but with one byte flipped before encryption.
+async fn corrupted_state(dir: &std::path::Path, verify_on_read: bool) -> Arc<EnigmaS3State> {
+    let key_material = KeyMaterial {
+        id: "test-key-1".to_string(),
+        key: [0x42; 32],
+    };
+
+    let db = ManifestDb::open_in_memory().unwrap();
+    let pid = db
+        .insert_provider("local", ProviderType::Local, dir.to_str().unwrap(), None, 1)
+        .unwrap();
+    let ns = db.create_namespace("bucket").unwrap();
+    let distributor = Distributor::round_robin(db.list_providers().unwrap()).unwrap();
+    let provider = LocalStorageProvider::new(&dir.join("chunks"), "local").unwrap();
+
+    let original = b"the quick brown fox jumps over the lazy dog".to_vec();
+    let hash = compute_hash(&original);
+    let mut corrupted = original.clone();
+    corrupted[0] ^= 0x01;
+
+    let encrypted = encrypt_chunk(&corrupted, &hash, &key_material).unwrap();
+    let storage_key = hash.storage_key();
+    provider
+        .upload_chunk(&storage_key, &encrypted.ciphertext)
+        .await
+        .unwrap();
+    db.insert_or_dedup_chunk(
+        &hash.to_hex(),
+        &encrypted.nonce,
+        &key_material.id,
+        pid,
+        &storage_key,
+        original.len() as u64,
+        encrypted.ciphertext.len() as u64,
+        None,
+    )
+    .unwrap();
+    let oid = db
+        .insert_object(
+            ns,
+            "obj.txt",
+            original.len() as u64,
+            "etag",
+            None,
+            1,
+            &key_material.id,
+        )
+        .unwrap();
+    db.insert_object_chunk(oid, &hash.to_hex(), 0, 0).unwrap();
+
+    let mut providers: HashMap<i64, Box<dyn StorageProvider>> = HashMap::new();
+    providers.insert(pid, Box::new(provider));
+
+    Arc::new(EnigmaS3State {
+        db: Arc::new(Mutex::new(db)),
+        providers: providers.into(),
+        distributor: distributor.into(),
+        key_material,
+        ..TestState::new(dir)
+            .local_provider()
+            .configure(|c| c.verify_on_read = verify_on_read)
+            .into_state()
+    })
+}
+
+#[tokio::test]
+async fn corrupted_chunk_rejected_when_verify_on_read() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = corrupted_state(tmp.path(), true).await;
+
+    let err = enigma_s3::ops::retrieve_object_parallel(&state, "bucket", "obj.txt")
+        .await
+        .unwrap_err();
+    assert!(
+        err.to_string().contains("hash mismatch"),
+        "unexpected error: {err}"
+    );
+}
+
+#[tokio::test]
+async fn corrupted_chunk_passes_without_verify_on_read() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state = corrupted_state(tmp.path(), false).await;
+
+    // Decryption alone cannot detect the corruption
+    let data = enigma_s3::ops::retrieve_object_parallel(&state, "bucket", "obj.txt")
+        .await
+        .unwrap();
+    assert_eq!(data[0], b't' ^ 0x01);
+}
@@ -1,15 +1,28 @@
 #[cfg(feature = "azure")]
 mod inner {
+    use std::sync::Arc;
+
     use async_trait::async_trait;
+    use azure_core::TransportOptions;
     use azure_storage::StorageCredentials;
     use azure_storage_blobs::prelude::*;
+    use futures::StreamExt;
 
-    use crate::provider::StorageProvider;
+    use crate::credentials::RotatingClient;
+    use crate::provider::{
+        CredentialProvider, Credentials, ProviderUsage, StorageProvider, TimeoutConfig,
+    };
 
     /// Azure Blob Storage provider.
     pub struct AzureStorageProvider {
         container_client: ContainerClient,
+        /// Replaces `container_client` when credentials rotate.
+        rotating: Option<RotatingClient<ContainerClient>>,
+        container: String,
+        timeouts: TimeoutConfig,
         name: String,
+        storage_class: Option<String>,
+        access_tier: Option<AccessTier>,
     }
 
     impl AzureStorageProvider {
@@ -19,14 +32,19 @@ mod inner {
             access_key: &str,
             container: &str,
             name: &str,
+            timeouts: TimeoutConfig,
         ) -> anyhow::Result<Self> {
             let credentials = StorageCredentials::access_key(account, access_key.to_string());
-            let container_client =
-                ClientBuilder::new(account, credentials).container_client(container);
+            let container_client = container_client(account, credentials, container, timeouts)?;
 
             Ok(Self {
                 container_client,
+                rotating: None,
+                container: container.to_string(),
+                timeouts,
                 name: name.to_string(),
+                storage_class: None,
+                access_tier: None,
             })
         }
 
@@ -36,38 +54,123 @@ mod inner {
 
             Ok(Self {
                 container_client,
+                rotating: None,
+                container: container.to_string(),
+                timeouts: TimeoutConfig::default(),
                 name: name.to_string(),
+                storage_class: None,
+                access_tier: None,
             })
         }
     }
 
+    impl AzureStorageProvider {
+        /// Write chunks in access tier `storage_class`: `HOT`, `COOL` or
+        /// `ARCHIVE`. `None` uses the account default.
+        pub fn with_storage_class(mut self, storage_class: Option<&str>) -> anyhow::Result<Self> {
+            self.access_tier = storage_class.map(access_tier).transpose()?;
+            self.storage_class = storage_class.map(str::to_string);
+            Ok(self)
+        }
+
+        /// Sign requests with `credentials` instead of the static account
+        /// key: the access key is the storage account name, the secret its
+        /// key, and a session token, if any, a SAS token used instead.
+        pub fn with_credential_provider(
+            mut self,
+            credentials: Box<dyn CredentialProvider>,
+        ) -> Self {
+            let (container, timeouts) = (self.container.clone(), self.timeouts);
+            self.rotating = Some(RotatingClient::new(credentials, move |credentials| {
+                container_client(
+                    &credentials.access_key,
+                    storage_credentials(credentials)?,
+                    &container,
+                    timeouts,
+                )
+            }));
+            self
+        }
+
+        /// Reload the credentials now instead of on their schedule, e.g.
+        /// after a SAS token was revoked.
+        pub async fn force_refresh(&self) -> anyhow::Result<()> {
+            match &self.rotating {
+                Some(rotating) => rotating.refresh().await,
+                None => Ok(()),
+            }
+        }
+
+        async fn client(&self) -> anyhow::Result<ContainerClient> {
+            match &self.rotating {
+                Some(rotating) => rotating.client().await,
+                None => Ok(self.container_client.clone()),
+            }
+        }
+    }
+
+    fn storage_credentials(credentials: &Credentials) -> anyhow::Result<StorageCredentials> {
+        Ok(match &credentials.session_token {
+            Some(sas) => StorageCredentials::sas_token(sas)?,
+            None => StorageCredentials::access_key(
+                credentials.access_key.as_str(),
+                credentials.secret_key.clone(),
+            ),
+        })
+    }
+
+    fn container_client(
+        account: &str,
+        credentials: StorageCredentials,
+        container: &str,
+        timeouts: TimeoutConfig,
+    ) -> anyhow::Result<ContainerClient> {
+        let mut builder = ClientBuilder::new(account, credentials);
+        if timeouts.is_set() {
+            let http = timeouts.reqwest_client()?;
+            builder = builder.transport(TransportOptions::new(Arc::new(http)));
+        }
+        Ok(builder.container_client(container))
+    }
+
+    fn access_tier(storage_class: &str) -> anyhow::Result<AccessTier> {
+        match storage_class.to_ascii_uppercase().as_str() {
+            "HOT" => Ok(AccessTier::Hot),
+            "COOL" => Ok(AccessTier::Cool),
+            "ARCHIVE" => Ok(AccessTier::Archive),
+            _ => anyhow::bail!(
+                "unsupported Azure access tier \"{storage_class}\" (expected HOT, COOL or ARCHIVE)"
+            ),
+        }
+    }
+
     #[async_trait]
     impl StorageProvider for AzureStorageProvider {
         async fn upload_chunk(&self, key: &str, data: &[u8]) -> anyhow::Result<()> {
-            self.container_client
+            let mut upload = self
+                .client()
+                .await?
                 .blob_client(key)
-                .put_block_blob(data.to_vec())
-                .await?;
+                .put_block_blob(data.to_vec());
+            if let Some(tier) = self.access_tier {
+                upload = upload.access_tier(tier);
+            }
+            upload.await?;
             Ok(())
         }
 
         async fn download_chunk(&self, key: &str) -> anyhow::Result<Vec<u8>> {
-            let resp = self.container_client.blob_client(key).get_content().await?;
+            let resp = self.client().await?.blob_client(key).get_content().await?;
             Ok(resp)
         }
 
         async fn delete_chunk(&self, key: &str) -> anyhow::Result<()> {
-            self.container_client.blob_client(key).delete().await?;
+            self.client().await?.blob_client(key).delete().await?;
             Ok(())
         }
 
         async fn chunk_exists(&self, key: &str) -> anyhow::Result<bool> {
-            match self
-                .container_client
-                .blob_client(key)
-                .get_properties()
-                .await
-            {
+            match self.client().await?.blob_client(key).get_properties().await {
                 Ok(_) => Ok(true),
                 Err(e) => {
                     let err_string = e.to_string();
@@ -84,10 +187,39 @@ mod inner {
         }
 
         async fn test_connection(&self) -> anyhow::Result<()> {
-            self.container_client.get_properties().await?;
+            self.client().await?.get_properties().await?;
             Ok(())
         }
 
+        async fn get_usage(&self) -> anyhow::Result<ProviderUsage> {
+            // Container properties carry no size: sum the blob listing
+            let mut usage = ProviderUsage::default();
+            let client = self.client().await?;
+            let mut pages = client.list_blobs().into_stream();
+            while let Some(page) = pages.next().await {
+                for blob in page?.blobs.blobs() {
+                    usage.used_bytes += blob.properties.content_length;
+                    usage.object_count += 1;
+                }
+            }
+            Ok(usage)
+        }
+
+        /// Rehydrate the blob to the Hot tier. Rehydration from Archive
+        /// takes hours; the blob stays unreadable until it is done.
+        async fn restore_from_archive(&self, key: &str) -> anyhow::Result<()> {
+            self.client()
+                .await?
+                .blob_client(key)
+                .set_blob_tier(AccessTier::Hot)
+                .await?;
+            Ok(())
+        }
+
+        fn storage_class(&self) -> Option<&str> {
+            self.storage_class.as_deref()
+        }
+
         fn name(&self) -> &str {
             &self.name
         }
@@ -0,0 +1,287 @@
+//! Cloud credentials that are re-read while the proxy runs, so rotated keys
+//! and session tokens are picked up without a restart.
+
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+
+use crate::provider::{CredentialProvider, Credentials};
+
+/// Where a [`RotatingCredentialProvider`] reads its credentials from.
+#[derive(Debug, Clone)]
+pub enum CredentialSource {
+    /// Environment variables holding the access key, secret and, optionally,
+    /// session token.
+    Env {
+        access_key_var: String,
+        secret_key_var: String,
+        session_token_var: Option<String>,
+    },
+    /// A file in the AWS shared credentials format: `aws_access_key_id`,
+    /// `aws_secret_access_key` and optional `aws_session_token` lines. Only
+    /// the first profile is read.
+    File(PathBuf),
+    /// Credentials that never change.
+    Static(Credentials),
+}
+
+impl CredentialSource {
+    /// `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and `AWS_SESSION_TOKEN`.
+    pub fn aws_env() -> Self {
+        Self::Env {
+            access_key_var: "AWS_ACCESS_KEY_ID".to_string(),
+            secret_key_var: "AWS_SECRET_ACCESS_KEY".to_string(),
+            session_token_var: Some("AWS_SESSION_TOKEN".to_string()),
+        }
+    }
+
+    /// `AZURE_STORAGE_ACCOUNT`, `AZURE_STORAGE_KEY` and
+    /// `AZURE_STORAGE_SAS_TOKEN`.
+    pub fn azure_env() -> Self {
+        Self::Env {
+            access_key_var: "AZURE_STORAGE_ACCOUNT".to_string(),
+            secret_key_var: "AZURE_STORAGE_KEY".to_string(),
+            session_token_var: Some("AZURE_STORAGE_SAS_TOKEN".to_string()),
+        }
+    }
+
+    fn load(&self) -> anyhow::Result<Credentials> {
+        match self {
+            Self::Env {
+                access_key_var,
+                secret_key_var,
+                session_token_var,
+            } => {
+                let var = |name: &str| {
+                    std::env::var(name).map_err(|_| anyhow::anyhow!("{name} is not set"))
+                };
+                Ok(Credentials {
+                    access_key: var(access_key_var)?,
+                    secret_key: var(secret_key_var)?,
+                    session_token: session_token_var
+                        .as_deref()
+                        .and_then(|name| std::env::var(name).ok()),
+                })
+            }
+            Self::File(path) => {
+                let contents = std::fs::read_to_string(path).map_err(|e| {
+                    anyhow::anyhow!("cannot read credentials file {}: {e}", path.display())
+                })?;
+                parse_credentials_file(&contents).map_err(|e| {
+                    anyhow::anyhow!("invalid credentials file {}: {e}", path.display())
+                })
+            }
+            Self::Static(credentials) => Ok(credentials.clone()),
+        }
+    }
+}
+
+fn parse_credentials_file(contents: &str) -> anyhow::Result<Credentials> {
+    let (mut access_key, mut secret_key, mut session_token) = (None, None, None);
+    let mut profiles = 0;
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if line.starts_with('[') {
+            profiles += 1;
+            if profiles > 1 {
+                break;
+            }
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            anyhow::bail!("expected `key = value`, got \"{line}\"");
+        };
+        let value = Some(value.trim().to_string());
+        match key.trim() {
+            "aws_access_key_id" => access_key = value,
+            "aws_secret_access_key" => secret_key = value,
+            "aws_session_token" => session_token = value,
+            _ => {}
+        }
+    }
+    Ok(Credentials {
+        access_key: access_key.ok_or_else(|| anyhow::anyhow!("missing aws_access_key_id"))?,
+        secret_key: secret_key.ok_or_else(|| anyhow::anyhow!("missing aws_secret_access_key"))?,
+        session_token,
+    })
+}
+
+/// Credentials re-read from their [`CredentialSource`] every `interval`,
+/// and whenever they are refreshed.
+pub struct RotatingCredentialProvider {
+    source: CredentialSource,
+    interval: Option<Duration>,
+    current: Mutex<(Credentials, Instant)>,
+}
+
+impl RotatingCredentialProvider {
+    /// Load the credentials from `source`. With `interval` `None`, they are
+    /// only re-read by [`CredentialProvider::refresh_credentials`].
+    pub fn new(source: CredentialSource, interval: Option<Duration>) -> anyhow::Result<Self> {
+        let credentials = source.load()?;
+        Ok(Self {
+            source,
+            interval,
+            current: Mutex::new((credentials, Instant::now())),
+        })
+    }
+}
+
+#[async_trait]
+impl CredentialProvider for RotatingCredentialProvider {
+    async fn get_credentials(&self) -> anyhow::Result<Credentials> {
+        let mut current = self.current.lock().unwrap_or_else(|e| e.into_inner());
+        if self
+            .interval
+            .is_some_and(|interval| current.1.elapsed() >= interval)
+        {
+            // Keep the current credentials until the next attempt if the
+            // source is unreadable, rather than failing every request
+            match self.source.load() {
+                Ok(credentials) => current.0 = credentials,
+                Err(e) => tracing::warn!("Failed to re-read credentials: {e}"),
+            }
+            current.1 = Instant::now();
+        }
+        Ok(current.0.clone())
+    }
+
+    async fn refresh_credentials(&mut self) -> anyhow::Result<()> {
+        let credentials = self.source.load()?;
+        *self.current.get_mut().unwrap_or_else(|e| e.into_inner()) = (credentials, Instant::now());
+        Ok(())
+    }
+}
+
+/// Builds a backend client signing with the given credentials.
+#[cfg(any(feature = "s3", feature = "azure"))]
+type BuildClient<C> = Box<dyn Fn(&Credentials) -> anyhow::Result<C> + Send + Sync>;
+
+/// A backend client built from a [`CredentialProvider`]'s credentials,
+/// rebuilt whenever they change.
+#[cfg(any(feature = "s3", feature = "azure"))]
+pub(crate) struct RotatingClient<C> {
+    credentials: tokio::sync::RwLock<Box<dyn CredentialProvider>>,
+    build: BuildClient<C>,
+    current: Mutex<Option<(Credentials, C)>>,
+}
+
+#[cfg(any(feature = "s3", feature = "azure"))]
+impl<C: Clone> RotatingClient<C> {
+    pub(crate) fn new(
+        credentials: Box<dyn CredentialProvider>,
+        build: impl Fn(&Credentials) -> anyhow::Result<C> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            credentials: tokio::sync::RwLock::new(credentials),
+            build: Box::new(build),
+            current: Mutex::new(None),
+        }
+    }
+
+    /// A client signing with the current credentials.
+    pub(crate) async fn client(&self) -> anyhow::Result<C> {
+        let credentials = self.credentials.read().await.get_credentials().await?;
+        let mut current = self.current.lock().unwrap_or_else(|e| e.into_inner());
+        match &*current {
+            Some((used, client)) if *used == credentials => Ok(client.clone()),
+            _ => {
+                let client = (self.build)(&credentials)?;
+                *current = Some((credentials, client.clone()));
+                Ok(client)
+            }
+        }
+    }
+
+    /// Reload the credentials; the next [`RotatingClient::client`] call
+    /// picks them up.
+    pub(crate) async fn refresh(&self) -> anyhow::Result<()> {
+        self.credentials.write().await.refresh_credentials().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn credentials(access_key: &str) -> Credentials {
+        Credentials {
+            access_key: access_key.to_string(),
+            secret_key: "secret".to_string(),
+            session_token: None,
+        }
+    }
+
+    #[test]
+    fn parses_the_first_profile_of_a_credentials_file() {
+        let parsed = parse_credentials_file(
+            "# rotated hourly\n[default]\naws_access_key_id = AKID\n\
+             aws_secret_access_key=secret\naws_session_token = token\n\
+             [other]\naws_access_key_id = OTHER\n",
+        )
+        .unwrap();
+        assert_eq!(
+            parsed,
+            Credentials {
+                access_key: "AKID".to_string(),
+                secret_key: "secret".to_string(),
+                session_token: Some("token".to_string()),
+            }
+        );
+
+        let err = parse_credentials_file("aws_access_key_id = AKID\n").unwrap_err();
+        assert_eq!(err.to_string(), "missing aws_secret_access_key");
+    }
+
+    #[tokio::test]
+    async fn file_credentials_are_reread_on_schedule_or_refresh() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("credentials");
+        let write = |access_key: &str| {
+            std::fs::write(
+                &path,
+                format!("aws_access_key_id = {access_key}\naws_secret_access_key = secret\n"),
+            )
+            .unwrap()
+        };
+
+        write("first");
+        let mut hourly = RotatingCredentialProvider::new(
+            CredentialSource::File(path.clone()),
+            Some(Duration::from_secs(3600)),
+        )
+        .unwrap();
+        let always = RotatingCredentialProvider::new(
+            CredentialSource::File(path.clone()),
+            Some(Duration::ZERO),
+        )
+        .unwrap();
+
+        write("second");
+        assert_eq!(
+            hourly.get_credentials().await.unwrap(),
+            credentials("first")
+        );
+        assert_eq!(
+            always.get_credentials().await.unwrap(),
+            credentials("second")
+        );
+        hourly.refresh_credentials().await.unwrap();
+        assert_eq!(
+            hourly.get_credentials().await.unwrap(),
+            credentials("second")
+        );
+
+        // An unreadable file keeps the last good credentials
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            always.get_credentials().await.unwrap(),
+            credentials("second")
+        );
+        assert!(hourly.refresh_credentials().await.is_err());
+    }
+}
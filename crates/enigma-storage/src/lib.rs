@@ -1,8 +1,13 @@
 #[cfg(feature = "azure")]
 pub mod azure;
+pub mod credentials;
 #[cfg(feature = "gcs")]
 pub mod gcs;
 pub mod local;
+pub mod migrate;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod mock;
 pub mod provider;
+pub mod reencrypt;
 #[cfg(feature = "s3")]
 pub mod s3;
@@ -1,7 +1,8 @@
 use async_trait::async_trait;
 use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
 
-use crate::provider::StorageProvider;
+use crate::provider::{ProviderUsage, StorageProvider};
 
 /// Filesystem-based storage provider for local testing.
 pub struct LocalStorageProvider {
@@ -25,6 +26,65 @@ impl LocalStorageProvider {
         }
         Ok(self.base_path.join(key))
     }
+
+    /// Write `data` to a uniquely named temp file next to `path`, then
+    /// rename it into place, so readers never see a partial chunk.
+    async fn write_atomic(path: &Path, data: &[u8]) -> anyhow::Result<()> {
+        let file_name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| anyhow::anyhow!("invalid chunk path: {}", path.display()))?;
+        let tmp = path.with_file_name(format!(".{file_name}.{}.tmp", uuid::Uuid::new_v4()));
+
+        let result = async {
+            let mut file = tokio::fs::File::create(&tmp).await?;
+            file.write_all(data).await?;
+            file.flush().await?;
+            drop(file);
+            tokio::fs::rename(&tmp, path).await
+        }
+        .await;
+        if result.is_err() {
+            let _ = tokio::fs::remove_file(&tmp).await;
+        }
+        Ok(result?)
+    }
+
+    /// Total size and number of the files under `dir`.
+    fn walk(dir: &Path) -> std::io::Result<(u64, u64)> {
+        let (mut bytes, mut files) = (0, 0);
+        let mut stack = vec![dir.to_path_buf()];
+        while let Some(dir) = stack.pop() {
+            for entry in std::fs::read_dir(dir)? {
+                let entry = entry?;
+                let meta = entry.metadata()?;
+                if meta.is_dir() {
+                    stack.push(entry.path());
+                } else if !entry.file_name().to_string_lossy().ends_with(".tmp") {
+                    bytes += meta.len();
+                    files += 1;
+                }
+            }
+        }
+        Ok((bytes, files))
+    }
+}
+
+/// Space left for unprivileged writes on the filesystem holding `path`.
+#[cfg(unix)]
+fn available_bytes(path: &Path) -> std::io::Result<u64> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes())?;
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: c_path is NUL-terminated and stat is only read after success
+    if unsafe { libc::statvfs(c_path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    // SAFETY: statvfs returned 0, so it filled in the struct
+    let stat = unsafe { stat.assume_init() };
+    #[allow(clippy::unnecessary_cast)]
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
 }
 
 #[async_trait]
@@ -34,8 +94,7 @@ impl StorageProvider for LocalStorageProvider {
         if let Some(parent) = path.parent() {
             tokio::fs::create_dir_all(parent).await?;
         }
-        tokio::fs::write(&path, data).await?;
-        Ok(())
+        Self::write_atomic(&path, data).await
     }
 
     async fn download_chunk(&self, key: &str) -> anyhow::Result<Vec<u8>> {
@@ -64,6 +123,23 @@ impl StorageProvider for LocalStorageProvider {
         Ok(())
     }
 
+    async fn get_usage(&self) -> anyhow::Result<ProviderUsage> {
+        let base_path = self.base_path.clone();
+        tokio::task::spawn_blocking(move || {
+            let (used_bytes, object_count) = Self::walk(&base_path)?;
+            #[cfg(unix)]
+            let available_bytes = Some(available_bytes(&base_path)?);
+            #[cfg(not(unix))]
+            let available_bytes = None;
+            Ok(ProviderUsage {
+                used_bytes,
+                available_bytes,
+                object_count,
+            })
+        })
+        .await?
+    }
+
     fn name(&self) -> &str {
         &self.name
     }
@@ -92,6 +168,59 @@ mod tests {
         assert!(!provider.chunk_exists(key).await.unwrap());
     }
 
+    #[tokio::test]
+    async fn concurrent_uploads_do_not_corrupt() {
+        let tmp = TempDir::new().unwrap();
+        let provider =
+            std::sync::Arc::new(LocalStorageProvider::new(tmp.path(), "test-local").unwrap());
+
+        // Half the uploads race on one key, the rest use their own
+        let tasks: Vec<_> = (0..50u8)
+            .map(|i| {
+                let provider = provider.clone();
+                tokio::spawn(async move {
+                    let key = if i % 2 == 0 {
+                        "enigma/chunks/00/00/shared".to_string()
+                    } else {
+                        format!("enigma/chunks/{i:02x}/00/chunk{i}")
+                    };
+                    let data = vec![i; 64 * 1024];
+                    provider.upload_chunk(&key, &data).await.unwrap();
+                    (key, data)
+                })
+            })
+            .collect();
+        let mut results = Vec::with_capacity(tasks.len());
+        for task in tasks {
+            results.push(task.await.unwrap());
+        }
+
+        for (key, data) in &results {
+            let downloaded = provider.download_chunk(key).await.unwrap();
+            if key.ends_with("shared") {
+                // Whole content of exactly one writer
+                assert_eq!(downloaded.len(), data.len());
+                assert!(downloaded.iter().all(|b| *b == downloaded[0]));
+                assert_eq!(downloaded[0] % 2, 0);
+            } else {
+                assert_eq!(&downloaded, data);
+            }
+        }
+
+        // No temp files left behind
+        let mut stack = vec![tmp.path().to_path_buf()];
+        while let Some(dir) = stack.pop() {
+            for entry in std::fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    stack.push(path);
+                } else {
+                    assert!(!path.to_string_lossy().ends_with(".tmp"), "{path:?}");
+                }
+            }
+        }
+    }
+
     #[tokio::test]
     async fn manifest_roundtrip() {
         let tmp = TempDir::new().unwrap();
@@ -110,6 +239,30 @@ mod tests {
         provider.test_connection().await.unwrap();
     }
 
+    #[tokio::test]
+    async fn usage_counts_stored_chunks() {
+        let tmp = TempDir::new().unwrap();
+        let provider = LocalStorageProvider::new(tmp.path(), "test-local").unwrap();
+
+        let empty = provider.get_usage().await.unwrap();
+        assert_eq!((empty.used_bytes, empty.object_count), (0, 0));
+
+        provider
+            .upload_chunk("enigma/chunks/aa/one", &[1; 100])
+            .await
+            .unwrap();
+        provider
+            .upload_chunk("enigma/chunks/bb/two", &[2; 50])
+            .await
+            .unwrap();
+        provider.upload_manifest(b"manifest").await.unwrap();
+
+        let usage = provider.get_usage().await.unwrap();
+        assert_eq!(usage.used_bytes, 158);
+        assert_eq!(usage.object_count, 3);
+        assert!(usage.available_bytes.unwrap() > 0);
+    }
+
     #[tokio::test]
     async fn rejects_path_traversal() {
         let tmp = TempDir::new().unwrap();
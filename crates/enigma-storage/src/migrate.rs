@@ -0,0 +1,320 @@
+//! Move stored chunks from one storage key layout to another.
+//!
+//! Each copy of a chunk is downloaded from its old key and uploaded under
+//! the new one; once every copy has moved, the manifest is pointed at the
+//! new key and the old blobs are deleted. An interrupted run can be
+//! restarted: chunks already recorded under the new key are skipped, and
+//! copies uploaded before the interruption are simply written again.
+
+use std::collections::HashMap;
+
+use anyhow::{Context, Result, anyhow};
+
+use enigma_core::manifest::ManifestDb;
+
+use crate::provider::StorageProvider;
+
+/// Chunk hashes fetched from the manifest per query.
+const PAGE_SIZE: u32 = 256;
+
+/// Maps a chunk's hex hash to its storage key.
+pub type KeyLayout = fn(&str) -> String;
+
+/// Known storage key layouts, by name.
+pub const LAYOUTS: &[(&str, KeyLayout)] = &[
+    ("two-level", two_level_key),
+    ("three-level", three_level_key),
+];
+
+/// `enigma/chunks/ab/cd/{hash}`, the layout written by
+/// [`ChunkHash::storage_key`](enigma_core::types::ChunkHash::storage_key).
+pub fn two_level_key(hash_hex: &str) -> String {
+    format!(
+        "enigma/chunks/{}/{}/{hash_hex}",
+        &hash_hex[..2],
+        &hash_hex[2..4]
+    )
+}
+
+/// `enigma/chunks/ab/cd/ef/{hash}`, spreading chunks over 256 times more
+/// directories.
+pub fn three_level_key(hash_hex: &str) -> String {
+    format!(
+        "enigma/chunks/{}/{}/{}/{hash_hex}",
+        &hash_hex[..2],
+        &hash_hex[2..4],
+        &hash_hex[4..6]
+    )
+}
+
+/// Key function of the layout called `name`.
+pub fn layout(name: &str) -> Option<KeyLayout> {
+    LAYOUTS
+        .iter()
+        .find(|(layout, _)| *layout == name)
+        .map(|(_, key_fn)| *key_fn)
+}
+
+/// Outcome of [`migrate_chunk_layout`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MigrationStats {
+    /// Chunks moved to the new layout (or that would be, in a dry run).
+    pub migrated: u64,
+    /// Chunk copies moved (one per replica).
+    pub copies_moved: u64,
+    /// Chunks already stored under the new layout.
+    pub skipped: u64,
+    /// Chunks left where they were because a copy could not be moved, or
+    /// because their key matches neither layout. Running again retries them.
+    pub failed: u64,
+}
+
+impl std::ops::AddAssign for MigrationStats {
+    fn add_assign(&mut self, other: Self) {
+        self.migrated += other.migrated;
+        self.copies_moved += other.copies_moved;
+        self.skipped += other.skipped;
+        self.failed += other.failed;
+    }
+}
+
+/// Move every chunk stored under `old_key_fn`'s layout to `new_key_fn`'s.
+///
+/// With `dry_run`, chunks are only counted. A chunk that fails is logged,
+/// counted in [`MigrationStats::failed`] and left under its old key; the
+/// others are still processed.
+pub async fn migrate_chunk_layout(
+    db: &ManifestDb,
+    providers: &HashMap<i64, Box<dyn StorageProvider>>,
+    old_key_fn: KeyLayout,
+    new_key_fn: KeyLayout,
+    dry_run: bool,
+) -> Result<MigrationStats> {
+    let mut stats = MigrationStats::default();
+    let mut after = String::new();
+    loop {
+        let page = db.chunk_hashes(&after, PAGE_SIZE)?;
+        let Some(last) = page.last() else {
+            break;
+        };
+        after = last.clone();
+
+        for hash in &page {
+            let (_, _, locations, ..) = db
+                .get_chunk_locations(hash)?
+                .ok_or_else(|| anyhow!("chunk {hash} not found"))?;
+            let old_key = old_key_fn(hash);
+            let new_key = new_key_fn(hash);
+
+            let pending: Vec<i64> = locations
+                .iter()
+                .filter(|(_, key)| *key != new_key)
+                .map(|(provider_id, _)| *provider_id)
+                .collect();
+            if pending.is_empty() {
+                stats.skipped += 1;
+                continue;
+            }
+            if locations
+                .iter()
+                .any(|(_, key)| *key != new_key && *key != old_key)
+            {
+                tracing::warn!("Chunk {hash} is stored under a key of neither layout");
+                stats.failed += 1;
+                continue;
+            }
+            if dry_run {
+                stats.migrated += 1;
+                stats.copies_moved += pending.len() as u64;
+                continue;
+            }
+
+            match move_chunk(db, providers, hash, &pending, &old_key, &new_key).await {
+                Ok(()) => {
+                    stats.migrated += 1;
+                    stats.copies_moved += pending.len() as u64;
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to migrate chunk {hash}: {e:#}");
+                    stats.failed += 1;
+                }
+            }
+        }
+    }
+
+    Ok(stats)
+}
+
+/// Copy a chunk from `old_key` to `new_key` on `provider_ids`, record the
+/// new key, then delete the old copies.
+async fn move_chunk(
+    db: &ManifestDb,
+    providers: &HashMap<i64, Box<dyn StorageProvider>>,
+    hash: &str,
+    provider_ids: &[i64],
+    old_key: &str,
+    new_key: &str,
+) -> Result<()> {
+    let mut moved = Vec::new();
+    for provider_id in provider_ids {
+        let provider = providers
+            .get(provider_id)
+            .ok_or_else(|| anyhow!("provider {provider_id} is not configured"))?;
+        let data = provider
+            .download_chunk(old_key)
+            .await
+            .with_context(|| format!("download from provider {provider_id}"))?;
+        provider
+            .upload_chunk(new_key, &data)
+            .await
+            .with_context(|| format!("upload to provider {provider_id}"))?;
+        moved.push(provider);
+    }
+
+    // Old copies stay until the manifest no longer points at them
+    db.rename_chunk_storage_key(hash, old_key, new_key)?;
+
+    for provider in moved {
+        if let Err(e) = provider.delete_chunk(old_key).await {
+            tracing::warn!(
+                "Failed to delete old copy of chunk {hash} from {}: {e:#}",
+                provider.name()
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::local::LocalStorageProvider;
+    use enigma_core::types::ProviderType;
+    use tempfile::TempDir;
+
+    /// Two local providers holding a replica of every chunk.
+    fn setup(tmp: &TempDir) -> (ManifestDb, HashMap<i64, Box<dyn StorageProvider>>) {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let mut providers: HashMap<i64, Box<dyn StorageProvider>> = HashMap::new();
+        for name in ["a", "b"] {
+            let dir = tmp.path().join(name);
+            let pid = db
+                .insert_provider(name, ProviderType::Local, dir.to_str().unwrap(), None, 1)
+                .unwrap();
+            providers.insert(
+                pid,
+                Box::new(LocalStorageProvider::new(&dir, name).unwrap()),
+            );
+        }
+        (db, providers)
+    }
+
+    async fn store(
+        db: &ManifestDb,
+        providers: &HashMap<i64, Box<dyn StorageProvider>>,
+        hash: &str,
+        data: &[u8],
+    ) {
+        let storage_key = two_level_key(hash);
+        let mut pids: Vec<i64> = providers.keys().copied().collect();
+        pids.sort();
+        db.insert_or_dedup_chunk(
+            hash,
+            &[0; 12],
+            "k",
+            pids[0],
+            &storage_key,
+            data.len() as u64,
+            data.len() as u64,
+            None,
+        )
+        .unwrap();
+        let replicas: Vec<(i64, &str)> = pids.iter().map(|p| (*p, storage_key.as_str())).collect();
+        db.insert_chunk_replicas(hash, &replicas).unwrap();
+        for provider in providers.values() {
+            provider.upload_chunk(&storage_key, data).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn moves_every_copy_and_skips_done_chunks() {
+        let tmp = TempDir::new().unwrap();
+        let (db, providers) = setup(&tmp);
+        let first = "aa".repeat(32);
+        let second = "bb".repeat(32);
+        store(&db, &providers, &first, b"first").await;
+        store(&db, &providers, &second, b"second").await;
+
+        let stats = migrate_chunk_layout(&db, &providers, two_level_key, three_level_key, true)
+            .await
+            .unwrap();
+        assert_eq!(stats.migrated, 2);
+        assert_eq!(stats.copies_moved, 4);
+        let (_, _, locations, ..) = db.get_chunk_locations(&first).unwrap().unwrap();
+        assert!(
+            locations
+                .iter()
+                .all(|(_, key)| *key == two_level_key(&first))
+        );
+
+        let stats = migrate_chunk_layout(&db, &providers, two_level_key, three_level_key, false)
+            .await
+            .unwrap();
+        assert_eq!(
+            stats,
+            MigrationStats {
+                migrated: 2,
+                copies_moved: 4,
+                skipped: 0,
+                failed: 0,
+            }
+        );
+        for (hash, data) in [
+            (&first, b"first".as_slice()),
+            (&second, b"second".as_slice()),
+        ] {
+            let (_, _, locations, ..) = db.get_chunk_locations(hash).unwrap().unwrap();
+            assert_eq!(locations.len(), 2);
+            for (pid, key) in locations {
+                assert_eq!(key, three_level_key(hash));
+                assert_eq!(providers[&pid].download_chunk(&key).await.unwrap(), data);
+                assert!(
+                    !providers[&pid]
+                        .chunk_exists(&two_level_key(hash))
+                        .await
+                        .unwrap()
+                );
+            }
+        }
+
+        // A restarted run finds nothing left to move
+        let stats = migrate_chunk_layout(&db, &providers, two_level_key, three_level_key, false)
+            .await
+            .unwrap();
+        assert_eq!(stats.skipped, 2);
+        assert_eq!(stats.migrated, 0);
+    }
+
+    #[tokio::test]
+    async fn missing_copy_leaves_chunk_under_old_key() {
+        let tmp = TempDir::new().unwrap();
+        let (db, providers) = setup(&tmp);
+        let hash = "cc".repeat(32);
+        store(&db, &providers, &hash, b"missing replica").await;
+
+        let (_, _, locations, ..) = db.get_chunk_locations(&hash).unwrap().unwrap();
+        let (pid, key) = &locations[1];
+        providers[pid].delete_chunk(key).await.unwrap();
+
+        let stats = migrate_chunk_layout(&db, &providers, two_level_key, three_level_key, false)
+            .await
+            .unwrap();
+        assert_eq!(stats.failed, 1);
+        let (_, _, locations, ..) = db.get_chunk_locations(&hash).unwrap().unwrap();
+        assert!(
+            locations
+                .iter()
+                .all(|(_, key)| *key == two_level_key(&hash))
+        );
+    }
+}
@@ -0,0 +1,312 @@
+//! In-memory `StorageProvider` for tests.
+//!
+//! Blobs live in a `HashMap` behind an `Arc<Mutex<..>>`, so a test can keep
+//! a clone of the provider after boxing it into a provider map and inspect
+//! what was stored, how often each method ran, and how many calls overlapped.
+//! Failures and latency can be injected per method. Available to other
+//! crates with the `test-utils` feature.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use crate::provider::{ProviderUsage, StorageProvider};
+
+/// Provider methods that can fail or be slowed down on demand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MockMethod {
+    UploadChunk,
+    DownloadChunk,
+    DeleteChunk,
+    ChunkExists,
+    TestConnection,
+    GetUsage,
+}
+
+impl MockMethod {
+    /// Key of this method in [`MockStorageProvider::call_counts`].
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::UploadChunk => "upload_chunk",
+            Self::DownloadChunk => "download_chunk",
+            Self::DeleteChunk => "delete_chunk",
+            Self::ChunkExists => "chunk_exists",
+            Self::TestConnection => "test_connection",
+            Self::GetUsage => "get_usage",
+        }
+    }
+}
+
+struct Failure {
+    /// Calls of the method up to this count still succeed.
+    succeed_until: u32,
+    error: String,
+}
+
+#[derive(Default)]
+struct Behaviour {
+    failures: HashMap<MockMethod, Failure>,
+    delays: HashMap<MockMethod, Duration>,
+    in_flight: HashMap<MockMethod, u32>,
+    max_in_flight: HashMap<MockMethod, u32>,
+    capacity: Option<u64>,
+}
+
+/// In-memory storage provider with call counting and failure injection.
+/// Clones share the same blobs, counters and injected behaviour.
+#[derive(Clone)]
+pub struct MockStorageProvider {
+    name: String,
+    pub blobs: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+    pub call_counts: Arc<Mutex<HashMap<String, u32>>>,
+    behaviour: Arc<Mutex<Behaviour>>,
+}
+
+impl Default for MockStorageProvider {
+    fn default() -> Self {
+        Self::new("mock")
+    }
+}
+
+impl MockStorageProvider {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            blobs: Default::default(),
+            call_counts: Default::default(),
+            behaviour: Default::default(),
+        }
+    }
+
+    /// Store `data` under `key` without counting a call.
+    pub fn insert(&self, key: &str, data: &[u8]) {
+        self.blobs
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), data.to_vec());
+    }
+
+    /// Whether a blob is stored under `key`, without counting a call.
+    pub fn contains(&self, key: &str) -> bool {
+        self.blobs.lock().unwrap().contains_key(key)
+    }
+
+    /// Number of stored blobs.
+    pub fn len(&self) -> usize {
+        self.blobs.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Let the next `after_n_calls` calls of `method` succeed, then fail
+    /// every later one with `error` until [`clear_failure`](Self::clear_failure).
+    pub fn inject_failure(&self, method: MockMethod, after_n_calls: u32, error: &str) {
+        let succeed_until = self.call_count(method) + after_n_calls;
+        self.behaviour.lock().unwrap().failures.insert(
+            method,
+            Failure {
+                succeed_until,
+                error: error.to_string(),
+            },
+        );
+    }
+
+    /// Make `method` succeed again.
+    pub fn clear_failure(&self, method: MockMethod) {
+        self.behaviour.lock().unwrap().failures.remove(&method);
+    }
+
+    /// Sleep for `delay` at the start of every call of `method`.
+    pub fn set_delay(&self, method: MockMethod, delay: Duration) {
+        self.behaviour.lock().unwrap().delays.insert(method, delay);
+    }
+
+    /// Report a fixed capacity from `get_usage`, so `available_bytes` is
+    /// what the stored blobs leave of it. Unset, the mock has no limit.
+    pub fn set_capacity(&self, bytes: u64) {
+        self.behaviour.lock().unwrap().capacity = Some(bytes);
+    }
+
+    /// How many times `method` has been called, failed calls included.
+    pub fn call_count(&self, method: MockMethod) -> u32 {
+        self.call_counts
+            .lock()
+            .unwrap()
+            .get(method.name())
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Highest number of calls of `method` that were running at once.
+    pub fn max_concurrent_calls(&self, method: MockMethod) -> u32 {
+        self.behaviour
+            .lock()
+            .unwrap()
+            .max_in_flight
+            .get(&method)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Count the call, apply the injected delay and failure.
+    async fn enter(&self, method: MockMethod) -> anyhow::Result<InFlight<'_>> {
+        let n = {
+            let mut counts = self.call_counts.lock().unwrap();
+            let count = counts.entry(method.name().to_string()).or_insert(0);
+            *count += 1;
+            *count
+        };
+        let delay = {
+            let mut behaviour = self.behaviour.lock().unwrap();
+            let running = behaviour.in_flight.entry(method).or_insert(0);
+            *running += 1;
+            let running = *running;
+            let max = behaviour.max_in_flight.entry(method).or_insert(0);
+            *max = (*max).max(running);
+            behaviour.delays.get(&method).copied()
+        };
+        let guard = InFlight {
+            provider: self,
+            method,
+        };
+
+        if let Some(delay) = delay {
+            tokio::time::sleep(delay).await;
+        }
+        let behaviour = self.behaviour.lock().unwrap();
+        if let Some(failure) = behaviour.failures.get(&method)
+            && n > failure.succeed_until
+        {
+            anyhow::bail!("{}", failure.error);
+        }
+        drop(behaviour);
+        Ok(guard)
+    }
+}
+
+/// Marks a call as finished when dropped.
+struct InFlight<'a> {
+    provider: &'a MockStorageProvider,
+    method: MockMethod,
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut behaviour) = self.provider.behaviour.lock()
+            && let Some(running) = behaviour.in_flight.get_mut(&self.method)
+        {
+            *running -= 1;
+        }
+    }
+}
+
+#[async_trait]
+impl StorageProvider for MockStorageProvider {
+    async fn upload_chunk(&self, key: &str, data: &[u8]) -> anyhow::Result<()> {
+        let _call = self.enter(MockMethod::UploadChunk).await?;
+        self.insert(key, data);
+        Ok(())
+    }
+
+    async fn download_chunk(&self, key: &str) -> anyhow::Result<Vec<u8>> {
+        let _call = self.enter(MockMethod::DownloadChunk).await?;
+        self.blobs
+            .lock()
+            .unwrap()
+            .get(key)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("chunk not found: {key}"))
+    }
+
+    async fn delete_chunk(&self, key: &str) -> anyhow::Result<()> {
+        let _call = self.enter(MockMethod::DeleteChunk).await?;
+        self.blobs.lock().unwrap().remove(key);
+        Ok(())
+    }
+
+    async fn chunk_exists(&self, key: &str) -> anyhow::Result<bool> {
+        let _call = self.enter(MockMethod::ChunkExists).await?;
+        Ok(self.contains(key))
+    }
+
+    async fn test_connection(&self) -> anyhow::Result<()> {
+        let _call = self.enter(MockMethod::TestConnection).await?;
+        Ok(())
+    }
+
+    async fn get_usage(&self) -> anyhow::Result<ProviderUsage> {
+        let _call = self.enter(MockMethod::GetUsage).await?;
+        let blobs = self.blobs.lock().unwrap();
+        let used_bytes = blobs.values().map(|b| b.len() as u64).sum();
+        let capacity = self.behaviour.lock().unwrap().capacity;
+        Ok(ProviderUsage {
+            used_bytes,
+            available_bytes: capacity.map(|c| c.saturating_sub(used_bytes)),
+            object_count: blobs.len() as u64,
+        })
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn stores_and_counts_calls() {
+        let mock = MockStorageProvider::default();
+        mock.upload_chunk("a", b"one").await.unwrap();
+        mock.upload_manifest(b"manifest").await.unwrap();
+
+        assert_eq!(mock.download_chunk("a").await.unwrap(), b"one");
+        assert!(mock.download_chunk("missing").await.is_err());
+        mock.delete_chunk("a").await.unwrap();
+        assert!(!mock.chunk_exists("a").await.unwrap());
+
+        assert_eq!(mock.call_count(MockMethod::UploadChunk), 2);
+        assert_eq!(mock.call_count(MockMethod::DownloadChunk), 2);
+        assert_eq!(mock.call_count(MockMethod::DeleteChunk), 1);
+        assert_eq!(mock.call_count(MockMethod::ChunkExists), 1);
+        assert_eq!(mock.call_counts.lock().unwrap()["upload_chunk"], 2);
+        assert_eq!(mock.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn injected_failure_starts_after_n_calls() {
+        let mock = MockStorageProvider::default();
+        let shared = mock.clone();
+        mock.inject_failure(MockMethod::UploadChunk, 2, "connection reset");
+
+        mock.upload_chunk("a", b"1").await.unwrap();
+        mock.upload_chunk("b", b"2").await.unwrap();
+        let err = mock.upload_chunk("c", b"3").await.unwrap_err();
+        assert_eq!(err.to_string(), "connection reset");
+        assert!(!shared.contains("c"));
+        // Other methods are unaffected
+        assert!(mock.chunk_exists("a").await.unwrap());
+
+        shared.clear_failure(MockMethod::UploadChunk);
+        mock.upload_chunk("c", b"3").await.unwrap();
+        assert_eq!(shared.call_count(MockMethod::UploadChunk), 4);
+    }
+
+    #[tokio::test]
+    async fn tracks_overlapping_calls() {
+        let mock = MockStorageProvider::default();
+        mock.insert("a", b"x");
+        mock.set_delay(MockMethod::DownloadChunk, Duration::from_millis(50));
+
+        let (a, b) = tokio::join!(mock.download_chunk("a"), mock.download_chunk("a"));
+        a.unwrap();
+        b.unwrap();
+        assert_eq!(mock.max_concurrent_calls(MockMethod::DownloadChunk), 2);
+        assert_eq!(mock.max_concurrent_calls(MockMethod::UploadChunk), 0);
+    }
+}
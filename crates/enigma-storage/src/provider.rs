@@ -1,8 +1,22 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
 use async_trait::async_trait;
 
 /// The well-known key used to store the encrypted manifest.
 pub const MANIFEST_KEY: &str = "enigma-manifest.enc";
 
+/// Space taken up on a storage backend.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProviderUsage {
+    /// Bytes stored, manifest included.
+    pub used_bytes: u64,
+    /// Free space left, for backends with a fixed capacity (`None` for
+    /// cloud buckets, which have no limit).
+    pub available_bytes: Option<u64>,
+    pub object_count: u64,
+}
+
 /// Trait for cloud/local storage backends.
 #[async_trait]
 pub trait StorageProvider: Send + Sync {
@@ -31,6 +45,500 @@ pub trait StorageProvider: Send + Sync {
     /// Test connectivity.
     async fn test_connection(&self) -> anyhow::Result<()>;
 
+    /// Bytes and objects stored. Cloud backends list the whole bucket, so
+    /// callers should cache the result.
+    async fn get_usage(&self) -> anyhow::Result<ProviderUsage>;
+
+    /// Start bringing an archived chunk back online so it can be downloaded.
+    /// Backends without an offline tier have nothing to do.
+    async fn restore_from_archive(&self, _key: &str) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// A URL a client can download the chunk at `key` from directly,
+    /// valid for `expires_in`. Only backends that can sign URLs support it.
+    fn signed_url(&self, _key: &str, _expires_in: Duration) -> anyhow::Result<String> {
+        anyhow::bail!("provider {} cannot generate signed URLs", self.name())
+    }
+
+    /// Storage class new chunks are written with, as configured.
+    fn storage_class(&self) -> Option<&str> {
+        None
+    }
+
     /// Provider name for display.
     fn name(&self) -> &str;
 }
+
+/// Whether chunks in `storage_class` sit in an offline archive tier and
+/// need [`StorageProvider::restore_from_archive`] before a download.
+pub fn is_archive_class(storage_class: &str) -> bool {
+    matches!(
+        storage_class.to_ascii_uppercase().as_str(),
+        "ARCHIVE" | "GLACIER" | "DEEP_ARCHIVE"
+    )
+}
+
+/// A shared provider is a provider, so maps of `Arc`s (such as the S3
+/// proxy's, which can grow at runtime) work where boxed providers are expected.
+#[async_trait]
+impl<T: StorageProvider + ?Sized> StorageProvider for Arc<T> {
+    async fn upload_chunk(&self, key: &str, data: &[u8]) -> anyhow::Result<()> {
+        (**self).upload_chunk(key, data).await
+    }
+
+    async fn download_chunk(&self, key: &str) -> anyhow::Result<Vec<u8>> {
+        (**self).download_chunk(key).await
+    }
+
+    async fn delete_chunk(&self, key: &str) -> anyhow::Result<()> {
+        (**self).delete_chunk(key).await
+    }
+
+    async fn chunk_exists(&self, key: &str) -> anyhow::Result<bool> {
+        (**self).chunk_exists(key).await
+    }
+
+    async fn upload_manifest(&self, data: &[u8]) -> anyhow::Result<()> {
+        (**self).upload_manifest(data).await
+    }
+
+    async fn download_manifest(&self) -> anyhow::Result<Vec<u8>> {
+        (**self).download_manifest().await
+    }
+
+    async fn test_connection(&self) -> anyhow::Result<()> {
+        (**self).test_connection().await
+    }
+
+    async fn get_usage(&self) -> anyhow::Result<ProviderUsage> {
+        (**self).get_usage().await
+    }
+
+    async fn restore_from_archive(&self, key: &str) -> anyhow::Result<()> {
+        (**self).restore_from_archive(key).await
+    }
+
+    fn signed_url(&self, key: &str, expires_in: Duration) -> anyhow::Result<String> {
+        (**self).signed_url(key, expires_in)
+    }
+
+    fn storage_class(&self) -> Option<&str> {
+        (**self).storage_class()
+    }
+
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+}
+
+// ── Timeouts ────────────────────────────────────────────────
+
+/// Access key, secret and optional session token (an AWS session token or
+/// Azure SAS token) for a cloud backend.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Credentials {
+    pub access_key: String,
+    pub secret_key: String,
+    pub session_token: Option<String>,
+}
+
+impl std::fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Credentials")
+            .field("access_key", &self.access_key)
+            .field("secret_key", &"<redacted>")
+            .field(
+                "session_token",
+                &self.session_token.as_ref().map(|_| "<redacted>"),
+            )
+            .finish()
+    }
+}
+
+/// Source of credentials that expire, so a provider can pick up new ones
+/// without a restart.
+#[async_trait]
+pub trait CredentialProvider: Send + Sync {
+    /// The credentials to sign requests with now.
+    async fn get_credentials(&self) -> anyhow::Result<Credentials>;
+
+    /// Reload the credentials, e.g. after the backend rejected them.
+    async fn refresh_credentials(&mut self) -> anyhow::Result<()>;
+}
+
+/// Per-provider network timeouts. `None` keeps the SDK default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimeoutConfig {
+    /// Time allowed to establish a TCP (and TLS) connection.
+    pub connect: Option<Duration>,
+    /// Time allowed for a whole request.
+    pub request: Option<Duration>,
+    /// How long an idle pooled connection is kept open.
+    pub idle: Option<Duration>,
+}
+
+impl TimeoutConfig {
+    pub fn is_set(&self) -> bool {
+        self.connect.is_some() || self.request.is_some() || self.idle.is_some()
+    }
+
+    /// HTTP client for SDKs that accept a caller-built reqwest client.
+    #[cfg(any(feature = "azure", feature = "gcs"))]
+    pub(crate) fn reqwest_client(&self) -> anyhow::Result<reqwest::Client> {
+        let mut builder = reqwest::Client::builder();
+        if let Some(d) = self.connect {
+            builder = builder.connect_timeout(d);
+        }
+        if let Some(d) = self.request {
+            builder = builder.timeout(d);
+        }
+        if let Some(d) = self.idle {
+            builder = builder.pool_idle_timeout(d);
+        }
+        Ok(builder.build()?)
+    }
+}
+
+impl From<&enigma_core::config::ProviderConfig> for TimeoutConfig {
+    fn from(pc: &enigma_core::config::ProviderConfig) -> Self {
+        Self {
+            connect: pc.connect_timeout_ms.map(Duration::from_millis),
+            request: pc.request_timeout_ms.map(Duration::from_millis),
+            idle: pc.idle_timeout_ms.map(Duration::from_millis),
+        }
+    }
+}
+
+// ── Circuit breaker ─────────────────────────────────────────
+
+/// Circuit breaker thresholds for a storage provider.
+#[derive(Debug, Clone, Copy)]
+pub struct CircuitBreaker {
+    /// Consecutive failures (while Closed) before the circuit opens.
+    pub failure_threshold: u32,
+    /// Consecutive successes (while HalfOpen) before the circuit closes again.
+    pub success_threshold: u32,
+    /// How long the circuit stays Open before a probe request is let through.
+    pub open_duration: Duration,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            success_threshold: 2,
+            open_duration: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Normal operation: requests go to the inner provider.
+    Closed,
+    /// Failing fast: requests are rejected without calling the inner provider.
+    Open,
+    /// Probing: a single request at a time is let through to test recovery.
+    HalfOpen,
+}
+
+/// Error returned while the circuit is open.
+#[derive(Debug, thiserror::Error)]
+#[error("circuit open for storage provider '{provider}'")]
+pub struct CircuitOpen {
+    pub provider: String,
+}
+
+struct BreakerState {
+    state: CircuitState,
+    failures: u32,
+    successes: u32,
+    opened_at: Instant,
+    probe_in_flight: bool,
+}
+
+/// Shared breaker state, readable from outside the wrapper (e.g. by the distributor).
+pub struct BreakerHandle {
+    config: CircuitBreaker,
+    inner: Mutex<BreakerState>,
+}
+
+impl BreakerHandle {
+    fn lock(&self) -> std::sync::MutexGuard<'_, BreakerState> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Current state. An Open circuit whose `open_duration` has elapsed is
+    /// reported as HalfOpen, since the next request will probe.
+    pub fn state(&self) -> CircuitState {
+        let s = self.lock();
+        match s.state {
+            CircuitState::Open if s.opened_at.elapsed() >= self.config.open_duration => {
+                CircuitState::HalfOpen
+            }
+            state => state,
+        }
+    }
+
+    /// Whether requests to this provider are currently let through.
+    pub fn is_available(&self) -> bool {
+        self.state() != CircuitState::Open
+    }
+
+    /// Reserve a slot for one request, or reject it if the circuit is open.
+    fn try_acquire(&self) -> bool {
+        let mut s = self.lock();
+        match s.state {
+            CircuitState::Closed => true,
+            CircuitState::Open => {
+                if s.opened_at.elapsed() < self.config.open_duration {
+                    return false;
+                }
+                s.state = CircuitState::HalfOpen;
+                s.successes = 0;
+                s.probe_in_flight = true;
+                true
+            }
+            CircuitState::HalfOpen => {
+                if s.probe_in_flight {
+                    return false;
+                }
+                s.probe_in_flight = true;
+                true
+            }
+        }
+    }
+
+    fn record(&self, success: bool) {
+        let mut s = self.lock();
+        match s.state {
+            CircuitState::Closed => {
+                if success {
+                    s.failures = 0;
+                } else {
+                    s.failures += 1;
+                    if s.failures >= self.config.failure_threshold {
+                        s.state = CircuitState::Open;
+                        s.opened_at = Instant::now();
+                    }
+                }
+            }
+            CircuitState::HalfOpen => {
+                s.probe_in_flight = false;
+                if success {
+                    s.successes += 1;
+                    if s.successes >= self.config.success_threshold {
+                        s.state = CircuitState::Closed;
+                        s.failures = 0;
+                    }
+                } else {
+                    s.state = CircuitState::Open;
+                    s.opened_at = Instant::now();
+                }
+            }
+            // A request started before the circuit opened; its outcome is stale.
+            CircuitState::Open => {}
+        }
+    }
+}
+
+/// Wraps a provider so that a failing backend is short-circuited instead of
+/// stalling every chunk request for the full network timeout.
+pub struct CircuitBreakerStorageProvider {
+    inner: Box<dyn StorageProvider>,
+    breaker: Arc<BreakerHandle>,
+}
+
+impl CircuitBreakerStorageProvider {
+    pub fn new(inner: Box<dyn StorageProvider>, config: CircuitBreaker) -> Self {
+        Self {
+            inner,
+            breaker: Arc::new(BreakerHandle {
+                config,
+                inner: Mutex::new(BreakerState {
+                    state: CircuitState::Closed,
+                    failures: 0,
+                    successes: 0,
+                    opened_at: Instant::now(),
+                    probe_in_flight: false,
+                }),
+            }),
+        }
+    }
+
+    /// Handle to the breaker state, shared with this wrapper.
+    pub fn breaker(&self) -> Arc<BreakerHandle> {
+        self.breaker.clone()
+    }
+
+    pub fn state(&self) -> CircuitState {
+        self.breaker.state()
+    }
+
+    async fn call<T>(
+        &self,
+        fut: impl std::future::Future<Output = anyhow::Result<T>>,
+    ) -> anyhow::Result<T> {
+        if !self.breaker.try_acquire() {
+            return Err(CircuitOpen {
+                provider: self.inner.name().to_string(),
+            }
+            .into());
+        }
+        let result = fut.await;
+        self.breaker.record(result.is_ok());
+        result
+    }
+}
+
+#[async_trait]
+impl StorageProvider for CircuitBreakerStorageProvider {
+    async fn upload_chunk(&self, key: &str, data: &[u8]) -> anyhow::Result<()> {
+        self.call(self.inner.upload_chunk(key, data)).await
+    }
+
+    async fn download_chunk(&self, key: &str) -> anyhow::Result<Vec<u8>> {
+        self.call(self.inner.download_chunk(key)).await
+    }
+
+    async fn delete_chunk(&self, key: &str) -> anyhow::Result<()> {
+        self.call(self.inner.delete_chunk(key)).await
+    }
+
+    async fn chunk_exists(&self, key: &str) -> anyhow::Result<bool> {
+        self.call(self.inner.chunk_exists(key)).await
+    }
+
+    async fn upload_manifest(&self, data: &[u8]) -> anyhow::Result<()> {
+        self.call(self.inner.upload_manifest(data)).await
+    }
+
+    async fn download_manifest(&self) -> anyhow::Result<Vec<u8>> {
+        self.call(self.inner.download_manifest()).await
+    }
+
+    async fn test_connection(&self) -> anyhow::Result<()> {
+        self.call(self.inner.test_connection()).await
+    }
+
+    async fn get_usage(&self) -> anyhow::Result<ProviderUsage> {
+        self.call(self.inner.get_usage()).await
+    }
+
+    async fn restore_from_archive(&self, key: &str) -> anyhow::Result<()> {
+        self.call(self.inner.restore_from_archive(key)).await
+    }
+
+    fn signed_url(&self, key: &str, expires_in: Duration) -> anyhow::Result<String> {
+        self.inner.signed_url(key, expires_in)
+    }
+
+    fn storage_class(&self) -> Option<&str> {
+        self.inner.storage_class()
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{MockMethod, MockStorageProvider};
+
+    /// Breaker around a mock whose uploads fail until the failure is cleared.
+    fn breaker(open_duration: Duration) -> (CircuitBreakerStorageProvider, MockStorageProvider) {
+        let mock = MockStorageProvider::new("flaky");
+        mock.inject_failure(MockMethod::UploadChunk, 0, "backend down");
+        let provider = CircuitBreakerStorageProvider::new(
+            Box::new(mock.clone()),
+            CircuitBreaker {
+                failure_threshold: 3,
+                success_threshold: 2,
+                open_duration,
+            },
+        );
+        (provider, mock)
+    }
+
+    #[cfg(any(feature = "azure", feature = "gcs"))]
+    #[tokio::test]
+    async fn connect_timeout_fails_fast() {
+        let timeouts = TimeoutConfig {
+            connect: Some(Duration::from_millis(1)),
+            ..Default::default()
+        };
+        let client = timeouts.reqwest_client().unwrap();
+
+        // Non-routable address: without a connect timeout this hangs until
+        // the OS gives up
+        let start = Instant::now();
+        let result = client.get("http://10.255.255.1/").send().await;
+        assert!(result.is_err());
+        assert!(start.elapsed() < Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn opens_after_threshold_and_fails_fast() {
+        let (cb, mock) = breaker(Duration::from_secs(3600));
+
+        for _ in 0..3 {
+            assert!(cb.upload_chunk("k", b"x").await.is_err());
+        }
+        assert_eq!(cb.state(), CircuitState::Open);
+        assert!(!cb.breaker().is_available());
+
+        // Rejected without reaching the inner provider
+        let err = cb.upload_chunk("k", b"x").await.unwrap_err();
+        assert!(err.downcast_ref::<CircuitOpen>().is_some());
+        assert_eq!(mock.call_count(MockMethod::UploadChunk), 3);
+    }
+
+    #[tokio::test]
+    async fn half_open_closes_after_successes() {
+        let (cb, mock) = breaker(Duration::ZERO);
+
+        for _ in 0..3 {
+            assert!(cb.upload_chunk("k", b"x").await.is_err());
+        }
+        mock.clear_failure(MockMethod::UploadChunk);
+        // open_duration elapsed immediately: next request probes
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+
+        cb.upload_chunk("k", b"x").await.unwrap();
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+        cb.upload_chunk("k", b"x").await.unwrap();
+        assert_eq!(cb.state(), CircuitState::Closed);
+        assert_eq!(mock.call_count(MockMethod::UploadChunk), 5);
+    }
+
+    #[tokio::test]
+    async fn failed_probe_reopens() {
+        let (cb, mock) = breaker(Duration::ZERO);
+
+        for _ in 0..3 {
+            assert!(cb.upload_chunk("k", b"x").await.is_err());
+        }
+        // Probe fails (4th failure): circuit goes back to Open
+        assert!(cb.upload_chunk("k", b"x").await.is_err());
+        assert_eq!(cb.breaker.lock().state, CircuitState::Open);
+        mock.clear_failure(MockMethod::UploadChunk);
+
+        cb.upload_chunk("k", b"x").await.unwrap();
+        cb.upload_chunk("k", b"x").await.unwrap();
+        assert_eq!(cb.state(), CircuitState::Closed);
+    }
+
+    #[tokio::test]
+    async fn success_resets_failure_count() {
+        let (cb, mock) = breaker(Duration::from_secs(3600));
+
+        assert!(cb.upload_chunk("k", b"x").await.is_err());
+        assert!(cb.upload_chunk("k", b"x").await.is_err());
+        mock.clear_failure(MockMethod::UploadChunk);
+        cb.upload_chunk("k", b"x").await.unwrap();
+        assert_eq!(cb.state(), CircuitState::Closed);
+    }
+}
@@ -0,0 +1,365 @@
+//! Re-encrypt stored chunks with a new key after key rotation.
+//!
+//! Each chunk is downloaded, decrypted with the old key, encrypted with the
+//! new key under a fresh nonce and written back to the same storage key on
+//! every provider holding a copy. The new nonce is journaled in the manifest
+//! (`chunk_rekeys`) before any copy is overwritten, so an interrupted run can
+//! be restarted: chunks already moved to the new key are skipped, and copies
+//! rewritten before the interruption are recognised by the journaled nonce.
+
+use std::collections::HashMap;
+
+use anyhow::{Context, Result, anyhow, bail};
+
+use enigma_core::crypto::{chunk_hmac_tag, decrypt_chunk, encrypt_chunk};
+use enigma_core::manifest::ManifestDb;
+use enigma_core::types::{ChunkHash, EncryptedChunk, KeyMaterial};
+
+use crate::provider::StorageProvider;
+
+/// Chunk hashes fetched from the manifest per query.
+const PAGE_SIZE: u32 = 256;
+
+/// Outcome of [`reencrypt_all_chunks`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ReencryptStats {
+    /// Chunks moved to the new key.
+    pub reencrypted: u64,
+    /// Chunk copies uploaded (one per replica).
+    pub copies_rewritten: u64,
+    /// Chunks left on the old key because a copy could not be read,
+    /// decrypted or written. Running again retries them.
+    pub failed: u64,
+}
+
+impl std::ops::AddAssign for ReencryptStats {
+    fn add_assign(&mut self, other: Self) {
+        self.reencrypted += other.reencrypted;
+        self.copies_rewritten += other.copies_rewritten;
+        self.failed += other.failed;
+    }
+}
+
+/// Re-encrypt every chunk still encrypted with `old_key` to `new_key`.
+///
+/// A chunk that fails is logged, counted in [`ReencryptStats::failed`] and
+/// left on the old key; the others are still processed.
+pub async fn reencrypt_all_chunks(
+    db: &ManifestDb,
+    providers: &HashMap<i64, Box<dyn StorageProvider>>,
+    old_key: &KeyMaterial,
+    new_key: &KeyMaterial,
+) -> Result<ReencryptStats> {
+    if old_key.id == new_key.id {
+        bail!("old and new key are the same ({})", new_key.id);
+    }
+
+    let mut stats = ReencryptStats::default();
+    let mut after = String::new();
+    loop {
+        let page = db.chunk_hashes_with_key(&old_key.id, &after, PAGE_SIZE)?;
+        let Some(last) = page.last() else {
+            break;
+        };
+        after = last.clone();
+
+        for hash in &page {
+            match reencrypt_chunk(db, providers, hash, old_key, new_key).await {
+                Ok(copies) => {
+                    stats.reencrypted += 1;
+                    stats.copies_rewritten += copies;
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to re-encrypt chunk {hash}: {e:#}");
+                    stats.failed += 1;
+                }
+            }
+        }
+    }
+
+    Ok(stats)
+}
+
+/// Move one chunk to `new_key`. Returns the number of copies uploaded.
+async fn reencrypt_chunk(
+    db: &ManifestDb,
+    providers: &HashMap<i64, Box<dyn StorageProvider>>,
+    hash_hex: &str,
+    old_key: &KeyMaterial,
+    new_key: &KeyMaterial,
+) -> Result<u64> {
+    let (nonce, _key_id, locations, _size_enc, _size_compressed) = db
+        .get_chunk_locations(hash_hex)?
+        .ok_or_else(|| anyhow!("chunk not found"))?;
+    let hash = ChunkHash(
+        hex::decode(hash_hex)?
+            .try_into()
+            .map_err(|_| anyhow!("invalid hash length"))?,
+    );
+    let old_nonce = to_nonce(nonce)?;
+
+    // Nonce journaled by an earlier, interrupted run with this same new key
+    let pending = match db.get_chunk_rekey(hash_hex)? {
+        Some((nonce, key_id)) if key_id == new_key.id => Some(to_nonce(nonce)?),
+        _ => None,
+    };
+
+    // Every copy must be readable before any is overwritten, so that no copy
+    // is left on a nonce the manifest no longer knows
+    let mut plaintext = None;
+    let mut rewritten = None;
+    let mut stale = Vec::new();
+    for (provider_id, storage_key) in &locations {
+        let provider = providers
+            .get(provider_id)
+            .ok_or_else(|| anyhow!("provider {provider_id} is not configured"))?;
+        let ciphertext = provider
+            .download_chunk(storage_key)
+            .await
+            .with_context(|| format!("download from provider {provider_id}"))?;
+
+        let mut encrypted = EncryptedChunk {
+            hash: hash.clone(),
+            nonce: old_nonce,
+            ciphertext,
+            key_id: old_key.id.clone(),
+        };
+        if let Ok(data) = decrypt_chunk(&encrypted, old_key) {
+            plaintext = Some(data);
+            stale.push((provider, storage_key));
+            continue;
+        }
+        if let Some(nonce) = pending {
+            encrypted.nonce = nonce;
+            if decrypt_chunk(&encrypted, new_key).is_ok() {
+                rewritten = Some((nonce, encrypted.ciphertext));
+                continue;
+            }
+        }
+        bail!("copy on provider {provider_id} decrypts with neither key");
+    }
+
+    // Copies already rewritten keep their ciphertext; the rest get the same
+    // bytes so every copy ends up identical
+    let (nonce, ciphertext) = match (rewritten, plaintext) {
+        (Some(rewritten), _) => rewritten,
+        (None, Some(plaintext)) => {
+            let encrypted = encrypt_chunk(&plaintext, &hash, new_key)?;
+            db.begin_chunk_rekey(hash_hex, &encrypted.nonce, &new_key.id)?;
+            (encrypted.nonce, encrypted.ciphertext)
+        }
+        (None, None) => bail!("chunk has no storage locations"),
+    };
+
+    for (provider, storage_key) in &stale {
+        provider
+            .upload_chunk(storage_key, &ciphertext)
+            .await
+            .with_context(|| format!("upload to provider {}", provider.name()))?;
+    }
+    // Copies share one storage key; the tag is re-signed with the new key
+    let tag = chunk_hmac_tag(new_key, &locations[0].1, &ciphertext);
+    db.finish_chunk_rekey(hash_hex, &nonce, &new_key.id, Some(&tag))?;
+
+    Ok(stale.len() as u64)
+}
+
+fn to_nonce(nonce: Vec<u8>) -> Result<[u8; 12]> {
+    nonce
+        .try_into()
+        .map_err(|_| anyhow!("invalid nonce length"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::local::LocalStorageProvider;
+    use enigma_core::crypto::verify_chunk_hmac;
+    use enigma_core::dedup::compute_hash;
+    use enigma_core::types::ProviderType;
+    use tempfile::TempDir;
+
+    fn key(id: &str, byte: u8) -> KeyMaterial {
+        KeyMaterial {
+            id: id.to_string(),
+            key: [byte; 32],
+        }
+    }
+
+    /// Two local providers holding a replica of every chunk.
+    fn setup(tmp: &TempDir) -> (ManifestDb, HashMap<i64, Box<dyn StorageProvider>>) {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let mut providers: HashMap<i64, Box<dyn StorageProvider>> = HashMap::new();
+        for name in ["a", "b"] {
+            let dir = tmp.path().join(name);
+            let pid = db
+                .insert_provider(name, ProviderType::Local, dir.to_str().unwrap(), None, 1)
+                .unwrap();
+            providers.insert(
+                pid,
+                Box::new(LocalStorageProvider::new(&dir, name).unwrap()),
+            );
+        }
+        (db, providers)
+    }
+
+    async fn store(
+        db: &ManifestDb,
+        providers: &HashMap<i64, Box<dyn StorageProvider>>,
+        data: &[u8],
+        key: &KeyMaterial,
+    ) -> String {
+        let hash = compute_hash(data);
+        let encrypted = encrypt_chunk(data, &hash, key).unwrap();
+        let storage_key = hash.storage_key();
+        let mut pids: Vec<i64> = providers.keys().copied().collect();
+        pids.sort();
+        db.insert_or_dedup_chunk(
+            &hash.to_hex(),
+            &encrypted.nonce,
+            &key.id,
+            pids[0],
+            &storage_key,
+            data.len() as u64,
+            encrypted.ciphertext.len() as u64,
+            None,
+        )
+        .unwrap();
+        let replicas: Vec<(i64, &str)> = pids.iter().map(|p| (*p, storage_key.as_str())).collect();
+        db.insert_chunk_replicas(&hash.to_hex(), &replicas).unwrap();
+        for provider in providers.values() {
+            provider
+                .upload_chunk(&storage_key, &encrypted.ciphertext)
+                .await
+                .unwrap();
+        }
+        hash.to_hex()
+    }
+
+    /// Decrypt every copy of a chunk with the key the manifest records,
+    /// checking its HMAC tag if it has one.
+    async fn read_all(
+        db: &ManifestDb,
+        providers: &HashMap<i64, Box<dyn StorageProvider>>,
+        hash_hex: &str,
+        key: &KeyMaterial,
+    ) -> Vec<Vec<u8>> {
+        let (nonce, key_id, locations, ..) = db.get_chunk_locations(hash_hex).unwrap().unwrap();
+        assert_eq!(key_id, key.id);
+        let tag = db.get_chunk_hmac_tag(hash_hex).unwrap();
+        let mut copies = Vec::new();
+        for (pid, storage_key) in locations {
+            let ciphertext = providers[&pid].download_chunk(&storage_key).await.unwrap();
+            if let Some(tag) = &tag {
+                verify_chunk_hmac(key, &storage_key, &ciphertext, tag).unwrap();
+            }
+            let encrypted = EncryptedChunk {
+                hash: ChunkHash(hex::decode(hash_hex).unwrap().try_into().unwrap()),
+                nonce: nonce.clone().try_into().unwrap(),
+                ciphertext,
+                key_id: key_id.clone(),
+            };
+            copies.push(decrypt_chunk(&encrypted, key).unwrap());
+        }
+        copies
+    }
+
+    #[tokio::test]
+    async fn reencrypts_every_copy_and_skips_done_chunks() {
+        let tmp = TempDir::new().unwrap();
+        let (db, providers) = setup(&tmp);
+        let old = key("old", 0x11);
+        let new = key("new", 0x22);
+        let first = store(&db, &providers, b"first chunk", &old).await;
+        let second = store(&db, &providers, b"second chunk", &old).await;
+
+        let stats = reencrypt_all_chunks(&db, &providers, &old, &new)
+            .await
+            .unwrap();
+        assert_eq!(
+            stats,
+            ReencryptStats {
+                reencrypted: 2,
+                copies_rewritten: 4,
+                failed: 0,
+            }
+        );
+        assert_eq!(
+            read_all(&db, &providers, &first, &new).await,
+            vec![b"first chunk".to_vec(); 2]
+        );
+        assert_eq!(
+            read_all(&db, &providers, &second, &new).await,
+            vec![b"second chunk".to_vec(); 2]
+        );
+        assert!(db.get_chunk_hmac_tag(&first).unwrap().is_some());
+
+        // Nothing left on the old key
+        let stats = reencrypt_all_chunks(&db, &providers, &old, &new)
+            .await
+            .unwrap();
+        assert_eq!(stats, ReencryptStats::default());
+    }
+
+    #[tokio::test]
+    async fn resumes_after_interrupted_rewrite() {
+        let tmp = TempDir::new().unwrap();
+        let (db, providers) = setup(&tmp);
+        let old = key("old", 0x11);
+        let new = key("new", 0x22);
+        let hash_hex = store(&db, &providers, b"interrupted", &old).await;
+
+        // Simulate a run that journaled the new nonce and rewrote only one
+        // replica before crashing
+        let hash = compute_hash(b"interrupted");
+        let encrypted = encrypt_chunk(b"interrupted", &hash, &new).unwrap();
+        db.begin_chunk_rekey(&hash_hex, &encrypted.nonce, &new.id)
+            .unwrap();
+        let (_, _, locations, ..) = db.get_chunk_locations(&hash_hex).unwrap().unwrap();
+        let (pid, storage_key) = &locations[0];
+        providers[pid]
+            .upload_chunk(storage_key, &encrypted.ciphertext)
+            .await
+            .unwrap();
+
+        let stats = reencrypt_all_chunks(&db, &providers, &old, &new)
+            .await
+            .unwrap();
+        assert_eq!(
+            stats,
+            ReencryptStats {
+                reencrypted: 1,
+                copies_rewritten: 1,
+                failed: 0,
+            }
+        );
+        assert_eq!(
+            read_all(&db, &providers, &hash_hex, &new).await,
+            vec![b"interrupted".to_vec(); 2]
+        );
+        assert_eq!(db.get_chunk_rekey(&hash_hex).unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn unreadable_copy_leaves_chunk_on_old_key() {
+        let tmp = TempDir::new().unwrap();
+        let (db, providers) = setup(&tmp);
+        let old = key("old", 0x11);
+        let new = key("new", 0x22);
+        let hash_hex = store(&db, &providers, b"missing replica", &old).await;
+
+        let (_, _, locations, ..) = db.get_chunk_locations(&hash_hex).unwrap().unwrap();
+        let (pid, storage_key) = &locations[1];
+        providers[pid].delete_chunk(storage_key).await.unwrap();
+
+        let stats = reencrypt_all_chunks(&db, &providers, &old, &new)
+            .await
+            .unwrap();
+        assert_eq!(stats.failed, 1);
+        assert_eq!(stats.reencrypted, 0);
+        assert_eq!(
+            db.chunk_hashes_with_key("old", "", 10).unwrap(),
+            vec![hash_hex]
+        );
+    }
+}
@@ -1,10 +1,18 @@
 #[cfg(feature = "s3")]
 mod inner {
+    use std::future::Future;
+
     use async_trait::async_trait;
     use aws_sdk_s3::Client;
+    use aws_sdk_s3::config::http::HttpResponse;
+    use aws_sdk_s3::error::{ProvideErrorMetadata, SdkError};
     use aws_sdk_s3::primitives::ByteStream;
+    use aws_sdk_s3::types::{GlacierJobParameters, RestoreRequest, StorageClass, Tier};
 
-    use crate::provider::StorageProvider;
+    use crate::credentials::RotatingClient;
+    use crate::provider::{
+        CredentialProvider, Credentials, ProviderUsage, StorageProvider, TimeoutConfig,
+    };
 
     /// AWS S3 and S3-compatible storage provider.
     ///
@@ -12,8 +20,11 @@ mod inner {
     /// and any other service implementing the S3 API.
     pub struct S3StorageProvider {
         client: Client,
+        /// Replaces `client` when credentials rotate.
+        rotating: Option<RotatingClient<Client>>,
         bucket: String,
         name: String,
+        storage_class: Option<String>,
     }
 
     /// Options for creating an S3 provider.
@@ -30,11 +41,18 @@ mod inner {
         pub access_key: Option<&'a str>,
         /// Explicit secret key. If None, uses env/profile credentials.
         pub secret_key: Option<&'a str>,
+        /// Connect and request timeouts. The SDK has no idle timeout.
+        pub timeouts: TimeoutConfig,
     }
 
     impl S3StorageProvider {
         /// Create for standard AWS S3.
-        pub async fn new(bucket: &str, region: Option<&str>, name: &str) -> anyhow::Result<Self> {
+        pub async fn new(
+            bucket: &str,
+            region: Option<&str>,
+            name: &str,
+            timeouts: TimeoutConfig,
+        ) -> anyhow::Result<Self> {
             Self::with_options(S3Options {
                 bucket,
                 region,
@@ -43,6 +61,7 @@ mod inner {
                 path_style: false,
                 access_key: None,
                 secret_key: None,
+                timeouts,
             })
             .await
         }
@@ -55,6 +74,7 @@ mod inner {
             name: &str,
             access_key: Option<&str>,
             secret_key: Option<&str>,
+            timeouts: TimeoutConfig,
         ) -> anyhow::Result<Self> {
             Self::with_options(S3Options {
                 bucket,
@@ -64,6 +84,7 @@ mod inner {
                 path_style: true,
                 access_key,
                 secret_key,
+                timeouts,
             })
             .await
         }
@@ -83,6 +104,23 @@ mod inner {
                 config_loader = config_loader.credentials_provider(creds);
             }
 
+            if opts.timeouts.connect.is_some() || opts.timeouts.request.is_some() {
+                let mut timeout_config = aws_config::timeout::TimeoutConfig::builder();
+                if let Some(d) = opts.timeouts.connect {
+                    timeout_config = timeout_config.connect_timeout(d);
+                }
+                if let Some(d) = opts.timeouts.request {
+                    timeout_config = timeout_config.operation_timeout(d);
+                }
+                config_loader = config_loader.timeout_config(timeout_config.build());
+            }
+            if opts.timeouts.idle.is_some() {
+                tracing::warn!(
+                    provider = opts.name,
+                    "S3 provider does not support idle_timeout_ms, ignoring"
+                );
+            }
+
             let sdk_config = config_loader.load().await;
 
             let mut s3_config_builder = aws_sdk_s3::config::Builder::from(&sdk_config);
@@ -99,54 +137,153 @@ mod inner {
 
             Ok(Self {
                 client,
+                rotating: None,
                 bucket: opts.bucket.to_string(),
                 name: opts.name.to_string(),
+                storage_class: None,
             })
         }
+
+        /// Write chunks with `x-amz-storage-class: <storage_class>`, e.g.
+        /// `INTELLIGENT_TIERING` or `GLACIER`. `None` uses the bucket default.
+        pub fn with_storage_class(mut self, storage_class: Option<&str>) -> Self {
+            self.storage_class = storage_class.map(str::to_string);
+            self
+        }
+
+        /// Sign requests with `credentials` instead of the static ones.
+        /// They are reloaded, and the request retried once, when S3
+        /// rejects them with 401/403.
+        pub fn with_credential_provider(
+            mut self,
+            credentials: Box<dyn CredentialProvider>,
+        ) -> Self {
+            let config = self.client.config().clone();
+            self.rotating = Some(RotatingClient::new(credentials, move |credentials| {
+                Ok(client_with_credentials(&config, credentials))
+            }));
+            self
+        }
+
+        async fn current_client(&self) -> anyhow::Result<Client> {
+            match &self.rotating {
+                Some(rotating) => rotating.client().await,
+                None => Ok(self.client.clone()),
+            }
+        }
+
+        /// Send a request built by `op`. With rotating credentials, a
+        /// request rejected with 401/403 is retried once after reloading them.
+        async fn send<T, E, F, Fut>(&self, op: F) -> Result<T, SdkError<E, HttpResponse>>
+        where
+            F: Fn(Client) -> Fut,
+            Fut: Future<Output = Result<T, SdkError<E, HttpResponse>>>,
+        {
+            let client = self
+                .current_client()
+                .await
+                .map_err(SdkError::construction_failure)?;
+            let result = op(client).await;
+            let Some(rotating) = &self.rotating else {
+                return result;
+            };
+            match result {
+                Err(err) if is_auth_error(&err) => {
+                    tracing::info!(provider = %self.name, "S3 rejected the credentials, reloading them");
+                    if let Err(e) = rotating.refresh().await {
+                        tracing::warn!(provider = %self.name, "Failed to reload credentials: {e}");
+                        return Err(err);
+                    }
+                    let client = rotating
+                        .client()
+                        .await
+                        .map_err(SdkError::construction_failure)?;
+                    op(client).await
+                }
+                result => result,
+            }
+        }
     }
 
+    fn client_with_credentials(config: &aws_sdk_s3::Config, credentials: &Credentials) -> Client {
+        let credentials = aws_sdk_s3::config::Credentials::new(
+            &credentials.access_key,
+            &credentials.secret_key,
+            credentials.session_token.clone(),
+            None,
+            "enigma-rotating",
+        );
+        Client::from_conf(
+            config
+                .to_builder()
+                .credentials_provider(credentials)
+                .build(),
+        )
+    }
+
+    fn is_auth_error<E>(err: &SdkError<E, HttpResponse>) -> bool {
+        err.raw_response()
+            .is_some_and(|response| matches!(response.status().as_u16(), 401 | 403))
+    }
+
+    /// Days a restored copy of an archived chunk stays readable.
+    const RESTORE_DAYS: i32 = 1;
+
     #[async_trait]
     impl StorageProvider for S3StorageProvider {
         async fn upload_chunk(&self, key: &str, data: &[u8]) -> anyhow::Result<()> {
-            self.client
-                .put_object()
-                .bucket(&self.bucket)
-                .key(key)
-                .body(ByteStream::from(data.to_vec()))
-                .send()
-                .await?;
+            self.send(|client| async move {
+                client
+                    .put_object()
+                    .bucket(&self.bucket)
+                    .key(key)
+                    .set_storage_class(self.storage_class.as_deref().map(StorageClass::from))
+                    .body(ByteStream::from(data.to_vec()))
+                    .send()
+                    .await
+            })
+            .await?;
             Ok(())
         }
 
         async fn download_chunk(&self, key: &str) -> anyhow::Result<Vec<u8>> {
             let resp = self
-                .client
-                .get_object()
-                .bucket(&self.bucket)
-                .key(key)
-                .send()
+                .send(|client| async move {
+                    client
+                        .get_object()
+                        .bucket(&self.bucket)
+                        .key(key)
+                        .send()
+                        .await
+                })
                 .await?;
             let data = resp.body.collect().await?;
             Ok(data.to_vec())
         }
 
         async fn delete_chunk(&self, key: &str) -> anyhow::Result<()> {
-            self.client
-                .delete_object()
-                .bucket(&self.bucket)
-                .key(key)
-                .send()
-                .await?;
+            self.send(|client| async move {
+                client
+                    .delete_object()
+                    .bucket(&self.bucket)
+                    .key(key)
+                    .send()
+                    .await
+            })
+            .await?;
             Ok(())
         }
 
         async fn chunk_exists(&self, key: &str) -> anyhow::Result<bool> {
             match self
-                .client
-                .head_object()
-                .bucket(&self.bucket)
-                .key(key)
-                .send()
+                .send(|client| async move {
+                    client
+                        .head_object()
+                        .bucket(&self.bucket)
+                        .key(key)
+                        .send()
+                        .await
+                })
                 .await
             {
                 Ok(_) => Ok(true),
@@ -162,18 +299,240 @@ mod inner {
         }
 
         async fn test_connection(&self) -> anyhow::Result<()> {
-            self.client
-                .head_bucket()
-                .bucket(&self.bucket)
-                .send()
-                .await?;
+            self.send(
+                |client| async move { client.head_bucket().bucket(&self.bucket).send().await },
+            )
+            .await?;
             Ok(())
         }
 
+        async fn get_usage(&self) -> anyhow::Result<ProviderUsage> {
+            // S3 has no bucket size call: sum the listing
+            let mut usage = ProviderUsage::default();
+            let mut pages = self
+                .current_client()
+                .await?
+                .list_objects_v2()
+                .bucket(&self.bucket)
+                .into_paginator()
+                .send();
+            while let Some(page) = pages.next().await {
+                for object in page?.contents() {
+                    usage.used_bytes += object.size().unwrap_or(0).max(0) as u64;
+                    usage.object_count += 1;
+                }
+            }
+            Ok(usage)
+        }
+
+        /// Request a temporary restored copy. Glacier restores take minutes
+        /// to hours; one already under way counts as started.
+        async fn restore_from_archive(&self, key: &str) -> anyhow::Result<()> {
+            let request = RestoreRequest::builder()
+                .days(RESTORE_DAYS)
+                .glacier_job_parameters(
+                    GlacierJobParameters::builder()
+                        .tier(Tier::Standard)
+                        .build()?,
+                )
+                .build();
+            match self
+                .send(|client| {
+                    let request = request.clone();
+                    async move {
+                        client
+                            .restore_object()
+                            .bucket(&self.bucket)
+                            .key(key)
+                            .restore_request(request)
+                            .send()
+                            .await
+                    }
+                })
+                .await
+            {
+                Ok(_) => Ok(()),
+                Err(err) => {
+                    let service_err = err.into_service_error();
+                    if service_err.code() == Some("RestoreAlreadyInProgress") {
+                        Ok(())
+                    } else {
+                        Err(service_err.into())
+                    }
+                }
+            }
+        }
+
+        fn storage_class(&self) -> Option<&str> {
+            self.storage_class.as_deref()
+        }
+
         fn name(&self) -> &str {
             &self.name
         }
     }
+
+    #[cfg(test)]
+    mod tests {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        use super::*;
+        use wiremock::matchers::{header, header_regex, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        async fn provider(server: &MockServer) -> S3StorageProvider {
+            S3StorageProvider::s3_compatible(
+                "bucket",
+                &server.uri(),
+                None,
+                "s3-test",
+                Some("access"),
+                Some("secret"),
+                TimeoutConfig::default(),
+            )
+            .await
+            .unwrap()
+        }
+
+        #[tokio::test]
+        async fn upload_sends_storage_class() {
+            let server = MockServer::start().await;
+            Mock::given(method("PUT"))
+                .and(path("/bucket/chunk-1"))
+                .and(header("x-amz-storage-class", "INTELLIGENT_TIERING"))
+                .respond_with(ResponseTemplate::new(200))
+                .expect(1)
+                .mount(&server)
+                .await;
+
+            let provider = provider(&server)
+                .await
+                .with_storage_class(Some("INTELLIGENT_TIERING"));
+            assert_eq!(provider.storage_class(), Some("INTELLIGENT_TIERING"));
+            provider.upload_chunk("chunk-1", b"data").await.unwrap();
+        }
+
+        #[tokio::test]
+        async fn upload_without_storage_class_uses_bucket_default() {
+            let server = MockServer::start().await;
+            Mock::given(method("PUT"))
+                .respond_with(ResponseTemplate::new(200))
+                .mount(&server)
+                .await;
+
+            provider(&server)
+                .await
+                .upload_chunk("chunk-1", b"data")
+                .await
+                .unwrap();
+            let requests = server.received_requests().await.unwrap();
+            assert_eq!(requests.len(), 1);
+            assert!(!requests[0].headers.contains_key("x-amz-storage-class"));
+        }
+
+        /// Hands out "expired" credentials on the first call and "rotated"
+        /// ones from the second on.
+        #[derive(Default)]
+        struct RotatingOnSecondCall {
+            calls: AtomicU32,
+            refreshes: Arc<AtomicU32>,
+        }
+
+        #[async_trait]
+        impl CredentialProvider for RotatingOnSecondCall {
+            async fn get_credentials(&self) -> anyhow::Result<Credentials> {
+                let access_key = match self.calls.fetch_add(1, Ordering::SeqCst) {
+                    0 => "expired",
+                    _ => "rotated",
+                };
+                Ok(Credentials {
+                    access_key: access_key.to_string(),
+                    secret_key: "secret".to_string(),
+                    session_token: Some("token".to_string()),
+                })
+            }
+
+            async fn refresh_credentials(&mut self) -> anyhow::Result<()> {
+                self.refreshes.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }
+        }
+
+        #[tokio::test]
+        async fn upload_succeeds_after_credentials_rotate() {
+            let server = MockServer::start().await;
+            Mock::given(method("PUT"))
+                .and(header_regex("authorization", "Credential=rotated/"))
+                .and(header("x-amz-security-token", "token"))
+                .respond_with(ResponseTemplate::new(200))
+                .expect(1)
+                .mount(&server)
+                .await;
+            Mock::given(method("PUT"))
+                .respond_with(ResponseTemplate::new(403).set_body_string(
+                    "<Error><Code>ExpiredToken</Code>\
+                     <Message>The provided token has expired.</Message></Error>",
+                ))
+                .mount(&server)
+                .await;
+
+            let credentials = RotatingOnSecondCall::default();
+            let refreshes = credentials.refreshes.clone();
+            let provider = provider(&server)
+                .await
+                .with_credential_provider(Box::new(credentials));
+            provider.upload_chunk("chunk-1", b"data").await.unwrap();
+
+            assert_eq!(refreshes.load(Ordering::SeqCst), 1);
+            assert_eq!(server.received_requests().await.unwrap().len(), 2);
+        }
+
+        #[tokio::test]
+        async fn static_credentials_are_not_retried() {
+            let server = MockServer::start().await;
+            Mock::given(method("PUT"))
+                .respond_with(ResponseTemplate::new(403))
+                .mount(&server)
+                .await;
+
+            provider(&server)
+                .await
+                .upload_chunk("chunk-1", b"data")
+                .await
+                .unwrap_err();
+            assert_eq!(server.received_requests().await.unwrap().len(), 1);
+        }
+
+        #[tokio::test]
+        async fn restore_already_in_progress_counts_as_started() {
+            let server = MockServer::start().await;
+            Mock::given(method("POST"))
+                .and(path("/bucket/chunk-1"))
+                .respond_with(ResponseTemplate::new(202))
+                .up_to_n_times(1)
+                .mount(&server)
+                .await;
+            Mock::given(method("POST"))
+                .and(path("/bucket/chunk-1"))
+                .respond_with(ResponseTemplate::new(409).set_body_string(
+                    "<Error><Code>RestoreAlreadyInProgress</Code>\
+                     <Message>Object restore is already in progress</Message></Error>",
+                ))
+                .mount(&server)
+                .await;
+
+            let provider = provider(&server).await.with_storage_class(Some("GLACIER"));
+            provider.restore_from_archive("chunk-1").await.unwrap();
+            provider.restore_from_archive("chunk-1").await.unwrap();
+
+            let requests = server.received_requests().await.unwrap();
+            assert_eq!(requests.len(), 2);
+            assert!(requests[0].url.query().unwrap().contains("restore"));
+            let body = String::from_utf8_lossy(&requests[0].body);
+            assert!(body.contains("<Days>1</Days>"));
+        }
+    }
 }
 
 #[cfg(feature = "s3")]
@@ -106,6 +106,7 @@ async fn bench_all_providers() {
             &key,
             "enigma-chunks",
             "azure-1-westeurope",
+            Default::default(),
         )
         .expect("Azure 1 init failed");
         names.push("Azure (westeurope)".to_string());
@@ -122,6 +123,7 @@ async fn bench_all_providers() {
             &key,
             "enigma-chunks",
             "azure-2-northeurope",
+            Default::default(),
         )
         .expect("Azure 2 init failed");
         names.push("Azure (northeurope)".to_string());
@@ -131,8 +133,12 @@ async fn bench_all_providers() {
     // GCS 1
     if let Ok(bucket) = std::env::var("GCS_BUCKET_1") {
         if !bucket.is_empty() {
-            if let Ok(p) =
-                enigma_storage::gcs::GcsStorageProvider::new(&bucket, "gcs-1-west1").await
+            if let Ok(p) = enigma_storage::gcs::GcsStorageProvider::new(
+                &bucket,
+                "gcs-1-west1",
+                Default::default(),
+            )
+            .await
             {
                 names.push("GCS (europe-west1)".to_string());
                 providers.push(Box::new(p));
@@ -143,8 +149,12 @@ async fn bench_all_providers() {
     // GCS 2
     if let Ok(bucket) = std::env::var("GCS_BUCKET_2") {
         if !bucket.is_empty() {
-            if let Ok(p) =
-                enigma_storage::gcs::GcsStorageProvider::new(&bucket, "gcs-2-west4").await
+            if let Ok(p) = enigma_storage::gcs::GcsStorageProvider::new(
+                &bucket,
+                "gcs-2-west4",
+                Default::default(),
+            )
+            .await
             {
                 names.push("GCS (europe-west4)".to_string());
                 providers.push(Box::new(p));
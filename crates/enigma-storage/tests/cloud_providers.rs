@@ -17,7 +17,14 @@ mod azure_tests {
     fn get_azure_provider() -> Option<AzureStorageProvider> {
         let account = std::env::var("AZURE_STORAGE_ACCOUNT").ok()?;
         let key = std::env::var("AZURE_STORAGE_KEY").ok()?;
-        AzureStorageProvider::new(&account, &key, "enigma-chunks", "azure-test").ok()
+        AzureStorageProvider::new(
+            &account,
+            &key,
+            "enigma-chunks",
+            "azure-test",
+            Default::default(),
+        )
+        .ok()
     }
 
     #[tokio::test]
@@ -79,7 +86,9 @@ mod gcs_tests {
         if bucket.is_empty() {
             return None;
         }
-        GcsStorageProvider::new(&bucket, "gcs-test").await.ok()
+        GcsStorageProvider::new(&bucket, "gcs-test", Default::default())
+            .await
+            .ok()
     }
 
     #[tokio::test]
@@ -145,6 +145,7 @@ async fn multicloud_azure_gcs_e2e() {
         &azure_key,
         "enigma-chunks",
         "azure-e2e",
+        Default::default(),
     )
     .expect("Azure init failed");
     azure
@@ -152,9 +153,10 @@ async fn multicloud_azure_gcs_e2e() {
         .await
         .expect("Azure connection failed");
 
-    let gcs = enigma_storage::gcs::GcsStorageProvider::new(&gcs_bucket, "gcs-e2e")
-        .await
-        .expect("GCS init failed");
+    let gcs =
+        enigma_storage::gcs::GcsStorageProvider::new(&gcs_bucket, "gcs-e2e", Default::default())
+            .await
+            .expect("GCS init failed");
     gcs.test_connection().await.expect("GCS connection failed");
 
     println!("OK: Both providers connected (Azure + GCS)");
@@ -3,11 +3,21 @@ import { get } from 'svelte/store';
 
 const BASE = '/api';
 
+// CSRF token of the current session, fetched before the first
+// state-changing call
+let csrfToken = '';
+
 async function request(path, options = {}) {
   const t = get(token);
+  // Login and the other /auth routes are exempt
+  const unsafe = (options.method || 'GET') !== 'GET' && !path.startsWith('/auth/');
+  if (t && unsafe && !csrfToken) {
+    csrfToken = (await request('/csrf-token')).token;
+  }
   const headers = {
     'Content-Type': 'application/json',
     ...(t ? { Authorization: `Bearer ${t}` } : {}),
+    ...(t && unsafe ? { 'X-CSRF-Token': csrfToken } : {}),
     ...options.headers,
   };
 
@@ -31,6 +41,7 @@ export async function login(username, password) {
     body: JSON.stringify({ username, password }),
   });
   token.set(data.token);
+  csrfToken = '';
   return data;
 }
 
@@ -46,6 +57,19 @@ export async function getChunkStats() {
   return request('/storage/chunks/stats');
 }
 
+export async function getChunkHistogram(backupId) {
+  const query = backupId ? `?backup_id=${encodeURIComponent(backupId)}` : '';
+  return request(`/storage/chunks/histogram${query}`);
+}
+
+export async function getDedupStats() {
+  return request('/storage/dedup');
+}
+
+export async function getCrossNamespaceDedup(limit = 1000, offset = 0) {
+  return request(`/storage/dedup/cross-namespace?limit=${limit}&offset=${offset}`);
+}
+
 export async function getBackups() {
   return request('/storage/backups');
 }
@@ -61,3 +85,13 @@ export async function getObjects(namespace) {
 export async function getCluster() {
   return request('/cluster');
 }
+
+// Live backup progress; call the returned function to stop listening.
+// EventSource cannot send the Authorization header, so the stream is
+// opened with a single-use ticket rather than the session token.
+export async function subscribeBackupEvents(onEvent) {
+  const { ticket } = await request('/stream-ticket', { method: 'POST' });
+  const source = new EventSource(`${BASE}/events/backup?ticket=${encodeURIComponent(ticket)}`);
+  source.addEventListener('backup_progress', (e) => onEvent(JSON.parse(e.data)));
+  return () => source.close();
+}
@@ -1,6 +1,12 @@
 import { writable } from 'svelte/store';
 
-export const token = writable(localStorage.getItem('enigma_token') || '');
+// Single sign-on lands on the UI with the session token in the URL fragment
+const ssoToken = new URLSearchParams(location.hash.slice(1)).get('token');
+if (ssoToken) {
+  history.replaceState(null, '', location.pathname + location.search);
+}
+
+export const token = writable(ssoToken || localStorage.getItem('enigma_token') || '');
 export const currentPage = writable('dashboard');
 export const sidebarCollapsed = writable(localStorage.getItem('enigma_sidebar') === 'collapsed');
 
@@ -0,0 +1,57 @@
+//! Nightly purge of auth audit entries older than `audit_retention_days`.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Days, Utc};
+use enigma_auth::AuthStore;
+
+/// Time from `now` to the next midnight, UTC.
+fn until_next_midnight(now: DateTime<Utc>) -> Duration {
+    let midnight = (now.date_naive() + Days::new(1))
+        .and_hms_opt(0, 0, 0)
+        .expect("midnight is a valid time")
+        .and_utc();
+    (midnight - now).to_std().unwrap_or_default()
+}
+
+/// Purge the audit log every night at midnight (UTC), keeping the last
+/// `retention_days` days. 0 keeps everything.
+pub(crate) fn spawn(store: Arc<dyn AuthStore>, retention_days: u32) {
+    if retention_days == 0 {
+        return;
+    }
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(until_next_midnight(Utc::now())).await;
+            match store.purge_old_audit_entries(retention_days).await {
+                Ok(deleted) => {
+                    tracing::info!(deleted, retention_days, "Purged old auth audit entries")
+                }
+                Err(e) => tracing::warn!("Failed to purge the auth audit log: {e}"),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn sleeps_until_the_next_midnight() {
+        let at = |h, m, s| Utc.with_ymd_and_hms(2026, 2, 28, h, m, s).unwrap();
+        assert_eq!(until_next_midnight(at(23, 59, 30)), Duration::from_secs(30));
+        assert_eq!(
+            until_next_midnight(at(12, 0, 0)),
+            Duration::from_secs(12 * 3600)
+        );
+        // At midnight exactly, the next run is a day away
+        assert_eq!(
+            until_next_midnight(at(0, 0, 0)),
+            Duration::from_secs(24 * 3600)
+        );
+    }
+}
@@ -1,29 +1,41 @@
+use std::net::SocketAddr;
 use std::sync::Arc;
 
-use axum::extract::State;
-use axum::http::StatusCode;
-use axum::response::Response;
+use axum::extract::{ConnectInfo, FromRequestParts, Query, State};
+use axum::http::{HeaderValue, StatusCode, header};
+use axum::response::{IntoResponse, Redirect, Response};
 use axum::{Json, middleware::Next};
+use enigma_auth::{AuthError, AuthUser, LockoutInfo};
+use enigma_core::notify::NotificationConfig;
 use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
 use serde::{Deserialize, Serialize};
 use subtle::ConstantTimeEq;
+use utoipa::{IntoParams, ToSchema};
 
-use crate::state::AppState;
+use crate::correlation::CorrelationId;
+use crate::state::{AppState, OidcConfig, PasswordResetConfig};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String,
     pub exp: usize,
     pub iat: usize,
+    /// Admin user ID on tokens from `POST /api/admin/impersonate`, whose
+    /// `sub` is the impersonated user's ID.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub impersonated_by: Option<String>,
+    /// Session ID, unique per issued token; CSRF tokens are bound to it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct LoginRequest {
     pub username: String,
     pub password: String,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct LoginResponse {
     pub token: String,
     pub expires_in: u64,
@@ -35,6 +47,8 @@ pub fn create_token(username: &str, secret: &str) -> Result<String, jsonwebtoken
         sub: username.to_string(),
         exp: now + 86400,
         iat: now,
+        impersonated_by: None,
+        jti: Some(uuid::Uuid::now_v7().to_string()),
     };
     encode(
         &Header::default(),
@@ -52,26 +66,97 @@ pub fn verify_token(token: &str, secret: &str) -> Result<Claims, jsonwebtoken::e
     Ok(data.claims)
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/auth/login",
+    tag = "auth",
+    request_body = LoginRequest,
+    security(()),
+    responses(
+        (status = 200, description = "JWT issued", body = LoginResponse),
+        (status = 401, description = "Invalid credentials"),
+        (status = 429, description = "Account locked after repeated failed logins"),
+    )
+)]
 pub async fn login(
     State(state): State<Arc<AppState>>,
+    correlation_id: CorrelationId,
     Json(req): Json<LoginRequest>,
-) -> Result<Json<LoginResponse>, StatusCode> {
-    let user_match: bool = req
-        .username
-        .as_bytes()
-        .ct_eq(state.admin_user.as_bytes())
-        .into();
-    let pass_match: bool = req
-        .password
-        .as_bytes()
-        .ct_eq(state.admin_pass.as_bytes())
-        .into();
-    if !user_match || !pass_match {
-        return Err(StatusCode::UNAUTHORIZED);
+) -> Result<Json<LoginResponse>, Response> {
+    let user = match state.auth_store.get_user_by_username(&req.username).await {
+        Ok(user) => Some(user),
+        Err(AuthError::NotFound(_)) => None,
+        Err(e) => return Err(e.into_response()),
+    };
+
+    // Refuse a locked account before looking at the password
+    if let Some(user) = &user {
+        let lock = state
+            .auth_store
+            .lockout_status(&user.id)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        if let Some(lock) = lock {
+            return Err(locked(&lock));
+        }
     }
 
+    // The user's stored password, or the configured admin credentials
+    let stored_match = match &user {
+        Some(user) if user.is_active => {
+            let hash = state
+                .auth_store
+                .get_password_hash(&user.id)
+                .await
+                .map_err(IntoResponse::into_response)?;
+            // Hashes of accounts without a password do not parse
+            enigma_auth::verify_password(&req.password, &hash).unwrap_or(false)
+        }
+        _ => false,
+    };
+    let admin_match: bool = (req.username.as_bytes().ct_eq(state.admin_user.as_bytes())
+        & req.password.as_bytes().ct_eq(state.admin_pass.as_bytes()))
+    .into();
+    if !(stored_match || admin_match) {
+        if let Some(user) = &user {
+            let lock = state
+                .auth_store
+                .record_failed_login(&user.id)
+                .await
+                .map_err(IntoResponse::into_response)?;
+            if let Some(lock) = lock {
+                tracing::warn!(
+                    username = %user.username,
+                    until = %lock.locked_until,
+                    "account locked after repeated failed logins"
+                );
+                return Err(locked(&lock));
+            }
+        }
+        return Err(StatusCode::UNAUTHORIZED.into_response());
+    }
+
+    if let Some(user) = &user {
+        state
+            .auth_store
+            .clear_failed_logins(&user.id)
+            .await
+            .map_err(IntoResponse::into_response)?;
+    }
+    state
+        .auth_store
+        .log_audit(
+            user.as_ref().map(|u| u.id.as_str()),
+            "user.login",
+            Some(&req.username),
+            None,
+            &correlation_id.0,
+        )
+        .await
+        .map_err(IntoResponse::into_response)?;
+
     let token = create_token(&req.username, &state.jwt_secret)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())?;
 
     Ok(Json(LoginResponse {
         token,
@@ -79,11 +164,284 @@ pub async fn login(
     }))
 }
 
+#[derive(Deserialize, IntoParams)]
+pub struct OidcCallbackQuery {
+    /// Authorization code; absent when the provider reports an error.
+    pub code: Option<String>,
+    pub state: String,
+    /// Error code from the provider, e.g. `access_denied`.
+    pub error: Option<String>,
+}
+
+fn oidc_config(state: &AppState) -> Result<&OidcConfig, (StatusCode, &'static str)> {
+    state
+        .oidc
+        .as_ref()
+        .ok_or((StatusCode::NOT_FOUND, "single sign-on not configured"))
+}
+
+/// GET /api/auth/oidc/login
+///
+/// Start single sign-on: redirect the browser to the identity provider.
+#[utoipa::path(
+    get,
+    path = "/api/auth/oidc/login",
+    tag = "auth",
+    security(()),
+    responses(
+        (status = 303, description = "Redirect to the identity provider"),
+        (status = 404, description = "Single sign-on not configured"),
+    )
+)]
+pub async fn oidc_login(State(state): State<Arc<AppState>>) -> Result<Redirect, Response> {
+    let oidc = oidc_config(&state).map_err(IntoResponse::into_response)?;
+    let url = oidc
+        .provider
+        .authorize(state.auth_store.as_ref())
+        .await
+        .map_err(IntoResponse::into_response)?;
+    Ok(Redirect::to(&url))
+}
+
+/// GET /api/auth/oidc/callback
+///
+/// Finish single sign-on and redirect to the UI with a session token in
+/// the URL fragment. The user is created on first login.
+#[utoipa::path(
+    get,
+    path = "/api/auth/oidc/callback",
+    tag = "auth",
+    params(OidcCallbackQuery),
+    security(()),
+    responses(
+        (status = 303, description = "Redirect to the UI with `#token=<jwt>`"),
+        (status = 401, description = "Login refused, expired or not started here"),
+        (status = 403, description = "Account disabled"),
+        (status = 404, description = "Single sign-on not configured"),
+        (status = 409, description = "A local account already uses this name"),
+    )
+)]
+pub async fn oidc_callback(
+    State(state): State<Arc<AppState>>,
+    correlation_id: CorrelationId,
+    Query(query): Query<OidcCallbackQuery>,
+) -> Result<Redirect, Response> {
+    let oidc = oidc_config(&state).map_err(IntoResponse::into_response)?;
+    let code = match (query.code, query.error) {
+        (Some(code), None) => code,
+        (_, error) => {
+            tracing::warn!(
+                error = error.as_deref().unwrap_or("no code"),
+                "identity provider refused single sign-on"
+            );
+            return Err(StatusCode::UNAUTHORIZED.into_response());
+        }
+    };
+
+    let login = oidc
+        .provider
+        .complete(state.auth_store.as_ref(), &code, &query.state)
+        .await
+        .map_err(IntoResponse::into_response)?;
+    if !login.user.is_active {
+        return Err(AuthError::Forbidden("account disabled".into()).into_response());
+    }
+    state
+        .auth_store
+        .log_audit(
+            Some(&login.user.id),
+            "user.oidc_login",
+            None,
+            None,
+            &correlation_id.0,
+        )
+        .await
+        .map_err(IntoResponse::into_response)?;
+    tracing::info!(
+        username = %login.user.username,
+        name = login.name.as_deref().unwrap_or(""),
+        "single sign-on login"
+    );
+
+    let token = create_token(&login.user.username, &state.jwt_secret)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())?;
+    Ok(Redirect::to(&format!(
+        "{}#token={token}",
+        oidc.post_login_redirect
+    )))
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ForgotPasswordRequest {
+    pub email: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ResetPasswordRequest {
+    /// Token from the reset link.
+    pub token: String,
+    pub new_password: String,
+}
+
+/// POST /api/auth/forgot-password
+///
+/// Mail a password reset link to the account with this email address.
+/// The answer is the same whether or not such an account exists.
+#[utoipa::path(
+    post,
+    path = "/api/auth/forgot-password",
+    tag = "auth",
+    request_body = ForgotPasswordRequest,
+    security(()),
+    responses(
+        (status = 202, description = "Reset link mailed if the account exists"),
+        (status = 404, description = "Password reset not configured"),
+    )
+)]
+pub async fn forgot_password(
+    State(state): State<Arc<AppState>>,
+    correlation_id: CorrelationId,
+    Json(req): Json<ForgotPasswordRequest>,
+) -> Result<StatusCode, Response> {
+    let Some((reset, smtp)) = state
+        .password_reset
+        .as_ref()
+        .and_then(|reset| Some((reset, reset.smtp.as_ref()?)))
+    else {
+        return Err((StatusCode::NOT_FOUND, "password reset not configured").into_response());
+    };
+
+    // Everything else happens in the background: looking the address up
+    // or waiting on SMTP would tell known addresses apart by response time
+    let (reset, smtp) = (reset.clone(), smtp.clone());
+    tokio::spawn(async move {
+        let email = req.email.trim();
+        if let Err(e) = mail_reset_link(&state, &reset, &smtp, email, &correlation_id.0).await {
+            tracing::warn!("Failed to mail password reset link: {e}");
+        }
+    });
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Issue a reset token for the active account with `email`, if any, and
+/// mail it the link.
+async fn mail_reset_link(
+    state: &AppState,
+    reset: &PasswordResetConfig,
+    smtp: &NotificationConfig,
+    email: &str,
+    correlation_id: &str,
+) -> anyhow::Result<()> {
+    let user = match state.auth_store.get_user_by_email(email).await {
+        Ok(user) if user.is_active => user,
+        Ok(_) | Err(AuthError::NotFound(_)) => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    let token = state
+        .auth_store
+        .create_reset_token(&user.id, reset.token_ttl_minutes)
+        .await?;
+    state
+        .auth_store
+        .log_audit(
+            Some(&user.id),
+            "user.password_reset_requested",
+            None,
+            None,
+            correlation_id,
+        )
+        .await?;
+
+    let link = format!("{}?token={token}", reset.reset_url);
+    let to = user.email.unwrap_or_default();
+    enigma_core::notify::send_password_reset(smtp, &to, &link, reset.token_ttl_minutes).await
+}
+
+/// POST /api/auth/reset-password
+///
+/// Set a new password with the token from a reset link. Each token works
+/// once.
+#[utoipa::path(
+    post,
+    path = "/api/auth/reset-password",
+    tag = "auth",
+    request_body = ResetPasswordRequest,
+    security(()),
+    responses(
+        (status = 204, description = "Password changed"),
+        (status = 400, description = "Invalid, expired or used token, or weak password"),
+    )
+)]
+pub async fn reset_password(
+    State(state): State<Arc<AppState>>,
+    correlation_id: CorrelationId,
+    Json(req): Json<ResetPasswordRequest>,
+) -> Result<StatusCode, AuthError> {
+    let password = enigma_auth::NewPassword::new(&req.new_password, &state.password_policy)?;
+    state
+        .auth_store
+        .use_reset_token(&req.token, &password)
+        .await?;
+    state
+        .auth_store
+        .log_audit(None, "user.password_reset", None, None, &correlation_id.0)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn locked(lock: &LockoutInfo) -> Response {
+    let mut resp = (StatusCode::TOO_MANY_REQUESTS, "account locked").into_response();
+    resp.headers_mut().insert(
+        header::RETRY_AFTER,
+        HeaderValue::from(lock.retry_after_seconds),
+    );
+    resp
+}
+
+/// The account behind a session JWT, with its permissions; `None` when it
+/// is not in the auth store. The configured admin account holds every
+/// permission.
+async fn session_user(state: &AppState, claims: &Claims) -> Result<Option<AuthUser>, AuthError> {
+    // Impersonation tokens name the user by ID, login tokens by name
+    let user = match &claims.impersonated_by {
+        Some(_) => state.auth_store.get_user_by_id(&claims.sub).await,
+        None => state.auth_store.get_user_by_username(&claims.sub).await,
+    };
+    let user = match user {
+        Ok(user) => user,
+        Err(AuthError::NotFound(_)) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let permissions = if claims.impersonated_by.is_none() && claims.sub == state.admin_user {
+        vec!["*".to_string()]
+    } else {
+        state.auth_store.get_user_permissions(&user.id).await?
+    };
+    let groups = state
+        .auth_store
+        .list_user_groups(&user.id)
+        .await?
+        .into_iter()
+        .map(|g| g.name)
+        .collect();
+    Ok(Some(AuthUser {
+        user_id: user.id,
+        username: user.username,
+        groups,
+        permissions,
+        api_token: None,
+        impersonated_by: claims.impersonated_by.clone(),
+        namespace_prefix: None,
+    }))
+}
+
+/// Authenticates the API: a session JWT from login, or an `egt_` API
+/// token. Inserts the [`AuthUser`], and for JWTs also the [`Claims`].
 pub async fn auth_middleware(
     State(state): State<Arc<AppState>>,
-    request: axum::extract::Request,
+    mut request: axum::extract::Request,
     next: Next,
-) -> Result<Response, StatusCode> {
+) -> Result<Response, Response> {
     let auth_header = request
         .headers()
         .get("Authorization")
@@ -91,10 +449,361 @@ pub async fn auth_middleware(
 
     let token = match auth_header {
         Some(h) if h.starts_with("Bearer ") => &h[7..],
-        _ => return Err(StatusCode::UNAUTHORIZED),
+        _ => return Err(StatusCode::UNAUTHORIZED.into_response()),
     };
 
-    verify_token(token, &state.jwt_secret).map_err(|_| StatusCode::UNAUTHORIZED)?;
+    if token.starts_with("egt_") {
+        let (mut parts, body) = request.into_parts();
+        let user = AuthUser::from_request_parts(&mut parts, &())
+            .await
+            .map_err(IntoResponse::into_response)?;
+        parts.extensions.insert(user);
+        let request = axum::extract::Request::from_parts(parts, body);
+        return Ok(next.run(request).await);
+    }
+
+    let claims = verify_token(token, &state.jwt_secret)
+        .map_err(|_| StatusCode::UNAUTHORIZED.into_response())?;
 
+    // Every call made while impersonating is attributed to the admin
+    if let Some(admin_id) = &claims.impersonated_by {
+        tracing::Span::current().record("impersonated_by", admin_id.as_str());
+        let correlation_id = request
+            .extensions()
+            .get::<CorrelationId>()
+            .cloned()
+            .unwrap_or_default();
+        let ip = request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ci| ci.0.ip().to_string());
+        let target = format!("{} {}", request.method(), request.uri().path());
+        state
+            .auth_store
+            .log_impersonated_audit(
+                &claims.sub,
+                admin_id,
+                "api.request",
+                Some(&target),
+                ip.as_deref(),
+                &correlation_id.0,
+            )
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())?;
+    }
+
+    if let Some(user) = session_user(&state, &claims)
+        .await
+        .map_err(IntoResponse::into_response)?
+    {
+        request.extensions_mut().insert(user);
+    }
+    request.extensions_mut().insert(claims);
     Ok(next.run(request).await)
 }
+
+#[cfg(test)]
+mod tests {
+    use enigma_auth::{AuthStore, LockoutPolicy, SqliteAuthStore};
+
+    use super::*;
+
+    async fn test_state() -> Arc<AppState> {
+        test_state_with_oidc(None).await
+    }
+
+    async fn test_state_with_oidc(oidc: Option<OidcConfig>) -> Arc<AppState> {
+        test_state_with(oidc, None).await
+    }
+
+    async fn test_state_with(
+        oidc: Option<OidcConfig>,
+        password_reset: Option<PasswordResetConfig>,
+    ) -> Arc<AppState> {
+        let store = SqliteAuthStore::open_in_memory()
+            .unwrap()
+            .with_lockout(LockoutPolicy {
+                threshold: 3,
+                duration_seconds: 60,
+            });
+        store.migrate().await.unwrap();
+        let password = enigma_auth::NewPassword::disabled();
+        store.create_user("admin", &password, None).await.unwrap();
+        store
+            .create_user("alice", &password, Some("alice@example.com"))
+            .await
+            .unwrap();
+
+        Arc::new(AppState {
+            auth_store: Arc::new(store),
+            oidc,
+            password_reset,
+            ..AppState::for_tests()
+        })
+    }
+
+    async fn try_login(state: &Arc<AppState>, password: &str) -> Result<(), Response> {
+        let req = LoginRequest {
+            username: "admin".to_string(),
+            password: password.to_string(),
+        };
+        login(State(state.clone()), CorrelationId::default(), Json(req))
+            .await
+            .map(|_| ())
+    }
+
+    #[tokio::test]
+    async fn locks_out_after_failed_logins() {
+        let state = test_state().await;
+
+        for _ in 0..2 {
+            let resp = try_login(&state, "wrong").await.unwrap_err();
+            assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        }
+        let resp = try_login(&state, "wrong").await.unwrap_err();
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        // The right password is refused while locked
+        let resp = try_login(&state, "admin").await.unwrap_err();
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        let retry_after: u64 = resp.headers()[header::RETRY_AFTER]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(retry_after > 0 && retry_after <= 60);
+    }
+
+    #[tokio::test]
+    async fn successful_login_resets_failures() {
+        let state = test_state().await;
+
+        try_login(&state, "wrong").await.unwrap_err();
+        try_login(&state, "wrong").await.unwrap_err();
+        try_login(&state, "admin").await.unwrap();
+
+        // Counter starts over: two more failures do not lock
+        try_login(&state, "wrong").await.unwrap_err();
+        let resp = try_login(&state, "wrong").await.unwrap_err();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        try_login(&state, "admin").await.unwrap();
+    }
+
+    fn callback(code: Option<&str>, error: Option<&str>) -> Query<OidcCallbackQuery> {
+        Query(OidcCallbackQuery {
+            code: code.map(str::to_string),
+            state: "state".to_string(),
+            error: error.map(str::to_string),
+        })
+    }
+
+    #[tokio::test]
+    async fn oidc_routes_are_off_unless_configured() {
+        let state = test_state().await;
+
+        let resp = oidc_login(State(state.clone())).await.unwrap_err();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        let resp = oidc_callback(
+            State(state),
+            CorrelationId::default(),
+            callback(Some("code"), None),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn oidc_callback_refuses_provider_errors_and_unknown_state() {
+        let state = test_state_with_oidc(Some(OidcConfig {
+            provider: enigma_auth::OidcProvider {
+                discovery_url: "http://127.0.0.1:9".to_string(),
+                client_id: "enigma".to_string(),
+                client_secret: "secret".to_string(),
+                redirect_uri: "http://localhost/api/auth/oidc/callback".to_string(),
+            },
+            post_login_redirect: "/".to_string(),
+        }))
+        .await;
+
+        let resp = oidc_callback(
+            State(state.clone()),
+            CorrelationId::default(),
+            callback(None, Some("access_denied")),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        let resp = oidc_callback(
+            State(state),
+            CorrelationId::default(),
+            callback(Some("code"), None),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn login_audit_records_the_request_id() {
+        use axum::body::Body;
+        use axum::http::Request;
+        use tower::ServiceExt;
+
+        let state = test_state().await;
+        let login_request = |request_id: Option<&str>| {
+            let mut request = Request::builder()
+                .method("POST")
+                .uri("/api/auth/login")
+                .header(header::CONTENT_TYPE, "application/json");
+            if let Some(id) = request_id {
+                request = request.header("X-Request-ID", id);
+            }
+            let request = request
+                .body(Body::from(r#"{"username":"admin","password":"admin"}"#))
+                .unwrap();
+            crate::routes::build_router(state.clone()).oneshot(request)
+        };
+
+        let resp = login_request(Some("test-123")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()["x-request-id"], "test-123");
+        let audit = state.auth_store.list_audit(10, 0).await.unwrap();
+        assert_eq!(audit.len(), 1);
+        assert_eq!(audit[0].action, "user.login");
+        assert_eq!(audit[0].correlation_id, "test-123");
+
+        // Without one, an ID is generated
+        let resp = login_request(None).await.unwrap();
+        let generated = resp.headers()["x-request-id"].to_str().unwrap();
+        assert!(uuid::Uuid::parse_str(generated).is_ok());
+    }
+
+    /// Password reset mailing to an SMTP server nobody listens on.
+    fn password_reset() -> PasswordResetConfig {
+        PasswordResetConfig {
+            reset_url: "https://enigma.example.com/reset-password".to_string(),
+            token_ttl_minutes: 30,
+            smtp: Some(enigma_core::notify::NotificationConfig {
+                smtp_host: "127.0.0.1".to_string(),
+                smtp_port: 9,
+                smtp_tls: false,
+                smtp_user: None,
+                smtp_pass: None,
+                from: "Enigma <enigma@example.com>".to_string(),
+                to: Vec::new(),
+                on_success: false,
+                on_failure: false,
+                on_slow: None,
+            }),
+        }
+    }
+
+    async fn forgot(state: &Arc<AppState>, email: &str) -> Result<StatusCode, Response> {
+        let req = ForgotPasswordRequest {
+            email: email.to_string(),
+        };
+        forgot_password(State(state.clone()), CorrelationId::default(), Json(req)).await
+    }
+
+    #[tokio::test]
+    async fn forgot_password_does_not_reveal_unknown_emails() {
+        let state = test_state_with(None, Some(password_reset())).await;
+
+        let known = forgot(&state, "alice@example.com").await.unwrap();
+        let unknown = forgot(&state, "mallory@example.com").await.unwrap();
+        assert_eq!(known, unknown);
+
+        // Only the real account got a token, in the background
+        let mut audit = Vec::new();
+        for _ in 0..50 {
+            audit = state.auth_store.list_audit(10, 0).await.unwrap();
+            if !audit.is_empty() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(audit.len(), 1);
+        assert_eq!(audit[0].action, "user.password_reset_requested");
+
+        let resp = forgot(&test_state().await, "alice@example.com")
+            .await
+            .unwrap_err();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn reset_token_works_once() {
+        let state = test_state_with(None, Some(password_reset())).await;
+        let alice = state
+            .auth_store
+            .get_user_by_username("alice")
+            .await
+            .unwrap();
+        let token = state
+            .auth_store
+            .create_reset_token(&alice.id, 30)
+            .await
+            .unwrap();
+        let reset = |new_password: &str| {
+            let req = ResetPasswordRequest {
+                token: token.clone(),
+                new_password: new_password.to_string(),
+            };
+            reset_password(State(state.clone()), CorrelationId::default(), Json(req))
+        };
+
+        // A weak password does not use up the token
+        assert!(matches!(
+            reset("short").await,
+            Err(AuthError::WeakPassword(_))
+        ));
+        assert_eq!(
+            reset("Correct-Horse-9").await.unwrap(),
+            StatusCode::NO_CONTENT
+        );
+        let hash = state.auth_store.get_password_hash(&alice.id).await.unwrap();
+        assert!(enigma_auth::verify_password("Correct-Horse-9", &hash).unwrap());
+
+        assert!(matches!(
+            reset("Another-Horse-9").await,
+            Err(AuthError::InvalidInput(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn login_takes_the_reset_password() {
+        let state = test_state_with(None, Some(password_reset())).await;
+        let alice = state
+            .auth_store
+            .get_user_by_username("alice")
+            .await
+            .unwrap();
+        let token = state
+            .auth_store
+            .create_reset_token(&alice.id, 30)
+            .await
+            .unwrap();
+        let req = ResetPasswordRequest {
+            token,
+            new_password: "Correct-Horse-9".to_string(),
+        };
+        reset_password(State(state.clone()), CorrelationId::default(), Json(req))
+            .await
+            .unwrap();
+
+        let login_as = |password: &str| {
+            let req = LoginRequest {
+                username: "alice".to_string(),
+                password: password.to_string(),
+            };
+            login(State(state.clone()), CorrelationId::default(), Json(req))
+        };
+        assert!(login_as("Correct-Horse-9").await.is_ok());
+        let resp = login_as("unused").await.map(|_| ()).unwrap_err();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        // The configured admin password is no use for other accounts
+        let resp = login_as("admin").await.map(|_| ()).unwrap_err();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -1,3 +1,20 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// Health of one Raft peer, as seen from this node.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PeerHealth {
+    pub node_id: u64,
+    /// Raft gRPC address.
+    pub addr: String,
+    /// `leader`, `follower` or `learner`.
+    pub state: String,
+    /// Last log index replicated to the peer; only known on the leader.
+    pub last_log_index: u64,
+    /// Round trip of a gRPC health check, `None` when the peer did not answer.
+    pub last_contact_ms: Option<u64>,
+}
+
 /// Trait for cluster operations — abstracts Raft from the web layer.
 #[async_trait::async_trait]
 pub trait ClusterHandle: Send + Sync {
@@ -7,9 +24,16 @@ pub trait ClusterHandle: Send + Sync {
     /// Add a new node to the cluster.
     async fn add_node(&self, node_id: u64, addr: String) -> anyhow::Result<()>;
 
+    /// Add a learner: it receives the replicated log and serves reads, but
+    /// never votes, so the quorum stays as it is.
+    async fn add_learner(&self, node_id: u64, addr: String) -> anyhow::Result<()>;
+
     /// Remove a node from the cluster.
     async fn remove_node(&self, node_id: u64) -> anyhow::Result<()>;
 
     /// Trigger a snapshot on the leader.
     async fn trigger_snapshot(&self) -> anyhow::Result<()>;
+
+    /// Replication state and liveness of every other cluster member.
+    async fn peer_health(&self) -> anyhow::Result<Vec<PeerHealth>>;
 }
@@ -0,0 +1,83 @@
+//! Request correlation IDs.
+//!
+//! Every request runs inside a `request` tracing span carrying its
+//! `request_id`, so each log line a handler writes can be matched to the
+//! request. The ID comes from the client's `X-Request-ID` header when it
+//! sends a usable one, and is echoed back on the response. Requests made
+//! with an impersonation token also carry the admin's `impersonated_by`.
+
+use std::convert::Infallible;
+
+use axum::extract::{FromRequestParts, Request};
+use axum::http::HeaderValue;
+use axum::http::request::Parts;
+use axum::middleware::Next;
+use axum::response::Response;
+use tracing::Instrument;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest client-supplied ID that is kept; longer ones are replaced.
+const MAX_LEN: usize = 128;
+
+/// The current request's ID, for handlers that record it, e.g. in the
+/// audit log. Empty outside [`correlation_id_middleware`].
+#[derive(Debug, Clone, Default)]
+pub struct CorrelationId(pub String);
+
+impl<S: Send + Sync> FromRequestParts<S> for CorrelationId {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts.extensions.get::<Self>().cloned().unwrap_or_default())
+    }
+}
+
+/// Client-supplied IDs end up in logs, so only short, plain ones are taken.
+fn usable(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_LEN
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b':'))
+}
+
+pub async fn correlation_id_middleware(mut request: Request, next: Next) -> Response {
+    let id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|id| usable(id))
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::now_v7().to_string());
+
+    let span = tracing::info_span!(
+        "request",
+        request_id = %id,
+        method = %request.method(),
+        path = %request.uri().path(),
+        impersonated_by = tracing::field::Empty,
+    );
+    request.extensions_mut().insert(CorrelationId(id.clone()));
+
+    let mut response = next.run(request).instrument(span).await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_plain_ids_are_kept() {
+        assert!(usable("test-123"));
+        assert!(usable("0190b6c2-7a4e-7c1d-9f00-1b2c3d4e5f60"));
+        assert!(!usable(""));
+        assert!(!usable("two words"));
+        assert!(!usable("line\nbreak"));
+        assert!(!usable(&"a".repeat(MAX_LEN + 1)));
+    }
+}
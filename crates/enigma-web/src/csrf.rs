@@ -0,0 +1,300 @@
+//! CSRF protection for the authenticated API (double-submit cookie).
+//!
+//! Safe requests get a `csrf_token` cookie unless they already carry a
+//! valid one; state-changing requests must echo it in the `X-CSRF-Token`
+//! header or are refused with 403. A token is a random nonce and an HMAC
+//! of it with the session's JWT `jti`, so a token planted in the browser
+//! before login, or taken from another session, does not verify.
+//! The cookie is HTTP-only: SPA clients read the token from
+//! `GET /api/csrf-token` instead.
+
+use std::sync::Arc;
+
+use axum::extract::{Request, State};
+use axum::http::{HeaderMap, HeaderValue, Method, StatusCode, header};
+use axum::middleware::Next;
+use axum::response::Response;
+use axum::{Extension, Json};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use enigma_auth::AuthUser;
+use ring::hmac;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::Serialize;
+use subtle::ConstantTimeEq;
+use utoipa::ToSchema;
+
+use crate::auth::Claims;
+use crate::state::AppState;
+
+pub const CSRF_COOKIE: &str = "csrf_token";
+pub const CSRF_HEADER: &str = "x-csrf-token";
+
+/// The request's CSRF token, set by [`csrf_middleware`] on safe requests.
+#[derive(Debug, Clone)]
+pub struct CsrfToken(pub String);
+
+#[derive(Serialize, ToSchema)]
+pub struct CsrfTokenResponse {
+    /// Value to send in the `X-CSRF-Token` header.
+    pub token: String,
+}
+
+/// What a token is bound to: the JWT `jti`, or for tokens issued without
+/// one, the subject and issue time.
+fn session_id(claims: &Claims) -> String {
+    claims
+        .jti
+        .clone()
+        .unwrap_or_else(|| format!("{}:{}", claims.sub, claims.iat))
+}
+
+fn signed_message(session: &str, nonce: &[u8]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(session.len() + nonce.len() + 1);
+    message.extend_from_slice(session.as_bytes());
+    message.push(0);
+    message.extend_from_slice(nonce);
+    message
+}
+
+fn hmac_key(secret: &str) -> hmac::Key {
+    hmac::Key::new(hmac::HMAC_SHA256, format!("csrf:{secret}").as_bytes())
+}
+
+/// A new token for `session`: `<nonce>.<hmac>`, both base64url.
+fn issue_token(secret: &str, session: &str) -> String {
+    let mut nonce = [0u8; 32];
+    SystemRandom::new()
+        .fill(&mut nonce)
+        .expect("system random number generator failed");
+    let tag = hmac::sign(&hmac_key(secret), &signed_message(session, &nonce));
+    format!(
+        "{}.{}",
+        URL_SAFE_NO_PAD.encode(nonce),
+        URL_SAFE_NO_PAD.encode(tag.as_ref())
+    )
+}
+
+fn token_is_valid(secret: &str, session: &str, token: &str) -> bool {
+    let Some((nonce, tag)) = token.split_once('.') else {
+        return false;
+    };
+    let (Ok(nonce), Ok(tag)) = (URL_SAFE_NO_PAD.decode(nonce), URL_SAFE_NO_PAD.decode(tag)) else {
+        return false;
+    };
+    nonce.len() == 32
+        && hmac::verify(&hmac_key(secret), &signed_message(session, &nonce), &tag).is_ok()
+}
+
+fn cookie<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+/// Runs behind [`crate::auth::auth_middleware`], whose claims it needs.
+/// Requests made with an API token pass: browsers never send one on
+/// their own.
+pub async fn csrf_middleware(
+    State(state): State<Arc<AppState>>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    let Some(claims) = request.extensions().get::<Claims>() else {
+        let api_token = request
+            .extensions()
+            .get::<AuthUser>()
+            .is_some_and(|user| user.api_token.is_some());
+        if api_token {
+            return Ok(next.run(request).await);
+        }
+        return Err(StatusCode::UNAUTHORIZED);
+    };
+    let session = session_id(claims);
+    let current = cookie(request.headers(), CSRF_COOKIE)
+        .filter(|token| token_is_valid(&state.jwt_secret, &session, token))
+        .map(str::to_string);
+
+    if matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    ) {
+        let (token, fresh) = match current {
+            Some(token) => (token, false),
+            None => (issue_token(&state.jwt_secret, &session), true),
+        };
+        request.extensions_mut().insert(CsrfToken(token.clone()));
+        let mut response = next.run(request).await;
+        if fresh
+            && let Ok(value) = HeaderValue::from_str(&format!(
+                "{CSRF_COOKIE}={token}; Path=/api; HttpOnly; SameSite=Strict"
+            ))
+        {
+            response.headers_mut().append(header::SET_COOKIE, value);
+        }
+        return Ok(response);
+    }
+
+    let sent = request
+        .headers()
+        .get(CSRF_HEADER)
+        .and_then(|v| v.to_str().ok());
+    match (current, sent) {
+        (Some(expected), Some(sent)) if bool::from(expected.as_bytes().ct_eq(sent.as_bytes())) => {
+            Ok(next.run(request).await)
+        }
+        _ => Err(StatusCode::FORBIDDEN),
+    }
+}
+
+/// GET /api/csrf-token
+///
+/// The CSRF token of the current session, also set as the `csrf_token`
+/// cookie. Send it back in `X-CSRF-Token` on PUT, POST and DELETE.
+#[utoipa::path(
+    get,
+    path = "/api/csrf-token",
+    tag = "auth",
+    responses(
+        (status = 200, description = "CSRF token", body = CsrfTokenResponse),
+        (status = 401, description = "Missing or invalid credentials"),
+    )
+)]
+pub async fn get_csrf_token(Extension(token): Extension<CsrfToken>) -> Json<CsrfTokenResponse> {
+    Json(CsrfTokenResponse { token: token.0 })
+}
+
+/// Adds a valid CSRF cookie and header for the session of `jwt` to a test
+/// request.
+#[cfg(test)]
+pub(crate) fn with_csrf(
+    request: axum::http::request::Builder,
+    jwt: &str,
+    secret: &str,
+) -> axum::http::request::Builder {
+    let claims = crate::auth::verify_token(jwt, secret).unwrap();
+    let token = issue_token(secret, &session_id(&claims));
+    request
+        .header(header::COOKIE, format!("{CSRF_COOKIE}={token}"))
+        .header(CSRF_HEADER, token)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::auth::create_token;
+    use crate::routes::build_router;
+
+    fn test_state() -> Arc<AppState> {
+        Arc::new(AppState::for_tests())
+    }
+
+    /// POST to a protected route; no cluster is configured, so a request
+    /// that gets past the CSRF check is answered with 400.
+    async fn post_learner(state: &Arc<AppState>, jwt: &str, csrf: Option<&str>) -> StatusCode {
+        let mut request = Request::builder()
+            .method("POST")
+            .uri("/api/cluster/learners")
+            .header("Authorization", format!("Bearer {jwt}"))
+            .header("Content-Type", "application/json");
+        if let Some(token) = csrf {
+            request = request
+                .header(header::COOKIE, format!("{CSRF_COOKIE}={token}"))
+                .header(CSRF_HEADER, token);
+        }
+        let request = request
+            .body(Body::from(r#"{"node_id":4,"addr":"10.0.1.4:9100"}"#))
+            .unwrap();
+        build_router(state.clone())
+            .oneshot(request)
+            .await
+            .unwrap()
+            .status()
+    }
+
+    /// The token and `Set-Cookie` header from `GET /api/csrf-token`.
+    async fn fetch_token(state: &Arc<AppState>, jwt: &str) -> (String, String) {
+        let request = Request::builder()
+            .uri("/api/csrf-token")
+            .header("Authorization", format!("Bearer {jwt}"))
+            .body(Body::empty())
+            .unwrap();
+        let resp = build_router(state.clone()).oneshot(request).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let set_cookie = resp.headers()[header::SET_COOKIE]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        (body["token"].as_str().unwrap().to_string(), set_cookie)
+    }
+
+    #[tokio::test]
+    async fn state_changing_request_needs_the_token() {
+        let state = test_state();
+        let jwt = create_token("admin", &state.jwt_secret).unwrap();
+        assert_eq!(
+            post_learner(&state, &jwt, None).await,
+            StatusCode::FORBIDDEN
+        );
+
+        let (token, set_cookie) = fetch_token(&state, &jwt).await;
+        assert!(set_cookie.starts_with(&format!("{CSRF_COOKIE}={token};")));
+        assert!(set_cookie.contains("HttpOnly"));
+        assert!(set_cookie.contains("SameSite=Strict"));
+        assert_eq!(
+            post_learner(&state, &jwt, Some(&token)).await,
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[tokio::test]
+    async fn token_is_bound_to_the_session() {
+        let state = test_state();
+        let jwt = create_token("admin", &state.jwt_secret).unwrap();
+        let (token, _) = fetch_token(&state, &jwt).await;
+
+        // Another login of the same user has another jti
+        let other = create_token("admin", &state.jwt_secret).unwrap();
+        assert_eq!(
+            post_learner(&state, &other, Some(&token)).await,
+            StatusCode::FORBIDDEN
+        );
+        // A made-up token that matches its own cookie is not enough
+        assert_eq!(
+            post_learner(&state, &jwt, Some("bm9uY2U.dGFn")).await,
+            StatusCode::FORBIDDEN
+        );
+    }
+
+    #[tokio::test]
+    async fn header_must_match_the_cookie() {
+        let state = test_state();
+        let jwt = create_token("admin", &state.jwt_secret).unwrap();
+        let (token, _) = fetch_token(&state, &jwt).await;
+        let (other_token, _) = fetch_token(&state, &jwt).await;
+        assert_ne!(token, other_token);
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/api/cluster/learners")
+            .header("Authorization", format!("Bearer {jwt}"))
+            .header(header::COOKIE, format!("{CSRF_COOKIE}={token}"))
+            .header(CSRF_HEADER, other_token)
+            .body(Body::empty())
+            .unwrap();
+        let resp = build_router(state).oneshot(request).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    }
+}
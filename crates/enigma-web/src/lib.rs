@@ -1,31 +1,89 @@
+mod audit_purge;
 mod auth;
+pub mod cluster_handle;
+mod correlation;
+mod csrf;
 mod models;
+mod openapi;
+mod pool;
+mod rate_limit;
 mod routes;
 mod state;
 mod static_files;
+mod stream_ticket;
 
 use std::net::SocketAddr;
 use std::path::Path;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 
-pub use state::WebConfig;
+use enigma_auth::AuthStore;
+
+pub use state::{OidcConfig, PasswordResetConfig, RateLimitConfig, WebConfig};
 
 use state::AppState;
 
-/// Start the web UI server. Opens its own ManifestDb connection to the same SQLite file.
+/// Start the web UI server. Opens its own pools of ManifestDb connections, writable and
+/// read-only, to the same SQLite file.
+/// Backup progress published on `events` is streamed to `/api/ws/status` clients.
+/// `key_provider` and `storage_providers` are probed by the health endpoints
+/// and used by re-encryption, which notifies `webhooks`.
+/// `cluster` backs the cluster routes when running under Raft.
+#[allow(clippy::too_many_arguments)]
 pub async fn start_web_server(
     config: WebConfig,
     db_path: &str,
     enigma_config: enigma_core::config::EnigmaSettings,
+    events: enigma_core::events::BackupEvents,
+    key_provider: Option<Arc<dyn enigma_keys::provider::KeyProvider>>,
+    storage_providers: Vec<Arc<dyn enigma_storage::provider::StorageProvider>>,
+    webhooks: Vec<enigma_core::webhook::WebhookConfig>,
+    cluster: Option<Arc<dyn cluster_handle::ClusterHandle>>,
 ) -> anyhow::Result<()> {
-    let db = enigma_core::manifest::ManifestDb::open(Path::new(db_path))?;
+    // Migrate once up front; pooled connections skip it
+    enigma_core::manifest::ManifestDb::open(Path::new(db_path))?;
+    let db = pool::build_pool(Path::new(db_path), config.db_pool_size)?;
+    let query_cache = Arc::new(enigma_core::manifest::QueryCache::new(
+        &enigma_config.cache_ttl_seconds,
+    ));
+    let readonly_db =
+        pool::build_readonly_pool(Path::new(db_path), config.db_pool_size, query_cache)?;
+
+    // Auth tables live in the same SQLite file as the manifest
+    let lockout = enigma_auth::LockoutPolicy {
+        threshold: config.lockout_threshold,
+        duration_seconds: config.lockout_duration_seconds,
+    };
+    let auth_store = enigma_auth::SqliteAuthStore::open(db_path)?.with_lockout(lockout);
+    auth_store.migrate().await?;
+    // Permissions and the built-in groups the API routes check against
+    auth_store.seed_defaults().await?;
+    ensure_admin_user(&auth_store, &config).await?;
+    let auth_store: Arc<dyn AuthStore> = Arc::new(auth_store);
+    audit_purge::spawn(auth_store.clone(), config.audit_retention_days);
 
     let state = Arc::new(AppState {
-        db: Mutex::new(db),
+        db,
+        readonly_db,
         config: enigma_config,
         jwt_secret: config.jwt_secret.clone(),
         admin_user: config.admin_user.clone(),
         admin_pass: config.admin_pass.clone(),
+        rate_limit: config.rate_limit.clone(),
+        login_rate_limit: config.login_rate_limit.clone(),
+        trusted_proxies: enigma_auth::parse_allowed_ips(&config.trusted_proxies.join(","))?,
+        password_policy: config.password_policy.clone(),
+        auth_store,
+        events,
+        key_provider,
+        storage_providers,
+        webhooks,
+        usage_cache: state::UsageCache::new(std::time::Duration::from_secs(
+            config.usage_cache_seconds,
+        )),
+        oidc: config.oidc.clone(),
+        cluster,
+        password_reset: config.password_reset.clone(),
+        stream_tickets: Default::default(),
     });
 
     if state.jwt_secret == "enigma-jwt-secret-change-me" {
@@ -44,7 +102,28 @@ pub async fn start_web_server(
     tracing::info!("Starting web interface on http://{addr}");
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
 
     Ok(())
 }
+
+/// Give the configured admin account a row in the auth store so failed
+/// logins against it can be counted and locked out.
+async fn ensure_admin_user(store: &dyn AuthStore, config: &WebConfig) -> anyhow::Result<()> {
+    match store.get_user_by_username(&config.admin_user).await {
+        Ok(_) => Ok(()),
+        Err(enigma_auth::AuthError::NotFound(_)) => {
+            // The admin logs in against the config, never this row
+            let password = enigma_auth::NewPassword::disabled();
+            store
+                .create_user(&config.admin_user, &password, None)
+                .await?;
+            Ok(())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
@@ -1,6 +1,7 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct StatusResponse {
     pub version: String,
     pub key_provider: String,
@@ -12,7 +13,7 @@ pub struct StatusResponse {
     pub total_namespaces: usize,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct ProviderResponse {
     pub id: i64,
     pub name: String,
@@ -22,13 +23,192 @@ pub struct ProviderResponse {
     pub weight: u32,
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Serialize, ToSchema)]
+pub struct ProviderUsageResponse {
+    /// Manifest id of the provider, if it is registered there.
+    pub provider_id: Option<i64>,
+    pub name: String,
+    pub used_bytes: u64,
+    /// Free space, for providers with a fixed capacity.
+    pub available_bytes: Option<u64>,
+    pub object_count: u64,
+    /// `used_bytes` as a percentage of `used_bytes + available_bytes`.
+    pub filled_percent: Option<f64>,
+    /// Why the usage could not be read; the counts are then zero.
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct S3AccessLogResponse {
+    pub bucket: String,
+    pub key: Option<String>,
+    /// S3 operation name, e.g. `GetObject`.
+    pub operation: String,
+    pub requester_ip: Option<String>,
+    pub timestamp: String,
+    pub status_code: u16,
+    pub bytes_transferred: u64,
+}
+
+/// The latest S3 inventory report; every field is null until one is
+/// generated.
+#[derive(Serialize, ToSchema)]
+pub struct InventoryStatusResponse {
+    /// When the report was generated (RFC 3339, UTC).
+    pub last_generated_at: Option<String>,
+    /// Key of the CSV report in the `reports` bucket.
+    pub key: Option<String>,
+    /// Report size in bytes.
+    pub size: Option<u64>,
+    /// Objects listed in the report.
+    pub objects: Option<u64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct PresignResponse {
+    /// URL the chunk can be downloaded from without going through Enigma.
+    pub url: String,
+    /// Seconds the URL stays valid.
+    pub expires_in: u64,
+}
+
+#[derive(Serialize, ToSchema)]
 pub struct ChunkStatsResponse {
     pub total_chunks: u64,
     pub orphan_chunks: u64,
+    /// Deduplication of the most recent backup, if any.
+    pub latest_backup_dedup: Option<DedupStatsResponse>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct DedupStatsResponse {
+    pub backup_id: String,
+    pub total_chunks: u64,
+    pub unique_chunks: u64,
+    pub dedup_chunks: u64,
+    pub total_bytes_logical: u64,
+    pub total_bytes_stored: u64,
+    pub dedup_ratio_percent: f64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct GlobalDedupStatsResponse {
+    pub backups: u64,
+    pub total_chunks: u64,
+    pub shared_chunks: u64,
+    pub dedup_chunks: u64,
+    pub total_bytes_logical: u64,
+    pub total_bytes_stored: u64,
+    pub dedup_ratio_percent: f64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CrossNamespaceDedupEntryResponse {
+    pub chunk_hash: String,
+    pub namespaces: Vec<String>,
+    pub ref_count: u32,
+    pub size_bytes: u64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CrossNamespaceDedupResponse {
+    /// Chunks shared between namespaces, largest first.
+    pub entries: Vec<CrossNamespaceDedupEntryResponse>,
+    pub limit: u32,
+    pub offset: u32,
+    /// Bytes saved over storing each shared chunk once per namespace.
+    pub savings_bytes: u64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CheckpointResponse {
+    /// Frames in the write-ahead log.
+    pub wal_frames: u32,
+    /// Frames now copied into the database file.
+    pub checkpointed_frames: u32,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct AuditPurgeResponse {
+    /// Audit entries deleted.
+    pub deleted: u64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct AuditStatsResponse {
+    pub total_entries: u64,
+    /// `created_at` of the oldest entry; null when the log is empty.
+    pub oldest_entry_date: Option<String>,
+    /// Space taken by the entries, as estimated by the database.
+    pub estimated_size_bytes: u64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ImpersonateResponse {
+    /// JWT for the target user, carrying an `impersonated_by` claim.
+    pub token: String,
+    pub expires_in: u64,
+    /// Record of the token in the auth store.
+    pub impersonation_id: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct HistogramBucketResponse {
+    pub start_kb: u64,
+    pub end_kb: u64,
+    pub count: u64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ProviderChunkSizeResponse {
+    pub provider_id: i64,
+    pub name: String,
+    pub chunks: u64,
+    pub avg_chunk_bytes: u64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ChunkHistogramResponse {
+    /// Backup the chunks were taken from; all chunks when absent.
+    pub backup_id: Option<String>,
+    pub bucket_width_kb: u64,
+    /// Non-empty buckets only, smallest first. The last bucket also counts
+    /// every larger chunk.
+    pub buckets: Vec<HistogramBucketResponse>,
+    /// Fraction of plaintext bytes saved by compression (0.0–1.0).
+    pub compression_ratio: f64,
+    /// Chunks per provider by primary copy, with their average size.
+    pub providers: Vec<ProviderChunkSizeResponse>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ComponentHealthResponse {
+    /// "ok", "degraded" or "unavailable".
+    pub status: String,
+    pub latency_ms: u64,
+    pub message: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct StorageHealthResponse {
+    pub name: String,
+    /// "ok" or "unavailable".
+    pub status: String,
+    pub latency_ms: u64,
+    pub message: Option<String>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
+pub struct HealthResponse {
+    /// "ok", "degraded" or "unavailable".
+    pub status: String,
+    pub database: ComponentHealthResponse,
+    /// Absent when the web UI has no key provider to probe.
+    pub key_provider: Option<ComponentHealthResponse>,
+    pub storage: Vec<StorageHealthResponse>,
+}
+
+#[derive(Serialize, ToSchema)]
 pub struct BackupResponse {
     pub id: String,
     pub source_path: String,
@@ -41,15 +221,23 @@ pub struct BackupResponse {
     pub completed_at: Option<String>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct NamespaceResponse {
     pub id: i64,
     pub name: String,
     pub created_at: String,
     pub object_count: u64,
+    pub tags: Vec<NamespaceTag>,
 }
 
-#[derive(Serialize)]
+/// A bucket tag, as set through S3 PutBucketTagging or the web API.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct NamespaceTag {
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Serialize, ToSchema)]
 pub struct ObjectResponse {
     pub key: String,
     pub size: u64,
@@ -57,15 +245,72 @@ pub struct ObjectResponse {
     pub created_at: String,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct ClusterResponse {
     pub mode: String,
     pub node_id: Option<u64>,
     pub peers: Vec<PeerResponse>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct PeerResponse {
     pub id: u64,
     pub addr: String,
 }
+
+/// An API token as listed to its owner; the token itself is never shown
+/// again after creation.
+#[derive(Serialize, ToSchema)]
+pub struct TokenResponse {
+    pub id: String,
+    pub name: String,
+    /// `egt_` and the first 8 hex characters, to tell tokens apart.
+    pub token_prefix: String,
+    /// Comma-separated permissions the token is limited to; `*` for all.
+    pub scopes: String,
+    pub expires_at: Option<String>,
+    pub last_used_at: Option<String>,
+    pub created_at: String,
+    /// Comma-separated CIDRs the token may be used from; any when absent.
+    pub allowed_ips: Option<String>,
+    /// Namespace name prefix the token is restricted to.
+    pub namespace_prefix: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CreateTokenResponse {
+    #[serde(flatten)]
+    pub token: TokenResponse,
+    /// The `egt_` token to send as `Authorization: Bearer`; shown only once.
+    pub raw_token: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct PermissionResponse {
+    pub id: String,
+    /// e.g. `buckets:read`; `*` grants every permission.
+    pub action: String,
+    pub description: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct GroupResponse {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    /// Built-in groups (`read`, `admin`, `owner`) cannot be deleted.
+    pub is_system: bool,
+    pub permissions: Vec<PermissionResponse>,
+    pub created_at: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct UserResponse {
+    pub id: String,
+    pub username: String,
+    pub email: Option<String>,
+    pub is_active: bool,
+    pub groups: Vec<GroupResponse>,
+    pub created_at: String,
+    pub updated_at: String,
+}
@@ -0,0 +1,152 @@
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+/// Base OpenAPI document. Paths and schemas are collected from the router
+/// in `routes::build_router`, so only metadata and security live here.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Enigma API",
+        description = "Management API for the Enigma encrypted multi-cloud gateway"
+    ),
+    modifiers(&SecurityAddon),
+    security(("bearer_jwt" = []), ("api_token" = [])),
+    tags(
+        (name = "auth", description = "Authentication"),
+        (name = "status", description = "System status"),
+        (name = "storage", description = "Storage providers, chunks and backups"),
+        (name = "namespaces", description = "S3 namespaces and objects"),
+        (name = "cluster", description = "Cluster topology"),
+        (name = "users", description = "Users and their groups"),
+        (name = "groups", description = "Groups, their permissions and nested groups"),
+        (name = "admin", description = "Maintenance"),
+    )
+)]
+pub struct ApiDoc;
+
+struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer_jwt",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .description(Some("JWT obtained from POST /api/auth/login"))
+                    .build(),
+            ),
+        );
+        components.add_security_scheme(
+            "api_token",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("egt_<hex>")
+                    .description(Some("Long-lived API token (egt_ prefix)"))
+                    .build(),
+            ),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use tower::ServiceExt;
+
+    use crate::routes::build_router;
+    use crate::state::AppState;
+
+    fn test_state() -> Arc<AppState> {
+        Arc::new(AppState::for_tests())
+    }
+
+    #[tokio::test]
+    async fn openapi_json_is_valid() {
+        let app = build_router(test_state());
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/openapi.json")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let spec: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        // Structural subset of the OpenAPI 3.x document schema.
+        let schema = serde_json::json!({
+            "type": "object",
+            "required": ["openapi", "info", "paths", "components"],
+            "properties": {
+                "openapi": { "type": "string", "pattern": "^3\\." },
+                "info": {
+                    "type": "object",
+                    "required": ["title", "version"],
+                },
+                "paths": {
+                    "type": "object",
+                    "required": [
+                        "/api/auth/login",
+                        "/api/auth/oidc/login",
+                        "/api/auth/oidc/callback",
+                        "/api/status",
+                        "/api/health",
+                        "/api/keys/health",
+                        "/api/storage/providers",
+                        "/api/storage/providers/usage",
+                        "/api/storage/chunks/stats",
+                        "/api/storage/chunks/histogram",
+                        "/api/storage/dedup",
+                        "/api/storage/dedup/cross-namespace",
+                        "/api/storage/backups",
+                        "/api/storage/access-logs",
+                        "/api/storage/inventory/status",
+                        "/api/storage/presign",
+                        "/api/namespaces",
+                        "/api/namespaces/{name}/objects",
+                        "/api/namespaces/{name}/object-lock",
+                        "/api/namespaces/{name}/tags",
+                        "/api/cluster",
+                        "/api/cluster/peers",
+                        "/api/cluster/learners",
+                        "/api/admin/db/checkpoint",
+                        "/api/admin/impersonate",
+                        "/api/admin/audit/purge",
+                        "/api/admin/audit/stats",
+                        "/api/csrf-token",
+                        "/api/stream-ticket",
+                    ],
+                },
+                "components": {
+                    "type": "object",
+                    "required": ["schemas", "securitySchemes"],
+                    "properties": {
+                        "securitySchemes": {
+                            "type": "object",
+                            "required": ["bearer_jwt", "api_token"],
+                        },
+                    },
+                },
+            },
+        });
+        let validator = jsonschema::validator_for(&schema).unwrap();
+        let errors: Vec<String> = validator
+            .iter_errors(&spec)
+            .map(|e| e.to_string())
+            .collect();
+        assert!(errors.is_empty(), "invalid OpenAPI spec: {errors:?}");
+    }
+}
@@ -0,0 +1,178 @@
+//! Pools of manifest connections shared by the request handlers.
+//!
+//! SQLite in WAL mode lets readers run alongside a writer, so each request
+//! takes its own connection instead of queueing behind a single one. GET
+//! routes use the read-only pool; only the admin API's mutations check out
+//! a writable connection. In a cluster writes still go through the Raft
+//! state machine, which applies them on its own connection.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use deadpool::managed::{self, Metrics, RecycleError, RecycleResult};
+use enigma_core::manifest::{ManifestDb, QueryCache, ReadonlyManifestDb};
+use rusqlite::Connection;
+
+pub type DbPool = managed::Pool<ManifestManager>;
+pub type ReadonlyDbPool = managed::Pool<ReadonlyManifestManager>;
+
+/// Opens manifest connections to one SQLite file.
+pub struct ManifestManager {
+    path: PathBuf,
+}
+
+impl managed::Manager for ManifestManager {
+    type Type = ManifestDb;
+    type Error = rusqlite::Error;
+
+    async fn create(&self) -> Result<ManifestDb, rusqlite::Error> {
+        let conn = Connection::open(&self.path)?;
+        conn.execute_batch(
+            "PRAGMA journal_mode=WAL;
+             PRAGMA synchronous=NORMAL;
+             PRAGMA wal_autocheckpoint=0;
+             PRAGMA foreign_keys=ON;",
+        )?;
+        Ok(ManifestDb::from_connection(conn))
+    }
+
+    async fn recycle(
+        &self,
+        db: &mut ManifestDb,
+        _metrics: &Metrics,
+    ) -> RecycleResult<rusqlite::Error> {
+        // A handler that bailed out mid-transaction must not hand it on
+        if !db.conn().is_autocommit() {
+            return Err(RecycleError::Message(
+                "connection returned inside a transaction".into(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Opens read-only manifest connections to one SQLite file, all sharing
+/// one query cache.
+pub struct ReadonlyManifestManager {
+    path: PathBuf,
+    cache: Arc<QueryCache>,
+}
+
+impl managed::Manager for ReadonlyManifestManager {
+    type Type = ReadonlyManifestDb;
+    type Error = enigma_core::error::EnigmaError;
+
+    async fn create(&self) -> Result<ReadonlyManifestDb, Self::Error> {
+        ReadonlyManifestDb::open_with_cache(&self.path, self.cache.clone())
+    }
+
+    async fn recycle(
+        &self,
+        _db: &mut ReadonlyManifestDb,
+        _metrics: &Metrics,
+    ) -> RecycleResult<Self::Error> {
+        Ok(())
+    }
+}
+
+/// Pool of up to `max_size` connections to the manifest at `path`, which
+/// must already have been migrated with [`ManifestDb::open`].
+pub fn build_pool(path: &Path, max_size: usize) -> anyhow::Result<DbPool> {
+    let manager = ManifestManager {
+        path: path.to_path_buf(),
+    };
+    Ok(DbPool::builder(manager).max_size(max_size).build()?)
+}
+
+/// Pool of up to `max_size` read-only connections to the manifest at
+/// `path`, which must already have been migrated with [`ManifestDb::open`].
+/// Expensive queries are cached in `cache` across connections.
+pub fn build_readonly_pool(
+    path: &Path,
+    max_size: usize,
+    cache: Arc<QueryCache>,
+) -> anyhow::Result<ReadonlyDbPool> {
+    let manager = ReadonlyManifestManager {
+        path: path.to_path_buf(),
+        cache,
+    };
+    Ok(ReadonlyDbPool::builder(manager)
+        .max_size(max_size)
+        .build()?)
+}
+
+/// Pool for tests that never query the manifest: no connection is opened
+/// until one is requested.
+#[cfg(test)]
+pub(crate) fn unused_pool() -> DbPool {
+    build_pool(Path::new(":memory:"), 1).unwrap()
+}
+
+/// Read-only counterpart of [`unused_pool`].
+#[cfg(test)]
+pub(crate) fn unused_readonly_pool() -> ReadonlyDbPool {
+    build_readonly_pool(Path::new(":memory:"), 1, Default::default()).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use enigma_core::types::ProviderType;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::auth::create_token;
+    use crate::routes::build_router;
+    use crate::state::{AppState, RateLimitConfig};
+
+    #[tokio::test]
+    async fn concurrent_requests_share_the_pool() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("manifest.db");
+        let db = ManifestDb::open(&db_path).unwrap();
+        db.insert_provider("local", ProviderType::Local, "/tmp/chunks", None, 1)
+            .unwrap();
+
+        let state = Arc::new(AppState {
+            rate_limit: RateLimitConfig {
+                requests_per_second: 1000.0,
+                burst: 1000,
+                per_ip: false,
+            },
+            ..AppState::for_tests_with_db(tmp.path(), &db_path, 4)
+        });
+        let token = create_token("admin", &state.jwt_secret).unwrap();
+        let app = build_router(state.clone());
+
+        let requests = (0..100).map(|i| {
+            let app = app.clone();
+            let uri = if i % 2 == 0 {
+                "/api/storage/providers"
+            } else {
+                "/api/storage/chunks/stats"
+            };
+            let request = Request::builder()
+                .uri(uri)
+                .header("Authorization", format!("Bearer {token}"))
+                .body(Body::empty())
+                .unwrap();
+            tokio::spawn(app.oneshot(request))
+        });
+        let responses = tokio::time::timeout(
+            std::time::Duration::from_secs(30),
+            futures::future::join_all(requests),
+        )
+        .await
+        .expect("requests starved waiting for a connection");
+
+        for resp in responses {
+            assert_eq!(resp.unwrap().unwrap().status(), StatusCode::OK);
+        }
+        let status = state.readonly_db.status();
+        assert!(status.size <= 4);
+        assert_eq!(status.available, status.size);
+    }
+}
@@ -0,0 +1,253 @@
+use std::net::IpAddr;
+use std::num::NonZeroU32;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+use axum::extract::{Request, State};
+use axum::http::{HeaderValue, StatusCode, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use enigma_auth::middleware::client_ip;
+use governor::clock::{Clock, DefaultClock};
+use governor::{DefaultDirectRateLimiter, DefaultKeyedRateLimiter, Quota, RateLimiter};
+use ipnet::IpNet;
+
+use crate::state::RateLimitConfig;
+
+/// Per-client checks between sweeps of idle client buckets.
+const PRUNE_EVERY: u32 = 1024;
+
+/// Token-bucket limiter built from a `RateLimitConfig`, either shared by all
+/// clients or split per client IP.
+pub struct RateLimit {
+    per_ip: bool,
+    global: DefaultDirectRateLimiter,
+    clients: DefaultKeyedRateLimiter<IpAddr>,
+    /// Per-client checks since the last sweep.
+    checks: AtomicU32,
+    trusted_proxies: Vec<IpNet>,
+}
+
+impl RateLimit {
+    /// `trusted_proxies` are the peers whose `X-Forwarded-For` names the
+    /// client.
+    pub fn new(config: &RateLimitConfig, trusted_proxies: &[IpNet]) -> Self {
+        let period = Duration::from_secs_f64(1.0 / config.requests_per_second.max(f64::EPSILON));
+        let burst = NonZeroU32::new(config.burst.max(1)).unwrap_or(NonZeroU32::MIN);
+        let quota = Quota::with_period(period)
+            .unwrap_or_else(|| Quota::per_second(NonZeroU32::MIN))
+            .allow_burst(burst);
+        Self {
+            per_ip: config.per_ip,
+            global: RateLimiter::direct(quota),
+            clients: RateLimiter::keyed(quota),
+            checks: AtomicU32::new(0),
+            trusted_proxies: trusted_proxies.to_vec(),
+        }
+    }
+
+    /// Consume one cell for `ip`. On rejection returns how long to wait.
+    fn check(&self, ip: Option<IpAddr>) -> Result<(), Duration> {
+        let result = match ip {
+            Some(ip) if self.per_ip => {
+                // Drop the buckets of clients that have been idle long
+                // enough to be full again; they would start full anyway
+                if self.checks.fetch_add(1, Ordering::Relaxed) + 1 >= PRUNE_EVERY {
+                    self.checks.store(0, Ordering::Relaxed);
+                    self.clients.retain_recent();
+                    self.clients.shrink_to_fit();
+                }
+                self.clients.check_key(&ip)
+            }
+            _ => self.global.check(),
+        };
+        result.map_err(|not_until| not_until.wait_time_from(DefaultClock::default().now()))
+    }
+}
+
+pub async fn rate_limit_middleware(
+    State(limiter): State<Arc<RateLimit>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let ip = client_ip(
+        request.headers(),
+        request.extensions(),
+        &limiter.trusted_proxies,
+    );
+    match limiter.check(ip) {
+        Ok(()) => next.run(request).await,
+        Err(wait) => {
+            let retry_after = wait.as_secs_f64().ceil().max(1.0) as u64;
+            let mut resp = (StatusCode::TOO_MANY_REQUESTS, "rate limit exceeded").into_response();
+            resp.headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+            resp
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::body::Body;
+    use axum::extract::ConnectInfo;
+    use axum::routing::get;
+    use std::net::SocketAddr;
+    use tower::ServiceExt;
+
+    use crate::routes::build_router;
+    use crate::state::AppState;
+
+    fn limited_router(per_ip: bool) -> Router {
+        let limiter = Arc::new(RateLimit::new(
+            &RateLimitConfig {
+                requests_per_second: 10.0,
+                burst: 10,
+                per_ip,
+            },
+            &[],
+        ));
+        Router::new().route("/", get(|| async { "ok" })).layer(
+            axum::middleware::from_fn_with_state(limiter, rate_limit_middleware),
+        )
+    }
+
+    /// `request`, with an empty body, coming from the socket peer `ip`.
+    fn from(ip: &str, request: axum::http::request::Builder) -> Request {
+        let mut request = request.body(Body::empty()).unwrap();
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(SocketAddr::new(ip.parse().unwrap(), 40000)));
+        request
+    }
+
+    async fn fire(app: &Router, ip: &str) -> Response {
+        app.clone()
+            .oneshot(from(ip, Request::builder().uri("/")))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn burst_beyond_limit_gets_429() {
+        let app = limited_router(false);
+        let mut statuses = Vec::with_capacity(200);
+        for _ in 0..200 {
+            statuses.push(fire(&app, "10.0.0.1").await.status());
+        }
+        assert!(statuses[..10].iter().all(|s| *s == StatusCode::OK));
+        assert!(
+            statuses[10..]
+                .iter()
+                .all(|s| *s == StatusCode::TOO_MANY_REQUESTS)
+        );
+    }
+
+    #[tokio::test]
+    async fn rejected_response_has_retry_after() {
+        let app = limited_router(false);
+        for _ in 0..10 {
+            fire(&app, "10.0.0.1").await;
+        }
+        let resp = fire(&app, "10.0.0.1").await;
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(resp.headers().contains_key(header::RETRY_AFTER));
+    }
+
+    #[tokio::test]
+    async fn per_ip_buckets_are_independent() {
+        let app = limited_router(true);
+        for _ in 0..10 {
+            assert_eq!(fire(&app, "10.0.0.1").await.status(), StatusCode::OK);
+        }
+        assert_eq!(
+            fire(&app, "10.0.0.1").await.status(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+        assert_eq!(fire(&app, "10.0.0.2").await.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn idle_client_buckets_are_dropped() {
+        let limiter = RateLimit::new(
+            &RateLimitConfig {
+                requests_per_second: 1000.0,
+                burst: 1,
+                per_ip: true,
+            },
+            &[],
+        );
+        for i in 0..10 {
+            let _ = limiter.check(Some(IpAddr::from([10, 0, 0, i])));
+        }
+        assert_eq!(limiter.clients.len(), 10);
+
+        // Full again after a few milliseconds
+        std::thread::sleep(Duration::from_millis(20));
+        let busy = Some(IpAddr::from([10, 0, 1, 1]));
+        for _ in 0..PRUNE_EVERY {
+            let _ = limiter.check(busy);
+        }
+        assert_eq!(limiter.clients.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn login_limit_applies_per_client_behind_trusted_proxies() {
+        let state = Arc::new(AppState {
+            login_rate_limit: RateLimitConfig {
+                requests_per_second: 0.01,
+                burst: 2,
+                per_ip: true,
+            },
+            trusted_proxies: vec!["10.0.0.0/8".parse().unwrap()],
+            ..AppState::for_tests()
+        });
+        let app = build_router(state);
+        let login = |peer: &str, forwarded_for: &str| {
+            let request = Request::builder()
+                .method("POST")
+                .uri("/api/auth/login")
+                .header(header::CONTENT_TYPE, "application/json")
+                .header("X-Forwarded-For", forwarded_for);
+            let mut request = from(peer, request);
+            *request.body_mut() = Body::from(r#"{"username":"admin","password":"wrong"}"#);
+            let app = app.clone();
+            async move { app.oneshot(request).await.unwrap().status() }
+        };
+
+        // Through the proxy at 10.0.0.1
+        assert_eq!(
+            login("10.0.0.1", "203.0.113.9").await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            login("10.0.0.1", "203.0.113.9").await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            login("10.0.0.1", "203.0.113.9").await,
+            StatusCode::TOO_MANY_REQUESTS
+        );
+        assert_eq!(
+            login("10.0.0.1", "203.0.113.10").await,
+            StatusCode::UNAUTHORIZED
+        );
+
+        // A direct client cannot pick a fresh bucket with the header
+        assert_eq!(
+            login("198.51.100.7", "192.0.2.1").await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            login("198.51.100.7", "192.0.2.2").await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            login("198.51.100.7", "192.0.2.3").await,
+            StatusCode::TOO_MANY_REQUESTS
+        );
+    }
+}
@@ -0,0 +1,546 @@
+use std::sync::Arc;
+
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json};
+use enigma_auth::AuthError;
+use enigma_core::manifest::CheckpointMode;
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::auth::Claims;
+use crate::correlation::CorrelationId;
+use crate::models::{
+    AuditPurgeResponse, AuditStatsResponse, CheckpointResponse, ImpersonateResponse,
+};
+use crate::state::AppState;
+
+/// Longest an impersonation token may be valid for.
+const MAX_IMPERSONATION_SECONDS: u32 = 3600;
+
+#[derive(Deserialize, IntoParams)]
+pub struct CheckpointQuery {
+    /// "passive", "full" (default) or "truncate".
+    pub mode: Option<String>,
+}
+
+/// POST /api/admin/db/checkpoint
+///
+/// Checkpoint the manifest's write-ahead log now instead of waiting for
+/// the proxy's scheduled one.
+#[utoipa::path(
+    post,
+    path = "/api/admin/db/checkpoint",
+    tag = "admin",
+    params(CheckpointQuery),
+    responses(
+        (status = 200, description = "WAL checkpointed", body = CheckpointResponse),
+        (status = 400, description = "Unknown checkpoint mode"),
+        (status = 401, description = "Missing or invalid credentials"),
+    )
+)]
+pub async fn checkpoint_db(
+    State(state): State<Arc<AppState>>,
+    Query(q): Query<CheckpointQuery>,
+) -> Result<Json<CheckpointResponse>, (StatusCode, &'static str)> {
+    let mode = match q.mode.as_deref().unwrap_or("full") {
+        "passive" => CheckpointMode::Passive,
+        "full" => CheckpointMode::Full,
+        "truncate" => CheckpointMode::Truncate,
+        _ => return Err((StatusCode::BAD_REQUEST, "unknown checkpoint mode")),
+    };
+    let db = state
+        .db
+        .get()
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "internal error"))?;
+    let (wal_frames, checkpointed_frames) = db
+        .checkpoint(mode)
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "internal error"))?;
+    tracing::info!("Manifest WAL checkpoint ({mode:?}): {checkpointed_frames}/{wal_frames} frames");
+    Ok(Json(CheckpointResponse {
+        wal_frames,
+        checkpointed_frames,
+    }))
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ImpersonateRequest {
+    /// ID of the user to act as.
+    pub user_id: String,
+    /// Token lifetime, at most 3600 seconds.
+    pub duration_seconds: u32,
+}
+
+/// POST /api/admin/impersonate
+///
+/// Issue a short-lived token that acts as another user, to see what they
+/// can access. Requires `admin:impersonate`; the configured admin account
+/// holds every permission. The token carries an `impersonated_by` claim and
+/// every call made with it is audited.
+#[utoipa::path(
+    post,
+    path = "/api/admin/impersonate",
+    tag = "admin",
+    request_body = ImpersonateRequest,
+    responses(
+        (status = 200, description = "Impersonation token issued", body = ImpersonateResponse),
+        (status = 400, description = "Duration out of range"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Missing admin:impersonate, or already impersonating"),
+        (status = 404, description = "No such user"),
+    )
+)]
+pub async fn impersonate(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    correlation_id: CorrelationId,
+    Json(req): Json<ImpersonateRequest>,
+) -> Result<Json<ImpersonateResponse>, Response> {
+    if claims.impersonated_by.is_some() {
+        return Err(AuthError::Forbidden("already impersonating".into()).into_response());
+    }
+    if !(1..=MAX_IMPERSONATION_SECONDS).contains(&req.duration_seconds) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "duration_seconds must be between 1 and 3600",
+        )
+            .into_response());
+    }
+
+    let store = &state.auth_store;
+    let admin = match store.get_user_by_username(&claims.sub).await {
+        Ok(admin) => admin,
+        Err(AuthError::NotFound(_)) => {
+            return Err(AuthError::Forbidden("unknown account".into()).into_response());
+        }
+        Err(e) => return Err(e.into_response()),
+    };
+    if claims.sub != state.admin_user {
+        let permissions = store
+            .get_user_permissions(&admin.id)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        if !enigma_auth::has_permission(&permissions, "admin:impersonate") {
+            return Err(
+                AuthError::Forbidden("missing permission: admin:impersonate".into())
+                    .into_response(),
+            );
+        }
+    }
+
+    let target = store
+        .get_user_by_id(&req.user_id)
+        .await
+        .map_err(IntoResponse::into_response)?;
+    let permissions = store
+        .get_user_permissions(&target.id)
+        .await
+        .map_err(IntoResponse::into_response)?;
+    let groups = store
+        .list_user_groups(&target.id)
+        .await
+        .map_err(IntoResponse::into_response)?
+        .into_iter()
+        .map(|g| g.name)
+        .collect();
+
+    let impersonation = store
+        .create_impersonation(&admin.id, &target.id, req.duration_seconds)
+        .await
+        .map_err(IntoResponse::into_response)?;
+    let token = enigma_auth::create_impersonation_jwt(
+        &target.id,
+        &target.username,
+        groups,
+        permissions,
+        &admin.id,
+        &impersonation.id,
+        req.duration_seconds,
+        &state.jwt_secret,
+    )
+    .map_err(IntoResponse::into_response)?;
+    store
+        .log_audit(
+            Some(&admin.id),
+            "user.impersonate",
+            Some(&target.id),
+            None,
+            &correlation_id.0,
+        )
+        .await
+        .map_err(IntoResponse::into_response)?;
+    tracing::info!(
+        admin = %admin.username,
+        target = %target.username,
+        seconds = req.duration_seconds,
+        "impersonation token issued"
+    );
+
+    Ok(Json(ImpersonateResponse {
+        token,
+        expires_in: u64::from(req.duration_seconds),
+        impersonation_id: impersonation.id,
+    }))
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct AuditPurgeRequest {
+    /// Delete entries older than this many days; at least 1.
+    pub older_than_days: u32,
+}
+
+/// POST /api/admin/audit/purge
+///
+/// Delete old auth audit entries now instead of waiting for the nightly
+/// purge. Admin account only; the purge is itself audited.
+#[utoipa::path(
+    post,
+    path = "/api/admin/audit/purge",
+    tag = "admin",
+    request_body = AuditPurgeRequest,
+    responses(
+        (status = 200, description = "Old entries deleted", body = AuditPurgeResponse),
+        (status = 400, description = "older_than_days is 0"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Not the admin account"),
+    )
+)]
+pub async fn purge_audit(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    correlation_id: CorrelationId,
+    Json(req): Json<AuditPurgeRequest>,
+) -> Result<Json<AuditPurgeResponse>, Response> {
+    if claims.sub != state.admin_user {
+        return Err(AuthError::Forbidden("admin only".into()).into_response());
+    }
+    if req.older_than_days == 0 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "older_than_days must be at least 1",
+        )
+            .into_response());
+    }
+    let deleted = state
+        .auth_store
+        .purge_old_audit_entries(req.older_than_days)
+        .await
+        .map_err(IntoResponse::into_response)?;
+    state
+        .auth_store
+        .log_audit(
+            None,
+            "audit.purge",
+            Some(&req.older_than_days.to_string()),
+            None,
+            &correlation_id.0,
+        )
+        .await
+        .map_err(IntoResponse::into_response)?;
+    tracing::info!(
+        deleted,
+        older_than_days = req.older_than_days,
+        "Purged old auth audit entries"
+    );
+    Ok(Json(AuditPurgeResponse { deleted }))
+}
+
+/// GET /api/admin/audit/stats
+///
+/// Size of the auth audit log. Admin account only.
+#[utoipa::path(
+    get,
+    path = "/api/admin/audit/stats",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Audit log size", body = AuditStatsResponse),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Not the admin account"),
+    )
+)]
+pub async fn get_audit_stats(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<AuditStatsResponse>, Response> {
+    if claims.sub != state.admin_user {
+        return Err(AuthError::Forbidden("admin only".into()).into_response());
+    }
+    let stats = state
+        .auth_store
+        .audit_stats()
+        .await
+        .map_err(IntoResponse::into_response)?;
+    Ok(Json(AuditStatsResponse {
+        total_entries: stats.total_entries,
+        oldest_entry_date: stats.oldest_entry_date,
+        estimated_size_bytes: stats.estimated_size_bytes,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::Request;
+    use enigma_core::manifest::ManifestDb;
+    use tower::ServiceExt;
+
+    use crate::auth::create_token;
+    use crate::routes::build_router;
+
+    use super::*;
+
+    fn app_state(dir: &std::path::Path, db_path: &std::path::Path) -> Arc<AppState> {
+        Arc::new(AppState::for_tests_with_db(dir, db_path, 1))
+    }
+
+    #[tokio::test]
+    async fn checkpoint_truncates_the_wal() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("manifest.db");
+        let db = ManifestDb::open(&db_path).unwrap();
+        db.create_namespace("bucket").unwrap();
+
+        let state = app_state(tmp.path(), &db_path);
+        let token = create_token("admin", &state.jwt_secret).unwrap();
+        let post = |uri: &str| {
+            let request = crate::csrf::with_csrf(Request::builder(), &token, &state.jwt_secret)
+                .method("POST")
+                .uri(uri)
+                .header("Authorization", format!("Bearer {token}"))
+                .body(Body::empty())
+                .unwrap();
+            build_router(state.clone()).oneshot(request)
+        };
+
+        let resp = post("/api/admin/db/checkpoint?mode=sometimes")
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let resp = post("/api/admin/db/checkpoint").await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let full: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(full["wal_frames"].as_u64().unwrap() > 0);
+        assert_eq!(full["checkpointed_frames"], full["wal_frames"]);
+
+        let resp = post("/api/admin/db/checkpoint?mode=truncate")
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let wal = std::fs::metadata(tmp.path().join("manifest.db-wal")).unwrap();
+        assert_eq!(wal.len(), 0);
+        assert_eq!(db.list_namespaces().unwrap().len(), 1);
+    }
+
+    /// POST `body` as JSON to `uri` with `token`.
+    async fn post_json(
+        state: &Arc<AppState>,
+        uri: &str,
+        token: &str,
+        body: serde_json::Value,
+    ) -> (StatusCode, serde_json::Value) {
+        let request = crate::csrf::with_csrf(Request::builder(), token, &state.jwt_secret)
+            .method("POST")
+            .uri(uri)
+            .header("Authorization", format!("Bearer {token}"))
+            .header("Content-Type", "application/json")
+            .header("X-Request-ID", "imp-req")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let resp = build_router(state.clone()).oneshot(request).await.unwrap();
+        let status = resp.status();
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or_default())
+    }
+
+    #[tokio::test]
+    async fn impersonation_token_acts_as_the_user_and_is_audited() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("manifest.db");
+        ManifestDb::open(&db_path).unwrap();
+        let state = app_state(tmp.path(), &db_path);
+        let store = state.auth_store.clone();
+        store.migrate().await.unwrap();
+        store.seed_defaults().await.unwrap();
+        let password = enigma_auth::NewPassword::disabled();
+        let admin = store.create_user("admin", &password, None).await.unwrap();
+        let bob = store.create_user("bob", &password, None).await.unwrap();
+        let read = store.get_group_by_name("read").await.unwrap();
+        store.add_user_group(&bob.id, &read.id).await.unwrap();
+        store.create_user("carol", &password, None).await.unwrap();
+
+        let admin_token = create_token("admin", &state.jwt_secret).unwrap();
+        let (status, _) = post_json(
+            &state,
+            "/api/admin/impersonate",
+            &admin_token,
+            serde_json::json!({ "user_id": bob.id, "duration_seconds": 7200 }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        // Without admin:impersonate
+        let carol_token = create_token("carol", &state.jwt_secret).unwrap();
+        let (status, _) = post_json(
+            &state,
+            "/api/admin/impersonate",
+            &carol_token,
+            serde_json::json!({ "user_id": bob.id, "duration_seconds": 600 }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let (status, body) = post_json(
+            &state,
+            "/api/admin/impersonate",
+            &admin_token,
+            serde_json::json!({ "user_id": bob.id, "duration_seconds": 600 }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["expires_in"], 600);
+        let token = body["token"].as_str().unwrap().to_string();
+        let claims = enigma_auth::verify_jwt(&token, &state.jwt_secret).unwrap();
+        assert_eq!(claims.sub, bob.id);
+        assert_eq!(claims.impersonated_by.as_deref(), Some(admin.id.as_str()));
+        assert!(claims.permissions.contains(&"buckets:read".to_string()));
+
+        let active = store.list_active_impersonations().await.unwrap();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].id, body["impersonation_id"].as_str().unwrap());
+        assert_eq!(
+            (
+                active[0].admin_user_id.as_str(),
+                active[0].target_user_id.as_str()
+            ),
+            (admin.id.as_str(), bob.id.as_str())
+        );
+
+        // The token works, but cannot be used to impersonate again
+        let (status, _) = post_json(
+            &state,
+            "/api/admin/db/checkpoint",
+            &token,
+            serde_json::json!({}),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = post_json(
+            &state,
+            "/api/admin/impersonate",
+            &token,
+            serde_json::json!({ "user_id": admin.id, "duration_seconds": 600 }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let audit = store.list_audit(100, 0).await.unwrap();
+        let issued: Vec<_> = audit
+            .iter()
+            .filter(|e| e.action == "user.impersonate")
+            .collect();
+        assert_eq!(issued.len(), 1);
+        assert_eq!(issued[0].user_id.as_deref(), Some(admin.id.as_str()));
+        assert_eq!(issued[0].target.as_deref(), Some(bob.id.as_str()));
+
+        let mut calls: Vec<(&str, Option<&str>, Option<&str>, &str)> = audit
+            .iter()
+            .filter(|e| e.action == "api.request")
+            .map(|e| {
+                (
+                    e.target.as_deref().unwrap_or(""),
+                    e.user_id.as_deref(),
+                    e.impersonated_by.as_deref(),
+                    e.correlation_id.as_str(),
+                )
+            })
+            .collect();
+        calls.sort();
+        assert_eq!(
+            calls,
+            vec![
+                (
+                    "POST /api/admin/db/checkpoint",
+                    Some(bob.id.as_str()),
+                    Some(admin.id.as_str()),
+                    "imp-req"
+                ),
+                (
+                    "POST /api/admin/impersonate",
+                    Some(bob.id.as_str()),
+                    Some(admin.id.as_str()),
+                    "imp-req"
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn audit_purge_keeps_recent_entries_and_reports_stats() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("manifest.db");
+        ManifestDb::open(&db_path).unwrap();
+        let state = app_state(tmp.path(), &db_path);
+        let store = state.auth_store.clone();
+        store.migrate().await.unwrap();
+        store
+            .log_audit(None, "user.login", Some("bob"), None, "req-1")
+            .await
+            .unwrap();
+
+        let admin_token = create_token("admin", &state.jwt_secret).unwrap();
+        let carol_token = create_token("carol", &state.jwt_secret).unwrap();
+        let purge = serde_json::json!({ "older_than_days": 30 });
+        let (status, _) = post_json(
+            &state,
+            "/api/admin/audit/purge",
+            &carol_token,
+            purge.clone(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = post_json(
+            &state,
+            "/api/admin/audit/purge",
+            &admin_token,
+            serde_json::json!({ "older_than_days": 0 }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, body) = post_json(&state, "/api/admin/audit/purge", &admin_token, purge).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, serde_json::json!({ "deleted": 0 }));
+
+        let request = Request::builder()
+            .uri("/api/admin/audit/stats")
+            .header("Authorization", format!("Bearer {admin_token}"))
+            .body(Body::empty())
+            .unwrap();
+        let resp = build_router(state.clone()).oneshot(request).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let stats: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        // The login, and the purge itself
+        assert_eq!(stats["total_entries"], 2);
+        assert!(stats["oldest_entry_date"].is_string());
+        assert!(stats["estimated_size_bytes"].as_u64().unwrap() > 0);
+        let actions: Vec<String> = store
+            .list_audit(10, 0)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|e| e.action)
+            .collect();
+        assert_eq!(actions, vec!["audit.purge", "user.login"]);
+    }
+}
@@ -30,6 +30,7 @@ pub async fn list_audit(
                 action: e.action,
                 target: e.target,
                 ip_addr: e.ip_addr,
+                correlation_id: e.correlation_id,
                 created_at: e.created_at,
             })
             .collect(),
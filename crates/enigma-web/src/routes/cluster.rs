@@ -1,7 +1,24 @@
+use std::sync::Arc;
+
 use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use serde::Deserialize;
+use utoipa::ToSchema;
 
+use crate::cluster_handle::PeerHealth;
 use crate::models::ClusterResponse;
+use crate::state::AppState;
 
+#[utoipa::path(
+    get,
+    path = "/api/cluster",
+    tag = "cluster",
+    responses(
+        (status = 200, description = "Cluster topology", body = ClusterResponse),
+        (status = 401, description = "Missing or invalid credentials"),
+    )
+)]
 pub async fn get_cluster() -> Json<ClusterResponse> {
     Json(ClusterResponse {
         mode: "single-node".to_string(),
@@ -9,3 +26,213 @@ pub async fn get_cluster() -> Json<ClusterResponse> {
         peers: vec![],
     })
 }
+
+/// GET /api/cluster/peers
+///
+/// Replication state and health-check round trip of each Raft peer. Empty
+/// in single-node mode.
+#[utoipa::path(
+    get,
+    path = "/api/cluster/peers",
+    tag = "cluster",
+    responses(
+        (status = 200, description = "Peer health", body = Vec<PeerHealth>),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 500, description = "Cluster state unavailable"),
+    )
+)]
+pub async fn get_peers(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<PeerHealth>>, (StatusCode, &'static str)> {
+    let Some(cluster) = &state.cluster else {
+        return Ok(Json(Vec::new()));
+    };
+    let peers = cluster.peer_health().await.map_err(|e| {
+        tracing::warn!("Failed to gather peer health: {e}");
+        (StatusCode::INTERNAL_SERVER_ERROR, "internal error")
+    })?;
+    Ok(Json(peers))
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct AddLearnerRequest {
+    /// Raft node ID of the new learner.
+    pub node_id: u64,
+    /// Its Raft gRPC address.
+    pub addr: String,
+}
+
+/// POST /api/cluster/learners
+///
+/// Add a learner: a node that replicates the manifest and serves S3 reads
+/// but never votes, for read scale-out without changing the quorum. It
+/// answers S3 writes with 503.
+#[utoipa::path(
+    post,
+    path = "/api/cluster/learners",
+    tag = "cluster",
+    request_body = AddLearnerRequest,
+    responses(
+        (status = 204, description = "Learner added and caught up"),
+        (status = 400, description = "Not running in cluster mode"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 500, description = "The learner could not be added"),
+    )
+)]
+pub async fn add_learner(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<AddLearnerRequest>,
+) -> Result<StatusCode, (StatusCode, &'static str)> {
+    let Some(cluster) = &state.cluster else {
+        return Err((StatusCode::BAD_REQUEST, "not running in cluster mode"));
+    };
+    cluster
+        .add_learner(req.node_id, req.addr.clone())
+        .await
+        .map_err(|e| {
+            tracing::warn!("Failed to add learner {} at {}: {e}", req.node_id, req.addr);
+            (StatusCode::INTERNAL_SERVER_ERROR, "internal error")
+        })?;
+    tracing::info!("Added Raft learner {} at {}", req.node_id, req.addr);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    use crate::auth::create_token;
+    use crate::cluster_handle::ClusterHandle;
+    use crate::routes::build_router;
+
+    use super::*;
+
+    /// Two-peer cluster where node 3 does not answer health checks.
+    /// Records the learners added to it.
+    #[derive(Default)]
+    struct MockCluster {
+        learners: std::sync::Mutex<Vec<(u64, String)>>,
+    }
+
+    #[async_trait::async_trait]
+    impl ClusterHandle for MockCluster {
+        async fn metrics(&self) -> serde_json::Value {
+            serde_json::json!({ "mode": "raft", "node_id": 1 })
+        }
+
+        async fn add_node(&self, _node_id: u64, _addr: String) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn add_learner(&self, node_id: u64, addr: String) -> anyhow::Result<()> {
+            self.learners.lock().unwrap().push((node_id, addr));
+            Ok(())
+        }
+
+        async fn remove_node(&self, _node_id: u64) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn trigger_snapshot(&self) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn peer_health(&self) -> anyhow::Result<Vec<PeerHealth>> {
+            Ok(vec![
+                PeerHealth {
+                    node_id: 2,
+                    addr: "10.0.0.2:9100".to_string(),
+                    state: "follower".to_string(),
+                    last_log_index: 42,
+                    last_contact_ms: Some(3),
+                },
+                PeerHealth {
+                    node_id: 3,
+                    addr: "10.0.0.3:9100".to_string(),
+                    state: "learner".to_string(),
+                    last_log_index: 17,
+                    last_contact_ms: None,
+                },
+            ])
+        }
+    }
+
+    fn app_state(cluster: Option<Arc<dyn ClusterHandle>>) -> Arc<AppState> {
+        Arc::new(AppState {
+            cluster,
+            ..AppState::for_tests()
+        })
+    }
+
+    async fn get_peers_json(state: Arc<AppState>) -> (StatusCode, serde_json::Value) {
+        let token = create_token("admin", &state.jwt_secret).unwrap();
+        let request = Request::builder()
+            .uri("/api/cluster/peers")
+            .header("Authorization", format!("Bearer {token}"))
+            .body(Body::empty())
+            .unwrap();
+        let resp = build_router(state).oneshot(request).await.unwrap();
+        let status = resp.status();
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn peers_come_from_the_cluster_handle() {
+        let (status, body) =
+            get_peers_json(app_state(Some(Arc::new(MockCluster::default())))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body,
+            serde_json::json!([
+                {
+                    "node_id": 2,
+                    "addr": "10.0.0.2:9100",
+                    "state": "follower",
+                    "last_log_index": 42,
+                    "last_contact_ms": 3,
+                },
+                {
+                    "node_id": 3,
+                    "addr": "10.0.0.3:9100",
+                    "state": "learner",
+                    "last_log_index": 17,
+                    "last_contact_ms": null,
+                },
+            ])
+        );
+
+        let (status, body) = get_peers_json(app_state(None)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, serde_json::json!([]));
+    }
+
+    async fn post_learner(state: Arc<AppState>) -> StatusCode {
+        let token = create_token("admin", &state.jwt_secret).unwrap();
+        let request = crate::csrf::with_csrf(Request::builder(), &token, &state.jwt_secret)
+            .method("POST")
+            .uri("/api/cluster/learners")
+            .header("Authorization", format!("Bearer {token}"))
+            .header("Content-Type", "application/json")
+            .body(Body::from(r#"{"node_id":4,"addr":"10.0.1.4:9100"}"#))
+            .unwrap();
+        build_router(state).oneshot(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn learners_are_added_through_the_cluster_handle() {
+        let cluster = Arc::new(MockCluster::default());
+        let status = post_learner(app_state(Some(cluster.clone() as Arc<dyn ClusterHandle>))).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        assert_eq!(
+            *cluster.learners.lock().unwrap(),
+            vec![(4, "10.0.1.4:9100".to_string())]
+        );
+
+        assert_eq!(post_learner(app_state(None)).await, StatusCode::BAD_REQUEST);
+    }
+}
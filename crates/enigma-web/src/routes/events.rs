@@ -0,0 +1,156 @@
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::{Query, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::IntoResponse;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use tokio::sync::broadcast::Receiver;
+use tokio::sync::broadcast::error::RecvError;
+
+use enigma_core::events::BackupEvent;
+
+use crate::state::AppState;
+use crate::stream_ticket::{StreamQuery, stream_authorized};
+
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// GET /api/events/backup — live backup progress as Server-Sent Events.
+///
+/// Each `BackupEvent` is sent as a `backup_progress` event with a JSON
+/// payload; a `ping` event every 15 seconds keeps proxies from closing an
+/// idle stream. `EventSource` cannot set headers, so a stream ticket may
+/// be passed as `?ticket=` instead.
+pub async fn backup_events(
+    State(state): State<Arc<AppState>>,
+    Query(q): Query<StreamQuery>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, StatusCode> {
+    if !stream_authorized(&state, &headers, &q) {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    // A client that disconnects drops the stream, and with it the receiver
+    let stream = futures::stream::unfold(state.events.subscribe(), next_event);
+    let sse = Sse::new(stream).keep_alive(
+        KeepAlive::new()
+            .interval(KEEPALIVE_INTERVAL)
+            .event(Event::default().event("ping")),
+    );
+    Ok(([(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")], sse))
+}
+
+/// Wait for the next backup event; `None` ends the stream once the event
+/// channel closes (server shutting down).
+async fn next_event(
+    mut events: Receiver<BackupEvent>,
+) -> Option<(Result<Event, Infallible>, Receiver<BackupEvent>)> {
+    loop {
+        match events.recv().await {
+            Ok(event) => match Event::default().event("backup_progress").json_data(&event) {
+                Ok(sse) => return Some((Ok(sse), events)),
+                Err(e) => tracing::warn!("Cannot serialize backup event: {e}"),
+            },
+            Err(RecvError::Lagged(skipped)) => {
+                tracing::warn!("SSE client lagging, skipped {skipped} backup events");
+            }
+            Err(RecvError::Closed) => return None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use enigma_core::events::{BackupEvents, BackupPhase, BackupProgress};
+
+    use super::*;
+    use crate::auth::create_token;
+    use crate::routes::build_router;
+
+    /// Serve the web router on a random port publishing `events`.
+    async fn serve(events: BackupEvents) -> (SocketAddr, Arc<AppState>) {
+        let state = Arc::new(AppState {
+            events,
+            ..AppState::for_tests()
+        });
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = build_router(state.clone()).into_make_service_with_connect_info::<SocketAddr>();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (addr, state)
+    }
+
+    /// Read the stream until a `backup_progress` event arrives.
+    async fn next_progress(resp: &mut reqwest::Response, buffer: &mut String) -> BackupEvent {
+        loop {
+            if let Some(end) = buffer.find("\n\n") {
+                let frame: String = buffer.drain(..end + 2).collect();
+                if frame.lines().any(|l| l == "event: backup_progress") {
+                    let data = frame
+                        .lines()
+                        .find_map(|l| l.strip_prefix("data: "))
+                        .unwrap();
+                    return serde_json::from_str(data).unwrap();
+                }
+                continue;
+            }
+            let chunk = tokio::time::timeout(Duration::from_secs(5), resp.chunk())
+                .await
+                .expect("timed out waiting for backup events")
+                .unwrap()
+                .expect("stream ended");
+            buffer.push_str(std::str::from_utf8(&chunk).unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn streams_backup_progress() {
+        let events = BackupEvents::default();
+        let (addr, state) = serve(events.clone()).await;
+        let ticket = state.stream_tickets.issue();
+
+        let mut resp = reqwest::get(format!("http://{addr}/api/events/backup?ticket={ticket}"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()[header::CONTENT_TYPE], "text/event-stream");
+        assert_eq!(resp.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+
+        // Subscribed once the response headers are back
+        let mut progress = BackupProgress::new("nightly", 2, 100);
+        events.publish(progress.event(BackupPhase::Uploading));
+        progress.chunk_done(100, false);
+        events.publish(progress.event(BackupPhase::Done));
+
+        let mut buffer = String::new();
+        let first = next_progress(&mut resp, &mut buffer).await;
+        assert_eq!(first.backup_id, "nightly");
+        assert_eq!(first.phase, BackupPhase::Uploading);
+        let done = next_progress(&mut resp, &mut buffer).await;
+        assert_eq!(done.phase, BackupPhase::Done);
+        assert_eq!(done.bytes_done, 100);
+    }
+
+    #[tokio::test]
+    async fn rejects_missing_invalid_or_used_ticket() {
+        let (addr, state) = serve(BackupEvents::default()).await;
+        let used = state.stream_tickets.issue();
+        assert!(state.stream_tickets.redeem(&used));
+        // A session JWT is only taken from the Authorization header
+        let jwt = create_token("admin", &state.jwt_secret).unwrap();
+
+        for url in [
+            format!("http://{addr}/api/events/backup"),
+            format!("http://{addr}/api/events/backup?ticket=not-a-ticket"),
+            format!("http://{addr}/api/events/backup?ticket={used}"),
+            format!("http://{addr}/api/events/backup?token={jwt}"),
+        ] {
+            let resp = reqwest::get(url).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        }
+    }
+}
@@ -7,6 +7,7 @@ use axum::http::{HeaderMap, HeaderValue, StatusCode, header};
 use axum::response::{IntoResponse, Response};
 use axum::Json;
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 
 use enigma_auth::middleware::require_permission;
 use enigma_auth::AuthUser;
@@ -15,26 +16,26 @@ use crate::state::AppState;
 
 // ── Types ────────────────────────────────────────────────────
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
 pub struct BrowseQuery {
     #[serde(default)]
     pub path: String,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct BrowseResponse {
     pub path: String,
     pub folders: Vec<FolderItem>,
     pub files: Vec<FileItem>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct FolderItem {
     pub name: String,
     pub path: String,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct FileItem {
     pub name: String,
     pub key: String,
@@ -43,12 +44,12 @@ pub struct FileItem {
     pub created_at: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct MkdirRequest {
     pub path: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
 pub struct DeleteQuery {
     pub path: String,
 }
@@ -78,10 +79,10 @@ impl IntoResponse for FilesError {
     }
 }
 
-fn get_s3_and_bucket(state: &AppState) -> Result<(&enigma_s3::EnigmaS3State, String), FilesError> {
+fn get_s3_and_bucket(state: &AppState) -> Result<(enigma_s3::SharedState, String), FilesError> {
     let s3 = state
         .s3_state
-        .as_deref()
+        .clone()
         .ok_or_else(|| FilesError::Internal("file storage not configured".into()))?;
     let bucket = {
         let db = s3
@@ -101,6 +102,17 @@ fn get_s3_and_bucket(state: &AppState) -> Result<(&enigma_s3::EnigmaS3State, Str
 // ── Handlers ─────────────────────────────────────────────────
 
 /// GET /api/files?path=folder/
+#[utoipa::path(
+    get,
+    path = "/api/files",
+    tag = "files",
+    params(BrowseQuery),
+    responses(
+        (status = 200, description = "Folder listing", body = BrowseResponse),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Missing buckets:read permission"),
+    )
+)]
 pub async fn browse(
     auth_user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -110,7 +122,7 @@ pub async fn browse(
     tracing::info!(user = %auth_user.username, path = %q.path, "browsing files");
 
     let (s3, bucket) = get_s3_and_bucket(&state)?;
-    let listing = enigma_s3::ops::list_folder(s3, &bucket, &q.path)
+    let listing = enigma_s3::ops::list_folder(&s3, &bucket, &q.path)
         .await
         .map_err(|e| {
             tracing::error!(user = %auth_user.username, path = %q.path, error = %e, "browse failed");
@@ -150,6 +162,17 @@ pub async fn browse(
 }
 
 /// POST /api/files/upload  (multipart: path + file)
+#[utoipa::path(
+    post,
+    path = "/api/files/upload",
+    tag = "files",
+    request_body(content_type = "multipart/form-data", description = "`path` prefix and `file` fields"),
+    responses(
+        (status = 200, description = "Stored object key, size and etag", body = serde_json::Value),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Missing buckets:write permission"),
+    )
+)]
 pub async fn upload(
     auth_user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -235,7 +258,7 @@ pub async fn upload(
         "storing object to cloud providers"
     );
 
-    let etag = enigma_s3::ops::store_object(s3, &bucket, &key, &file_data, content_type.as_deref())
+    let etag = enigma_s3::ops::store_object(&s3, &bucket, &key, &file_data, content_type.as_deref())
         .await
         .map_err(|e| {
             tracing::error!(
@@ -275,6 +298,17 @@ pub async fn upload(
 }
 
 /// GET /api/files/download?path=folder/file.txt
+#[utoipa::path(
+    get,
+    path = "/api/files/download",
+    tag = "files",
+    params(BrowseQuery),
+    responses(
+        (status = 200, description = "File contents", content_type = "application/octet-stream"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Missing buckets:read permission"),
+    )
+)]
 pub async fn download(
     auth_user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -285,7 +319,7 @@ pub async fn download(
     tracing::info!(user = %auth_user.username, path = %q.path, "download started");
 
     let (s3, bucket) = get_s3_and_bucket(&state)?;
-    let file = enigma_s3::ops::retrieve_object(s3, &bucket, &q.path)
+    let file = enigma_s3::ops::retrieve_object(&s3, &bucket, &q.path)
         .await
         .map_err(|e| {
             tracing::error!(user = %auth_user.username, path = %q.path, error = %e, "download failed");
@@ -297,7 +331,7 @@ pub async fn download(
         path = %q.path,
         size_bytes = file.size,
         elapsed_ms = started.elapsed().as_millis() as u64,
-        "download complete"
+        "download streaming"
     );
 
     let filename = q.path.rsplit('/').next().unwrap_or(&q.path);
@@ -319,10 +353,21 @@ pub async fn download(
             .unwrap_or_else(|_| HeaderValue::from_static("0")),
     );
 
-    Ok((headers, Body::from(file.data)).into_response())
+    Ok((headers, Body::from_stream(file.reader)).into_response())
 }
 
 /// DELETE /api/files?path=folder/file.txt
+#[utoipa::path(
+    delete,
+    path = "/api/files",
+    tag = "files",
+    params(DeleteQuery),
+    responses(
+        (status = 200, description = "Deleted key", body = serde_json::Value),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Missing buckets:write permission"),
+    )
+)]
 pub async fn delete(
     auth_user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -332,7 +377,7 @@ pub async fn delete(
     tracing::info!(user = %auth_user.username, path = %q.path, "deleting file");
 
     let (s3, bucket) = get_s3_and_bucket(&state)?;
-    enigma_s3::ops::remove_object(s3, &bucket, &q.path)
+    enigma_s3::ops::remove_object(&s3, &bucket, &q.path)
         .await
         .map_err(|e| {
             tracing::error!(user = %auth_user.username, path = %q.path, error = %e, "delete failed");
@@ -344,6 +389,17 @@ pub async fn delete(
 }
 
 /// POST /api/files/mkdir  { "path": "folder/subfolder/" }
+#[utoipa::path(
+    post,
+    path = "/api/files/mkdir",
+    tag = "files",
+    request_body = MkdirRequest,
+    responses(
+        (status = 200, description = "Created folder marker", body = serde_json::Value),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Missing buckets:write permission"),
+    )
+)]
 pub async fn mkdir(
     auth_user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -361,7 +417,7 @@ pub async fn mkdir(
     };
 
     tracing::info!(user = %auth_user.username, path = %path, "creating folder");
-    enigma_s3::ops::store_object(s3, &bucket, &path, &[], None)
+    enigma_s3::ops::store_object(&s3, &bucket, &path, &[], None)
         .await
         .map_err(|e| {
             tracing::error!(user = %auth_user.username, path = %path, error = %e, "mkdir failed");
@@ -3,10 +3,11 @@ use std::sync::Arc;
 use axum::Json;
 use axum::extract::{Path, State};
 
-use enigma_auth::middleware::require_permission;
 use enigma_auth::AuthUser;
 use enigma_auth::error::AuthError;
+use enigma_auth::middleware::require_permission;
 
+use crate::correlation::CorrelationId;
 use crate::models::{GroupResponse, PermissionResponse};
 use crate::state::AppState;
 
@@ -32,6 +33,17 @@ async fn group_to_response(
     })
 }
 
+/// GET /api/groups
+#[utoipa::path(
+    get,
+    path = "/api/groups",
+    tag = "groups",
+    responses(
+        (status = 200, description = "All groups", body = Vec<GroupResponse>),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Missing groups:read permission"),
+    )
+)]
 pub async fn list_groups(
     auth_user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -46,6 +58,19 @@ pub async fn list_groups(
     Ok(Json(result))
 }
 
+/// GET /api/groups/{id}
+#[utoipa::path(
+    get,
+    path = "/api/groups/{id}",
+    tag = "groups",
+    params(("id" = String, Path, description = "Group ID")),
+    responses(
+        (status = 200, description = "The group", body = GroupResponse),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Missing groups:read permission"),
+        (status = 404, description = "No such group"),
+    )
+)]
 pub async fn get_group(
     auth_user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -57,8 +82,23 @@ pub async fn get_group(
     Ok(Json(group_to_response(&state, group).await?))
 }
 
+/// POST /api/groups
+#[utoipa::path(
+    post,
+    path = "/api/groups",
+    tag = "groups",
+    request_body = enigma_auth::CreateGroupRequest,
+    responses(
+        (status = 200, description = "Group created", body = GroupResponse),
+        (status = 400, description = "Missing name"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Missing groups:write permission"),
+        (status = 409, description = "Name taken"),
+    )
+)]
 pub async fn create_group(
     auth_user: AuthUser,
+    correlation_id: CorrelationId,
     State(state): State<Arc<AppState>>,
     Json(req): Json<enigma_auth::CreateGroupRequest>,
 ) -> Result<Json<GroupResponse>, AuthError> {
@@ -80,14 +120,30 @@ pub async fn create_group(
             "group.create",
             Some(&group.name),
             None,
+            &correlation_id.0,
         )
         .await;
 
     Ok(Json(group_to_response(&state, group).await?))
 }
 
+/// PUT /api/groups/{id}
+#[utoipa::path(
+    put,
+    path = "/api/groups/{id}",
+    tag = "groups",
+    params(("id" = String, Path, description = "Group ID")),
+    request_body = enigma_auth::UpdateGroupRequest,
+    responses(
+        (status = 200, description = "Group updated", body = GroupResponse),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Missing groups:write permission"),
+        (status = 404, description = "No such group"),
+    )
+)]
 pub async fn update_group(
     auth_user: AuthUser,
+    correlation_id: CorrelationId,
     State(state): State<Arc<AppState>>,
     Path(id): Path<String>,
     Json(req): Json<enigma_auth::UpdateGroupRequest>,
@@ -103,14 +159,31 @@ pub async fn update_group(
             "group.update",
             Some(&group.name),
             None,
+            &correlation_id.0,
         )
         .await;
 
     Ok(Json(group_to_response(&state, group).await?))
 }
 
+/// DELETE /api/groups/{id}
+///
+/// Built-in groups cannot be deleted.
+#[utoipa::path(
+    delete,
+    path = "/api/groups/{id}",
+    tag = "groups",
+    params(("id" = String, Path, description = "Group ID")),
+    responses(
+        (status = 200, description = "Group deleted"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Missing groups:write permission, or a built-in group"),
+        (status = 404, description = "No such group"),
+    )
+)]
 pub async fn delete_group(
     auth_user: AuthUser,
+    correlation_id: CorrelationId,
     State(state): State<Arc<AppState>>,
     Path(id): Path<String>,
 ) -> Result<Json<serde_json::Value>, AuthError> {
@@ -126,12 +199,25 @@ pub async fn delete_group(
             "group.delete",
             Some(&group.name),
             None,
+            &correlation_id.0,
         )
         .await;
 
     Ok(Json(serde_json::json!({"ok": true})))
 }
 
+/// GET /api/groups/{id}/permissions
+#[utoipa::path(
+    get,
+    path = "/api/groups/{id}/permissions",
+    tag = "groups",
+    params(("id" = String, Path, description = "Group ID")),
+    responses(
+        (status = 200, description = "Permissions of the group", body = Vec<PermissionResponse>),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Missing groups:read permission"),
+    )
+)]
 pub async fn list_group_permissions(
     auth_user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -152,8 +238,22 @@ pub async fn list_group_permissions(
     ))
 }
 
+/// POST /api/groups/{id}/permissions
+#[utoipa::path(
+    post,
+    path = "/api/groups/{id}/permissions",
+    tag = "groups",
+    params(("id" = String, Path, description = "Group ID")),
+    request_body = enigma_auth::GroupPermissionRequest,
+    responses(
+        (status = 200, description = "Permission granted"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Missing groups:write permission"),
+    )
+)]
 pub async fn add_group_permission(
     auth_user: AuthUser,
+    correlation_id: CorrelationId,
     State(state): State<Arc<AppState>>,
     Path(id): Path<String>,
     Json(req): Json<enigma_auth::GroupPermissionRequest>,
@@ -172,14 +272,31 @@ pub async fn add_group_permission(
             "group.permission.add",
             Some(&format!("{id}:{}", req.permission_id)),
             None,
+            &correlation_id.0,
         )
         .await;
 
     Ok(Json(serde_json::json!({"ok": true})))
 }
 
+/// DELETE /api/groups/{id}/permissions/{permission_id}
+#[utoipa::path(
+    delete,
+    path = "/api/groups/{id}/permissions/{permission_id}",
+    tag = "groups",
+    params(
+        ("id" = String, Path, description = "Group ID"),
+        ("permission_id" = String, Path, description = "Permission ID"),
+    ),
+    responses(
+        (status = 200, description = "Permission revoked"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Missing groups:write permission"),
+    )
+)]
 pub async fn remove_group_permission(
     auth_user: AuthUser,
+    correlation_id: CorrelationId,
     State(state): State<Arc<AppState>>,
     Path((id, permission_id)): Path<(String, String)>,
 ) -> Result<Json<serde_json::Value>, AuthError> {
@@ -197,8 +314,249 @@ pub async fn remove_group_permission(
             "group.permission.remove",
             Some(&format!("{id}:{permission_id}")),
             None,
+            &correlation_id.0,
+        )
+        .await;
+
+    Ok(Json(serde_json::json!({"ok": true})))
+}
+
+/// GET /api/groups/{id}/members
+///
+/// Groups nested in this one, whose permissions it holds too.
+#[utoipa::path(
+    get,
+    path = "/api/groups/{id}/members",
+    tag = "groups",
+    params(("id" = String, Path, description = "Group ID")),
+    responses(
+        (status = 200, description = "Nested groups", body = Vec<GroupResponse>),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Missing groups:read permission"),
+    )
+)]
+pub async fn list_group_members(
+    auth_user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<GroupResponse>>, AuthError> {
+    require_permission(&auth_user, "groups:read")?;
+
+    let groups = state.auth_store.list_group_member_groups(&id).await?;
+    let mut result = Vec::new();
+    for g in groups {
+        result.push(group_to_response(&state, g).await?);
+    }
+    Ok(Json(result))
+}
+
+/// POST /api/groups/{id}/members
+///
+/// Nest a group in this one, which then holds its permissions too.
+#[utoipa::path(
+    post,
+    path = "/api/groups/{id}/members",
+    tag = "groups",
+    params(("id" = String, Path, description = "Group ID")),
+    request_body = enigma_auth::GroupMemberRequest,
+    responses(
+        (status = 200, description = "Group nested"),
+        (status = 400, description = "Nesting would create a cycle"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Missing groups:write permission"),
+    )
+)]
+pub async fn add_group_member(
+    auth_user: AuthUser,
+    correlation_id: CorrelationId,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Json(req): Json<enigma_auth::GroupMemberRequest>,
+) -> Result<Json<serde_json::Value>, AuthError> {
+    require_permission(&auth_user, "groups:write")?;
+
+    state
+        .auth_store
+        .add_group_member_group(&id, &req.group_id)
+        .await?;
+
+    let _ = state
+        .auth_store
+        .log_audit(
+            Some(&auth_user.user_id),
+            "group.member.add",
+            Some(&format!("{id}:{}", req.group_id)),
+            None,
+            &correlation_id.0,
         )
         .await;
 
     Ok(Json(serde_json::json!({"ok": true})))
 }
+
+/// DELETE /api/groups/{id}/members/{child_id}
+#[utoipa::path(
+    delete,
+    path = "/api/groups/{id}/members/{child_id}",
+    tag = "groups",
+    params(
+        ("id" = String, Path, description = "Group ID"),
+        ("child_id" = String, Path, description = "Nested group ID"),
+    ),
+    responses(
+        (status = 200, description = "Group no longer nested"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Missing groups:write permission"),
+    )
+)]
+pub async fn remove_group_member(
+    auth_user: AuthUser,
+    correlation_id: CorrelationId,
+    State(state): State<Arc<AppState>>,
+    Path((id, child_id)): Path<(String, String)>,
+) -> Result<Json<serde_json::Value>, AuthError> {
+    require_permission(&auth_user, "groups:write")?;
+
+    state
+        .auth_store
+        .remove_group_member_group(&id, &child_id)
+        .await?;
+
+    let _ = state
+        .auth_store
+        .log_audit(
+            Some(&auth_user.user_id),
+            "group.member.remove",
+            Some(&format!("{id}:{child_id}")),
+            None,
+            &correlation_id.0,
+        )
+        .await;
+
+    Ok(Json(serde_json::json!({"ok": true})))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use enigma_auth::{AuthStore, SqliteAuthStore};
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::auth::create_token;
+    use crate::routes::build_router;
+
+    /// A state whose store holds the configured admin and `carol`, in no
+    /// group.
+    async fn test_state() -> Arc<AppState> {
+        let store = SqliteAuthStore::open_in_memory().unwrap();
+        store.migrate().await.unwrap();
+        store.seed_defaults().await.unwrap();
+        for name in ["admin", "carol"] {
+            store
+                .create_user(name, &enigma_auth::NewPassword::disabled(), None)
+                .await
+                .unwrap();
+        }
+        Arc::new(AppState {
+            auth_store: Arc::new(store),
+            ..AppState::for_tests()
+        })
+    }
+
+    /// Send `body` as JSON to `uri`, logged in as `username`.
+    async fn send(
+        state: &Arc<AppState>,
+        method: &str,
+        uri: &str,
+        username: &str,
+        body: serde_json::Value,
+    ) -> (StatusCode, serde_json::Value) {
+        let jwt = create_token(username, &state.jwt_secret).unwrap();
+        let request = crate::csrf::with_csrf(Request::builder(), &jwt, &state.jwt_secret)
+            .method(method)
+            .uri(uri)
+            .header("Authorization", format!("Bearer {jwt}"))
+            .header("Content-Type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let resp = build_router(state.clone()).oneshot(request).await.unwrap();
+        let status = resp.status();
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or_default())
+    }
+
+    #[tokio::test]
+    async fn nested_group_permissions_apply_to_members() {
+        let state = test_state().await;
+        let read = state.auth_store.get_group_by_name("read").await.unwrap();
+        let carol = state
+            .auth_store
+            .get_user_by_username("carol")
+            .await
+            .unwrap();
+
+        let (status, ops) = send(
+            &state,
+            "POST",
+            "/api/groups",
+            "admin",
+            json!({ "name": "ops" }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let ops = ops["id"].as_str().unwrap().to_string();
+        let members = format!("/api/groups/{ops}/members");
+        let (status, _) = send(
+            &state,
+            "POST",
+            &members,
+            "admin",
+            json!({ "group_id": read.id }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, listed) = send(&state, "GET", &members, "admin", json!({})).await;
+        assert_eq!(listed[0]["name"], "read");
+
+        // Nesting the other way round would be a cycle
+        let (status, _) = send(
+            &state,
+            "POST",
+            &format!("/api/groups/{}/members", read.id),
+            "admin",
+            json!({ "group_id": ops }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        // carol gets tokens:own from `read` through `ops`
+        let (status, _) = send(
+            &state,
+            "POST",
+            &format!("/api/users/{}/groups", carol.id),
+            "admin",
+            json!({ "group_id": ops }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = send(&state, "GET", "/api/auth/tokens", "carol", json!({})).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, _) = send(
+            &state,
+            "DELETE",
+            &format!("{members}/{}", read.id),
+            "admin",
+            json!({}),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = send(&state, "GET", "/api/auth/tokens", "carol", json!({})).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+}
@@ -0,0 +1,295 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use enigma_keys::provider::{HealthStatus, KeyProvider, KeyProviderHealth};
+use enigma_storage::provider::StorageProvider;
+
+use crate::models::{ComponentHealthResponse, HealthResponse, StorageHealthResponse};
+use crate::state::AppState;
+
+/// A probe that has not answered by then counts as unavailable.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+impl From<KeyProviderHealth> for ComponentHealthResponse {
+    fn from(health: KeyProviderHealth) -> Self {
+        Self {
+            status: health.status.as_str().to_string(),
+            latency_ms: health.latency_ms,
+            message: health.message,
+        }
+    }
+}
+
+fn http_status(status: HealthStatus) -> StatusCode {
+    match status {
+        HealthStatus::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+        HealthStatus::Ok | HealthStatus::Degraded => StatusCode::OK,
+    }
+}
+
+async fn check_key_provider(provider: &dyn KeyProvider) -> KeyProviderHealth {
+    let started = Instant::now();
+    match tokio::time::timeout(PROBE_TIMEOUT, provider.health_check()).await {
+        Ok(Ok(health)) => health,
+        Ok(Err(e)) => KeyProviderHealth::from_probe(Err(e), started.elapsed()),
+        Err(_) => {
+            KeyProviderHealth::from_probe(Err(anyhow::anyhow!("timed out")), started.elapsed())
+        }
+    }
+}
+
+async fn check_storage(provider: &dyn StorageProvider) -> KeyProviderHealth {
+    let started = Instant::now();
+    let result = match tokio::time::timeout(PROBE_TIMEOUT, provider.test_connection()).await {
+        Ok(result) => result,
+        Err(_) => Err(anyhow::anyhow!("timed out")),
+    };
+    KeyProviderHealth::from_probe(result, started.elapsed())
+}
+
+/// Overall status: unavailable if the manifest, the key provider or every
+/// storage provider is down; degraded if anything else is not ok.
+fn overall_status(
+    database: HealthStatus,
+    key_provider: Option<HealthStatus>,
+    storage: &[HealthStatus],
+) -> HealthStatus {
+    let all_storage_down =
+        !storage.is_empty() && storage.iter().all(|s| *s == HealthStatus::Unavailable);
+    if database == HealthStatus::Unavailable
+        || key_provider == Some(HealthStatus::Unavailable)
+        || all_storage_down
+    {
+        HealthStatus::Unavailable
+    } else if database == HealthStatus::Ok
+        && key_provider.is_none_or(|s| s == HealthStatus::Ok)
+        && storage.iter().all(|s| *s == HealthStatus::Ok)
+    {
+        HealthStatus::Ok
+    } else {
+        HealthStatus::Degraded
+    }
+}
+
+/// GET /api/keys/health
+///
+/// Probe the configured key provider. Responds 503 when it is unavailable.
+#[utoipa::path(
+    get,
+    path = "/api/keys/health",
+    tag = "status",
+    responses(
+        (status = 200, description = "Key provider reachable", body = ComponentHealthResponse),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 404, description = "No key provider configured"),
+        (status = 503, description = "Key provider unavailable", body = ComponentHealthResponse),
+    )
+)]
+pub async fn get_key_health(
+    State(state): State<Arc<AppState>>,
+) -> Result<(StatusCode, Json<ComponentHealthResponse>), (StatusCode, &'static str)> {
+    let provider = state
+        .key_provider
+        .as_deref()
+        .ok_or((StatusCode::NOT_FOUND, "no key provider configured"))?;
+    let health = check_key_provider(provider).await;
+    Ok((http_status(health.status), Json(health.into())))
+}
+
+/// GET /api/health
+///
+/// Readiness probe for load balancers: manifest database, key provider and
+/// storage providers. Responds 503 when the gateway cannot serve requests.
+#[utoipa::path(
+    get,
+    path = "/api/health",
+    tag = "status",
+    security(()),
+    responses(
+        (status = 200, description = "Ready (possibly degraded)", body = HealthResponse),
+        (status = 503, description = "Not ready", body = HealthResponse),
+    )
+)]
+pub async fn get_health(State(state): State<Arc<AppState>>) -> (StatusCode, Json<HealthResponse>) {
+    let started = Instant::now();
+    let database = match state.db.get().await {
+        Ok(db) => db
+            .conn()
+            .query_row("PRAGMA integrity_check(1)", [], |row| {
+                row.get::<_, String>(0)
+            })
+            .map_err(anyhow::Error::from)
+            .and_then(|result| match result.as_str() {
+                "ok" => Ok(()),
+                other => Err(anyhow::anyhow!("integrity check failed: {other}")),
+            }),
+        Err(e) => Err(anyhow::anyhow!("no database connection: {e}")),
+    };
+    let database = KeyProviderHealth::from_probe(database, started.elapsed());
+
+    let key_provider = match state.key_provider.as_deref() {
+        Some(provider) => Some(check_key_provider(provider).await),
+        None => None,
+    };
+    let storage = futures::future::join_all(
+        state
+            .storage_providers
+            .iter()
+            .map(|provider| check_storage(provider.as_ref())),
+    )
+    .await;
+
+    let storage_status: Vec<HealthStatus> = storage.iter().map(|s| s.status).collect();
+    let status = overall_status(
+        database.status,
+        key_provider.as_ref().map(|k| k.status),
+        &storage_status,
+    );
+    let storage = state
+        .storage_providers
+        .iter()
+        .zip(storage)
+        .map(|(provider, health)| StorageHealthResponse {
+            name: provider.name().to_string(),
+            status: health.status.as_str().to_string(),
+            latency_ms: health.latency_ms,
+            message: health.message,
+        })
+        .collect();
+    (
+        http_status(status),
+        Json(HealthResponse {
+            status: status.as_str().to_string(),
+            database: database.into(),
+            key_provider: key_provider.map(Into::into),
+            storage,
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use enigma_core::manifest::ManifestDb;
+    use enigma_keys::local::LocalKeyProvider;
+    use enigma_storage::mock::{MockMethod, MockStorageProvider};
+    use tower::ServiceExt;
+
+    use crate::auth::create_token;
+    use crate::routes::build_router;
+    use crate::state::RateLimitConfig;
+
+    fn app_state(
+        dir: &std::path::Path,
+        key_provider: Option<Arc<dyn KeyProvider>>,
+        storage_providers: Vec<Arc<dyn StorageProvider>>,
+    ) -> Arc<AppState> {
+        let db_path = dir.join("manifest.db");
+        ManifestDb::open(&db_path).unwrap();
+        Arc::new(AppState {
+            rate_limit: RateLimitConfig {
+                requests_per_second: 1000.0,
+                burst: 1000,
+                per_ip: false,
+            },
+            key_provider,
+            storage_providers,
+            ..AppState::for_tests_with_db(dir, &db_path, 2)
+        })
+    }
+
+    async fn get(
+        state: Arc<AppState>,
+        uri: &str,
+        token: Option<&str>,
+    ) -> (StatusCode, serde_json::Value) {
+        let mut request = Request::builder().uri(uri);
+        if let Some(token) = token {
+            request = request.header("Authorization", format!("Bearer {token}"));
+        }
+        let resp = build_router(state)
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = resp.status();
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or_default())
+    }
+
+    #[tokio::test]
+    async fn health_reports_every_component() {
+        let tmp = tempfile::tempdir().unwrap();
+        let keys = LocalKeyProvider::create(&tmp.path().join("keys.enc"), b"pass").unwrap();
+        let down = MockStorageProvider::new("down");
+        down.inject_failure(MockMethod::TestConnection, 0, "connection refused");
+        let state = app_state(
+            tmp.path(),
+            Some(Arc::new(keys)),
+            vec![Arc::new(MockStorageProvider::new("up")), Arc::new(down)],
+        );
+
+        // Public: no token needed
+        let (status, body) = get(state, "/api/health", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "degraded");
+        assert_eq!(body["database"]["status"], "ok");
+        assert_eq!(body["key_provider"]["status"], "ok");
+        assert_eq!(body["storage"][0]["name"], "up");
+        assert_eq!(body["storage"][0]["status"], "ok");
+        assert_eq!(body["storage"][1]["status"], "unavailable");
+        assert_eq!(body["storage"][1]["message"], "connection refused");
+    }
+
+    #[tokio::test]
+    async fn health_is_unavailable_when_all_storage_is_down() {
+        let tmp = tempfile::tempdir().unwrap();
+        let down = MockStorageProvider::new("down");
+        down.inject_failure(MockMethod::TestConnection, 0, "connection refused");
+        let state = app_state(tmp.path(), None, vec![Arc::new(down)]);
+
+        let (status, body) = get(state, "/api/health", None).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["status"], "unavailable");
+        assert!(body["key_provider"].is_null());
+    }
+
+    #[tokio::test]
+    async fn key_health_requires_auth_and_provider() {
+        let tmp = tempfile::tempdir().unwrap();
+        let keys = LocalKeyProvider::create(&tmp.path().join("keys.enc"), b"pass").unwrap();
+        let state = app_state(tmp.path(), Some(Arc::new(keys)), Vec::new());
+        let token = create_token("admin", &state.jwt_secret).unwrap();
+
+        let (status, _) = get(state.clone(), "/api/keys/health", None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, body) = get(state, "/api/keys/health", Some(&token)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "ok");
+        assert!(body["latency_ms"].is_u64());
+
+        let unconfigured = app_state(tmp.path(), None, Vec::new());
+        let (status, _) = get(unconfigured, "/api/keys/health", Some(&token)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn overall_status_rules() {
+        use HealthStatus::{Degraded, Unavailable};
+        let ok = HealthStatus::Ok;
+        assert_eq!(overall_status(ok, None, &[]), ok);
+        assert_eq!(overall_status(ok, Some(ok), &[ok, ok]), ok);
+        assert_eq!(overall_status(ok, Some(Degraded), &[ok]), Degraded);
+        assert_eq!(overall_status(ok, Some(ok), &[ok, Unavailable]), Degraded);
+        assert_eq!(overall_status(ok, Some(ok), &[Unavailable]), Unavailable);
+        assert_eq!(overall_status(ok, Some(Unavailable), &[ok]), Unavailable);
+        assert_eq!(overall_status(Unavailable, None, &[]), Unavailable);
+    }
+}
@@ -0,0 +1,277 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::Json;
+use axum::extract::{Query, State};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use utoipa::{IntoParams, ToSchema};
+
+use enigma_auth::AuthUser;
+use enigma_auth::error::AuthError;
+use enigma_auth::middleware::require_permission;
+use enigma_core::types::KeyMaterial;
+use enigma_core::webhook::{self, WebhookPayload};
+use enigma_storage::provider::StorageProvider;
+use enigma_storage::reencrypt::{ReencryptStats, reencrypt_all_chunks};
+
+use crate::state::AppState;
+
+// ── Types ────────────────────────────────────────────────────
+
+#[derive(Deserialize, IntoParams)]
+pub struct ReencryptQuery {
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct KeyChunkCount {
+    pub key_id: String,
+    pub chunks: u64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ReencryptResponse {
+    pub current_key_id: String,
+    pub old_keys: Vec<KeyChunkCount>,
+    pub dry_run: bool,
+    pub reencrypted: u64,
+    pub copies_rewritten: u64,
+    pub failed: u64,
+}
+
+fn internal(e: impl std::fmt::Display) -> AuthError {
+    AuthError::Internal(e.to_string())
+}
+
+// ── Handlers ─────────────────────────────────────────────────
+
+/// POST /api/admin/keys/reencrypt?dry_run=true
+///
+/// Re-encrypt every chunk still stored under an old key with the current key.
+#[utoipa::path(
+    post,
+    path = "/api/admin/keys/reencrypt",
+    tag = "admin",
+    params(ReencryptQuery),
+    responses(
+        (status = 200, description = "Re-encryption summary", body = ReencryptResponse),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Missing storage:write permission"),
+    )
+)]
+pub async fn reencrypt(
+    auth_user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Query(q): Query<ReencryptQuery>,
+) -> Result<Json<ReencryptResponse>, AuthError> {
+    require_permission(&auth_user, "storage:write")?;
+
+    let key_provider = state
+        .key_provider
+        .clone()
+        .ok_or_else(|| internal("key provider not configured"))?;
+    let current = key_provider.get_current_key().await.map_err(internal)?;
+    let new_key = KeyMaterial {
+        id: current.id.clone(),
+        key: current.key,
+    };
+
+    let db = state.db.get().await.map_err(internal)?;
+    let old_keys: Vec<(String, u64)> = db
+        .chunk_key_ids()
+        .map_err(internal)?
+        .into_iter()
+        .filter(|(key_id, _)| *key_id != new_key.id)
+        .collect();
+
+    let mut stats = ReencryptStats::default();
+    if !q.dry_run && !old_keys.is_empty() {
+        tracing::info!(user = %auth_user.username, new_key = %new_key.id, "re-encrypting chunks");
+
+        let mut keys = Vec::new();
+        for (key_id, _) in &old_keys {
+            let managed = key_provider.get_key_by_id(key_id).await.map_err(internal)?;
+            keys.push(KeyMaterial {
+                id: managed.id.clone(),
+                key: managed.key,
+            });
+        }
+
+        // Chunks record the manifest ID of their provider
+        let ids: HashMap<String, i64> = db
+            .list_providers()
+            .map_err(internal)?
+            .into_iter()
+            .map(|p| (p.name, p.id))
+            .collect();
+        let providers: HashMap<i64, Box<dyn StorageProvider>> = state
+            .storage_providers
+            .iter()
+            .filter_map(|provider| {
+                let id = *ids.get(provider.name())?;
+                Some((id, Box::new(provider.clone()) as Box<dyn StorageProvider>))
+            })
+            .collect();
+
+        // ManifestDb is not Sync, so the run keeps its connection on a
+        // blocking thread instead of holding it across awaits
+        let handle = tokio::runtime::Handle::current();
+        let run_key = new_key.clone();
+        let (db, run_stats) = tokio::task::spawn_blocking(move || -> anyhow::Result<_> {
+            let mut stats = ReencryptStats::default();
+            for old_key in &keys {
+                stats +=
+                    handle.block_on(reencrypt_all_chunks(&db, &providers, old_key, &run_key))?;
+            }
+            Ok((db, stats))
+        })
+        .await
+        .map_err(internal)?
+        .map_err(internal)?;
+        stats = run_stats;
+
+        tracing::info!(
+            user = %auth_user.username,
+            reencrypted = stats.reencrypted,
+            failed = stats.failed,
+            "re-encryption finished"
+        );
+        let old_key_ids: Vec<&str> = old_keys.iter().map(|(id, _)| id.as_str()).collect();
+        let payload = WebhookPayload::new(
+            "key.rotated",
+            json!({
+                "key_id": new_key.id,
+                "old_key_ids": old_key_ids,
+                "reencrypted": stats.reencrypted,
+                "failed": stats.failed,
+            }),
+        );
+        if let Err(e) = webhook::enqueue(&db, &state.webhooks, &payload) {
+            tracing::warn!("Failed to queue key.rotated webhook: {e}");
+        }
+    }
+
+    Ok(Json(ReencryptResponse {
+        current_key_id: new_key.id.clone(),
+        old_keys: old_keys
+            .into_iter()
+            .map(|(key_id, chunks)| KeyChunkCount { key_id, chunks })
+            .collect(),
+        dry_run: q.dry_run,
+        reencrypted: stats.reencrypted,
+        copies_rewritten: stats.copies_rewritten,
+        failed: stats.failed,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use enigma_core::crypto::encrypt_chunk;
+    use enigma_core::dedup::compute_hash;
+    use enigma_core::manifest::ManifestDb;
+    use enigma_core::types::ProviderType;
+    use enigma_core::webhook::WebhookConfig;
+    use enigma_keys::local::LocalKeyProvider;
+    use enigma_keys::provider::KeyProvider;
+    use enigma_storage::local::LocalStorageProvider;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::auth::create_token;
+    use crate::routes::build_router;
+
+    async fn post(state: &Arc<AppState>, uri: &str) -> (StatusCode, serde_json::Value) {
+        let jwt = create_token("admin", &state.jwt_secret).unwrap();
+        let request = crate::csrf::with_csrf(Request::builder(), &jwt, &state.jwt_secret)
+            .method("POST")
+            .uri(uri)
+            .header("Authorization", format!("Bearer {jwt}"))
+            .body(Body::empty())
+            .unwrap();
+        let resp = build_router(state.clone()).oneshot(request).await.unwrap();
+        let status = resp.status();
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or_default())
+    }
+
+    #[tokio::test]
+    async fn reencrypt_moves_chunks_to_the_current_key() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("manifest.db");
+        let db = ManifestDb::open(&db_path).unwrap();
+        let pid = db
+            .insert_provider("local", ProviderType::Local, "chunks", None, 1)
+            .unwrap();
+        let provider = LocalStorageProvider::new(&tmp.path().join("chunks"), "local").unwrap();
+
+        // One chunk under the first key, then rotate
+        let mut keys = LocalKeyProvider::create(&tmp.path().join("keys.enc"), b"pass").unwrap();
+        let old = keys.get_current_key().await.unwrap();
+        let old = KeyMaterial {
+            id: old.id.clone(),
+            key: old.key,
+        };
+        let hash = compute_hash(b"chunk");
+        let encrypted = encrypt_chunk(b"chunk", &hash, &old).unwrap();
+        let storage_key = hash.storage_key();
+        db.insert_or_dedup_chunk(
+            &hash.to_hex(),
+            &encrypted.nonce,
+            &old.id,
+            pid,
+            &storage_key,
+            5,
+            encrypted.ciphertext.len() as u64,
+            None,
+        )
+        .unwrap();
+        db.insert_chunk_replicas(&hash.to_hex(), &[(pid, storage_key.as_str())])
+            .unwrap();
+        provider
+            .upload_chunk(&storage_key, &encrypted.ciphertext)
+            .await
+            .unwrap();
+        let new = keys.create_key().await.unwrap();
+
+        let state = AppState {
+            key_provider: Some(Arc::new(keys)),
+            storage_providers: vec![Arc::new(provider)],
+            webhooks: vec![WebhookConfig {
+                url: "http://127.0.0.1:9/hook".into(),
+                secret: "secret".into(),
+                events: vec!["key.rotated".into()],
+                max_retries: 0,
+                timeout_ms: 1000,
+            }],
+            ..AppState::for_tests_with_db(tmp.path(), &db_path, 2)
+        };
+        state
+            .auth_store
+            .create_user("admin", &enigma_auth::NewPassword::disabled(), None)
+            .await
+            .unwrap();
+        let state = Arc::new(state);
+
+        let (status, body) = post(&state, "/api/admin/keys/reencrypt?dry_run=true").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["current_key_id"], new.id.as_str());
+        assert_eq!(body["old_keys"][0]["key_id"], old.id.as_str());
+        assert_eq!(body["old_keys"][0]["chunks"], 1);
+        assert_eq!(body["reencrypted"], 0);
+
+        let (status, body) = post(&state, "/api/admin/keys/reencrypt").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["reencrypted"], 1);
+        assert_eq!(body["failed"], 0);
+        assert_eq!(db.chunk_key_ids().unwrap(), vec![(new.id.clone(), 1)]);
+        let queued = db.pending_webhooks(10).unwrap();
+        assert_eq!(queued.len(), 1);
+        assert_eq!(queued[0].event, "key.rotated");
+    }
+}
@@ -1,52 +1,179 @@
+pub mod admin;
 pub mod cluster;
+pub mod events;
+pub mod groups;
+pub mod health;
+pub mod keys;
 pub mod namespaces;
 pub mod status;
 pub mod storage;
+pub mod tokens;
+pub mod users;
+pub mod ws;
 
 // Pending integration (files exist but not yet wired into the router):
 // - audit
 // - files
-// - groups
 // - permissions
-// - tokens
-// - users
 
 use std::sync::Arc;
 
-use axum::Router;
-use axum::middleware;
-use axum::routing::{get, post};
+use axum::routing::get;
+use axum::{Extension, Json, Router, middleware};
+use enigma_auth::middleware::AuthState;
+use utoipa::OpenApi;
+use utoipa_axum::router::OpenApiRouter;
+use utoipa_axum::routes;
 
 use crate::auth;
+use crate::correlation;
+use crate::csrf;
+use crate::openapi::ApiDoc;
+use crate::rate_limit::{self, RateLimit};
 use crate::state::AppState;
 use crate::static_files;
+use crate::stream_ticket;
 
 pub fn build_router(state: Arc<AppState>) -> Router {
     // Protected API routes (require JWT)
-    let api = Router::new()
-        .route("/api/status", get(status::get_status))
-        .route("/api/storage/providers", get(storage::get_providers))
-        .route("/api/storage/chunks/stats", get(storage::get_chunk_stats))
-        .route("/api/storage/backups", get(storage::get_backups))
-        .route("/api/namespaces", get(namespaces::list_namespaces))
-        .route(
-            "/api/namespaces/{name}/objects",
-            get(namespaces::list_objects),
-        )
-        .route("/api/cluster", get(cluster::get_cluster))
+    let api = OpenApiRouter::new()
+        .routes(routes!(status::get_status))
+        .routes(routes!(health::get_key_health))
+        .routes(routes!(storage::get_providers))
+        .routes(routes!(storage::get_provider_usage))
+        .routes(routes!(storage::get_chunk_stats))
+        .routes(routes!(storage::get_chunk_histogram))
+        .routes(routes!(storage::get_dedup_stats))
+        .routes(routes!(storage::get_cross_namespace_dedup))
+        .routes(routes!(storage::get_backups))
+        .routes(routes!(storage::get_access_logs))
+        .routes(routes!(storage::get_inventory_status))
+        .routes(routes!(storage::presign_chunk))
+        .routes(routes!(namespaces::list_namespaces))
+        .routes(routes!(namespaces::list_objects))
+        .routes(routes!(namespaces::restore_namespace))
+        .routes(routes!(namespaces::enable_object_lock))
+        .routes(routes!(
+            namespaces::get_tags,
+            namespaces::put_tags,
+            namespaces::delete_tags
+        ))
+        .routes(routes!(cluster::get_cluster))
+        .routes(routes!(cluster::get_peers))
+        .routes(routes!(cluster::add_learner))
+        .routes(routes!(admin::checkpoint_db))
+        .routes(routes!(admin::impersonate))
+        .routes(routes!(admin::purge_audit))
+        .routes(routes!(admin::get_audit_stats))
+        .routes(routes!(keys::reencrypt))
+        .routes(routes!(csrf::get_csrf_token))
+        .routes(routes!(stream_ticket::issue_stream_ticket))
+        .routes(routes!(tokens::list_tokens, tokens::create_token))
+        .routes(routes!(tokens::update_token_scopes))
+        .routes(routes!(tokens::update_token_allowed_ips))
+        .routes(routes!(tokens::get_token_usage))
+        .routes(routes!(users::list_users, users::create_user))
+        .routes(routes!(
+            users::get_user,
+            users::update_user,
+            users::delete_user
+        ))
+        .routes(routes!(users::update_password))
+        .routes(routes!(users::list_user_groups, users::add_user_group))
+        .routes(routes!(users::remove_user_group))
+        .routes(routes!(users::unlock_user))
+        .routes(routes!(users::get_user_usage))
+        .routes(routes!(users::set_user_quota))
+        .routes(routes!(groups::list_groups, groups::create_group))
+        .routes(routes!(
+            groups::get_group,
+            groups::update_group,
+            groups::delete_group
+        ))
+        .routes(routes!(
+            groups::list_group_permissions,
+            groups::add_group_permission
+        ))
+        .routes(routes!(groups::remove_group_permission))
+        .routes(routes!(
+            groups::list_group_members,
+            groups::add_group_member
+        ))
+        .routes(routes!(groups::remove_group_member))
+        .routes(routes!(tokens::revoke_token))
+        // Inside the auth layer: CSRF tokens are bound to the JWT's session
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            csrf::csrf_middleware,
+        ))
         .layer(middleware::from_fn_with_state(
             state.clone(),
             auth::auth_middleware,
         ))
-        .with_state(state.clone());
+        // Outside the auth layer, which checks API tokens against this store
+        .layer(Extension(AuthState {
+            jwt_secret: state.jwt_secret.clone(),
+            auth_store: state.auth_store.clone(),
+            trusted_proxies: state.trusted_proxies.clone(),
+        }));
+
+    // WebSocket and SSE progress streams; they check their JWT or stream
+    // ticket themselves since browsers cannot send an Authorization
+    // header there
+    let ws_routes = OpenApiRouter::new()
+        .route("/api/ws/status", get(ws::ws_status))
+        .route("/api/events/backup", get(events::backup_events));
 
-    // Public auth route
-    let auth_routes = Router::new()
-        .route("/api/auth/login", post(auth::login))
-        .with_state(state);
+    // Public readiness probe for load balancers
+    let health_routes = OpenApiRouter::new().routes(routes!(health::get_health));
 
-    Router::new()
+    // Public auth routes, with their own stricter limiter
+    let login_limiter = Arc::new(RateLimit::new(
+        &state.login_rate_limit,
+        &state.trusted_proxies,
+    ));
+    let auth_routes = OpenApiRouter::new()
+        .routes(routes!(auth::login))
+        .routes(routes!(auth::oidc_login))
+        .routes(routes!(auth::oidc_callback))
+        .routes(routes!(auth::forgot_password))
+        .routes(routes!(auth::reset_password))
+        .layer(middleware::from_fn_with_state(
+            login_limiter,
+            rate_limit::rate_limit_middleware,
+        ));
+
+    let limiter = Arc::new(RateLimit::new(&state.rate_limit, &state.trusted_proxies));
+
+    let (router, spec) = OpenApiRouter::with_openapi(ApiDoc::openapi())
         .merge(auth_routes)
         .merge(api)
+        .merge(ws_routes)
+        .merge(health_routes)
+        .with_state(state)
+        .split_for_parts();
+
+    // Public OpenAPI spec
+    let router = router.route(
+        "/api/openapi.json",
+        get(move || {
+            let spec = spec.clone();
+            async move { Json(spec) }
+        }),
+    );
+
+    #[cfg(feature = "web-swagger")]
+    let router = router.merge(
+        utoipa_swagger_ui::SwaggerUi::new("/api/swagger-ui")
+            .config(utoipa_swagger_ui::Config::from("/api/openapi.json")),
+    );
+
+    router
         .fallback(static_files::static_handler)
+        .layer(middleware::from_fn_with_state(
+            limiter,
+            rate_limit::rate_limit_middleware,
+        ))
+        // Outermost, so that every response carries the request ID
+        .layer(middleware::from_fn(correlation::correlation_id_middleware))
 }
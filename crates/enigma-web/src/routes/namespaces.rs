@@ -3,40 +3,67 @@ use std::sync::Arc;
 use axum::Json;
 use axum::extract::{Path, State};
 use axum::http::StatusCode;
+use enigma_core::tagging::{MAX_BUCKET_TAGS, validate_tag_set};
 
-use crate::models::{NamespaceResponse, ObjectResponse};
+use crate::models::{NamespaceResponse, NamespaceTag, ObjectResponse};
 use crate::state::AppState;
 
+#[utoipa::path(
+    get,
+    path = "/api/namespaces",
+    tag = "namespaces",
+    responses(
+        (status = 200, description = "S3 namespaces (buckets)", body = Vec<NamespaceResponse>),
+        (status = 401, description = "Missing or invalid credentials"),
+    )
+)]
 pub async fn list_namespaces(
     State(state): State<Arc<AppState>>,
 ) -> Result<Json<Vec<NamespaceResponse>>, (StatusCode, &'static str)> {
     let db = state
-        .db
-        .lock()
+        .readonly_db
+        .get()
+        .await
         .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "internal error"))?;
     let ns = db.list_namespaces().unwrap_or_default();
     Ok(Json(
         ns.iter()
             .map(|(id, name, created_at)| {
                 let count = db.count_objects_with_prefix(*id, "").unwrap_or(0);
+                let tags = db.get_namespace_tags(*id).unwrap_or_default();
                 NamespaceResponse {
                     id: *id,
                     name: name.clone(),
                     created_at: created_at.clone(),
                     object_count: count,
+                    tags: tags
+                        .into_iter()
+                        .map(|(key, value)| NamespaceTag { key, value })
+                        .collect(),
                 }
             })
             .collect(),
     ))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/namespaces/{name}/objects",
+    tag = "namespaces",
+    params(("name" = String, Path, description = "Namespace name")),
+    responses(
+        (status = 200, description = "Objects in the namespace", body = Vec<ObjectResponse>),
+        (status = 401, description = "Missing or invalid credentials"),
+    )
+)]
 pub async fn list_objects(
     State(state): State<Arc<AppState>>,
     Path(name): Path<String>,
 ) -> Result<Json<Vec<ObjectResponse>>, (StatusCode, &'static str)> {
     let db = state
-        .db
-        .lock()
+        .readonly_db
+        .get()
+        .await
         .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "internal error"))?;
     let ns_id = db.get_namespace_id(&name).unwrap_or(None);
     let Some(ns_id) = ns_id else {
@@ -55,3 +82,329 @@ pub async fn list_objects(
             .collect(),
     ))
 }
+
+#[utoipa::path(
+    post,
+    path = "/api/namespaces/{name}/restore",
+    tag = "namespaces",
+    params(("name" = String, Path, description = "Namespace name")),
+    responses(
+        (status = 204, description = "Namespace restored"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 404, description = "No deleted namespace with this name"),
+    )
+)]
+pub async fn restore_namespace(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, (StatusCode, &'static str)> {
+    let db = state
+        .db
+        .get()
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "internal error"))?;
+    let restored = db
+        .restore_namespace(&name)
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "internal error"))?;
+    if !restored {
+        return Err((StatusCode::NOT_FOUND, "no deleted namespace with this name"));
+    }
+    tracing::info!("Restored namespace {name}");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Enable S3 object lock, so objects in the namespace accept retention
+/// settings. Object lock cannot be disabled again.
+#[utoipa::path(
+    put,
+    path = "/api/namespaces/{name}/object-lock",
+    tag = "namespaces",
+    params(("name" = String, Path, description = "Namespace name")),
+    responses(
+        (status = 204, description = "Object lock enabled"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 404, description = "Namespace not found"),
+    )
+)]
+pub async fn enable_object_lock(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, (StatusCode, &'static str)> {
+    let db = state
+        .db
+        .get()
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "internal error"))?;
+    let ns_id = db
+        .get_namespace_id(&name)
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "internal error"))?
+        .ok_or((StatusCode::NOT_FOUND, "namespace not found"))?;
+    db.enable_namespace_object_lock(ns_id)
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "internal error"))?;
+    tracing::info!("Enabled object lock on namespace {name}");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/namespaces/{name}/tags",
+    tag = "namespaces",
+    params(("name" = String, Path, description = "Namespace name")),
+    responses(
+        (status = 200, description = "Tags of the namespace", body = Vec<NamespaceTag>),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 404, description = "Namespace not found"),
+    )
+)]
+pub async fn get_tags(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+) -> Result<Json<Vec<NamespaceTag>>, (StatusCode, &'static str)> {
+    let db = state
+        .readonly_db
+        .get()
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "internal error"))?;
+    let ns_id = db
+        .get_namespace_id(&name)
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "internal error"))?
+        .ok_or((StatusCode::NOT_FOUND, "namespace not found"))?;
+    let tags = db
+        .get_namespace_tags(ns_id)
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "internal error"))?;
+    Ok(Json(
+        tags.into_iter()
+            .map(|(key, value)| NamespaceTag { key, value })
+            .collect(),
+    ))
+}
+
+/// Replace the tag set of a namespace, with the S3 bucket tag limits.
+#[utoipa::path(
+    put,
+    path = "/api/namespaces/{name}/tags",
+    tag = "namespaces",
+    params(("name" = String, Path, description = "Namespace name")),
+    request_body = Vec<NamespaceTag>,
+    responses(
+        (status = 204, description = "Tags replaced"),
+        (status = 400, description = "More than 50 tags, or an empty, duplicate or too long key or value"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 404, description = "Namespace not found"),
+    )
+)]
+pub async fn put_tags(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    Json(tags): Json<Vec<NamespaceTag>>,
+) -> Result<StatusCode, (StatusCode, &'static str)> {
+    let tags: Vec<(String, String)> = tags.into_iter().map(|t| (t.key, t.value)).collect();
+    validate_tag_set(&tags, MAX_BUCKET_TAGS).map_err(|_| {
+        (
+            StatusCode::BAD_REQUEST,
+            "invalid tags: at most 50, with unique non-empty keys",
+        )
+    })?;
+    set_tags(&state, &name, &tags).await
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/namespaces/{name}/tags",
+    tag = "namespaces",
+    params(("name" = String, Path, description = "Namespace name")),
+    responses(
+        (status = 204, description = "Tags removed"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 404, description = "Namespace not found"),
+    )
+)]
+pub async fn delete_tags(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, (StatusCode, &'static str)> {
+    set_tags(&state, &name, &[]).await
+}
+
+async fn set_tags(
+    state: &AppState,
+    name: &str,
+    tags: &[(String, String)],
+) -> Result<StatusCode, (StatusCode, &'static str)> {
+    let db = state
+        .db
+        .get()
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "internal error"))?;
+    let ns_id = db
+        .get_namespace_id(name)
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "internal error"))?
+        .ok_or((StatusCode::NOT_FOUND, "namespace not found"))?;
+    db.set_namespace_tags(ns_id, tags)
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "internal error"))?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::Request;
+    use enigma_core::manifest::ManifestDb;
+    use tower::ServiceExt;
+
+    use crate::auth::create_token;
+    use crate::routes::build_router;
+    use crate::state::AppState;
+
+    use super::*;
+
+    fn app_state(dir: &std::path::Path, db_path: &std::path::Path) -> Arc<AppState> {
+        Arc::new(AppState::for_tests_with_db(dir, db_path, 1))
+    }
+
+    #[tokio::test]
+    async fn restore_deleted_namespace() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("manifest.db");
+        let db = ManifestDb::open(&db_path).unwrap();
+        db.create_namespace("bucket").unwrap();
+        db.delete_namespace("bucket").unwrap();
+
+        let state = app_state(tmp.path(), &db_path);
+        let token = create_token("admin", &state.jwt_secret).unwrap();
+        let secret = state.jwt_secret.clone();
+        let app = build_router(state);
+        let restore = || {
+            crate::csrf::with_csrf(Request::builder(), &token, &secret)
+                .method("POST")
+                .uri("/api/namespaces/bucket/restore")
+                .header("Authorization", format!("Bearer {token}"))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let resp = app.clone().oneshot(restore()).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert!(db.get_namespace_id("bucket").unwrap().is_some());
+
+        // Only a deleted namespace can be restored
+        let resp = app.oneshot(restore()).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn enable_object_lock_on_namespace() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("manifest.db");
+        let db = ManifestDb::open(&db_path).unwrap();
+        let ns_id = db.create_namespace("bucket").unwrap();
+
+        let state = app_state(tmp.path(), &db_path);
+        let token = create_token("admin", &state.jwt_secret).unwrap();
+        let secret = state.jwt_secret.clone();
+        let app = build_router(state);
+        let enable = |name: &str| {
+            crate::csrf::with_csrf(Request::builder(), &token, &secret)
+                .method("PUT")
+                .uri(format!("/api/namespaces/{name}/object-lock"))
+                .header("Authorization", format!("Bearer {token}"))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let resp = app.clone().oneshot(enable("bucket")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert!(db.get_namespace_object_lock(ns_id).unwrap());
+
+        let resp = app.oneshot(enable("missing")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn namespace_tags_roundtrip() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("manifest.db");
+        let db = ManifestDb::open(&db_path).unwrap();
+        let ns_id = db.create_namespace("bucket").unwrap();
+
+        let state = app_state(tmp.path(), &db_path);
+        let token = create_token("admin", &state.jwt_secret).unwrap();
+        let secret = state.jwt_secret.clone();
+        let app = build_router(state);
+        let request = |method: &str, uri: &str, body: Body| {
+            crate::csrf::with_csrf(Request::builder(), &token, &secret)
+                .method(method)
+                .uri(uri)
+                .header("Authorization", format!("Bearer {token}"))
+                .header("Content-Type", "application/json")
+                .body(body)
+                .unwrap()
+        };
+        let get_json = |uri: &'static str| {
+            let app = app.clone();
+            let req = request("GET", uri, Body::empty());
+            async move {
+                let resp = app.oneshot(req).await.unwrap();
+                assert_eq!(resp.status(), StatusCode::OK);
+                let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+            }
+        };
+
+        let tags = r#"[{"key":"env","value":"prod"},{"key":"team","value":"ops"}]"#;
+        let resp = app
+            .clone()
+            .oneshot(request(
+                "PUT",
+                "/api/namespaces/bucket/tags",
+                Body::from(tags),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert_eq!(db.get_namespace_tags(ns_id).unwrap().len(), 2);
+        assert_eq!(
+            get_json("/api/namespaces/bucket/tags").await,
+            serde_json::json!([{"key": "env", "value": "prod"}, {"key": "team", "value": "ops"}])
+        );
+        let namespaces = get_json("/api/namespaces").await;
+        assert_eq!(namespaces[0]["tags"][1]["key"], "team");
+
+        // More than 50 tags is rejected and leaves the tags as they were
+        let too_many: Vec<_> = (0..51)
+            .map(|i| serde_json::json!({"key": format!("k{i}"), "value": "v"}))
+            .collect();
+        let body = Body::from(serde_json::to_vec(&too_many).unwrap());
+        let resp = app
+            .clone()
+            .oneshot(request("PUT", "/api/namespaces/bucket/tags", body))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(db.get_namespace_tags(ns_id).unwrap().len(), 2);
+
+        let resp = app
+            .clone()
+            .oneshot(request(
+                "DELETE",
+                "/api/namespaces/bucket/tags",
+                Body::empty(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert!(db.get_namespace_tags(ns_id).unwrap().is_empty());
+
+        let resp = app
+            .oneshot(request(
+                "GET",
+                "/api/namespaces/missing/tags",
+                Body::empty(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -7,12 +7,22 @@ use axum::http::StatusCode;
 use crate::models::StatusResponse;
 use crate::state::AppState;
 
+#[utoipa::path(
+    get,
+    path = "/api/status",
+    tag = "status",
+    responses(
+        (status = 200, description = "System overview", body = StatusResponse),
+        (status = 401, description = "Missing or invalid credentials"),
+    )
+)]
 pub async fn get_status(
     State(state): State<Arc<AppState>>,
 ) -> Result<Json<StatusResponse>, (StatusCode, &'static str)> {
     let db = state
-        .db
-        .lock()
+        .readonly_db
+        .get()
+        .await
         .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "internal error"))?;
     let providers = db.list_providers().unwrap_or_default();
     let (total_chunks, _) = db.chunk_stats().unwrap_or((0, 0));
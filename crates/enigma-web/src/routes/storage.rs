@@ -1,18 +1,39 @@
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use axum::Json;
-use axum::extract::State;
+use axum::extract::{Query, State};
 use axum::http::StatusCode;
+use axum::{Extension, Json};
+use enigma_core::manifest::HISTOGRAM_BUCKET_KB;
+use serde::Deserialize;
+use utoipa::IntoParams;
 
-use crate::models::{BackupResponse, ChunkStatsResponse, ProviderResponse};
+use crate::auth::Claims;
+use crate::models::{
+    BackupResponse, ChunkHistogramResponse, ChunkStatsResponse, CrossNamespaceDedupEntryResponse,
+    CrossNamespaceDedupResponse, DedupStatsResponse, GlobalDedupStatsResponse,
+    HistogramBucketResponse, InventoryStatusResponse, PresignResponse, ProviderChunkSizeResponse,
+    ProviderResponse, ProviderUsageResponse, S3AccessLogResponse,
+};
 use crate::state::AppState;
 
+#[utoipa::path(
+    get,
+    path = "/api/storage/providers",
+    tag = "storage",
+    responses(
+        (status = 200, description = "Configured storage providers", body = Vec<ProviderResponse>),
+        (status = 401, description = "Missing or invalid credentials"),
+    )
+)]
 pub async fn get_providers(
     State(state): State<Arc<AppState>>,
 ) -> Result<Json<Vec<ProviderResponse>>, (StatusCode, &'static str)> {
     let db = state
-        .db
-        .lock()
+        .readonly_db
+        .get()
+        .await
         .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "internal error"))?;
     let providers = db.list_providers().unwrap_or_default();
     Ok(Json(
@@ -30,26 +51,288 @@ pub async fn get_providers(
     ))
 }
 
+/// GET /api/storage/providers/usage
+///
+/// Space used on each storage backend. Answers are cached for
+/// `usage_cache_seconds` since cloud backends list every object.
+#[utoipa::path(
+    get,
+    path = "/api/storage/providers/usage",
+    tag = "storage",
+    responses(
+        (status = 200, description = "Bytes and objects stored per provider", body = Vec<ProviderUsageResponse>),
+        (status = 401, description = "Missing or invalid credentials"),
+    )
+)]
+pub async fn get_provider_usage(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<ProviderUsageResponse>>, (StatusCode, &'static str)> {
+    // Held while gathering, so concurrent requests wait for one listing
+    let mut cached = state.usage_cache.entry.lock().await;
+    if let Some((at, usage)) = cached.as_ref()
+        && at.elapsed() < state.usage_cache.ttl
+    {
+        return Ok(Json(usage.clone()));
+    }
+
+    let ids: HashMap<String, i64> = {
+        let db = state
+            .readonly_db
+            .get()
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "internal error"))?;
+        db.list_providers()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|p| (p.name, p.id))
+            .collect()
+    };
+    let usage = futures::future::join_all(
+        state
+            .storage_providers
+            .iter()
+            .map(|provider| provider.get_usage()),
+    )
+    .await;
+    let usage: Vec<ProviderUsageResponse> = state
+        .storage_providers
+        .iter()
+        .zip(usage)
+        .map(|(provider, usage)| {
+            let name = provider.name().to_string();
+            let provider_id = ids.get(&name).copied();
+            match usage {
+                Ok(u) => ProviderUsageResponse {
+                    provider_id,
+                    name,
+                    used_bytes: u.used_bytes,
+                    available_bytes: u.available_bytes,
+                    object_count: u.object_count,
+                    filled_percent: u.available_bytes.map(|free| {
+                        let total = u.used_bytes + free;
+                        if total == 0 {
+                            0.0
+                        } else {
+                            u.used_bytes as f64 * 100.0 / total as f64
+                        }
+                    }),
+                    error: None,
+                },
+                Err(e) => {
+                    tracing::warn!(provider = %name, "Failed to read storage usage: {e}");
+                    ProviderUsageResponse {
+                        provider_id,
+                        name,
+                        used_bytes: 0,
+                        available_bytes: None,
+                        object_count: 0,
+                        filled_percent: None,
+                        error: Some(e.to_string()),
+                    }
+                }
+            }
+        })
+        .collect();
+
+    *cached = Some((Instant::now(), usage.clone()));
+    Ok(Json(usage))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/storage/chunks/stats",
+    tag = "storage",
+    responses(
+        (status = 200, description = "Chunk totals", body = ChunkStatsResponse),
+        (status = 401, description = "Missing or invalid credentials"),
+    )
+)]
 pub async fn get_chunk_stats(
     State(state): State<Arc<AppState>>,
 ) -> Result<Json<ChunkStatsResponse>, (StatusCode, &'static str)> {
     let db = state
-        .db
-        .lock()
+        .readonly_db
+        .get()
+        .await
         .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "internal error"))?;
     let (total, orphans) = db.chunk_stats().unwrap_or((0, 0));
+    let latest_backup_dedup = db.latest_backup().ok().flatten().and_then(|b| {
+        let stats = db.backup_dedup_stats(&b.id).ok()?;
+        Some(DedupStatsResponse {
+            backup_id: b.id,
+            total_chunks: stats.total_chunks,
+            unique_chunks: stats.unique_chunks,
+            dedup_chunks: stats.dedup_chunks,
+            total_bytes_logical: stats.total_bytes_logical,
+            total_bytes_stored: stats.total_bytes_stored,
+            dedup_ratio_percent: stats.dedup_ratio_percent,
+        })
+    });
     Ok(Json(ChunkStatsResponse {
         total_chunks: total,
         orphan_chunks: orphans,
+        latest_backup_dedup,
+    }))
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct HistogramQuery {
+    /// Only count the chunks of this backup.
+    pub backup_id: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/storage/chunks/histogram",
+    tag = "storage",
+    params(HistogramQuery),
+    responses(
+        (status = 200, description = "Chunk size distribution and compression", body = ChunkHistogramResponse),
+        (status = 401, description = "Missing or invalid credentials"),
+    )
+)]
+pub async fn get_chunk_histogram(
+    State(state): State<Arc<AppState>>,
+    Query(q): Query<HistogramQuery>,
+) -> Result<Json<ChunkHistogramResponse>, (StatusCode, &'static str)> {
+    let db = state
+        .readonly_db
+        .get()
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "internal error"))?;
+    let backup_id = q.backup_id.as_deref();
+    let internal = |_| (StatusCode::INTERNAL_SERVER_ERROR, "internal error");
+    let buckets = db.chunk_size_histogram(backup_id).map_err(internal)?;
+    let compression_ratio = db.compression_ratio(backup_id).map_err(internal)?;
+    let providers = db.provider_chunk_sizes(backup_id).map_err(internal)?;
+    Ok(Json(ChunkHistogramResponse {
+        backup_id: q.backup_id.clone(),
+        bucket_width_kb: HISTOGRAM_BUCKET_KB,
+        buckets: buckets
+            .into_iter()
+            .map(|(start_kb, count)| HistogramBucketResponse {
+                start_kb,
+                end_kb: start_kb + HISTOGRAM_BUCKET_KB,
+                count,
+            })
+            .collect(),
+        compression_ratio,
+        providers: providers
+            .into_iter()
+            .map(
+                |(provider_id, name, chunks, avg_chunk_bytes)| ProviderChunkSizeResponse {
+                    provider_id,
+                    name,
+                    chunks,
+                    avg_chunk_bytes,
+                },
+            )
+            .collect(),
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/storage/dedup",
+    tag = "storage",
+    responses(
+        (status = 200, description = "Deduplication across all completed backups", body = GlobalDedupStatsResponse),
+        (status = 401, description = "Missing or invalid credentials"),
+    )
+)]
+pub async fn get_dedup_stats(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<GlobalDedupStatsResponse>, (StatusCode, &'static str)> {
+    let db = state
+        .readonly_db
+        .get()
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "internal error"))?;
+    let stats = db
+        .global_dedup_stats()
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "internal error"))?;
+    Ok(Json(GlobalDedupStatsResponse {
+        backups: stats.backups,
+        total_chunks: stats.total_chunks,
+        shared_chunks: stats.shared_chunks,
+        dedup_chunks: stats.dedup_chunks,
+        total_bytes_logical: stats.total_bytes_logical,
+        total_bytes_stored: stats.total_bytes_stored,
+        dedup_ratio_percent: stats.dedup_ratio_percent,
+    }))
+}
+
+/// Largest page of the cross-namespace dedup report.
+const CROSS_NAMESPACE_MAX_LIMIT: u32 = 1000;
+
+#[derive(Deserialize, IntoParams)]
+pub struct CrossNamespaceQuery {
+    /// Entries per page (default and maximum 1000).
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/storage/dedup/cross-namespace",
+    tag = "storage",
+    params(CrossNamespaceQuery),
+    responses(
+        (status = 200, description = "Chunks shared between namespaces", body = CrossNamespaceDedupResponse),
+        (status = 401, description = "Missing or invalid credentials"),
+    )
+)]
+pub async fn get_cross_namespace_dedup(
+    State(state): State<Arc<AppState>>,
+    Query(q): Query<CrossNamespaceQuery>,
+) -> Result<Json<CrossNamespaceDedupResponse>, (StatusCode, &'static str)> {
+    let db = state
+        .readonly_db
+        .get()
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "internal error"))?;
+    let limit = q
+        .limit
+        .unwrap_or(CROSS_NAMESPACE_MAX_LIMIT)
+        .min(CROSS_NAMESPACE_MAX_LIMIT);
+    let offset = q.offset.unwrap_or(0);
+    let internal = |_| (StatusCode::INTERNAL_SERVER_ERROR, "internal error");
+    let entries = db
+        .cross_namespace_dedup_page(limit, offset)
+        .map_err(internal)?;
+    let savings_bytes = db.cross_namespace_savings_bytes().map_err(internal)?;
+    Ok(Json(CrossNamespaceDedupResponse {
+        entries: entries
+            .into_iter()
+            .map(|e| CrossNamespaceDedupEntryResponse {
+                chunk_hash: e.chunk_hash,
+                namespaces: e.namespaces,
+                ref_count: e.ref_count,
+                size_bytes: e.size_bytes,
+            })
+            .collect(),
+        limit,
+        offset,
+        savings_bytes,
     }))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/storage/backups",
+    tag = "storage",
+    responses(
+        (status = 200, description = "Backup history, newest first", body = Vec<BackupResponse>),
+        (status = 401, description = "Missing or invalid credentials"),
+    )
+)]
 pub async fn get_backups(
     State(state): State<Arc<AppState>>,
 ) -> Result<Json<Vec<BackupResponse>>, (StatusCode, &'static str)> {
     let db = state
-        .db
-        .lock()
+        .readonly_db
+        .get()
+        .await
         .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "internal error"))?;
     let backups = db.list_backups().unwrap_or_default();
     Ok(Json(
@@ -69,3 +352,673 @@ pub async fn get_backups(
             .collect(),
     ))
 }
+
+/// Largest page of S3 access log entries.
+const ACCESS_LOG_MAX_LIMIT: u32 = 1000;
+
+#[derive(Deserialize, IntoParams)]
+pub struct AccessLogQuery {
+    /// Only entries for this bucket.
+    pub bucket: Option<String>,
+    /// Earliest timestamp (RFC 3339, inclusive).
+    pub from: Option<String>,
+    /// Latest timestamp (RFC 3339, inclusive).
+    pub to: Option<String>,
+    /// Entries to return (default and maximum 1000).
+    pub limit: Option<u32>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/storage/access-logs",
+    tag = "storage",
+    params(AccessLogQuery),
+    responses(
+        (status = 200, description = "S3 access log entries, oldest first", body = Vec<S3AccessLogResponse>),
+        (status = 401, description = "Missing or invalid credentials"),
+    )
+)]
+pub async fn get_access_logs(
+    State(state): State<Arc<AppState>>,
+    Query(q): Query<AccessLogQuery>,
+) -> Result<Json<Vec<S3AccessLogResponse>>, (StatusCode, &'static str)> {
+    let db = state
+        .readonly_db
+        .get()
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "internal error"))?;
+    let limit = q
+        .limit
+        .unwrap_or(ACCESS_LOG_MAX_LIMIT)
+        .min(ACCESS_LOG_MAX_LIMIT);
+    let entries = db
+        .list_s3_logs(
+            q.bucket.as_deref(),
+            q.from.as_deref().unwrap_or(""),
+            q.to.as_deref().unwrap_or("9999"),
+            limit,
+        )
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "internal error"))?;
+    Ok(Json(
+        entries
+            .into_iter()
+            .map(|e| S3AccessLogResponse {
+                bucket: e.bucket,
+                key: e.key,
+                operation: e.operation,
+                requester_ip: e.requester_ip,
+                timestamp: e.timestamp,
+                status_code: e.status_code,
+                bytes_transferred: e.bytes_transferred,
+            })
+            .collect(),
+    ))
+}
+
+/// GET /api/storage/inventory/status
+///
+/// Date and size of the latest S3 inventory report.
+#[utoipa::path(
+    get,
+    path = "/api/storage/inventory/status",
+    tag = "storage",
+    responses(
+        (status = 200, description = "Latest inventory report", body = InventoryStatusResponse),
+        (status = 401, description = "Missing or invalid credentials"),
+    )
+)]
+pub async fn get_inventory_status(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<InventoryStatusResponse>, (StatusCode, &'static str)> {
+    let db = state
+        .readonly_db
+        .get()
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "internal error"))?;
+    let report = db
+        .latest_inventory_report()
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "internal error"))?;
+    Ok(Json(InventoryStatusResponse {
+        last_generated_at: report.as_ref().map(|r| r.generated_at.clone()),
+        key: report.as_ref().map(|r| r.key.clone()),
+        size: report.as_ref().map(|r| r.size),
+        objects: report.map(|r| r.rows),
+    }))
+}
+
+/// Longest a presigned URL may be valid for: GCS refuses more than 7 days.
+const PRESIGN_MAX_TTL_SECONDS: u64 = 7 * 24 * 3600;
+
+#[derive(Deserialize, IntoParams)]
+pub struct PresignQuery {
+    /// Manifest id of the provider holding the chunk.
+    pub provider_id: i64,
+    /// Key of the chunk on that provider.
+    pub storage_key: String,
+    /// URL lifetime in seconds (default: 900, at most 604800).
+    pub ttl_seconds: Option<u64>,
+}
+
+/// GET /api/storage/presign
+///
+/// A signed URL to download a chunk straight from its provider, for
+/// debugging. Only providers that can sign URLs (GCS with
+/// `gcs_signing_key_path`) support it. Admin account only.
+#[utoipa::path(
+    get,
+    path = "/api/storage/presign",
+    tag = "storage",
+    params(PresignQuery),
+    responses(
+        (status = 200, description = "Signed download URL", body = PresignResponse),
+        (status = 400, description = "TTL out of range, or the provider cannot sign URLs"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Not the admin account"),
+        (status = 404, description = "No such provider"),
+    )
+)]
+pub async fn presign_chunk(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Query(q): Query<PresignQuery>,
+) -> Result<Json<PresignResponse>, (StatusCode, &'static str)> {
+    if claims.sub != state.admin_user {
+        return Err((StatusCode::FORBIDDEN, "admin only"));
+    }
+    let ttl = q.ttl_seconds.unwrap_or(900);
+    if !(1..=PRESIGN_MAX_TTL_SECONDS).contains(&ttl) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "ttl_seconds must be between 1 and 604800",
+        ));
+    }
+
+    let name = {
+        let db = state
+            .readonly_db
+            .get()
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "internal error"))?;
+        db.list_providers()
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "internal error"))?
+            .into_iter()
+            .find(|p| p.id == q.provider_id)
+            .map(|p| p.name)
+    };
+    let provider = name
+        .and_then(|name| {
+            state
+                .storage_providers
+                .iter()
+                .find(|provider| provider.name() == name)
+        })
+        .ok_or((StatusCode::NOT_FOUND, "no such provider"))?;
+    let url = provider
+        .signed_url(&q.storage_key, Duration::from_secs(ttl))
+        .map_err(|e| {
+            tracing::warn!(provider = %provider.name(), "Failed to sign URL: {e}");
+            (StatusCode::BAD_REQUEST, "provider cannot sign URLs")
+        })?;
+    Ok(Json(PresignResponse {
+        url,
+        expires_in: ttl,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use enigma_core::manifest::ManifestDb;
+    use enigma_core::types::ProviderType;
+    use enigma_storage::mock::{MockMethod, MockStorageProvider};
+    use enigma_storage::provider::StorageProvider;
+    use tower::ServiceExt;
+
+    use crate::auth::create_token;
+    use crate::routes::build_router;
+    use crate::state::{RateLimitConfig, UsageCache};
+
+    fn app_state(dir: &std::path::Path, db_path: &std::path::Path) -> Arc<AppState> {
+        app_state_with_providers(dir, db_path, Vec::new(), UsageCache::default())
+    }
+
+    fn app_state_with_providers(
+        dir: &std::path::Path,
+        db_path: &std::path::Path,
+        storage_providers: Vec<Arc<dyn StorageProvider>>,
+        usage_cache: UsageCache,
+    ) -> Arc<AppState> {
+        Arc::new(AppState {
+            rate_limit: RateLimitConfig {
+                requests_per_second: 1000.0,
+                burst: 1000,
+                per_ip: false,
+            },
+            storage_providers,
+            usage_cache,
+            ..AppState::for_tests_with_db(dir, db_path, 2)
+        })
+    }
+
+    async fn get_json(state: &Arc<AppState>, uri: &str) -> (StatusCode, serde_json::Value) {
+        let token = create_token("admin", &state.jwt_secret).unwrap();
+        let request = Request::builder()
+            .uri(uri)
+            .header("Authorization", format!("Bearer {token}"))
+            .body(Body::empty())
+            .unwrap();
+        let resp = build_router(state.clone()).oneshot(request).await.unwrap();
+        let status = resp.status();
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or_default())
+    }
+
+    #[tokio::test]
+    async fn provider_usage_reports_each_provider() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("manifest.db");
+        let db = ManifestDb::open(&db_path).unwrap();
+        let pid = db
+            .insert_provider("disk", ProviderType::Local, "/tmp/disk", None, 1)
+            .unwrap();
+
+        let disk = MockStorageProvider::new("disk");
+        disk.set_capacity(1000);
+        disk.insert("a", &[0; 150]);
+        disk.insert("b", &[0; 100]);
+        let cloud = MockStorageProvider::new("cloud");
+        cloud.insert("c", &[0; 42]);
+        let down = MockStorageProvider::new("down");
+        down.inject_failure(MockMethod::GetUsage, 0, "access denied");
+        let state = app_state_with_providers(
+            tmp.path(),
+            &db_path,
+            vec![Arc::new(disk), Arc::new(cloud), Arc::new(down)],
+            UsageCache::default(),
+        );
+
+        let (status, body) = get_json(&state, "/api/storage/providers/usage").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body,
+            serde_json::json!([
+                {
+                    "provider_id": pid,
+                    "name": "disk",
+                    "used_bytes": 250,
+                    "available_bytes": 750,
+                    "object_count": 2,
+                    "filled_percent": 25.0,
+                    "error": null,
+                },
+                {
+                    "provider_id": null,
+                    "name": "cloud",
+                    "used_bytes": 42,
+                    "available_bytes": null,
+                    "object_count": 1,
+                    "filled_percent": null,
+                    "error": null,
+                },
+                {
+                    "provider_id": null,
+                    "name": "down",
+                    "used_bytes": 0,
+                    "available_bytes": null,
+                    "object_count": 0,
+                    "filled_percent": null,
+                    "error": "access denied",
+                },
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn provider_usage_is_cached() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("manifest.db");
+        ManifestDb::open(&db_path).unwrap();
+
+        let mock = MockStorageProvider::new("p1");
+        mock.insert("a", b"one");
+        let state = app_state_with_providers(
+            tmp.path(),
+            &db_path,
+            vec![Arc::new(mock.clone())],
+            UsageCache::default(),
+        );
+        let (_, first) = get_json(&state, "/api/storage/providers/usage").await;
+        mock.insert("b", b"two");
+        let (_, second) = get_json(&state, "/api/storage/providers/usage").await;
+        assert_eq!(first, second);
+        assert_eq!(second[0]["object_count"], 1);
+        assert_eq!(mock.call_count(MockMethod::GetUsage), 1);
+
+        // With no time to live every request reads the providers again
+        let state = app_state_with_providers(
+            tmp.path(),
+            &db_path,
+            vec![Arc::new(mock.clone())],
+            UsageCache::new(std::time::Duration::ZERO),
+        );
+        let (_, fresh) = get_json(&state, "/api/storage/providers/usage").await;
+        assert_eq!(fresh[0]["object_count"], 2);
+        assert_eq!(mock.call_count(MockMethod::GetUsage), 2);
+    }
+
+    #[tokio::test]
+    async fn histogram_counts_chunks_per_bucket() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("manifest.db");
+        let db = ManifestDb::open(&db_path).unwrap();
+        let p1 = db
+            .insert_provider("p1", ProviderType::Local, "/tmp/p1", None, 1)
+            .unwrap();
+        let p2 = db
+            .insert_provider("p2", ProviderType::Local, "/tmp/p2", None, 1)
+            .unwrap();
+        db.create_backup("b1", "/src").unwrap();
+        let file_id = db
+            .insert_backup_file("b1", "f.bin", 0, None, "h", 2)
+            .unwrap();
+        let chunks = [
+            ("a", p1, 10_000, Some(5_000)),
+            ("b", p1, 30_000, Some(15_000)),
+            ("c", p2, 100_000, None),
+        ];
+        for (hash, pid, size, compressed) in chunks {
+            db.insert_or_dedup_chunk(hash, &[0u8; 12], "k", pid, hash, size, size, compressed)
+                .unwrap();
+        }
+        db.insert_file_chunk(file_id, "a", 0, 0).unwrap();
+        db.insert_file_chunk(file_id, "c", 1, 0).unwrap();
+
+        let state = app_state(tmp.path(), &db_path);
+        let token = create_token("admin", &state.jwt_secret).unwrap();
+
+        let get = |uri: &str| {
+            let request = Request::builder()
+                .uri(uri)
+                .header("Authorization", format!("Bearer {token}"))
+                .body(Body::empty())
+                .unwrap();
+            build_router(state.clone()).oneshot(request)
+        };
+
+        let resp = get("/api/storage/chunks/histogram").await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let all: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(all["bucket_width_kb"], 64);
+        assert_eq!(
+            all["buckets"],
+            serde_json::json!([
+                { "start_kb": 0, "end_kb": 64, "count": 2 },
+                { "start_kb": 64, "end_kb": 128, "count": 1 },
+            ])
+        );
+        assert_eq!(all["compression_ratio"], 1.0 - 120_000.0 / 140_000.0);
+        assert_eq!(all["providers"][0]["name"], "p1");
+        assert_eq!(all["providers"][0]["chunks"], 2);
+        assert_eq!(all["providers"][0]["avg_chunk_bytes"], 20_000);
+        assert_eq!(all["providers"][1]["avg_chunk_bytes"], 100_000);
+
+        let resp = get("/api/storage/chunks/histogram?backup_id=b1")
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let backup: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(backup["backup_id"], "b1");
+        assert_eq!(backup["buckets"][0]["count"], 1);
+        assert_eq!(backup["buckets"][1]["count"], 1);
+        assert_eq!(backup["providers"][0]["chunks"], 1);
+        assert_eq!(backup["providers"][0]["avg_chunk_bytes"], 10_000);
+    }
+
+    #[tokio::test]
+    async fn cross_namespace_dedup_is_paginated() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("manifest.db");
+        let db = ManifestDb::open(&db_path).unwrap();
+        let pid = db
+            .insert_provider("p1", ProviderType::Local, "/tmp/p1", None, 1)
+            .unwrap();
+        let a = db.create_namespace("a").unwrap();
+        let b = db.create_namespace("b").unwrap();
+        for (ns, hashes) in [(a, ["x", "y"]), (b, ["x", "y"])] {
+            let oid = db.insert_object(ns, "obj", 1, "e", None, 2, "k").unwrap();
+            for (idx, hash) in hashes.iter().enumerate() {
+                let size = if *hash == "x" { 300 } else { 100 };
+                db.insert_or_dedup_chunk(hash, &[0u8; 12], "k", pid, hash, size, size, None)
+                    .unwrap();
+                db.insert_object_chunk(oid, hash, idx as u32, 0).unwrap();
+            }
+        }
+        let state = app_state(tmp.path(), &db_path);
+        let token = create_token("admin", &state.jwt_secret).unwrap();
+
+        let get = |uri: &str| {
+            let request = Request::builder()
+                .uri(uri)
+                .header("Authorization", format!("Bearer {token}"))
+                .body(Body::empty())
+                .unwrap();
+            build_router(state.clone()).oneshot(request)
+        };
+
+        let resp = get("/api/storage/dedup/cross-namespace?limit=5000")
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let all: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(all["limit"], 1000);
+        assert_eq!(all["savings_bytes"], 400);
+        assert_eq!(
+            all["entries"][0],
+            serde_json::json!({
+                "chunk_hash": "x",
+                "namespaces": ["a", "b"],
+                "ref_count": 2,
+                "size_bytes": 300,
+            })
+        );
+        assert_eq!(all["entries"].as_array().unwrap().len(), 2);
+
+        let resp = get("/api/storage/dedup/cross-namespace?limit=1&offset=1")
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let page: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(page["entries"].as_array().unwrap().len(), 1);
+        assert_eq!(page["entries"][0]["chunk_hash"], "y");
+        assert_eq!(page["savings_bytes"], 400);
+    }
+
+    #[tokio::test]
+    async fn access_logs_filter_by_bucket_and_time() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("manifest.db");
+        let db = ManifestDb::open(&db_path).unwrap();
+        for (bucket, timestamp) in [
+            ("photos", "2026-01-01T10:00:00Z"),
+            ("photos", "2026-01-02T10:00:00Z"),
+            ("docs", "2026-01-02T11:00:00Z"),
+        ] {
+            db.insert_s3_log(&enigma_core::types::S3AccessLogEntry {
+                bucket: bucket.to_string(),
+                key: Some("a.txt".to_string()),
+                operation: "GetObject".to_string(),
+                requester_ip: Some("10.0.0.1".to_string()),
+                timestamp: timestamp.to_string(),
+                status_code: 200,
+                bytes_transferred: 5,
+            })
+            .unwrap();
+        }
+        let state = app_state(tmp.path(), &db_path);
+
+        let (status, all) = get_json(&state, "/api/storage/access-logs").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(all.as_array().unwrap().len(), 3);
+        assert_eq!(
+            all[0],
+            serde_json::json!({
+                "bucket": "photos",
+                "key": "a.txt",
+                "operation": "GetObject",
+                "requester_ip": "10.0.0.1",
+                "timestamp": "2026-01-01T10:00:00Z",
+                "status_code": 200,
+                "bytes_transferred": 5,
+            })
+        );
+
+        let (_, photos) = get_json(
+            &state,
+            "/api/storage/access-logs?bucket=photos&from=2026-01-02T00:00:00Z",
+        )
+        .await;
+        assert_eq!(photos.as_array().unwrap().len(), 1);
+        assert_eq!(photos[0]["timestamp"], "2026-01-02T10:00:00Z");
+
+        let (_, page) = get_json(&state, "/api/storage/access-logs?limit=2").await;
+        assert_eq!(page.as_array().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn inventory_status_reports_the_latest_report() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("manifest.db");
+        let db = ManifestDb::open(&db_path).unwrap();
+        let state = app_state(tmp.path(), &db_path);
+
+        let (status, none) = get_json(&state, "/api/storage/inventory/status").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(none["last_generated_at"], serde_json::Value::Null);
+
+        db.insert_inventory_report(&enigma_core::types::InventoryReport {
+            key: "inventory/2026-10-16/inventory.csv".to_string(),
+            size: 4096,
+            rows: 100,
+            generated_at: "2026-10-16T00:00:00Z".to_string(),
+        })
+        .unwrap();
+        let (_, latest) = get_json(&state, "/api/storage/inventory/status").await;
+        assert_eq!(
+            latest,
+            serde_json::json!({
+                "last_generated_at": "2026-10-16T00:00:00Z",
+                "key": "inventory/2026-10-16/inventory.csv",
+                "size": 4096,
+                "objects": 100,
+            })
+        );
+    }
+
+    /// A mock that signs URLs the way a GCS provider with a key would.
+    struct SigningProvider(MockStorageProvider);
+
+    #[async_trait::async_trait]
+    impl StorageProvider for SigningProvider {
+        async fn upload_chunk(&self, key: &str, data: &[u8]) -> anyhow::Result<()> {
+            self.0.upload_chunk(key, data).await
+        }
+
+        async fn download_chunk(&self, key: &str) -> anyhow::Result<Vec<u8>> {
+            self.0.download_chunk(key).await
+        }
+
+        async fn delete_chunk(&self, key: &str) -> anyhow::Result<()> {
+            self.0.delete_chunk(key).await
+        }
+
+        async fn chunk_exists(&self, key: &str) -> anyhow::Result<bool> {
+            self.0.chunk_exists(key).await
+        }
+
+        async fn test_connection(&self) -> anyhow::Result<()> {
+            self.0.test_connection().await
+        }
+
+        async fn get_usage(&self) -> anyhow::Result<enigma_storage::provider::ProviderUsage> {
+            self.0.get_usage().await
+        }
+
+        fn signed_url(&self, key: &str, expires_in: Duration) -> anyhow::Result<String> {
+            Ok(format!(
+                "https://signed.example/{key}?expires={}",
+                expires_in.as_secs()
+            ))
+        }
+
+        fn name(&self) -> &str {
+            self.0.name()
+        }
+    }
+
+    #[tokio::test]
+    async fn presign_returns_the_providers_signed_url() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("manifest.db");
+        let db = ManifestDb::open(&db_path).unwrap();
+        let gcs = db
+            .insert_provider("gcs", ProviderType::Gcs, "enigma-chunks", None, 1)
+            .unwrap();
+        let state = app_state_with_providers(
+            tmp.path(),
+            &db_path,
+            vec![Arc::new(SigningProvider(MockStorageProvider::new("gcs")))],
+            UsageCache::default(),
+        );
+
+        let (status, body) = get_json(
+            &state,
+            &format!("/api/storage/presign?provider_id={gcs}&storage_key=ab/cd"),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "url": "https://signed.example/ab/cd?expires=900",
+                "expires_in": 900,
+            })
+        );
+
+        let (_, body) = get_json(
+            &state,
+            &format!("/api/storage/presign?provider_id={gcs}&storage_key=ab&ttl_seconds=60"),
+        )
+        .await;
+        assert_eq!(body["url"], "https://signed.example/ab?expires=60");
+    }
+
+    #[tokio::test]
+    async fn presign_refusals() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("manifest.db");
+        let db = ManifestDb::open(&db_path).unwrap();
+        let gcs = db
+            .insert_provider("gcs", ProviderType::Gcs, "enigma-chunks", None, 1)
+            .unwrap();
+        let disk = db
+            .insert_provider("disk", ProviderType::Local, "/tmp/disk", None, 1)
+            .unwrap();
+        let state = app_state_with_providers(
+            tmp.path(),
+            &db_path,
+            vec![
+                Arc::new(SigningProvider(MockStorageProvider::new("gcs"))),
+                Arc::new(MockStorageProvider::new("disk")),
+            ],
+            UsageCache::default(),
+        );
+
+        for (uri, expected) in [
+            (
+                format!("/api/storage/presign?provider_id={gcs}&storage_key=k&ttl_seconds=0"),
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                format!("/api/storage/presign?provider_id={gcs}&storage_key=k&ttl_seconds=604801"),
+                StatusCode::BAD_REQUEST,
+            ),
+            // Mock providers cannot sign URLs
+            (
+                format!("/api/storage/presign?provider_id={disk}&storage_key=k"),
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                "/api/storage/presign?provider_id=999&storage_key=k".to_string(),
+                StatusCode::NOT_FOUND,
+            ),
+        ] {
+            assert_eq!(get_json(&state, &uri).await.0, expected, "{uri}");
+        }
+
+        // Only the admin account may presign
+        let token = create_token("carol", &state.jwt_secret).unwrap();
+        let request = Request::builder()
+            .uri(format!(
+                "/api/storage/presign?provider_id={gcs}&storage_key=k"
+            ))
+            .header("Authorization", format!("Bearer {token}"))
+            .body(Body::empty())
+            .unwrap();
+        let resp = build_router(state).oneshot(request).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    }
+}
@@ -1,15 +1,31 @@
 use std::sync::Arc;
 
 use axum::Json;
-use axum::extract::{Path, State};
+use axum::extract::{Path, Query, State};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 
-use enigma_auth::middleware::require_permission;
 use enigma_auth::AuthUser;
 use enigma_auth::error::AuthError;
+use enigma_auth::middleware::require_permission;
 
+use crate::correlation::CorrelationId;
 use crate::models::{CreateTokenResponse, TokenResponse};
 use crate::state::AppState;
 
+/// GET /api/auth/tokens
+///
+/// The caller's API tokens.
+#[utoipa::path(
+    get,
+    path = "/api/auth/tokens",
+    tag = "auth",
+    responses(
+        (status = 200, description = "The caller's API tokens", body = Vec<TokenResponse>),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Missing tokens:own permission"),
+    )
+)]
 pub async fn list_tokens(
     auth_user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -28,13 +44,32 @@ pub async fn list_tokens(
                 expires_at: t.expires_at,
                 last_used_at: t.last_used_at,
                 created_at: t.created_at,
+                allowed_ips: t.allowed_ips,
+                namespace_prefix: t.namespace_prefix,
             })
             .collect(),
     ))
 }
 
+/// POST /api/auth/tokens
+///
+/// Issue an API token for the caller. The raw `egt_` token is in the
+/// answer and cannot be read back later.
+#[utoipa::path(
+    post,
+    path = "/api/auth/tokens",
+    tag = "auth",
+    request_body = enigma_auth::CreateTokenRequest,
+    responses(
+        (status = 200, description = "Token issued", body = CreateTokenResponse),
+        (status = 400, description = "Missing name or invalid CIDR"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Missing tokens:own permission"),
+    )
+)]
 pub async fn create_token(
     auth_user: AuthUser,
+    correlation_id: CorrelationId,
     State(state): State<Arc<AppState>>,
     Json(req): Json<enigma_auth::CreateTokenRequest>,
 ) -> Result<Json<CreateTokenResponse>, AuthError> {
@@ -49,6 +84,8 @@ pub async fn create_token(
     let token_prefix = &raw_token[..12]; // "egt_" + 8 hex chars
     let scopes = req.scopes.as_deref().unwrap_or("*");
 
+    let allowed_ips = normalize_allowed_ips(req.allowed_ips.as_deref())?;
+
     let expires_at = req.expires_in_days.map(|days| {
         let dt = chrono::Utc::now() + chrono::Duration::days(days as i64);
         dt.format("%Y-%m-%d %H:%M:%S").to_string()
@@ -65,6 +102,25 @@ pub async fn create_token(
             expires_at.as_deref(),
         )
         .await?;
+    // The raw token is not handed out yet, so it is never usable unrestricted
+    let api_token = match allowed_ips {
+        Some(ref ips) => {
+            state
+                .auth_store
+                .update_token_allowed_ips(&api_token.id, Some(ips.as_str()))
+                .await?
+        }
+        None => api_token,
+    };
+    let api_token = match req.namespace_prefix.as_deref().filter(|p| !p.is_empty()) {
+        Some(prefix) => {
+            state
+                .auth_store
+                .update_token_namespace_prefix(&api_token.id, Some(prefix))
+                .await?
+        }
+        None => api_token,
+    };
 
     let _ = state
         .auth_store
@@ -73,6 +129,7 @@ pub async fn create_token(
             "token.create",
             Some(&req.name),
             None,
+            &correlation_id.0,
         )
         .await;
 
@@ -85,13 +142,162 @@ pub async fn create_token(
             expires_at: api_token.expires_at,
             last_used_at: api_token.last_used_at,
             created_at: api_token.created_at,
+            allowed_ips: api_token.allowed_ips,
+            namespace_prefix: api_token.namespace_prefix,
         },
         raw_token,
     }))
 }
 
+/// `PUT /api/auth/tokens/{id}/scopes` — replace a token's scopes in place,
+/// without revoking and re-issuing it.
+#[utoipa::path(
+    put,
+    path = "/api/auth/tokens/{id}/scopes",
+    tag = "auth",
+    params(("id" = String, Path, description = "Token ID")),
+    request_body = enigma_auth::UpdateTokenScopesRequest,
+    responses(
+        (status = 200, description = "Scopes replaced", body = TokenResponse),
+        (status = 400, description = "Empty scopes"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Not the caller's token"),
+        (status = 404, description = "No such token"),
+    )
+)]
+pub async fn update_token_scopes(
+    auth_user: AuthUser,
+    correlation_id: CorrelationId,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Json(req): Json<enigma_auth::UpdateTokenScopesRequest>,
+) -> Result<Json<TokenResponse>, AuthError> {
+    require_permission(&auth_user, "tokens:own")?;
+
+    if req.scopes.trim().is_empty() {
+        return Err(AuthError::InvalidInput("scopes required".into()));
+    }
+
+    // Check token ownership unless admin
+    if !enigma_auth::has_permission(&auth_user.permissions, "tokens:admin") {
+        let tokens = state.auth_store.list_tokens(&auth_user.user_id).await?;
+        if !tokens.iter().any(|t| t.id == id) {
+            return Err(AuthError::Forbidden("not your token".into()));
+        }
+    }
+
+    let api_token = state
+        .auth_store
+        .update_token_scopes(&id, req.scopes.trim())
+        .await?;
+
+    let _ = state
+        .auth_store
+        .log_audit(
+            Some(&auth_user.user_id),
+            "token.update_scopes",
+            Some(&id),
+            None,
+            &correlation_id.0,
+        )
+        .await;
+
+    Ok(Json(TokenResponse {
+        id: api_token.id,
+        name: api_token.name,
+        token_prefix: api_token.token_prefix,
+        scopes: api_token.scopes,
+        expires_at: api_token.expires_at,
+        last_used_at: api_token.last_used_at,
+        created_at: api_token.created_at,
+        allowed_ips: api_token.allowed_ips,
+        namespace_prefix: api_token.namespace_prefix,
+    }))
+}
+
+/// `PUT /api/auth/tokens/{id}/allowed_ips` — restrict a token to a
+/// comma-separated list of source CIDRs; an empty or missing list lifts the
+/// restriction.
+#[utoipa::path(
+    put,
+    path = "/api/auth/tokens/{id}/allowed_ips",
+    tag = "auth",
+    params(("id" = String, Path, description = "Token ID")),
+    request_body = enigma_auth::UpdateTokenAllowedIpsRequest,
+    responses(
+        (status = 200, description = "Restriction replaced", body = TokenResponse),
+        (status = 400, description = "Invalid CIDR"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Not the caller's token"),
+        (status = 404, description = "No such token"),
+    )
+)]
+pub async fn update_token_allowed_ips(
+    auth_user: AuthUser,
+    correlation_id: CorrelationId,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Json(req): Json<enigma_auth::UpdateTokenAllowedIpsRequest>,
+) -> Result<Json<TokenResponse>, AuthError> {
+    require_permission(&auth_user, "tokens:own")?;
+
+    let allowed_ips = normalize_allowed_ips(req.allowed_ips.as_deref())?;
+
+    // Check token ownership unless admin
+    if !enigma_auth::has_permission(&auth_user.permissions, "tokens:admin") {
+        let tokens = state.auth_store.list_tokens(&auth_user.user_id).await?;
+        if !tokens.iter().any(|t| t.id == id) {
+            return Err(AuthError::Forbidden("not your token".into()));
+        }
+    }
+
+    let api_token = state
+        .auth_store
+        .update_token_allowed_ips(&id, allowed_ips.as_deref())
+        .await?;
+
+    let _ = state
+        .auth_store
+        .log_audit(
+            Some(&auth_user.user_id),
+            "token.update_allowed_ips",
+            Some(&id),
+            None,
+            &correlation_id.0,
+        )
+        .await;
+
+    Ok(Json(TokenResponse {
+        id: api_token.id,
+        name: api_token.name,
+        token_prefix: api_token.token_prefix,
+        scopes: api_token.scopes,
+        expires_at: api_token.expires_at,
+        last_used_at: api_token.last_used_at,
+        created_at: api_token.created_at,
+        allowed_ips: api_token.allowed_ips,
+        namespace_prefix: api_token.namespace_prefix,
+    }))
+}
+
+/// DELETE /api/auth/tokens/{id}
+///
+/// Revoke a token; admins holding `tokens:admin` may revoke anyone's.
+#[utoipa::path(
+    delete,
+    path = "/api/auth/tokens/{id}",
+    tag = "auth",
+    params(("id" = String, Path, description = "Token ID")),
+    responses(
+        (status = 200, description = "Token revoked"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Not the caller's token"),
+        (status = 404, description = "No such token"),
+    )
+)]
 pub async fn revoke_token(
     auth_user: AuthUser,
+    correlation_id: CorrelationId,
     State(state): State<Arc<AppState>>,
     Path(id): Path<String>,
 ) -> Result<Json<serde_json::Value>, AuthError> {
@@ -114,8 +320,230 @@ pub async fn revoke_token(
             "token.revoke",
             Some(&id),
             None,
+            &correlation_id.0,
         )
         .await;
 
     Ok(Json(serde_json::json!({"ok": true})))
 }
+
+#[derive(Deserialize, IntoParams)]
+pub struct TokenUsageQuery {
+    /// Days of history, ending today (default 30, at most 366).
+    pub days: Option<u32>,
+}
+
+/// One point of a token's daily usage chart.
+#[derive(Serialize, ToSchema)]
+pub struct TokenUsagePoint {
+    pub date: String,
+    pub requests: u64,
+    pub bytes: u64,
+}
+
+/// `GET /api/auth/tokens/{id}/usage?days=30` — daily request and byte
+/// counts, oldest first, one point per day.
+#[utoipa::path(
+    get,
+    path = "/api/auth/tokens/{id}/usage",
+    tag = "auth",
+    params(("id" = String, Path, description = "Token ID"), TokenUsageQuery),
+    responses(
+        (status = 200, description = "Daily usage, oldest first", body = Vec<TokenUsagePoint>),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Not the caller's token"),
+    )
+)]
+pub async fn get_token_usage(
+    auth_user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Query(query): Query<TokenUsageQuery>,
+) -> Result<Json<Vec<TokenUsagePoint>>, AuthError> {
+    require_permission(&auth_user, "tokens:own")?;
+
+    // Check token ownership unless admin
+    if !enigma_auth::has_permission(&auth_user.permissions, "tokens:admin") {
+        let tokens = state.auth_store.list_tokens(&auth_user.user_id).await?;
+        if !tokens.iter().any(|t| t.id == id) {
+            return Err(AuthError::Forbidden("not your token".into()));
+        }
+    }
+
+    let days = query.days.unwrap_or(30).clamp(1, 366);
+    let history = state.auth_store.get_token_usage_history(&id, days).await?;
+    Ok(Json(
+        history
+            .into_iter()
+            .map(|day| TokenUsagePoint {
+                date: day.date,
+                requests: day.request_count,
+                bytes: day.bytes_transferred,
+            })
+            .collect(),
+    ))
+}
+
+/// Validate a CIDR list and store it without blanks; an empty list means no
+/// restriction.
+fn normalize_allowed_ips(list: Option<&str>) -> Result<Option<String>, AuthError> {
+    let nets = enigma_auth::parse_allowed_ips(list.unwrap_or(""))?;
+    if nets.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(
+        nets.iter()
+            .map(|n| n.to_string())
+            .collect::<Vec<_>>()
+            .join(","),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use axum::body::Body;
+    use axum::extract::ConnectInfo;
+    use axum::http::{Request, StatusCode};
+    use enigma_auth::AuthStore;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::routes::build_router;
+
+    /// A state whose store holds `ci`, a member of the `read` group, and
+    /// the raw API token issued to it.
+    async fn state_with_token() -> (Arc<AppState>, String) {
+        let store = enigma_auth::SqliteAuthStore::open_in_memory().unwrap();
+        store.migrate().await.unwrap();
+        store.seed_defaults().await.unwrap();
+        let user = store
+            .create_user("ci", &enigma_auth::NewPassword::disabled(), None)
+            .await
+            .unwrap();
+        let read = store.get_group_by_name("read").await.unwrap();
+        store.add_user_group(&user.id, &read.id).await.unwrap();
+        let raw_token = enigma_auth::generate_api_token();
+        store
+            .create_token(
+                &user.id,
+                "ci",
+                &enigma_auth::hash_token(&raw_token),
+                &raw_token[..12],
+                "*",
+                None,
+            )
+            .await
+            .unwrap();
+        let state = Arc::new(AppState {
+            auth_store: Arc::new(store),
+            ..AppState::for_tests()
+        });
+        (state, raw_token)
+    }
+
+    async fn send(
+        state: &Arc<AppState>,
+        method: &str,
+        token: &str,
+        body: Option<serde_json::Value>,
+    ) -> (StatusCode, serde_json::Value) {
+        send_to(state, method, "/api/auth/tokens", token, body).await
+    }
+
+    async fn send_to(
+        state: &Arc<AppState>,
+        method: &str,
+        uri: &str,
+        token: &str,
+        body: Option<serde_json::Value>,
+    ) -> (StatusCode, serde_json::Value) {
+        let mut request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("Authorization", format!("Bearer {token}"))
+            .header("Content-Type", "application/json")
+            .body(body.map_or_else(Body::empty, |b| Body::from(b.to_string())))
+            .unwrap();
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(SocketAddr::from(([203, 0, 113, 9], 40000))));
+        let resp = build_router(state.clone()).oneshot(request).await.unwrap();
+        let status = resp.status();
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or_default())
+    }
+
+    #[tokio::test]
+    async fn api_token_authenticates_through_the_router() {
+        let (state, raw_token) = state_with_token().await;
+
+        let (status, body) = send(&state, "GET", &raw_token, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body[0]["token_prefix"], raw_token[..12]);
+
+        // No CSRF token needed: browsers never send an API token on their own
+        let (status, body) = send(
+            &state,
+            "POST",
+            &raw_token,
+            Some(serde_json::json!({ "name": "deploy" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body["raw_token"].as_str().unwrap().starts_with("egt_"));
+        let (_, body) = send(&state, "GET", &raw_token, None).await;
+        assert_eq!(body.as_array().unwrap().len(), 2);
+
+        let (status, _) = send(&state, "GET", "egt_0000000000000000", None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn allowed_ips_are_enforced_on_live_requests() {
+        let (state, raw_token) = state_with_token().await;
+        let (_, body) = send(&state, "GET", &raw_token, None).await;
+        let id = body[0]["id"].as_str().unwrap().to_string();
+        let uri = format!("/api/auth/tokens/{id}/allowed_ips");
+
+        // Requests come from 203.0.113.9
+        let (status, body) = send_to(
+            &state,
+            "PUT",
+            &uri,
+            &raw_token,
+            Some(serde_json::json!({ "allowed_ips": "198.51.100.0/24" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["allowed_ips"], "198.51.100.0/24");
+        let (status, body) = send(&state, "GET", &raw_token, None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body["error"], "ip_not_allowed");
+    }
+
+    #[tokio::test]
+    async fn usage_counts_live_requests() {
+        let (state, raw_token) = state_with_token().await;
+        let (_, body) = send(&state, "GET", &raw_token, None).await;
+        let id = body[0]["id"].as_str().unwrap().to_string();
+        let uri = format!("/api/auth/tokens/{id}/usage?days=1");
+
+        // Requests are counted in the background
+        let mut requests = 0;
+        for _ in 0..50 {
+            let (status, body) = send_to(&state, "GET", &uri, &raw_token, None).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(body.as_array().unwrap().len(), 1);
+            requests = body[0]["requests"].as_u64().unwrap();
+            if requests >= 2 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert!(requests >= 2);
+    }
+}
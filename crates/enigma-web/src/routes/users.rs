@@ -3,10 +3,11 @@ use std::sync::Arc;
 use axum::Json;
 use axum::extract::{Path, State};
 
-use enigma_auth::middleware::require_permission;
 use enigma_auth::AuthUser;
 use enigma_auth::error::AuthError;
+use enigma_auth::middleware::require_permission;
 
+use crate::correlation::CorrelationId;
 use crate::models::{GroupResponse, PermissionResponse, UserResponse};
 use crate::state::AppState;
 
@@ -45,6 +46,17 @@ async fn user_to_response(
     })
 }
 
+/// GET /api/users
+#[utoipa::path(
+    get,
+    path = "/api/users",
+    tag = "users",
+    responses(
+        (status = 200, description = "All users", body = Vec<UserResponse>),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Missing users:read permission"),
+    )
+)]
 pub async fn list_users(
     auth_user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -59,6 +71,19 @@ pub async fn list_users(
     Ok(Json(result))
 }
 
+/// GET /api/users/{id}
+#[utoipa::path(
+    get,
+    path = "/api/users/{id}",
+    tag = "users",
+    params(("id" = String, Path, description = "User ID")),
+    responses(
+        (status = 200, description = "The user", body = UserResponse),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Missing users:read permission"),
+        (status = 404, description = "No such user"),
+    )
+)]
 pub async fn get_user(
     auth_user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -70,23 +95,37 @@ pub async fn get_user(
     Ok(Json(user_to_response(&state, user).await?))
 }
 
+/// POST /api/users
+///
+/// Create a local account; the password must meet the password policy.
+#[utoipa::path(
+    post,
+    path = "/api/users",
+    tag = "users",
+    request_body = enigma_auth::CreateUserRequest,
+    responses(
+        (status = 200, description = "User created", body = UserResponse),
+        (status = 400, description = "Missing username or weak password"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Missing users:write permission"),
+        (status = 409, description = "Username taken"),
+    )
+)]
 pub async fn create_user(
     auth_user: AuthUser,
+    correlation_id: CorrelationId,
     State(state): State<Arc<AppState>>,
     Json(req): Json<enigma_auth::CreateUserRequest>,
 ) -> Result<Json<UserResponse>, AuthError> {
     require_permission(&auth_user, "users:write")?;
 
-    if req.username.is_empty() || req.password.len() < 4 {
-        return Err(AuthError::InvalidInput(
-            "username required, password min 4 chars".into(),
-        ));
+    if req.username.is_empty() {
+        return Err(AuthError::InvalidInput("username required".into()));
     }
-
-    let password_hash = enigma_auth::hash_password(&req.password)?;
+    let password = enigma_auth::NewPassword::new(&req.password, &state.password_policy)?;
     let user = state
         .auth_store
-        .create_user(&req.username, &password_hash, req.email.as_deref())
+        .create_user(&req.username, &password, req.email.as_deref())
         .await?;
 
     let _ = state
@@ -96,14 +135,30 @@ pub async fn create_user(
             "user.create",
             Some(&user.username),
             None,
+            &correlation_id.0,
         )
         .await;
 
     Ok(Json(user_to_response(&state, user).await?))
 }
 
+/// PUT /api/users/{id}
+#[utoipa::path(
+    put,
+    path = "/api/users/{id}",
+    tag = "users",
+    params(("id" = String, Path, description = "User ID")),
+    request_body = enigma_auth::UpdateUserRequest,
+    responses(
+        (status = 200, description = "User updated", body = UserResponse),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Missing users:write permission"),
+        (status = 404, description = "No such user"),
+    )
+)]
 pub async fn update_user(
     auth_user: AuthUser,
+    correlation_id: CorrelationId,
     State(state): State<Arc<AppState>>,
     Path(id): Path<String>,
     Json(req): Json<enigma_auth::UpdateUserRequest>,
@@ -119,14 +174,29 @@ pub async fn update_user(
             "user.update",
             Some(&user.username),
             None,
+            &correlation_id.0,
         )
         .await;
 
     Ok(Json(user_to_response(&state, user).await?))
 }
 
+/// DELETE /api/users/{id}
+#[utoipa::path(
+    delete,
+    path = "/api/users/{id}",
+    tag = "users",
+    params(("id" = String, Path, description = "User ID")),
+    responses(
+        (status = 200, description = "User deleted"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Missing users:write permission, or deleting oneself"),
+        (status = 404, description = "No such user"),
+    )
+)]
 pub async fn delete_user(
     auth_user: AuthUser,
+    correlation_id: CorrelationId,
     State(state): State<Arc<AppState>>,
     Path(id): Path<String>,
 ) -> Result<Json<serde_json::Value>, AuthError> {
@@ -146,14 +216,33 @@ pub async fn delete_user(
             "user.delete",
             Some(&user.username),
             None,
+            &correlation_id.0,
         )
         .await;
 
     Ok(Json(serde_json::json!({"ok": true})))
 }
 
+/// PUT /api/users/{id}/password
+///
+/// Users may always change their own password; the new one must meet the
+/// password policy.
+#[utoipa::path(
+    put,
+    path = "/api/users/{id}/password",
+    tag = "users",
+    params(("id" = String, Path, description = "User ID")),
+    request_body = enigma_auth::UpdatePasswordRequest,
+    responses(
+        (status = 200, description = "Password changed"),
+        (status = 400, description = "Weak password"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Missing users:write permission"),
+    )
+)]
 pub async fn update_password(
     auth_user: AuthUser,
+    correlation_id: CorrelationId,
     State(state): State<Arc<AppState>>,
     Path(id): Path<String>,
     Json(req): Json<enigma_auth::UpdatePasswordRequest>,
@@ -163,12 +252,8 @@ pub async fn update_password(
         require_permission(&auth_user, "users:write")?;
     }
 
-    if req.password.len() < 4 {
-        return Err(AuthError::InvalidInput("password min 4 chars".into()));
-    }
-
-    let password_hash = enigma_auth::hash_password(&req.password)?;
-    state.auth_store.update_password(&id, &password_hash).await?;
+    let password = enigma_auth::NewPassword::new(&req.password, &state.password_policy)?;
+    state.auth_store.update_password(&id, &password).await?;
 
     let _ = state
         .auth_store
@@ -177,12 +262,142 @@ pub async fn update_password(
             "user.password_change",
             Some(&id),
             None,
+            &correlation_id.0,
         )
         .await;
 
     Ok(Json(serde_json::json!({"ok": true})))
 }
 
+/// POST /api/admin/users/{id}/unlock
+///
+/// Lift a login lockout and reset the failed-login counter.
+#[utoipa::path(
+    post,
+    path = "/api/admin/users/{id}/unlock",
+    tag = "users",
+    params(("id" = String, Path, description = "User ID")),
+    responses(
+        (status = 200, description = "Account unlocked"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Missing users:write permission"),
+    )
+)]
+pub async fn unlock_user(
+    auth_user: AuthUser,
+    correlation_id: CorrelationId,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<serde_json::Value>, AuthError> {
+    require_permission(&auth_user, "users:write")?;
+
+    state.auth_store.clear_failed_logins(&id).await?;
+
+    let _ = state
+        .auth_store
+        .log_audit(
+            Some(&auth_user.user_id),
+            "user.unlock",
+            Some(&id),
+            None,
+            &correlation_id.0,
+        )
+        .await;
+
+    Ok(Json(serde_json::json!({"ok": true})))
+}
+
+/// GET /api/users/{id}/usage
+///
+/// Stored bytes and objects per namespace, with the user's quota. Users may
+/// always read their own usage.
+#[utoipa::path(
+    get,
+    path = "/api/users/{id}/usage",
+    tag = "users",
+    params(("id" = String, Path, description = "User ID")),
+    responses(
+        (status = 200, description = "Quota, total and per-namespace usage"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Missing users:read permission"),
+    )
+)]
+pub async fn get_user_usage(
+    auth_user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<serde_json::Value>, AuthError> {
+    if auth_user.user_id != id {
+        require_permission(&auth_user, "users:read")?;
+    }
+
+    let quota = state.auth_store.get_user_quota(&id).await?;
+    let usage = state.auth_store.get_user_usage(&id).await?;
+    let bytes_stored: u64 = usage.iter().map(|u| u.bytes_stored).sum();
+    Ok(Json(serde_json::json!({
+        "quota_bytes": quota,
+        "bytes_stored": bytes_stored,
+        "namespaces": usage,
+    })))
+}
+
+/// PUT /api/admin/users/{id}/quota
+///
+/// Set or (with `quota_bytes: null`) remove a user's storage quota.
+#[utoipa::path(
+    put,
+    path = "/api/admin/users/{id}/quota",
+    tag = "users",
+    params(("id" = String, Path, description = "User ID")),
+    request_body = enigma_auth::UpdateUserQuotaRequest,
+    responses(
+        (status = 200, description = "Quota set"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Missing users:write permission"),
+    )
+)]
+pub async fn set_user_quota(
+    auth_user: AuthUser,
+    correlation_id: CorrelationId,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Json(req): Json<enigma_auth::UpdateUserQuotaRequest>,
+) -> Result<Json<serde_json::Value>, AuthError> {
+    require_permission(&auth_user, "users:write")?;
+
+    state
+        .auth_store
+        .set_user_quota(&id, req.quota_bytes)
+        .await?;
+
+    let _ = state
+        .auth_store
+        .log_audit(
+            Some(&auth_user.user_id),
+            "user.set_quota",
+            Some(&id),
+            None,
+            &correlation_id.0,
+        )
+        .await;
+
+    Ok(Json(
+        serde_json::json!({"ok": true, "quota_bytes": req.quota_bytes}),
+    ))
+}
+
+/// GET /api/users/{id}/groups
+#[utoipa::path(
+    get,
+    path = "/api/users/{id}/groups",
+    tag = "users",
+    params(("id" = String, Path, description = "User ID")),
+    responses(
+        (status = 200, description = "Groups of the user", body = Vec<GroupResponse>),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Missing users:read permission"),
+    )
+)]
 pub async fn list_user_groups(
     auth_user: AuthUser,
     State(state): State<Arc<AppState>>,
@@ -213,8 +428,22 @@ pub async fn list_user_groups(
     Ok(Json(result))
 }
 
+/// POST /api/users/{id}/groups
+#[utoipa::path(
+    post,
+    path = "/api/users/{id}/groups",
+    tag = "users",
+    params(("id" = String, Path, description = "User ID")),
+    request_body = enigma_auth::UserGroupRequest,
+    responses(
+        (status = 200, description = "User added to the group"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Missing users:write permission"),
+    )
+)]
 pub async fn add_user_group(
     auth_user: AuthUser,
+    correlation_id: CorrelationId,
     State(state): State<Arc<AppState>>,
     Path(id): Path<String>,
     Json(req): Json<enigma_auth::UserGroupRequest>,
@@ -230,14 +459,31 @@ pub async fn add_user_group(
             "user.group.add",
             Some(&format!("{}:{}", id, req.group_id)),
             None,
+            &correlation_id.0,
         )
         .await;
 
     Ok(Json(serde_json::json!({"ok": true})))
 }
 
+/// DELETE /api/users/{id}/groups/{group_id}
+#[utoipa::path(
+    delete,
+    path = "/api/users/{id}/groups/{group_id}",
+    tag = "users",
+    params(
+        ("id" = String, Path, description = "User ID"),
+        ("group_id" = String, Path, description = "Group ID"),
+    ),
+    responses(
+        (status = 200, description = "User removed from the group"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Missing users:write permission"),
+    )
+)]
 pub async fn remove_user_group(
     auth_user: AuthUser,
+    correlation_id: CorrelationId,
     State(state): State<Arc<AppState>>,
     Path((id, group_id)): Path<(String, String)>,
 ) -> Result<Json<serde_json::Value>, AuthError> {
@@ -252,8 +498,181 @@ pub async fn remove_user_group(
             "user.group.remove",
             Some(&format!("{id}:{group_id}")),
             None,
+            &correlation_id.0,
         )
         .await;
 
     Ok(Json(serde_json::json!({"ok": true})))
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use enigma_auth::{AuthStore, LockoutPolicy, SqliteAuthStore};
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::auth::create_token;
+    use crate::routes::build_router;
+
+    /// A state whose store holds the configured admin, `alice` and `carol`,
+    /// none of them in a group.
+    async fn test_state() -> Arc<AppState> {
+        let store = SqliteAuthStore::open_in_memory()
+            .unwrap()
+            .with_lockout(LockoutPolicy {
+                threshold: 2,
+                duration_seconds: 60,
+            });
+        store.migrate().await.unwrap();
+        store.seed_defaults().await.unwrap();
+        for name in ["admin", "alice", "carol"] {
+            store
+                .create_user(name, &enigma_auth::NewPassword::disabled(), None)
+                .await
+                .unwrap();
+        }
+        Arc::new(AppState {
+            auth_store: Arc::new(store),
+            ..AppState::for_tests()
+        })
+    }
+
+    async fn user_id(state: &AppState, username: &str) -> String {
+        state
+            .auth_store
+            .get_user_by_username(username)
+            .await
+            .unwrap()
+            .id
+    }
+
+    /// Send `body` as JSON to `uri`, logged in as `username`.
+    async fn send(
+        state: &Arc<AppState>,
+        method: &str,
+        uri: &str,
+        username: &str,
+        body: serde_json::Value,
+    ) -> (StatusCode, serde_json::Value) {
+        let jwt = create_token(username, &state.jwt_secret).unwrap();
+        let request = crate::csrf::with_csrf(Request::builder(), &jwt, &state.jwt_secret)
+            .method(method)
+            .uri(uri)
+            .header("Authorization", format!("Bearer {jwt}"))
+            .header("Content-Type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let resp = build_router(state.clone()).oneshot(request).await.unwrap();
+        let status = resp.status();
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or_default())
+    }
+
+    #[tokio::test]
+    async fn admin_unlocks_a_locked_account() {
+        let state = test_state().await;
+        let alice = user_id(&state, "alice").await;
+        state.auth_store.record_failed_login(&alice).await.unwrap();
+        state.auth_store.record_failed_login(&alice).await.unwrap();
+        assert!(
+            state
+                .auth_store
+                .lockout_status(&alice)
+                .await
+                .unwrap()
+                .is_some()
+        );
+        let uri = format!("/api/admin/users/{alice}/unlock");
+
+        let (status, _) = send(&state, "POST", &uri, "carol", serde_json::json!({})).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert!(
+            state
+                .auth_store
+                .lockout_status(&alice)
+                .await
+                .unwrap()
+                .is_some()
+        );
+
+        let (status, _) = send(&state, "POST", &uri, "admin", serde_json::json!({})).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(
+            state
+                .auth_store
+                .lockout_status(&alice)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        let audit = state.auth_store.list_audit(10, 0).await.unwrap();
+        assert_eq!(audit[0].action, "user.unlock");
+    }
+
+    #[tokio::test]
+    async fn weak_passwords_are_rejected() {
+        let state = test_state().await;
+        let json = serde_json::json!({ "username": "dave", "password": "short" });
+        let (status, body) = send(&state, "POST", "/api/users", "admin", json).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            body["error"],
+            "weak password: must be at least 12 characters long"
+        );
+        assert!(state.auth_store.get_user_by_username("dave").await.is_err());
+
+        let alice = user_id(&state, "alice").await;
+        let uri = format!("/api/users/{alice}/password");
+        let json = serde_json::json!({ "password": "no-uppercase-1" });
+        let (status, body) = send(&state, "PUT", &uri, "alice", json).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            body["error"],
+            "weak password: must contain an uppercase letter"
+        );
+
+        let json = serde_json::json!({ "password": "Long-enough-1" });
+        let (status, _) = send(&state, "PUT", &uri, "alice", json).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn admin_sets_the_quota_users_read_their_usage() {
+        let state = test_state().await;
+        let alice = user_id(&state, "alice").await;
+        state
+            .auth_store
+            .update_user_usage(&alice, "logs", 60, 2)
+            .await
+            .unwrap();
+        let quota_uri = format!("/api/admin/users/{alice}/quota");
+        let usage_uri = format!("/api/users/{alice}/usage");
+
+        let json = serde_json::json!({ "quota_bytes": 100 });
+        let (status, _) = send(&state, "PUT", &quota_uri, "alice", json.clone()).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = send(&state, "PUT", &quota_uri, "admin", json).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, body) = send(&state, "GET", &usage_uri, "alice", serde_json::json!({})).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["quota_bytes"], 100);
+        assert_eq!(body["bytes_stored"], 60);
+        assert_eq!(body["namespaces"][0]["namespace"], "logs");
+        assert_eq!(body["namespaces"][0]["object_count"], 2);
+
+        // Other users' usage needs users:read
+        let (status, _) = send(&state, "GET", &usage_uri, "carol", serde_json::json!({})).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let json = serde_json::json!({ "quota_bytes": null });
+        let (status, _) = send(&state, "PUT", &quota_uri, "admin", json).await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, body) = send(&state, "GET", &usage_uri, "admin", serde_json::json!({})).await;
+        assert!(body["quota_bytes"].is_null());
+    }
+}
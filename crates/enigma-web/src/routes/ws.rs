@@ -0,0 +1,226 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Bytes;
+use axum::extract::ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade, close_code};
+use axum::extract::{Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::Response;
+use futures::{SinkExt, StreamExt};
+use tokio::sync::broadcast::Receiver;
+use tokio::sync::broadcast::error::RecvError;
+
+use enigma_core::events::BackupEvent;
+
+use crate::state::AppState;
+use crate::stream_ticket::{StreamQuery, stream_authorized};
+
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// GET /api/ws/status — live backup progress as JSON `BackupEvent`s.
+///
+/// Browsers cannot set headers on a WebSocket handshake, so a stream
+/// ticket may be passed as `?ticket=` instead of an `Authorization` header.
+pub async fn ws_status(
+    State(state): State<Arc<AppState>>,
+    Query(q): Query<StreamQuery>,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> Result<Response, StatusCode> {
+    if !stream_authorized(&state, &headers, &q) {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    // Subscribe before upgrading so no event published after the
+    // handshake is missed
+    let events = state.events.subscribe();
+    Ok(ws.on_upgrade(move |socket| stream_events(socket, events)))
+}
+
+async fn stream_events(socket: WebSocket, mut events: Receiver<BackupEvent>) {
+    let (mut sender, mut receiver) = socket.split();
+    let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+    heartbeat.tick().await; // the first tick completes immediately
+
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => {
+                    let Ok(json) = serde_json::to_string(&event) else {
+                        continue;
+                    };
+                    if sender.send(Message::Text(json.into())).await.is_err() {
+                        return;
+                    }
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!("WebSocket client lagging, skipped {skipped} backup events");
+                }
+                Err(RecvError::Closed) => break,
+            },
+            _ = heartbeat.tick() => {
+                if sender.send(Message::Ping(Bytes::new())).await.is_err() {
+                    return;
+                }
+            }
+            msg = receiver.next() => match msg {
+                // The close handshake reply is sent by the protocol layer
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+
+    // Event channel closed: the server is shutting down
+    let _ = sender
+        .send(Message::Close(Some(CloseFrame {
+            code: close_code::AWAY,
+            reason: "server shutting down".into(),
+        })))
+        .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::net::SocketAddr;
+    use std::sync::Mutex;
+
+    use enigma_core::config::EnigmaConfig;
+    use enigma_core::distributor::Distributor;
+    use enigma_core::events::BackupPhase;
+    use enigma_core::manifest::ManifestDb;
+    use enigma_core::types::{KeyMaterial, ProviderType};
+    use enigma_s3::EnigmaS3State;
+    use enigma_storage::local::LocalStorageProvider;
+    use enigma_storage::provider::StorageProvider;
+    use tokio_tungstenite::tungstenite;
+
+    use super::*;
+    use crate::auth::create_token;
+    use crate::routes::build_router;
+
+    fn s3_state(dir: &std::path::Path) -> EnigmaS3State {
+        let db = ManifestDb::open_in_memory().unwrap();
+        let pid = db
+            .insert_provider("local", ProviderType::Local, dir.to_str().unwrap(), None, 1)
+            .unwrap();
+        db.create_namespace("bucket").unwrap();
+        let distributor = Distributor::round_robin(db.list_providers().unwrap()).unwrap();
+
+        let mut providers: HashMap<i64, Box<dyn StorageProvider>> = HashMap::new();
+        providers.insert(
+            pid,
+            Box::new(LocalStorageProvider::new(&dir.join("chunks"), "local").unwrap()),
+        );
+
+        EnigmaS3State {
+            db: Arc::new(Mutex::new(db)),
+            providers: providers.into(),
+            distributor: distributor.into(),
+            key_material: KeyMaterial {
+                id: "test-key-1".to_string(),
+                key: [0x42; 32],
+            },
+            config: EnigmaConfig::default_config(dir),
+            raft: Default::default(),
+            events: Default::default(),
+            usage: Default::default(),
+            access_control: Default::default(),
+            multipart_limits: Default::default(),
+            chunk_cache: Default::default(),
+            upload_semaphore: Default::default(),
+            provider_limiters: Default::default(),
+        }
+    }
+
+    /// Serve the web router on a random port sharing `s3`'s event channel.
+    async fn serve(s3: &EnigmaS3State) -> (SocketAddr, Arc<AppState>) {
+        let state = Arc::new(AppState {
+            events: s3.events.clone(),
+            ..AppState::for_tests()
+        });
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = build_router(state.clone()).into_make_service_with_connect_info::<SocketAddr>();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (addr, state)
+    }
+
+    #[tokio::test]
+    async fn backup_events_stream_until_done() {
+        let tmp = tempfile::tempdir().unwrap();
+        let s3 = s3_state(tmp.path());
+        let (addr, state) = serve(&s3).await;
+        let ticket = state.stream_tickets.issue();
+
+        let (mut ws, _) =
+            tokio_tungstenite::connect_async(format!("ws://{addr}/api/ws/status?ticket={ticket}"))
+                .await
+                .unwrap();
+
+        let data = b"hello websocket".repeat(100);
+        enigma_s3::ops::store_object(&s3, "bucket", "a.bin", &data, None)
+            .await
+            .unwrap();
+
+        let mut received = Vec::new();
+        loop {
+            let msg = tokio::time::timeout(Duration::from_secs(5), ws.next())
+                .await
+                .expect("timed out waiting for backup events")
+                .unwrap()
+                .unwrap();
+            let tungstenite::Message::Text(text) = msg else {
+                continue;
+            };
+            let event: BackupEvent = serde_json::from_str(&text).unwrap();
+            let phase = event.phase;
+            received.push(event);
+            if matches!(phase, BackupPhase::Done | BackupPhase::Error) {
+                break;
+            }
+        }
+
+        let phases: Vec<BackupPhase> = received.iter().map(|e| e.phase).collect();
+        assert_eq!(
+            phases,
+            vec![
+                BackupPhase::Chunking,
+                BackupPhase::Encrypting,
+                BackupPhase::Uploading,
+                BackupPhase::Done,
+            ]
+        );
+        let done = received.last().unwrap();
+        assert_eq!(done.backup_id, "bucket/a.bin");
+        assert_eq!((done.files_done, done.files_total), (1, 1));
+        assert_eq!(done.bytes_done, data.len() as u64);
+        assert_eq!(done.bytes_total, data.len() as u64);
+
+        ws.close(None).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn rejects_missing_or_invalid_ticket() {
+        let tmp = tempfile::tempdir().unwrap();
+        let s3 = s3_state(tmp.path());
+        let (addr, state) = serve(&s3).await;
+        let jwt = create_token("admin", &state.jwt_secret).unwrap();
+
+        for url in [
+            format!("ws://{addr}/api/ws/status"),
+            format!("ws://{addr}/api/ws/status?ticket=not-a-ticket"),
+            format!("ws://{addr}/api/ws/status?token={jwt}"),
+        ] {
+            match tokio_tungstenite::connect_async(url).await {
+                Err(tungstenite::Error::Http(resp)) => {
+                    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED)
+                }
+                Err(e) => panic!("expected 401, got {e}"),
+                Ok(_) => panic!("expected 401, connection was accepted"),
+            }
+        }
+    }
+}
@@ -1,15 +1,136 @@
-use std::sync::Mutex;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use enigma_auth::{AuthStore, OidcProvider, PasswordPolicy};
 use enigma_core::config::EnigmaSettings;
-use enigma_core::manifest::ManifestDb;
+use enigma_core::events::BackupEvents;
+use enigma_core::notify::NotificationConfig;
+use enigma_core::webhook::WebhookConfig;
+use enigma_keys::provider::KeyProvider;
+use enigma_storage::provider::StorageProvider;
+use ipnet::IpNet;
 use serde::{Deserialize, Serialize};
 
+use crate::cluster_handle::ClusterHandle;
+use crate::models::ProviderUsageResponse;
+use crate::pool::{DbPool, ReadonlyDbPool};
+use crate::stream_ticket::StreamTickets;
+
 pub struct AppState {
-    pub db: Mutex<ManifestDb>,
+    /// Manifest connections; each handler checks one out per request.
+    /// Only mutations use this pool.
+    pub db: DbPool,
+    /// Read-only manifest connections for the GET routes, so listings
+    /// never wait on a writable connection.
+    pub readonly_db: ReadonlyDbPool,
     pub config: EnigmaSettings,
     pub jwt_secret: String,
     pub admin_user: String,
     pub admin_pass: String,
+    pub rate_limit: RateLimitConfig,
+    pub login_rate_limit: RateLimitConfig,
+    /// Parsed from [`WebConfig::trusted_proxies`].
+    pub trusted_proxies: Vec<IpNet>,
+    pub password_policy: PasswordPolicy,
+    /// Users, groups and tokens; also tracks failed logins for lockout.
+    pub auth_store: Arc<dyn AuthStore>,
+    /// Backup progress forwarded to `/api/ws/status` clients.
+    pub events: BackupEvents,
+    /// Probed by `/api/keys/health` and `/api/health`, and the source of
+    /// keys for re-encryption; unset when the web UI runs without access to
+    /// the key provider.
+    pub key_provider: Option<Arc<dyn KeyProvider>>,
+    /// Storage backends whose connection `/api/health` tests, and whose
+    /// chunks re-encryption rewrites.
+    pub storage_providers: Vec<Arc<dyn StorageProvider>>,
+    /// Endpoints notified of events the API triggers, such as `key.rotated`.
+    pub webhooks: Vec<WebhookConfig>,
+    /// Last answer of `/api/storage/providers/usage`.
+    pub usage_cache: UsageCache,
+    /// Single sign-on through an OpenID Connect provider, when configured.
+    pub oidc: Option<OidcConfig>,
+    /// Raft cluster behind `/api/cluster/peers`; unset in single-node mode.
+    pub cluster: Option<Arc<dyn ClusterHandle>>,
+    /// Mailing of password reset links, when configured.
+    pub password_reset: Option<PasswordResetConfig>,
+    /// Tickets that open `/api/ws/status` and `/api/events/backup`.
+    pub stream_tickets: StreamTickets,
+}
+
+#[cfg(test)]
+impl AppState {
+    /// State for tests: the default config, manifest pools that are never
+    /// opened unless queried, and an empty, migrated in-memory auth store.
+    /// Tests set other fields with struct update syntax.
+    pub(crate) fn for_tests() -> Self {
+        let config = enigma_core::config::EnigmaConfig::default_config(std::path::Path::new(
+            "/tmp/enigma-test",
+        ));
+        let auth_store = enigma_auth::SqliteAuthStore::open_in_memory().unwrap();
+        // The SQLite store never awaits, so this cannot stall a test runtime
+        futures::executor::block_on(auth_store.migrate()).unwrap();
+        Self {
+            db: crate::pool::unused_pool(),
+            readonly_db: crate::pool::unused_readonly_pool(),
+            config: config.enigma,
+            jwt_secret: "test-secret-test-secret-test-secret".to_string(),
+            admin_user: "admin".to_string(),
+            admin_pass: "admin".to_string(),
+            rate_limit: RateLimitConfig::default(),
+            login_rate_limit: RateLimitConfig::default(),
+            trusted_proxies: Vec::new(),
+            password_policy: Default::default(),
+            auth_store: Arc::new(auth_store),
+            events: Default::default(),
+            key_provider: None,
+            storage_providers: Vec::new(),
+            webhooks: Vec::new(),
+            usage_cache: Default::default(),
+            oidc: None,
+            cluster: None,
+            password_reset: None,
+            stream_tickets: Default::default(),
+        }
+    }
+
+    /// Like [`AppState::for_tests`], with pools of `pool_size` connections
+    /// to the manifest at `db_path` and the default config rooted at `dir`.
+    pub(crate) fn for_tests_with_db(
+        dir: &std::path::Path,
+        db_path: &std::path::Path,
+        pool_size: usize,
+    ) -> Self {
+        let config = enigma_core::config::EnigmaConfig::default_config(dir);
+        Self {
+            db: crate::pool::build_pool(db_path, pool_size).unwrap(),
+            readonly_db: crate::pool::build_readonly_pool(db_path, pool_size, Default::default())
+                .unwrap(),
+            config: config.enigma,
+            ..Self::for_tests()
+        }
+    }
+}
+
+/// Provider usage is costly to gather (cloud backends list every object),
+/// so it is reused until it is `ttl` old.
+pub struct UsageCache {
+    pub ttl: Duration,
+    pub entry: tokio::sync::Mutex<Option<(Instant, Vec<ProviderUsageResponse>)>>,
+}
+
+impl UsageCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entry: Default::default(),
+        }
+    }
+}
+
+impl Default for UsageCache {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(default_usage_cache_seconds()))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,6 +143,91 @@ pub struct WebConfig {
     pub admin_user: String,
     #[serde(default = "default_admin_pass")]
     pub admin_pass: String,
+    /// Limit applied to every request.
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    /// Stricter limit applied to `POST /api/auth/login`.
+    #[serde(default = "default_login_rate_limit")]
+    pub login_rate_limit: RateLimitConfig,
+    /// Rules for passwords set through the user routes.
+    #[serde(default)]
+    pub password_policy: PasswordPolicy,
+    /// Consecutive failed logins before an account is locked (0 = never).
+    #[serde(default = "default_lockout_threshold")]
+    pub lockout_threshold: u32,
+    /// How long a locked account stays locked.
+    #[serde(default = "default_lockout_duration_seconds")]
+    pub lockout_duration_seconds: u64,
+    /// Manifest connections kept open for concurrent requests, in each of
+    /// the writable and read-only pools.
+    #[serde(default = "default_db_pool_size")]
+    pub db_pool_size: usize,
+    /// How long `/api/storage/providers/usage` reuses its last answer.
+    #[serde(default = "default_usage_cache_seconds")]
+    pub usage_cache_seconds: u64,
+    /// Enables `/api/auth/oidc/login` single sign-on.
+    #[serde(default)]
+    pub oidc: Option<OidcConfig>,
+    /// Enables `/api/auth/forgot-password`.
+    #[serde(default)]
+    pub password_reset: Option<PasswordResetConfig>,
+    /// Days auth audit entries are kept; older ones are purged every
+    /// night at midnight UTC (0 = never).
+    #[serde(default = "default_audit_retention_days")]
+    pub audit_retention_days: u32,
+    /// Reverse proxies in front of the web UI, as CIDRs or addresses. Only
+    /// their `X-Forwarded-For` is used to find the client IP for rate
+    /// limiting and token IP restrictions.
+    #[serde(default)]
+    pub trusted_proxies: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimitConfig {
+    /// Sustained request rate.
+    #[serde(default = "default_requests_per_second")]
+    pub requests_per_second: f64,
+    /// Requests allowed in a burst above the sustained rate.
+    #[serde(default = "default_burst")]
+    pub burst: u32,
+    /// Track a separate bucket per client IP instead of one shared bucket.
+    #[serde(default = "default_per_ip")]
+    pub per_ip: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OidcConfig {
+    #[serde(flatten)]
+    pub provider: OidcProvider,
+    /// Where the browser lands after login, with the session token in the
+    /// URL fragment (`#token=...`).
+    #[serde(default = "default_post_login_redirect")]
+    pub post_login_redirect: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PasswordResetConfig {
+    /// Web UI page taking the token, e.g.
+    /// "https://enigma.example.com/reset-password"; the mailed link appends
+    /// `?token=...`.
+    pub reset_url: String,
+    /// How long a reset link stays valid.
+    #[serde(default = "default_reset_token_ttl_minutes")]
+    pub token_ttl_minutes: u32,
+    /// SMTP server and sender of the emails, taken from the proxy's
+    /// `[notifications]` section.
+    #[serde(skip)]
+    pub smtp: Option<NotificationConfig>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            requests_per_second: default_requests_per_second(),
+            burst: default_burst(),
+            per_ip: default_per_ip(),
+        }
+    }
 }
 
 fn default_web_addr() -> String {
@@ -36,6 +242,43 @@ fn default_admin_user() -> String {
 fn default_admin_pass() -> String {
     "enigma".to_string()
 }
+fn default_requests_per_second() -> f64 {
+    50.0
+}
+fn default_burst() -> u32 {
+    100
+}
+fn default_per_ip() -> bool {
+    true
+}
+fn default_lockout_threshold() -> u32 {
+    5
+}
+fn default_lockout_duration_seconds() -> u64 {
+    900
+}
+fn default_db_pool_size() -> usize {
+    8
+}
+fn default_usage_cache_seconds() -> u64 {
+    60
+}
+fn default_audit_retention_days() -> u32 {
+    90
+}
+fn default_reset_token_ttl_minutes() -> u32 {
+    30
+}
+fn default_post_login_redirect() -> String {
+    "/".to_string()
+}
+fn default_login_rate_limit() -> RateLimitConfig {
+    RateLimitConfig {
+        requests_per_second: 0.2,
+        burst: 5,
+        per_ip: true,
+    }
+}
 
 impl Default for WebConfig {
     fn default() -> Self {
@@ -44,6 +287,17 @@ impl Default for WebConfig {
             jwt_secret: default_jwt_secret(),
             admin_user: default_admin_user(),
             admin_pass: default_admin_pass(),
+            rate_limit: RateLimitConfig::default(),
+            login_rate_limit: default_login_rate_limit(),
+            password_policy: PasswordPolicy::default(),
+            lockout_threshold: default_lockout_threshold(),
+            lockout_duration_seconds: default_lockout_duration_seconds(),
+            db_pool_size: default_db_pool_size(),
+            usage_cache_seconds: default_usage_cache_seconds(),
+            oidc: None,
+            password_reset: None,
+            audit_retention_days: default_audit_retention_days(),
+            trusted_proxies: Vec::new(),
         }
     }
 }
@@ -0,0 +1,184 @@
+//! Single-use tickets for the progress streams browsers open without being
+//! able to set headers: WebSocket handshakes and `EventSource` streams.
+//!
+//! A logged-in client trades its session for a ticket with
+//! `POST /api/stream-ticket` and opens the stream with `?ticket=`. The
+//! ticket is random, expires after 30 seconds and is used up by the first
+//! connection, so the URLs that end up in proxy logs and browser history
+//! never carry the session JWT.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use axum::Json;
+use axum::extract::State;
+use axum::http::{HeaderMap, header};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::auth::verify_token;
+use crate::state::AppState;
+
+const TICKET_TTL: Duration = Duration::from_secs(30);
+
+/// Tickets issued and not yet used, with their issue time.
+pub struct StreamTickets {
+    ttl: Duration,
+    issued: Mutex<HashMap<String, Instant>>,
+}
+
+impl StreamTickets {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            issued: Default::default(),
+        }
+    }
+
+    pub fn issue(&self) -> String {
+        let mut bytes = [0u8; 32];
+        SystemRandom::new()
+            .fill(&mut bytes)
+            .expect("system random number generator failed");
+        let ticket = URL_SAFE_NO_PAD.encode(bytes);
+        let mut issued = self.issued.lock().unwrap_or_else(PoisonError::into_inner);
+        // Tickets nobody came back for would otherwise pile up
+        issued.retain(|_, at| at.elapsed() < self.ttl);
+        issued.insert(ticket.clone(), Instant::now());
+        ticket
+    }
+
+    /// Use up `ticket`; false if it is unknown, used or expired.
+    pub fn redeem(&self, ticket: &str) -> bool {
+        self.issued
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(ticket)
+            .is_some_and(|at| at.elapsed() < self.ttl)
+    }
+}
+
+impl Default for StreamTickets {
+    fn default() -> Self {
+        Self::new(TICKET_TTL)
+    }
+}
+
+/// `?ticket=` query parameter of the stream routes.
+#[derive(Deserialize)]
+pub struct StreamQuery {
+    pub ticket: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct StreamTicketResponse {
+    /// Pass as `?ticket=` to `/api/ws/status` or `/api/events/backup`.
+    pub ticket: String,
+}
+
+/// Whether a stream request carries a valid JWT in the `Authorization:
+/// Bearer` header or an unused `?ticket=`.
+pub fn stream_authorized(state: &AppState, headers: &HeaderMap, query: &StreamQuery) -> bool {
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "));
+    match (bearer, &query.ticket) {
+        (Some(token), _) => verify_token(token, &state.jwt_secret).is_ok(),
+        (None, Some(ticket)) => state.stream_tickets.redeem(ticket),
+        (None, None) => false,
+    }
+}
+
+/// POST /api/stream-ticket
+///
+/// A single-use ticket for opening one progress stream, valid for 30
+/// seconds.
+#[utoipa::path(
+    post,
+    path = "/api/stream-ticket",
+    tag = "auth",
+    responses(
+        (status = 200, description = "Stream ticket", body = StreamTicketResponse),
+        (status = 401, description = "Missing or invalid credentials"),
+    )
+)]
+pub async fn issue_stream_ticket(State(state): State<Arc<AppState>>) -> Json<StreamTicketResponse> {
+    Json(StreamTicketResponse {
+        ticket: state.stream_tickets.issue(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::auth::create_token;
+    use crate::routes::build_router;
+
+    #[test]
+    fn ticket_is_single_use() {
+        let tickets = StreamTickets::default();
+        let ticket = tickets.issue();
+        assert!(tickets.redeem(&ticket));
+        assert!(!tickets.redeem(&ticket));
+        assert!(!tickets.redeem("not-a-ticket"));
+    }
+
+    #[test]
+    fn expired_ticket_is_refused() {
+        let tickets = StreamTickets::new(Duration::ZERO);
+        let ticket = tickets.issue();
+        assert!(!tickets.redeem(&ticket));
+    }
+
+    #[tokio::test]
+    async fn session_gets_a_ticket() {
+        let state = Arc::new(AppState::for_tests());
+        let jwt = create_token("admin", &state.jwt_secret).unwrap();
+        let resp = build_router(state.clone())
+            .oneshot(
+                crate::csrf::with_csrf(Request::builder(), &jwt, &state.jwt_secret)
+                    .method("POST")
+                    .uri("/api/stream-ticket")
+                    .header(header::AUTHORIZATION, format!("Bearer {jwt}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(
+            state
+                .stream_tickets
+                .redeem(body["ticket"].as_str().unwrap())
+        );
+    }
+
+    #[tokio::test]
+    async fn ticket_needs_a_session() {
+        let resp = build_router(Arc::new(AppState::for_tests()))
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/stream-ticket")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+}